@@ -0,0 +1,66 @@
+//! Embeds the commit and the date of the build in the binaries, see `src/build_info.rs`.
+
+use std::{
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+fn main() {
+    // Build again when the checked out commit changes
+    println!("cargo:rerun-if-changed=../../.git/HEAD");
+    println!("cargo:rerun-if-changed=../../.git/index");
+
+    println!("cargo:rustc-env=IFT6251_GIT_HASH={}", git_hash());
+    println!("cargo:rustc-env=IFT6251_BUILD_DATE={}", build_date());
+}
+
+/// The short hash of the commit, marked as dirty when the tree has changes, or `unknown` outside
+/// of a git checkout.
+fn git_hash() -> String {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+    };
+    match git(&["rev-parse", "--short", "HEAD"]) {
+        Some(hash) => match git(&["status", "--porcelain"]) {
+            Some(status) if !status.is_empty() => format!("{hash}-dirty"),
+            _ => hash,
+        },
+        None => "unknown".to_owned(),
+    }
+}
+
+/// The UTC date of the build, as `YYYY-MM-DD`.
+fn build_date() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let (year, month, day) = civil_from_days((seconds / 86400) as i64);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// The date of a number of days since the UNIX epoch, in the proleptic Gregorian calendar.
+///
+/// See Howard Hinnant's `civil_from_days` algorithm.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
@@ -1,7 +1,19 @@
-use ift6251::get_save_path;
+use ift6251::{
+    build_info::{self, BUILD_INFO_KEY},
+    display::DisplayOptions,
+    get_save_path,
+    recorder::{RECORD_KEY, Recorder},
+    utils::{
+        hotkeys::{Binding, HELP_KEY, HotkeyHelp, action_for, describe},
+        palette::{Command, CommandPalette, PALETTE_KEY},
+    },
+    watchdog::{self, Watchdog},
+};
 use nannou::prelude::*;
+use nannou_egui::Egui;
 
 fn main() {
+    watchdog::supervise();
     nannou::app(model).update(update).run();
 }
 
@@ -116,25 +128,58 @@ impl Agent {
 
 struct Model {
     agents: Vec<Agent>,
+    egui: Egui,
+    palette: CommandPalette,
+    help: HotkeyHelp,
+    recorder: Recorder,
+    /// Whether the build is drawn in a corner of the window.
+    show_build_info: bool,
+    /// Reports the frames to the supervisor, when the sketch is supervised.
+    watchdog: Option<Watchdog>,
 }
 
 fn model(app: &App) -> Model {
-    app.new_window()
+    let window_id = app
+        .new_window()
         .title("Birds")
-        .fullscreen()
+        .fullscreen_with(Some(DisplayOptions::load("birds").fullscreen(app)))
         .view(view)
         .key_pressed(key_pressed)
+        .raw_event(raw_window_event)
         .build()
         .unwrap();
 
+    let window = app.window(window_id).unwrap();
+    let egui = Egui::from_window(&window);
+
     let agents = (0..Agent::NUM_AGENTS)
         .map(|_| Agent::new(app.window_rect()))
         .collect();
 
-    Model { agents }
+    Model {
+        agents,
+        egui,
+        palette: CommandPalette::new(),
+        help: HotkeyHelp::new(),
+        recorder: Recorder::new(),
+        show_build_info: false,
+        watchdog: Watchdog::from_env("birds"),
+    }
 }
 
-fn update(app: &App, model: &mut Model, _update: Update) {
+fn update(app: &App, model: &mut Model, update: Update) {
+    if let Some(watchdog) = &mut model.watchdog {
+        watchdog.beat();
+    }
+
+    model.egui.set_elapsed_time(update.since_start);
+    let ctx = model.egui.begin_frame();
+    let commands = commands(model.recorder.is_recording());
+    if let Some(action) = model.palette.show(&ctx, &commands) {
+        perform(app, &mut model.recorder, &mut model.show_build_info, action);
+    }
+    model.help.show(&ctx, &describe(&bindings()));
+
     let previous_agents = model.agents.clone();
     model
         .agents
@@ -154,17 +199,92 @@ fn view(app: &App, model: &Model, frame: Frame) {
         agent.display(&draw, Agent::COLOR.into());
     });
 
+    if model.show_build_info {
+        build_info::draw_overlay(&draw, app.window_rect());
+    }
+
     // Write the result of our drawing to the window's frame.
     draw.to_frame(app, &frame).unwrap();
+    model.recorder.capture(&frame);
+    model.egui.draw_to_frame(&frame).unwrap();
+}
+
+fn raw_window_event(_app: &App, model: &mut Model, event: &nannou::winit::event::WindowEvent) {
+    // Let egui handle things like keyboard and mouse input.
+    model.egui.handle_raw_event(event);
+}
+
+/// The actions that can be run from the keyboard or the command palette.
+#[derive(Clone, Copy)]
+enum Action {
+    ToggleRecording,
+    SaveFrame,
+    ToggleBuildInfo,
+    Quit,
+}
+
+fn commands(recording: bool) -> Vec<Command<Action>> {
+    vec![
+        match recording {
+            false => Command::new("Start recording", Action::ToggleRecording),
+            true => Command::new("Stop recording", Action::ToggleRecording),
+        },
+        Command::new("Save frame", Action::SaveFrame),
+        Command::new("Toggle build info", Action::ToggleBuildInfo),
+        Command::new("Quit", Action::Quit),
+    ]
+}
+
+/// The hotkeys of the sketch, listed by the help overlay.
+fn bindings() -> Vec<Binding<Action>> {
+    vec![
+        Binding::new(
+            &[RECORD_KEY],
+            "Start or stop recording the frames",
+            Action::ToggleRecording,
+        ),
+        Binding::new(&[Key::S], "Save the current frame", Action::SaveFrame),
+        Binding::new(
+            &[BUILD_INFO_KEY],
+            "Show or hide the build in a corner",
+            Action::ToggleBuildInfo,
+        ),
+        Binding::new(&[Key::Q], "Quit", Action::Quit),
+    ]
 }
 
-fn key_pressed(app: &App, _model: &mut Model, key: Key) {
-    match key {
-        Key::Q => app.quit(),
-        Key::S => {
-            app.main_window()
-                .capture_frame(get_save_path(&app.exe_name().unwrap()));
+fn perform(app: &App, recorder: &mut Recorder, show_build_info: &mut bool, action: Action) {
+    match action {
+        Action::ToggleRecording => {
+            recorder.toggle(&app.exe_name().unwrap(), app.main_window().device());
+        }
+        Action::SaveFrame => {
+            recorder.save_frame(get_save_path(&app.exe_name().unwrap()), None);
         }
-        _other_key => {}
+        Action::ToggleBuildInfo => *show_build_info = !*show_build_info,
+        Action::Quit => {
+            recorder.stop(app.main_window().device());
+            app.quit();
+        }
+    }
+}
+
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    if key == PALETTE_KEY {
+        model.palette.toggle();
+        return;
+    }
+    // The keys are used to type in the palette
+    if model.palette.is_open() {
+        return;
+    }
+
+    if key == HELP_KEY {
+        model.help.toggle();
+        return;
+    }
+
+    if let Some(action) = action_for(&bindings(), key) {
+        perform(app, &mut model.recorder, &mut model.show_build_info, action);
     }
 }
@@ -0,0 +1,116 @@
+use std::{fs::File, io::BufWriter};
+
+use nannou::{image::RgbaImage, prelude::*};
+use serde::Serialize;
+
+/// The key showing and hiding the build of the sketch in a corner of the window, and of the
+/// captures.
+pub const BUILD_INFO_KEY: Key = Key::F3;
+
+/// The short hash of the commit the binary was built from, marked `-dirty` when the tree had
+/// changes.
+pub const GIT_HASH: &str = env!("IFT6251_GIT_HASH");
+/// The UTC date the binary was built on, as `YYYY-MM-DD`.
+pub const BUILD_DATE: &str = env!("IFT6251_BUILD_DATE");
+
+/// The name and version of the crate with the commit and date of the build, to trace an output
+/// back to the code that produced it.
+pub fn version() -> String {
+    format!(
+        "{} {} ({GIT_HASH}, {BUILD_DATE})",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+/// Summarizes the settings of a sketch as JSON, to stamp into its captures.
+pub fn parameters<T: Serialize>(settings: &T) -> Option<String> {
+    serde_json::to_string(settings)
+        .inspect_err(|e| eprintln!("Failed to summarize the parameters: {e}"))
+        .ok()
+}
+
+/// Saves an image as a PNG file stamped with the build, and with the parameters that produced it
+/// when they are given.
+///
+/// The build is saved as the `Software` text of the file and the parameters as its `Comment`, which
+/// most image viewers and `exiftool` show.
+pub fn save_png(image: &RgbaImage, path: &str, parameters: Option<&str>) -> std::io::Result<()> {
+    let mut encoder = png_encoder(path, image.width(), image.height(), parameters)?;
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(image.as_raw())?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Creates the encoder of an RGBA PNG file stamped like [`save_png`] does, for the bit depth to be
+/// set on.
+pub(crate) fn png_encoder(
+    path: &str,
+    width: u32,
+    height: u32,
+    parameters: Option<&str>,
+) -> std::io::Result<png::Encoder<'static, BufWriter<File>>> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.add_text_chunk("Software".to_owned(), version())?;
+    if let Some(parameters) = parameters {
+        // The parameters are UTF-8, which only the international text chunks hold
+        encoder.add_itxt_chunk("Comment".to_owned(), parameters.to_owned())?;
+    }
+    Ok(encoder)
+}
+
+/// Draws the build in the bottom-left corner of the window, over a dark backing so it stays
+/// readable on any image.
+pub fn draw_overlay(draw: &Draw, window: Rect) {
+    const FONT_SIZE: u32 = 12;
+    const MARGIN: f32 = 8.0;
+
+    let text = version();
+    let width = text.len() as f32 * FONT_SIZE as f32 * 0.6;
+    let height = FONT_SIZE as f32 * 1.6;
+    let rect = Rect::from_w_h(width, height)
+        .bottom_left_of(window)
+        .shift(vec2(MARGIN, MARGIN));
+    draw.rect()
+        .xy(rect.xy())
+        .wh(rect.wh())
+        .color(rgba(0.0, 0.0, 0.0, 0.6));
+    draw.text(&text)
+        .xy(rect.xy())
+        .wh(rect.wh())
+        .font_size(FONT_SIZE)
+        .color(WHITE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_images_are_stamped() {
+        let path = std::env::temp_dir().join(format!("build_info_{}.png", std::process::id()));
+        let path = path.to_str().unwrap();
+        let image = RgbaImage::from_pixel(3, 2, nannou::image::Rgba([10, 20, 30, 255]));
+        save_png(&image, path, Some("{\"zoom\": 2.5}")).unwrap();
+
+        let decoder = png::Decoder::new(File::open(path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let info = reader.info();
+        let software = &info.uncompressed_latin1_text[0];
+        assert_eq!(software.keyword, "Software");
+        assert_eq!(software.text, version());
+        let comment = &info.utf8_text[0];
+        assert_eq!(comment.keyword, "Comment");
+        assert_eq!(comment.get_text().unwrap(), "{\"zoom\": 2.5}");
+
+        // The pixels are saved as they are
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(pixels, image.into_raw());
+    }
+}
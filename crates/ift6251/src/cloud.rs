@@ -3,85 +3,910 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-use ift6251::get_save_path;
-use nannou::{prelude::*, state::keys, winit};
+#[cfg(target_os = "linux")]
+use ift6251::gamepad::{Gamepad, GamepadMotion, GamepadSettings};
+use ift6251::{
+    build_info::{self, BUILD_INFO_KEY},
+    display::{self, DisplayOptions, PanoramaLayout},
+    get_export_path, get_save_directory, get_save_path,
+    ndi::NdiOutput,
+    osc::{OSC_PORT, OscServer},
+    recorder::{RECORD_KEY, Recorder},
+    save::ImageOptions,
+    session::SessionStats,
+    utils::{
+        ab::{AbSlots, Parameters, Slot},
+        attract::{AttractLoop, Blend, lerp, lerp_array},
+        audio::{
+            AUDIO_EXTENSIONS, AudioAnalyzer, AudioFeatures, AudioScope, Transport, format_time,
+            read_track,
+        },
+        hotkeys::{Binding, HELP_KEY, HotkeyHelp, action_for, describe},
+        palette::{Command, CommandPalette, PALETTE_KEY},
+        presets::{PresetLibrary, PresetRequest, read_preset},
+        rng::{self, random_range},
+        widgets::{Dropdown, FilePicker, LabelledSlider, SpectrumScope, UiScale},
+    },
+    watchdog::{self, Watchdog},
+};
+use nannou::{image::RgbaImage, prelude::*, state::keys, winit};
 use nannou_audio::{Buffer, Host, Stream};
 use nannou_egui::{
     Egui,
     egui::{self},
 };
 use point_cloud_renderer::{
-    camera::{Camera, CameraConfig, Direction},
-    loader::{generate_random_point_cloud, read_e57},
+    attractor::AttractorData,
+    background::{IMAGE_EXTENSIONS, ImageSequence},
+    camera::{Camera, CameraBookmark, CameraBookmarks, CameraConfig, CameraPath, Direction},
+    clip::ClipBox,
+    collision::OccupancyGrid,
+    coloring::{ColorMode, ColorSettings, RampPreset},
+    diff,
+    effects::{CurlNoiseData, Effect, EffectStack},
+    export::PlySequence,
+    exposure::ExposureSettings,
+    fog::FogSettings,
+    gaussian::{GAUSSIAN_EXTENSIONS, Gaussian, read_gaussians_async},
+    gizmo::GizmoSettings,
+    glow::GlowSettings,
+    gravity::GravityData,
+    history::CloudHistory,
+    hot_reload::ShaderWatcher,
+    impulse::ImpulseData,
+    layer::{CloudLayer, CloudTransform},
+    lighting::{LightingSettings, ShadingMode},
+    loader::{LoadHandle, estimate_normals, generate_random_point_cloud, write_ply},
+    lod::{LodSettings, Octree},
+    mask::{EffectMasks, Mask},
+    metadata::{CloudMetadata, CloudSource},
+    picking::{PickRay, PickedPoint},
     pipeline::GPUPipeline,
-    point::{CloudData, Point},
+    point::{BandTarget, CloudData, Point},
+    pulse::PulseData,
+    readback::PointReadback,
+    registry::{LoadOptions, Registry},
+    resolution::ResolutionScaler,
+    spectrum::{SpectralLayout, SpectrumData},
+    still::TiledStill,
+    streaming::{TileCache, TileStreamer, write_tiles_async},
+    trail::TrailData,
+    warp::{MESH_SIZE, WarpSettings},
 };
-use spectrum_analyzer::{FrequencyLimit, samples_fft_to_spectrum, windows::hann_window};
+use serde::{Deserialize, Serialize};
+
+/// A point cloud of the scene, as listed in the settings.
+#[derive(Clone, PartialEq)]
+struct SceneCloud {
+    name: String,
+    layer: CloudLayer,
+}
+
+impl SceneCloud {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            layer: CloudLayer::default(),
+        }
+    }
+}
+
+const USAGE: &str = "Usage: cloud [--help] [--cloud PATH] [--audio PATH] [--fullscreen] [--resolution WxH] [--monitor INDEX] [--video-mode WxH[@HZ]] [--layout FILE] [--preset FILE] [--max-buffer-mib MIB] [--seed NUMBER] [--supervise [SECONDS]]";
+/// The file the options are read from when they are not given on the command line.
+const CONFIG_PATH: &str = "./config.toml";
+
+/// The keys of the camera bookmarks, in the order of their slots.
+const BOOKMARK_KEYS: [Key; CameraBookmarks::SLOTS] = [
+    Key::Key1,
+    Key::Key2,
+    Key::Key3,
+    Key::Key4,
+    Key::Key5,
+    Key::Key6,
+    Key::Key7,
+    Key::Key8,
+    Key::Key9,
+];
+
+/// The options the viewer starts with, so an installation boots straight into its scene without
+/// touching the GUI.
+///
+/// They are given on the command line, or else read from `config.toml`, such as:
+///
+/// ```toml
+/// cloud = "./data/union_station.e57"
+/// audio = "./data/audio.wav"
+/// resolution = [1920, 1080]
+/// layout = "./panorama.toml"
+/// preset = "./presets/cloud/night.json"
+/// max_buffer_mib = 1024
+/// ```
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct Options {
+    cloud: Option<String>,
+    audio: Option<String>,
+    /// Whether the window fills the screen even when a resolution is given.
+    fullscreen: bool,
+    /// The size of the window in pixels, written `WxH` on the command line; the window fills the
+    /// screen when it is not given.
+    resolution: Option<[u32; 2]>,
+    /// A layout file of a panorama spanning several monitors, with a window per monitor.
+    layout: Option<String>,
+    /// A preset file of the settings, as saved from the settings panel.
+    preset: Option<String>,
+    /// The size of the largest GPU buffer requested, in MiB, capped to what the adapter supports;
+    /// 2 GiB when it is not given.
+    max_buffer_mib: Option<u64>,
+}
+
+impl Options {
+    /// Reads the options of the config file, overridden by those of the command line.
+    fn load() -> Self {
+        let config = match std::fs::read_to_string(CONFIG_PATH) {
+            Ok(config) => toml::from_str(&config).unwrap_or_else(|e| {
+                eprintln!("Ignoring {CONFIG_PATH}: {e}");
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        };
+        Self::parse_args().or(config)
+    }
+
+    /// Parses the options of the command line, exiting with the usage if they are invalid.
+    fn parse_args() -> Self {
+        Self::parse(std::env::args().skip(1)).unwrap_or_else(|e| {
+            eprintln!("{e}\n{USAGE}");
+            std::process::exit(2);
+        })
+    }
+
+    fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut args = args.peekable();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--help" | "-h" => {
+                    println!("{USAGE}");
+                    std::process::exit(0);
+                }
+                "--cloud" => options.cloud = Some(args.next().ok_or("The cloud path is missing")?),
+                "--audio" => options.audio = Some(args.next().ok_or("The audio path is missing")?),
+                "--fullscreen" => options.fullscreen = true,
+                "--resolution" => {
+                    let resolution = args
+                        .next()
+                        .and_then(|resolution| {
+                            let (width, height) = resolution.split_once('x')?;
+                            Some([width.parse().ok()?, height.parse().ok()?])
+                        })
+                        .filter(|size: &[u32; 2]| size.iter().all(|&side| side > 0))
+                        .ok_or("The resolution must be written WxH in pixels")?;
+                    options.resolution = Some(resolution);
+                }
+                "--layout" => {
+                    options.layout = Some(args.next().ok_or("The layout file is missing")?)
+                }
+                "--preset" => {
+                    options.preset = Some(args.next().ok_or("The preset file is missing")?)
+                }
+                "--max-buffer-mib" => {
+                    let mib = args
+                        .next()
+                        .and_then(|mib| mib.parse().ok())
+                        .filter(|&mib| mib > 0)
+                        .ok_or("The buffer size must be a positive number of MiB")?;
+                    options.max_buffer_mib = Some(mib);
+                }
+                // Handled by the display options
+                flag if display::FLAGS.contains(&flag) => {
+                    args.next();
+                }
+                // Handled by the shared generator of random numbers
+                rng::SEED_FLAG => {
+                    args.next();
+                }
+                // Handled by the supervisor, with its optional timeout
+                watchdog::SUPERVISE_FLAG => {
+                    args.next_if(|seconds| seconds.parse::<f32>().is_ok());
+                }
+                _ => return Err(format!("Unknown option: {arg}")),
+            }
+        }
+        Ok(options)
+    }
+
+    /// Fills the options not given with those of `other`.
+    fn or(self, other: Self) -> Self {
+        Self {
+            cloud: self.cloud.or(other.cloud),
+            audio: self.audio.or(other.audio),
+            fullscreen: self.fullscreen || other.fullscreen,
+            resolution: self.resolution.or(other.resolution),
+            layout: self.layout.or(other.layout),
+            preset: self.preset.or(other.preset),
+            max_buffer_mib: self.max_buffer_mib.or(other.max_buffer_mib),
+        }
+    }
+}
 
 fn main() {
-    nannou::app(model).event(event).update(update).run();
+    // The command line is checked before the supervisor restarts the sketch or the window opens
+    Options::parse_args();
+    watchdog::supervise();
+    nannou::app(model)
+        .event(event)
+        .update(update)
+        .exit(exit)
+        .run();
 }
 
 struct State {
+    points: Vec<Point>,
+    history: CloudHistory,
     cloud_file_path: String,
+    las_classes: String,
+    /// The scan of the same space the cloud is compared with, to show what changed.
+    compare_file_path: String,
+    /// The distance to the other scan shown by the last color of the ramp when comparing them.
+    compare_range: f32,
+    /// The name of the generator creating the points when no file is loaded.
+    generator: String,
     audio_file_path: String,
+    /// A frame of the image sequence played behind the points.
+    background_file_path: String,
+    /// The number of frames of the background played per second.
+    background_frame_rate: f32,
+    /// The `.splat` or PLY file of a 3D Gaussian Splatting capture drawn among the points.
+    gaussian_file_path: String,
+    /// How much the Gaussians of the capture are scaled, 1 drawing them as captured.
+    gaussian_scale: f32,
     movement_speed: f64,
     mouse_sensitivity: f32,
+    #[cfg(target_os = "linux")]
+    gamepad: GamepadSettings,
+    /// Whether clicking picks the points of the cloud instead of dropping attractors.
+    picking: bool,
+    /// The picked points, placed in the scene, the distance between the two being measured.
+    picks: Vec<PickedPoint>,
+    /// The voxels of the cloud the camera collides with, while collisions are on.
+    obstacles: Option<OccupancyGrid>,
+    /// The number of voxels of the collisions along the longest side of the cloud.
+    collision_resolution: usize,
+    cloud_data: CloudData,
+    attractor_data: AttractorData,
+    attractor_strength: f32,
+    attractor_distance: f32,
+    masks: EffectMasks,
+    export_directory: String,
+    export_every_n_frames: u32,
+    /// The number of columns and rows of displays of the video wall the stills are tiled for.
+    wall_tiles: [u32; 2],
+    /// The resolution of each display of the video wall.
+    wall_tile_size: [u32; 2],
+    /// The size of the high-resolution stills, independent of the window.
+    still_size: [u32; 2],
+    /// The format the stills are saved in.
+    still_options: ImageOptions,
+    /// The directory the shaders of the deformation are reloaded from while they are edited.
+    shader_directory: String,
+    auto_rotate: bool,
+    rotation_speed: f32,
+    rotation_audio_modulation: f32,
+    world_angle: f32,
+    cloud_center: Point3,
+    /// The source, size and bounds of the points.
+    metadata: CloudMetadata,
+    /// The point clouds of the scene, the first being the main cloud the other settings act on.
+    clouds: Vec<SceneCloud>,
+    looks: AbSlots<Look>,
+    spectrum_data: SpectrumData,
+    fog_settings: FogSettings,
+    /// The ground grid, axes and bounding box drawn to tell the orientation and scale of the cloud.
+    gizmo: GizmoSettings,
+    /// Whether the points glow additively, dimmed where they hide behind others.
+    glow: GlowSettings,
+    /// How the points are lit from their normals, once they are estimated.
+    lighting: LightingSettings,
+    /// The number of neighbors the normal of each point is fitted to.
+    normal_neighbors: usize,
+    coloring: ColorSettings,
+    clip_box: ClipBox,
+    trail_data: TrailData,
+    warp_settings: WarpSettings,
+    editing_warp: bool,
+    /// The exposure of the frame, adapting to its luminance so it stays readable on a projector.
+    exposure: ExposureSettings,
+    /// The named presets of the library, blended by the attract loop in the order of their names.
+    presets: Vec<Preset>,
+    /// The named presets of the settings, saved to disk.
+    preset_library: PresetLibrary,
+    attract_enabled: bool,
+    attract_loop: AttractLoop,
+    camera_path: CameraPath,
+    capture_path: bool,
+    /// The camera poses stored under the number keys, saved next to the cloud file.
+    camera_bookmarks: CameraBookmarks,
+    /// How long the camera takes to fly to a recalled bookmark, in seconds.
+    bookmark_duration: f32,
+    resolution_scaler: ResolutionScaler,
+    lod_settings: LodSettings,
+    splats: bool,
+    /// The frequencies separating the bass, mid and treble bands, in hertz.
+    band_edges: [f32; 2],
+    /// How the bass, mid and treble bands modulate the deformation.
+    band_mappings: [BandMapping; 3],
+    pulse_data: PulseData,
+    /// The effects deforming the points, in the order their passes run.
+    effects: EffectStack,
+    curl_noise: CurlNoiseData,
+    /// The progress of the gravity collapse and reassembly, which the camera path keyframes.
+    gravity: GravityData,
+    /// Whether the onsets of the audio send a pulse through the cloud.
+    pulse_on_beat: bool,
+    /// The wavefronts the onsets of the audio send through the cloud while the effect is on.
+    impulse_data: ImpulseData,
+    /// Whether the impulses leave from a random point of the cloud rather than its centroid.
+    impulses_from_points: bool,
+    // These will be accessed by the audio thread.
+    audio_features: Arc<Mutex<AudioFeatures>>,
+    spectrum_output: Arc<Mutex<[f32; SpectrumData::BANDS]>>,
+    /// The spectrum and waveform of the last buffer, shown by the spectrum overlay.
+    audio_scope: Arc<Mutex<AudioScope>>,
+    /// The transport of the audio file, which the audio thread advances.
+    transport: Arc<Mutex<Transport>>,
+}
+
+/// How a band of the audio modulates a parameter of the deformation.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct BandMapping {
+    target: BandTarget,
+    /// How much the logarithm of the energy of the band raises the target, as a fraction of its
+    /// value.
+    amount: f32,
+}
+
+/// A snapshot of the settings that change the look of the cloud, used for A/B comparison.
+#[derive(Clone, Serialize, Deserialize)]
+struct Look {
     cloud_data: CloudData,
-    // This will be accessed by the audio thread.
-    fft_output: Arc<Mutex<f32>>,
+    masks: EffectMasks,
+    attractor_strength: f32,
+    attractor_radius: f32,
+    auto_rotate: bool,
+    rotation_speed: f32,
+    rotation_audio_modulation: f32,
+    fog_settings: FogSettings,
+    coloring: ColorSettings,
+}
+
+impl Look {
+    fn from_state(state: &State) -> Self {
+        Self {
+            cloud_data: state.cloud_data,
+            masks: state.masks,
+            attractor_strength: state.attractor_strength,
+            attractor_radius: state.attractor_data.radius,
+            auto_rotate: state.auto_rotate,
+            rotation_speed: state.rotation_speed,
+            rotation_audio_modulation: state.rotation_audio_modulation,
+            fog_settings: state.fog_settings,
+            coloring: state.coloring,
+        }
+    }
+
+    fn apply(&self, state: &mut State) {
+        // Keep the values driven by the simulation
+        let sound_amplitude = state.cloud_data.sound_amplitude;
+        let time = state.cloud_data.time;
+        let band_modulation = state.cloud_data.band_modulation;
+        state.cloud_data = self.cloud_data;
+        state.cloud_data.sound_amplitude = sound_amplitude;
+        state.cloud_data.time = time;
+        state.cloud_data.band_modulation = band_modulation;
+
+        state.masks = self.masks;
+        state.attractor_strength = self.attractor_strength;
+        state.attractor_data.radius = self.attractor_radius;
+        state.attractor_data.set_strength(self.attractor_strength);
+        state.auto_rotate = self.auto_rotate;
+        state.rotation_speed = self.rotation_speed;
+        state.rotation_audio_modulation = self.rotation_audio_modulation;
+        state.fog_settings = self.fog_settings;
+        state.coloring = self.coloring;
+    }
+}
+
+impl Blend for Look {
+    fn blend(&self, other: &Self, t: f32) -> Self {
+        // Switches and flags cannot be interpolated, so they flip halfway through
+        let nearest = if t < 0.5 { self } else { other };
+
+        let (a, b) = (&self.cloud_data, &other.cloud_data);
+        let mut cloud_data = nearest.cloud_data;
+        cloud_data.wind_strength = lerp(a.wind_strength, b.wind_strength, t);
+        cloud_data.noise_scale = lerp(a.noise_scale, b.noise_scale, t);
+        cloud_data.spring_constant = lerp(a.spring_constant, b.spring_constant, t);
+        cloud_data.wind_direction = lerp_array(a.wind_direction, b.wind_direction, t);
+        cloud_data.gust_strength = lerp(a.gust_strength, b.gust_strength, t);
+        cloud_data.gust_frequency = lerp(a.gust_frequency, b.gust_frequency, t);
+        cloud_data.gust_spread = lerp(a.gust_spread, b.gust_spread, t);
+        cloud_data.point_size = lerp(a.point_size, b.point_size, t);
+
+        let blend_mask = |a: &Mask, b: &Mask, nearest: &Mask| {
+            let mut mask = *nearest;
+            mask.color = lerp_array(a.color, b.color, t);
+            mask.color_tolerance = lerp(a.color_tolerance, b.color_tolerance, t);
+            mask.min_height = lerp(a.min_height, b.min_height, t);
+            mask.max_height = lerp(a.max_height, b.max_height, t);
+            mask.feather = lerp(a.feather, b.feather, t);
+            mask
+        };
+        let masks = EffectMasks {
+            wind: blend_mask(&self.masks.wind, &other.masks.wind, &nearest.masks.wind),
+            attractors: blend_mask(
+                &self.masks.attractors,
+                &other.masks.attractors,
+                &nearest.masks.attractors,
+            ),
+        };
+
+        let (a, b) = (&self.fog_settings, &other.fog_settings);
+        let fog_settings = FogSettings {
+            enabled: nearest.fog_settings.enabled,
+            color: lerp_array(a.color, b.color, t),
+            height: lerp(a.height, b.height, t),
+            density: lerp(a.density, b.density, t),
+            reflection_strength: lerp(a.reflection_strength, b.reflection_strength, t),
+        };
+
+        let (a, b) = (&self.coloring, &other.coloring);
+        let coloring = ColorSettings {
+            mode: nearest.coloring.mode,
+            stops: std::array::from_fn(|i| lerp_array(a.stops[i], b.stops[i], t)),
+            range: lerp_array(a.range, b.range, t),
+        };
+
+        Self {
+            cloud_data,
+            masks,
+            attractor_strength: lerp(self.attractor_strength, other.attractor_strength, t),
+            attractor_radius: lerp(self.attractor_radius, other.attractor_radius, t),
+            auto_rotate: nearest.auto_rotate,
+            rotation_speed: lerp(self.rotation_speed, other.rotation_speed, t),
+            rotation_audio_modulation: lerp(
+                self.rotation_audio_modulation,
+                other.rotation_audio_modulation,
+                t,
+            ),
+            fog_settings,
+            coloring,
+        }
+    }
+}
+
+impl Parameters for Look {
+    fn parameters(&self) -> Vec<(&'static str, String)> {
+        let data = &self.cloud_data;
+        let mut parameters = vec![
+            ("noise_scale", format!("{:.4}", data.noise_scale)),
+            ("wind_strength", format!("{:.4}", data.wind_strength)),
+            ("wind_direction", format!("{:.2?}", data.wind_direction)),
+            ("gust_strength", format!("{:.4}", data.gust_strength)),
+            ("gust_frequency", format!("{:.4}", data.gust_frequency)),
+            ("gust_spread", format!("{:.4}", data.gust_spread)),
+            ("spring_constant", format!("{:.4}", data.spring_constant)),
+            ("point_size", format!("{:.4}", data.point_size)),
+            (
+                "attractor_strength",
+                format!("{:.4}", self.attractor_strength),
+            ),
+            ("attractor_radius", format!("{:.4}", self.attractor_radius)),
+            ("auto_rotate", format!("{}", self.auto_rotate)),
+            ("rotation_speed", format!("{:.4}", self.rotation_speed)),
+            (
+                "rotation_audio_modulation",
+                format!("{:.4}", self.rotation_audio_modulation),
+            ),
+            (
+                "fog",
+                match self.fog_settings.enabled {
+                    false => "off".to_owned(),
+                    true => format!(
+                        "color: {:.2?}, height: {:.2}, density: {:.4}, reflection: {:.2}",
+                        self.fog_settings.color,
+                        self.fog_settings.height,
+                        self.fog_settings.density,
+                        self.fog_settings.reflection_strength
+                    ),
+                },
+            ),
+            (
+                "coloring",
+                match self.coloring.mode {
+                    ColorMode::Rgb => "rgb".to_owned(),
+                    mode => format!(
+                        "{mode:?}, range: {:.2?}, stops: {:.2?}",
+                        self.coloring.range, self.coloring.stops
+                    ),
+                },
+            ),
+        ];
+
+        let masks = [
+            ("wind_mask", &self.masks.wind),
+            ("attractors_mask", &self.masks.attractors),
+        ];
+        masks.into_iter().for_each(|(name, mask)| {
+            let enabled = match (mask.height_enabled(), mask.color_enabled()) {
+                (false, false) => "off".to_owned(),
+                (height, color) => format!(
+                    "height: {height} [{:.2}, {:.2}], color: {color} {:.2?} ± {:.2}, inverted: {}",
+                    mask.min_height,
+                    mask.max_height,
+                    mask.color,
+                    mask.color_tolerance,
+                    mask.inverted()
+                ),
+            };
+            parameters.push((name, enabled));
+        });
+
+        parameters
+    }
+}
+
+/// The settings of the cloud saved in the named presets.
+#[derive(Serialize, Deserialize)]
+struct Settings {
+    look: Look,
+    band_mappings: [BandMapping; 3],
+    pulse_on_beat: bool,
+    movement_speed: f64,
+    mouse_sensitivity: f32,
+    /// The pose of the camera when the preset was saved, which the attract loop flies to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    camera: Option<CameraBookmark>,
+}
+
+impl Settings {
+    fn from_state(state: &State) -> Self {
+        Self {
+            look: Look::from_state(state),
+            band_mappings: state.band_mappings,
+            pulse_on_beat: state.pulse_on_beat,
+            movement_speed: state.movement_speed,
+            mouse_sensitivity: state.mouse_sensitivity,
+            camera: None,
+        }
+    }
+
+    fn apply(&self, state: &mut State) {
+        self.look.apply(state);
+        state.band_mappings = self.band_mappings;
+        state.pulse_on_beat = self.pulse_on_beat;
+        state.movement_speed = self.movement_speed;
+        state.mouse_sensitivity = self.mouse_sensitivity;
+    }
+}
+
+/// A look and a camera pose shown by the attract loop, read from a named preset.
+#[derive(Clone)]
+struct Preset {
+    name: String,
+    look: Look,
+    /// The pose of the camera, if it was saved with the preset.
+    camera: Option<CameraBookmark>,
+}
+
+impl Preset {
+    fn apply(&self, state: &mut State, camera: &mut Camera) {
+        self.look.apply(state);
+        if let Some(pose) = &self.camera {
+            pose.apply(camera);
+        }
+    }
+}
+
+impl Preset {
+    fn new(name: String, settings: Settings) -> Self {
+        Self {
+            name,
+            look: settings.look,
+            camera: settings.camera,
+        }
+    }
+}
+
+impl Blend for Preset {
+    fn blend(&self, other: &Self, t: f32) -> Self {
+        // The camera stays put when blending towards a preset saved without a pose
+        let camera = match (self.camera, other.camera) {
+            (Some(from), Some(to)) => {
+                // Turn the shortest way around
+                let tau = std::f32::consts::TAU;
+                let mut yaw_delta = (to.yaw - from.yaw).rem_euclid(tau);
+                if yaw_delta > tau / 2.0 {
+                    yaw_delta -= tau;
+                }
+                Some(CameraBookmark {
+                    position: lerp_array(from.position, to.position, t),
+                    pitch: lerp(from.pitch, to.pitch, t),
+                    yaw: from.yaw + yaw_delta * t,
+                })
+            }
+            (camera, _) => camera,
+        };
+
+        Self {
+            name: self.name.clone(),
+            look: self.look.blend(&other.look, t),
+            camera,
+        }
+    }
+}
+
+/// Reads the named presets of the library for the attract loop, in the order of their names.
+fn read_presets(library: &PresetLibrary) -> Vec<Preset> {
+    library
+        .load_all::<Settings>()
+        .into_iter()
+        .map(|(name, settings)| Preset::new(name, settings))
+        .collect()
 }
 
 struct Audio {
-    sounds: Vec<audrey::read::BufFileReader>,
-    fft_output: Arc<Mutex<f32>>,
+    /// The frames of the audio file, decoded so they can be sought through and looped.
+    track: Vec<[f32; 2]>,
+    transport: Arc<Mutex<Transport>>,
+    analysis: Analysis,
+}
+
+/// The analysis of the audio the cloud reacts to, whether it is played from a file or captured by
+/// the microphone.
+struct Analysis {
+    analyzer: AudioAnalyzer,
+    features: Arc<Mutex<AudioFeatures>>,
+    spectrum_output: Arc<Mutex<[f32; SpectrumData::BANDS]>>,
+    scope: Arc<Mutex<AudioScope>>,
+}
+
+impl Analysis {
+    fn new(state: &State) -> Self {
+        let [bass_max, mid_max] = state.band_edges;
+        Self {
+            analyzer: AudioAnalyzer::new().with_band_edges(bass_max, mid_max),
+            features: Arc::clone(&state.audio_features),
+            spectrum_output: Arc::clone(&state.spectrum_output),
+            scope: Arc::clone(&state.audio_scope),
+        }
+    }
+
+    /// Analyzes a buffer of audio and shares its features and spectrum with the sketch.
+    fn process(&mut self, buffer: &Buffer) {
+        // Merge the audio channels and analyze them
+        let samples: Vec<_> = buffer.frames().flatten().cloned().collect();
+        let features = self.analyzer.analyze(&samples, buffer.sample_rate());
+        let scope = AudioScope::capture(
+            &self.analyzer,
+            &samples,
+            buffer.channels(),
+            buffer.sample_rate(),
+        );
+
+        // The bands of the spectrum are spaced logarithmically between 80Hz and 16kHz
+        let max_frequency = 16000.0f32.min(buffer.sample_rate() as f32 / 2.0);
+        let bands = self.analyzer.log_bands(80.0, max_frequency);
+
+        // Update the audio features and the spectrum
+        self.features.lock().unwrap().update(features);
+        *self.spectrum_output.lock().unwrap() = bands;
+        *self.scope.lock().unwrap() = scope;
+    }
+}
+
+/// A window showing another part of a panorama spanning several monitors.
+struct Panel {
+    window_id: WindowId,
+    /// How far the window is turned to the right of the main window, in degrees.
+    yaw: f32,
 }
 
 struct Model {
     window_id: WindowId,
+    /// The other windows of the panorama, if any.
+    panels: Vec<Panel>,
+    /// The horizontal field of view of each window of the panorama in degrees, if any.
+    field_of_view: Option<f32>,
+    /// The limits negotiated with the GPU, which bound the size of the clouds.
+    gpu_limits: wgpu::Limits,
     egui: Egui,
     state: State,
     audio_stream: Stream<Audio>,
+    /// The stream capturing the microphone, while the cloud listens to it instead of the file.
+    microphone: Option<Stream<Analysis>>,
     shader_pipeline: RefCell<GPUPipeline>,
     update_camera: RefCell<bool>,
     update_cloud_data: RefCell<bool>,
+    update_attractors: RefCell<bool>,
+    update_masks: RefCell<bool>,
+    update_spectrum: RefCell<bool>,
+    update_pulse: RefCell<bool>,
+    update_curl_noise: RefCell<bool>,
+    update_gravity: RefCell<bool>,
+    update_impulses: RefCell<bool>,
+    update_fog: RefCell<bool>,
+    update_glow: RefCell<bool>,
+    update_lighting: RefCell<bool>,
+    update_coloring: RefCell<bool>,
+    update_clip: RefCell<bool>,
+    update_layers: RefCell<bool>,
+    update_trail: RefCell<bool>,
+    update_warp: RefCell<bool>,
+    update_gizmo: RefCell<bool>,
+    update_exposure: RefCell<bool>,
+    ply_sequence: Option<PlySequence>,
+    /// The copies of the points of the exported frames the GPU is not done with yet.
+    readback: PointReadback,
+    /// The directory the frames of the camera path are captured to, while it is played.
+    path_capture: Option<String>,
+    loading: Option<LoadHandle>,
+    /// The load of a point cloud added to the scene next to the main cloud.
+    adding: Option<LoadHandle>,
+    /// The load of the scan the cloud is compared with.
+    comparing: Option<LoadHandle>,
+    /// The load of the Gaussians of a capture drawn among the points.
+    loading_gaussians: Option<LoadHandle<Vec<Gaussian>>>,
+    /// The tiles of a cloud too large for the GPU being written to the disk, to stream them.
+    tiling: Option<LoadHandle<TileCache>>,
+    /// The streaming of the tiles of the main cloud nearest to the camera, if it is too large for
+    /// the GPU.
+    streamer: Option<TileStreamer>,
+    /// The high-resolution still being rendered a tile per frame, while the points are frozen.
+    still: Option<TiledStill>,
+    /// The image sequence played behind the points, if any.
+    background: Option<ImageSequence>,
+    /// The time of the background in seconds, which follows the camera path while it is played.
+    background_time: f32,
+    /// The frame of the background decoded since the last frame, to be uploaded.
+    background_frame: RefCell<Option<RgbaImage>>,
+    /// The formats the point clouds can be loaded from and the generators they can be created with.
+    registry: Registry,
+    palette: CommandPalette,
+    help: HotkeyHelp,
+    recorder: Recorder,
+    /// The stream of the frames published to compositing software over the network.
+    ndi: NdiOutput,
+    /// The gamepad moving the camera, for presenting away from the keyboard.
+    #[cfg(target_os = "linux")]
+    gamepad: Gamepad,
+    /// The statistics of the run, reported when the sketch exits.
+    session: SessionStats,
+    /// Whether the build is drawn in a corner of the window.
+    show_build_info: bool,
+    /// Whether the spectrum of the audio and the modulation of its bands are shown.
+    show_spectrum: bool,
+    /// The features of the audio the last frame reacted to, shown with the spectrum.
+    features: AudioFeatures,
+    /// The server receiving the parameters set remotely, if it could be started.
+    osc: Option<OscServer>,
     camera_is_active: bool,
+    /// Reports the frames to the supervisor and autosaves the settings, when the sketch is
+    /// supervised.
+    watchdog: Option<Watchdog>,
+}
+
+/// The number of points created by the generators.
+const GENERATED_POINTS: usize = 5000000;
+
+/// The number of points the generators create on a device deforming at most `max_points`.
+fn generated_points(max_points: usize) -> usize {
+    GENERATED_POINTS.min(max_points)
 }
 
-fn random_points() -> Vec<Point> {
+fn random_points(max_points: usize) -> Vec<Point> {
     let range_x = (-100.0, 100.0);
     let range_y = (-100.0, 100.0);
     let range_z = (-100.0, 100.0);
-    generate_random_point_cloud(5000000, range_x, range_y, range_z)
+    generate_random_point_cloud(
+        generated_points(max_points),
+        range_x,
+        range_y,
+        range_z,
+        rng::random(),
+    )
+}
+
+fn cloud_center(points: &[Point]) -> Point3 {
+    let (min, max) = Point::bounding_box(points);
+    (min + max) / 2.0
 }
 
 fn model(app: &App) -> Model {
+    let options = Options::load();
+    let mut display = DisplayOptions::load("cloud");
+    rng::load("cloud");
+    let layout = options
+        .layout
+        .as_deref()
+        .map_or_else(PanoramaLayout::default, |path| {
+            PanoramaLayout::read(path).unwrap_or_else(|e| {
+                eprintln!("Ignoring the layout {path}: {e}");
+                PanoramaLayout::default()
+            })
+        });
+    // The main window is the first of the panorama
+    if let Some(first) = layout.windows.first() {
+        display = display.or(first.display());
+    }
+
     // Setup app
     app.set_fullscreen_on_shortcut(true);
 
     // Set GPU device descriptor
+    // The limits are negotiated with the adapter, so devices without compute shaders or with
+    // small storage buffers still open and draw the points without deformation
+    let adapter = app
+        .wgpu_adapters()
+        .get_or_request(
+            // The adapter is shared with the window, which requests it with the same preference
+            wgpu::RequestAdapterOptions {
+                power_preference: wgpu::DEFAULT_POWER_PREFERENCE,
+                ..Default::default()
+            },
+            app.instance(),
+        )
+        .expect("no GPU adapter available");
+    let buffer_size = options
+        .max_buffer_mib
+        .map_or(GPUPipeline::DEFAULT_BUFFER_SIZE, |mib| mib << 20);
     let descriptor = wgpu::DeviceDescriptor {
         label: Some("Point Cloud Renderer Device"),
         features: wgpu::Features::default(),
-        limits: wgpu::Limits {
-            max_storage_buffer_binding_size: 2 << 30, // To support big point clouds
-            // max_texture_dimension_2d: 2 << 14,        // To support the big 9x3 4K display wall
-            ..Default::default()
-        },
+        limits: GPUPipeline::negotiate_limits(&adapter.limits(), buffer_size),
     };
 
     // Create a new window
-    let window_id = app
+    let mut window = app
         .new_window()
-        .fullscreen()
         .view(view)
         .raw_event(raw_window_event)
         .key_pressed(key_pressed)
-        .device_descriptor(descriptor)
-        .build()
-        .unwrap();
+        .mouse_pressed(mouse_pressed)
+        .device_descriptor(descriptor.clone());
+    if let Some([width, height]) = options.resolution {
+        window = window.size(width, height);
+    }
+    if options.fullscreen || options.resolution.is_none() || display.video_mode.is_some() {
+        window = window.fullscreen_with(Some(display.fullscreen(app)));
+    }
+    let window_id = window.build().unwrap();
+
+    // Open the other windows of the panorama, sharing the device of the main window
+    let panels: Vec<Panel> = layout
+        .windows
+        .iter()
+        .skip(1)
+        .map(|panel| {
+            let window_id = app
+                .new_window()
+                .view(view_panel)
+                .key_pressed(key_pressed)
+                .device_descriptor(descriptor.clone())
+                .fullscreen_with(Some(panel.display().fullscreen(app)))
+                .build()
+                .unwrap();
+            app.window(window_id).unwrap().set_cursor_visible(false);
+            Panel {
+                window_id,
+                yaw: panel.yaw - layout.windows[0].yaw,
+            }
+        })
+        .collect();
     let window = app.window(window_id).unwrap();
     let (window_width, window_height) = window.inner_size_pixels();
+    let gpu_limits = window.device().limits();
+    let max_points = GPUPipeline::max_points(&gpu_limits);
 
     // Get control of the cursor
     let camera_is_active = true;
@@ -90,65 +915,256 @@ fn model(app: &App) -> Model {
     }
     window.set_cursor_visible(false);
 
-    // Initialise the state that we want to live on the audio thread.
-    let audio_host = Host::new();
-    let fft_output = Arc::new(Mutex::new(1.0));
-    let audio_model = Audio {
-        sounds: Vec::new(),
-        fft_output: Arc::clone(&fft_output),
-    };
-
-    // Create audio stream
-    let audio_stream = audio_host
-        .new_output_stream(audio_model)
-        .sample_rate(48000)
-        .render(audio)
-        .build()
-        .unwrap();
+    // Initialise the state that we want to share with the audio thread.
+    let audio_features = Arc::new(Mutex::new(AudioFeatures::default()));
+    let spectrum_output = Arc::new(Mutex::new([0.0; SpectrumData::BANDS]));
+    let band_edges = AudioAnalyzer::new().band_edges();
 
     // Generate a random point cloud
-    let points = random_points();
+    let points = random_points(max_points);
 
     // Create the state
-    let cloud_data = CloudData {
-        sound_amplitude: 1.0,
-        wind_strength: 0.2,
-        noise_scale: 0.0,
-        spring_constant: 0.002,
-    };
+    let cloud_data = CloudData::new(0.2, [1.0, 1.0, 1.0], 0.0, 0.002).with_gusts(0.0, 0.2, 0.0);
+    // Spread the color ramp over the height of the cloud, and the crop box around it
+    let mut coloring = ColorSettings::default();
+    coloring.fit_elevation(&points);
+    let mut clip_box = ClipBox::default();
+    clip_box.fit(Point::bounding_box(&points));
+    let mut gravity = GravityData::default();
+    gravity.fit(&points);
+
+    let preset_library = PresetLibrary::new("cloud");
     let state = State {
         cloud_file_path: "./data/union_station.e57".to_owned(),
+        las_classes: String::new(),
+        compare_file_path: String::new(),
+        compare_range: 1.0,
+        generator: "Random cube".to_owned(),
         audio_file_path: "./data/audio.wav".to_owned(),
+        background_file_path: String::new(),
+        background_frame_rate: 30.0,
+        gaussian_file_path: String::new(),
+        gaussian_scale: 1.0,
         movement_speed: 0.5,
         mouse_sensitivity: 0.003,
+        #[cfg(target_os = "linux")]
+        gamepad: GamepadSettings::default(),
+        picking: false,
+        picks: Vec::new(),
+        obstacles: None,
+        collision_resolution: 128,
         cloud_data,
-        // This will be accessed by the audio thread.
-        fft_output,
+        attractor_data: AttractorData::default(),
+        attractor_strength: 0.5,
+        attractor_distance: 50.0,
+        masks: EffectMasks::default(),
+        export_directory: "./export".to_owned(),
+        export_every_n_frames: 2,
+        wall_tiles: [9, 3],
+        wall_tile_size: [3840, 2160],
+        still_size: [8192, 4608],
+        still_options: ImageOptions::default(),
+        shader_directory: ShaderWatcher::SOURCE_DIRECTORY.to_owned(),
+        auto_rotate: false,
+        rotation_speed: 10.0,
+        rotation_audio_modulation: 0.0,
+        world_angle: 0.0,
+        cloud_center: cloud_center(&points),
+        metadata: CloudMetadata::new(CloudSource::Generator("Random cube".to_owned()), &points),
+        clouds: vec![SceneCloud::new("Random cube generator".to_owned())],
+        looks: AbSlots::new(),
+        spectrum_data: SpectrumData::new(SpectralLayout::Off, 0.01, Point::bounding_box(&points)),
+        fog_settings: FogSettings::default(),
+        gizmo: GizmoSettings::default(),
+        glow: GlowSettings::default(),
+        lighting: LightingSettings::default(),
+        normal_neighbors: 16,
+        coloring,
+        clip_box,
+        trail_data: TrailData::default(),
+        warp_settings: WarpSettings::default(),
+        editing_warp: false,
+        exposure: ExposureSettings::default(),
+        presets: read_presets(&preset_library),
+        preset_library,
+        attract_enabled: false,
+        attract_loop: AttractLoop::default(),
+        camera_path: CameraPath::default(),
+        capture_path: false,
+        camera_bookmarks: CameraBookmarks::default(),
+        bookmark_duration: 1.5,
+        resolution_scaler: ResolutionScaler::default(),
+        lod_settings: LodSettings::default(),
+        splats: false,
+        band_edges,
+        band_mappings: [BandMapping {
+            target: BandTarget::Off,
+            amount: 0.5,
+        }; 3],
+        pulse_data: PulseData::default(),
+        effects: EffectStack::default(),
+        curl_noise: CurlNoiseData::default(),
+        gravity,
+        pulse_on_beat: false,
+        impulse_data: ImpulseData::default(),
+        impulses_from_points: false,
+        points,
+        history: CloudHistory::default(),
+        // These will be accessed by the audio thread.
+        audio_features,
+        spectrum_output,
+        audio_scope: Arc::new(Mutex::new(AudioScope::default())),
+        transport: Arc::new(Mutex::new(Transport::default())),
     };
 
+    // Create audio stream
+    let audio_model = Audio {
+        track: Vec::new(),
+        transport: Arc::clone(&state.transport),
+        analysis: Analysis::new(&state),
+    };
+    let audio_stream = Host::new()
+        .new_output_stream(audio_model)
+        .sample_rate(48000)
+        .render(audio)
+        .build()
+        .unwrap();
+
     // Create the camera
-    let camera_config = CameraConfig::default().with_aspect_ratio(window_width, window_height);
+    let mut camera_config = CameraConfig::default().with_aspect_ratio(window_width, window_height);
+    let field_of_view = (!panels.is_empty())
+        .then(|| layout.field_of_view())
+        .flatten();
+    if let Some(fov) = field_of_view {
+        camera_config.set_horizontal_fov(fov);
+    }
     let camera = Camera::new(camera_config);
 
     // Initialise the shader pipeline
-    let shader_pipeline = RefCell::new(GPUPipeline::new(&window, &points, camera, cloud_data));
+    let shader_pipeline = RefCell::new(GPUPipeline::new(
+        &window,
+        &state.points,
+        camera,
+        cloud_data,
+        state.attractor_data,
+        state.masks,
+        state.spectrum_data,
+    ));
 
     // Create the GUI
     let egui = Egui::from_window(&window);
 
-    Model {
+    // Listen to the parameters set remotely, within the ranges of their sliders
+    let osc = OscServer::start(OSC_PORT)
+        .inspect(|osc| {
+            osc.register("wind_strength", 0.0..=0.5);
+            osc.register("noise_scale", 0.0..=0.1);
+            osc.register("spring_constant", 0.0..=0.5);
+            osc.register("gust_strength", 0.0..=2.0);
+            osc.register("camera_speed", 0.01..=1.0);
+        })
+        .inspect_err(|e| eprintln!("{e}"))
+        .ok();
+
+    let mut model = Model {
         window_id,
+        panels,
+        field_of_view,
+        gpu_limits,
         egui,
         state,
         audio_stream,
+        microphone: None,
         shader_pipeline,
         update_camera: RefCell::new(false),
         update_cloud_data: RefCell::new(false),
+        update_attractors: RefCell::new(false),
+        update_masks: RefCell::new(false),
+        update_spectrum: RefCell::new(false),
+        update_pulse: RefCell::new(false),
+        update_curl_noise: RefCell::new(false),
+        update_gravity: RefCell::new(true),
+        update_impulses: RefCell::new(false),
+        update_fog: RefCell::new(false),
+        update_glow: RefCell::new(false),
+        update_lighting: RefCell::new(false),
+        update_coloring: RefCell::new(false),
+        update_clip: RefCell::new(false),
+        update_layers: RefCell::new(false),
+        update_trail: RefCell::new(false),
+        update_warp: RefCell::new(false),
+        update_gizmo: RefCell::new(false),
+        update_exposure: RefCell::new(false),
+        ply_sequence: None,
+        readback: PointReadback::default(),
+        path_capture: None,
+        loading: None,
+        adding: None,
+        comparing: None,
+        loading_gaussians: None,
+        tiling: None,
+        streamer: None,
+        still: None,
+        background: None,
+        background_time: 0.0,
+        background_frame: RefCell::new(None),
+        registry: Registry::default(),
+        palette: CommandPalette::new(),
+        help: HotkeyHelp::new(),
+        recorder: Recorder::new(),
+        ndi: NdiOutput::new("Point cloud"),
+        #[cfg(target_os = "linux")]
+        gamepad: Gamepad::connect(),
+        session: SessionStats::new(),
+        show_build_info: false,
+        show_spectrum: false,
+        features: AudioFeatures::default(),
+        osc,
         camera_is_active,
+        watchdog: Watchdog::from_env("cloud"),
+    };
+
+    // Boot into the scene given at launch
+    if let Some(path) = &options.preset {
+        match read_preset::<Settings>(path) {
+            Ok(settings) => {
+                settings.apply(&mut model.state);
+                *model.update_cloud_data.borrow_mut() = true;
+                *model.update_attractors.borrow_mut() = true;
+                *model.update_masks.borrow_mut() = true;
+                *model.update_fog.borrow_mut() = true;
+                *model.update_coloring.borrow_mut() = true;
+            }
+            Err(e) => eprintln!("{e}: {path}"),
+        }
+    }
+    if let Some(path) = options.cloud {
+        model.state.cloud_file_path = path;
+        perform(app, &mut model, Action::LoadCloud);
+    }
+    if let Some(path) = options.audio {
+        model.state.audio_file_path = path;
+        perform(app, &mut model, Action::LoadAudio);
+    }
+
+    // Pick up where the sketch was before it was restarted by its supervisor
+    if let Some(settings) = model
+        .watchdog
+        .as_ref()
+        .and_then(Watchdog::restore::<Settings>)
+    {
+        settings.apply(&mut model.state);
+        *model.update_cloud_data.borrow_mut() = true;
+        *model.update_attractors.borrow_mut() = true;
+        *model.update_masks.borrow_mut() = true;
+        *model.update_fog.borrow_mut() = true;
+        *model.update_coloring.borrow_mut() = true;
     }
+
+    model
 }
 
-fn view(_app: &App, model: &Model, frame: Frame) {
+fn view(app: &App, model: &Model, frame: Frame) {
     let mut pipeline = model.shader_pipeline.borrow_mut();
 
     // Check if the camera has been updated
@@ -167,224 +1183,2435 @@ fn view(_app: &App, model: &Model, frame: Frame) {
         *model.update_cloud_data.borrow_mut() = false;
     }
 
-    pipeline.render(&frame);
-    model.egui.draw_to_frame(&frame).unwrap();
-}
+    if *model.update_attractors.borrow() {
+        let device = frame.device_queue_pair().device();
+        let encoder = &mut frame.command_encoder();
+        pipeline.update_attractors(device, encoder, &model.state.attractor_data);
+        *model.update_attractors.borrow_mut() = false;
+    }
 
-fn update(app: &App, model: &mut Model, update: Update) {
-    // Update GUI
-    model.egui.set_elapsed_time(update.since_start);
-    let window = app.window(model.window_id).unwrap();
-    update_egui(model, window.device());
+    if *model.update_masks.borrow() {
+        let device = frame.device_queue_pair().device();
+        let encoder = &mut frame.command_encoder();
+        pipeline.update_masks(device, encoder, &model.state.masks);
+        *model.update_masks.borrow_mut() = false;
+    }
 
-    // Get the audio strength
-    let sound_amplitude = *model.state.fft_output.lock().unwrap();
-    // Check if the sound amplitude has changed
-    if model.state.cloud_data.sound_amplitude != sound_amplitude {
-        model.state.cloud_data.sound_amplitude = sound_amplitude;
-        *model.update_cloud_data.borrow_mut() = true;
+    if *model.update_spectrum.borrow() {
+        let device = frame.device_queue_pair().device();
+        let encoder = &mut frame.command_encoder();
+        pipeline.update_spectrum(device, encoder, &model.state.spectrum_data);
+        *model.update_spectrum.borrow_mut() = false;
     }
 
-    // Update the camera position
-    if model.camera_is_active {
-        let mut pipeline = model.shader_pipeline.borrow_mut();
-        let velocity = (update.since_last.secs() * model.state.movement_speed) as f32;
+    if *model.update_pulse.borrow() {
+        let device = frame.device_queue_pair().device();
+        let encoder = &mut frame.command_encoder();
+        pipeline.update_pulse(device, encoder, &model.state.pulse_data);
+        *model.update_pulse.borrow_mut() = false;
+    }
 
-        // Update camera and update the model if the camera has moved
-        if update_camera_position(pipeline.camera_mut(), velocity, &app.keys.down) {
-            *model.update_camera.borrow_mut() = true;
-        }
+    if *model.update_curl_noise.borrow() {
+        let device = frame.device_queue_pair().device();
+        let encoder = &mut frame.command_encoder();
+        pipeline.update_curl_noise(device, encoder, &model.state.curl_noise);
+        *model.update_curl_noise.borrow_mut() = false;
     }
-}
 
-fn audio(audio: &mut Audio, buffer: &mut Buffer) {
-    let mut have_ended = vec![];
-    let len_frames = buffer.len_frames();
+    if *model.update_gravity.borrow() {
+        let device = frame.device_queue_pair().device();
+        let encoder = &mut frame.command_encoder();
+        pipeline.update_gravity(device, encoder, &model.state.gravity);
+        *model.update_gravity.borrow_mut() = false;
+    }
 
-    // Sum all of the sounds onto the buffer.
-    audio.sounds.iter_mut().enumerate().for_each(|(i, sound)| {
-        let mut frame_count = 0;
-        let file_frames = sound.frames::<[f32; 2]>().filter_map(Result::ok);
-        for (frame, file_frame) in buffer.frames_mut().zip(file_frames) {
-            for (sample, file_sample) in frame.iter_mut().zip(&file_frame) {
-                *sample += *file_sample;
-            }
-            frame_count += 1;
-        }
+    if *model.update_impulses.borrow() {
+        let device = frame.device_queue_pair().device();
+        let encoder = &mut frame.command_encoder();
+        pipeline.update_impulses(device, encoder, &model.state.impulse_data);
+        *model.update_impulses.borrow_mut() = false;
+    }
 
-        // If the sound yielded less samples than are in the buffer, it must have ended.
-        if frame_count < len_frames {
-            have_ended.push(i);
-        }
-    });
+    if *model.update_fog.borrow() {
+        let device = frame.device_queue_pair().device();
+        let encoder = &mut frame.command_encoder();
+        pipeline.update_fog(device, encoder, model.state.fog_settings);
+        *model.update_fog.borrow_mut() = false;
+    }
 
-    // Remove all sounds that have ended.
-    have_ended.into_iter().rev().for_each(|i| {
-        audio.sounds.remove(i);
-    });
+    if *model.update_gizmo.borrow() {
+        let device = frame.device_queue_pair().device();
+        let bounds = model.state.metadata.bounds;
+        let placement = model.state.clouds[0].layer.transform.matrix();
+        pipeline.update_gizmo(device, &model.state.gizmo, bounds, placement);
+        *model.update_gizmo.borrow_mut() = false;
+    }
 
-    // Merge the audio channels and compute the FFT
-    let samples: Vec<_> = buffer.frames().flatten().cloned().collect();
-    let magnitude = compute_fft(&samples, buffer.sample_rate());
+    if *model.update_glow.borrow() {
+        let device = frame.device_queue_pair().device();
+        let encoder = &mut frame.command_encoder();
+        pipeline.update_glow(device, encoder, model.state.glow);
+        *model.update_glow.borrow_mut() = false;
+    }
 
-    // Update the audio strength value
-    *audio.fft_output.lock().unwrap() = magnitude;
-}
+    if *model.update_lighting.borrow() {
+        let device = frame.device_queue_pair().device();
+        let encoder = &mut frame.command_encoder();
+        pipeline.update_lighting(device, encoder, &model.state.lighting);
+        *model.update_lighting.borrow_mut() = false;
+    }
 
-fn compute_fft(samples: &[f32], sample_rate: u32) -> f32 {
-    // Apply hann window for smoothing; length must be a power of 2 for the FFT
-    let hann_window = hann_window(samples);
+    if *model.update_coloring.borrow() {
+        let device = frame.device_queue_pair().device();
+        let encoder = &mut frame.command_encoder();
+        pipeline.update_coloring(device, encoder, &model.state.coloring);
+        *model.update_coloring.borrow_mut() = false;
+    }
 
-    // Compute the FFT and get the spectrum
-    let spectrum =
-        samples_fft_to_spectrum(&hann_window, sample_rate, FrequencyLimit::Min(80.0), None).ok();
+    if *model.update_clip.borrow() {
+        let device = frame.device_queue_pair().device();
+        let encoder = &mut frame.command_encoder();
+        pipeline.update_clip(device, encoder, &model.state.clip_box);
+        *model.update_clip.borrow_mut() = false;
+    }
 
-    // Compute the sum of the magnitudes
-    match spectrum {
-        Some(s) => s.data().iter().map(|f| f.1.val()).sum::<f32>().max(1.0),
-        None => 1.0,
+    if *model.update_layers.borrow() {
+        let device = frame.device_queue_pair().device();
+        let encoder = &mut frame.command_encoder();
+        model
+            .state
+            .clouds
+            .iter()
+            .enumerate()
+            .for_each(|(index, cloud)| pipeline.update_layer(device, encoder, index, cloud.layer));
+        *model.update_layers.borrow_mut() = false;
     }
-}
 
-fn update_egui(model: &mut Model, device: &wgpu::Device) {
-    let ctx = model.egui.begin_frame();
-    let state = &mut model.state;
-    // Generate the settings window
-    egui::Window::new("Settings")
-        .default_width(0.0)
-        .show(&ctx, |ui| {
-            let prev_noise_scale = state.cloud_data.noise_scale;
-            ui.label("noise_scale:");
-            ui.add(egui::Slider::new(
-                &mut state.cloud_data.noise_scale,
-                0.0..=0.1,
-            ));
+    if *model.update_trail.borrow() {
+        let device = frame.device_queue_pair().device();
+        let encoder = &mut frame.command_encoder();
+        pipeline.update_trail(device, encoder, model.state.trail_data);
+        *model.update_trail.borrow_mut() = false;
+    }
 
-            let prev_wind_strength = state.cloud_data.wind_strength;
-            ui.label("wind_strength:");
-            ui.add(egui::Slider::new(
-                &mut state.cloud_data.wind_strength,
-                0.0..=0.5,
-            ));
+    if *model.update_warp.borrow() {
+        let device = frame.device_queue_pair().device();
+        pipeline.update_warp(device, model.state.warp_settings);
+        *model.update_warp.borrow_mut() = false;
+    }
 
-            let prev_spring_constant = state.cloud_data.spring_constant;
-            ui.label("spring_constant:");
-            ui.add(egui::Slider::new(
-                &mut state.cloud_data.spring_constant,
-                0.0..=0.5,
-            ));
+    // The automatic exposure adapts over the frames
+    if *model.update_exposure.borrow() || model.state.exposure.auto {
+        let device = frame.device_queue_pair().device();
+        let encoder = &mut frame.command_encoder();
+        let delta_time = app.duration.since_prev_update.as_secs_f32();
+        pipeline.update_exposure(device, encoder, model.state.exposure, delta_time);
+        *model.update_exposure.borrow_mut() = false;
+    }
 
-            // Check if the cloud data has changed
-            if prev_noise_scale != state.cloud_data.noise_scale
-                || prev_wind_strength != state.cloud_data.wind_strength
-                || prev_spring_constant != state.cloud_data.spring_constant
-            {
-                *model.update_cloud_data.borrow_mut() = true;
-            }
+    if let Some(image) = model.background_frame.borrow_mut().take() {
+        let device = frame.device_queue_pair().device();
+        let encoder = &mut frame.command_encoder();
+        pipeline.update_background(device, encoder, &image);
+    }
 
-            ui.label("movement_speed:");
-            ui.add(egui::Slider::new(&mut state.movement_speed, 0.01..=1.0));
+    pipeline.render(&frame);
+    let clip_box = &model.state.clip_box;
+    let show_outline = clip_box.enabled && clip_box.show_outline;
+    let picks = &model.state.picks;
+    if show_outline || model.show_build_info || !picks.is_empty() {
+        let draw = app.draw();
+        draw_picks(&draw, picks, pipeline.camera(), app.window_rect());
+        if show_outline {
+            clip_box
+                .outline(pipeline.camera(), app.window_rect())
+                .into_iter()
+                .for_each(|[start, end]| {
+                    draw.line().start(start).end(end).weight(1.0).color(YELLOW);
+                });
+        }
+        if model.show_build_info {
+            build_info::draw_overlay(&draw, app.window_rect());
+        }
+        draw.to_frame(app, &frame).unwrap();
+    }
+    model.recorder.capture(&frame);
+    model.ndi.capture(&frame);
+    model.egui.draw_to_frame(&frame).unwrap();
+}
 
-            ui.label("mouse_sensitivity:");
-            ui.add(egui::Slider::new(
-                &mut state.mouse_sensitivity,
-                0.001..=0.01,
-            ));
+/// Draws the part of the panorama shown by another window, turned from the camera.
+fn view_panel(_app: &App, model: &Model, frame: Frame) {
+    let Some(index) = model
+        .panels
+        .iter()
+        .position(|panel| panel.window_id == frame.window_id())
+    else {
+        return;
+    };
+    let mut pipeline = model.shader_pipeline.borrow_mut();
+    let mut camera = *pipeline.camera();
+    camera.update_yaw(-model.panels[index].yaw.to_radians());
+    let [width, height] = frame.texture_size();
+    camera.config.set_aspect_ratio(width, height);
+    if let Some(fov) = model.field_of_view {
+        camera.config.set_horizontal_fov(fov);
+    }
+    pipeline.render_panel(&frame, index, &camera);
+}
 
-            ui.label("E57 path:");
-            ui.text_edit_singleline(&mut state.cloud_file_path);
+fn exit(app: &App, model: Model) {
+    match model.session.write(&app.exe_name().unwrap()) {
+        Ok(path) => println!("Saved the session report to: {path}"),
+        Err(e) => eprintln!("{e}"),
+    }
+}
 
-            let load_cloud = ui.button("Load file").clicked();
-            if load_cloud {
-                // Get the points from the E57 file if possible
-                let points = if state.cloud_file_path.is_empty() {
-                    random_points()
-                } else {
-                    match read_e57(&state.cloud_file_path) {
-                        Ok(points) => points,
-                        Err(_) => random_points(),
-                    }
-                };
+fn update(app: &App, model: &mut Model, update: Update) {
+    model.session.frame();
+    if let Some(watchdog) = &mut model.watchdog {
+        watchdog.tick(|| Settings::from_state(&model.state));
+    }
 
-                // Update the camera and points
-                model
-                    .shader_pipeline
-                    .borrow_mut()
-                    .new_point_cloud(device, &points);
+    // Update GUI
+    model.egui.set_elapsed_time(update.since_start);
+    let window = app.window(model.window_id).unwrap();
+    if let Some(action) = update_egui(model, app.fps()) {
+        perform(app, model, action);
+    }
+
+    // Apply the parameters set remotely
+    if let Some(osc) = &model.osc {
+        let state = &mut model.state;
+        let prev_cloud_data = state.cloud_data;
+        let cloud_data = &mut state.cloud_data;
+        [
+            ("wind_strength", &mut cloud_data.wind_strength),
+            ("noise_scale", &mut cloud_data.noise_scale),
+            ("spring_constant", &mut cloud_data.spring_constant),
+            ("gust_strength", &mut cloud_data.gust_strength),
+        ]
+        .into_iter()
+        .for_each(|(name, parameter)| {
+            if let Some(value) = osc.take(name) {
+                *parameter = value;
+            }
+        });
+        if let Some(speed) = osc.take("camera_speed") {
+            state.movement_speed = speed as f64;
+        }
+        if state.cloud_data != prev_cloud_data {
+            *model.update_cloud_data.borrow_mut() = true;
+        }
+    }
+
+    // Render the next tile of the high-resolution still, and save it once done
+    if let Some(still) = &mut model.still {
+        let window = app.window(model.window_id).unwrap();
+        let device_queue_pair = window.device_queue_pair();
+        let mut pipeline = model.shader_pipeline.borrow_mut();
+        let rendered = still.render_next(
+            &mut pipeline,
+            device_queue_pair.device(),
+            device_queue_pair.queue(),
+        );
+        if let Err(e) = rendered {
+            eprintln!("Failed to render the still: {e}");
+            pipeline.set_frozen(false);
+            model.still = None;
+        } else if still.is_done() {
+            pipeline.set_frozen(false);
+            let image = model.still.take().unwrap().into_linear_image();
+            let parameters = build_info::parameters(&Settings::from_state(&model.state));
+            let options = model.state.still_options;
+            let path = options.save_path(&app.exe_name().unwrap());
+            match options.save(&image, &path, parameters.as_deref()) {
+                Ok(()) => model.session.capture(),
+                Err(e) => eprintln!("Failed to save the still: {e}"),
+            }
+        }
+    }
+
+    // Swap in the point cloud once it is loaded
+    if let Some(result) = model.loading.as_ref().and_then(LoadHandle::try_take) {
+        let path = model.loading.take().unwrap().path().to_owned();
+        let (points, source) = match result {
+            Ok(points) => {
+                model.session.file_loaded(&path);
+                (points, CloudSource::file(&path))
+            }
+            Err(e) => {
+                eprintln!("{e}: {path}");
+                let source = CloudSource::Generator("Random cube".to_owned());
+                (
+                    random_points(GPUPipeline::max_points(&model.gpu_limits)),
+                    source,
+                )
+            }
+        };
+        replace_points(app, model, points, source, format!("load of {path}"));
+    }
+
+    // Stream the detail of the cloud once its tiles are written
+    if let Some(result) = model.tiling.as_ref().and_then(LoadHandle::try_take) {
+        let path = model.tiling.take().unwrap().path().to_owned();
+        match result {
+            Ok(cache) => model.streamer = Some(TileStreamer::new(cache)),
+            Err(e) => eprintln!("{e}: {path}"),
+        }
+    }
+
+    // Color the points by their distance to the other scan once it is loaded
+    if let Some(result) = model.comparing.as_ref().and_then(LoadHandle::try_take) {
+        let path = model.comparing.take().unwrap().path().to_owned();
+        match result {
+            Ok(reference) => {
+                model.session.file_loaded(&path);
+                compare_points(app, model, &reference, &path);
+            }
+            Err(e) => eprintln!("{e}: {path}"),
+        }
+    }
+
+    // Add the point cloud to the scene once it is loaded
+    if let Some(result) = model.adding.as_ref().and_then(LoadHandle::try_take) {
+        let path = model.adding.take().unwrap().path().to_owned();
+        match result {
+            Ok(points) => {
+                model.session.file_loaded(&path);
+                add_cloud(app, model, points, path);
+            }
+            Err(e) => eprintln!("{e}: {path}"),
+        }
+    }
+
+    // Draw the Gaussians of the capture once they are loaded, framing them with the camera
+    if let Some(result) = model
+        .loading_gaussians
+        .as_ref()
+        .and_then(LoadHandle::try_take)
+    {
+        let path = model.loading_gaussians.take().unwrap().path().to_owned();
+        match result {
+            Ok(gaussians) => {
+                model.session.file_loaded(&path);
+                let min = gaussians
+                    .iter()
+                    .fold(Vec3::splat(f32::MAX), |min, gaussian| {
+                        min.min(Vec3::from(gaussian.position))
+                    });
+                let max = gaussians
+                    .iter()
+                    .fold(Vec3::splat(f32::MIN), |max, gaussian| {
+                        max.max(Vec3::from(gaussian.position))
+                    });
+                let mut pipeline = model.shader_pipeline.borrow_mut();
+                pipeline.set_gaussians(window.device(), &gaussians);
+                if pipeline.gaussian_count() > 0 {
+                    pipeline.camera_mut().fit_bounds(min, max);
+                }
+            }
+            Err(e) => eprintln!("{e}: {path}"),
+        }
+    }
+
+    // Scale the resolution to hold the target frame rate
+    let scale = model
+        .state
+        .resolution_scaler
+        .update(update.since_last.secs() as f32);
+    let mut pipeline = model.shader_pipeline.borrow_mut();
+    pipeline.set_render_scale(scale);
+    pipeline.set_lod_settings(model.state.lod_settings);
+    pipeline.set_splats(model.state.splats);
+    pipeline.set_gaussian_scale(model.state.gaussian_scale);
+    drop(pipeline);
+
+    // Get the audio strength and the modulation of the bands
+    // The onset is taken so that each beat only sends one pulse
+    let features = model.state.audio_features.lock().unwrap().take();
+    model.features = features;
+    let sound_amplitude = features.magnitude;
+    let state = &mut model.state;
+    let prev_cloud_data = state.cloud_data;
+    let energies = [features.bass, features.mid, features.treble];
+    let levels = std::array::from_fn(|i| state.band_mappings[i].amount * energies[i].ln_1p());
+    state.cloud_data.sound_amplitude = sound_amplitude;
+    state
+        .cloud_data
+        .set_band_levels(state.band_mappings.map(|mapping| mapping.target), levels);
+    // Check if the audio has changed the deformation
+    if state.cloud_data != prev_cloud_data {
+        *model.update_cloud_data.borrow_mut() = true;
+    }
+
+    // Send a pulse on each beat and move the pulse in flight
+    let state = &mut model.state;
+    if features.onset && state.pulse_on_beat {
+        state.pulse_data.trigger(state.cloud_center);
+        *model.update_pulse.borrow_mut() = true;
+    } else if state.pulse_data.is_active() {
+        state.pulse_data.tick(update.since_last.secs() as f32);
+        *model.update_pulse.borrow_mut() = true;
+    }
+
+    // Move the impulses in flight, and send one on each onset while the effect is on
+    if state.impulse_data.is_active() {
+        state.impulse_data.tick(update.since_last.secs() as f32);
+        *model.update_impulses.borrow_mut() = true;
+    }
+    if features.onset
+        && state
+            .effects
+            .enabled()
+            .any(|effect| effect == Effect::Impulses)
+    {
+        let origin = impulse_origin(state);
+        state.impulse_data.spawn(origin);
+        *model.update_impulses.borrow_mut() = true;
+    }
+
+    // Get the energy of each band of the spectrum
+    if model.state.spectrum_data.layout() != SpectralLayout::Off {
+        model.state.spectrum_data.bands = *model.state.spectrum_output.lock().unwrap();
+        *model.update_spectrum.borrow_mut() = true;
+    }
+
+    // Advance the time of the gusts
+    if model.state.cloud_data.gust_strength != 0.0 || model.state.cloud_data.gust_spread != 0.0 {
+        model.state.cloud_data.time = update.since_start.secs() as f32;
+        *model.update_cloud_data.borrow_mut() = true;
+    }
+
+    // Shorten the trails the device cannot hold, such as after loading a larger cloud, so they are
+    // not cleared again each frame
+    let max_trail_length = model.shader_pipeline.borrow().max_trail_length();
+    if model.state.trail_data.length() > max_trail_length {
+        model.state.trail_data.set_length(max_trail_length);
+        *model.update_trail.borrow_mut() = true;
+    }
+
+    // Record the positions of the points for their trails
+    if model.state.trail_data.is_enabled() {
+        model.state.trail_data.tick();
+        *model.update_trail.borrow_mut() = true;
+    }
+
+    // Rotate the world around the y axis like a turntable
+    if model.state.auto_rotate {
+        let state = &mut model.state;
+        let modulation = 1.0 + state.rotation_audio_modulation * sound_amplitude.ln();
+        let delta = state.rotation_speed * modulation * update.since_last.secs() as f32;
+        state.world_angle = (state.world_angle + delta) % 360.0;
+        model
+            .shader_pipeline
+            .borrow_mut()
+            .camera_mut()
+            .config
+            .set_rotation_about(state.world_angle, state.cloud_center);
+        *model.update_camera.borrow_mut() = true;
+    }
+
+    // Blend between the presets while no one is interacting
+    if model.state.attract_enabled {
+        let state = &mut model.state;
+        let delta = update.since_last.secs() as f32;
+        if let Some(preset) = state.attract_loop.tick(delta, &state.presets) {
+            preset.apply(state, model.shader_pipeline.borrow_mut().camera_mut());
+            *model.update_camera.borrow_mut() = true;
+            *model.update_cloud_data.borrow_mut() = true;
+            *model.update_attractors.borrow_mut() = true;
+            *model.update_masks.borrow_mut() = true;
+            *model.update_fog.borrow_mut() = true;
+            *model.update_coloring.borrow_mut() = true;
+        }
+    }
+
+    // Fly the camera to the recalled bookmark, unless the path below takes over
+    let delta = update.since_last.secs() as f32;
+    if model
+        .state
+        .camera_bookmarks
+        .advance(model.shader_pipeline.borrow_mut().camera_mut(), delta)
+    {
+        *model.update_camera.borrow_mut() = true;
+    }
+
+    // Fly the camera along the recorded path, one fixed step per frame
+    if let Some((frame, keyframe)) = model.state.camera_path.next_frame() {
+        keyframe.apply(model.shader_pipeline.borrow_mut().camera_mut());
+        keyframe.apply_gravity(&mut model.state.gravity);
+        *model.update_camera.borrow_mut() = true;
+        *model.update_gravity.borrow_mut() = true;
+        model.background_time = keyframe.time;
+        model.state.attract_loop.interact();
+        if let Some(directory) = &model.path_capture {
+            model
+                .recorder
+                .save_frame(format!("{directory}/frame_{frame:05}.png"), None);
+        }
+    } else {
+        model.path_capture = None;
+        model.background_time += update.since_last.secs() as f32;
+    }
+
+    // Show the frame of the background at the time of the timeline
+    // Captured frames wait for their image so the footage stays in sync with the points
+    if let Some(sequence) = &mut model.background {
+        sequence.frame_rate = model.state.background_frame_rate;
+        let wait = model.path_capture.is_some();
+        if let Some(image) = sequence.poll(model.background_time, wait) {
+            *model.background_frame.borrow_mut() = Some(image);
+        }
+    }
+
+    // Export the deformed points, read back from the GPU over the next frames so it is not
+    // waited on
+    if let Some(sequence) = &mut model.ply_sequence {
+        submit_read_back(sequence, model.readback.poll(window.device()));
+        if let Some(frame) = sequence.tick()
+            && !model.shader_pipeline.borrow().request_points(
+                window.device(),
+                window.queue(),
+                &mut model.readback,
+                frame,
+            )
+        {
+            eprintln!("warning: the GPU is behind the export, dropping frame {frame}");
+        }
+    }
+
+    // Swap in the shaders once they are edited
+    model
+        .shader_pipeline
+        .borrow_mut()
+        .reload_shaders(window.device());
+
+    // The camera collides with the main cloud where its transform places it
+    if let Some(obstacles) = &mut model.state.obstacles {
+        obstacles.set_placement(model.state.clouds[0].layer.transform.matrix());
+    }
+
+    // Update the camera position, unless the keys are used to type in the palette
+    if model.camera_is_active && !model.palette.is_open() {
+        let mut pipeline = model.shader_pipeline.borrow_mut();
+        let velocity = (update.since_last.secs() * model.state.movement_speed) as f32;
+
+        // Update camera and update the model if the camera has moved
+        let obstacles = model.state.obstacles.as_ref();
+        if update_camera_position(pipeline.camera_mut(), velocity, &app.keys.down, obstacles) {
+            *model.update_camera.borrow_mut() = true;
+        }
+    }
+
+    // Bring the tiles nearest to the camera to the GPU, evicting those it moved away from
+    if let Some(streamer) = &mut model.streamer {
+        let mut pipeline = model.shader_pipeline.borrow_mut();
+        let camera = pipeline.camera();
+        let to_cloud = model.state.clouds[0].layer.transform.matrix().inverse() * camera.to_scene();
+        let changes = streamer.update(to_cloud.transform_point3(camera.position));
+        changes
+            .evicted
+            .into_iter()
+            .for_each(|tile| pipeline.evict_tile(tile));
+        changes
+            .loaded
+            .into_iter()
+            .for_each(|(tile, points)| pipeline.upload_tile(window.device(), tile, &points));
+    }
+
+    // Move the camera with the gamepad, whether or not the cursor is bound to the window
+    #[cfg(target_os = "linux")]
+    fly_with_gamepad(model, update.since_last.secs() as f32);
+}
+
+/// Moves the camera by the sticks and triggers of the gamepad over `delta` seconds.
+///
+/// The gamepad is only read on Linux, see [`Gamepad`].
+#[cfg(target_os = "linux")]
+fn fly_with_gamepad(model: &mut Model, delta: f32) {
+    let motion = model
+        .gamepad
+        .axes()
+        .map(|axes| model.state.gamepad.motion(&axes))
+        .unwrap_or_default();
+    if motion == GamepadMotion::default() {
+        return;
+    }
+    let velocity = delta * model.state.movement_speed as f32;
+    let mut pipeline = model.shader_pipeline.borrow_mut();
+    let camera = pipeline.camera_mut();
+    let obstacles = model.state.obstacles.as_ref();
+    camera.move_towards(Direction::Right, motion.movement.x * velocity, obstacles);
+    camera.move_towards(Direction::Up, motion.movement.y * velocity, obstacles);
+    camera.move_towards(Direction::Forward, motion.movement.z * velocity, obstacles);
+    camera.update_yaw(-motion.look.x * delta);
+    camera.update_pitch(motion.look.y * delta);
+    *model.update_camera.borrow_mut() = true;
+    model.state.camera_bookmarks.cancel();
+    model.state.attract_loop.interact();
+}
+
+/// Queues the points read back from the GPU to be written by the export, by the number of their
+/// frame.
+fn submit_read_back(
+    sequence: &mut PlySequence,
+    read_back: Vec<(u32, Result<Vec<Point>, &'static str>)>,
+) {
+    read_back
+        .into_iter()
+        .for_each(|(frame, points)| match points {
+            Ok(points) => sequence.submit(frame, points),
+            Err(e) => eprintln!("{e}: frame {frame}"),
+        });
+}
+
+fn audio(audio: &mut Audio, buffer: &mut Buffer) {
+    // Play the file onto the buffer from the playhead
+    audio
+        .transport
+        .lock()
+        .unwrap()
+        .play_into(&audio.track, buffer.frames_mut());
+
+    audio.analysis.process(buffer);
+}
+
+fn capture(analysis: &mut Analysis, buffer: &Buffer) {
+    analysis.process(buffer);
+}
+
+/// Opens a stream analyzing the default microphone.
+fn open_microphone(state: &State) -> Result<Stream<Analysis>, &'static str> {
+    let stream = Host::new()
+        .new_input_stream(Analysis::new(state))
+        .capture(capture)
+        .build()
+        .map_err(|_| "Failed to open the microphone")?;
+    stream
+        .play()
+        .map_err(|_| "Failed to start capturing the microphone")?;
+    Ok(stream)
+}
+
+fn update_egui(model: &mut Model, fps: f32) -> Option<Action> {
+    let ctx = model.egui.begin_frame();
+    let exporting = model.ply_sequence.is_some();
+    let recording = model.recorder.is_recording();
+    let streaming = model.ndi.is_running();
+    let listening = model.microphone.is_some();
+    let commands = commands(&model.state, exporting, recording, streaming, listening);
+    let mut action = model.palette.show(&ctx, &commands);
+    let mut hotkeys = describe(&bindings());
+    hotkeys.extend([
+        (
+            "1 - 9".to_owned(),
+            "Store the camera pose as a bookmark".to_owned(),
+        ),
+        (
+            "Shift + 1 - 9".to_owned(),
+            "Fly the camera to a bookmark".to_owned(),
+        ),
+    ]);
+    hotkeys.extend(describe(&camera_bindings()));
+    model.help.show(&ctx, &hotkeys);
+    let state = &mut model.state;
+    let drawn_points = model.shader_pipeline.borrow().drawn_points();
+    let gpu_memory = model.shader_pipeline.borrow().gpu_memory();
+    let deforms = model.shader_pipeline.borrow().deforms();
+    let max_trail_length = model.shader_pipeline.borrow().max_trail_length();
+    let gpu_limits = &model.gpu_limits;
+    let shader_reload = model
+        .shader_pipeline
+        .borrow()
+        .shader_watcher()
+        .map(|watcher| watcher.error().map(str::to_owned));
+    let prev_band_edges = state.band_edges;
+
+    // Show the coordinates and colors of the picked points, and the distance between them
+    if state.picking {
+        egui::Window::new("Measure")
+            .default_width(280.0)
+            .show(&ctx, |ui| show_picks(ui, &state.picks));
+    }
+
+    // Show which frequencies drive the deformation, and how much
+    if model.show_spectrum {
+        let scope = state.audio_scope.lock().unwrap().clone();
+        let features = model.features;
+        egui::Window::new("Spectrum")
+            .default_width(360.0)
+            .show(&ctx, |ui| {
+                ui.add(SpectrumScope::new(&scope, &mut state.band_edges));
+                let energies = [features.bass, features.mid, features.treble];
+                egui::Grid::new("Band modulation").show(ui, |ui| {
+                    ["bass", "mid", "treble"]
+                        .into_iter()
+                        .zip(energies)
+                        .zip(state.band_mappings)
+                        .zip(SpectrumScope::BAND_COLORS)
+                        .for_each(|(((band, energy), mapping), color)| {
+                            ui.colored_label(color, band);
+                            ui.label(format!("{energy:.3}"));
+                            match mapping.target {
+                                BandTarget::Off => ui.label("off"),
+                                target => ui.label(format!(
+                                    "{target:?} +{:.0}%",
+                                    mapping.amount * energy.ln_1p() * 100.0
+                                )),
+                            };
+                            ui.end_row();
+                        });
+                });
+            });
+    }
+
+    // Generate the stats overlay
+    egui::Area::new("Stats")
+        .anchor(egui::Align2::RIGHT_TOP, [-10.0, 10.0])
+        .show(&ctx, |ui| {
+            ui.label(format!("fps: {fps:.1}"));
+            ui.label(format!(
+                "render_scale: {:.0}%",
+                state.resolution_scaler.scale() * 100.0
+            ));
+            ui.label(format!("drawn_points: {drawn_points}"));
+            ui.label(format!(
+                "storage_binding: {} MiB",
+                gpu_limits.max_storage_buffer_binding_size >> 20
+            ));
+            ui.label(format!(
+                "max_buffer: {} MiB",
+                gpu_limits.max_buffer_size >> 20
+            ));
+            ui.label(format!(
+                "max_points: {}",
+                GPUPipeline::max_points(gpu_limits)
+            ));
+            if !deforms {
+                ui.colored_label(egui::Color32::YELLOW, "deformation unavailable on this GPU");
+            }
+        });
+
+    // Generate the settings window
+    egui::Window::new("Settings")
+        .default_width(0.0)
+        .show(&ctx, |ui| {
+            let prev_effects = state.effects.clone();
+            let prev_curl_noise = state.curl_noise;
+            let prev_gravity = state.gravity;
+            let prev_impulses = state.impulse_data;
+            ui.collapsing("Effect stack", |ui| {
+                let send_impulse = effects_ui(
+                    ui,
+                    &mut state.effects,
+                    &mut state.curl_noise,
+                    &mut state.gravity,
+                    &mut state.impulse_data,
+                    &mut state.impulses_from_points,
+                );
+                if send_impulse {
+                    action = Some(Action::SendImpulse);
+                }
+            });
+            if prev_effects != state.effects {
                 model
                     .shader_pipeline
                     .borrow_mut()
-                    .camera_mut()
-                    .fit_points(&points);
-                *model.update_camera.borrow_mut() = true;
+                    .set_effects(state.effects.clone());
+            }
+            if prev_curl_noise != state.curl_noise {
+                *model.update_curl_noise.borrow_mut() = true;
+            }
+            if prev_gravity != state.gravity {
+                *model.update_gravity.borrow_mut() = true;
+            }
+            if prev_impulses != state.impulse_data {
+                *model.update_impulses.borrow_mut() = true;
+            }
+
+            let prev_cloud_data = state.cloud_data;
+            ui.add(LabelledSlider::new(
+                "noise_scale",
+                &mut state.cloud_data.noise_scale,
+                0.0..=0.1,
+            ));
+            ui.add(LabelledSlider::new(
+                "wind_strength",
+                &mut state.cloud_data.wind_strength,
+                0.0..=0.5,
+            ));
+
+            ui.label("wind_direction:");
+            ui.horizontal(|ui| {
+                let direction = &mut state.cloud_data.wind_direction;
+                ui.add(
+                    egui::DragValue::new(&mut direction[0])
+                        .speed(0.01)
+                        .clamp_range(-1.0..=1.0)
+                        .prefix("x: "),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut direction[1])
+                        .speed(0.01)
+                        .clamp_range(-1.0..=1.0)
+                        .prefix("y: "),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut direction[2])
+                        .speed(0.01)
+                        .clamp_range(-1.0..=1.0)
+                        .prefix("z: "),
+                );
+            });
+
+            ui.add(LabelledSlider::new(
+                "gust_strength",
+                &mut state.cloud_data.gust_strength,
+                0.0..=2.0,
+            ));
+            ui.add(LabelledSlider::new(
+                "gust_frequency",
+                &mut state.cloud_data.gust_frequency,
+                0.01..=2.0,
+            ));
+            ui.add(LabelledSlider::new(
+                "gust_spread",
+                &mut state.cloud_data.gust_spread,
+                0.0..=1.0,
+            ));
+            ui.add(LabelledSlider::new(
+                "spring_constant",
+                &mut state.cloud_data.spring_constant,
+                0.0..=0.5,
+            ));
+
+            ui.checkbox(&mut state.splats, "Splats");
+
+            ui.add(
+                LabelledSlider::new("point_size", &mut state.cloud_data.point_size, 0.001..=2.0)
+                    .logarithmic(),
+            );
+
+            // Check if the cloud data has changed
+            if prev_cloud_data != state.cloud_data {
+                *model.update_cloud_data.borrow_mut() = true;
+            }
+
+            ui.separator();
+
+            ui.label(format!(
+                "attractors: {}/{}",
+                state.attractor_data.len(),
+                AttractorData::MAX_ATTRACTORS
+            ));
+
+            let prev_attractor_strength = state.attractor_strength;
+            ui.add(LabelledSlider::new(
+                "attractor_strength",
+                &mut state.attractor_strength,
+                -2.0..=2.0,
+            ));
+
+            let prev_attractor_radius = state.attractor_data.radius;
+            ui.add(LabelledSlider::new(
+                "attractor_radius",
+                &mut state.attractor_data.radius,
+                0.1..=100.0,
+            ));
+            ui.add(LabelledSlider::new(
+                "attractor_distance",
+                &mut state.attractor_distance,
+                1.0..=200.0,
+            ));
+
+            if ui.button("Clear attractors").clicked() {
+                action = Some(Action::ClearAttractors);
+            }
+
+            // Check if the attractors have changed
+            if prev_attractor_strength != state.attractor_strength {
+                state.attractor_data.set_strength(state.attractor_strength);
+                *model.update_attractors.borrow_mut() = true;
+            }
+            if prev_attractor_radius != state.attractor_data.radius {
+                *model.update_attractors.borrow_mut() = true;
+            }
+
+            ui.separator();
+
+            let mut layout = state.spectrum_data.layout();
+            let layouts = [
+                (SpectralLayout::Off, "Off"),
+                (SpectralLayout::AxisX, "AxisX"),
+                (SpectralLayout::Radial, "Radial"),
+            ];
+            if ui
+                .add(Dropdown::new("spectral_layout", &mut layout, layouts))
+                .changed()
+            {
+                state.spectrum_data.set_layout(layout);
+                *model.update_spectrum.borrow_mut() = true;
+            }
+
+            ui.add(
+                LabelledSlider::new(
+                    "spectral_strength",
+                    &mut state.spectrum_data.strength,
+                    0.0..=0.1,
+                )
+                .logarithmic(),
+            );
+
+            ui.separator();
+
+            ui.add(
+                LabelledSlider::new(
+                    "bass_max_frequency (Hz)",
+                    &mut state.band_edges[0],
+                    40.0..=1000.0,
+                )
+                .logarithmic(),
+            );
+            ui.add(
+                LabelledSlider::new(
+                    "mid_max_frequency (Hz)",
+                    &mut state.band_edges[1],
+                    1000.0..=12000.0,
+                )
+                .logarithmic(),
+            );
+
+            let bands = ["bass", "mid", "treble"];
+            bands
+                .into_iter()
+                .zip(&mut state.band_mappings)
+                .for_each(|(band, mapping)| {
+                    ui.add(Dropdown::new(
+                        format!("{band}_target"),
+                        &mut mapping.target,
+                        [
+                            (BandTarget::Off, "Off"),
+                            (BandTarget::WindStrength, "WindStrength"),
+                            (BandTarget::NoiseScale, "NoiseScale"),
+                            (BandTarget::SpringConstant, "SpringConstant"),
+                        ],
+                    ));
+                    let label = format!("{band}_modulation");
+                    ui.add(LabelledSlider::new(label, &mut mapping.amount, 0.0..=1.0));
+                });
+
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut state.pulse_on_beat, "Pulse on beat");
+                if ui.button("Trigger pulse").clicked() {
+                    action = Some(Action::TriggerPulse);
+                }
+            });
+            let pulse = &mut state.pulse_data;
+            ui.add(
+                LabelledSlider::new("pulse_strength", &mut pulse.strength, 0.0..=0.5).logarithmic(),
+            );
+            ui.add(LabelledSlider::new("pulse_speed", &mut pulse.speed, 1.0..=200.0).logarithmic());
+            ui.add(LabelledSlider::new("pulse_decay", &mut pulse.decay, 0.1..=10.0).logarithmic());
+            ui.add(LabelledSlider::new("pulse_width", &mut pulse.width, 0.1..=50.0).logarithmic());
+
+            ui.separator();
+
+            let prev_coloring = state.coloring;
+            let coloring = &mut state.coloring;
+            ui.add(Dropdown::new(
+                "color_mode",
+                &mut coloring.mode,
+                [
+                    (ColorMode::Rgb, "RGB"),
+                    (ColorMode::Elevation, "Elevation"),
+                    (ColorMode::Intensity, "Intensity"),
+                    (ColorMode::Distance, "Distance"),
+                ],
+            ));
+            if coloring.mode != ColorMode::Rgb {
+                ui.horizontal(|ui| {
+                    ui.label("ramp:");
+                    coloring.stops.iter_mut().for_each(|stop| {
+                        ui.color_edit_button_rgb(stop);
+                    });
+                });
+                ui.horizontal(|ui| {
+                    RampPreset::ALL.into_iter().for_each(|preset| {
+                        if ui.button(preset.name()).clicked() {
+                            coloring.stops = preset.stops();
+                        }
+                    });
+                });
+            }
+            if matches!(coloring.mode, ColorMode::Elevation | ColorMode::Distance) {
+                ui.horizontal(|ui| {
+                    ui.label("color_range:");
+                    ui.add(egui::DragValue::new(&mut coloring.range[0]).speed(0.1));
+                    ui.add(egui::DragValue::new(&mut coloring.range[1]).speed(0.1));
+                    if coloring.mode == ColorMode::Elevation && ui.button("Fit").clicked() {
+                        coloring.fit_elevation(&state.points);
+                    }
+                });
+            }
+            if prev_coloring != state.coloring {
+                *model.update_coloring.borrow_mut() = true;
+            }
+
+            ui.separator();
+
+            let prev_clip_box = state.clip_box;
+            let clip_box = &mut state.clip_box;
+            ui.checkbox(&mut clip_box.enabled, "Crop box");
+            if clip_box.enabled {
+                ui.checkbox(&mut clip_box.show_outline, "Show outline");
+                let (min, max) = state.metadata.bounds;
+                ["x", "y", "z"]
+                    .into_iter()
+                    .enumerate()
+                    .for_each(|(axis, name)| {
+                        let range = min[axis]..=max[axis];
+                        ui.add(LabelledSlider::new(
+                            format!("crop_min_{name}"),
+                            &mut clip_box.min[axis],
+                            range.clone(),
+                        ));
+                        ui.add(LabelledSlider::new(
+                            format!("crop_max_{name}"),
+                            &mut clip_box.max[axis],
+                            range,
+                        ));
+                        // The planes cannot cross
+                        clip_box.max[axis] = clip_box.max[axis].max(clip_box.min[axis]);
+                    });
+                if ui.button("Reset crop box").clicked() {
+                    clip_box.fit(state.metadata.bounds);
+                }
+            }
+            if prev_clip_box != state.clip_box {
+                *model.update_clip.borrow_mut() = true;
+            }
+
+            ui.separator();
+
+            let prev_fog_settings = state.fog_settings;
+            let fog = &mut state.fog_settings;
+            ui.checkbox(&mut fog.enabled, "Fog plane");
+            if fog.enabled {
+                ui.horizontal(|ui| {
+                    ui.label("fog_color:");
+                    ui.color_edit_button_rgb(&mut fog.color);
+                });
+                ui.label("fog_height:");
+                ui.add(egui::DragValue::new(&mut fog.height).speed(0.1));
+                ui.add(
+                    LabelledSlider::new("fog_density", &mut fog.density, 0.0001..=1.0)
+                        .logarithmic(),
+                );
+                ui.add(LabelledSlider::new(
+                    "reflection_strength",
+                    &mut fog.reflection_strength,
+                    0.0..=1.0,
+                ));
+            }
+            if prev_fog_settings != state.fog_settings {
+                *model.update_fog.borrow_mut() = true;
+            }
+
+            ui.separator();
+
+            let prev_gizmo = state.gizmo;
+            let gizmo = &mut state.gizmo;
+            ui.checkbox(&mut gizmo.enabled, "Guides");
+            if gizmo.enabled {
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut gizmo.grid, "Ground grid");
+                    ui.checkbox(&mut gizmo.axes, "Axes");
+                    ui.checkbox(&mut gizmo.bounds, "Bounding box");
+                });
+            }
+            if prev_gizmo != state.gizmo {
+                *model.update_gizmo.borrow_mut() = true;
             }
 
-            ui.label("Audio path:");
-            ui.text_edit_singleline(&mut state.audio_file_path);
+            ui.separator();
+
+            let prev_glow = state.glow;
+            let glow = &mut state.glow;
+            ui.checkbox(&mut glow.additive, "Additive glow");
+            if glow.additive {
+                ui.add(
+                    LabelledSlider::new("glow_intensity", &mut glow.intensity, 0.001..=1.0)
+                        .logarithmic(),
+                );
+                ui.add(LabelledSlider::new(
+                    "occlusion",
+                    &mut glow.occlusion,
+                    0.0..=1.0,
+                ));
+                ui.add(
+                    LabelledSlider::new("occlusion_falloff", &mut glow.falloff, 0.1..=100.0)
+                        .logarithmic(),
+                );
+            }
+            if prev_glow != state.glow {
+                *model.update_glow.borrow_mut() = true;
+            }
+
+            ui.separator();
+
+            let prev_lighting = state.lighting;
+            let lighting = &mut state.lighting;
+            ui.add(Dropdown::new(
+                "shading",
+                &mut lighting.mode,
+                [
+                    (ShadingMode::Unlit, "Unlit"),
+                    (ShadingMode::Lambert, "Lambert"),
+                    (ShadingMode::Headlight, "Headlight"),
+                ],
+            ));
+            if lighting.mode != ShadingMode::Unlit {
+                if lighting.mode == ShadingMode::Lambert {
+                    ui.horizontal(|ui| {
+                        ui.label("light_direction:");
+                        lighting.direction.iter_mut().for_each(|axis| {
+                            ui.add(egui::DragValue::new(axis).speed(0.01));
+                        });
+                    });
+                }
+                ui.add(LabelledSlider::new(
+                    "ambient",
+                    &mut lighting.ambient,
+                    0.0..=1.0,
+                ));
+            }
+            if prev_lighting != state.lighting {
+                *model.update_lighting.borrow_mut() = true;
+            }
+            ui.horizontal(|ui| {
+                ui.add(LabelledSlider::new(
+                    "normal_neighbors",
+                    &mut state.normal_neighbors,
+                    3..=64,
+                ));
+                if ui.button("Estimate normals").clicked() {
+                    action = Some(Action::EstimateNormals);
+                }
+            });
+
+            ui.separator();
+
+            let prev_trail_data = state.trail_data;
+            let mut trail_length = state.trail_data.length();
+            ui.add(LabelledSlider::new(
+                "trail_length",
+                &mut trail_length,
+                0..=max_trail_length,
+            ));
+            if trail_length != state.trail_data.length() {
+                state.trail_data.set_length(trail_length);
+            }
+            if max_trail_length == 0 {
+                ui.label("The positions of the trails of this cloud do not fit in the GPU");
+            } else if max_trail_length < TrailData::MAX_LENGTH {
+                ui.label(format!(
+                    "The trails are limited to {max_trail_length} ghosts so the positions of \
+                     the cloud fit in a buffer of the GPU"
+                ));
+            }
+            if state.trail_data.is_enabled() {
+                ui.add(LabelledSlider::new(
+                    "trail_interval (frames)",
+                    &mut state.trail_data.interval,
+                    1..=30,
+                ));
+                ui.add(LabelledSlider::new(
+                    "trail_fade",
+                    &mut state.trail_data.fade,
+                    0.0..=1.0,
+                ));
+            }
+            if prev_trail_data != state.trail_data {
+                *model.update_trail.borrow_mut() = true;
+            }
+
+            ui.separator();
+
+            let prev_masks = state.masks;
+            mask_ui(ui, "Wind mask", &mut state.masks.wind);
+            mask_ui(ui, "Attractors mask", &mut state.masks.attractors);
+            if prev_masks != state.masks {
+                *model.update_masks.borrow_mut() = true;
+            }
+
+            ui.separator();
+
+            ui.checkbox(&mut state.auto_rotate, "Auto-rotate world");
+
+            ui.add(LabelledSlider::new(
+                "rotation_speed (deg/s)",
+                &mut state.rotation_speed,
+                -90.0..=90.0,
+            ));
+            ui.add(LabelledSlider::new(
+                "rotation_audio_modulation",
+                &mut state.rotation_audio_modulation,
+                0.0..=1.0,
+            ));
+
+            ui.separator();
+
+            ui.label(match state.looks.active() {
+                Some(slot) => format!("A/B comparison (showing {slot:?}):"),
+                None => "A/B comparison:".to_owned(),
+            });
+            let mut recalled = None;
+            ui.horizontal(|ui| {
+                [Slot::A, Slot::B].into_iter().for_each(|slot| {
+                    if ui.button(format!("Store {slot:?}")).clicked() {
+                        state.looks.store(slot, Look::from_state(state));
+                    }
+                });
+                [Slot::A, Slot::B].into_iter().for_each(|slot| {
+                    let button = egui::Button::new(format!("Recall {slot:?}"));
+                    let enabled = state.looks.get(slot).is_some();
+                    if ui.add_enabled(enabled, button).clicked() {
+                        recalled = state.looks.recall(slot);
+                    }
+                });
+            });
+            let diff = state.looks.diff();
+            if !diff.is_empty() {
+                egui::Grid::new("ab_diff").striped(true).show(ui, |ui| {
+                    ui.strong("parameter");
+                    ui.strong("A");
+                    ui.strong("B");
+                    ui.end_row();
+                    diff.into_iter().for_each(|(name, a, b)| {
+                        ui.label(name);
+                        ui.label(a);
+                        ui.label(b);
+                        ui.end_row();
+                    });
+                });
+            }
+            if let Some(look) = recalled {
+                look.apply(state);
+                *model.update_cloud_data.borrow_mut() = true;
+                *model.update_attractors.borrow_mut() = true;
+                *model.update_masks.borrow_mut() = true;
+                *model.update_fog.borrow_mut() = true;
+                *model.update_coloring.borrow_mut() = true;
+            }
+
+            ui.separator();
+
+            ui.checkbox(&mut state.attract_enabled, "Attract mode when idle");
+
+            ui.add(LabelledSlider::new(
+                "idle_timeout (s)",
+                &mut state.attract_loop.idle_timeout,
+                5.0..=600.0,
+            ));
+            ui.add(LabelledSlider::new(
+                "hold (s)",
+                &mut state.attract_loop.hold,
+                0.0..=120.0,
+            ));
+            ui.add(LabelledSlider::new(
+                "transition (s)",
+                &mut state.attract_loop.transition,
+                1.0..=120.0,
+            ));
+
+            ui.label(format!(
+                "The loop blends the {} named presets below",
+                state.presets.len()
+            ));
+
+            ui.separator();
+
+            match state.preset_library.show(ui) {
+                Some(PresetRequest::Save) => action = Some(Action::SaveSettings),
+                Some(PresetRequest::Load) => action = Some(Action::LoadSettings),
+                None => {}
+            }
+
+            ui.separator();
+
+            ui.add(UiScale);
+            model.recorder.show(ui);
+
+            ui.separator();
+
+            let label = match model.ndi.is_running() {
+                true => "Stop NDI stream",
+                false => "Start NDI stream",
+            };
+            if ui.button(label).clicked() {
+                action = Some(Action::ToggleNdi);
+            }
+            model.ndi.show(ui);
+
+            ui.separator();
+
+            let path = &mut state.camera_path;
+            ui.label(format!(
+                "{} keyframes, {:.1} s",
+                path.keyframes().len(),
+                path.duration()
+            ));
+            ui.add(LabelledSlider::new(
+                "frame_rate",
+                &mut path.frame_rate,
+                1.0..=120.0,
+            ));
+            ui.checkbox(&mut state.capture_path, "Capture frames while playing");
+            ui.horizontal(|ui| {
+                if ui.button("Add keyframe").clicked() {
+                    action = Some(Action::AddKeyframe);
+                }
+                let label = match state.camera_path.is_playing() {
+                    false => "Play path",
+                    true => "Stop path",
+                };
+                if ui.button(label).clicked() {
+                    action = Some(Action::TogglePath);
+                }
+                if ui.button("Clear path").clicked() {
+                    action = Some(Action::ClearPath);
+                }
+            });
+
+            ui.separator();
+
+            let bookmarks = &state.camera_bookmarks;
+            let stored = bookmarks
+                .stored()
+                .map(|slot| (slot + 1).to_string())
+                .collect::<Vec<_>>();
+            ui.label(match stored.is_empty() {
+                true => "No camera bookmarks".to_owned(),
+                false => format!("Camera bookmarks: {}", stored.join(", ")),
+            });
+            ui.add(LabelledSlider::new(
+                "bookmark_duration",
+                &mut state.bookmark_duration,
+                0.0..=5.0,
+            ));
+
+            ui.separator();
+
+            let prev_warp_settings = state.warp_settings;
+            let warp = &mut state.warp_settings;
+            ui.checkbox(&mut warp.enabled, "Projection warp");
+            if warp.enabled {
+                ui.checkbox(&mut state.editing_warp, "Edit warp on screen");
+                ui.checkbox(&mut warp.mesh_enabled, "Mesh warp");
+                if ui.button("Reset warp").clicked() {
+                    warp.reset();
+                }
+            }
+            if prev_warp_settings != state.warp_settings {
+                *model.update_warp.borrow_mut() = true;
+            }
+
+            ui.separator();
+
+            let prev_exposure = state.exposure;
+            let exposure = &mut state.exposure;
+            ui.checkbox(&mut exposure.auto, "Automatic exposure");
+            let label = match exposure.auto {
+                true => "exposure_compensation (stops)",
+                false => "exposure (stops)",
+            };
+            ui.add(LabelledSlider::new(label, &mut exposure.stops, -4.0..=4.0));
+            if exposure.auto {
+                ui.add(
+                    LabelledSlider::new("target_luminance", &mut exposure.target, 0.01..=1.0)
+                        .logarithmic(),
+                );
+                ui.add(
+                    LabelledSlider::new("adaptation_speed", &mut exposure.speed, 0.1..=10.0)
+                        .logarithmic(),
+                );
+                let [min, max] = &mut exposure.range;
+                ui.add(LabelledSlider::new("min_exposure (stops)", min, -8.0..=0.0));
+                ui.add(LabelledSlider::new("max_exposure (stops)", max, 0.0..=8.0));
+            }
+            if ui.button("Reset exposure").clicked() {
+                *exposure = ExposureSettings {
+                    auto: exposure.auto,
+                    ..ExposureSettings::default()
+                };
+            }
+            if prev_exposure != state.exposure {
+                *model.update_exposure.borrow_mut() = true;
+            }
+
+            ui.separator();
+
+            ui.checkbox(&mut state.resolution_scaler.enabled, "Adaptive resolution");
+
+            ui.add(LabelledSlider::new(
+                "target_fps",
+                &mut state.resolution_scaler.target_fps,
+                15.0..=144.0,
+            ));
+            ui.add(LabelledSlider::new(
+                "min_scale",
+                &mut state.resolution_scaler.min_scale,
+                0.25..=1.0,
+            ));
+
+            ui.separator();
+
+            let mut lod_enabled = state.lod_settings.enabled;
+            if ui.checkbox(&mut lod_enabled, "Level of detail").changed() {
+                action = Some(Action::ToggleLod);
+            }
+
+            ui.add(LabelledSlider::new(
+                "lod_max_error",
+                &mut state.lod_settings.max_error,
+                0.5..=16.0,
+            ));
+
+            let budget = &mut state.lod_settings.point_budget;
+            ui.add(
+                LabelledSlider::new("lod_point_budget", budget, 100_000..=100_000_000)
+                    .logarithmic(),
+            );
+
+            ui.separator();
+
+            ui.add(LabelledSlider::new(
+                "movement_speed",
+                &mut state.movement_speed,
+                0.01..=1.0,
+            ));
+            ui.add(LabelledSlider::new(
+                "mouse_sensitivity",
+                &mut state.mouse_sensitivity,
+                0.001..=0.01,
+            ));
+            #[cfg(target_os = "linux")]
+            ui.collapsing("Gamepad", |ui| state.gamepad.show(ui, &model.gamepad));
+
+            let mut colliding = state.obstacles.is_some();
+            if ui
+                .checkbox(&mut colliding, "Collide with the cloud")
+                .changed()
+            {
+                action = Some(Action::ToggleCollision);
+            }
+            let resolution = LabelledSlider::new(
+                "collision_resolution",
+                &mut state.collision_resolution,
+                16..=256,
+            );
+            if ui.add(resolution).changed() && state.obstacles.is_some() {
+                state.obstacles = Some(OccupancyGrid::build(
+                    &state.points,
+                    state.collision_resolution,
+                ));
+            }
+
+            let formats: Vec<&str> = model
+                .registry
+                .loaders()
+                .map(|loader| loader.name())
+                .collect();
+            // Picking a file in the browser loads it, like typing its path and pressing load
+            let mut picked = false;
+            ui.add(
+                FilePicker::new(
+                    format!("Point cloud path ({})", formats.join(", ")),
+                    &mut state.cloud_file_path,
+                    &model.registry.extensions(),
+                )
+                .picked(&mut picked),
+            );
+            if picked {
+                action = Some(Action::LoadCloud);
+            }
+
+            ui.label("LAS classes (e.g. 2, 6; empty for all):");
+            ui.text_edit_singleline(&mut state.las_classes);
+
+            match &model.loading {
+                Some(loading) => {
+                    let (read, total) = loading.progress();
+                    let progress = egui::ProgressBar::new(loading.fraction())
+                        .text(format!("Loading {read} / {total} points"));
+                    ui.add(progress);
+                }
+                None => {
+                    if ui.button("Load file").clicked() {
+                        action = Some(Action::LoadCloud);
+                    }
+                }
+            }
+
+            let generators = model
+                .registry
+                .generators()
+                .map(|generator| (generator.name().to_owned(), generator.name().to_owned()));
+            ui.add(Dropdown::new("generator", &mut state.generator, generators));
+            // Restarting from the seed generates the same cloud again
+            if ui.button("Generate").clicked() | rng::show(ui) {
+                action = Some(Action::GenerateCloud);
+            }
+
+            ui.horizontal(|ui| {
+                let undo = state
+                    .history
+                    .undo_label()
+                    .map(|label| format!("Undo {label}"));
+                let button = egui::Button::new(undo.as_deref().unwrap_or("Undo"));
+                if ui.add_enabled(undo.is_some(), button).clicked() {
+                    action = Some(Action::Undo);
+                }
+                let redo = state
+                    .history
+                    .redo_label()
+                    .map(|label| format!("Redo {label}"));
+                let button = egui::Button::new(redo.as_deref().unwrap_or("Redo"));
+                if ui.add_enabled(redo.is_some(), button).clicked() {
+                    action = Some(Action::Redo);
+                }
+            });
+            ui.collapsing("Compare scans", |ui| {
+                ui.add(FilePicker::new(
+                    "Other scan path",
+                    &mut state.compare_file_path,
+                    &model.registry.extensions(),
+                ));
+                ui.add(LabelledSlider::new(
+                    "compare_range",
+                    &mut state.compare_range,
+                    0.01..=10.0,
+                ));
+                match &model.comparing {
+                    Some(comparing) => {
+                        ui.add(egui::ProgressBar::new(comparing.fraction()).text("Loading scan"));
+                    }
+                    None => {
+                        if ui.button("Color by change").clicked() {
+                            action = Some(Action::CompareCloud);
+                        }
+                    }
+                }
+            });
+
+            metadata_ui(ui, &state.metadata, gpu_memory);
+
+            if model.tiling.is_some() || model.streamer.is_some() {
+                ui.collapsing("Streaming", |ui| {
+                    streaming_ui(ui, model.tiling.as_ref(), model.streamer.as_mut());
+                });
+            }
+
+            let prev_clouds = state.clouds.clone();
+            ui.collapsing("Scene clouds", |ui| {
+                let scene_data = state.cloud_data;
+                state
+                    .clouds
+                    .iter_mut()
+                    .enumerate()
+                    .for_each(|(index, cloud)| {
+                        ui.push_id(index, |ui| {
+                            if scene_cloud_ui(ui, cloud, &scene_data, index > 0) {
+                                action = Some(Action::RemoveCloud(index));
+                            }
+                        });
+                    });
+                match &model.adding {
+                    Some(adding) => {
+                        ui.add(egui::ProgressBar::new(adding.fraction()).text("Adding cloud"));
+                    }
+                    None => {
+                        if ui.button("Add cloud file").clicked() {
+                            action = Some(Action::AddCloud);
+                        }
+                    }
+                }
+            });
+            if prev_clouds != state.clouds {
+                *model.update_layers.borrow_mut() = true;
+                *model.update_gizmo.borrow_mut() = true;
+            }
+
+            ui.collapsing("Background footage", |ui| {
+                ui.add(FilePicker::new(
+                    "Any frame of the image sequence",
+                    &mut state.background_file_path,
+                    &IMAGE_EXTENSIONS,
+                ));
+                ui.add(LabelledSlider::new(
+                    "background_frame_rate",
+                    &mut state.background_frame_rate,
+                    1.0..=60.0,
+                ));
+                ui.horizontal(|ui| {
+                    if ui.button("Play behind points").clicked() {
+                        action = Some(Action::LoadBackground);
+                    }
+                    if let Some(sequence) = &model.background {
+                        if ui.button("Remove").clicked() {
+                            action = Some(Action::RemoveBackground);
+                        }
+                        ui.label(format!("{} frames", sequence.len()));
+                    }
+                });
+            });
+
+            ui.collapsing("Gaussian splats", |ui| {
+                let mut picked = false;
+                ui.add(
+                    FilePicker::new(
+                        "3D Gaussian Splatting capture",
+                        &mut state.gaussian_file_path,
+                        &GAUSSIAN_EXTENSIONS,
+                    )
+                    .picked(&mut picked),
+                );
+                if picked {
+                    action = Some(Action::LoadGaussians);
+                }
+                ui.add(LabelledSlider::new(
+                    "gaussian_scale",
+                    &mut state.gaussian_scale,
+                    0.1..=2.0,
+                ));
+                match &model.loading_gaussians {
+                    Some(loading) => {
+                        ui.add(egui::ProgressBar::new(loading.fraction()).text("Loading splats"));
+                    }
+                    None => {
+                        ui.horizontal(|ui| {
+                            if ui.button("Load splats").clicked() {
+                                action = Some(Action::LoadGaussians);
+                            }
+                            let count = model.shader_pipeline.borrow().gaussian_count();
+                            if count > 0 {
+                                if ui.button("Remove").clicked() {
+                                    action = Some(Action::RemoveGaussians);
+                                }
+                                ui.label(format!("{count} Gaussians"));
+                            }
+                        });
+                    }
+                }
+            });
+
+            ui.collapsing("Still format", |ui| state.still_options.show(ui));
+
+            ui.collapsing("Display wall", |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Displays:");
+                    ui.add(egui::DragValue::new(&mut state.wall_tiles[0]).clamp_range(1..=16));
+                    ui.label("×");
+                    ui.add(egui::DragValue::new(&mut state.wall_tiles[1]).clamp_range(1..=16));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Resolution:");
+                    let [width, height] = &mut state.wall_tile_size;
+                    ui.add(egui::DragValue::new(width).clamp_range(1..=8192));
+                    ui.label("×");
+                    ui.add(egui::DragValue::new(height).clamp_range(1..=8192));
+                });
+                let [columns, rows] = state.wall_tiles;
+                let [width, height] = state.wall_tile_size;
+                ui.label(format!(
+                    "The stills are {}×{} pixels",
+                    width * columns,
+                    height * rows
+                ));
+                if ui.button("Save wall still").clicked() {
+                    action = Some(Action::SaveWallStill);
+                }
+            });
+
+            ui.collapsing("High-resolution still", |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Resolution:");
+                    let [width, height] = &mut state.still_size;
+                    ui.add(egui::DragValue::new(width).clamp_range(1..=32768));
+                    ui.label("×");
+                    ui.add(egui::DragValue::new(height).clamp_range(1..=32768));
+                });
+                match &model.still {
+                    Some(still) => {
+                        let text = format!(
+                            "Rendering tile {} of {}",
+                            still.rendered_tiles() + 1,
+                            still.tile_count()
+                        );
+                        ui.add(egui::ProgressBar::new(still.fraction()).text(text));
+                    }
+                    None => {
+                        if ui.button("Render high-res still").clicked() {
+                            action = Some(Action::RenderStill);
+                        }
+                    }
+                }
+            });
+
+            ui.separator();
+
+            ui.label("Export directory:");
+            ui.text_edit_singleline(&mut state.export_directory);
+
+            ui.add(LabelledSlider::new(
+                "export_every_n_frames",
+                &mut state.export_every_n_frames,
+                1..=60,
+            ));
+
+            match &model.ply_sequence {
+                Some(sequence) => {
+                    ui.label(format!(
+                        "Exported {} frames to {}",
+                        sequence.written(),
+                        sequence.directory().display()
+                    ));
+                    if ui.button("Stop export").clicked() {
+                        action = Some(Action::ToggleExport);
+                    }
+                }
+                None => {
+                    ui.horizontal(|ui| {
+                        if ui.button("Start export").clicked() {
+                            action = Some(Action::ToggleExport);
+                        }
+                        if ui.button("Save current frame").clicked() {
+                            action = Some(Action::SaveCloud);
+                        }
+                    });
+                }
+            }
+
+            ui.separator();
+
+            ui.label("Shader directory:");
+            ui.text_edit_singleline(&mut state.shader_directory);
+            match &shader_reload {
+                Some(error) => {
+                    if ui.button("Stop reloading shaders").clicked() {
+                        action = Some(Action::ToggleShaderReload);
+                    }
+                    if let Some(error) = error {
+                        ui.colored_label(
+                            egui::Color32::RED,
+                            egui::RichText::new(error).monospace(),
+                        );
+                    }
+                }
+                None => {
+                    if ui.button("Reload shaders on change").clicked() {
+                        action = Some(Action::ToggleShaderReload);
+                    }
+                }
+            }
+
+            ui.separator();
+
+            let mut picked = false;
+            ui.add(
+                FilePicker::new("Audio path", &mut state.audio_file_path, AUDIO_EXTENSIONS)
+                    .picked(&mut picked),
+            );
+            if picked {
+                action = Some(Action::LoadAudio);
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Load file").clicked() {
+                    action = Some(Action::LoadAudio);
+                }
+                let mut listening = model.microphone.is_some();
+                if ui.checkbox(&mut listening, "Microphone").changed() {
+                    action = Some(Action::ToggleMicrophone);
+                }
+            });
+
+            // The audio thread moves the playhead, so only the edits are written back
+            let mut transport = *state.transport.lock().unwrap();
+            let prev_transport = transport;
+            transport_ui(ui, &mut transport);
+            if transport != prev_transport {
+                *state.transport.lock().unwrap() = transport;
+            }
+        });
+
+    // The bands are split on the audio thread
+    if state.band_edges != prev_band_edges {
+        let [bass_max, mid_max] = state.band_edges;
+        model
+            .audio_stream
+            .send(move |audio| audio.analysis.analyzer.set_band_edges(bass_max, mid_max))
+            .ok();
+        if let Some(microphone) = &model.microphone {
+            microphone
+                .send(move |analysis| analysis.analyzer.set_band_edges(bass_max, mid_max))
+                .ok();
+        }
+    }
+
+    // Generate the handles of the projection warp
+    if state.warp_settings.enabled
+        && state.editing_warp
+        && warp_editor(&ctx, &mut state.warp_settings)
+    {
+        *model.update_warp.borrow_mut() = true;
+    }
+
+    action
+}
+
+/// Draws draggable handles on the corner pins and, if enabled, the control points of the mesh.
+///
+/// Returns whether a handle was moved.
+fn warp_editor(ctx: &egui::Context, warp: &mut WarpSettings) -> bool {
+    const RADIUS: f32 = 8.0;
+    let screen = ctx.screen_rect();
+    let to_screen = |ndc: Vec2| {
+        egui::pos2(
+            (ndc.x + 1.0) / 2.0 * screen.width(),
+            (1.0 - ndc.y) / 2.0 * screen.height(),
+        )
+    };
+
+    // Returns how far the handle was dragged, in normalized device coordinates
+    let handle = |id: (&str, usize), position: Vec2, color: egui::Color32| {
+        let mut delta = Vec2::ZERO;
+        egui::Area::new(egui::Id::new(id))
+            .fixed_pos(to_screen(position) - egui::vec2(RADIUS, RADIUS))
+            .show(ctx, |ui| {
+                let size = egui::vec2(2.0 * RADIUS, 2.0 * RADIUS);
+                let (rect, response) = ui.allocate_exact_size(size, egui::Sense::drag());
+                let stroke = egui::Stroke::new(2.0, color);
+                ui.painter().circle_stroke(rect.center(), RADIUS, stroke);
+                let drag = response.drag_delta();
+                delta = vec2(
+                    drag.x * 2.0 / screen.width(),
+                    -drag.y * 2.0 / screen.height(),
+                );
+            });
+        delta
+    };
+
+    let mut moved = false;
+    (0..warp.corners.len()).for_each(|i| {
+        let delta = handle(("warp_corner", i), warp.corners[i], egui::Color32::YELLOW);
+        warp.corners[i] += delta;
+        moved |= delta != Vec2::ZERO;
+    });
 
-            if ui.button("Load file").clicked() {
-                let audio_stream = &mut model.audio_stream;
-                // Load the audio file if possible
-                if let Ok(sound) = audrey::open(&state.audio_file_path) {
-                    audio_stream
-                        .send(move |audio| {
-                            audio.sounds.clear();
-                            audio.sounds.push(sound);
-                        })
-                        .ok();
-                    audio_stream.play().unwrap();
-                } else {
-                    eprintln!("Failed to load audio file");
-                };
-            }
+    if warp.mesh_enabled {
+        (0..MESH_SIZE * MESH_SIZE).for_each(|i| {
+            let uv = WarpSettings::control_point_uv(i % MESH_SIZE, i / MESH_SIZE);
+            let position = warp.map_corners(uv) + warp.mesh[i];
+            let delta = handle(("warp_mesh", i), position, egui::Color32::LIGHT_BLUE);
+            warp.mesh[i] += delta;
+            moved |= delta != Vec2::ZERO;
         });
+    }
+
+    moved
 }
 
-fn update_camera_position(camera: &mut Camera, velocity: f32, keys: &keys::Down) -> bool {
-    let mut moved = false;
-    // Go forwards on W.
-    if keys.contains(&Key::W) || keys.contains(&Key::Up) {
-        camera.move_towards(Direction::Forward, velocity);
-        moved = true;
-    }
-    // Go backwards on S.
-    if keys.contains(&Key::S) || keys.contains(&Key::Down) {
-        camera.move_towards(Direction::Backward, velocity);
-        moved = true;
+/// Shows the transport of the audio file, to play or pause it, seek through it, loop it and set
+/// its gain.
+fn transport_ui(ui: &mut egui::Ui, transport: &mut Transport) {
+    ui.horizontal(|ui| {
+        let label = if transport.playing { "Pause" } else { "Play" };
+        if ui.button(label).clicked() {
+            transport.toggle();
+        }
+        ui.checkbox(&mut transport.looping, "Loop");
+        ui.label(format!(
+            "{} / {}",
+            format_time(transport.position()),
+            format_time(transport.duration())
+        ));
+    });
+    let mut position = transport.position();
+    let playhead = egui::Slider::new(&mut position, 0.0..=transport.duration()).show_value(false);
+    if ui.add(playhead).changed() {
+        transport.seek(position);
     }
-    // Strafe left on A.
-    if keys.contains(&Key::A) || keys.contains(&Key::Left) {
-        camera.move_towards(Direction::Left, velocity);
-        moved = true;
+    ui.add(LabelledSlider::new("gain", &mut transport.gain, 0.0..=2.0));
+}
+
+/// Shows the effects deforming the points in the order their passes run, to turn them on and off
+/// and reorder them.
+///
+/// Returns whether an impulse was sent by hand.
+fn effects_ui(
+    ui: &mut egui::Ui,
+    effects: &mut EffectStack,
+    curl_noise: &mut CurlNoiseData,
+    gravity: &mut GravityData,
+    impulses: &mut ImpulseData,
+    impulses_from_points: &mut bool,
+) -> bool {
+    let count = effects.layers.len();
+    let mut moved = None;
+    let mut send_impulse = false;
+    effects
+        .layers
+        .iter_mut()
+        .enumerate()
+        .for_each(|(index, layer)| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut layer.enabled, layer.effect.name());
+                if ui.add_enabled(index > 0, egui::Button::new("⏶")).clicked() {
+                    moved = Some((index, true));
+                }
+                if ui
+                    .add_enabled(index + 1 < count, egui::Button::new("⏷"))
+                    .clicked()
+                {
+                    moved = Some((index, false));
+                }
+            });
+            if layer.effect == Effect::CurlNoise && layer.enabled {
+                ui.add(LabelledSlider::new(
+                    "curl_strength",
+                    &mut curl_noise.strength,
+                    0.0..=2.0,
+                ));
+                ui.add(LabelledSlider::new(
+                    "curl_scale",
+                    &mut curl_noise.scale,
+                    0.0..=0.1,
+                ));
+                ui.add(LabelledSlider::new(
+                    "curl_speed",
+                    &mut curl_noise.speed,
+                    0.0..=2.0,
+                ));
+            }
+            if layer.effect == Effect::Collapse && layer.enabled {
+                ui.add(LabelledSlider::new(
+                    "collapse",
+                    &mut gravity.collapse,
+                    0.0..=1.0,
+                ));
+                let mut toward_center = gravity.toward_center();
+                if ui
+                    .checkbox(&mut toward_center, "Toward the centroid")
+                    .changed()
+                {
+                    gravity.set_toward_center(toward_center);
+                }
+            }
+            if layer.effect == Effect::Reassemble && layer.enabled {
+                ui.add(LabelledSlider::new(
+                    "reassemble",
+                    &mut gravity.reassemble,
+                    0.0..=1.0,
+                ));
+                ui.add(LabelledSlider::new(
+                    "overshoot",
+                    &mut gravity.overshoot,
+                    0.0..=4.0,
+                ));
+            }
+            if layer.effect == Effect::Impulses && layer.enabled {
+                ui.add(
+                    LabelledSlider::new("impulse_strength", &mut impulses.strength, 0.0..=0.5)
+                        .logarithmic(),
+                );
+                ui.add(
+                    LabelledSlider::new("impulse_speed", &mut impulses.speed, 1.0..=200.0)
+                        .logarithmic(),
+                );
+                ui.add(
+                    LabelledSlider::new("impulse_damping", &mut impulses.damping, 0.1..=10.0)
+                        .logarithmic(),
+                );
+                ui.add(
+                    LabelledSlider::new("impulse_wavelength", &mut impulses.wavelength, 0.1..=50.0)
+                        .logarithmic(),
+                );
+                ui.horizontal(|ui| {
+                    ui.checkbox(impulses_from_points, "From random points");
+                    send_impulse = ui.button("Send impulse").clicked();
+                });
+            }
+        });
+    match moved {
+        Some((index, true)) => effects.move_up(index),
+        Some((index, false)) => effects.move_down(index),
+        None => {}
     }
-    // Strafe right on D.
-    if keys.contains(&Key::D) || keys.contains(&Key::Right) {
-        camera.move_towards(Direction::Right, velocity);
-        moved = true;
+    ui.label("The springs pull the points back to rest after the effects.");
+    send_impulse
+}
+
+/// The point an impulse leaves from: the centroid of the cloud, or one of its points at random.
+fn impulse_origin(state: &State) -> Point3 {
+    match state.impulses_from_points && !state.points.is_empty() {
+        true => Vec3::from(state.points[random_range(0, state.points.len())].position),
+        false => state.cloud_center,
     }
-    // Float down on Q.
-    if keys.contains(&Key::Q) || keys.contains(&Key::Comma) {
-        camera.move_towards(Direction::Down, velocity);
-        moved = true;
+}
+
+/// Shows the settings of a cloud of the scene, returning whether it should be removed.
+fn scene_cloud_ui(
+    ui: &mut egui::Ui,
+    cloud: &mut SceneCloud,
+    scene_data: &CloudData,
+    removable: bool,
+) -> bool {
+    let layer = &mut cloud.layer;
+    ui.checkbox(&mut layer.visible, cloud.name.as_str());
+    ui.collapsing("transform", |ui| {
+        let transform = &mut layer.transform;
+        ui.horizontal(|ui| {
+            ui.label("translation:");
+            transform.translation.iter_mut().for_each(|offset| {
+                ui.add(egui::DragValue::new(offset).speed(0.1));
+            });
+        });
+        ["x", "y", "z"]
+            .into_iter()
+            .zip(&mut transform.rotation)
+            .for_each(|(axis, angle)| {
+                ui.add(LabelledSlider::new(
+                    format!("rotation_{axis}"),
+                    angle,
+                    -180.0..=180.0,
+                ));
+            });
+        ui.add(LabelledSlider::new(
+            "scale",
+            &mut transform.scale,
+            0.01..=10.0,
+        ));
+        ui.horizontal(|ui| {
+            // Scans are often captured with z up, while the scene has y up
+            if ui.button("Z up to Y up").clicked() {
+                transform.rotation = [-90.0, 0.0, 0.0];
+            }
+            if ui.button("Reset transform").clicked() {
+                *transform = CloudTransform::default();
+            }
+        });
+    });
+
+    // The overrides start from the deformation of the scene
+    let mut overriding = layer.overrides.is_some();
+    ui.checkbox(&mut overriding, "Override deformation");
+    layer.overrides = overriding.then(|| layer.overrides.unwrap_or(*scene_data));
+    if let Some(overrides) = &mut layer.overrides {
+        ui.add(LabelledSlider::new(
+            "wind_strength",
+            &mut overrides.wind_strength,
+            0.0..=0.5,
+        ));
+        ui.add(LabelledSlider::new(
+            "noise_scale",
+            &mut overrides.noise_scale,
+            0.0..=0.1,
+        ));
+        ui.add(LabelledSlider::new(
+            "spring_constant",
+            &mut overrides.spring_constant,
+            0.0..=0.5,
+        ));
     }
-    // Float up on E.
-    if keys.contains(&Key::E) || keys.contains(&Key::Period) {
-        camera.move_towards(Direction::Up, velocity);
-        moved = true;
+
+    removable && ui.button("Remove cloud").clicked()
+}
+
+fn metadata_ui(ui: &mut egui::Ui, metadata: &CloudMetadata, gpu_memory: u64) {
+    ui.collapsing("Cloud info", |ui| {
+        egui::Grid::new("cloud_info").striped(true).show(ui, |ui| {
+            ui.label("source");
+            ui.label(metadata.source.to_string());
+            ui.end_row();
+            if let CloudSource::File {
+                size: Some(size), ..
+            } = metadata.source
+            {
+                ui.label("file_size");
+                ui.label(format_bytes(size));
+                ui.end_row();
+            }
+            ui.label("points");
+            ui.label(metadata.point_count.to_string());
+            ui.end_row();
+            let [x, y, z] = metadata.dimensions().to_array();
+            ui.label("dimensions");
+            ui.label(format!("{x:.2} × {y:.2} × {z:.2}"));
+            ui.end_row();
+            ui.label("density");
+            ui.label(format!("{:.2} points / unit", metadata.density()));
+            ui.end_row();
+            ui.label("gpu_memory");
+            ui.label(format_bytes(gpu_memory));
+            ui.end_row();
+        });
+    });
+}
+
+/// Formats a number of bytes with the largest binary unit keeping it above one.
+fn format_bytes(bytes: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
     }
+    format!("{size:.1} {}", units[unit])
+}
+
+fn mask_ui(ui: &mut egui::Ui, name: &str, mask: &mut Mask) {
+    ui.collapsing(name, |ui| {
+        let mut height_enabled = mask.height_enabled();
+        ui.checkbox(&mut height_enabled, "Mask by height");
+        mask.set_height_enabled(height_enabled);
 
+        if height_enabled {
+            ui.label("min_height:");
+            ui.add(egui::DragValue::new(&mut mask.min_height).speed(0.1));
+            ui.label("max_height:");
+            ui.add(egui::DragValue::new(&mut mask.max_height).speed(0.1));
+            ui.add(LabelledSlider::new(
+                "feather",
+                &mut mask.feather,
+                0.0..=10.0,
+            ));
+        }
+
+        let mut color_enabled = mask.color_enabled();
+        ui.checkbox(&mut color_enabled, "Mask by color");
+        mask.set_color_enabled(color_enabled);
+
+        if color_enabled {
+            ui.horizontal(|ui| {
+                ui.label("color:");
+                ui.color_edit_button_rgb(&mut mask.color);
+            });
+            ui.add(LabelledSlider::new(
+                "color_tolerance",
+                &mut mask.color_tolerance,
+                0.0..=1.0,
+            ));
+        }
+
+        let mut inverted = mask.inverted();
+        ui.checkbox(&mut inverted, "Invert mask");
+        mask.set_inverted(inverted);
+    });
+}
+
+fn update_camera_position(
+    camera: &mut Camera,
+    velocity: f32,
+    keys: &keys::Down,
+    obstacles: Option<&OccupancyGrid>,
+) -> bool {
+    let mut moved = false;
+    camera_bindings().into_iter().for_each(|binding| {
+        if binding.keys.iter().any(|key| keys.contains(key)) {
+            camera.move_towards(binding.action, velocity, obstacles);
+            moved = true;
+        }
+    });
     moved
 }
 
 fn raw_window_event(_app: &App, model: &mut Model, event: &nannou::winit::event::WindowEvent) {
     // Let egui handle things like keyboard and mouse input.
     model.egui.handle_raw_event(event);
+
+    // Any input stops the attract loop
+    if matches!(
+        event,
+        winit::event::WindowEvent::KeyboardInput { .. }
+            | winit::event::WindowEvent::CursorMoved { .. }
+            | winit::event::WindowEvent::MouseInput { .. }
+            | winit::event::WindowEvent::MouseWheel { .. }
+    ) {
+        model.state.attract_loop.interact();
+    }
 }
 
-fn key_pressed(app: &App, model: &mut Model, key: Key) {
-    match key {
-        Key::X | Key::Escape => app.quit(),
-        Key::Space => {
+/// The actions that can be run from the keyboard, the settings or the command palette.
+#[derive(Clone, Copy)]
+enum Action {
+    LoadCloud,
+    AddCloud,
+    CompareCloud,
+    EstimateNormals,
+    RemoveCloud(usize),
+    GenerateCloud,
+    Undo,
+    Redo,
+    LoadAudio,
+    ToggleAudio,
+    ToggleMicrophone,
+    LoadBackground,
+    RemoveBackground,
+    LoadGaussians,
+    RemoveGaussians,
+    ToggleCursor,
+    ToggleLook,
+    ClearAttractors,
+    TriggerPulse,
+    SendImpulse,
+    ToggleAutoRotate,
+    ToggleFog,
+    ToggleGizmo,
+    ToggleClip,
+    ToggleAttract,
+    ToggleAdaptiveResolution,
+    ToggleAutoExposure,
+    ToggleLod,
+    ToggleCollision,
+    TogglePicking,
+    JumpToPreset(usize),
+    SaveSettings,
+    LoadSettings,
+    AddKeyframe,
+    TogglePath,
+    ClearPath,
+    StoreBookmark(usize),
+    RecallBookmark(usize),
+    ToggleExport,
+    SaveCloud,
+    ToggleShaderReload,
+    ToggleRecording,
+    ToggleNdi,
+    SaveFrame,
+    SaveStill,
+    SaveWallStill,
+    RenderStill,
+    ToggleBuildInfo,
+    ToggleSpectrum,
+    Quit,
+}
+
+fn commands(
+    state: &State,
+    exporting: bool,
+    recording: bool,
+    streaming: bool,
+    listening: bool,
+) -> Vec<Command<Action>> {
+    let mut commands = vec![
+        Command::new("Load point cloud file", Action::LoadCloud),
+        Command::new("Add point cloud file to the scene", Action::AddCloud),
+        Command::new("Compare with another scan", Action::CompareCloud),
+        Command::new("Estimate normals of the points", Action::EstimateNormals),
+        Command::new("Generate point cloud", Action::GenerateCloud),
+        Command::new("Undo cloud operation", Action::Undo),
+        Command::new("Redo cloud operation", Action::Redo),
+        Command::new("Load audio file", Action::LoadAudio),
+        Command::new("Play or pause audio file", Action::ToggleAudio),
+        match listening {
+            false => Command::new("Listen to microphone", Action::ToggleMicrophone),
+            true => Command::new("Stop listening to microphone", Action::ToggleMicrophone),
+        },
+        Command::new("Play background footage", Action::LoadBackground),
+        Command::new("Remove background footage", Action::RemoveBackground),
+        Command::new("Load Gaussian splats", Action::LoadGaussians),
+        Command::new("Remove Gaussian splats", Action::RemoveGaussians),
+        Command::new("Toggle cursor binding", Action::ToggleCursor),
+        Command::new("Toggle A/B look", Action::ToggleLook),
+        Command::new("Clear attractors", Action::ClearAttractors),
+        Command::new("Trigger pulse", Action::TriggerPulse),
+        Command::new("Send audio impulse", Action::SendImpulse),
+        Command::new("Toggle auto-rotate", Action::ToggleAutoRotate),
+        Command::new("Toggle fog plane", Action::ToggleFog),
+        Command::new("Toggle guides", Action::ToggleGizmo),
+        Command::new("Toggle crop box", Action::ToggleClip),
+        Command::new("Toggle attract mode", Action::ToggleAttract),
+        Command::new(
+            "Toggle adaptive resolution",
+            Action::ToggleAdaptiveResolution,
+        ),
+        Command::new("Toggle automatic exposure", Action::ToggleAutoExposure),
+        Command::new("Toggle level of detail", Action::ToggleLod),
+        Command::new("Toggle camera collisions", Action::ToggleCollision),
+        Command::new("Toggle point picking", Action::TogglePicking),
+    ];
+    commands.extend(state.presets.iter().enumerate().map(|(i, preset)| {
+        Command::new(
+            format!("Jump to preset {}", preset.name),
+            Action::JumpToPreset(i),
+        )
+    }));
+    commands.extend([
+        Command::new("Save settings preset", Action::SaveSettings),
+        Command::new("Load settings preset", Action::LoadSettings),
+        Command::new("Add camera keyframe", Action::AddKeyframe),
+        match state.camera_path.is_playing() {
+            false => Command::new("Play camera path", Action::TogglePath),
+            true => Command::new("Stop camera path", Action::TogglePath),
+        },
+        Command::new("Clear camera path", Action::ClearPath),
+    ]);
+    commands.extend(state.camera_bookmarks.stored().map(|slot| {
+        Command::new(
+            format!("Fly to camera bookmark {}", slot + 1),
+            Action::RecallBookmark(slot),
+        )
+    }));
+    commands.extend([
+        match exporting {
+            false => Command::new("Start PLY export", Action::ToggleExport),
+            true => Command::new("Stop PLY export", Action::ToggleExport),
+        },
+        Command::new("Save deformed cloud as PLY", Action::SaveCloud),
+        Command::new("Toggle shader hot-reload", Action::ToggleShaderReload),
+        match recording {
+            false => Command::new("Start recording", Action::ToggleRecording),
+            true => Command::new("Stop recording", Action::ToggleRecording),
+        },
+        match streaming {
+            false => Command::new("Start NDI stream", Action::ToggleNdi),
+            true => Command::new("Stop NDI stream", Action::ToggleNdi),
+        },
+        Command::new("Save frame", Action::SaveFrame),
+        Command::new("Save 4K still", Action::SaveStill),
+        Command::new("Save display wall still", Action::SaveWallStill),
+        Command::new("Render high-res still", Action::RenderStill),
+        Command::new("Toggle build info", Action::ToggleBuildInfo),
+        Command::new("Toggle spectrum overlay", Action::ToggleSpectrum),
+        Command::new("Quit", Action::Quit),
+    ]);
+    commands
+}
+
+fn perform(app: &App, model: &mut Model, action: Action) {
+    let state = &mut model.state;
+    match action {
+        Action::LoadCloud => {
+            // Points are generated right away, files are read in the background
+            if state.cloud_file_path.is_empty() {
+                perform(app, model, Action::GenerateCloud);
+            } else if model.loading.is_none() {
+                let loading = model
+                    .registry
+                    .load_async(&state.cloud_file_path, load_options(state));
+                model.loading = Some(loading);
+            }
+        }
+        Action::AddCloud => {
+            if !state.cloud_file_path.is_empty() && model.adding.is_none() {
+                let adding = model
+                    .registry
+                    .load_async(&state.cloud_file_path, load_options(state));
+                model.adding = Some(adding);
+            }
+        }
+        Action::CompareCloud => {
+            if !state.compare_file_path.is_empty() && model.comparing.is_none() {
+                let comparing = model
+                    .registry
+                    .load_async(&state.compare_file_path, load_options(state));
+                model.comparing = Some(comparing);
+            }
+        }
+        Action::RemoveCloud(index) => {
+            // The main cloud stays
+            if index > 0 && index < state.clouds.len() {
+                state.clouds.remove(index);
+                model.shader_pipeline.borrow_mut().remove_cloud(index);
+            }
+        }
+        Action::GenerateCloud => {
+            let name = state.generator.clone();
+            match model.registry.generator(&name) {
+                Some(generator) => {
+                    let max_points = GPUPipeline::max_points(&model.gpu_limits);
+                    let points = generator.generate(generated_points(max_points), rng::random());
+                    let source = CloudSource::Generator(name.clone());
+                    replace_points(app, model, points, source, format!("generation of {name}"));
+                }
+                None => eprintln!("Unknown generator: {name}"),
+            }
+        }
+        Action::EstimateNormals => {
+            let window = app.window(model.window_id).unwrap();
+            estimate_cloud_normals(model, window.device());
+        }
+        Action::Undo => {
+            if state.history.undo(&mut state.points).is_some() {
+                let window = app.window(model.window_id).unwrap();
+                upload_points(model, window.device());
+            }
+        }
+        Action::Redo => {
+            if state.history.redo(&mut state.points).is_some() {
+                let window = app.window(model.window_id).unwrap();
+                upload_points(model, window.device());
+            }
+        }
+        Action::LoadBackground => {
+            match ImageSequence::open(&state.background_file_path, state.background_frame_rate) {
+                Ok(sequence) => {
+                    model.background = Some(sequence);
+                    model.background_time = 0.0;
+                }
+                Err(e) => eprintln!("{e}: {}", state.background_file_path),
+            }
+        }
+        Action::RemoveBackground => {
+            model.background = None;
+            model.background_frame.borrow_mut().take();
+            model.shader_pipeline.borrow_mut().clear_background();
+        }
+        Action::LoadGaussians => {
+            model.loading_gaussians = Some(read_gaussians_async(&state.gaussian_file_path));
+        }
+        Action::RemoveGaussians => {
+            model.loading_gaussians = None;
+            model.shader_pipeline.borrow_mut().clear_gaussians();
+        }
+        Action::LoadAudio => {
+            // The file replaces the microphone
+            model.microphone = None;
+            let audio_stream = &mut model.audio_stream;
+            // Load the audio file if possible
+            match read_track(&state.audio_file_path) {
+                Ok((track, sample_rate)) => {
+                    // The transport is replaced along with the track so it never outlives it
+                    audio_stream
+                        .send(move |audio| {
+                            *audio.transport.lock().unwrap() =
+                                Transport::new(track.len(), sample_rate);
+                            audio.track = track;
+                        })
+                        .ok();
+                    audio_stream.play().unwrap();
+                }
+                Err(e) => eprintln!("{e}: {}", state.audio_file_path),
+            }
+        }
+        Action::ToggleAudio => state.transport.lock().unwrap().toggle(),
+        Action::ToggleMicrophone => match model.microphone.take() {
+            // Resume the file where it was paused
+            Some(_) => model.audio_stream.play().unwrap(),
+            None => match open_microphone(state) {
+                Ok(microphone) => {
+                    model.audio_stream.pause().ok();
+                    model.microphone = Some(microphone);
+                }
+                Err(e) => eprintln!("{e}"),
+            },
+        },
+        Action::ToggleCursor => {
             let window = app.main_window();
             if !model.camera_is_active {
                 if window.set_cursor_grab(true).is_ok() {
@@ -395,28 +3622,733 @@ fn key_pressed(app: &App, model: &mut Model, key: Key) {
             }
             window.set_cursor_visible(!model.camera_is_active);
         }
-        Key::Z => app
-            .main_window()
-            .capture_frame(get_save_path(&app.exe_name().unwrap())),
-        _other_key => {}
+        Action::ToggleLook => {
+            if let Some(look) = state.looks.toggle() {
+                look.apply(state);
+                *model.update_cloud_data.borrow_mut() = true;
+                *model.update_attractors.borrow_mut() = true;
+                *model.update_masks.borrow_mut() = true;
+                *model.update_fog.borrow_mut() = true;
+                *model.update_coloring.borrow_mut() = true;
+            }
+        }
+        Action::ClearAttractors => {
+            state.attractor_data.clear();
+            *model.update_attractors.borrow_mut() = true;
+        }
+        Action::TriggerPulse => {
+            state.pulse_data.trigger(state.cloud_center);
+            *model.update_pulse.borrow_mut() = true;
+        }
+        Action::SendImpulse => {
+            let origin = impulse_origin(state);
+            state.impulse_data.spawn(origin);
+            *model.update_impulses.borrow_mut() = true;
+        }
+        Action::ToggleAutoRotate => state.auto_rotate = !state.auto_rotate,
+        Action::ToggleFog => {
+            state.fog_settings.enabled = !state.fog_settings.enabled;
+            *model.update_fog.borrow_mut() = true;
+        }
+        Action::ToggleGizmo => {
+            state.gizmo.enabled = !state.gizmo.enabled;
+            *model.update_gizmo.borrow_mut() = true;
+        }
+        Action::ToggleClip => {
+            state.clip_box.enabled = !state.clip_box.enabled;
+            *model.update_clip.borrow_mut() = true;
+        }
+        Action::ToggleAttract => state.attract_enabled = !state.attract_enabled,
+        Action::ToggleAdaptiveResolution => {
+            state.resolution_scaler.enabled = !state.resolution_scaler.enabled;
+        }
+        Action::ToggleAutoExposure => {
+            state.exposure.auto = !state.exposure.auto;
+            *model.update_exposure.borrow_mut() = true;
+        }
+        Action::ToggleLod => {
+            // The octree is built along with the upload of the points
+            state.lod_settings.enabled = !state.lod_settings.enabled;
+            let window = app.window(model.window_id).unwrap();
+            upload_points(model, window.device());
+        }
+        Action::ToggleCollision => {
+            state.obstacles = match state.obstacles {
+                Some(_) => None,
+                None => Some(OccupancyGrid::build(
+                    &state.points,
+                    state.collision_resolution,
+                )),
+            };
+        }
+        Action::TogglePicking => {
+            state.picking = !state.picking;
+            state.picks.clear();
+        }
+        Action::JumpToPreset(i) => {
+            if let Some(preset) = state.presets.get(i).cloned() {
+                model.session.preset_used(&preset.name);
+                preset.apply(state, model.shader_pipeline.borrow_mut().camera_mut());
+                *model.update_camera.borrow_mut() = true;
+                *model.update_cloud_data.borrow_mut() = true;
+                *model.update_attractors.borrow_mut() = true;
+                *model.update_masks.borrow_mut() = true;
+                *model.update_fog.borrow_mut() = true;
+                *model.update_coloring.borrow_mut() = true;
+            }
+        }
+        Action::SaveSettings => {
+            let mut settings = Settings::from_state(state);
+            settings.camera = Some(CameraBookmark::new(model.shader_pipeline.borrow().camera()));
+            match state.preset_library.save(&settings) {
+                Ok(path) => {
+                    println!("Saving preset to: {}", path.display());
+                    state.presets = read_presets(&state.preset_library);
+                }
+                Err(e) => eprintln!("{e}"),
+            }
+        }
+        Action::LoadSettings => match state.preset_library.load::<Settings>() {
+            Ok(settings) => {
+                model.session.preset_used(&state.preset_library.name);
+                settings.apply(state);
+                *model.update_cloud_data.borrow_mut() = true;
+                *model.update_attractors.borrow_mut() = true;
+                *model.update_masks.borrow_mut() = true;
+                *model.update_fog.borrow_mut() = true;
+                *model.update_coloring.borrow_mut() = true;
+            }
+            Err(e) => eprintln!("{e}: {}", state.preset_library.name),
+        },
+        Action::AddKeyframe => {
+            let pipeline = model.shader_pipeline.borrow();
+            state
+                .camera_path
+                .add_keyframe(pipeline.camera(), &state.gravity, app.time);
+        }
+        Action::TogglePath => {
+            if state.camera_path.is_playing() {
+                state.camera_path.stop();
+                model.path_capture = None;
+                return;
+            }
+            state.camera_path.play();
+            if state.capture_path && state.camera_path.is_playing() {
+                let directory = get_save_directory(&app.exe_name().unwrap());
+                match std::fs::create_dir_all(&directory) {
+                    Ok(()) => {
+                        model.session.capture();
+                        model.path_capture = Some(directory);
+                    }
+                    Err(e) => eprintln!("Failed to create {directory}: {e}"),
+                }
+            }
+        }
+        Action::ClearPath => {
+            state.camera_path.clear();
+            model.path_capture = None;
+        }
+        Action::StoreBookmark(slot) => {
+            let pipeline = model.shader_pipeline.borrow();
+            state.camera_bookmarks.store(slot, pipeline.camera());
+            println!("Stored camera bookmark {}", slot + 1);
+            if let Err(e) = save_bookmarks(&state.metadata.source, &state.camera_bookmarks) {
+                eprintln!("Failed to save the camera bookmarks: {e}");
+            }
+        }
+        Action::RecallBookmark(slot) => {
+            let pipeline = model.shader_pipeline.borrow();
+            let duration = state.bookmark_duration;
+            if state
+                .camera_bookmarks
+                .recall(slot, pipeline.camera(), duration)
+            {
+                state.attract_loop.interact();
+            } else {
+                eprintln!("No camera bookmark {}", slot + 1);
+            }
+        }
+        Action::ToggleExport => match &mut model.ply_sequence {
+            Some(sequence) => {
+                // Write the frames still being read back before closing the sequence
+                let window = app.window(model.window_id).unwrap();
+                submit_read_back(sequence, model.readback.finish(window.device()));
+                model.ply_sequence = None;
+            }
+            None => match PlySequence::new(&state.export_directory, state.export_every_n_frames) {
+                Ok(sequence) => model.ply_sequence = Some(sequence),
+                Err(e) => eprintln!("{e}"),
+            },
+        },
+        Action::SaveCloud => {
+            let window = app.window(model.window_id).unwrap();
+            let points = model
+                .shader_pipeline
+                .borrow()
+                .read_back_points(window.device(), window.queue());
+            let path = get_export_path(&app.exe_name().unwrap(), "ply");
+            match points.and_then(|points| write_ply(&path, &points)) {
+                Ok(()) => model.session.capture(),
+                Err(e) => eprintln!("{e}: {path}"),
+            }
+        }
+        Action::ToggleShaderReload => {
+            let mut pipeline = model.shader_pipeline.borrow_mut();
+            let directory = match pipeline.shader_watcher() {
+                Some(_) => None,
+                None => Some(state.shader_directory.clone().into()),
+            };
+            pipeline.watch_shaders(directory);
+        }
+        Action::ToggleRecording => {
+            if !model.recorder.is_recording() {
+                model.session.capture();
+            }
+            model
+                .recorder
+                .toggle(&app.exe_name().unwrap(), app.main_window().device());
+        }
+        Action::ToggleNdi => model.ndi.toggle(app.main_window().device()),
+        Action::SaveFrame => {
+            model.session.capture();
+            model.recorder.save_frame(
+                get_save_path(&app.exe_name().unwrap()),
+                build_info::parameters(&Settings::from_state(&model.state)),
+            );
+        }
+        Action::SaveStill => {
+            // Render offscreen at a width of 4K times the capture scale, keeping the aspect ratio
+            // of the window
+            const STILL_WIDTH: u32 = 3840;
+            let window = app.window(model.window_id).unwrap();
+            let (width, height) = window.inner_size_pixels();
+            let still_width = (STILL_WIDTH as f32 * model.recorder.capture_scale).round() as u32;
+            let size = [still_width, (still_width * height / width).max(1)];
+            let device_queue_pair = window.device_queue_pair();
+            let image = model.shader_pipeline.borrow_mut().render_to_linear_image(
+                device_queue_pair.device(),
+                device_queue_pair.queue(),
+                size,
+            );
+            let image = match image {
+                Ok(image) => image,
+                Err(e) => {
+                    eprintln!("Failed to render the still: {e}");
+                    return;
+                }
+            };
+            let parameters = build_info::parameters(&Settings::from_state(&model.state));
+            let options = model.state.still_options;
+            let path = options.save_path(&app.exe_name().unwrap());
+            match options.save(&image, &path, parameters.as_deref()) {
+                Ok(()) => model.session.capture(),
+                Err(e) => eprintln!("Failed to save the still: {e}"),
+            }
+        }
+        Action::SaveWallStill => {
+            // Render the wall display by display, so no texture exceeds the size of a display
+            let window = app.window(model.window_id).unwrap();
+            let device_queue_pair = window.device_queue_pair();
+            let image = model
+                .shader_pipeline
+                .borrow_mut()
+                .render_tiled_to_linear_image(
+                    device_queue_pair.device(),
+                    device_queue_pair.queue(),
+                    state.wall_tile_size,
+                    state.wall_tiles,
+                );
+            let image = match image {
+                Ok(image) => image,
+                Err(e) => {
+                    eprintln!("Failed to render the wall still: {e}");
+                    return;
+                }
+            };
+            let parameters = build_info::parameters(&Settings::from_state(&model.state));
+            let options = model.state.still_options;
+            let path = options.save_path(&app.exe_name().unwrap());
+            match options.save(&image, &path, parameters.as_deref()) {
+                Ok(()) => model.session.capture(),
+                Err(e) => eprintln!("Failed to save the wall still: {e}"),
+            }
+        }
+        Action::RenderStill => {
+            if model.still.is_some() {
+                return;
+            }
+            // The tiles are rendered a frame apart, so the points hold still meanwhile
+            const MAX_TILE: u32 = 4096;
+            let mut pipeline = model.shader_pipeline.borrow_mut();
+            let max_tile = model.gpu_limits.max_texture_dimension_2d.min(MAX_TILE);
+            let still = TiledStill::new(*pipeline.camera(), state.still_size, max_tile);
+            let [width, height] = still.size();
+            println!(
+                "Rendering a {width}x{height} still in {} tiles",
+                still.tile_count()
+            );
+            pipeline.set_frozen(true);
+            model.still = Some(still);
+        }
+        Action::ToggleBuildInfo => model.show_build_info = !model.show_build_info,
+        Action::ToggleSpectrum => model.show_spectrum = !model.show_spectrum,
+        Action::Quit => {
+            model.recorder.stop(app.main_window().device());
+            model.ndi.stop(app.main_window().device());
+            app.quit();
+        }
+    }
+}
+
+/// Lists the coordinates and colors of the picked points, and the distance between the two.
+fn show_picks(ui: &mut egui::Ui, picks: &[PickedPoint]) {
+    if picks.is_empty() {
+        ui.label("Click on the cloud to pick a point, right click to clear");
+    }
+    egui::Grid::new("picks").num_columns(3).show(ui, |ui| {
+        picks.iter().zip(["A", "B"]).for_each(|(pick, name)| {
+            let [x, y, z] = pick.position.to_array();
+            let [red, green, blue, alpha] = pick.color.map(|channel| (channel * 255.0) as u8);
+            let color = egui::Color32::from_rgba_unmultiplied(red, green, blue, alpha);
+            ui.label(name);
+            ui.label(format!("{x:.3}, {y:.3}, {z:.3}"));
+            egui::color_picker::show_color(ui, color, egui::vec2(24.0, 16.0))
+                .on_hover_text(format!("#{red:02x}{green:02x}{blue:02x}"));
+            ui.end_row();
+        });
+    });
+    if let [a, b] = picks {
+        ui.label(format!("distance: {:.3}", a.position.distance(b.position)));
+    }
+}
+
+/// Marks the picked points over the frame, joined by a line labelled with their distance.
+fn draw_picks(draw: &Draw, picks: &[PickedPoint], camera: &Camera, window: Rect) {
+    let marks: Vec<_> = picks
+        .iter()
+        .filter_map(|pick| camera.to_window(pick.position, window))
+        .collect();
+    if let ([a, b], [start, end]) = (picks, marks.as_slice()) {
+        draw.line().start(*start).end(*end).weight(2.0).color(CYAN);
+        let distance = a.position.distance(b.position);
+        draw.text(&format!("{distance:.3}"))
+            .xy((*start + *end) / 2.0 + vec2(0.0, 12.0))
+            .color(CYAN);
+    }
+    marks.iter().for_each(|&mark| {
+        draw.ellipse()
+            .xy(mark)
+            .radius(4.0)
+            .no_fill()
+            .stroke(CYAN)
+            .stroke_weight(2.0);
+    });
+}
+
+/// Replaces the points of the cloud, keeping the replaced points so the change can be undone.
+fn replace_points(
+    app: &App,
+    model: &mut Model,
+    points: Vec<Point>,
+    source: CloudSource,
+    label: String,
+) {
+    let points = stream_large_cloud(model, points, &source);
+    let points = fit_to_device(model, points);
+    let previous = std::mem::replace(&mut model.state.points, points);
+    model.state.history.record(label, previous);
+    model.state.clouds[0].name = source.to_string();
+    model.state.camera_bookmarks = load_bookmarks(&source);
+    model.state.metadata.source = source;
+
+    // Update the camera and points
+    let window = app.window(model.window_id).unwrap();
+    upload_points(model, window.device());
+    model
+        .shader_pipeline
+        .borrow_mut()
+        .camera_mut()
+        .fit_points(&model.state.points);
+    *model.update_camera.borrow_mut() = true;
+    model.state.coloring.fit_elevation(&model.state.points);
+    *model.update_coloring.borrow_mut() = true;
+    model.state.clip_box.fit(model.state.metadata.bounds);
+    *model.update_clip.borrow_mut() = true;
+    model.state.gravity.fit(&model.state.points);
+    *model.update_gravity.borrow_mut() = true;
+}
+
+/// The file the camera bookmarks of a cloud are saved to, next to the cloud file on the disk.
+fn bookmarks_path(source: &CloudSource) -> Option<String> {
+    match source {
+        CloudSource::File { path, .. } if !path.contains("://") => {
+            Some(format!("{path}.bookmarks.json"))
+        }
+        _ => None,
+    }
+}
+
+/// Reads the camera bookmarks saved for a cloud, none if it has no file of bookmarks.
+fn load_bookmarks(source: &CloudSource) -> CameraBookmarks {
+    let Some(path) = bookmarks_path(source) else {
+        return CameraBookmarks::default();
+    };
+    let Ok(json) = std::fs::read_to_string(&path) else {
+        return CameraBookmarks::default();
+    };
+    serde_json::from_str(&json).unwrap_or_else(|e| {
+        eprintln!("Failed to read the camera bookmarks of {path}: {e}");
+        CameraBookmarks::default()
+    })
+}
+
+/// Writes the camera bookmarks of a cloud next to its file, a generated cloud keeping them only
+/// until the next one is loaded.
+fn save_bookmarks(source: &CloudSource, bookmarks: &CameraBookmarks) -> std::io::Result<()> {
+    let Some(path) = bookmarks_path(source) else {
+        return Ok(());
+    };
+    let json = serde_json::to_string_pretty(bookmarks)?;
+    std::fs::write(path, json)
+}
+
+/// Colors the points of the cloud by their distance to another scan of the same space, keeping
+/// the previous colors so the comparison can be undone.
+fn compare_points(app: &App, model: &mut Model, reference: &[Point], path: &str) {
+    let state = &mut model.state;
+    let mut points = state.points.clone();
+    diff::color_by_change(&mut points, reference, &state.coloring, state.compare_range);
+    let previous = std::mem::replace(&mut state.points, points);
+    state
+        .history
+        .record(format!("comparison with {path}"), previous);
+
+    // The ramp is baked into the colors of the points
+    state.coloring.mode = ColorMode::Rgb;
+    *model.update_coloring.borrow_mut() = true;
+    let window = app.window(model.window_id).unwrap();
+    upload_points(model, window.device());
+}
+
+/// Estimates the normals of the points of the cloud so they can be lit, keeping the previous
+/// points so the estimation can be undone.
+///
+/// The points are lit from the headlight once they have normals, unless another shading is set.
+fn estimate_cloud_normals(model: &mut Model, device: &wgpu::Device) {
+    let state = &mut model.state;
+    let mut points = state.points.clone();
+    estimate_normals(&mut points, state.normal_neighbors);
+    let previous = std::mem::replace(&mut state.points, points);
+    state.history.record("normal estimation", previous);
+
+    if state.lighting.mode == ShadingMode::Unlit {
+        state.lighting.mode = ShadingMode::Headlight;
+        *model.update_lighting.borrow_mut() = true;
+    }
+    upload_points(model, device);
+}
+
+/// Adds a point cloud to the scene next to the main cloud, where it can be placed on its own.
+fn add_cloud(app: &App, model: &mut Model, points: Vec<Point>, name: String) {
+    let mut points = fit_to_device(model, points);
+    let octree = model
+        .state
+        .lod_settings
+        .enabled
+        .then(|| Octree::build(&mut points));
+    let cloud = SceneCloud::new(name);
+    let window = app.window(model.window_id).unwrap();
+    model
+        .shader_pipeline
+        .borrow_mut()
+        .add_cloud(window.device(), &points, octree, cloud.layer);
+    model.state.clouds.push(cloud);
+}
+
+/// Streams the detail of a cloud too large for the GPU from a cache of its tiles next to its file,
+/// returning an overview of its points to deform and draw whole.
+///
+/// The tiles are written on a background thread the first time the file is loaded. The other
+/// clouds are returned as they are, those read from the web being thinned out to fit instead.
+fn stream_large_cloud(model: &mut Model, points: Vec<Point>, source: &CloudSource) -> Vec<Point> {
+    model.tiling = None;
+    model.streamer = None;
+    model.shader_pipeline.borrow_mut().clear_tiles();
+
+    let max_points = GPUPipeline::max_points(&model.gpu_limits);
+    let path = match source {
+        CloudSource::File { path, .. } if !path.contains("://") && points.len() > max_points => {
+            path
+        }
+        _ => return points,
+    };
+    let Some(cache_path) = TileCache::path_for(path) else {
+        eprintln!(
+            "The cloud of {} points exceeds the {max_points} points the GPU can hold, and no directory can hold its tiles",
+            points.len()
+        );
+        return points;
+    };
+    eprintln!(
+        "The cloud of {} points exceeds the {max_points} points the GPU can hold, its detail is streamed from {cache_path}",
+        points.len()
+    );
+    let overview = Point::sample(&points, TileStreamer::OVERVIEW_POINTS.min(max_points));
+    match TileCache::open_fresh(&cache_path, path, points.len()) {
+        Some(cache) => model.streamer = Some(TileStreamer::new(cache)),
+        None => model.tiling = Some(write_tiles_async(&cache_path, points)),
+    }
+    overview
+}
+
+/// Shows the progress of the tiles being written, or how many are streamed to the GPU.
+fn streaming_ui(
+    ui: &mut egui::Ui,
+    tiling: Option<&LoadHandle<TileCache>>,
+    streamer: Option<&mut TileStreamer>,
+) {
+    if let Some(tiling) = tiling {
+        ui.add(egui::ProgressBar::new(tiling.fraction()).text("Writing tiles"));
+    }
+    let Some(streamer) = streamer else {
+        return;
+    };
+    let (tiles, points) = streamer.resident();
+    ui.label(format!(
+        "{tiles} / {} tiles on the GPU, {points} points",
+        streamer.cache().tiles().len()
+    ));
+    ui.label(format!("{} tiles being read", streamer.pending()));
+    ui.checkbox(&mut streamer.enabled, "Stream the detail of the cloud");
+    ui.add(
+        LabelledSlider::new(
+            "stream_budget",
+            &mut streamer.budget,
+            1_000_000..=64_000_000,
+        )
+        .logarithmic(),
+    );
+}
+
+/// Thins out the points of a cloud too large for the storage buffers of the device, so it is still
+/// deformed.
+fn fit_to_device(model: &Model, points: Vec<Point>) -> Vec<Point> {
+    let max_points = GPUPipeline::max_points(&model.gpu_limits);
+    if points.len() > max_points {
+        eprintln!(
+            "warning: the cloud of {} points exceeds the {max_points} points the GPU can hold, it is thinned out",
+            points.len()
+        );
+    }
+    Point::decimate(points, max_points)
+}
+
+/// The options of the loaders set in the settings.
+fn load_options(state: &State) -> LoadOptions {
+    let classes = state
+        .las_classes
+        .split(',')
+        .filter_map(|class| class.trim().parse().ok())
+        .collect();
+    LoadOptions { classes }
+}
+
+/// Sends the points of the state to the GPU, resetting their deformation.
+fn upload_points(model: &mut Model, device: &wgpu::Device) {
+    let state = &mut model.state;
+    state.cloud_center = cloud_center(&state.points);
+    state.metadata.measure(&state.points);
+    state.spectrum_data.set_bounds(state.metadata.bounds);
+    *model.update_spectrum.borrow_mut() = true;
+    state.trail_data.reset();
+    *model.update_trail.borrow_mut() = true;
+    *model.update_gizmo.borrow_mut() = true;
+    if state.obstacles.is_some() {
+        state.obstacles = Some(OccupancyGrid::build(
+            &state.points,
+            state.collision_resolution,
+        ));
+    }
+
+    // Reorder the points into an octree so only the visible ones are drawn, at the detail needed
+    let octree = state
+        .lod_settings
+        .enabled
+        .then(|| Octree::build(&mut state.points));
+    let mut pipeline = model.shader_pipeline.borrow_mut();
+    pipeline.new_point_cloud(device, &state.points);
+    pipeline.set_octree(octree);
+}
+
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    if key == PALETTE_KEY {
+        model.palette.toggle();
+        return;
+    }
+    // The keys are used to type in the palette or in a text field of the settings, such as the
+    // LAS classes, where the number keys would overwrite the bookmarks
+    if model.palette.is_open() || model.egui.ctx().wants_keyboard_input() {
+        return;
+    }
+
+    if key == HELP_KEY {
+        model.help.toggle();
+        return;
+    }
+
+    // The number keys store the camera pose as a bookmark, and fly back to it with shift held
+    if let Some(slot) = BOOKMARK_KEYS.iter().position(|&bookmark| bookmark == key) {
+        let action = match app.keys.mods.shift() {
+            false => Action::StoreBookmark(slot),
+            true => Action::RecallBookmark(slot),
+        };
+        perform(app, model, action);
+        return;
+    }
+
+    if let Some(action) = action_for(&bindings(), key) {
+        perform(app, model, action);
+    }
+}
+
+/// The hotkeys of the sketch, listed by the help overlay.
+fn bindings() -> Vec<Binding<Action>> {
+    vec![
+        Binding::new(
+            &[RECORD_KEY],
+            "Start or stop recording the frames",
+            Action::ToggleRecording,
+        ),
+        Binding::new(
+            &[Key::Space],
+            "Toggle binding the cursor to the window",
+            Action::ToggleCursor,
+        ),
+        Binding::new(
+            &[Key::Tab],
+            "Toggle between the looks of the A/B slots",
+            Action::ToggleLook,
+        ),
+        Binding::new(
+            &[Key::B],
+            "Send a pulse through the cloud",
+            Action::TriggerPulse,
+        ),
+        Binding::new(
+            &[Key::K],
+            "Add the camera pose as a keyframe of the camera path",
+            Action::AddKeyframe,
+        ),
+        Binding::new(
+            &[Key::P],
+            "Play or stop the camera path",
+            Action::TogglePath,
+        ),
+        Binding::new(
+            &[Key::G],
+            "Show or hide the ground grid, the axes and the bounding box",
+            Action::ToggleGizmo,
+        ),
+        Binding::new(
+            &[Key::M],
+            "Pick points to measure instead of dropping attractors",
+            Action::TogglePicking,
+        ),
+        Binding::new(&[Key::Z], "Save the current frame", Action::SaveFrame),
+        Binding::new(
+            &[BUILD_INFO_KEY],
+            "Show or hide the build in a corner",
+            Action::ToggleBuildInfo,
+        ),
+        Binding::new(&[Key::X, Key::Escape], "Quit", Action::Quit),
+    ]
+}
+
+/// The keys held down to move the camera, listed by the help overlay after the hotkeys.
+fn camera_bindings() -> Vec<Binding<Direction>> {
+    vec![
+        Binding::new(&[Key::W, Key::Up], "Move forwards", Direction::Forward),
+        Binding::new(&[Key::S, Key::Down], "Move backwards", Direction::Backward),
+        Binding::new(&[Key::A, Key::Left], "Strafe left", Direction::Left),
+        Binding::new(&[Key::D, Key::Right], "Strafe right", Direction::Right),
+        Binding::new(&[Key::Q, Key::Comma], "Float down", Direction::Down),
+        Binding::new(&[Key::E, Key::Period], "Float up", Direction::Up),
+    ]
+}
+
+fn mouse_pressed(app: &App, model: &mut Model, button: MouseButton) {
+    // Let egui handle clicks on the settings window
+    if !model.camera_is_active && model.egui.ctx().wants_pointer_input() {
+        return;
+    }
+
+    let state = &mut model.state;
+    match button {
+        MouseButton::Left => {
+            // While the camera is active the cursor is grabbed, so we pick through the center
+            let ndc = if model.camera_is_active {
+                Vec2::ZERO
+            } else {
+                let rect = app.window_rect();
+                vec2(app.mouse.x / rect.w() * 2.0, app.mouse.y / rect.h() * 2.0)
+            };
+
+            let pipeline = model.shader_pipeline.borrow();
+            let (origin, direction) = pipeline.camera().screen_ray(ndc);
+
+            // Pick the point under the cursor, starting a new measure once two are picked
+            // The ray is cast in the coordinates of the main cloud, undoing its transform
+            if state.picking {
+                let placement = state.clouds[0].layer.transform.matrix();
+                let to_cloud = placement.inverse();
+                let ray = PickRay::new(
+                    to_cloud.transform_point3(origin),
+                    to_cloud.transform_vector3(direction),
+                    PickRay::TOLERANCE,
+                );
+                if let Some(mut pick) = ray.pick(&state.points, pipeline.octree()) {
+                    pick.position = placement.transform_point3(pick.position);
+                    if state.picks.len() == 2 {
+                        state.picks.clear();
+                    }
+                    state.picks.push(pick);
+                }
+                return;
+            }
+
+            // Drop an attractor along the picked ray
+            let position = origin + direction * state.attractor_distance;
+            state
+                .attractor_data
+                .push(position, state.attractor_strength);
+        }
+        MouseButton::Right if state.picking => {
+            state.picks.clear();
+            return;
+        }
+        MouseButton::Right => {
+            state.attractor_data.pop();
+        }
+        _other_button => return,
     }
+    *model.update_attractors.borrow_mut() = true;
 }
 
 fn event(_app: &App, model: &mut Model, event: Event) {
-    if model.camera_is_active {
-        if let Event::DeviceEvent(_device_id, winit::event::DeviceEvent::Motion { axis, value }) =
+    if model.camera_is_active
+        && let Event::DeviceEvent(_device_id, winit::event::DeviceEvent::Motion { axis, value }) =
             event
-        {
-            let delta = -value as f32 * model.state.mouse_sensitivity;
-            let mut pipeline = model.shader_pipeline.borrow_mut();
-            let camera = pipeline.camera_mut();
-            match axis {
-                // Yaw left and right on mouse x axis movement.
-                0 => camera.update_yaw(delta),
-                // Pitch up and down on mouse y axis movement.
-                _ => camera.update_pitch(delta),
-            }
-            *model.update_camera.borrow_mut() = true;
+    {
+        let delta = -value as f32 * model.state.mouse_sensitivity;
+        let mut pipeline = model.shader_pipeline.borrow_mut();
+        let camera = pipeline.camera_mut();
+        match axis {
+            // Yaw left and right on mouse x axis movement.
+            0 => camera.update_yaw(delta),
+            // Pitch up and down on mouse y axis movement.
+            _ => camera.update_pitch(delta),
         }
+        *model.update_camera.borrow_mut() = true;
+        model.state.attract_loop.interact();
     }
 }
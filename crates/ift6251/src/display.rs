@@ -0,0 +1,428 @@
+use std::str::FromStr;
+
+use nannou::{
+    App,
+    winit::{monitor::MonitorHandle, window::Fullscreen},
+};
+use serde::Deserialize;
+
+/// The file the display options are read from when they are not given on the command line.
+const CONFIG_PATH: &str = "./config.toml";
+
+/// The flags of the display options, each followed by its value.
+pub const FLAGS: [&str; 2] = ["--monitor", "--video-mode"];
+
+/// The monitor a sketch goes fullscreen on, and the video mode it switches it to, so each projector
+/// of a multi-display setup gets its own sketch.
+///
+/// They are given on the command line as `--monitor INDEX` and `--video-mode WxH[@HZ]`, or else
+/// read from the table of the sketch in `config.toml`, such as:
+///
+/// ```toml
+/// [display.birds]
+/// monitor = 1
+/// video_mode = "1920x1080@60"
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct DisplayOptions {
+    /// The index of the monitor, in the order the system lists them; the primary monitor when it
+    /// is not given.
+    pub monitor: Option<usize>,
+    /// The video mode the monitor is switched to in exclusive fullscreen; the window is borderless
+    /// at the resolution of the desktop when it is not given.
+    pub video_mode: Option<VideoMode>,
+}
+
+impl DisplayOptions {
+    /// Reads the display options of the sketch from its table of the config file, overridden by
+    /// those of the command line.
+    pub fn load(sketch: &str) -> Self {
+        let config =
+            std::fs::read_to_string(CONFIG_PATH)
+                .ok()
+                .map_or_else(Self::default, |config| {
+                    Self::from_config(&config, sketch).unwrap_or_else(|e| {
+                        eprintln!("Ignoring the display of {sketch} in {CONFIG_PATH}: {e}");
+                        Self::default()
+                    })
+                });
+        let options = Self::parse(std::env::args().skip(1)).unwrap_or_else(|e| {
+            eprintln!("{e}\nUsage: {sketch} [--monitor INDEX] [--video-mode WxH[@HZ]]");
+            std::process::exit(2);
+        });
+        options.or(config)
+    }
+
+    /// Reads the `display.<sketch>` table of a config file, if any.
+    fn from_config(config: &str, sketch: &str) -> Result<Self, toml::de::Error> {
+        #[derive(Default, Deserialize)]
+        #[serde(default)]
+        struct Config {
+            display: toml::value::Table,
+        }
+
+        let mut config: Config = toml::from_str(config)?;
+        match config.display.remove(sketch) {
+            Some(table) => table.try_into(),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Parses the display options among the arguments, leaving out the others for the sketch.
+    pub fn parse(args: impl Iterator<Item = String>) -> Result<Self, &'static str> {
+        let mut options = Self::default();
+        let mut args = args.peekable();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--monitor" => {
+                    let index = args
+                        .next()
+                        .and_then(|index| index.parse().ok())
+                        .ok_or("The monitor must be given by its index")?;
+                    options.monitor = Some(index);
+                }
+                "--video-mode" => {
+                    let mode = args.next().ok_or("The video mode is missing")?;
+                    options.video_mode = Some(mode.parse()?);
+                }
+                _ => {}
+            }
+        }
+        Ok(options)
+    }
+
+    /// Fills the options not given with those of `other`.
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            monitor: self.monitor.or(other.monitor),
+            video_mode: self.video_mode.or(other.video_mode),
+        }
+    }
+
+    /// The fullscreen of the window on the chosen monitor, exclusive when a video mode is given.
+    ///
+    /// When the monitor or the video mode is not available, the ones available are listed and the
+    /// window falls back to the primary monitor or to a borderless window. Wayland does not support
+    /// exclusive fullscreen.
+    pub fn fullscreen(&self, app: &App) -> Fullscreen {
+        let monitors = app.available_monitors();
+        let monitor = match self.monitor {
+            Some(index) => monitors.get(index).cloned().or_else(|| {
+                eprintln!("warning: there is no monitor {index}, using the primary monitor");
+                list_monitors(&monitors);
+                app.primary_monitor()
+            }),
+            None => app.primary_monitor(),
+        };
+        let (Some(mode), Some(monitor)) = (self.video_mode, &monitor) else {
+            return Fullscreen::Borderless(monitor);
+        };
+
+        let modes: Vec<_> = monitor.video_modes().collect();
+        let candidates: Vec<_> = modes
+            .iter()
+            .map(|mode| (mode.size().into(), mode.refresh_rate_millihertz()))
+            .collect();
+        match mode.best_of(&candidates) {
+            Some(index) => Fullscreen::Exclusive(modes[index].clone()),
+            None => {
+                eprintln!("warning: the monitor does not support {mode}, staying borderless");
+                list_monitors(std::slice::from_ref(monitor));
+                Fullscreen::Borderless(Some(monitor.clone()))
+            }
+        }
+    }
+}
+
+/// The windows of a panorama spanning several monitors, each turned by its own yaw so together
+/// they show a seamless view, read from a layout file such as:
+///
+/// ```toml
+/// # The horizontal field of view of each window in degrees; the gap between the yaws of the
+/// # windows when it is not given
+/// field_of_view = 60.0
+///
+/// [[window]]
+/// monitor = 1
+/// yaw = -60.0
+///
+/// [[window]]
+/// monitor = 2
+/// yaw = 0.0
+///
+/// [[window]]
+/// monitor = 3
+/// yaw = 60.0
+/// video_mode = "1920x1080@60"
+/// ```
+///
+/// The first window is the main one, looking where the camera looks; the others are turned from
+/// it.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct PanoramaLayout {
+    /// The horizontal field of view of each window in degrees.
+    pub field_of_view: Option<f32>,
+    #[serde(rename = "window")]
+    pub windows: Vec<PanoramaWindow>,
+}
+
+impl PanoramaLayout {
+    /// Reads a layout file.
+    pub fn read(path: &str) -> Result<Self, String> {
+        let layout = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::from_toml(&layout).map_err(|e| e.to_string())
+    }
+
+    fn from_toml(layout: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(layout)
+    }
+
+    /// The horizontal field of view of each window in degrees, so the edges of neighbouring
+    /// windows meet; the smallest gap between the yaws of the windows when it is not given.
+    pub fn field_of_view(&self) -> Option<f32> {
+        if self.field_of_view.is_some() {
+            return self.field_of_view;
+        }
+        let mut yaws: Vec<f32> = self.windows.iter().map(|window| window.yaw).collect();
+        yaws.sort_by(f32::total_cmp);
+        yaws.windows(2)
+            .map(|pair| pair[1] - pair[0])
+            .filter(|&gap| gap > 0.0)
+            .min_by(f32::total_cmp)
+    }
+}
+
+/// A window of a panorama, on its own monitor.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct PanoramaWindow {
+    /// The index of the monitor, in the order the system lists them.
+    pub monitor: Option<usize>,
+    /// The video mode the monitor is switched to in exclusive fullscreen.
+    pub video_mode: Option<VideoMode>,
+    /// How far the window is turned to the right, in degrees.
+    pub yaw: f32,
+}
+
+impl PanoramaWindow {
+    /// The display of the window, fullscreen on its monitor.
+    pub fn display(&self) -> DisplayOptions {
+        DisplayOptions {
+            monitor: self.monitor,
+            video_mode: self.video_mode,
+        }
+    }
+}
+
+/// A resolution to switch a monitor to, with its refresh rate, written `WxH[@HZ]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct VideoMode {
+    /// The width and height of the mode in pixels.
+    pub size: [u32; 2],
+    /// The refresh rate of the mode in hertz; the highest one at the resolution when it is not
+    /// given.
+    pub refresh_rate: Option<u32>,
+}
+
+impl VideoMode {
+    /// Returns the index of the mode matching this one among the sizes and refresh rates of the
+    /// modes of a monitor, in millihertz, preferring the highest refresh rate.
+    pub fn best_of(&self, modes: &[([u32; 2], u32)]) -> Option<usize> {
+        modes
+            .iter()
+            .enumerate()
+            .filter(|(_, (size, millihertz))| {
+                *size == self.size
+                    && self
+                        .refresh_rate
+                        .is_none_or(|hertz| (millihertz + 500) / 1000 == hertz)
+            })
+            .max_by_key(|(_, (_, millihertz))| *millihertz)
+            .map(|(index, _)| index)
+    }
+}
+
+impl FromStr for VideoMode {
+    type Err = &'static str;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        const ERROR: &str = "The video mode must be written WxH or WxH@HZ";
+        let (size, refresh_rate) = match mode.split_once('@') {
+            Some((size, hertz)) => (size, Some(hertz.parse().map_err(|_| ERROR)?)),
+            None => (mode, None),
+        };
+        let (width, height) = size.split_once('x').ok_or(ERROR)?;
+        let size = [
+            width.parse().map_err(|_| ERROR)?,
+            height.parse().map_err(|_| ERROR)?,
+        ];
+        if size.contains(&0) || refresh_rate == Some(0) {
+            return Err(ERROR);
+        }
+        Ok(Self { size, refresh_rate })
+    }
+}
+
+impl TryFrom<String> for VideoMode {
+    type Error = &'static str;
+
+    fn try_from(mode: String) -> Result<Self, Self::Error> {
+        mode.parse()
+    }
+}
+
+impl std::fmt::Display for VideoMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [width, height] = self.size;
+        write!(f, "{width}x{height}")?;
+        match self.refresh_rate {
+            Some(hertz) => write!(f, "@{hertz}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Prints the monitors with their index and the video modes they support.
+fn list_monitors(monitors: &[MonitorHandle]) {
+    monitors.iter().enumerate().for_each(|(index, monitor)| {
+        let size = monitor.size();
+        let name = monitor.name().unwrap_or_else(|| "unnamed".to_owned());
+        eprintln!("  monitor {index}: {name}, {}x{}", size.width, size.height);
+        let mut modes: Vec<_> = monitor
+            .video_modes()
+            .map(|mode| {
+                let size = mode.size();
+                let hertz = (mode.refresh_rate_millihertz() + 500) / 1000;
+                format!("{}x{}@{hertz}", size.width, size.height)
+            })
+            .collect();
+        modes.dedup();
+        if !modes.is_empty() {
+            eprintln!("    video modes: {}", modes.join(", "));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn video_modes_round_trip() {
+        let mode: VideoMode = "3840x2160@60".parse().unwrap();
+        assert_eq!(mode.size, [3840, 2160]);
+        assert_eq!(mode.refresh_rate, Some(60));
+        assert_eq!(mode.to_string(), "3840x2160@60");
+        assert_eq!("1920x1080".parse::<VideoMode>().unwrap().refresh_rate, None);
+        [
+            "",
+            "1920",
+            "1920x",
+            "0x1080",
+            "1920x1080@",
+            "1920x1080@0",
+            "axb",
+        ]
+        .into_iter()
+        .for_each(|mode| assert!(mode.parse::<VideoMode>().is_err(), "{mode}"));
+    }
+
+    #[test]
+    fn the_command_line_overrides_the_config() {
+        let args = |args: &[&str]| {
+            args.iter()
+                .map(|arg| arg.to_string())
+                .collect::<Vec<_>>()
+                .into_iter()
+        };
+        let options = DisplayOptions::parse(args(&[
+            "--cloud",
+            "a.ply",
+            "--monitor",
+            "1",
+            "--fullscreen",
+        ]))
+        .unwrap();
+        assert_eq!(options.monitor, Some(1));
+        assert_eq!(options.video_mode, None);
+        assert!(DisplayOptions::parse(args(&["--monitor", "left"])).is_err());
+        assert!(DisplayOptions::parse(args(&["--video-mode"])).is_err());
+
+        let config = r#"
+            cloud = "./data/scan.e57"
+
+            [display.cloud]
+            monitor = 2
+            video_mode = "1920x1080@60"
+
+            [display.birds]
+            monitor = 0
+        "#;
+        let cloud = DisplayOptions::from_config(config, "cloud").unwrap();
+        assert_eq!(cloud.monitor, Some(2));
+        assert_eq!(cloud.video_mode, Some("1920x1080@60".parse().unwrap()));
+        assert_eq!(
+            DisplayOptions::from_config(config, "triangles").unwrap(),
+            DisplayOptions::default()
+        );
+        assert!(
+            DisplayOptions::from_config("[display.birds]\nvideo_mode = \"big\"", "birds").is_err()
+        );
+
+        let merged = options.or(cloud);
+        assert_eq!(merged.monitor, Some(1));
+        assert_eq!(merged.video_mode, cloud.video_mode);
+    }
+
+    #[test]
+    fn the_highest_refresh_rate_is_preferred() {
+        let modes = [
+            ([1920, 1080], 59_940),
+            ([3840, 2160], 60_000),
+            ([1920, 1080], 144_000),
+            ([1920, 1080], 60_000),
+        ];
+        let mode = |mode: &str| mode.parse::<VideoMode>().unwrap();
+        assert_eq!(mode("1920x1080").best_of(&modes), Some(2));
+        assert_eq!(mode("1920x1080@60").best_of(&modes), Some(3));
+        assert_eq!(mode("3840x2160@60").best_of(&modes), Some(1));
+        assert_eq!(mode("3840x2160@120").best_of(&modes), None);
+        assert_eq!(mode("1280x720").best_of(&modes), None);
+    }
+
+    #[test]
+    fn panoramas_span_the_gaps_between_their_windows() {
+        let layout = PanoramaLayout::from_toml(
+            r#"
+            [[window]]
+            monitor = 1
+
+            [[window]]
+            monitor = 2
+            yaw = 45.0
+            video_mode = "1920x1080@60"
+
+            [[window]]
+            monitor = 0
+            yaw = -45.0
+            "#,
+        )
+        .unwrap();
+        assert_eq!(layout.windows.len(), 3);
+        assert_eq!(layout.windows[0].yaw, 0.0);
+        assert_eq!(layout.windows[1].display().monitor, Some(2));
+        assert_eq!(
+            layout.windows[1].display().video_mode,
+            Some("1920x1080@60".parse().unwrap())
+        );
+        assert_eq!(layout.field_of_view(), Some(45.0));
+
+        let layout = PanoramaLayout::from_toml("field_of_view = 50.0\n[[window]]").unwrap();
+        assert_eq!(layout.field_of_view(), Some(50.0));
+        assert_eq!(PanoramaLayout::default().field_of_view(), None);
+        assert!(PanoramaLayout::from_toml("[[window]]\nyaw = \"left\"").is_err());
+    }
+}
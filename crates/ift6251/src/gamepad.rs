@@ -0,0 +1,272 @@
+use std::{
+    fs::File,
+    io::Read,
+    sync::{Arc, Mutex, Weak},
+    thread,
+    time::Duration,
+};
+
+use nannou::prelude::*;
+use nannou_egui::egui;
+
+use crate::utils::widgets::LabelledSlider;
+
+/// The joystick devices searched for a gamepad, in order.
+const DEVICES: [&str; 4] = [
+    "/dev/input/js0",
+    "/dev/input/js1",
+    "/dev/input/js2",
+    "/dev/input/js3",
+];
+
+/// How long to wait before searching for a gamepad again when none is plugged in.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// The position of the sticks and triggers of a gamepad.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GamepadAxes {
+    /// The left stick from -1 to 1 on each axis, right and up being positive.
+    pub left_stick: Vec2,
+    /// The right stick from -1 to 1 on each axis, right and up being positive.
+    pub right_stick: Vec2,
+    /// The left trigger from 0, released, to 1, pulled all the way.
+    pub left_trigger: f32,
+    /// The right trigger from 0, released, to 1, pulled all the way.
+    pub right_trigger: f32,
+}
+
+impl GamepadAxes {
+    /// Updates the axes from an event of the Linux joystick interface.
+    ///
+    /// The axes follow the layout of the Xbox and PlayStation drivers: the left stick, the left
+    /// trigger, the right stick, then the right trigger. The sticks read positive downwards and
+    /// the triggers from -1 when released, so both are turned around.
+    fn apply_event(&mut self, event: [u8; 8]) {
+        const AXIS: u8 = 0x02;
+        // Set on the events reporting the state of the gamepad as it is opened
+        const INIT: u8 = 0x80;
+
+        let [_, _, _, _, low, high, kind, number] = event;
+        if kind & !INIT != AXIS {
+            return;
+        }
+        let value = (i16::from_ne_bytes([low, high]) as f32 / i16::MAX as f32).clamp(-1.0, 1.0);
+        match number {
+            0 => self.left_stick.x = value,
+            1 => self.left_stick.y = -value,
+            2 => self.left_trigger = (value + 1.0) / 2.0,
+            3 => self.right_stick.x = value,
+            4 => self.right_stick.y = -value,
+            5 => self.right_trigger = (value + 1.0) / 2.0,
+            _ => {}
+        }
+    }
+}
+
+/// The first gamepad plugged in, read on a background thread so the render loop never waits on
+/// it.
+///
+/// The gamepad is read through the joystick devices of Linux, which the Xbox and PlayStation
+/// controllers are exposed as without any driver to install. It can be plugged in and out while
+/// the sketch runs. The other systems have no such devices, so this module is only built on Linux
+/// and the sketches leave out their gamepad settings elsewhere.
+pub struct Gamepad {
+    /// The position of the axes, if a gamepad is plugged in.
+    axes: Arc<Mutex<Option<GamepadAxes>>>,
+}
+
+impl Gamepad {
+    /// Starts searching for a gamepad, reading it once it is plugged in.
+    pub fn connect() -> Self {
+        let axes = Arc::new(Mutex::new(None));
+        let reader = Arc::downgrade(&axes);
+        thread::spawn(move || read_gamepads(reader));
+        Self { axes }
+    }
+
+    /// The position of the axes, if a gamepad is plugged in.
+    pub fn axes(&self) -> Option<GamepadAxes> {
+        *self.axes.lock().unwrap()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.axes().is_some()
+    }
+}
+
+/// Reads the first gamepad found until it is unplugged, then searches again, for as long as the
+/// [`Gamepad`] reading it lives.
+fn read_gamepads(axes: Weak<Mutex<Option<GamepadAxes>>>) {
+    loop {
+        for device in DEVICES {
+            if let Ok(file) = File::open(device) {
+                read_events(file, &axes);
+            }
+        }
+        let Some(axes) = axes.upgrade() else {
+            return;
+        };
+        *axes.lock().unwrap() = None;
+        drop(axes);
+        thread::sleep(RETRY_DELAY);
+    }
+}
+
+/// Reads the events of a gamepad into its axes until it is unplugged.
+fn read_events(mut file: File, axes: &Weak<Mutex<Option<GamepadAxes>>>) {
+    let mut state = GamepadAxes::default();
+    let mut event = [0; 8];
+    while file.read_exact(&mut event).is_ok() {
+        state.apply_event(event);
+        let Some(axes) = axes.upgrade() else {
+            return;
+        };
+        *axes.lock().unwrap() = Some(state);
+    }
+}
+
+/// How the gamepad moves the camera: the left stick strafes, the right stick looks around, and the
+/// triggers float down and up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GamepadSettings {
+    pub enabled: bool,
+    /// The part of the travel of the sticks and triggers ignored around their rest, so a worn
+    /// stick does not drift.
+    pub dead_zone: f32,
+    /// How fast the sticks move the camera, 1 matching the speed of the keys.
+    pub move_sensitivity: f32,
+    /// How fast the right stick turns the camera, in radians per second.
+    pub look_sensitivity: f32,
+    /// Whether pushing the right stick up looks down, like a flight stick.
+    pub invert_look: bool,
+}
+
+/// The movement of the camera asked by the gamepad.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GamepadMotion {
+    /// The movement to the right, up and forwards, from -1 to 1 times the sensitivity.
+    pub movement: Vec3,
+    /// The turn to the right and up, in radians per second.
+    pub look: Vec2,
+}
+
+impl GamepadSettings {
+    /// The movement of the camera asked by the position of the axes.
+    pub fn motion(&self, axes: &GamepadAxes) -> GamepadMotion {
+        if !self.enabled {
+            return GamepadMotion::default();
+        }
+        let strafe = apply_dead_zone(axes.left_stick, self.dead_zone);
+        let rise = apply_dead_zone(vec2(axes.right_trigger, 0.0), self.dead_zone).x
+            - apply_dead_zone(vec2(axes.left_trigger, 0.0), self.dead_zone).x;
+        let mut look = apply_dead_zone(axes.right_stick, self.dead_zone);
+        if self.invert_look {
+            look.y = -look.y;
+        }
+        GamepadMotion {
+            movement: vec3(strafe.x, rise, strafe.y) * self.move_sensitivity,
+            look: look * self.look_sensitivity,
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui, gamepad: &Gamepad) {
+        ui.label(match gamepad.is_connected() {
+            true => "Gamepad connected",
+            false => "No gamepad found",
+        });
+        ui.checkbox(&mut self.enabled, "Move the camera with the gamepad");
+        ui.add(LabelledSlider::new(
+            "gamepad_dead_zone",
+            &mut self.dead_zone,
+            0.0..=0.5,
+        ));
+        ui.add(LabelledSlider::new(
+            "gamepad_move_sensitivity",
+            &mut self.move_sensitivity,
+            0.1..=4.0,
+        ));
+        ui.add(LabelledSlider::new(
+            "gamepad_look_sensitivity",
+            &mut self.look_sensitivity,
+            0.1..=6.0,
+        ));
+        ui.checkbox(&mut self.invert_look, "Invert the look");
+    }
+}
+
+impl Default for GamepadSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dead_zone: 0.15,
+            move_sensitivity: 1.0,
+            look_sensitivity: 2.0,
+            invert_look: false,
+        }
+    }
+}
+
+/// Ignores the travel of a stick within the dead zone around its rest, rescaling the rest of its
+/// travel so it still goes smoothly from 0 to 1 past the dead zone.
+fn apply_dead_zone(stick: Vec2, dead_zone: f32) -> Vec2 {
+    let length = stick.length();
+    if length <= dead_zone {
+        return Vec2::ZERO;
+    }
+    let scaled = ((length - dead_zone) / (1.0 - dead_zone)).min(1.0);
+    stick / length * scaled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: u8, number: u8, value: i16) -> [u8; 8] {
+        let [low, high] = value.to_ne_bytes();
+        [0, 0, 0, 0, low, high, kind, number]
+    }
+
+    #[test]
+    fn events_move_the_axes_the_right_way_around() {
+        let mut axes = GamepadAxes::default();
+        axes.apply_event(event(0x02, 0, i16::MAX));
+        axes.apply_event(event(0x02, 1, i16::MIN));
+        axes.apply_event(event(0x82, 2, -i16::MAX));
+        axes.apply_event(event(0x02, 5, i16::MAX));
+        // Buttons leave the axes alone
+        axes.apply_event(event(0x01, 0, 1));
+
+        assert_eq!(axes.left_stick, vec2(1.0, 1.0));
+        assert_eq!(axes.right_stick, Vec2::ZERO);
+        assert_eq!(axes.left_trigger, 0.0);
+        assert_eq!(axes.right_trigger, 1.0);
+    }
+
+    #[test]
+    fn the_dead_zone_is_ignored_and_the_rest_rescaled() {
+        assert_eq!(apply_dead_zone(vec2(0.1, 0.0), 0.2), Vec2::ZERO);
+        assert_eq!(apply_dead_zone(vec2(0.0, -1.0), 0.2), vec2(0.0, -1.0));
+        let half = apply_dead_zone(vec2(0.6, 0.0), 0.2);
+        assert!((half.x - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn the_sticks_and_triggers_move_the_camera() {
+        let settings = GamepadSettings::default();
+        let axes = GamepadAxes {
+            left_stick: vec2(0.0, 1.0),
+            right_stick: vec2(-1.0, 0.0),
+            left_trigger: 0.0,
+            right_trigger: 1.0,
+        };
+        let motion = settings.motion(&axes);
+        assert_eq!(motion.movement, vec3(0.0, 1.0, 1.0));
+        assert_eq!(motion.look, vec2(-settings.look_sensitivity, 0.0));
+
+        let disabled = GamepadSettings {
+            enabled: false,
+            ..settings
+        };
+        assert_eq!(disabled.motion(&axes), GamepadMotion::default());
+    }
+}
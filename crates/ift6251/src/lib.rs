@@ -1,13 +1,44 @@
+// The `noise` crate re-exports `Perlin` through overlapping globs
+#![allow(ambiguous_glob_imports)]
+
 use std::time::SystemTime;
 
+pub mod build_info;
+pub mod display;
+#[cfg(target_os = "linux")]
+pub mod gamepad;
+pub mod ndi;
+pub mod osc;
+pub mod recorder;
+pub mod save;
+pub mod session;
 pub mod utils;
+pub mod watchdog;
 
 pub fn get_save_path(prefix: &str) -> String {
-    let time = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .unwrap()
-        .as_millis();
-    let path = format!("./{}_{:?}.png", prefix, time);
+    let path = format!("./{}_{:?}.png", prefix, timestamp());
     println!("Saving image to: {}", path);
     path
 }
+
+/// Returns the path of a new file to export data to, with the given extension.
+pub fn get_export_path(prefix: &str, extension: &str) -> String {
+    let path = format!("./{}_{:?}.{}", prefix, timestamp(), extension);
+    println!("Exporting to: {}", path);
+    path
+}
+
+/// Returns the path of a new directory to save a sequence of frames to.
+pub fn get_save_directory(prefix: &str) -> String {
+    let path = format!("./{}_{:?}", prefix, timestamp());
+    println!("Saving frames to: {}", path);
+    path
+}
+
+/// The number of milliseconds elapsed since the UNIX epoch.
+fn timestamp() -> u128 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+}
@@ -1,52 +1,329 @@
-use std::sync::Mutex;
+// The `noise` crate re-exports `Perlin` through overlapping globs
+#![allow(ambiguous_glob_imports)]
+
+use std::{
+    borrow::Cow,
+    sync::{Arc, Mutex},
+};
 
 use ift6251::{
-    get_save_path,
+    build_info::{self, BUILD_INFO_KEY},
+    get_save_directory, get_save_path,
+    osc::{OSC_PORT, OscServer},
+    recorder::{RECORD_KEY, Recorder},
+    save::ImageOptions,
     utils::{
-        images::{create_texture, equalize, recalibrate},
-        mandelbrot::{get_shift_speed, is_in_mandelbrot, shift, zoom},
+        bookmarks::{BOOKMARKS_PATH, Bookmark, append_bookmark},
+        hotkeys::{Binding, HELP_KEY, HotkeyHelp, action_for, describe},
+        images::{create_texture, edge_pixels, equalize, recalibrate},
+        mandelbrot::{Coloring, Fractal, IterationSettings, IterationStats, Style, Viewport},
+        mandelbrot_gpu::MandelbrotPipeline,
+        palette::{Command, CommandPalette, PALETTE_KEY},
+        perturbation::{PreciseCenter, ReferenceOrbit},
+        presets::{PresetLibrary, PresetRequest},
+        widgets::{Dropdown, LabelledSlider, UiScale},
     },
+    watchdog::{self, Watchdog},
 };
 use indicatif::{ProgressBar, ProgressStyle};
 use nannou::{
-    color::{IntoColor, encoding::Srgb},
     image::{self, ImageBuffer, RgbaImage},
-    noise::{NoiseFn, Perlin},
+    noise::Perlin,
     prelude::*,
 };
 use nannou_egui::{
     Egui, FrameCtx,
     egui::{self},
 };
-use rayon::iter::{IntoParallelIterator, ParallelBridge, ParallelIterator};
+use point_cloud_renderer::color::LinearImage;
+use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
 
 fn main() {
+    watchdog::supervise();
     nannou::app(model).update(update).run()
 }
 
 struct State {
     redraw: bool,
     continuous_redraw: bool,
+    /// Whether the panes are rendered by the compute shader, when it is precise enough.
+    gpu: bool,
     image: ImageBuffer<image::Rgba<u8>, Vec<u8>>,
+    /// The format the images are saved in, the higher precisions being colored again from the
+    /// escape times rather than taken from the 8-bit image.
+    image_options: ImageOptions,
+    mandelbrot_array: Vec<Vec<f64>>,
+    julia_array: Vec<Vec<f64>>,
     delta: f64,
-    x_range: (f64, f64),
-    y_range: (f64, f64),
+    viewport: Viewport,
+    /// The center of the view of Mandlebrot's set in arbitrary precision, which the viewport
+    /// rounds, for the zooms past the precision of f64.
+    center: PreciseCenter,
     zoom_speed: f64,
     shift_speed: u32,
     max_iterations: usize,
+    escape_radius: f64,
+    power: f64,
+    adaptive_aa: bool,
+    aa_factor: usize,
+    aa_threshold: f64,
     select_in_mandelbrot: bool,
     plot_trajectory: bool,
-    noise: Perlin,
+    show_stats: bool,
+    stats: IterationStats,
+    split_view: bool,
+    /// Whether the Julia set of the chosen point fills the window in place of Mandlebrot's set.
+    julia_mode: bool,
+    julia_viewport: Viewport,
+    julia_c: (f64, f64),
+    julia_locked: bool,
+    redraw_julia: bool,
+    seed: u32,
     hue_scale: f64,
     saturation: f32,
+    cycle_colors: bool,
+    cycle_speed: f64,
+    cycle_frames: usize,
+    hue_phase: f64,
     noise_scale_x: f64,
     noise_scale_y: f64,
     noise_scale_z: f64,
+    coloring: Coloring,
+    style_history: Vec<Style>,
+    recolor: bool,
+    recorder: Recorder,
+    /// Whether the build is drawn in a corner of the window.
+    show_build_info: bool,
+    /// The named presets of the settings, saved to disk.
+    preset_library: PresetLibrary,
+}
+
+/// The settings of the render saved in the named presets.
+#[derive(Serialize, Deserialize)]
+struct Settings {
+    delta: f64,
+    zoom_speed: f64,
+    shift_speed: u32,
+    max_iterations: usize,
+    escape_radius: f64,
+    power: f64,
+    adaptive_aa: bool,
+    aa_factor: usize,
+    aa_threshold: f64,
+    style: Style,
+    coloring: Coloring,
+    cycle_colors: bool,
+    cycle_speed: f64,
+    cycle_frames: usize,
+}
+
+impl Settings {
+    fn from_state(state: &State) -> Self {
+        Self {
+            delta: state.delta,
+            zoom_speed: state.zoom_speed,
+            shift_speed: state.shift_speed,
+            max_iterations: state.max_iterations,
+            escape_radius: state.escape_radius,
+            power: state.power,
+            adaptive_aa: state.adaptive_aa,
+            aa_factor: state.aa_factor,
+            aa_threshold: state.aa_threshold,
+            style: style(state),
+            coloring: state.coloring,
+            cycle_colors: state.cycle_colors,
+            cycle_speed: state.cycle_speed,
+            cycle_frames: state.cycle_frames,
+        }
+    }
+
+    fn apply(&self, state: &mut State) {
+        state.delta = self.delta;
+        state.zoom_speed = self.zoom_speed;
+        state.shift_speed = self.shift_speed;
+        state.max_iterations = self.max_iterations;
+        state.escape_radius = self.escape_radius;
+        state.power = self.power;
+        state.adaptive_aa = self.adaptive_aa;
+        state.aa_factor = self.aa_factor;
+        state.aa_threshold = self.aa_threshold;
+        state.coloring = self.coloring;
+        state.cycle_colors = self.cycle_colors;
+        state.cycle_speed = self.cycle_speed;
+        state.cycle_frames = self.cycle_frames;
+        apply_style(state, &self.style);
+        state.redraw = true;
+    }
 }
 
 struct Model {
     egui: Egui,
+    palette: CommandPalette,
+    help: HotkeyHelp,
     state: State,
+    gpu_pipeline: MandelbrotPipeline,
+    /// The server receiving the parameters set remotely, if it could be started.
+    osc: Option<OscServer>,
+    /// Reports the frames to the supervisor and autosaves the settings, when the sketch is
+    /// supervised.
+    watchdog: Option<Watchdog>,
+}
+
+/// The number of looks kept in the history.
+const STYLE_HISTORY_LENGTH: usize = 16;
+
+/// The style the panes are colored with.
+fn style(state: &State) -> Style {
+    Style {
+        seed: state.seed,
+        hue_scale: state.hue_scale,
+        saturation: state.saturation,
+        noise_scale: [
+            state.noise_scale_x,
+            state.noise_scale_y,
+            state.noise_scale_z,
+        ],
+    }
+}
+
+/// Colors the panes with a style, like the sliders of the settings do.
+fn apply_style(state: &mut State, style: &Style) {
+    state.seed = style.seed;
+    state.hue_scale = style.hue_scale;
+    state.saturation = style.saturation;
+    [
+        state.noise_scale_x,
+        state.noise_scale_y,
+        state.noise_scale_z,
+    ] = style.noise_scale;
+    state.recolor = true;
+}
+
+/// The sequence iterated for each pixel.
+fn iteration_settings(state: &State) -> IterationSettings {
+    IterationSettings::new(state.max_iterations)
+        .with_escape_radius(state.escape_radius)
+        .with_power(state.power)
+}
+
+/// The view of the whole set, of magnification 1.
+fn home_viewport() -> Viewport {
+    Viewport::new((-2.0, 0.50), (-1.25, 1.25))
+}
+
+/// The view of the whole Julia sets.
+fn julia_home_viewport() -> Viewport {
+    Viewport::new((-1.6, 1.6), (-1.6, 1.6))
+}
+
+/// Moves the view of Mandlebrot's set, keeping its center in arbitrary precision.
+fn shift_view(state: &mut State, offset: (f64, f64)) {
+    state.center.shift(offset);
+    state.viewport.set_center(state.center.to_f64());
+}
+
+/// Zooms the view of Mandlebrot's set around a pixel of its pane, like [`Viewport::zoom_at`], but
+/// keeping its center in arbitrary precision.
+fn zoom_view_at(
+    state: &mut State,
+    pixel: (f64, f64),
+    (width, height): (usize, usize),
+    factor: f64,
+) {
+    if factor <= 0.0 || !factor.is_finite() {
+        return;
+    }
+    let offset = state.viewport.offset_at(pixel, width, height);
+    shift_view(
+        state,
+        (offset.0 * (1.0 - factor), offset.1 * (1.0 - factor)),
+    );
+    state.viewport.zoom(factor);
+}
+
+/// A pane of the window, showing one of the fractals with its own viewport.
+///
+/// Without the split view, Mandlebrot's set covers the whole window, unless the Julia mode shows
+/// the Julia set in its place.
+#[derive(Clone, Copy, PartialEq)]
+enum Pane {
+    Mandelbrot,
+    Julia,
+}
+
+impl Pane {
+    /// Returns the pane under a pixel of the window of the given size.
+    ///
+    /// # Returns
+    ///
+    /// A tuple containing:
+    /// - The pane under the pixel.
+    /// - The pixel, counted from the top-left corner of the pane.
+    /// - The width and height of the pane.
+    fn at(
+        state: &State,
+        pixel: (f64, f64),
+        (width, height): (usize, usize),
+    ) -> (Self, (f64, f64), (usize, usize)) {
+        let mandelbrot_width = Self::mandelbrot_width(state, width);
+        if pixel.0 < mandelbrot_width as f64 {
+            (Pane::Mandelbrot, pixel, (mandelbrot_width, height))
+        } else {
+            let pixel = (pixel.0 - mandelbrot_width as f64, pixel.1);
+            (Pane::Julia, pixel, (width - mandelbrot_width, height))
+        }
+    }
+
+    /// The width of the pane of Mandlebrot's set, the Julia set taking the rest of the window.
+    fn mandelbrot_width(state: &State, width: usize) -> usize {
+        match (state.julia_mode, state.split_view) {
+            (true, _) => 0,
+            (false, true) => width / 2,
+            (false, false) => width,
+        }
+    }
+
+    /// Whether the Julia set is shown, on its own or beside Mandlebrot's set.
+    fn shows_julia(state: &State) -> bool {
+        state.julia_mode || state.split_view
+    }
+
+    fn viewport_mut(self, state: &mut State) -> &mut Viewport {
+        match self {
+            Pane::Mandelbrot => &mut state.viewport,
+            Pane::Julia => &mut state.julia_viewport,
+        }
+    }
+
+    /// Marks the pane to be drawn again.
+    fn redraw(self, state: &mut State) {
+        match self {
+            Pane::Mandelbrot => state.redraw = true,
+            Pane::Julia => state.redraw_julia = true,
+        }
+    }
+}
+
+/// Returns the size of the window in physical pixels, the size of the image, so it is as sharp as
+/// the display on HiDPI displays.
+fn window_size(app: &App) -> (usize, usize) {
+    let (width, height) = app.main_window().inner_size_pixels();
+    (width as usize, height as usize)
+}
+
+/// Returns the physical pixel under the cursor, counted from the top-left corner of the window like
+/// the rows of the image, or `None` if the cursor is outside of the window.
+fn cursor_pixel(app: &App) -> Option<(f64, f64)> {
+    let rect = app.window_rect();
+    let cursor = app.mouse.position();
+    let scale = app.main_window().scale_factor() as f64;
+    rect.contains(cursor).then(|| {
+        (
+            (cursor.x + rect.w() / 2.0) as f64 * scale,
+            (rect.h() / 2.0 - cursor.y) as f64 * scale,
+        )
+    })
 }
 
 fn model(app: &App) -> Model {
@@ -57,34 +334,88 @@ fn model(app: &App) -> Model {
         .raw_event(raw_window_event)
         .key_pressed(key_pressed)
         .mouse_wheel(mouse_wheel)
+        .mouse_pressed(mouse_pressed)
         .build()
         .unwrap();
 
     let window = app.window(window_id).unwrap();
-    let (width, height) = window.rect().w_h();
-    let state = State {
+    let (width, height) = window.inner_size_pixels();
+    let mut state = State {
         redraw: true,
         continuous_redraw: false,
-        image: ImageBuffer::new(width as u32, height as u32),
+        gpu: true,
+        image: ImageBuffer::new(width, height),
+        image_options: ImageOptions::default(),
+        mandelbrot_array: Vec::new(),
+        julia_array: Vec::new(),
         delta: 0.50,
-        x_range: (-2.0, 0.50),
-        y_range: (-1.25, 1.25),
+        viewport: home_viewport(),
+        center: PreciseCenter::new(home_viewport().center()),
         zoom_speed: 0.001,
         shift_speed: 100,
         max_iterations: 100,
+        escape_radius: 2.0,
+        power: 2.0,
+        adaptive_aa: false,
+        aa_factor: 2,
+        aa_threshold: 0.2,
         select_in_mandelbrot: false,
         plot_trajectory: false,
-        noise: Perlin::new(),
+        show_stats: false,
+        stats: IterationStats::default(),
+        split_view: false,
+        julia_mode: false,
+        julia_viewport: julia_home_viewport(),
+        julia_c: (0.0, 0.0),
+        julia_locked: false,
+        redraw_julia: false,
+        seed: Perlin::DEFAULT_SEED,
         hue_scale: 0.0,
         noise_scale_x: 1.35,
         noise_scale_y: 0.75,
         noise_scale_z: 1.0,
+        coloring: Coloring::Equalized,
         saturation: 0.5,
+        cycle_colors: false,
+        cycle_speed: 0.1,
+        cycle_frames: 120,
+        hue_phase: 0.0,
+        style_history: Vec::new(),
+        recolor: false,
+        recorder: Recorder::new(),
+        show_build_info: false,
+        preset_library: PresetLibrary::new("mandelbrot"),
     };
 
     let egui = Egui::from_window(&window);
 
-    Model { egui, state }
+    // Listen to the parameters set remotely, the zoom being the order of magnitude of the
+    // magnification
+    let osc = OscServer::start(OSC_PORT)
+        .inspect(|osc| {
+            osc.register("zoom", 0.0..=14.0);
+            osc.register("max_iterations", 10.0..=10000.0);
+        })
+        .inspect_err(|e| eprintln!("{e}"))
+        .ok();
+
+    let gpu_pipeline = MandelbrotPipeline::new(Arc::clone(window.device_queue_pair()));
+
+    // Pick up where the sketch was before it was restarted by its supervisor
+    let watchdog = Watchdog::from_env("mandelbrot");
+    if let Some(settings) = watchdog.as_ref().and_then(Watchdog::restore::<Settings>) {
+        settings.apply(&mut state);
+    }
+
+    Model {
+        egui,
+        palette: CommandPalette::new(),
+        help: HotkeyHelp::new(),
+        state,
+        gpu_pipeline,
+        osc,
+        watchdog,
+    }
 }
 
 fn update_egui(ctx: FrameCtx, state: &mut State, app: &App) {
@@ -92,42 +423,150 @@ fn update_egui(ctx: FrameCtx, state: &mut State, app: &App) {
     egui::Window::new("Settings")
         .default_width(0.0)
         .show(&ctx, |ui| {
-            ui.label("Delta:");
-            ui.add(egui::Slider::new(&mut state.delta, 0.05..=1.0));
+            ui.add(LabelledSlider::new("Delta", &mut state.delta, 0.05..=1.0));
             // Round delta to be a divisor of 1.0
             state.delta = 1.0 / (1.0 / state.delta).round();
 
-            ui.label("Max iterations:");
-            ui.add(egui::Slider::new(&mut state.max_iterations, 10..=10000));
+            ui.add(LabelledSlider::new(
+                "Max iterations",
+                &mut state.max_iterations,
+                10..=10000,
+            ));
+            ui.add(
+                LabelledSlider::new("Escape radius", &mut state.escape_radius, 2.0..=1000.0)
+                    .logarithmic(),
+            );
+            ui.add(LabelledSlider::new("Power", &mut state.power, 1.0..=8.0));
 
-            ui.label("Zoom speed:");
-            ui.add(egui::Slider::new(&mut state.zoom_speed, 0.0001..=0.1));
+            ui.checkbox(&mut state.adaptive_aa, "Adaptive Anti-Aliasing");
 
-            ui.label("Shift speed:");
-            ui.add(egui::Slider::new(&mut state.shift_speed, 10..=100));
+            ui.add(LabelledSlider::new(
+                "AA factor",
+                &mut state.aa_factor,
+                2..=8,
+            ));
+            ui.add(LabelledSlider::new(
+                "AA threshold",
+                &mut state.aa_threshold,
+                0.01..=1.0,
+            ));
+            ui.add(LabelledSlider::new(
+                "Zoom speed",
+                &mut state.zoom_speed,
+                0.0001..=0.1,
+            ));
+            ui.add(LabelledSlider::new(
+                "Shift speed",
+                &mut state.shift_speed,
+                10..=100,
+            ));
 
             ui.separator();
 
-            ui.label("Hue scale:");
-            ui.add(egui::Slider::new(&mut state.hue_scale, 0.0..=1.0));
+            let coloring = ui.add(Dropdown::new(
+                "Coloring",
+                &mut state.coloring,
+                [
+                    (Coloring::Equalized, "Histogram equalized"),
+                    (Coloring::Smooth, "Smooth"),
+                ],
+            ));
+            // The smooth coloring sums other values than the escape iterations
+            if coloring.changed() {
+                state.redraw = true;
+            }
+
+            ui.add(LabelledSlider::new(
+                "Hue scale",
+                &mut state.hue_scale,
+                0.0..=1.0,
+            ));
+            ui.add(LabelledSlider::new(
+                "Saturation",
+                &mut state.saturation,
+                0.0..=1.0,
+            ));
+
+            ui.checkbox(&mut state.cycle_colors, "Cycle Palette");
 
-            ui.label("Saturation:");
-            ui.add(egui::Slider::new(&mut state.saturation, 0.0..=1.0));
+            ui.add(LabelledSlider::new(
+                "Cycle speed",
+                &mut state.cycle_speed,
+                0.01..=1.0,
+            ));
+            ui.add(LabelledSlider::new(
+                "Cycle frames",
+                &mut state.cycle_frames,
+                10..=600,
+            ));
 
-            ui.label("Noise scale x:");
-            ui.add(egui::Slider::new(&mut state.noise_scale_x, 0.50..=1.5));
+            if ui.button("Export Cycle").clicked() {
+                perform(app, state, Action::ExportCycle);
+            }
 
-            ui.label("Noise scale y:");
-            ui.add(egui::Slider::new(&mut state.noise_scale_y, 0.00..=0.75));
+            ui.add(LabelledSlider::new(
+                "Noise scale x",
+                &mut state.noise_scale_x,
+                0.50..=1.5,
+            ));
+            ui.add(LabelledSlider::new(
+                "Noise scale y",
+                &mut state.noise_scale_y,
+                0.00..=0.75,
+            ));
+            ui.add(LabelledSlider::new(
+                "Noise scale z",
+                &mut state.noise_scale_z,
+                0.00..=1.0,
+            ));
 
-            ui.label("Noise scale z:");
-            ui.add(egui::Slider::new(&mut state.noise_scale_z, 0.00..=1.0));
+            if ui.button("Randomize Look").clicked() {
+                perform(app, state, Action::RandomizeStyle);
+            }
+            style_history_ui(ui, state);
 
             ui.separator();
 
             ui.checkbox(&mut state.select_in_mandelbrot, "Select in Mandelbrot");
             ui.checkbox(&mut state.plot_trajectory, "Plot Trajectory");
             ui.checkbox(&mut state.continuous_redraw, "Continuous Redraw");
+            ui.checkbox(&mut state.gpu, "GPU Rendering")
+                .on_hover_text("Deep zooms and trajectories are still rendered on the CPU");
+            ui.checkbox(&mut state.show_stats, "Show Statistics");
+
+            let mut split_view = state.split_view;
+            if ui.checkbox(&mut split_view, "Julia Split View").changed() {
+                perform(app, state, Action::ToggleSplitView);
+            }
+            let mut julia_mode = state.julia_mode;
+            if ui.checkbox(&mut julia_mode, "Julia Mode").changed() {
+                perform(app, state, Action::ToggleJuliaMode);
+            }
+            if state.split_view && !state.julia_mode {
+                ui.checkbox(&mut state.julia_locked, "Lock Julia");
+            }
+
+            ui.separator();
+
+            match state.preset_library.show(ui) {
+                Some(PresetRequest::Save) => perform(app, state, Action::SaveSettings),
+                Some(PresetRequest::Load) => perform(app, state, Action::LoadSettings),
+                None => {}
+            }
+
+            ui.separator();
+
+            ui.add(UiScale);
+            state.recorder.show(ui);
+            state.image_options.show(ui);
+
+            ui.separator();
+
+            location_ui(ui, state, app);
+
+            if ui.button("Bookmark").clicked() {
+                perform(app, state, Action::SaveBookmark);
+            }
 
             let update = ui.button("Update").clicked();
             if update {
@@ -136,96 +575,643 @@ fn update_egui(ctx: FrameCtx, state: &mut State, app: &App) {
 
             let save = ui.button("Save").clicked();
             if save {
-                state
-                    .image
-                    .save(get_save_path(&app.exe_name().unwrap()))
-                    .unwrap();
+                perform(app, state, Action::SaveImage);
             }
         });
+
+    // Generate the statistics window
+    if state.show_stats {
+        egui::Window::new("Statistics")
+            .default_width(0.0)
+            .show(&ctx, |ui| stats_ui(ui, &state.stats, state.max_iterations));
+    }
+}
+
+/// Lists the randomized looks, the most recent first, so a good one can be recalled.
+fn style_history_ui(ui: &mut egui::Ui, state: &mut State) {
+    if state.style_history.is_empty() {
+        return;
+    }
+
+    ui.label("Look history:");
+    let current = style(state);
+    let mut recalled = None;
+    ui.horizontal_wrapped(|ui| {
+        state
+            .style_history
+            .iter()
+            .enumerate()
+            .rev()
+            .for_each(|(i, style)| {
+                let [x, y, z] = style.noise_scale;
+                let label = ui
+                    .selectable_label(*style == current, format!("#{}", i + 1))
+                    .on_hover_text(format!(
+                        "Seed: {}\nHue scale: {:.3}\nSaturation: {:.3}\nNoise scale: {x:.3}, {y:.3}, {z:.3}",
+                        style.seed, style.hue_scale, style.saturation
+                    ));
+                if label.clicked() {
+                    recalled = Some(*style);
+                }
+            });
+    });
+    if let Some(style) = recalled {
+        apply_style(state, &style);
+    }
+}
+
+/// Shows the coordinates of the view and of the cursor, with enough digits to find the same
+/// location in other fractal software.
+///
+/// The center is shown in arbitrary precision, with all the digits the pixels need.
+fn location_ui(ui: &mut egui::Ui, state: &State, app: &App) {
+    let viewport = &state.viewport;
+    let size = window_size(app);
+    let (width, height) = (Pane::mandelbrot_width(state, size.0), size.1);
+    let digits = viewport.coordinate_digits(width, height);
+    let format = |(re, im): (f64, f64)| format!("{re:+.digits$} {im:+.digits$}i");
+
+    let center_digits = viewport.pixel_digits(width, height);
+    let (center_re, center_im) = (&state.center.real, &state.center.imaginary);
+    let center = format!("{center_re:+.center_digits$} {center_im:+.center_digits$}i");
+    let magnification = viewport.magnification(&home_viewport());
+    ui.label(format!("Center: {center}"));
+    ui.label(format!("Magnification: {magnification:.3e}"));
+
+    if let Some(pixel) = cursor_pixel(app) {
+        let (pane, pixel, (pane_width, pane_height)) = Pane::at(state, pixel, size);
+        let c = match pane {
+            Pane::Mandelbrot => viewport.complex_at(pixel, pane_width, pane_height),
+            Pane::Julia => state
+                .julia_viewport
+                .complex_at(pixel, pane_width, pane_height),
+        };
+        ui.label(format!("Cursor: {}", format(c)));
+    }
+    if Pane::shows_julia(state) {
+        ui.label(format!("Julia: {}", format(state.julia_c)));
+    }
+
+    if viewport.is_precision_exhausted(width, height) {
+        match state.power == 2.0 {
+            true => ui.label("Deep zoom: the samples perturb a reference orbit"),
+            false => ui.colored_label(
+                ui.visuals().warn_fg_color,
+                "The zoom is past the precision of f64, deep zooms need a power of 2",
+            ),
+        };
+    }
+
+    if ui.button("Copy location").clicked() {
+        ui.output_mut(|output| {
+            output.copied_text = format!(
+                "re: {center_re:.center_digits$}\nim: {center_im:.center_digits$}\nzoom: \
+                 {magnification:e}"
+            )
+        });
+    }
+}
+
+/// Shows the distribution of the escape iterations of the current view.
+fn stats_ui(ui: &mut egui::Ui, stats: &IterationStats, max_iterations: usize) {
+    const BINS: usize = 64;
+
+    ui.label(format!("Samples: {}", stats.samples()));
+    ui.label(format!(
+        "Interior: {:.2}%",
+        stats.interior_fraction() * 100.0
+    ));
+    if let (Some(median), Some(high)) =
+        (stats.escape_percentile(0.5), stats.escape_percentile(0.99))
+    {
+        ui.label(format!("Median escape: {median} iterations"));
+        ui.label(format!("99% escape within: {high} iterations"));
+    }
+
+    ui.label(format!(
+        "Escape iterations, 0 to {max_iterations} (log scale):"
+    ));
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(256.0, 96.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+
+    // The counts span orders of magnitude, so the bars follow their logarithm
+    let histogram = stats.histogram(BINS);
+    let max = histogram.iter().copied().max().unwrap_or(0).max(1) as f32;
+    let width = rect.width() / BINS as f32;
+    histogram.iter().enumerate().for_each(|(i, &count)| {
+        let height = (count as f32).ln_1p() / max.ln_1p() * rect.height();
+        let left = rect.left() + i as f32 * width;
+        let bar = egui::Rect::from_min_max(
+            egui::pos2(left, rect.bottom() - height),
+            egui::pos2(left + width - 1.0, rect.bottom()),
+        );
+        painter.rect_filled(bar, 0.0, ui.visuals().selection.bg_fill);
+    });
 }
 
 fn update(app: &App, model: &mut Model, update: Update) {
+    if let Some(watchdog) = &mut model.watchdog {
+        watchdog.tick(|| Settings::from_state(&model.state));
+    }
+
     let egui = &mut model.egui;
     let state = &mut model.state;
-    let (width, height) = app.window_rect().w_h();
+    let (width, height) = window_size(app);
 
     egui.set_elapsed_time(update.since_start);
     let ctx = egui.begin_frame();
+    if let Some(action) = model
+        .palette
+        .show(&ctx, &commands(state.recorder.is_recording()))
+    {
+        perform(app, state, action);
+    }
+    model.help.show(&ctx, &describe(&bindings()));
     update_egui(ctx, state, app);
 
-    if state.redraw || state.continuous_redraw {
-        let mut mandelbrot_array = compute_mandelbrot_array(width as usize, height as usize, state);
-        recalibrate(&mut mandelbrot_array);
-        equalize(&mut mandelbrot_array, 0.0);
-        let image = to_image(mandelbrot_array, state);
-        state.image = image;
+    // Apply the parameters set remotely
+    if let Some(osc) = &model.osc {
+        if let Some(zoom) = osc.take("zoom") {
+            let magnification = state.viewport.magnification(&home_viewport());
+            state.viewport.zoom(magnification / 10f64.powf(zoom as f64));
+            state.redraw = true;
+        }
+        if let Some(max_iterations) = osc.take("max_iterations") {
+            state.max_iterations = max_iterations as usize;
+            state.redraw = true;
+        }
+    }
+
+    let gpu_pipeline = state.gpu.then_some(&model.gpu_pipeline);
+
+    // Follow the cursor with the Julia set until it is locked
+    if state.split_view
+        && !state.julia_locked
+        && let Some(pixel) = cursor_pixel(app)
+        && let (Pane::Mandelbrot, pixel, (pane_width, pane_height)) =
+            Pane::at(state, pixel, (width, height))
+    {
+        let c = state.viewport.complex_at(pixel, pane_width, pane_height);
+        if c != state.julia_c {
+            state.julia_c = c;
+            state.redraw_julia = true;
+        }
+    }
+
+    // The panes are rendered independently, the whole image is rendered again on resize
+    let resized = state.image.dimensions() != (width as u32, height as u32);
+    let mandelbrot_width = Pane::mandelbrot_width(state, width);
+    let mut recolor = std::mem::take(&mut state.recolor);
+    if state.redraw || state.continuous_redraw || resized {
+        if resized {
+            state.image = RgbaImage::new(width as u32, height as u32);
+        }
+        if !state.julia_mode {
+            let (array, stats) = render_pane(
+                mandelbrot_width,
+                height,
+                Fractal::Mandelbrot,
+                &state.viewport,
+                state,
+                gpu_pipeline,
+            );
+            state.mandelbrot_array = array;
+            state.stats = stats;
+        }
         state.redraw = false;
+        // The Julia set shares the settings of the render
+        state.redraw_julia = true;
+        recolor = true;
+    }
+    if Pane::shows_julia(state) && state.redraw_julia {
+        let fractal = Fractal::Julia(state.julia_c);
+        let (array, _) = render_pane(
+            width - mandelbrot_width,
+            height,
+            fractal,
+            &state.julia_viewport,
+            state,
+            gpu_pipeline,
+        );
+        state.julia_array = array;
+        state.redraw_julia = false;
+        recolor = true;
+    }
+
+    // Cycle the palette over the rendered arrays without computing them again
+    if state.cycle_colors {
+        state.hue_phase = (state.hue_phase + state.cycle_speed * update.since_last.secs()).fract();
+        recolor = true;
+    }
+    if recolor {
+        compose_image(state);
     }
 }
 
+/// Renders the recalibrated array of a fractal at the given size, along with the statistics of its
+/// samples.
+///
+/// The array is rendered by the compute shader when it is given, unless the trajectories are
+/// plotted or the viewport is too deep for single precision, and on the CPU if the shader fails.
+/// Past the precision of f64, the samples of Mandlebrot's set are rendered as perturbations of the
+/// orbit of the precise center.
+fn render_pane(
+    width: usize,
+    height: usize,
+    fractal: Fractal,
+    viewport: &Viewport,
+    state: &State,
+    gpu_pipeline: Option<&MandelbrotPipeline>,
+) -> (Vec<Vec<f64>>, IterationStats) {
+    let samples = (1.0 / state.delta).round() as usize;
+    let reference = (fractal == Fractal::Mandelbrot
+        && state.power == 2.0
+        && !state.plot_trajectory
+        && viewport.is_precision_exhausted(width, height))
+    .then(|| ReferenceOrbit::mandelbrot(&state.center, &iteration_settings(state)));
+    let rendered = match gpu_pipeline {
+        Some(pipeline)
+            if !state.plot_trajectory
+                && MandelbrotPipeline::is_precise_enough(viewport, width, samples) =>
+        {
+            let settings = iteration_settings(state);
+            pipeline
+                .render(
+                    width,
+                    height,
+                    fractal,
+                    viewport,
+                    &settings,
+                    samples,
+                    state.select_in_mandelbrot,
+                    state.coloring,
+                )
+                .inspect_err(|e| eprintln!("{e}, rendering on the CPU instead"))
+                .ok()
+        }
+        _ => None,
+    };
+    let (mut array, stats) = match rendered {
+        Some((mut array, stats)) => {
+            if state.adaptive_aa {
+                supersample_edges(&mut array, width, height, fractal, viewport, state, None);
+            }
+            (array, stats)
+        }
+        None => {
+            compute_mandelbrot_array(width, height, fractal, viewport, state, reference.as_ref())
+        }
+    };
+    recalibrate(&mut array);
+    (array, stats)
+}
+
+/// Colors the rendered arrays of the panes into the image.
+fn compose_image(state: &mut State) {
+    let x = match state.julia_mode {
+        true => 0,
+        false if state.mandelbrot_array.is_empty() => return,
+        false => {
+            let image = to_image(&state.mandelbrot_array, state);
+            image::imageops::replace(&mut state.image, &image, 0, 0);
+            state.mandelbrot_array[0].len() as u32
+        }
+    };
+    if Pane::shows_julia(state) && !state.julia_array.is_empty() {
+        let image = to_image(&state.julia_array, state);
+        image::imageops::replace(&mut state.image, &image, x, 0);
+    }
+}
+
+/// Colors the panes like [`compose_image`], keeping the linear channels in floating point for the
+/// saves of more than 8 bits.
+fn compose_linear_image(state: &State) -> LinearImage {
+    let mut image = LinearImage::new(state.image.width(), state.image.height());
+    let x = match state.julia_mode {
+        true => 0,
+        false if state.mandelbrot_array.is_empty() => return image,
+        false => {
+            let pane = to_linear_image(&state.mandelbrot_array, state);
+            image::imageops::replace(&mut image, &pane, 0, 0);
+            state.mandelbrot_array[0].len() as u32
+        }
+    };
+    if Pane::shows_julia(state) && !state.julia_array.is_empty() {
+        let pane = to_linear_image(&state.julia_array, state);
+        image::imageops::replace(&mut image, &pane, x, 0);
+    }
+    image
+}
+
 fn raw_window_event(_app: &App, model: &mut Model, event: &nannou::winit::event::WindowEvent) {
     // Let egui handle things like keyboard and mouse input.
     model.egui.handle_raw_event(event);
 }
 
-fn key_pressed(app: &App, model: &mut Model, key: Key) {
-    let state = &mut model.state;
-    match key {
-        Key::Left => {
-            let shift_x = get_shift_speed(state.x_range, state.shift_speed);
-            state.x_range = shift(state.x_range, -shift_x);
+/// The actions that can be run from the keyboard or the command palette.
+#[derive(Clone, Copy)]
+enum Action {
+    ShiftLeft,
+    ShiftRight,
+    ShiftUp,
+    ShiftDown,
+    ZoomIn,
+    ZoomOut,
+    ToggleSplitView,
+    ToggleJuliaLock,
+    ToggleJuliaMode,
+    ToggleCycle,
+    ToggleGpu,
+    ExportCycle,
+    RandomizeStyle,
+    SaveBookmark,
+    SaveSettings,
+    LoadSettings,
+    Redraw,
+    ToggleRecording,
+    SaveImage,
+    ToggleBuildInfo,
+    Quit,
+}
+
+fn commands(recording: bool) -> Vec<Command<Action>> {
+    vec![
+        Command::new("Shift left", Action::ShiftLeft),
+        Command::new("Shift right", Action::ShiftRight),
+        Command::new("Shift up", Action::ShiftUp),
+        Command::new("Shift down", Action::ShiftDown),
+        Command::new("Zoom in", Action::ZoomIn),
+        Command::new("Zoom out", Action::ZoomOut),
+        Command::new("Toggle Julia split view", Action::ToggleSplitView),
+        Command::new("Toggle Julia lock", Action::ToggleJuliaLock),
+        Command::new("Toggle Julia mode", Action::ToggleJuliaMode),
+        Command::new("Toggle palette cycling", Action::ToggleCycle),
+        Command::new("Toggle GPU rendering", Action::ToggleGpu),
+        Command::new("Export palette cycle", Action::ExportCycle),
+        Command::new("Randomize look", Action::RandomizeStyle),
+        Command::new("Bookmark location", Action::SaveBookmark),
+        Command::new("Save settings preset", Action::SaveSettings),
+        Command::new("Load settings preset", Action::LoadSettings),
+        Command::new("Redraw", Action::Redraw),
+        match recording {
+            false => Command::new("Start recording", Action::ToggleRecording),
+            true => Command::new("Stop recording", Action::ToggleRecording),
+        },
+        Command::new("Save image", Action::SaveImage),
+        Command::new("Toggle build info", Action::ToggleBuildInfo),
+        Command::new("Quit", Action::Quit),
+    ]
+}
+
+fn perform(app: &App, state: &mut State, action: Action) {
+    match action {
+        Action::ShiftLeft => {
+            let (shift_x, _) = state.viewport.shift_speed(state.shift_speed);
+            shift_view(state, (-shift_x, 0.0));
             state.redraw = true;
         }
-        Key::Right => {
-            let shift_x = get_shift_speed(state.x_range, state.shift_speed);
-            state.x_range = shift(state.x_range, shift_x);
+        Action::ShiftRight => {
+            let (shift_x, _) = state.viewport.shift_speed(state.shift_speed);
+            shift_view(state, (shift_x, 0.0));
             state.redraw = true;
         }
-        Key::Up => {
-            let shift_y = get_shift_speed(state.y_range, state.shift_speed);
-            state.y_range = shift(state.y_range, -shift_y);
+        Action::ShiftUp => {
+            let (_, shift_y) = state.viewport.shift_speed(state.shift_speed);
+            shift_view(state, (0.0, -shift_y));
             state.redraw = true;
         }
-        Key::Down => {
-            let shift_y = get_shift_speed(state.y_range, state.shift_speed);
-            state.y_range = shift(state.y_range, shift_y);
+        Action::ShiftDown => {
+            let (_, shift_y) = state.viewport.shift_speed(state.shift_speed);
+            shift_view(state, (0.0, shift_y));
             state.redraw = true;
         }
-        Key::Plus | Key::Equals => {
+        Action::ZoomIn => {
             let zoom_factor = 1.0 - 10.0 * state.zoom_speed;
-            (state.x_range, state.y_range) = zoom(state.x_range, state.y_range, zoom_factor);
+            state.viewport.zoom(zoom_factor);
             state.redraw = true;
         }
-        Key::Minus => {
+        Action::ZoomOut => {
             let zoom_factor = 1.0 + 10.0 * state.zoom_speed;
-            (state.x_range, state.y_range) = zoom(state.x_range, state.y_range, zoom_factor);
+            state.viewport.zoom(zoom_factor);
+            state.redraw = true;
+        }
+        Action::ToggleSplitView => {
+            state.split_view = !state.split_view;
+            // Resize the window so each pane keeps the aspect ratio of the viewports
+            let window = app.main_window();
+            let (width, height) = window.inner_size_points();
+            let width = if state.split_view {
+                width * 2.0
+            } else {
+                width / 2.0
+            };
+            window.set_inner_size_points(width, height);
+            state.redraw = true;
+        }
+        Action::ToggleJuliaLock => state.julia_locked = !state.julia_locked,
+        Action::ToggleJuliaMode => {
+            state.julia_mode = !state.julia_mode;
             state.redraw = true;
         }
-        Key::Q => app.quit(),
-        Key::S => model
-            .state
-            .image
-            .save(get_save_path(&app.exe_name().unwrap()))
-            .unwrap(),
-        Key::Return => model.state.redraw = true,
-        _other_key => {}
+        Action::ToggleCycle => state.cycle_colors = !state.cycle_colors,
+        Action::ToggleGpu => {
+            state.gpu = !state.gpu;
+            state.redraw = true;
+        }
+        Action::ExportCycle => {
+            // Save one full turn of the palette, which loops seamlessly
+            let directory = get_save_directory(&app.exe_name().unwrap());
+            if let Err(e) = std::fs::create_dir_all(&directory) {
+                eprintln!("Failed to create {directory}: {e}");
+                return;
+            }
+            let phase = state.hue_phase;
+            (0..state.cycle_frames).for_each(|i| {
+                state.hue_phase = (phase + i as f64 / state.cycle_frames as f64).fract();
+                compose_image(state);
+                let path = format!("{directory}/frame_{i:05}.png");
+                if let Err(e) = build_info::save_png(&state.image, &path, None) {
+                    eprintln!("Failed to save {path}: {e}");
+                }
+            });
+            state.hue_phase = phase;
+            compose_image(state);
+        }
+        Action::RandomizeStyle => {
+            // Keep the current look too, in case it was tuned by hand
+            let current = style(state);
+            if state.style_history.last() != Some(&current) {
+                state.style_history.push(current);
+            }
+            let style = Style::random();
+            state.style_history.push(style);
+            let excess = state
+                .style_history
+                .len()
+                .saturating_sub(STYLE_HISTORY_LENGTH);
+            state.style_history.drain(..excess);
+            apply_style(state, &style);
+        }
+        Action::SaveBookmark => {
+            let bookmark = Bookmark {
+                viewport: state.viewport,
+                iteration: iteration_settings(state),
+                style: style(state),
+            };
+            match append_bookmark(BOOKMARKS_PATH, &bookmark) {
+                Ok(()) => println!("Saved bookmark to: {BOOKMARKS_PATH}"),
+                Err(e) => eprintln!("{e}"),
+            }
+        }
+        Action::SaveSettings => {
+            let settings = Settings::from_state(state);
+            match state.preset_library.save(&settings) {
+                Ok(path) => println!("Saving preset to: {}", path.display()),
+                Err(e) => eprintln!("{e}"),
+            }
+        }
+        Action::LoadSettings => match state.preset_library.load::<Settings>() {
+            Ok(settings) => settings.apply(state),
+            Err(e) => eprintln!("{e}: {}", state.preset_library.name),
+        },
+        Action::Redraw => state.redraw = true,
+        Action::ToggleRecording => state
+            .recorder
+            .toggle(&app.exe_name().unwrap(), app.main_window().device()),
+        Action::SaveImage if state.image_options.is_high_precision() => {
+            let options = state.image_options;
+            let path = options.save_path(&app.exe_name().unwrap());
+            let parameters = build_info::parameters(&Settings::from_state(state));
+            if let Err(e) = options.save(&compose_linear_image(state), &path, parameters.as_deref())
+            {
+                eprintln!("Failed to save {path}: {e}");
+            }
+        }
+        Action::SaveImage => state.recorder.save_image(
+            &state.image,
+            &get_save_path(&app.exe_name().unwrap()),
+            build_info::parameters(&Settings::from_state(state)).as_deref(),
+        ),
+        Action::ToggleBuildInfo => state.show_build_info = !state.show_build_info,
+        Action::Quit => {
+            state.recorder.stop(app.main_window().device());
+            app.quit();
+        }
     }
 }
 
-fn mouse_wheel(_app: &App, model: &mut Model, delta: MouseScrollDelta, _phase: TouchPhase) {
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    if key == PALETTE_KEY {
+        model.palette.toggle();
+        return;
+    }
+    // The keys are used to type in the palette
+    if model.palette.is_open() {
+        return;
+    }
+
+    if key == HELP_KEY {
+        model.help.toggle();
+        return;
+    }
+
+    if let Some(action) = action_for(&bindings(), key) {
+        perform(app, &mut model.state, action);
+    }
+}
+
+/// The hotkeys of the sketch, listed by the help overlay.
+fn bindings() -> Vec<Binding<Action>> {
+    vec![
+        Binding::new(
+            &[RECORD_KEY],
+            "Start or stop recording the frames",
+            Action::ToggleRecording,
+        ),
+        Binding::new(&[Key::Left], "Move the viewport left", Action::ShiftLeft),
+        Binding::new(&[Key::Right], "Move the viewport right", Action::ShiftRight),
+        Binding::new(&[Key::Up], "Move the viewport up", Action::ShiftUp),
+        Binding::new(&[Key::Down], "Move the viewport down", Action::ShiftDown),
+        Binding::new(&[Key::Plus, Key::Equals], "Zoom in", Action::ZoomIn),
+        Binding::new(&[Key::Minus], "Zoom out", Action::ZoomOut),
+        Binding::new(
+            &[Key::J],
+            "Toggle the Julia split view",
+            Action::ToggleSplitView,
+        ),
+        Binding::new(
+            &[Key::L],
+            "Lock or unlock the Julia set to a point",
+            Action::ToggleJuliaLock,
+        ),
+        Binding::new(&[Key::M], "Toggle the Julia mode", Action::ToggleJuliaMode),
+        Binding::new(&[Key::C], "Toggle the palette cycling", Action::ToggleCycle),
+        Binding::new(&[Key::G], "Toggle the GPU rendering", Action::ToggleGpu),
+        Binding::new(
+            &[Key::R],
+            "Randomize the look of the coloring",
+            Action::RandomizeStyle,
+        ),
+        Binding::new(
+            &[Key::B],
+            "Bookmark the current location",
+            Action::SaveBookmark,
+        ),
+        Binding::new(&[Key::S], "Save the current frame", Action::SaveImage),
+        Binding::new(
+            &[BUILD_INFO_KEY],
+            "Show or hide the build in a corner",
+            Action::ToggleBuildInfo,
+        ),
+        Binding::new(&[Key::Return], "Force redraw", Action::Redraw),
+        Binding::new(&[Key::Q], "Quit", Action::Quit),
+    ]
+}
+
+fn mouse_wheel(app: &App, model: &mut Model, delta: MouseScrollDelta, _phase: TouchPhase) {
     let state = &mut model.state;
 
-    match delta {
-        MouseScrollDelta::LineDelta(_, y) => {
-            let zoom_factor = 1.0 + y as f64 * state.zoom_speed;
-            (state.x_range, state.y_range) = zoom(state.x_range, state.y_range, zoom_factor);
-        }
-        MouseScrollDelta::PixelDelta(pos) => {
-            let zoom_factor = 1.0 + pos.y * state.zoom_speed;
-            (state.x_range, state.y_range) = zoom(state.x_range, state.y_range, zoom_factor);
+    let zoom_factor = match delta {
+        MouseScrollDelta::LineDelta(_, y) => 1.0 + y as f64 * state.zoom_speed,
+        MouseScrollDelta::PixelDelta(pos) => 1.0 + pos.y * state.zoom_speed,
+    };
+
+    // Zoom the pane under the cursor around the point of the set under the cursor
+    let Some(pixel) = cursor_pixel(app) else {
+        return;
+    };
+    let (pane, pixel, (pane_width, pane_height)) = Pane::at(state, pixel, window_size(app));
+    match pane {
+        Pane::Mandelbrot => zoom_view_at(state, pixel, (pane_width, pane_height), zoom_factor),
+        Pane::Julia => {
+            let viewport = pane.viewport_mut(state);
+            let c = viewport.complex_at(pixel, pane_width, pane_height);
+            viewport.zoom_at(c, zoom_factor);
         }
     }
-    model.state.redraw = true;
+    pane.redraw(state);
+}
+
+fn mouse_pressed(app: &App, model: &mut Model, button: MouseButton) {
+    // The clicks on the settings are for egui
+    if button != MouseButton::Left || model.egui.ctx().is_pointer_over_area() {
+        return;
+    }
+
+    // Clicking Mandlebrot's set locks the Julia set to the clicked point, or unlocks it, and
+    // ctrl-clicking picks the point of the Julia set in any view
+    let state = &mut model.state;
+    let picking = app.keys.mods.ctrl();
+    if (state.split_view || picking)
+        && let Some(pixel) = cursor_pixel(app)
+        && let (Pane::Mandelbrot, pixel, (pane_width, pane_height)) =
+            Pane::at(state, pixel, window_size(app))
+    {
+        state.julia_locked = picking || !state.julia_locked;
+        state.julia_c = state.viewport.complex_at(pixel, pane_width, pane_height);
+        state.redraw_julia = true;
+    }
 }
 
 fn view(app: &App, model: &Model, frame: Frame) {
@@ -233,18 +1219,56 @@ fn view(app: &App, model: &Model, frame: Frame) {
     let draw = app.draw();
     let state = &model.state;
 
+    // The image is in physical pixels, drawn over the whole window in points
     let texture = create_texture(app.main_window(), state.image.clone());
-    draw.texture(&texture);
+    draw.texture(&texture).wh(app.window_rect().wh());
+
+    if state.show_build_info {
+        build_info::draw_overlay(&draw, app.window_rect());
+    }
 
     draw.to_frame(app, &frame).unwrap();
+    state.recorder.capture(&frame);
     model.egui.draw_to_frame(&frame).unwrap();
 }
 
-fn compute_mandelbrot_array(width: usize, height: usize, state: &State) -> Vec<Vec<f64>> {
+/// Iterates the sequence of a sample of a pane, as a perturbation of the reference orbit when one
+/// is given, in which case the path of the sequence is not recorded.
+///
+/// Returns the same tuple as [`Fractal::orbit`].
+fn sample(
+    pixel: (f64, f64),
+    width: usize,
+    height: usize,
+    fractal: Fractal,
+    viewport: &Viewport,
+    settings: &IterationSettings,
+    reference: Option<&ReferenceOrbit>,
+) -> (Option<usize>, f64, Vec<(usize, usize)>) {
+    match reference {
+        Some(reference) => {
+            let delta_c = viewport.offset_at(pixel, width, height);
+            let (escape, smooth) = reference.smooth_escape(delta_c, settings);
+            (escape, smooth, Vec::new())
+        }
+        None => fractal.orbit(pixel, width, height, viewport, settings),
+    }
+}
+
+fn compute_mandelbrot_array(
+    width: usize,
+    height: usize,
+    fractal: Fractal,
+    viewport: &Viewport,
+    state: &State,
+    reference: Option<&ReferenceOrbit>,
+) -> (Vec<Vec<f64>>, IterationStats) {
     let delta = state.delta;
-    let max_iterations = state.max_iterations;
+    let settings = iteration_settings(state);
+    let max_iterations = settings.max_iterations;
     let select_in_mandelbrot = state.select_in_mandelbrot;
     let plot_trajectory = state.plot_trajectory;
+    let coloring = state.coloring;
 
     // Display sub-fractal of mandelbrot set
     let iterations_per_row = (width as f64 / delta) as u64;
@@ -260,6 +1284,7 @@ fn compute_mandelbrot_array(width: usize, height: usize, state: &State) -> Vec<V
 
     // Create a 2D array to store the pixel values
     let array = Mutex::new(vec![vec![0.0; width]; height]);
+    let stats = Mutex::new(IterationStats::new(max_iterations));
 
     // Iterate over the rows of the image
     (0..(height as f64 / delta) as usize)
@@ -268,6 +1293,7 @@ fn compute_mandelbrot_array(width: usize, height: usize, state: &State) -> Vec<V
             // Store the pixel values for the visited pixels
             // This prvents locking the array for each pixel
             let mut pixel_array = Vec::new();
+            let mut row_stats = IterationStats::new(max_iterations);
 
             // Iterate over the columns of the row
             (0..(width as f64 / delta) as usize).for_each(|x| {
@@ -275,15 +1301,16 @@ fn compute_mandelbrot_array(width: usize, height: usize, state: &State) -> Vec<V
                 let y = y as f64 * delta;
 
                 // Store list of x,y coordinates at each iteration
-                let (in_mandelbrot, pixels) = is_in_mandelbrot(
-                    x,
-                    y,
+                let (in_mandelbrot, smooth, pixels) = sample(
+                    (x, y),
                     width,
                     height,
-                    state.x_range,
-                    state.y_range,
-                    max_iterations,
+                    fractal,
+                    viewport,
+                    &settings,
+                    reference,
                 );
+                row_stats.record(in_mandelbrot);
 
                 // Skip the pixel or not
                 if in_mandelbrot.is_none() == select_in_mandelbrot {
@@ -292,15 +1319,15 @@ fn compute_mandelbrot_array(width: usize, height: usize, state: &State) -> Vec<V
                         pixel_array.append(
                             &mut pixels
                                 .into_iter()
-                                .map(|(x, y)| (x, y, in_mandelbrot.unwrap_or(1)))
+                                .map(|(x, y)| (x, y, in_mandelbrot.unwrap_or(1) as f64))
                                 .collect(),
                         );
                     } else {
-                        pixel_array.push((
-                            x as usize,
-                            y as usize,
-                            in_mandelbrot.unwrap_or(max_iterations),
-                        ));
+                        let value = match coloring {
+                            Coloring::Equalized => in_mandelbrot.unwrap_or(max_iterations) as f64,
+                            Coloring::Smooth => smooth,
+                        };
+                        pixel_array.push((x as usize, y as usize, value));
                     }
                 }
             });
@@ -308,9 +1335,12 @@ fn compute_mandelbrot_array(width: usize, height: usize, state: &State) -> Vec<V
             // Increment the pixel value for the visited pixels
             let mut array_lock = array.lock().unwrap();
             pixel_array.into_iter().for_each(|(x, y, v)| {
-                array_lock[y][x] += v as f64;
+                array_lock[y][x] += v;
             });
 
+            drop(array_lock);
+            stats.lock().unwrap().merge(&row_stats);
+
             // Update the progress bar
             pb.inc(iterations_per_row);
         });
@@ -318,38 +1348,373 @@ fn compute_mandelbrot_array(width: usize, height: usize, state: &State) -> Vec<V
     // Finish the progress bar
     pb.finish_with_message("Rendered");
 
+    // Supersample the edges, the trajectories have no edges to smooth
+    let mut array = array.into_inner().unwrap();
+    if state.adaptive_aa && !plot_trajectory {
+        supersample_edges(
+            &mut array, width, height, fractal, viewport, state, reference,
+        );
+    }
+
     // Return the array
-    let array_lock = array.lock().unwrap();
-    array_lock.clone()
+    (array, stats.into_inner().unwrap())
 }
 
-fn to_image(array: Vec<Vec<f64>>, state: &mut State) -> ImageBuffer<image::Rgba<u8>, Vec<u8>> {
-    let width = array[0].len() as u32;
-    let height = array.len() as u32;
-    let height_half = height as f64 / 2.0;
-    let noise = &mut state.noise;
+/// Recomputes the pixels with a high gradient with `aa_factor` times more samples per axis.
+///
+/// The refined values are scaled to the number of samples of the other pixels so they blend in.
+fn supersample_edges(
+    array: &mut [Vec<f64>],
+    width: usize,
+    height: usize,
+    fractal: Fractal,
+    viewport: &Viewport,
+    state: &State,
+    reference: Option<&ReferenceOrbit>,
+) {
+    let edges = edge_pixels(array, state.aa_threshold);
+    let samples = (1.0 / state.delta).round() as usize;
+    let fine_samples = samples * state.aa_factor;
+    let spacing = 1.0 / fine_samples as f64;
+    let weight = (samples * samples) as f64 / (fine_samples * fine_samples) as f64;
+    let settings = iteration_settings(state);
 
-    let mut image: RgbaImage = RgbaImage::new(width, height);
-    image
-        .enumerate_pixels_mut()
-        .par_bridge()
-        .for_each(|(x, y, pixel)| {
-            let symmetry_y = (y as f64 / height_half - 1.0).abs();
-
-            let lightness = array[y as usize][x as usize] / 255.0;
-            let hue = (lightness * state.hue_scale
-                + noise.get([
-                    lightness * state.noise_scale_z,
-                    x as f64 / width as f64 * state.noise_scale_x,
-                    symmetry_y * state.noise_scale_y,
-                ])) as f32;
-
-            let (r, g, b) = hsl(hue, state.saturation, lightness as f32)
-                .into_rgb::<Srgb>()
-                .into_format::<u8>()
-                .into_components();
-
-            *pixel = image::Rgba([r, g, b, 255])
-        });
-    image
+    let values: Vec<f64> = edges
+        .par_iter()
+        .map(|&(x, y)| {
+            let mut sum = 0.0;
+            (0..fine_samples).for_each(|i| {
+                (0..fine_samples).for_each(|j| {
+                    let pixel = (x as f64 + j as f64 * spacing, y as f64 + i as f64 * spacing);
+                    let (in_mandelbrot, smooth, _) = sample(
+                        pixel, width, height, fractal, viewport, &settings, reference,
+                    );
+                    if in_mandelbrot.is_none() == state.select_in_mandelbrot {
+                        sum += match state.coloring {
+                            Coloring::Equalized => {
+                                in_mandelbrot.unwrap_or(state.max_iterations) as f64
+                            }
+                            Coloring::Smooth => smooth,
+                        };
+                    }
+                });
+            });
+            sum * weight
+        })
+        .collect();
+
+    edges
+        .into_iter()
+        .zip(values)
+        .for_each(|((x, y), value)| array[y][x] = value);
+}
+
+/// Colors a recalibrated array of a pane, equalizing its histogram first unless it holds the
+/// continuous escape values of the smooth coloring.
+fn to_image(array: &[Vec<f64>], state: &State) -> ImageBuffer<image::Rgba<u8>, Vec<u8>> {
+    style(state).colorize(&lightness(array, state), state.hue_phase)
+}
+
+fn to_linear_image(array: &[Vec<f64>], state: &State) -> LinearImage {
+    style(state).colorize_linear(&lightness(array, state), state.hue_phase)
+}
+
+/// The lightness of the pixels of a pane, equalized with the coloring of the same name.
+fn lightness<'a>(array: &'a [Vec<f64>], state: &State) -> Cow<'a, [Vec<f64>]> {
+    match state.coloring {
+        Coloring::Equalized => {
+            let mut array = array.to_vec();
+            equalize(&mut array, 0.0);
+            Cow::Owned(array)
+        }
+        Coloring::Smooth => Cow::Borrowed(array),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ift6251::utils::golden::{Tolerance, assert_golden, compare, from_luma};
+
+    use super::*;
+
+    const WIDTH: usize = 64;
+    const HEIGHT: usize = 64;
+
+    /// The default settings of the sketch, at a size small enough to render quickly.
+    fn state() -> State {
+        State {
+            redraw: true,
+            continuous_redraw: false,
+            gpu: false,
+            image: ImageBuffer::new(WIDTH as u32, HEIGHT as u32),
+            image_options: ImageOptions::default(),
+            mandelbrot_array: Vec::new(),
+            julia_array: Vec::new(),
+            delta: 0.5,
+            viewport: home_viewport(),
+            center: PreciseCenter::new(home_viewport().center()),
+            zoom_speed: 0.001,
+            shift_speed: 100,
+            max_iterations: 100,
+            escape_radius: 2.0,
+            power: 2.0,
+            adaptive_aa: false,
+            aa_factor: 2,
+            aa_threshold: 0.2,
+            select_in_mandelbrot: false,
+            plot_trajectory: false,
+            show_stats: false,
+            stats: IterationStats::default(),
+            split_view: false,
+            julia_mode: false,
+            julia_viewport: julia_home_viewport(),
+            julia_c: (0.0, 0.0),
+            julia_locked: false,
+            redraw_julia: false,
+            seed: Perlin::DEFAULT_SEED,
+            hue_scale: 0.0,
+            noise_scale_x: 1.35,
+            noise_scale_y: 0.75,
+            noise_scale_z: 1.0,
+            coloring: Coloring::Equalized,
+            saturation: 0.5,
+            cycle_colors: false,
+            cycle_speed: 0.1,
+            cycle_frames: 120,
+            hue_phase: 0.0,
+            style_history: Vec::new(),
+            recolor: false,
+            recorder: Recorder::new(),
+            show_build_info: false,
+            preset_library: PresetLibrary::new("mandelbrot"),
+        }
+    }
+
+    /// Renders the array of the set and recalibrates it like the sketch does.
+    fn recalibrated(state: &State) -> Vec<Vec<f64>> {
+        let (mut array, _) = compute_mandelbrot_array(
+            WIDTH,
+            HEIGHT,
+            Fractal::Mandelbrot,
+            &state.viewport,
+            state,
+            None,
+        );
+        recalibrate(&mut array);
+        array
+    }
+
+    /// Renders the array of the set and equalizes it like the sketch does before coloring it.
+    fn render(state: &State) -> Vec<Vec<f64>> {
+        let mut array = recalibrated(state);
+        equalize(&mut array, 0.0);
+        array
+    }
+
+    #[test]
+    fn escape_time_matches_golden() {
+        let array = render(&state());
+        assert_golden(
+            "mandelbrot_escape_time",
+            &from_luma(&array),
+            Tolerance::EXACT,
+        );
+    }
+
+    #[test]
+    fn trajectories_match_golden() {
+        let state = State {
+            select_in_mandelbrot: true,
+            plot_trajectory: true,
+            ..state()
+        };
+        let array = render(&state);
+        assert_golden(
+            "mandelbrot_trajectories",
+            &from_luma(&array),
+            Tolerance::EXACT,
+        );
+    }
+
+    #[test]
+    fn coloring_matches_golden() {
+        let state = State {
+            hue_scale: 0.5,
+            ..state()
+        };
+        let array = recalibrated(&state);
+        let image = to_image(&array, &state);
+        // The hues go through floating point noise, so allow for tiny differences
+        assert_golden("mandelbrot_coloring", &image, Tolerance::default());
+    }
+
+    #[test]
+    fn palette_cycle_loops() {
+        let mut state = State {
+            hue_scale: 0.5,
+            ..state()
+        };
+        let array = recalibrated(&state);
+        let start = to_image(&array, &state);
+        state.hue_phase = 0.5;
+        let halfway = to_image(&array, &state);
+        state.hue_phase = 1.0;
+        let end = to_image(&array, &state);
+
+        let tolerance = Tolerance::default();
+        let differs = |a, b| {
+            !compare(a, b, tolerance.threshold)
+                .unwrap()
+                .passes(tolerance)
+        };
+        assert!(differs(&start, &halfway));
+        assert!(!differs(&start, &end));
+    }
+
+    #[test]
+    fn recalled_style_colors_alike() {
+        let mut state = state();
+        let array = recalibrated(&state);
+        let style = Style::random();
+        apply_style(&mut state, &style);
+        let original = to_image(&array, &state);
+
+        apply_style(&mut state, &Style::random());
+        apply_style(&mut state, &style);
+        assert!(super::style(&state) == style);
+        assert_eq!(original, to_image(&array, &state));
+    }
+
+    #[test]
+    fn presets_restore_the_render() {
+        let saved = State {
+            max_iterations: 500,
+            power: 3.0,
+            adaptive_aa: true,
+            ..state()
+        };
+        let json = serde_json::to_string(&Settings::from_state(&saved)).unwrap();
+
+        let mut loaded = state();
+        apply_style(&mut loaded, &Style::random());
+        serde_json::from_str::<Settings>(&json)
+            .unwrap()
+            .apply(&mut loaded);
+        assert_eq!(render(&loaded), render(&saved));
+        assert!(super::style(&loaded) == super::style(&saved));
+    }
+
+    #[test]
+    fn smooth_coloring_removes_the_bands() {
+        let banded = |coloring| State {
+            max_iterations: 20,
+            escape_radius: 1000.0,
+            coloring,
+            ..state()
+        };
+        let shades = |state: &State| {
+            let mut shades: Vec<u64> = recalibrated(state)
+                .iter()
+                .flatten()
+                .map(|value| value.to_bits())
+                .collect();
+            shades.sort();
+            shades.dedup();
+            shades.len()
+        };
+
+        // The whole iterations only give a handful of shades, the continuous values many more
+        let shades = (
+            shades(&banded(Coloring::Equalized)),
+            shades(&banded(Coloring::Smooth)),
+        );
+        assert!(shades.1 > shades.0 * 10, "{shades:?}");
+    }
+
+    #[test]
+    fn adaptive_aa_matches_golden() {
+        let state = State {
+            adaptive_aa: true,
+            ..state()
+        };
+        let array = render(&state);
+        assert_golden(
+            "mandelbrot_adaptive_aa",
+            &from_luma(&array),
+            Tolerance::EXACT,
+        );
+    }
+
+    #[test]
+    fn perturbation_matches_the_direct_render() {
+        let state = state();
+        let settings = iteration_settings(&state);
+        let reference = ReferenceOrbit::mandelbrot(&state.center, &settings);
+        let render = |reference| {
+            let viewport = &state.viewport;
+            compute_mandelbrot_array(
+                WIDTH,
+                HEIGHT,
+                Fractal::Mandelbrot,
+                viewport,
+                &state,
+                reference,
+            )
+            .0
+        };
+
+        // The rounding differs between the methods on the boundary of the set
+        let (direct, perturbed) = (render(None), render(Some(&reference)));
+        let matching = direct
+            .iter()
+            .flatten()
+            .zip(perturbed.iter().flatten())
+            .filter(|(direct, perturbed)| direct == perturbed)
+            .count();
+        assert!(
+            matching >= WIDTH * HEIGHT * 95 / 100,
+            "{matching} pixels match"
+        );
+    }
+
+    #[test]
+    fn deep_zooms_keep_the_point_under_the_cursor() {
+        let mut state = state();
+        let pixel = (16.0, 40.0);
+        let offset = state.viewport.offset_at(pixel, WIDTH, HEIGHT);
+        (0..60).for_each(|_| zoom_view_at(&mut state, pixel, (WIDTH, HEIGHT), 0.5));
+        assert!(state.viewport.is_precision_exhausted(WIDTH, HEIGHT));
+
+        // The center is a fraction of the offset away from the point that was under the cursor
+        let mut expected = PreciseCenter::new(home_viewport().center());
+        expected.shift(offset);
+        expected.shift((-offset.0 * 0.5f64.powi(60), -offset.1 * 0.5f64.powi(60)));
+        assert_eq!(state.center, expected);
+        assert_eq!(state.viewport.center(), expected.to_f64());
+    }
+
+    #[test]
+    fn julia_mode_fills_the_window() {
+        let state = State {
+            julia_mode: true,
+            julia_c: (-0.8, 0.156),
+            ..state()
+        };
+        let (pane, pixel, size) = Pane::at(&state, (10.0, 20.0), (WIDTH, HEIGHT));
+        assert!(pane == Pane::Julia && pixel == (10.0, 20.0) && size == (WIDTH, HEIGHT));
+
+        let mut state = state;
+        let (mut array, _) = compute_mandelbrot_array(
+            WIDTH,
+            HEIGHT,
+            Fractal::Julia(state.julia_c),
+            &state.julia_viewport,
+            &state,
+            None,
+        );
+        recalibrate(&mut array);
+        state.julia_array = array;
+        compose_image(&mut state);
+        assert_eq!(state.image, to_image(&state.julia_array, &state));
+    }
 }
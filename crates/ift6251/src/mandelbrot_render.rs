@@ -0,0 +1,135 @@
+// Renders the bookmarked locations of the mandelbrot sketch, one image per bookmark.
+//
+// Usage: `mandelbrot-render [BOOKMARKS] [--width PIXELS] [--output DIRECTORY] [--format FORMAT]
+// [--linear]`
+//
+// The bookmarks are read from the file saved by the sketch by default. Each image is rendered at
+// the given width, and at the height that keeps the aspect ratio of the bookmarked viewport.
+//
+// The images are saved as 8-bit PNG files by default, or as `png16` or `exr` files to be graded
+// without banding, the 16-bit PNG files keeping linear channels with `--linear`.
+
+use ift6251::{
+    get_save_directory,
+    save::{ImageFormat, ImageOptions},
+    utils::{
+        bookmarks::{BOOKMARKS_PATH, Bookmark, read_bookmarks},
+        images::{equalize, recalibrate},
+        mandelbrot::Fractal,
+    },
+};
+use indicatif::ParallelProgressIterator;
+use point_cloud_renderer::color::LinearImage;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+const USAGE: &str = "Usage: mandelbrot-render [BOOKMARKS] [--width PIXELS] [--output DIRECTORY] \
+                     [--format png|png16|exr] [--linear]";
+
+/// The options given on the command line.
+struct Options {
+    bookmarks_path: String,
+    width: usize,
+    output_directory: Option<String>,
+    image_options: ImageOptions,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, &'static str> {
+        let mut options = Self {
+            bookmarks_path: BOOKMARKS_PATH.to_owned(),
+            width: 4096,
+            output_directory: None,
+            image_options: ImageOptions::default(),
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--width" => {
+                    options.width = args
+                        .next()
+                        .and_then(|width| width.parse().ok())
+                        .filter(|&width| width > 0)
+                        .ok_or("The width must be a positive number of pixels")?;
+                }
+                "--output" => {
+                    options.output_directory =
+                        Some(args.next().ok_or("The output directory is missing")?);
+                }
+                "--format" => {
+                    options.image_options.format = args
+                        .next()
+                        .and_then(|format| ImageFormat::parse(&format))
+                        .ok_or("The format must be png, png16 or exr")?;
+                }
+                "--linear" => options.image_options.linear = true,
+                flag if flag.starts_with("--") => return Err("Unknown option"),
+                path => options.bookmarks_path = path.to_owned(),
+            }
+        }
+        Ok(options)
+    }
+}
+
+fn main() {
+    let options = Options::parse(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{e}\n{USAGE}");
+        std::process::exit(2);
+    });
+    let bookmarks = read_bookmarks(&options.bookmarks_path).unwrap_or_else(|e| {
+        eprintln!("{e}: {}", options.bookmarks_path);
+        std::process::exit(1);
+    });
+
+    let directory = options
+        .output_directory
+        .unwrap_or_else(|| get_save_directory("mandelbrot-render"));
+    if let Err(e) = std::fs::create_dir_all(&directory) {
+        eprintln!("Failed to create {directory}: {e}");
+        std::process::exit(1);
+    }
+
+    bookmarks.iter().enumerate().for_each(|(i, bookmark)| {
+        println!("Rendering bookmark {}/{}", i + 1, bookmarks.len());
+        let image = bookmark_image(bookmark, options.width);
+        let extension = options.image_options.format.extension();
+        let path = format!("{directory}/bookmark_{:03}.{extension}", i + 1);
+        match options
+            .image_options
+            .save(&image, &path, Some(&bookmark.to_string()))
+        {
+            Ok(()) => println!("Saved image to: {path}"),
+            Err(e) => eprintln!("Failed to save {path}: {e}"),
+        }
+    });
+}
+
+/// Renders the escape time of the pixels around a bookmark, colored with its style like the
+/// sketch does, in linear floating point so any format can be saved from it.
+fn bookmark_image(bookmark: &Bookmark, width: usize) -> LinearImage {
+    let (viewport_width, viewport_height) = bookmark.viewport.size();
+    let height = ((width as f64 * viewport_height / viewport_width).round() as usize).max(1);
+
+    // The points of the set stay black, like when they are not selected in the sketch
+    let mut array: Vec<Vec<f64>> = (0..height)
+        .into_par_iter()
+        .progress_count(height as u64)
+        .map(|y| {
+            (0..width)
+                .map(|x| {
+                    let pixel = (x as f64, y as f64);
+                    let (escape, _, _) = Fractal::Mandelbrot.orbit(
+                        pixel,
+                        width,
+                        height,
+                        &bookmark.viewport,
+                        &bookmark.iteration,
+                    );
+                    escape.unwrap_or(0) as f64
+                })
+                .collect()
+        })
+        .collect();
+
+    recalibrate(&mut array);
+    equalize(&mut array, 0.0);
+    bookmark.style.colorize_linear(&array, 0.0)
+}
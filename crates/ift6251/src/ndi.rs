@@ -0,0 +1,433 @@
+use std::{
+    ffi::{CString, c_char, c_void},
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU32, Ordering},
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use libloading::Library;
+use nannou::{
+    image::{self, RgbaImage, imageops::FilterType},
+    prelude::*,
+};
+use nannou_egui::egui;
+
+use crate::{recorder::FrameResolver, utils::widgets::LabelledSlider};
+
+/// Publishes the frames of a window as an NDI stream on the local network, so they can be
+/// composited live in software such as Resolume, OBS or TouchDesigner.
+///
+/// The NDI runtime is loaded when the stream starts, from the directory given by the
+/// `NDI_RUNTIME_DIR_V6` or `NDI_RUNTIME_DIR_V5` environment variable set by its installer, or else
+/// from the library path. Like the recordings, the frames are read back from the GPU and sent on
+/// a background thread, and frames are dropped rather than stalling the render loop.
+pub struct NdiOutput {
+    /// The name the stream is listed under by the receivers, after the name of the machine.
+    pub name: String,
+    /// The size the frames are sent at, fitted inside it with black bars; the size of the window
+    /// when it is not given.
+    pub resolution: Option<[u32; 2]>,
+    /// The highest number of frames sent per second.
+    pub frame_rate: u32,
+    capturer: wgpu::TextureCapturer,
+    resolver: Mutex<FrameResolver>,
+    /// The capture of the previous frame, which can only be read once the frame is submitted.
+    pending: Mutex<Option<wgpu::TextureSnapshot>>,
+    /// When the last frame was captured, to hold the frame rate.
+    last_capture: Mutex<Option<Instant>>,
+    stream: Option<Stream>,
+}
+
+/// A stream being published.
+struct Stream {
+    sender: SyncSender<OutgoingFrame>,
+    publisher: JoinHandle<()>,
+    dropped_frames: Arc<AtomicU32>,
+}
+
+/// A frame to send, with the settings of the stream when it was captured so they can change while
+/// it runs.
+struct OutgoingFrame {
+    image: RgbaImage,
+    resolution: Option<[u32; 2]>,
+    frame_rate: u32,
+}
+
+impl NdiOutput {
+    /// The number of frames that can wait to be sent before frames are dropped.
+    const QUEUE_LENGTH: usize = 4;
+
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            resolution: None,
+            frame_rate: 60,
+            capturer: wgpu::TextureCapturer::default(),
+            resolver: Mutex::default(),
+            pending: Mutex::new(None),
+            last_capture: Mutex::new(None),
+            stream: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// The number of frames of the stream that were dropped because the queue was full.
+    pub fn dropped_frames(&self) -> u32 {
+        self.stream
+            .as_ref()
+            .map_or(0, |stream| stream.dropped_frames.load(Ordering::Relaxed))
+    }
+
+    /// Loads the NDI runtime and starts publishing the stream.
+    pub fn start(&mut self) -> Result<(), String> {
+        if self.is_running() {
+            return Err("The NDI stream is already running".to_owned());
+        }
+        let library = NdiLibrary::load()?;
+        let name = CString::new(self.name.as_str())
+            .map_err(|_| "The name of the NDI stream cannot contain a null byte")?;
+
+        let (sender, receiver) = mpsc::sync_channel(Self::QUEUE_LENGTH);
+        let publisher = std::thread::spawn(move || publish_frames(receiver, library, name));
+        self.stream = Some(Stream {
+            sender,
+            publisher,
+            dropped_frames: Arc::new(AtomicU32::new(0)),
+        });
+        println!("Publishing the NDI stream {}", self.name);
+        Ok(())
+    }
+
+    /// Stops publishing the stream, waiting for its last frames to be sent.
+    pub fn stop(&mut self, device: &wgpu::Device) {
+        let Some(stream) = self.stream.take() else {
+            return;
+        };
+        self.pending.lock().unwrap().take();
+        if self.capturer.await_active_snapshots(device).is_err() {
+            eprintln!("Timed out while reading the last frames of the NDI stream");
+        }
+
+        let Stream {
+            sender, publisher, ..
+        } = stream;
+        drop(sender);
+        publisher.join().ok();
+        println!("Stopped the NDI stream");
+    }
+
+    /// Starts the stream if it is not running, else stops it.
+    pub fn toggle(&mut self, device: &wgpu::Device) {
+        match self.is_running() {
+            true => self.stop(device),
+            false => {
+                if let Err(e) = self.start() {
+                    eprintln!("{e}");
+                }
+            }
+        }
+    }
+
+    /// Shows the settings of the stream and its state.
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        ui.add_enabled_ui(!self.is_running(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("NDI name");
+                ui.text_edit_singleline(&mut self.name);
+            });
+        });
+        let mut window_size = self.resolution.is_none();
+        ui.checkbox(&mut window_size, "Stream at the window size");
+        match (window_size, &mut self.resolution) {
+            (true, resolution) => *resolution = None,
+            (false, None) => self.resolution = Some([1920, 1080]),
+            (false, Some([width, height])) => {
+                ui.horizontal(|ui| {
+                    ui.label("Stream resolution");
+                    ui.add(egui::DragValue::new(width).clamp_range(16..=7680));
+                    ui.label("x");
+                    ui.add(egui::DragValue::new(height).clamp_range(16..=4320));
+                });
+            }
+        }
+        ui.add(LabelledSlider::new(
+            "Stream frame rate",
+            &mut self.frame_rate,
+            1..=120,
+        ));
+        if self.is_running() {
+            ui.label(format!(
+                "Streaming, {} frames dropped",
+                self.dropped_frames()
+            ));
+        }
+    }
+
+    /// Captures what was drawn to the frame so far while the stream is running, at most at its
+    /// frame rate.
+    ///
+    /// Calling this before drawing the GUI keeps it out of the stream.
+    pub fn capture(&self, frame: &Frame) {
+        let Some(stream) = &self.stream else {
+            return;
+        };
+        // The capture of the previous frame was submitted with it, and can now be read
+        if let Some(snapshot) = self.pending.lock().unwrap().take() {
+            let (resolution, frame_rate) = (self.resolution, self.frame_rate);
+            let outgoing = move |image| OutgoingFrame {
+                image,
+                resolution,
+                frame_rate,
+            };
+            read_snapshot(snapshot, stream, outgoing);
+        }
+
+        let mut last_capture = self.last_capture.lock().unwrap();
+        let interval = Duration::from_secs_f32(1.0 / self.frame_rate.max(1) as f32);
+        if last_capture.is_some_and(|last| last.elapsed() < interval) {
+            return;
+        }
+        *last_capture = Some(Instant::now());
+
+        let device = frame.device_queue_pair().device();
+        let mut encoder = frame.command_encoder();
+        let mut resolver = self.resolver.lock().unwrap();
+        let texture = resolver.resolve(frame, &mut encoder);
+        let snapshot = self.capturer.capture(device, &mut encoder, texture);
+        *self.pending.lock().unwrap() = Some(snapshot);
+    }
+}
+
+/// Reads a captured frame back from the GPU and queues it to be sent.
+fn read_snapshot(
+    snapshot: wgpu::TextureSnapshot,
+    stream: &Stream,
+    outgoing: impl FnOnce(RgbaImage) -> OutgoingFrame + Send + 'static,
+) {
+    let sender = stream.sender.clone();
+    let dropped_frames = Arc::clone(&stream.dropped_frames);
+    let result = snapshot.read(move |result| match result {
+        Ok(image) => {
+            if let Err(TrySendError::Full(_)) = sender.try_send(outgoing(image.to_owned())) {
+                dropped_frames.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        Err(e) => eprintln!("Failed to read a frame of the NDI stream: {e:?}"),
+    });
+    if result.is_err() {
+        eprintln!("Timed out while reading a frame of the NDI stream");
+    }
+}
+
+/// Sends the frames received until the stream stops.
+fn publish_frames(receiver: Receiver<OutgoingFrame>, library: NdiLibrary, name: CString) {
+    let settings = SendCreate {
+        ndi_name: name.as_ptr(),
+        groups: std::ptr::null(),
+        // The frames are paced by the render loop
+        clock_video: false,
+        clock_audio: false,
+    };
+    let instance = unsafe { (library.send_create)(&settings) };
+    if instance.is_null() {
+        eprintln!("Failed to create the NDI stream");
+        return;
+    }
+
+    receiver.iter().for_each(|frame| {
+        let image = match frame.resolution {
+            Some(size) => letterbox(&frame.image, size),
+            None => frame.image,
+        };
+        let (width, height) = image.dimensions();
+        let video = VideoFrame {
+            xres: width as i32,
+            yres: height as i32,
+            four_cc: FOURCC_RGBA,
+            frame_rate_n: frame.frame_rate as i32,
+            frame_rate_d: 1,
+            picture_aspect_ratio: 0.0,
+            frame_format_type: FRAME_FORMAT_PROGRESSIVE,
+            timecode: TIMECODE_SYNTHESIZE,
+            data: image.as_ptr(),
+            line_stride_in_bytes: width as i32 * 4,
+            metadata: std::ptr::null(),
+            timestamp: 0,
+        };
+        // The frame is copied before the call returns
+        unsafe { (library.send_video)(instance, &video) };
+    });
+    unsafe { (library.send_destroy)(instance) };
+}
+
+/// Scales an image to fit inside the given size, keeping its aspect ratio, and centers it between
+/// black bars.
+fn letterbox(image: &RgbaImage, [width, height]: [u32; 2]) -> RgbaImage {
+    let (image_width, image_height) = image.dimensions();
+    if (image_width, image_height) == (width, height) {
+        return image.clone();
+    }
+    let scale = (width as f32 / image_width as f32).min(height as f32 / image_height as f32);
+    let fitted = |length: u32, max: u32| ((length as f32 * scale).round() as u32).clamp(1, max);
+    let (fitted_width, fitted_height) = (fitted(image_width, width), fitted(image_height, height));
+    let scaled = image::imageops::resize(image, fitted_width, fitted_height, FilterType::Triangle);
+
+    let mut output = RgbaImage::from_pixel(width, height, image::Rgba([0, 0, 0, 255]));
+    let x = (width - fitted_width) / 2;
+    let y = (height - fitted_height) / 2;
+    image::imageops::replace(&mut output, &scaled, x, y);
+    output
+}
+
+/// The settings of a sender, as `NDIlib_send_create_t` in the NDI SDK.
+#[repr(C)]
+struct SendCreate {
+    ndi_name: *const c_char,
+    groups: *const c_char,
+    clock_video: bool,
+    clock_audio: bool,
+}
+
+/// A frame of video, as `NDIlib_video_frame_v2_t` in the NDI SDK.
+#[repr(C)]
+struct VideoFrame {
+    xres: i32,
+    yres: i32,
+    four_cc: u32,
+    frame_rate_n: i32,
+    frame_rate_d: i32,
+    /// The aspect ratio of the frame; 0 for square pixels.
+    picture_aspect_ratio: f32,
+    frame_format_type: u32,
+    timecode: i64,
+    data: *const u8,
+    line_stride_in_bytes: i32,
+    metadata: *const c_char,
+    timestamp: i64,
+}
+
+/// Pixels of 8 bits per channel, in the order red, green, blue and alpha.
+const FOURCC_RGBA: u32 = u32::from_le_bytes(*b"RGBA");
+/// Whole frames rather than interlaced fields.
+const FRAME_FORMAT_PROGRESSIVE: u32 = 1;
+/// Lets the runtime stamp the frames with the time they are sent.
+const TIMECODE_SYNTHESIZE: i64 = i64::MAX;
+
+/// The functions of the NDI runtime used to send video.
+struct NdiLibrary {
+    send_create: unsafe extern "C" fn(*const SendCreate) -> *mut c_void,
+    send_destroy: unsafe extern "C" fn(*mut c_void),
+    send_video: unsafe extern "C" fn(*mut c_void, *const VideoFrame),
+    destroy: unsafe extern "C" fn(),
+    /// Kept loaded while its functions are used.
+    _library: Library,
+}
+
+impl NdiLibrary {
+    /// Loads and initializes the first NDI runtime found.
+    fn load() -> Result<Self, String> {
+        let library = runtime_candidates()
+            .into_iter()
+            .find_map(|path| unsafe { Library::new(path) }.ok())
+            .ok_or("The NDI runtime was not found, install it from https://ndi.video/tools")?;
+
+        let symbol = |name: &str| format!("The NDI runtime has no {name}");
+        unsafe {
+            let initialize = *library
+                .get::<unsafe extern "C" fn() -> bool>(b"NDIlib_initialize\0")
+                .map_err(|_| symbol("NDIlib_initialize"))?;
+            let ndi = Self {
+                send_create: *library
+                    .get(b"NDIlib_send_create\0")
+                    .map_err(|_| symbol("NDIlib_send_create"))?,
+                send_destroy: *library
+                    .get(b"NDIlib_send_destroy\0")
+                    .map_err(|_| symbol("NDIlib_send_destroy"))?,
+                send_video: *library
+                    .get(b"NDIlib_send_send_video_v2\0")
+                    .map_err(|_| symbol("NDIlib_send_send_video_v2"))?,
+                destroy: *library
+                    .get(b"NDIlib_destroy\0")
+                    .map_err(|_| symbol("NDIlib_destroy"))?,
+                _library: library,
+            };
+            // The runtime refuses to run on CPUs without SSE4.2
+            match initialize() {
+                true => Ok(ndi),
+                false => Err("The NDI runtime is not supported by this CPU".to_owned()),
+            }
+        }
+    }
+}
+
+impl Drop for NdiLibrary {
+    fn drop(&mut self) {
+        unsafe { (self.destroy)() };
+    }
+}
+
+/// The paths the NDI runtime is looked for at, in order.
+fn runtime_candidates() -> Vec<PathBuf> {
+    let file_name = match std::env::consts::OS {
+        "windows" => "Processing.NDI.Lib.x64.dll",
+        "macos" => "libndi.dylib",
+        _ => "libndi.so",
+    };
+    let mut candidates: Vec<PathBuf> = ["NDI_RUNTIME_DIR_V6", "NDI_RUNTIME_DIR_V5"]
+        .into_iter()
+        .filter_map(std::env::var_os)
+        .map(|directory| PathBuf::from(directory).join(file_name))
+        .collect();
+    match std::env::consts::OS {
+        "windows" => candidates.push(file_name.into()),
+        "macos" => candidates.extend([
+            "/Library/NDI SDK for Apple/lib/macOS/libndi.dylib".into(),
+            "/usr/local/lib/libndi.dylib".into(),
+        ]),
+        _ => candidates.extend(["libndi.so.6".into(), "libndi.so.5".into(), file_name.into()]),
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_are_letterboxed_to_the_stream_resolution() {
+        let red = image::Rgba([255, 0, 0, 255]);
+        let black = image::Rgba([0, 0, 0, 255]);
+        let image = RgbaImage::from_pixel(8, 4, red);
+
+        // Black bars above and below a wider frame
+        let output = letterbox(&image, [8, 8]);
+        assert_eq!(output.dimensions(), (8, 8));
+        assert_eq!(*output.get_pixel(4, 0), black);
+        assert_eq!(*output.get_pixel(4, 4), red);
+        assert_eq!(*output.get_pixel(4, 7), black);
+
+        // And on the sides of a taller frame
+        let output = letterbox(&image, [16, 4]);
+        assert_eq!(*output.get_pixel(0, 2), black);
+        assert_eq!(*output.get_pixel(8, 2), red);
+        assert_eq!(*output.get_pixel(15, 2), black);
+
+        assert_eq!(letterbox(&image, [8, 4]), image);
+    }
+
+    #[test]
+    fn the_pixel_format_matches_the_sdk() {
+        // NDI_LIB_FOURCC('R', 'G', 'B', 'A')
+        let fourcc = b'R' as u32 | (b'G' as u32) << 8 | (b'B' as u32) << 16 | (b'A' as u32) << 24;
+        assert_eq!(FOURCC_RGBA, fourcc);
+        assert!(!runtime_candidates().is_empty());
+    }
+}
@@ -0,0 +1,259 @@
+use std::{
+    collections::HashMap,
+    net::UdpSocket,
+    ops::RangeInclusive,
+    sync::{Arc, Mutex, Weak},
+    time::Duration,
+};
+
+/// The UDP port the sketches listen to for OSC messages.
+pub const OSC_PORT: u16 = 9000;
+
+/// The address prefix of the messages setting a parameter, followed by its name.
+pub const PARAMETER_PREFIX: &str = "/param/";
+
+/// A remote control server receiving OSC messages over UDP.
+///
+/// Sketches register named parameters, then take the values received for them every frame. A
+/// message to `/param/<name>` whose first argument is a number sets the parameter, clamped to
+/// its range. Messages to unregistered parameters are ignored.
+///
+/// The server stops listening once it is dropped.
+pub struct OscServer {
+    parameters: Arc<Mutex<HashMap<String, Parameter>>>,
+    port: u16,
+}
+
+/// A parameter that can be set remotely.
+struct Parameter {
+    range: RangeInclusive<f32>,
+    /// The last value received and not yet taken.
+    pending: Option<f32>,
+}
+
+impl OscServer {
+    /// How often the server checks whether it was dropped.
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+    /// The largest packet the server can receive.
+    const MAX_PACKET_SIZE: usize = 1536;
+
+    /// Starts listening on the given port of every interface; the port 0 picks a free one.
+    pub fn start(port: u16) -> Result<Self, &'static str> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))
+            .map_err(|_| "Failed to bind the socket of the OSC server")?;
+        socket
+            .set_read_timeout(Some(Self::POLL_INTERVAL))
+            .map_err(|_| "Failed to configure the socket of the OSC server")?;
+        let port = socket.local_addr().map_or(port, |address| address.port());
+
+        let parameters = Arc::new(Mutex::new(HashMap::new()));
+        let weak_parameters = Arc::downgrade(&parameters);
+        std::thread::spawn(move || listen(socket, weak_parameters));
+        println!("Listening to OSC messages on port {port}");
+
+        Ok(Self { parameters, port })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Registers a parameter, so the values received for it can be taken.
+    pub fn register(&self, name: &str, range: RangeInclusive<f32>) {
+        let parameter = Parameter {
+            range,
+            pending: None,
+        };
+        self.parameters
+            .lock()
+            .unwrap()
+            .insert(name.to_owned(), parameter);
+    }
+
+    /// Returns the last value received for a parameter since it was last taken, if any.
+    pub fn take(&self, name: &str) -> Option<f32> {
+        self.parameters
+            .lock()
+            .unwrap()
+            .get_mut(name)
+            .and_then(|parameter| parameter.pending.take())
+    }
+}
+
+/// Receives packets until the server is dropped.
+fn listen(socket: UdpSocket, parameters: Weak<Mutex<HashMap<String, Parameter>>>) {
+    let mut packet = [0; OscServer::MAX_PACKET_SIZE];
+    let mut messages = Vec::new();
+    loop {
+        let received = socket.recv(&mut packet);
+        let Some(parameters) = parameters.upgrade() else {
+            return;
+        };
+        let Ok(length) = received else {
+            continue;
+        };
+
+        messages.clear();
+        if decode_packet(&packet[..length], &mut messages).is_err() {
+            eprintln!("Ignored an invalid OSC packet");
+            continue;
+        }
+        let mut parameters = parameters.lock().unwrap();
+        messages.drain(..).for_each(|(address, value)| {
+            let parameter = address
+                .strip_prefix(PARAMETER_PREFIX)
+                .and_then(|name| parameters.get_mut(name));
+            if let Some(parameter) = parameter {
+                let value = value.clamp(*parameter.range.start(), *parameter.range.end());
+                parameter.pending = Some(value);
+            }
+        });
+    }
+}
+
+/// Decodes the messages of a packet, or of the packets of a bundle, into their address and the
+/// value of their first argument.
+///
+/// Messages without a numeric first argument are skipped.
+fn decode_packet(packet: &[u8], messages: &mut Vec<(String, f32)>) -> Result<(), &'static str> {
+    let mut reader = Reader { packet };
+    let address = reader.string()?;
+
+    // A bundle holds a time tag followed by packets prefixed by their size
+    if address == "#bundle" {
+        reader.bytes(8)?;
+        while !reader.packet.is_empty() {
+            let size = reader.int()?;
+            let size = usize::try_from(size).map_err(|_| "The OSC bundle has a negative size")?;
+            decode_packet(reader.bytes(size)?, messages)?;
+        }
+        return Ok(());
+    }
+
+    let tags = reader.string()?;
+    let Some(tags) = tags.strip_prefix(',') else {
+        return Err("The OSC message has no type tags");
+    };
+    let value = match tags.chars().next() {
+        Some('f') => reader.int().map(|bits| f32::from_bits(bits as u32))?,
+        Some('i') => reader.int()? as f32,
+        Some('d') => f64::from_bits(u64::from_be_bytes(reader.array()?)) as f32,
+        Some('h') => i64::from_be_bytes(reader.array()?) as f32,
+        Some('T') => 1.0,
+        Some('F') => 0.0,
+        _ => return Ok(()),
+    };
+    messages.push((address.to_owned(), value));
+    Ok(())
+}
+
+/// Reads the fields of an OSC packet, which are aligned on 4 bytes.
+struct Reader<'a> {
+    packet: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], &'static str> {
+        if length > self.packet.len() {
+            return Err("The OSC packet is truncated");
+        }
+        let (bytes, rest) = self.packet.split_at(length);
+        self.packet = rest;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], &'static str> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    fn int(&mut self) -> Result<i32, &'static str> {
+        Ok(i32::from_be_bytes(self.array()?))
+    }
+
+    /// Reads a string terminated by at least one null byte and padded to 4 bytes.
+    fn string(&mut self) -> Result<&'a str, &'static str> {
+        let length = self
+            .packet
+            .iter()
+            .position(|&byte| byte == 0)
+            .ok_or("The OSC string is not terminated")?;
+        let padded_length = (length / 4 + 1) * 4;
+        let bytes = self.bytes(padded_length.min(self.packet.len()))?;
+        std::str::from_utf8(&bytes[..length]).map_err(|_| "The OSC string is not valid UTF-8")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes a string padded to 4 bytes.
+    fn string(text: &str) -> Vec<u8> {
+        let mut bytes = text.as_bytes().to_vec();
+        bytes.resize((text.len() / 4 + 1) * 4, 0);
+        bytes
+    }
+
+    fn float_message(address: &str, value: f32) -> Vec<u8> {
+        [string(address), string(",f"), value.to_be_bytes().to_vec()].concat()
+    }
+
+    #[test]
+    fn messages_and_bundles_are_decoded() {
+        let mut messages = Vec::new();
+        decode_packet(&float_message("/param/wind", 0.5), &mut messages).unwrap();
+
+        // Messages without a numeric argument are skipped
+        let packets = [
+            [
+                string("/param/zoom"),
+                string(",i"),
+                3i32.to_be_bytes().to_vec(),
+            ]
+            .concat(),
+            [string("/param/name"), string(",s"), string("cloud")].concat(),
+        ];
+        let mut bundle = [string("#bundle"), vec![0; 8]].concat();
+        packets.iter().for_each(|packet| {
+            bundle.extend((packet.len() as i32).to_be_bytes());
+            bundle.extend(packet);
+        });
+        decode_packet(&bundle, &mut messages).unwrap();
+
+        assert_eq!(
+            messages,
+            [
+                ("/param/wind".to_owned(), 0.5),
+                ("/param/zoom".to_owned(), 3.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn truncated_packets_are_rejected() {
+        let message = float_message("/param/wind", 0.5);
+        let mut messages = Vec::new();
+        assert!(decode_packet(&message[..message.len() - 1], &mut messages).is_err());
+        assert!(decode_packet(b"/param", &mut messages).is_err());
+        assert!(messages.is_empty());
+    }
+
+    #[test]
+    fn received_values_are_clamped_and_taken_once() {
+        let server = OscServer::start(0).unwrap();
+        server.register("wind", 0.0..=1.0);
+
+        let socket = UdpSocket::bind(("127.0.0.1", 0)).unwrap();
+        let message = float_message("/param/wind", 2.0);
+        socket
+            .send_to(&message, ("127.0.0.1", server.port()))
+            .unwrap();
+
+        let value = (0..100).find_map(|_| {
+            std::thread::sleep(Duration::from_millis(10));
+            server.take("wind")
+        });
+        assert_eq!(value, Some(1.0));
+        assert_eq!(server.take("wind"), None);
+    }
+}
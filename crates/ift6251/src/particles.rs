@@ -2,15 +2,52 @@
 // The Nature of Code - Daniel Shiffman
 // http://natureofcode.com
 
-use ift6251::get_save_path;
+// The `noise` crate re-exports `Perlin` through overlapping globs
+#![allow(ambiguous_glob_imports)]
+
+use std::sync::{Arc, Mutex};
+
+use ift6251::{
+    build_info::{self, BUILD_INFO_KEY},
+    display::DisplayOptions,
+    get_export_path, get_save_path,
+    osc::{OSC_PORT, OscServer},
+    recorder::{RECORD_KEY, Recorder},
+    utils::{
+        audio::{AUDIO_EXTENSIONS, AudioAnalyzer, AudioFeatures},
+        contours::contour_segments,
+        density::DensityGrid,
+        field::VectorField,
+        hotkeys::{Binding, HELP_KEY, HotkeyHelp, action_for, describe},
+        images::create_texture,
+        mandelbrot::Style,
+        palette::{Command, CommandPalette, PALETTE_KEY},
+        rng::{self, random_f32, random_range},
+        spatial_hash::SpatialHash,
+        species::InteractionMatrix,
+        sph::Fluid,
+        synth::{Blip, BlipSynth},
+        trajectories::{Trajectories, TrajectoryFormat},
+        widgets::{Dropdown, FilePicker, LabelledSlider, UiScale},
+    },
+    watchdog::{self, Watchdog},
+};
 use nannou::{
+    image::RgbaImage,
     noise::{NoiseFn, Perlin},
     prelude::*,
 };
+use nannou_audio::{Buffer, Host, Stream};
+use nannou_egui::{Egui, egui};
 
-const INITIAL_PARTICLE_COUNT: u64 = 1000;
+const INITIAL_PARTICLE_COUNT: u32 = 4000;
+/// The size of the cells of the density field, in pixels.
+const DENSITY_CELL_SIZE: f32 = 4.0;
+/// The size of the cells the outlines of the blobs are traced over, in pixels.
+const BLOB_CELL_SIZE: f32 = 8.0;
 
 fn main() {
+    watchdog::supervise();
     nannou::app(model).update(update).run();
 }
 
@@ -23,19 +60,22 @@ struct Particle {
     life_span: f32,
     radius: f32,
     mass: f32,
+    species: usize,
     id: u64,
 }
 
 impl Particle {
-    const LIFE_SPAN_MAX: f32 = 512.0;
     const LIFE_SPAN_DELTA: f32 = 0.5;
     const MASS_MIN: f32 = 1.0;
     const MASS_MAX: f32 = 10.0;
-    const RESTITUTION_COEFFICIENT: f32 = 0.8;
-    const GRAVITATIONAL_CONSTANT: f32 = 0.025;
     const RADIUS: f32 = 2.0;
+    /// The distance within which the species attract or repel each other, in pixels.
+    const SPECIES_RADIUS: f32 = 80.0;
+    /// The distance beyond which the particles ignore each other, in pixels, as the gravity is
+    /// negligible further away.
+    const INTERACTION_RADIUS: f32 = 100.0;
 
-    fn new(position: Point2, id: u64) -> Self {
+    fn new(position: Point2, id: u64, species: usize, life_span: f32) -> Self {
         let mass = random_range(Self::MASS_MIN, Self::MASS_MAX);
         let radius = Self::RADIUS * mass / (4.0 * Self::MASS_MIN);
         // let radius = Self::RADIUS;
@@ -43,15 +83,25 @@ impl Particle {
             acceleration: Vec2::ZERO,
             velocity: vec2(random_range(-1.0, 1.0), random_range(-1.0, 1.0)),
             position,
-            life_span: Self::LIFE_SPAN_MAX,
+            life_span,
             radius,
             mass,
+            species,
             id,
         }
     }
 
-    fn interacts(&mut self, particles: &[Particle]) {
-        particles.iter().for_each(|particle| {
+    /// Applies the collisions, the gravity and the attraction between the species of the
+    /// neighbouring particles, recording the collisions where the particles meet.
+    fn interacts<'a>(
+        &mut self,
+        neighbours: impl Iterator<Item = &'a Particle>,
+        physics: &Physics,
+        interactions: &InteractionMatrix,
+        species_strength: f32,
+        collisions: &mut Vec<Collision>,
+    ) {
+        neighbours.for_each(|particle| {
             if particle.id != self.id {
                 // Compute the distance between the particles
                 let direction = self.position - particle.position;
@@ -73,18 +123,35 @@ impl Particle {
                     // Calculate the new velocity for particle 1 after the elastic collision
                     let force = -((2.0 * m2 / (m1 + m2)) * dot_product * distance_inverse)
                         * direction
-                        * Self::RESTITUTION_COEFFICIENT;
+                        * physics.restitution;
 
                     self.apply_force(force);
+
+                    // Record each pair once, when the particles are moving towards each other
+                    if dot_product > 0.0 && self.id < particle.id {
+                        collisions.push(Collision {
+                            position: (self.position + particle.position) / 2.0,
+                            mass: m1 + m2,
+                        });
+                    }
                     return; // Skip further processing if particles are overlapping
                 }
 
                 // Gravitational interaction
                 let force = direction.normalize()
-                    * (Self::GRAVITATIONAL_CONSTANT
+                    * (physics.gravitational_constant
                         * (self.mass * particle.mass)
                         * distance_inverse);
                 self.apply_force(force);
+
+                // The species attract or repel each other, most strongly halfway through the
+                // interaction radius
+                if distance < Self::SPECIES_RADIUS {
+                    let attraction = interactions.get(self.species, particle.species);
+                    let falloff = 1.0 - (2.0 * distance / Self::SPECIES_RADIUS - 1.0).abs();
+                    let force = direction / distance * attraction * falloff * species_strength;
+                    self.apply_force(force);
+                }
             }
         });
     }
@@ -112,6 +179,11 @@ impl Particle {
         self.acceleration += f;
     }
 
+    // Carry the particle along a flow
+    fn advect(&mut self, displacement: Vec2) {
+        self.position += displacement;
+    }
+
     // Method to update position
     fn update(&mut self) {
         self.velocity += self.acceleration;
@@ -120,15 +192,19 @@ impl Particle {
         self.life_span -= Self::LIFE_SPAN_DELTA;
     }
 
-    // Method to display
-    fn display(&self, draw: &Draw) {
+    // Method to display, with a hue per species when there are several
+    fn display(&self, draw: &Draw, species_count: usize) {
         let mass_color = self.mass / Self::MASS_MAX;
-        draw.ellipse().xy(self.position).radius(self.radius).rgba(
-            mass_color,
-            0.0,
-            0.0,
-            self.life_span / 255.0,
-        );
+        let ellipse = draw.ellipse().xy(self.position).radius(self.radius);
+        match species_count {
+            0 | 1 => ellipse.rgba(mass_color, 0.0, 0.0, self.life_span / 255.0),
+            _ => ellipse.hsla(
+                self.species as f32 / species_count as f32,
+                0.8,
+                0.2 + 0.4 * mass_color,
+                self.life_span / 255.0,
+            ),
+        };
     }
 
     // Is the particle still useful?
@@ -137,30 +213,157 @@ impl Particle {
     }
 }
 
+/// Where two particles collided and their total mass.
+struct Collision {
+    position: Point2,
+    mass: f32,
+}
+
+impl Collision {
+    /// The pitch of the collisions of the lightest particles, in hertz.
+    const HIGHEST_PITCH: f32 = 1760.0;
+    /// The number of octaves between the lightest and the heaviest collisions.
+    const PITCH_OCTAVES: f32 = 3.0;
+    /// The duration of the blip of a collision, in seconds.
+    const BLIP_DURATION: f32 = 0.15;
+
+    /// The blip sounding the collision, pitched by the mass and panned by the position within the
+    /// bounds.
+    fn blip(&self, bounds: &Rect, volume: f32) -> Blip {
+        let heaviness = (self.mass - 2.0 * Particle::MASS_MIN)
+            / (2.0 * (Particle::MASS_MAX - Particle::MASS_MIN));
+        let frequency = Self::HIGHEST_PITCH * (-Self::PITCH_OCTAVES * heaviness).exp2();
+        let pan = 2.0 * (self.position.x - bounds.left()) / bounds.w() - 1.0;
+        Blip::new(frequency, pan, volume, Self::BLIP_DURATION)
+    }
+}
+
 struct ParticleSystem {
     bounds: Rect,
     particles: Vec<Particle>,
     noise: Perlin,
+    /// The flow the particles are carried along, stretched over the bounds.
+    field: Option<VectorField>,
+    /// How much each species of particles is attracted to each other species.
+    interactions: InteractionMatrix,
+    next_id: u64,
 }
 
 impl ParticleSystem {
     const NOISE_SCALE: f64 = 0.0008;
-    const NOISE_FORCE_MULTIPLIER: f32 = 0.1;
+    /// The thickness of the outlines of the blobs, in pixels.
+    const OUTLINE_WEIGHT: f32 = 2.0;
 
     fn new(bounds: Rect) -> Self {
         ParticleSystem {
             bounds,
             particles: Vec::new(),
             noise: Perlin::new(),
+            field: None,
+            interactions: InteractionMatrix::default(),
+            next_id: 0,
         }
     }
 
-    fn add_particle(&mut self, origin: Point2, id: u64) {
-        self.particles.push(Particle::new(origin, id));
+    /// Moves the bounds of the simulation, bringing the particles left outside back in.
+    fn set_bounds(&mut self, bounds: Rect) {
+        self.bounds = bounds;
+        self.particles.iter_mut().for_each(|particle| {
+            particle.position = particle
+                .position
+                .clamp(bounds.bottom_left(), bounds.top_right());
+        });
     }
 
-    fn update(&mut self) {
+    fn add_particle(&mut self, origin: Point2, life_span: f32) {
+        let species = random_range(0, self.interactions.species());
+        self.particles
+            .push(Particle::new(origin, self.next_id, species, life_span));
+        self.next_id += 1;
+    }
+
+    /// Replaces the interactions between the species, spreading the particles randomly among the
+    /// new species.
+    fn set_interactions(&mut self, interactions: InteractionMatrix) {
+        let species = interactions.species();
+        self.interactions = interactions;
+        self.particles
+            .iter_mut()
+            .for_each(|particle| particle.species = random_range(0, species));
+    }
+
+    /// Adds particles living for `life_span` at random positions within a radius of a stroke from
+    /// `from` to `to`, kept within the bounds.
+    fn spray(&mut self, from: Point2, to: Point2, radius: f32, count: u32, life_span: f32) {
+        (0..count).for_each(|_| {
+            let along = from.lerp(to, random_f32());
+            // The square root spreads the particles evenly over the disc
+            let angle = random_range(0.0, TAU);
+            let scatter = vec2(angle.cos(), angle.sin()) * radius * random_f32().sqrt();
+            let origin =
+                (along + scatter).clamp(self.bounds.bottom_left(), self.bounds.top_right());
+            self.add_particle(origin, life_span);
+        });
+    }
+
+    /// Pushes the particles within a radius of a point, most strongly at the point.
+    fn push(&mut self, center: Point2, radius: f32, force: Vec2) {
+        self.particles.iter_mut().for_each(|particle| {
+            let distance = particle.position.distance(center);
+            if distance < radius {
+                // The particles move against their velocity
+                particle.apply_force(-force * (1.0 - distance / radius));
+            }
+        });
+    }
+
+    /// Adds particles living for `life_span` at random positions within the bounds.
+    fn spawn(&mut self, count: u32, life_span: f32) {
+        (0..count).for_each(|_| {
+            let origin = pt2(
+                random_range(self.bounds.left(), self.bounds.right()),
+                random_range(self.bounds.bottom(), self.bounds.top()),
+            );
+            self.add_particle(origin, life_span);
+        });
+    }
+
+    /// Moves the particles following the physics, scaling the force of the noise field by
+    /// `noise_strength`, the flow of the vector field by `field_strength` and the attraction
+    /// between the species by `species_strength`, and returns the collisions between them.
+    ///
+    /// When a fluid is given, its pressure and viscosity replace the gravity between the particles.
+    fn update(
+        &mut self,
+        physics: &Physics,
+        noise_strength: f32,
+        field_strength: f32,
+        species_strength: f32,
+        fluid: Option<&Fluid>,
+    ) -> Vec<Collision> {
         let particles = self.particles.clone();
+        let mut collisions = Vec::new();
+
+        // Bucket the particles so each only interacts with its neighbours
+        let hash = SpatialHash::new(
+            Particle::INTERACTION_RADIUS,
+            particles.iter().map(|p| p.position),
+        );
+
+        // The particles move against their velocity, so the fluid sees them moving the other way
+        let fluid_accelerations = fluid.map(|fluid| {
+            let positions: Vec<Vec2> = particles.iter().map(|p| p.position).collect();
+            let velocities: Vec<Vec2> = particles.iter().map(|p| -p.velocity).collect();
+            let masses: Vec<f32> = particles.iter().map(|p| p.mass).collect();
+            fluid.accelerations(&positions, &velocities, &masses)
+        });
+        let physics = Physics {
+            gravitational_constant: match fluid {
+                Some(_) => 0.0,
+                None => physics.gravitational_constant,
+            },
+            ..*physics
+        };
 
         // Update status of all particles and remove dead ones.
         // Also handle interatctions between particles.
@@ -172,18 +375,42 @@ impl ParticleSystem {
             // Check bounds
             particle.check_bounds(&self.bounds);
 
+            // Flow with the fluid
+            if let Some(accelerations) = &fluid_accelerations {
+                particle.apply_force(-accelerations[i]);
+            }
+
             // Apply force field
-            // let x = particle.position.x as f64 * Self::NOISE_SCALE;
-            // let y = particle.position.y as f64 * Self::NOISE_SCALE;
-            // let vx = particle.velocity.x as f64 * Self::NOISE_SCALE;
-            // let vy = particle.velocity.y as f64 * Self::NOISE_SCALE;
-            // let force_x = self.noise.get([x, y]) as f32;
-            // let force_y = self.noise.get([vx, vy]) as f32;
-            // let force = vec2(force_x, force_y) * Self::NOISE_FORCE_MULTIPLIER;
-            // particle.apply_force(force);
+            if noise_strength > 0.0 {
+                let x = particle.position.x as f64 * Self::NOISE_SCALE;
+                let y = particle.position.y as f64 * Self::NOISE_SCALE;
+                let vx = particle.velocity.x as f64 * Self::NOISE_SCALE;
+                let vy = particle.velocity.y as f64 * Self::NOISE_SCALE;
+                let force_x = self.noise.get([x, y]) as f32;
+                let force_y = self.noise.get([vx, vy]) as f32;
+                let force = vec2(force_x, force_y) * physics.noise_force * noise_strength;
+                particle.apply_force(force);
+            }
+
+            // Advect along the vector field
+            if let Some(field) = &self.field {
+                let x = (particle.position.x - self.bounds.left()) / self.bounds.w();
+                let y = (particle.position.y - self.bounds.bottom()) / self.bounds.h();
+                let flow: Vec2 = field.sample(x, y).into();
+                particle.advect(flow * field_strength);
+            }
 
             // Interactions between particles
-            particle.interacts(&particles);
+            let neighbours = hash
+                .neighbours(particles[i].position, Particle::INTERACTION_RADIUS)
+                .map(|j| &particles[j]);
+            particle.interacts(
+                neighbours,
+                &physics,
+                &self.interactions,
+                species_strength,
+                &mut collisions,
+            );
 
             // Update particle
             particle.update();
@@ -193,73 +420,1047 @@ impl ParticleSystem {
                 self.particles.remove(i);
             }
         }
+
+        collisions
+    }
+
+    /// Traces the outlines of the blobs formed by the particles where their density, spread by
+    /// `smoothing` passes of blur, exceeds a threshold.
+    fn blob_outlines(
+        &self,
+        grid: &mut DensityGrid,
+        threshold: f64,
+        smoothing: u32,
+    ) -> Vec<[Vec2; 2]> {
+        grid.clear();
+        self.deposit(grid);
+        grid.blur(smoothing);
+
+        // The contours are traced over the unit square, stretched over the bounds
+        let (origin, size) = (self.bounds.bottom_left(), self.bounds.wh());
+        contour_segments(grid.cells(), threshold)
+            .into_iter()
+            .map(|segment| segment.map(|point| origin + point * size))
+            .collect()
+    }
+
+    /// Accumulates the positions of the particles into a density grid stretched over the bounds.
+    fn deposit(&self, density: &mut DensityGrid) {
+        self.particles.iter().for_each(|particle| {
+            let x = (particle.position.x - self.bounds.left()) / self.bounds.w();
+            let y = (particle.position.y - self.bounds.bottom()) / self.bounds.h();
+            density.deposit(x, y, 1.0);
+        });
+    }
+
+    /// Records the current positions of the particles.
+    fn record(&self, trajectories: &mut Trajectories) {
+        trajectories.record(
+            self.particles
+                .iter()
+                .map(|particle| (particle.id, particle.position)),
+        );
     }
 
     fn draw(&self, draw: &Draw) {
         self.particles
             .iter()
-            .for_each(|particle| particle.display(draw));
+            .for_each(|particle| particle.display(draw, self.interactions.species()));
     }
+
+    fn draw_outlines(draw: &Draw, outlines: &[[Vec2; 2]]) {
+        outlines.iter().for_each(|[start, end]| {
+            draw.line()
+                .start(*start)
+                .end(*end)
+                .weight(Self::OUTLINE_WEIGHT)
+                .color(BLACK);
+        });
+    }
+}
+
+/// How the features of the audio drive the particle system.
+struct Settings {
+    audio_file_path: String,
+    field_file_path: String,
+    /// The distance the vector field carries the particles each frame, in pixels.
+    field_strength: f32,
+    /// The number of particles spawned every frame.
+    spawn_rate: u32,
+    /// The number of particles spawned at each onset, on top of the ones spawned every frame.
+    onset_spawn_count: u32,
+    /// How much the bass energy raises the gravitational constant.
+    bass_gravity_modulation: f32,
+    /// How much the treble energy strengthens the noise field.
+    treble_noise_modulation: f32,
+    /// Whether the collisions between the particles play blips.
+    sonify_collisions: bool,
+    collision_volume: f32,
+    density_mode: DensityMode,
+    /// The fraction of the density kept each frame, where 1 keeps it forever.
+    density_persistence: f64,
+    density_style: Style,
+    matrix_file_path: String,
+    /// How strongly the species attract or repel each other.
+    species_strength: f32,
+    /// Whether the simulation keeps its bounds when the window is resized, scaled to fit the
+    /// window, instead of following the window.
+    fixed_world: bool,
+    /// The number of frames the trajectories of the particles are recorded over.
+    trajectory_frames: u32,
+    trajectory_format: TrajectoryFormat,
+    /// The radius of the brush of the mouse, in pixels of the world.
+    brush_size: f32,
+    /// How much of the motion of the mouse the brush passes on to the particles it pushes.
+    brush_strength: f32,
+    /// The number of particles the brush sprays each frame.
+    spray_count: u32,
+    /// What pulls the particles together.
+    interaction_mode: InteractionMode,
+    fluid: Fluid,
+    physics: Physics,
+    particle_style: ParticleStyle,
+    /// The density of particles per cell the outlines of the blobs are traced at.
+    blob_threshold: f64,
+    /// The number of blur passes spreading the particles into blobs.
+    blob_smoothing: u32,
+}
+
+/// The constants of the motion of the particles.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Physics {
+    /// The strength of the gravity between the particles, before the bass raises it.
+    gravitational_constant: f32,
+    /// The fraction of the velocity the particles keep when they collide.
+    restitution: f32,
+    /// The life span of the new particles, which lose half of it a frame and fade out over the
+    /// last 255.
+    life_span: f32,
+    /// The force of the noise field, before the treble strengthens it.
+    noise_force: f32,
+}
+
+impl Default for Physics {
+    fn default() -> Self {
+        Self {
+            gravitational_constant: 0.025,
+            restitution: 0.8,
+            life_span: 512.0,
+            noise_force: 0.1,
+        }
+    }
+}
+
+/// What pulls the particles together, on top of the attraction between their species.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum InteractionMode {
+    /// The particles attract each other by gravity.
+    Gravity,
+    /// The particles flow like a liquid, pushed by their pressure.
+    Fluid,
+}
+
+/// How the particles themselves are drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ParticleStyle {
+    /// Each particle is drawn as a circle.
+    Circles,
+    /// The outlines of the blobs formed by neighbouring particles are drawn, like metaballs.
+    Blobs,
+}
+
+/// How the density accumulated by the particles is shown.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DensityMode {
+    /// Only the particles are drawn.
+    Off,
+    /// Only the density is drawn, like a long exposure.
+    Density,
+    /// The particles are drawn over the density.
+    Blended,
+}
+
+struct Audio {
+    sounds: Vec<audrey::read::BufFileReader>,
+    synth: BlipSynth,
+    analyzer: AudioAnalyzer,
+    features: Arc<Mutex<AudioFeatures>>,
 }
 
 struct Model {
     ps: ParticleSystem,
+    egui: Egui,
+    palette: CommandPalette,
+    help: HotkeyHelp,
+    recorder: Recorder,
+    /// Whether the build is drawn in a corner of the window.
+    show_build_info: bool,
+    settings: Settings,
+    audio_stream: Stream<Audio>,
+    audio_features: Arc<Mutex<AudioFeatures>>,
+    density: DensityGrid,
+    /// The colored density, while it is shown.
+    density_image: Option<RgbaImage>,
+    /// The server receiving the parameters set remotely, if it could be started.
+    osc: Option<OscServer>,
+    /// The paths of the particles, while they are recorded.
+    trajectories: Option<Trajectories>,
+    /// The grid the blobs are traced over, coarser than the density.
+    blob_grid: DensityGrid,
+    /// The segments of the outlines of the blobs, while they are drawn.
+    blob_outlines: Vec<[Vec2; 2]>,
+    /// Where the brush was on the last frame, in the world, while a button of the mouse is held.
+    brush_position: Option<Point2>,
+    /// Reports the frames to the supervisor, when the sketch is supervised.
+    watchdog: Option<Watchdog>,
 }
 
 fn model(app: &App) -> Model {
-    app.new_window()
+    rng::load("particles");
+    let window_id = app
+        .new_window()
         .title("Scratch")
-        .fullscreen()
+        .fullscreen_with(Some(DisplayOptions::load("particles").fullscreen(app)))
         .view(view)
         .key_pressed(key_pressed)
+        .raw_event(raw_window_event)
         .build()
         .unwrap();
 
+    let window = app.window(window_id).unwrap();
+    let egui = Egui::from_window(&window);
+
     let mut ps = ParticleSystem::new(app.window_rect());
 
-    let bounds = app.window_rect();
-    (0..INITIAL_PARTICLE_COUNT).for_each(|id| {
-        let origin = pt2(
-            random_range(bounds.left(), bounds.right()),
-            random_range(bounds.bottom(), bounds.top()),
+    // Initialise the state that we want to live on the audio thread.
+    let audio_host = Host::new();
+    let audio_features = Arc::new(Mutex::new(AudioFeatures::default()));
+    let audio_model = Audio {
+        sounds: Vec::new(),
+        synth: BlipSynth::new(),
+        analyzer: AudioAnalyzer::new(),
+        features: Arc::clone(&audio_features),
+    };
+
+    // Create audio stream
+    let audio_stream = audio_host
+        .new_output_stream(audio_model)
+        .sample_rate(48000)
+        .render(audio)
+        .build()
+        .unwrap();
+
+    let settings = Settings {
+        audio_file_path: "./data/audio.wav".to_owned(),
+        field_file_path: "./data/field.png".to_owned(),
+        field_strength: 2.0,
+        spawn_rate: 1,
+        onset_spawn_count: 20,
+        bass_gravity_modulation: 0.5,
+        treble_noise_modulation: 0.5,
+        sonify_collisions: false,
+        collision_volume: 0.1,
+        density_mode: DensityMode::Off,
+        density_persistence: 0.995,
+        density_style: Style {
+            hue_scale: 0.5,
+            ..Style::default()
+        },
+        matrix_file_path: "./data/interactions.txt".to_owned(),
+        species_strength: 0.02,
+        fixed_world: false,
+        trajectory_frames: 600,
+        trajectory_format: TrajectoryFormat::Svg,
+        brush_size: 40.0,
+        brush_strength: 0.2,
+        spray_count: 5,
+        interaction_mode: InteractionMode::Gravity,
+        fluid: Fluid::default(),
+        physics: Physics::default(),
+        particle_style: ParticleStyle::Circles,
+        blob_threshold: 0.05,
+        blob_smoothing: 2,
+    };
+    ps.spawn(INITIAL_PARTICLE_COUNT, settings.physics.life_span);
+
+    let density = density_grid(&ps.bounds, DENSITY_CELL_SIZE);
+    let blob_grid = density_grid(&ps.bounds, BLOB_CELL_SIZE);
+
+    // Listen to the parameters set remotely, within the ranges of their sliders
+    let osc = OscServer::start(OSC_PORT)
+        .inspect(|osc| {
+            osc.register("field_strength", 0.0..=10.0);
+            osc.register("bass_gravity_modulation", 0.0..=2.0);
+            osc.register("treble_noise_modulation", 0.0..=2.0);
+            osc.register("collision_volume", 0.0..=0.5);
+            osc.register("species_strength", 0.0..=0.1);
+            osc.register("gravitational_constant", 0.0..=0.2);
+            osc.register("noise_force", 0.0..=1.0);
+        })
+        .inspect_err(|e| eprintln!("{e}"))
+        .ok();
+
+    Model {
+        ps,
+        egui,
+        palette: CommandPalette::new(),
+        help: HotkeyHelp::new(),
+        recorder: Recorder::new(),
+        show_build_info: false,
+        watchdog: Watchdog::from_env("particles"),
+        settings,
+        audio_stream,
+        audio_features,
+        density,
+        density_image: None,
+        osc,
+        trajectories: None,
+        blob_grid,
+        blob_outlines: Vec::new(),
+        brush_position: None,
+    }
+}
+
+fn update(app: &App, m: &mut Model, update: Update) {
+    if let Some(watchdog) = &mut m.watchdog {
+        watchdog.beat();
+    }
+
+    m.egui.set_elapsed_time(update.since_start);
+    if let Some(action) = update_egui(m) {
+        perform(app, m, action);
+    }
+
+    // The window can be resized or made fullscreen at any time
+    fit_world_to_window(app, m);
+
+    // Apply the parameters set remotely
+    if let Some(osc) = &m.osc {
+        let settings = &mut m.settings;
+        [
+            ("field_strength", &mut settings.field_strength),
+            (
+                "bass_gravity_modulation",
+                &mut settings.bass_gravity_modulation,
+            ),
+            (
+                "treble_noise_modulation",
+                &mut settings.treble_noise_modulation,
+            ),
+            ("collision_volume", &mut settings.collision_volume),
+            ("species_strength", &mut settings.species_strength),
+            (
+                "gravitational_constant",
+                &mut settings.physics.gravitational_constant,
+            ),
+            ("noise_force", &mut settings.physics.noise_force),
+        ]
+        .into_iter()
+        .for_each(|(name, parameter)| {
+            if let Some(value) = osc.take(name) {
+                *parameter = value;
+            }
+        });
+    }
+
+    // Paint with the mouse before the particles move
+    brush(app, m);
+
+    // Each onset is taken once, even if the frame rate is lower than the rate of the buffers
+    let features = m.audio_features.lock().unwrap().take();
+    let settings = &m.settings;
+
+    // Add new particles, and a burst of particles on onsets
+    let spawn_count = match features.onset {
+        true => settings.spawn_rate + settings.onset_spawn_count,
+        false => settings.spawn_rate,
+    };
+    m.ps.spawn(spawn_count, settings.physics.life_span);
+
+    // Update the particle system
+    let physics = Physics {
+        gravitational_constant: settings.physics.gravitational_constant
+            * (1.0 + settings.bass_gravity_modulation * features.bass.ln_1p()),
+        ..settings.physics
+    };
+    let noise_strength = settings.treble_noise_modulation * features.treble.ln_1p();
+    let collisions = m.ps.update(
+        &physics,
+        noise_strength,
+        settings.field_strength,
+        settings.species_strength,
+        (settings.interaction_mode == InteractionMode::Fluid).then_some(&settings.fluid),
+    );
+
+    // Accumulate the positions of the particles
+    m.density_image = match settings.density_mode {
+        DensityMode::Off => None,
+        DensityMode::Density | DensityMode::Blended => {
+            m.density.fade(settings.density_persistence);
+            m.ps.deposit(&mut m.density);
+            Some(m.density.to_image(&settings.density_style))
+        }
+    };
+
+    // Trace the blobs formed by the particles
+    m.blob_outlines = match settings.particle_style {
+        ParticleStyle::Circles => Vec::new(),
+        ParticleStyle::Blobs => m.ps.blob_outlines(
+            &mut m.blob_grid,
+            settings.blob_threshold,
+            settings.blob_smoothing,
+        ),
+    };
+
+    // Play a blip for each collision, up to the number of voices of the synth
+    if settings.sonify_collisions && !collisions.is_empty() {
+        let blips: Vec<Blip> = collisions
+            .iter()
+            .take(BlipSynth::MAX_VOICES)
+            .map(|collision| collision.blip(&m.ps.bounds, settings.collision_volume))
+            .collect();
+        m.audio_stream
+            .send(move |audio| blips.into_iter().for_each(|blip| audio.synth.trigger(blip)))
+            .ok();
+    }
+
+    // Record the paths of the particles, saving them once all the frames are recorded
+    if let Some(trajectories) = &mut m.trajectories {
+        m.ps.record(trajectories);
+        if trajectories.is_finished() {
+            save_trajectories(app, m);
+        }
+    }
+}
+
+/// Sprays particles along the strokes of the left button of the mouse, and pushes them along the
+/// strokes of the right button.
+fn brush(app: &App, model: &mut Model) {
+    let buttons = &app.mouse.buttons;
+    let (spraying, pushing) = (buttons.left().is_down(), buttons.right().is_down());
+    let ctx = model.egui.ctx();
+    if !(spraying || pushing) || ctx.is_pointer_over_area() || ctx.wants_pointer_input() {
+        model.brush_position = None;
+        return;
+    }
+
+    // The strokes are drawn in the world, which is scaled to fit the window
+    let (scale, offset) = world_to_screen(&model.ps.bounds, &app.window_rect());
+    let position = (app.mouse.position() - offset) / scale;
+    let last_position = model.brush_position.replace(position).unwrap_or(position);
+
+    let settings = &model.settings;
+    if spraying {
+        model.ps.spray(
+            last_position,
+            position,
+            settings.brush_size,
+            settings.spray_count,
+            settings.physics.life_span,
         );
-        ps.add_particle(origin, id);
+    }
+    if pushing {
+        let force = (position - last_position) * settings.brush_strength;
+        model.ps.push(position, settings.brush_size, force);
+    }
+}
+
+/// Writes the trajectories recorded so far, and stops recording them.
+fn save_trajectories(app: &App, model: &mut Model) {
+    let Some(trajectories) = model.trajectories.take() else {
+        return;
+    };
+    let format = model.settings.trajectory_format;
+    let path = get_export_path(&app.exe_name().unwrap(), format.extension());
+    if let Err(e) = trajectories.write(&path, format, &model.ps.bounds) {
+        eprintln!("{e}: {path}");
+    }
+}
+
+/// Creates an empty density grid covering the bounds of the simulation with cells of a size.
+fn density_grid(bounds: &Rect, cell_size: f32) -> DensityGrid {
+    DensityGrid::new(
+        (bounds.w() / cell_size) as usize,
+        (bounds.h() / cell_size) as usize,
+    )
+}
+
+/// Fits the bounds of the simulation to the window, unless the world is fixed.
+fn fit_world_to_window(app: &App, model: &mut Model) {
+    let window = app.window_rect();
+    if model.settings.fixed_world || model.ps.bounds == window {
+        return;
+    }
+    model.ps.set_bounds(window);
+    model.density = density_grid(&window, DENSITY_CELL_SIZE);
+    model.blob_grid = density_grid(&window, BLOB_CELL_SIZE);
+}
+
+/// Returns the uniform scale and the translation fitting the world within the window, centered.
+fn world_to_screen(world: &Rect, window: &Rect) -> (f32, Vec2) {
+    let scale = (window.w() / world.w()).min(window.h() / world.h());
+    (scale, window.xy() - world.xy() * scale)
+}
+
+fn audio(audio: &mut Audio, buffer: &mut Buffer) {
+    let mut have_ended = vec![];
+    let len_frames = buffer.len_frames();
+
+    // Sum all of the sounds onto the buffer.
+    audio.sounds.iter_mut().enumerate().for_each(|(i, sound)| {
+        let mut frame_count = 0;
+        let file_frames = sound.frames::<[f32; 2]>().filter_map(Result::ok);
+        for (frame, file_frame) in buffer.frames_mut().zip(file_frames) {
+            for (sample, file_sample) in frame.iter_mut().zip(&file_frame) {
+                *sample += *file_sample;
+            }
+            frame_count += 1;
+        }
+
+        // If the sound yielded less samples than are in the buffer, it must have ended.
+        if frame_count < len_frames {
+            have_ended.push(i);
+        }
     });
 
-    Model { ps }
+    // Remove all sounds that have ended.
+    have_ended.into_iter().rev().for_each(|i| {
+        audio.sounds.remove(i);
+    });
+
+    // Mix in the blips of the collisions, which are analyzed along with the sounds
+    let sample_rate = buffer.sample_rate();
+    audio.synth.render(buffer.frames_mut(), sample_rate);
+
+    // Merge the audio channels and analyze them
+    let samples: Vec<_> = buffer.frames().flatten().cloned().collect();
+    let features = audio.analyzer.analyze(&samples, buffer.sample_rate());
+    audio.features.lock().unwrap().update(features);
 }
 
-fn update(app: &App, m: &mut Model, _update: Update) {
-    // Add a new particle
-    let bounds = app.window_rect();
-    let origin = pt2(
-        random_range(bounds.left(), bounds.right()),
-        random_range(bounds.bottom(), bounds.top()),
-    );
-    m.ps.add_particle(origin, app.elapsed_frames());
+fn update_egui(model: &mut Model) -> Option<Action> {
+    let ctx = model.egui.begin_frame();
+    let commands = commands(model.recorder.is_recording(), model.trajectories.is_some());
+    let mut action = model.palette.show(&ctx, &commands);
+    model.help.show(&ctx, &describe(&bindings()));
+    let settings = &mut model.settings;
 
-    // Update the particle system
-    m.ps.update();
+    // Generate the settings window
+    egui::Window::new("Settings")
+        .default_width(0.0)
+        .show(&ctx, |ui| {
+            ui.label(format!("particles: {}", model.ps.particles.len()));
+            if rng::show(ui) {
+                action = Some(Action::Restart);
+            }
+            ui.checkbox(&mut settings.fixed_world, "Fixed world")
+                .on_hover_text("Keep the bounds of the simulation when the window is resized");
+
+            ui.separator();
+
+            ui.add(LabelledSlider::new(
+                "brush_size",
+                &mut settings.brush_size,
+                5.0..=200.0,
+            ));
+            ui.add(LabelledSlider::new(
+                "brush_strength",
+                &mut settings.brush_strength,
+                0.0..=1.0,
+            ));
+            ui.add(LabelledSlider::new(
+                "spray_count",
+                &mut settings.spray_count,
+                1..=50,
+            ));
+
+            ui.separator();
+
+            ui.add(LabelledSlider::new(
+                "spawn_rate",
+                &mut settings.spawn_rate,
+                0..=20,
+            ));
+            ui.add(LabelledSlider::new(
+                "onset_spawn_count",
+                &mut settings.onset_spawn_count,
+                0..=100,
+            ));
+            ui.add(LabelledSlider::new(
+                "life_span",
+                &mut settings.physics.life_span,
+                64.0..=2048.0,
+            ));
+
+            ui.separator();
+
+            let physics = &mut settings.physics;
+            ui.add(LabelledSlider::new(
+                "gravitational_constant",
+                &mut physics.gravitational_constant,
+                0.0..=0.2,
+            ));
+            ui.add(LabelledSlider::new(
+                "restitution",
+                &mut physics.restitution,
+                0.0..=1.0,
+            ));
+            ui.add(LabelledSlider::new(
+                "noise_force",
+                &mut physics.noise_force,
+                0.0..=1.0,
+            ));
+
+            ui.add(Dropdown::new(
+                "interaction_mode",
+                &mut settings.interaction_mode,
+                [
+                    (InteractionMode::Gravity, "Gravity"),
+                    (InteractionMode::Fluid, "Fluid"),
+                ],
+            ));
+            if settings.interaction_mode == InteractionMode::Fluid {
+                let fluid = &mut settings.fluid;
+                ui.add(LabelledSlider::new(
+                    "smoothing_radius",
+                    &mut fluid.smoothing_radius,
+                    4.0..=80.0,
+                ));
+                ui.add(
+                    LabelledSlider::new("rest_density", &mut fluid.rest_density, 0.001..=0.2)
+                        .logarithmic(),
+                );
+                ui.add(LabelledSlider::new(
+                    "stiffness",
+                    &mut fluid.stiffness,
+                    0.0..=20.0,
+                ));
+                ui.add(LabelledSlider::new(
+                    "viscosity",
+                    &mut fluid.viscosity,
+                    0.0..=50.0,
+                ));
+                ui.add(LabelledSlider::new(
+                    "fluid_gravity",
+                    &mut fluid.gravity,
+                    0.0..=0.5,
+                ));
+            }
+
+            ui.add(LabelledSlider::new(
+                "bass_gravity_modulation",
+                &mut settings.bass_gravity_modulation,
+                0.0..=2.0,
+            ));
+            ui.add(LabelledSlider::new(
+                "treble_noise_modulation",
+                &mut settings.treble_noise_modulation,
+                0.0..=2.0,
+            ));
+
+            ui.separator();
+
+            if ui
+                .checkbox(&mut settings.sonify_collisions, "Sonify collisions")
+                .changed()
+                && settings.sonify_collisions
+            {
+                // The stream only plays once a file is loaded
+                model.audio_stream.play().ok();
+            }
+            ui.add(LabelledSlider::new(
+                "collision_volume",
+                &mut settings.collision_volume,
+                0.0..=0.5,
+            ));
+
+            ui.separator();
+
+            let interactions = &mut model.ps.interactions;
+            let mut species = interactions.species();
+            if ui
+                .add(LabelledSlider::new(
+                    "species",
+                    &mut species,
+                    1..=InteractionMatrix::MAX_SPECIES,
+                ))
+                .changed()
+            {
+                let mut resized = interactions.clone();
+                resized.resize(species);
+                model.ps.set_interactions(resized);
+            }
+            ui.add(LabelledSlider::new(
+                "species_strength",
+                &mut settings.species_strength,
+                0.0..=0.1,
+            ));
+
+            // Each row is how much a species is attracted to each species
+            let interactions = &mut model.ps.interactions;
+            let species = interactions.species();
+            if species > 1 {
+                egui::Grid::new("interactions").show(ui, |ui| {
+                    (0..species).for_each(|a| {
+                        (0..species).for_each(|b| {
+                            ui.add(
+                                egui::DragValue::new(interactions.get_mut(a, b))
+                                    .speed(0.01)
+                                    .clamp_range(-1.0..=1.0),
+                            );
+                        });
+                        ui.end_row();
+                    });
+                });
+            }
+
+            ui.add(FilePicker::new(
+                "Interaction matrix path",
+                &mut settings.matrix_file_path,
+                &["txt"],
+            ));
+            ui.horizontal(|ui| {
+                if ui.button("Randomize").clicked() {
+                    action = Some(Action::RandomizeInteractions);
+                }
+                if ui.button("Save").clicked() {
+                    action = Some(Action::SaveInteractions);
+                }
+                if ui.button("Load").clicked() {
+                    action = Some(Action::LoadInteractions);
+                }
+            });
+
+            ui.separator();
+
+            ui.add(Dropdown::new(
+                "particle_style",
+                &mut settings.particle_style,
+                [
+                    (ParticleStyle::Circles, "Circles"),
+                    (ParticleStyle::Blobs, "Blobs"),
+                ],
+            ));
+            ui.add(
+                LabelledSlider::new("blob_threshold", &mut settings.blob_threshold, 0.005..=1.0)
+                    .logarithmic(),
+            );
+            ui.add(LabelledSlider::new(
+                "blob_smoothing",
+                &mut settings.blob_smoothing,
+                0..=8,
+            ));
+
+            ui.separator();
+
+            ui.add(Dropdown::new(
+                "density_mode",
+                &mut settings.density_mode,
+                [
+                    (DensityMode::Off, "Off"),
+                    (DensityMode::Density, "Density"),
+                    (DensityMode::Blended, "Blended"),
+                ],
+            ));
+            ui.add(LabelledSlider::new(
+                "density_persistence",
+                &mut settings.density_persistence,
+                0.9..=1.0,
+            ));
+            ui.horizontal(|ui| {
+                if ui.button("Randomize colors").clicked() {
+                    settings.density_style = Style::random();
+                }
+                if ui.button("Clear density").clicked() {
+                    action = Some(Action::ClearDensity);
+                }
+            });
+
+            ui.separator();
+
+            ui.add(LabelledSlider::new(
+                "field_strength",
+                &mut settings.field_strength,
+                0.0..=10.0,
+            ));
+
+            ui.add(FilePicker::new(
+                "Vector field path (image or csv)",
+                &mut settings.field_file_path,
+                &["png", "jpg", "jpeg", "csv"],
+            ));
+
+            ui.horizontal(|ui| {
+                if ui.button("Load field").clicked() {
+                    action = Some(Action::LoadField);
+                }
+                if ui.button("Clear field").clicked() {
+                    action = Some(Action::ClearField);
+                }
+            });
+
+            ui.separator();
+
+            ui.add(LabelledSlider::new(
+                "trajectory_frames",
+                &mut settings.trajectory_frames,
+                10..=3600,
+            ));
+            ui.add(Dropdown::new(
+                "trajectory_format",
+                &mut settings.trajectory_format,
+                [
+                    (TrajectoryFormat::Svg, "Svg"),
+                    (TrajectoryFormat::Csv, "Csv"),
+                ],
+            ));
+            match &model.trajectories {
+                Some(trajectories) => {
+                    ui.add(egui::ProgressBar::new(trajectories.progress()));
+                    if ui.button("Stop and save trajectories").clicked() {
+                        action = Some(Action::ToggleTrajectories);
+                    }
+                }
+                None => {
+                    if ui.button("Record trajectories").clicked() {
+                        action = Some(Action::ToggleTrajectories);
+                    }
+                }
+            }
+
+            ui.separator();
+
+            ui.add(UiScale);
+            model.recorder.show(ui);
+
+            ui.separator();
+
+            ui.add(FilePicker::new(
+                "Audio path",
+                &mut settings.audio_file_path,
+                AUDIO_EXTENSIONS,
+            ));
+
+            if ui.button("Load file").clicked() {
+                action = Some(Action::LoadAudio);
+            }
+        });
+
+    action
 }
 
 fn view(app: &App, m: &Model, frame: Frame) {
     // Begin drawing
-    let draw = app.draw();
-    draw.background().color(WHITE);
+    let screen = app.draw();
+    screen.background().color(WHITE);
 
-    m.ps.draw(&draw);
+    // Draw the world of the simulation within the window
+    let (scale, offset) = world_to_screen(&m.ps.bounds, &app.window_rect());
+    let draw = screen.xy(offset).scale(scale);
+
+    // The density covers the world, stretched from the size of its grid
+    if let Some(image) = &m.density_image {
+        let texture = create_texture(app.main_window(), image.clone());
+        draw.texture(&texture)
+            .xy(m.ps.bounds.xy())
+            .wh(m.ps.bounds.wh());
+    }
+    if m.settings.density_mode != DensityMode::Density {
+        match m.settings.particle_style {
+            ParticleStyle::Circles => m.ps.draw(&draw),
+            ParticleStyle::Blobs => ParticleSystem::draw_outlines(&draw, &m.blob_outlines),
+        }
+    }
+
+    // Show the reach of the brush while painting
+    if let Some(position) = m.brush_position {
+        draw.ellipse()
+            .xy(position)
+            .radius(m.settings.brush_size)
+            .no_fill()
+            .stroke(GRAY)
+            .stroke_weight(1.0);
+    }
+
+    if m.show_build_info {
+        build_info::draw_overlay(&screen, app.window_rect());
+    }
 
     // Write the result of our drawing to the window's frame.
-    draw.to_frame(app, &frame).unwrap();
+    screen.to_frame(app, &frame).unwrap();
+    m.recorder.capture(&frame);
+    m.egui.draw_to_frame(&frame).unwrap();
+}
+
+fn raw_window_event(_app: &App, model: &mut Model, event: &nannou::winit::event::WindowEvent) {
+    // Let egui handle things like keyboard and mouse input.
+    model.egui.handle_raw_event(event);
+}
+
+/// The actions that can be run from the keyboard or the command palette.
+#[derive(Clone, Copy)]
+enum Action {
+    LoadAudio,
+    LoadField,
+    ClearField,
+    ClearDensity,
+    Restart,
+    RandomizeInteractions,
+    SaveInteractions,
+    LoadInteractions,
+    ToggleTrajectories,
+    ToggleRecording,
+    SaveFrame,
+    ToggleBuildInfo,
+    Quit,
+}
+
+fn commands(recording: bool, recording_trajectories: bool) -> Vec<Command<Action>> {
+    vec![
+        Command::new("Load audio file", Action::LoadAudio),
+        Command::new("Load vector field", Action::LoadField),
+        Command::new("Clear vector field", Action::ClearField),
+        Command::new("Clear density", Action::ClearDensity),
+        Command::new("Restart from the seed", Action::Restart),
+        Command::new(
+            "Randomize species interactions",
+            Action::RandomizeInteractions,
+        ),
+        Command::new("Save species interactions", Action::SaveInteractions),
+        Command::new("Load species interactions", Action::LoadInteractions),
+        match recording_trajectories {
+            false => Command::new("Record trajectories", Action::ToggleTrajectories),
+            true => Command::new("Stop and save trajectories", Action::ToggleTrajectories),
+        },
+        match recording {
+            false => Command::new("Start recording", Action::ToggleRecording),
+            true => Command::new("Stop recording", Action::ToggleRecording),
+        },
+        Command::new("Save frame", Action::SaveFrame),
+        Command::new("Toggle build info", Action::ToggleBuildInfo),
+        Command::new("Quit", Action::Quit),
+    ]
 }
 
-fn key_pressed(app: &App, _model: &mut Model, key: Key) {
-    match key {
-        Key::Q => app.quit(),
-        Key::S => {
-            app.main_window()
-                .capture_frame(get_save_path(&app.exe_name().unwrap()));
+/// The hotkeys of the sketch, listed by the help overlay.
+fn bindings() -> Vec<Binding<Action>> {
+    vec![
+        Binding::new(
+            &[RECORD_KEY],
+            "Start or stop recording the frames",
+            Action::ToggleRecording,
+        ),
+        Binding::new(&[Key::S], "Save the current frame", Action::SaveFrame),
+        Binding::new(
+            &[BUILD_INFO_KEY],
+            "Show or hide the build in a corner",
+            Action::ToggleBuildInfo,
+        ),
+        Binding::new(
+            &[Key::T],
+            "Start recording the trajectories, or stop and save them",
+            Action::ToggleTrajectories,
+        ),
+        Binding::new(&[Key::Q], "Quit", Action::Quit),
+    ]
+}
+
+fn perform(app: &App, model: &mut Model, action: Action) {
+    match action {
+        Action::LoadAudio => {
+            let audio_stream = &mut model.audio_stream;
+            // Load the audio file if possible
+            if let Ok(sound) = audrey::open(&model.settings.audio_file_path) {
+                audio_stream
+                    .send(move |audio| {
+                        audio.sounds.clear();
+                        audio.sounds.push(sound);
+                    })
+                    .ok();
+                audio_stream.play().unwrap();
+            } else {
+                eprintln!("Failed to load audio file");
+            };
+        }
+        Action::LoadField => match VectorField::read(&model.settings.field_file_path) {
+            Ok(field) => model.ps.field = Some(field),
+            Err(e) => eprintln!("{e}: {}", model.settings.field_file_path),
+        },
+        Action::ClearField => model.ps.field = None,
+        Action::ClearDensity => model.density.clear(),
+        Action::Restart => {
+            // The particles are drawn again in the same order, so they start the same
+            let seed = rng::seed();
+            println!("Restarting from the seed {seed}");
+            rng::set_seed(seed);
+            model.ps.particles.clear();
+            model.ps.next_id = 0;
+            model
+                .ps
+                .spawn(INITIAL_PARTICLE_COUNT, model.settings.physics.life_span);
+            model.density.clear();
+        }
+        Action::RandomizeInteractions => {
+            // A single species has no one else to interact with
+            let species = model.ps.interactions.species().max(2);
+            model
+                .ps
+                .set_interactions(InteractionMatrix::random(species));
         }
-        _other_key => {}
+        Action::SaveInteractions => {
+            let path = &model.settings.matrix_file_path;
+            match model.ps.interactions.write(path) {
+                Ok(()) => println!("Saving species interactions to: {path}"),
+                Err(e) => eprintln!("{e}: {path}"),
+            }
+        }
+        Action::LoadInteractions => {
+            let path = &model.settings.matrix_file_path;
+            match InteractionMatrix::read(path) {
+                Ok(interactions) => model.ps.set_interactions(interactions),
+                Err(e) => eprintln!("{e}: {path}"),
+            }
+        }
+        Action::ToggleTrajectories => match model.trajectories {
+            Some(_) => save_trajectories(app, model),
+            None => {
+                model.trajectories = Some(Trajectories::new(model.settings.trajectory_frames));
+            }
+        },
+        Action::ToggleRecording => {
+            model
+                .recorder
+                .toggle(&app.exe_name().unwrap(), app.main_window().device());
+        }
+        Action::SaveFrame => {
+            model
+                .recorder
+                .save_frame(get_save_path(&app.exe_name().unwrap()), None);
+        }
+        Action::ToggleBuildInfo => model.show_build_info = !model.show_build_info,
+        Action::Quit => {
+            model.recorder.stop(app.main_window().device());
+            app.quit();
+        }
+    }
+}
+
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    if key == PALETTE_KEY {
+        model.palette.toggle();
+        return;
+    }
+    // The keys are used to type in the palette
+    if model.palette.is_open() {
+        return;
+    }
+
+    if key == HELP_KEY {
+        model.help.toggle();
+        return;
+    }
+
+    if let Some(action) = action_for(&bindings(), key) {
+        perform(app, model, action);
     }
 }
@@ -0,0 +1,449 @@
+use std::{
+    io::Write,
+    process::{Child, Command, Stdio},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU32, Ordering},
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+    },
+    thread::JoinHandle,
+};
+
+use nannou::{
+    image::{self, RgbaImage, imageops::FilterType},
+    prelude::*,
+};
+use nannou_egui::egui;
+
+use crate::{build_info::save_png, timestamp, utils::widgets::LabelledSlider};
+
+/// The key starting and stopping the recording in every sketch.
+pub const RECORD_KEY: Key = Key::F2;
+
+/// Records the frames of a window, as a numbered PNG sequence or as a video encoded by ffmpeg, and
+/// saves single frames.
+///
+/// The frames are read back from the GPU and written on background threads. They wait in a queue
+/// of bounded length, so a slow disk or encoder drops frames instead of stalling the render loop.
+///
+/// The frames are captured in physical pixels, so on HiDPI displays they are as sharp as the
+/// window, then scaled by the capture scale.
+pub struct Recorder {
+    /// Whether to pipe the frames to ffmpeg when it is available.
+    pub use_ffmpeg: bool,
+    /// The frame rate of the videos encoded by ffmpeg.
+    pub frame_rate: u32,
+    /// How many times larger than the physical pixels of the window the frames are saved.
+    pub capture_scale: f32,
+    capturer: wgpu::TextureCapturer,
+    resolver: Mutex<FrameResolver>,
+    /// The capture of the previous frame, which can only be read once the frame is submitted.
+    pending: Mutex<Option<wgpu::TextureSnapshot>>,
+    session: Option<Session>,
+    /// Where the next captured frame is saved to, when a single frame was requested.
+    frame_request: Mutex<Option<FrameRequest>>,
+    /// The capture of the single frame requested for the previous frame, and where it is saved to.
+    pending_frame: Mutex<Option<(wgpu::TextureSnapshot, FrameRequest)>>,
+}
+
+/// A single frame to save.
+struct FrameRequest {
+    path: String,
+    /// The summary of the parameters of the sketch stamped into the file.
+    parameters: Option<String>,
+}
+
+/// A recording in progress.
+struct Session {
+    sender: SyncSender<RgbaImage>,
+    writer: JoinHandle<()>,
+    dropped_frames: Arc<AtomicU32>,
+}
+
+/// Where the frames of a recording are written.
+enum Output {
+    Sequence(String),
+    Video { path: String, frame_rate: u32 },
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Recorder {
+    /// The number of frames that can wait to be written before frames are dropped.
+    const QUEUE_LENGTH: usize = 16;
+
+    pub fn new() -> Self {
+        Self {
+            use_ffmpeg: true,
+            frame_rate: 60,
+            capture_scale: 1.0,
+            capturer: wgpu::TextureCapturer::default(),
+            resolver: Mutex::default(),
+            pending: Mutex::new(None),
+            session: None,
+            frame_request: Mutex::new(None),
+            pending_frame: Mutex::new(None),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.session.is_some()
+    }
+
+    /// The number of frames of the recording in progress that were dropped because the queue was
+    /// full.
+    pub fn dropped_frames(&self) -> u32 {
+        self.session
+            .as_ref()
+            .map_or(0, |session| session.dropped_frames.load(Ordering::Relaxed))
+    }
+
+    /// Starts a recording, named after the given prefix.
+    pub fn start(&mut self, prefix: &str) -> Result<(), &'static str> {
+        if self.is_recording() {
+            return Err("A recording is already in progress");
+        }
+
+        let output = match self.use_ffmpeg && is_ffmpeg_available() {
+            true => {
+                let path = format!("./{}_{:?}.mp4", prefix, timestamp());
+                println!("Saving video to: {}", path);
+                Output::Video {
+                    path,
+                    frame_rate: self.frame_rate,
+                }
+            }
+            false => {
+                let directory = crate::get_save_directory(prefix);
+                std::fs::create_dir_all(&directory)
+                    .map_err(|_| "Failed to create the directory of the frames")?;
+                Output::Sequence(directory)
+            }
+        };
+
+        let (sender, receiver) = mpsc::sync_channel(Self::QUEUE_LENGTH);
+        let scale = self.capture_scale;
+        let writer = std::thread::spawn(move || write_frames(receiver, output, scale));
+        self.session = Some(Session {
+            sender,
+            writer,
+            dropped_frames: Arc::new(AtomicU32::new(0)),
+        });
+        Ok(())
+    }
+
+    /// Stops the recording in progress, waiting for its frames to be written.
+    pub fn stop(&mut self, device: &wgpu::Device) {
+        let Some(session) = self.session.take() else {
+            return;
+        };
+
+        // The last frame was submitted since it was captured
+        if let Some(snapshot) = self.pending.lock().unwrap().take() {
+            read_snapshot(snapshot, &session);
+        }
+        if self.capturer.await_active_snapshots(device).is_err() {
+            eprintln!("Timed out while reading the last frames of the recording");
+        }
+
+        let Session {
+            sender,
+            writer,
+            dropped_frames,
+        } = session;
+        drop(sender);
+        writer.join().ok();
+        match dropped_frames.load(Ordering::Relaxed) {
+            0 => println!("Recording complete"),
+            dropped => println!("Recording complete, {dropped} frames were dropped"),
+        }
+    }
+
+    /// Starts a recording if none is in progress, else stops it.
+    pub fn toggle(&mut self, prefix: &str, device: &wgpu::Device) {
+        match self.is_recording() {
+            true => self.stop(device),
+            false => {
+                if let Err(e) = self.start(prefix) {
+                    eprintln!("{e}");
+                }
+            }
+        }
+    }
+
+    /// Saves the next captured frame to a PNG file at the capture scale, stamped with the build and
+    /// the parameters of the sketch when they are given.
+    pub fn save_frame(&self, path: String, parameters: Option<String>) {
+        *self.frame_request.lock().unwrap() = Some(FrameRequest { path, parameters });
+    }
+
+    /// Saves an image rendered by a sketch at the capture scale, like the captured frames.
+    pub fn save_image(&self, image: &RgbaImage, path: &str, parameters: Option<&str>) {
+        let image = scale_capture(image.clone(), self.capture_scale);
+        if let Err(e) = save_png(&image, path, parameters) {
+            eprintln!("Failed to save {path}: {e}");
+        }
+    }
+
+    /// Shows the settings of the captures and the state of the recording.
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        ui.add(
+            LabelledSlider::new("Capture scale", &mut self.capture_scale, 0.25..=4.0).logarithmic(),
+        )
+        .on_hover_text("Scales the saved frames and recordings, on top of the display scale");
+        ui.add_enabled(
+            !self.is_recording(),
+            egui::Checkbox::new(&mut self.use_ffmpeg, "Encode recordings with ffmpeg"),
+        );
+        if self.is_recording() {
+            ui.label(format!(
+                "Recording, {} frames dropped",
+                self.dropped_frames()
+            ));
+        }
+    }
+
+    /// Captures what was drawn to the frame so far if a recording is in progress or a frame was
+    /// requested.
+    ///
+    /// Calling this before drawing the GUI keeps it out of the captures.
+    pub fn capture(&self, frame: &Frame) {
+        // The captures of the previous frame were submitted with it, and can now be read
+        if let Some((snapshot, request)) = self.pending_frame.lock().unwrap().take() {
+            save_snapshot(snapshot, request, self.capture_scale);
+        }
+        if let Some(session) = &self.session
+            && let Some(snapshot) = self.pending.lock().unwrap().take()
+        {
+            read_snapshot(snapshot, session);
+        }
+
+        let request = self.frame_request.lock().unwrap().take();
+        if !self.is_recording() && request.is_none() {
+            return;
+        }
+
+        let device = frame.device_queue_pair().device();
+        let mut encoder = frame.command_encoder();
+        let mut resolver = self.resolver.lock().unwrap();
+        let texture = resolver.resolve(frame, &mut encoder);
+        if let Some(request) = request {
+            let snapshot = self.capturer.capture(device, &mut encoder, texture);
+            *self.pending_frame.lock().unwrap() = Some((snapshot, request));
+        }
+        if self.is_recording() {
+            let snapshot = self.capturer.capture(device, &mut encoder, texture);
+            *self.pending.lock().unwrap() = Some(snapshot);
+        }
+    }
+}
+
+/// The texture the multisampled frames are resolved to, as they cannot be captured directly.
+#[derive(Default)]
+pub(crate) struct FrameResolver {
+    resolved: Option<(wgpu::Texture, wgpu::TextureView)>,
+}
+
+impl FrameResolver {
+    /// Returns the texture holding what was drawn to the frame so far, resolving it first when
+    /// the frame is multisampled.
+    pub(crate) fn resolve<'a>(
+        &'a mut self,
+        frame: &'a Frame,
+        encoder: &mut wgpu::CommandEncoder,
+    ) -> &'a wgpu::Texture {
+        if frame.resolve_target().is_none() {
+            return frame.texture();
+        }
+        let size = frame.texture_size();
+        if self
+            .resolved
+            .as_ref()
+            .is_none_or(|(texture, _)| texture.size() != size)
+        {
+            let texture = wgpu::TextureBuilder::new()
+                .size(size)
+                .format(Frame::TEXTURE_FORMAT)
+                .usage(
+                    wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+                )
+                .build(frame.device_queue_pair().device());
+            let view = texture.view().build();
+            self.resolved = Some((texture, view));
+        }
+        let (texture, view) = self.resolved.as_ref().unwrap();
+        wgpu::resolve_texture(frame.texture_view(), view, encoder);
+        texture
+    }
+}
+
+/// Scales a captured image, keeping it at least a pixel wide and tall.
+pub fn scale_capture(image: RgbaImage, scale: f32) -> RgbaImage {
+    if scale == 1.0 {
+        return image;
+    }
+    let (width, height) = image.dimensions();
+    let scaled = |size: u32| ((size as f32 * scale).round() as u32).max(1);
+    image::imageops::resize(&image, scaled(width), scaled(height), FilterType::Lanczos3)
+}
+
+/// Reads a single captured frame back from the GPU and saves it, once its frame is submitted.
+fn save_snapshot(snapshot: wgpu::TextureSnapshot, request: FrameRequest, scale: f32) {
+    let result = snapshot.read(move |result| match result {
+        Ok(image) => {
+            let FrameRequest { path, parameters } = request;
+            let image = scale_capture(image.to_owned(), scale);
+            if let Err(e) = save_png(&image, &path, parameters.as_deref()) {
+                eprintln!("Failed to save {path}: {e}");
+            }
+        }
+        Err(e) => eprintln!("Failed to read the frame: {e:?}"),
+    });
+    if result.is_err() {
+        eprintln!("Timed out while reading the frame");
+    }
+}
+
+/// Reads a captured frame back from the GPU and queues it to be written.
+fn read_snapshot(snapshot: wgpu::TextureSnapshot, session: &Session) {
+    let sender = session.sender.clone();
+    let dropped_frames = Arc::clone(&session.dropped_frames);
+    let result = snapshot.read(move |result| match result {
+        Ok(image) => {
+            if let Err(TrySendError::Full(_)) = sender.try_send(image.to_owned()) {
+                dropped_frames.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        Err(e) => eprintln!("Failed to read a frame of the recording: {e:?}"),
+    });
+    if result.is_err() {
+        eprintln!("Timed out while reading a frame of the recording");
+    }
+}
+
+/// Whether ffmpeg can be run.
+fn is_ffmpeg_available() -> bool {
+    Command::new("ffmpeg")
+        .arg("-version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Starts ffmpeg to encode frames of the given size read from its standard input.
+fn spawn_ffmpeg(path: &str, (width, height): (u32, u32), frame_rate: u32) -> Option<Child> {
+    Command::new("ffmpeg")
+        .args([
+            "-y",
+            "-loglevel",
+            "error",
+            "-f",
+            "rawvideo",
+            "-pixel_format",
+            "rgba",
+        ])
+        .args(["-video_size", &format!("{width}x{height}")])
+        .args(["-framerate", &frame_rate.to_string(), "-i", "-"])
+        // The chroma subsampling of most players needs an even size
+        .args([
+            "-vf",
+            "pad=ceil(iw/2)*2:ceil(ih/2)*2",
+            "-pix_fmt",
+            "yuv420p",
+            path,
+        ])
+        .stdin(Stdio::piped())
+        .spawn()
+        .inspect_err(|e| eprintln!("Failed to start ffmpeg: {e}"))
+        .ok()
+}
+
+/// Writes the frames received until the recording stops, scaled by `scale`.
+fn write_frames(receiver: Receiver<RgbaImage>, output: Output, scale: f32) {
+    // The video is encoded at the size of its first frame
+    let mut encoder: Option<(Child, (u32, u32))> = None;
+
+    receiver
+        .iter()
+        .map(|image| scale_capture(image, scale))
+        .enumerate()
+        .for_each(|(i, image)| match &output {
+            Output::Sequence(directory) => {
+                let path = format!("{directory}/frame_{i:05}.png");
+                if let Err(e) = save_png(&image, &path, None) {
+                    eprintln!("Failed to save {path}: {e}");
+                }
+            }
+            Output::Video { path, frame_rate } => {
+                if encoder.is_none() {
+                    let size = image.dimensions();
+                    encoder = spawn_ffmpeg(path, size, *frame_rate).map(|child| (child, size));
+                }
+                let Some((child, size)) = &mut encoder else {
+                    return;
+                };
+                if image.dimensions() != *size {
+                    eprintln!("Skipped a frame of a different size than the video");
+                    return;
+                }
+                let stdin = child.stdin.as_mut().unwrap();
+                if let Err(e) = stdin.write_all(image.as_raw()) {
+                    eprintln!("Failed to write a frame to ffmpeg: {e}");
+                }
+            }
+        });
+
+    // Closing the input of ffmpeg finishes the video
+    if let Some((mut child, _)) = encoder {
+        drop(child.stdin.take());
+        if let Err(e) = child.wait() {
+            eprintln!("Failed to finish the video: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_are_written_in_order() {
+        let directory = std::env::temp_dir().join(format!("recording_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let (sender, receiver) = mpsc::sync_channel(Recorder::QUEUE_LENGTH);
+        let output = Output::Sequence(directory.to_str().unwrap().to_owned());
+        let writer = std::thread::spawn(move || write_frames(receiver, output, 1.0));
+        (0..3u8).for_each(|i| {
+            let image = RgbaImage::from_pixel(4, 2, nannou::image::Rgba([i, 0, 0, 255]));
+            sender.send(image).unwrap();
+        });
+        drop(sender);
+        writer.join().unwrap();
+
+        let reds: Vec<u8> = (0..3)
+            .map(|i| {
+                let path = directory.join(format!("frame_{i:05}.png"));
+                nannou::image::open(path)
+                    .unwrap()
+                    .to_rgba8()
+                    .get_pixel(0, 0)[0]
+            })
+            .collect();
+        std::fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(reds, [0, 1, 2]);
+    }
+
+    #[test]
+    fn captures_are_scaled() {
+        let image = RgbaImage::from_pixel(8, 3, nannou::image::Rgba([255, 0, 0, 255]));
+        assert_eq!(scale_capture(image.clone(), 2.0).dimensions(), (16, 6));
+        assert_eq!(scale_capture(image.clone(), 0.25).dimensions(), (2, 1));
+        assert_eq!(scale_capture(image.clone(), 1.0), image);
+    }
+}
@@ -0,0 +1,318 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use nannou::image::Rgba;
+use nannou_egui::egui;
+use point_cloud_renderer::color::{ColorSpace, LinearImage, encode_srgb};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    build_info::{png_encoder, save_png, version},
+    timestamp,
+    utils::widgets::Dropdown,
+};
+
+/// The file formats the images of the sketches can be saved in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImageFormat {
+    /// An 8-bit sRGB PNG file, which any viewer opens.
+    #[default]
+    Png,
+    /// A 16-bit PNG file, precise enough for smooth ramps to be graded without banding.
+    Png16,
+    /// An OpenEXR file of 32-bit floats in linear space, which also keeps the channels above 1.
+    Exr,
+}
+
+impl ImageFormat {
+    pub const ALL: [Self; 3] = [Self::Png, Self::Png16, Self::Exr];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Png => "PNG",
+            Self::Png16 => "PNG 16-bit",
+            Self::Exr => "OpenEXR",
+        }
+    }
+
+    /// Reads a format from its name on the command line: `png`, `png16` or `exr`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "png" => Some(Self::Png),
+            "png16" => Some(Self::Png16),
+            "exr" => Some(Self::Exr),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png | Self::Png16 => "png",
+            Self::Exr => "exr",
+        }
+    }
+}
+
+/// How an image is saved, chosen for each save.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageOptions {
+    pub format: ImageFormat,
+    /// Whether 16-bit PNG files keep the linear channels of the image instead of encoding them to
+    /// sRGB, their gamma being set to 1 so viewers still show them right.
+    ///
+    /// The 8-bit PNG files are always sRGB, where their precision is spent best, and the OpenEXR
+    /// files always linear, as the format expects.
+    pub linear: bool,
+}
+
+impl ImageOptions {
+    /// Whether the images are saved with more than 8 bits per channel, so they should be rendered
+    /// to a linear image rather than captured.
+    pub fn is_high_precision(&self) -> bool {
+        self.format != ImageFormat::Png
+    }
+
+    /// Returns the path of a new file to save an image to, with the extension of the format.
+    pub fn save_path(&self, prefix: &str) -> String {
+        let path = format!("./{}_{:?}.{}", prefix, timestamp(), self.format.extension());
+        println!("Saving image to: {}", path);
+        path
+    }
+
+    /// Saves an image in the chosen format, stamped with the build and with the parameters that
+    /// produced it when they are given, like [`save_png`] does.
+    pub fn save(
+        &self,
+        image: &LinearImage,
+        path: &str,
+        parameters: Option<&str>,
+    ) -> std::io::Result<()> {
+        match self.format {
+            ImageFormat::Png => save_png(&encode_srgb(image), path, parameters),
+            ImageFormat::Png16 => save_png16(image, path, parameters, self.linear),
+            ImageFormat::Exr => save_exr(image, path, parameters),
+        }
+    }
+
+    /// Shows the choice of the format.
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        ui.add(Dropdown::new(
+            "Image format",
+            &mut self.format,
+            ImageFormat::ALL.map(|format| (format, format.name())),
+        ));
+        ui.add_enabled(
+            self.format == ImageFormat::Png16,
+            egui::Checkbox::new(&mut self.linear, "Linear color"),
+        )
+        .on_hover_text("Keeps the linear channels instead of encoding them to sRGB");
+    }
+}
+
+/// Saves an image as a 16-bit PNG file, stamped like [`save_png`] does.
+///
+/// The channels are encoded to sRGB unless `linear` is set, in which case they are saved as they
+/// are with a gamma of 1.
+pub fn save_png16(
+    image: &LinearImage,
+    path: &str,
+    parameters: Option<&str>,
+    linear: bool,
+) -> std::io::Result<()> {
+    let mut encoder = png_encoder(path, image.width(), image.height(), parameters)?;
+    encoder.set_depth(png::BitDepth::Sixteen);
+    let color_space = match linear {
+        true => {
+            encoder.set_source_gamma(png::ScaledFloat::new(1.0));
+            ColorSpace::Linear
+        }
+        false => ColorSpace::Srgb,
+    };
+
+    // The samples of PNG files are big-endian
+    let quantize = |channel: f32| (channel.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16;
+    let data: Vec<u8> = image
+        .pixels()
+        .flat_map(|&Rgba(color)| color_space.from_linear(color))
+        .flat_map(|channel| quantize(channel).to_be_bytes())
+        .collect();
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Saves an image as an uncompressed OpenEXR file of 32-bit float channels in linear space.
+///
+/// The build is saved as the `software` attribute of the file and the parameters as its standard
+/// `comments` attribute.
+pub fn save_exr(image: &LinearImage, path: &str, parameters: Option<&str>) -> std::io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    let (width, height) = image.dimensions();
+    let data_window = [0, 0, width as i32 - 1, height as i32 - 1]
+        .into_iter()
+        .flat_map(i32::to_le_bytes)
+        .collect::<Vec<_>>();
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&EXR_MAGIC);
+    // Version 2, a single part of scan lines
+    header.extend_from_slice(&2u32.to_le_bytes());
+    let mut channels = Vec::new();
+    for name in EXR_CHANNELS {
+        channels.extend_from_slice(name.as_bytes());
+        channels.push(0);
+        channels.extend_from_slice(&EXR_FLOAT.to_le_bytes());
+        // Not linearly quantized, and reserved bytes
+        channels.extend_from_slice(&[0; 4]);
+        // Sampled at every pixel
+        channels.extend_from_slice(&1i32.to_le_bytes());
+        channels.extend_from_slice(&1i32.to_le_bytes());
+    }
+    channels.push(0);
+    exr_attribute(&mut header, "channels", "chlist", &channels);
+    exr_attribute(&mut header, "compression", "compression", &[0]);
+    exr_attribute(&mut header, "dataWindow", "box2i", &data_window);
+    exr_attribute(&mut header, "displayWindow", "box2i", &data_window);
+    exr_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    exr_attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    exr_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    exr_attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    exr_attribute(&mut header, "software", "string", version().as_bytes());
+    if let Some(parameters) = parameters {
+        exr_attribute(&mut header, "comments", "string", parameters.as_bytes());
+    }
+    header.push(0);
+    file.write_all(&header)?;
+
+    // Each scan line is a chunk, preceded by its row and its size, and the chunks are indexed by
+    // their offsets in the file
+    let line_size = (EXR_CHANNELS.len() * width as usize * size_of::<f32>()) as u64;
+    let chunk_size = 2 * size_of::<i32>() as u64 + line_size;
+    let first_chunk = header.len() as u64 + height as u64 * size_of::<u64>() as u64;
+    for y in 0..height as u64 {
+        file.write_all(&(first_chunk + y * chunk_size).to_le_bytes())?;
+    }
+    for y in 0..height {
+        file.write_all(&(y as i32).to_le_bytes())?;
+        file.write_all(&(line_size as i32).to_le_bytes())?;
+        // The channels of a line follow each other, in the order they are declared
+        for index in EXR_CHANNEL_INDICES {
+            for x in 0..width {
+                file.write_all(&image.get_pixel(x, y)[index].to_le_bytes())?;
+            }
+        }
+    }
+    file.flush()
+}
+
+/// The first bytes of an OpenEXR file.
+const EXR_MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+/// The type of the 32-bit float channels.
+const EXR_FLOAT: i32 = 2;
+/// The channels of the files, which must be declared in alphabetical order.
+const EXR_CHANNELS: [&str; 4] = ["A", "B", "G", "R"];
+/// The index in an RGBA pixel of each channel of the files.
+const EXR_CHANNEL_INDICES: [usize; 4] = [3, 2, 1, 0];
+
+/// Appends an attribute to the header of an OpenEXR file.
+fn exr_attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("save_{}_{name}", std::process::id()));
+        path.to_str().unwrap().to_owned()
+    }
+
+    #[test]
+    fn png16_keeps_the_precision_of_the_image() {
+        let path = temp_path("ramp.png");
+        let image = LinearImage::from_fn(256, 1, |x, _| {
+            let value = x as f32 / 1023.0;
+            Rgba([value, value, value, 1.0])
+        });
+        save_png16(&image, &path, None, true).unwrap();
+
+        let decoder = png::Decoder::new(File::open(&path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        assert_eq!(reader.info().bit_depth, png::BitDepth::Sixteen);
+        assert_eq!(reader.info().source_gamma, Some(png::ScaledFloat::new(1.0)));
+        let mut data = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut data).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // The steps of the ramp are finer than 8 bits, yet every one of them is kept
+        let reds: Vec<u16> = data
+            .chunks_exact(8)
+            .map(|pixel| u16::from_be_bytes([pixel[0], pixel[1]]))
+            .collect();
+        assert!(reds.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(reds[4], (4.0 / 1023.0 * 65535.0f32).round() as u16);
+    }
+
+    #[test]
+    fn exr_lines_hold_the_channels_of_their_pixels() {
+        let path = temp_path("image.exr");
+        let image = LinearImage::from_fn(3, 2, |x, y| Rgba([x as f32, y as f32, 2.5, 1.0]));
+        save_exr(&image, &path, Some("{\"zoom\": 2.5}")).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(bytes[..4], EXR_MAGIC);
+
+        let read_u64 = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+        let read_i32 = |at: usize| i32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        let read_f32 = |at: usize| f32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+
+        let comments = b"comments\0string\0";
+        assert!(
+            bytes
+                .windows(comments.len())
+                .any(|window| window == comments)
+        );
+
+        // The lines end the file, right after the table of their offsets
+        let line_size = 4 * 3 * 4;
+        let chunks = bytes.len() - 2 * (8 + line_size);
+        for y in 0..2 {
+            let offset = read_u64(chunks - 16 + y * 8) as usize;
+            assert_eq!(offset, chunks + y * (8 + line_size));
+            assert_eq!(read_i32(offset), y as i32);
+            assert_eq!(read_i32(offset + 4), line_size as i32);
+            // The channels are in the order A, B, G, R
+            let line = offset + 8;
+            let channel = |index: usize, x: usize| read_f32(line + (index * 3 + x) * 4);
+            assert_eq!((0..3).map(|x| channel(0, x)).collect::<Vec<_>>(), [1.0; 3]);
+            assert_eq!((0..3).map(|x| channel(1, x)).collect::<Vec<_>>(), [2.5; 3]);
+            assert_eq!(
+                (0..3).map(|x| channel(2, x)).collect::<Vec<_>>(),
+                [y as f32; 3]
+            );
+            assert_eq!(
+                (0..3).map(|x| channel(3, x)).collect::<Vec<_>>(),
+                [0.0, 1.0, 2.0]
+            );
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant, SystemTime},
+};
+
+use serde::Serialize;
+
+use crate::get_export_path;
+
+/// Keeps track of what happens during a run of a sketch, to write a report of the session when it
+/// exits.
+///
+/// The reports help debugging installations left running unattended, and document the
+/// performance of the sketches.
+#[derive(Debug)]
+pub struct SessionStats {
+    started: Instant,
+    started_at: SystemTime,
+    files_loaded: Vec<String>,
+    frames: u64,
+    captures: u32,
+    presets_used: BTreeMap<String, u32>,
+}
+
+impl Default for SessionStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SessionStats {
+    /// Starts the statistics of a session beginning now.
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            started_at: SystemTime::now(),
+            files_loaded: Vec::new(),
+            frames: 0,
+            captures: 0,
+            presets_used: BTreeMap::new(),
+        }
+    }
+
+    /// Records a file loaded by the sketch.
+    pub fn file_loaded(&mut self, path: &str) {
+        self.files_loaded.push(path.to_owned());
+    }
+
+    /// Records a frame rendered by the sketch.
+    pub fn frame(&mut self) {
+        self.frames += 1;
+    }
+
+    /// Records a capture taken by the sketch, such as a saved frame or a recording.
+    pub fn capture(&mut self) {
+        self.captures += 1;
+    }
+
+    /// Records a preset applied by the sketch, by name.
+    pub fn preset_used(&mut self, name: &str) {
+        *self.presets_used.entry(name.to_owned()).or_default() += 1;
+    }
+
+    /// Summarizes the session up to now.
+    pub fn report(&self) -> SessionReport {
+        self.report_after(self.started.elapsed())
+    }
+
+    /// Writes the report of the session to a new JSON file named after the sketch, returning its
+    /// path.
+    pub fn write(&self, prefix: &str) -> Result<String, &'static str> {
+        let json = serde_json::to_string_pretty(&self.report())
+            .map_err(|_| "Failed to serialize the session report")?;
+        let path = get_export_path(&format!("{prefix}_session"), "json");
+        std::fs::write(&path, json).map_err(|_| "Failed to write the session report")?;
+        Ok(path)
+    }
+
+    /// Summarizes the session as if it lasted for `duration`.
+    fn report_after(&self, duration: Duration) -> SessionReport {
+        let duration_secs = duration.as_secs_f64();
+        let average_fps = match duration_secs > 0.0 {
+            true => self.frames as f64 / duration_secs,
+            false => 0.0,
+        };
+        SessionReport {
+            started_at: self
+                .started_at
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs()),
+            duration_secs,
+            files_loaded: self.files_loaded.clone(),
+            frames_rendered: self.frames,
+            average_fps,
+            captures_taken: self.captures,
+            presets_used: self.presets_used.clone(),
+        }
+    }
+}
+
+/// The report of a session, as written to JSON.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SessionReport {
+    /// When the session started, in seconds since the UNIX epoch.
+    pub started_at: u64,
+    pub duration_secs: f64,
+    /// The files loaded, in the order they were loaded.
+    pub files_loaded: Vec<String>,
+    pub frames_rendered: u64,
+    pub average_fps: f64,
+    pub captures_taken: u32,
+    /// How many times each preset was applied.
+    pub presets_used: BTreeMap<String, u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_averages_the_frames_over_the_duration() {
+        let mut stats = SessionStats::new();
+        (0..120).for_each(|_| stats.frame());
+        let report = stats.report_after(Duration::from_secs(4));
+        assert_eq!(report.frames_rendered, 120);
+        assert_eq!(report.average_fps, 30.0);
+    }
+
+    #[test]
+    fn empty_session_has_no_frame_rate() {
+        let report = SessionStats::new().report_after(Duration::ZERO);
+        assert_eq!(report.average_fps, 0.0);
+    }
+
+    #[test]
+    fn report_counts_the_uses_of_each_preset() {
+        let mut stats = SessionStats::new();
+        stats.preset_used("night");
+        stats.preset_used("day");
+        stats.preset_used("night");
+        stats.file_loaded("scan.e57");
+        stats.capture();
+        let report = stats.report_after(Duration::from_secs(1));
+        assert_eq!(report.presets_used["night"], 2);
+        assert_eq!(report.presets_used["day"], 1);
+        assert_eq!(report.files_loaded, ["scan.e57"]);
+        assert_eq!(report.captures_taken, 1);
+    }
+
+    #[test]
+    fn report_serializes_to_json() {
+        let json = serde_json::to_string(&SessionStats::new().report()).unwrap();
+        assert!(json.contains("\"frames_rendered\":0"));
+        assert!(json.contains("\"presets_used\":{}"));
+    }
+}
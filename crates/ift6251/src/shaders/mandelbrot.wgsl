@@ -0,0 +1,107 @@
+// Iterates the sequence z -> z^d + c for the samples of each pixel of a pane, like
+// `compute_mandelbrot_array` does on the CPU, in single precision.
+
+struct Params {
+    // The center of the viewport in the complex plane
+    center: vec2<f32>,
+    // Half the width and height of the viewport
+    half_size: vec2<f32>,
+    // The number defining the Julia set
+    julia_c: vec2<f32>,
+    // The width and height of the pane in pixels
+    size: vec2<u32>,
+    max_iterations: u32,
+    // 0 for Mandlebrot's set, 1 for a Julia set
+    fractal: u32,
+    // The number of samples per axis of each pixel
+    samples: u32,
+    // 1 to sum the samples within the set instead of the escaping ones
+    select_interior: u32,
+    escape_radius: f32,
+    power: f32,
+    // 1 to sum the continuous escape values instead of the escape iterations
+    smooth_coloring: u32,
+    _padding: f32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+// The sum of the values of the samples of each pixel, row by row
+@group(0) @binding(1) var<storage, read_write> values: array<f32>;
+// The number of samples escaping after each number of iterations, the last one counting the
+// samples that never escaped
+@group(0) @binding(2) var<storage, read_write> counts: array<atomic<u32>>;
+
+fn next(z: vec2<f32>, c: vec2<f32>) -> vec2<f32> {
+    if params.power == 2.0 {
+        return vec2<f32>(z.x * z.x - z.y * z.y, 2.0 * z.x * z.y) + c;
+    }
+    // The argument of zero is undefined
+    if z.x == 0.0 && z.y == 0.0 {
+        return c;
+    }
+    // Raise z to the power in polar form
+    let modulus = pow(length(z), params.power);
+    let argument = atan2(z.y, z.x) * params.power;
+    return modulus * vec2<f32>(cos(argument), sin(argument)) + c;
+}
+
+struct Escape {
+    // The iteration at which the sequence escapes, or the maximum number of iterations if it never
+    // does
+    iterations: u32,
+    // The continuous escape value, like `smooth_escape`
+    value: f32,
+}
+
+fn escape(start: vec2<f32>, c: vec2<f32>) -> Escape {
+    let radius_squared = params.escape_radius * params.escape_radius;
+    var z = start;
+    for (var i = 0u; i < params.max_iterations; i++) {
+        z = next(z, c);
+        if dot(z, z) > radius_squared {
+            // A power of 1 grows linearly, without a fraction of iteration to recover
+            if params.power <= 1.0 || params.escape_radius <= 1.0 {
+                return Escape(i, f32(i));
+            }
+            let fraction = log(log(length(z)) / log(params.escape_radius)) / log(params.power);
+            return Escape(i, f32(i) + 1.0 - clamp(fraction, 0.0, 1.0));
+        }
+    }
+    return Escape(params.max_iterations, f32(params.max_iterations));
+}
+
+@compute @workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= params.size.x || id.y >= params.size.y {
+        return;
+    }
+
+    let spacing = 1.0 / f32(params.samples);
+    var sum = 0.0;
+    for (var i = 0u; i < params.samples; i++) {
+        for (var j = 0u; j < params.samples; j++) {
+            // The first row of pixels is the bottom of the imaginary range, like `complex_at`
+            let pixel = vec2<f32>(f32(id.x) + f32(j) * spacing, f32(id.y) + f32(i) * spacing);
+            let offset = pixel / vec2<f32>(params.size) * 2.0 - 1.0;
+            let point = params.center + offset * params.half_size;
+
+            var result: Escape;
+            if params.fractal == 0u {
+                result = escape(vec2<f32>(0.0, 0.0), point);
+            } else {
+                result = escape(point, params.julia_c);
+            }
+            atomicAdd(&counts[result.iterations], 1u);
+
+            let interior = result.iterations == params.max_iterations;
+            if interior == (params.select_interior == 1u) {
+                if params.smooth_coloring == 1u {
+                    sum += result.value;
+                } else {
+                    sum += f32(result.iterations);
+                }
+            }
+        }
+    }
+    values[id.y * params.size.x + id.x] = sum;
+}
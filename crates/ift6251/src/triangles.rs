@@ -11,15 +11,34 @@
 // Stroke color HSLA=(RgbHue(273.71014), 0.54207826, 0.23118138, 0.1)
 // Fill color HSLA=(RgbHue(332.50726), 0.7435478, 0.27593488, 0.01)
 
-use ift6251::get_save_path;
+// The `noise` crate re-exports `Perlin` through overlapping globs
+#![allow(ambiguous_glob_imports)]
+
+use ift6251::{
+    build_info::{self, BUILD_INFO_KEY},
+    display::DisplayOptions,
+    get_save_path,
+    osc::{OSC_PORT, OscServer},
+    recorder::{RECORD_KEY, Recorder},
+    utils::{
+        hotkeys::{Binding, HELP_KEY, HotkeyHelp, action_for, describe},
+        palette::{Command, CommandPalette, PALETTE_KEY},
+        presets::{PresetLibrary, PresetRequest},
+        rng::{self, random, random_range},
+        widgets::{HslaPicker, LabelledSlider, UiScale},
+    },
+    watchdog::{self, Watchdog},
+};
 use nannou::{
     color::Hue,
     noise::{NoiseFn, Perlin, Seedable},
     prelude::*,
 };
 use nannou_egui::{Egui, FrameCtx, egui};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 fn main() {
+    watchdog::supervise();
     nannou::app(model).update(update).run()
 }
 
@@ -30,6 +49,7 @@ struct State {
     roll: f32,
 }
 
+#[derive(Serialize, Deserialize)]
 struct Settings {
     noise_scale_x: f64,
     noise_scale_y: f64,
@@ -38,21 +58,65 @@ struct Settings {
     noise_scale_time_xy: f64,
     noise_scale_time_wh: f64,
     rotation_increment: f32,
+    #[serde(with = "hsla_values")]
     stroke_color: Hsla,
+    #[serde(with = "hsla_values")]
     fill_color: Hsla,
+    #[serde(with = "perlin_seed")]
     noise: Perlin,
 }
 
+/// Saves the colors as the values given to `hsla`, the hue being in turns.
+mod hsla_values {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(color: &Hsla, serializer: S) -> Result<S::Ok, S::Error> {
+        let hue = color.hue.to_positive_degrees() / 360.0;
+        [hue, color.saturation, color.lightness, color.alpha].serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Hsla, D::Error> {
+        let [h, s, l, a] = <[f32; 4]>::deserialize(deserializer)?;
+        Ok(hsla(h, s, l, a))
+    }
+}
+
+/// Saves the noise as its seed.
+mod perlin_seed {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(noise: &Perlin, serializer: S) -> Result<S::Ok, S::Error> {
+        noise.seed().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Perlin, D::Error> {
+        Ok(Perlin::new().set_seed(u32::deserialize(deserializer)?))
+    }
+}
+
 struct Model {
     egui: Egui,
+    palette: CommandPalette,
+    help: HotkeyHelp,
+    recorder: Recorder,
+    /// Whether the build is drawn in a corner of the window.
+    show_build_info: bool,
     settings: Settings,
     state: State,
+    /// The named presets of the settings, saved to disk.
+    preset_library: PresetLibrary,
+    /// The server receiving the parameters set remotely, if it could be started.
+    osc: Option<OscServer>,
+    /// Reports the frames to the supervisor and autosaves the settings, when the sketch is
+    /// supervised.
+    watchdog: Option<Watchdog>,
 }
 
 fn model(app: &App) -> Model {
+    rng::load("triangles");
     let window_id = app
         .new_window()
-        .fullscreen()
+        .fullscreen_with(Some(DisplayOptions::load("triangles").fullscreen(app)))
         .view(view)
         .key_pressed(key_pressed)
         .raw_event(raw_window_event)
@@ -62,7 +126,7 @@ fn model(app: &App) -> Model {
     let window = app.window(window_id).unwrap();
     let egui = Egui::from_window(&window);
 
-    let settings = Settings {
+    let mut settings = Settings {
         noise_scale_x: 0.1,
         noise_scale_y: 0.1,
         noise_scale_w: 0.042,
@@ -75,6 +139,12 @@ fn model(app: &App) -> Model {
         noise: Perlin::new(),
     };
 
+    // Pick up where the sketch was before it was restarted by its supervisor
+    let watchdog = Watchdog::from_env("triangles");
+    if let Some(restored) = watchdog.as_ref().and_then(Watchdog::restore) {
+        settings = restored;
+    }
+
     let state = State {
         position: vec2(0.0, 0.0),
         size: vec2(0.0, 0.0),
@@ -82,46 +152,80 @@ fn model(app: &App) -> Model {
         roll: 0.0,
     };
 
+    // Listen to the parameters set remotely, within the ranges of their sliders
+    let osc = OscServer::start(OSC_PORT)
+        .inspect(|osc| {
+            osc.register("noise_scale_time_xy", 0.0..=0.05);
+            osc.register("noise_scale_time_wh", 0.0..=0.05);
+            osc.register("rotation_increment", 0.0..=1.0);
+        })
+        .inspect_err(|e| eprintln!("{e}"))
+        .ok();
+
     Model {
         egui,
+        palette: CommandPalette::new(),
+        help: HotkeyHelp::new(),
+        recorder: Recorder::new(),
+        show_build_info: false,
         settings,
         state,
+        preset_library: PresetLibrary::new("triangles"),
+        osc,
+        watchdog,
     }
 }
 
-fn update_egui(ctx: FrameCtx, settings: &mut Settings) {
+fn update_egui(
+    ctx: FrameCtx,
+    settings: &mut Settings,
+    preset_library: &mut PresetLibrary,
+    recorder: &mut Recorder,
+) {
     // Generate the settings window
     egui::Window::new("Settings").show(&ctx, |ui| {
-        ui.label("Noise scale x:");
-        ui.add(egui::Slider::new(&mut settings.noise_scale_x, 0.00..=0.1));
-
-        ui.label("Noise scale y:");
-        ui.add(egui::Slider::new(&mut settings.noise_scale_y, 0.00..=0.1));
-
-        ui.label("Noise scale w:");
-        ui.add(egui::Slider::new(&mut settings.noise_scale_w, 0.00..=0.1));
-
-        ui.label("Noise scale h:");
-        ui.add(egui::Slider::new(&mut settings.noise_scale_h, 0.00..=0.1));
-
-        ui.label("Noise scale time xy:");
-        ui.add(egui::Slider::new(
+        ui.add(LabelledSlider::new(
+            "Noise scale x",
+            &mut settings.noise_scale_x,
+            0.00..=0.1,
+        ));
+        ui.add(LabelledSlider::new(
+            "Noise scale y",
+            &mut settings.noise_scale_y,
+            0.00..=0.1,
+        ));
+        ui.add(LabelledSlider::new(
+            "Noise scale w",
+            &mut settings.noise_scale_w,
+            0.00..=0.1,
+        ));
+        ui.add(LabelledSlider::new(
+            "Noise scale h",
+            &mut settings.noise_scale_h,
+            0.00..=0.1,
+        ));
+        ui.add(LabelledSlider::new(
+            "Noise scale time xy",
             &mut settings.noise_scale_time_xy,
             0.000..=0.05,
         ));
-
-        ui.label("Noise scale time wh:");
-        ui.add(egui::Slider::new(
+        ui.add(LabelledSlider::new(
+            "Noise scale time wh",
             &mut settings.noise_scale_time_wh,
             0.000..=0.05,
         ));
-
-        ui.label("Rotation increment:");
-        ui.add(egui::Slider::new(
+        ui.add(LabelledSlider::new(
+            "Rotation increment",
             &mut settings.rotation_increment,
             0.00..=1.00,
         ));
 
+        ui.add(HslaPicker::new("Stroke color", &mut settings.stroke_color));
+        ui.add(HslaPicker::new("Fill color", &mut settings.fill_color));
+
+        // The random values below are drawn again in the same order from the seed
+        rng::show(ui);
+
         let rnd_color = ui.button("Random color").clicked();
         if rnd_color {
             settings.stroke_color = hsla(random(), random(), random(), 0.1);
@@ -167,17 +271,69 @@ fn update_egui(ctx: FrameCtx, settings: &mut Settings) {
                 settings.fill_color.alpha
             );
         }
+
+        ui.separator();
+
+        match preset_library.show(ui) {
+            Some(PresetRequest::Save) => match preset_library.save(settings) {
+                Ok(path) => println!("Saving preset to: {}", path.display()),
+                Err(e) => eprintln!("{e}"),
+            },
+            Some(PresetRequest::Load) => match preset_library.load() {
+                Ok(loaded) => *settings = loaded,
+                Err(e) => eprintln!("{e}: {}", preset_library.name),
+            },
+            None => {}
+        }
+
+        ui.separator();
+
+        ui.add(UiScale);
+        recorder.show(ui);
     });
 }
 
 fn update(app: &App, model: &mut Model, update: Update) {
+    if let Some(watchdog) = &mut model.watchdog {
+        watchdog.tick(|| &model.settings);
+    }
+
     let egui = &mut model.egui;
     let settings = &mut model.settings;
     let state = &mut model.state;
 
     egui.set_elapsed_time(update.since_start);
     let ctx = egui.begin_frame();
-    update_egui(ctx, settings);
+    let commands = commands(model.recorder.is_recording());
+    if let Some(action) = model.palette.show(&ctx, &commands) {
+        perform(
+            app,
+            &mut model.recorder,
+            &mut model.show_build_info,
+            settings,
+            action,
+        );
+    }
+    model.help.show(&ctx, &describe(&bindings()));
+    update_egui(
+        ctx,
+        settings,
+        &mut model.preset_library,
+        &mut model.recorder,
+    );
+
+    // Apply the parameters set remotely
+    if let Some(osc) = &model.osc {
+        if let Some(scale) = osc.take("noise_scale_time_xy") {
+            settings.noise_scale_time_xy = scale as f64;
+        }
+        if let Some(scale) = osc.take("noise_scale_time_wh") {
+            settings.noise_scale_time_wh = scale as f64;
+        }
+        if let Some(increment) = osc.take("rotation_increment") {
+            settings.rotation_increment = increment;
+        }
+    }
 
     // Compute a subsection of the window size
     let window_width = (app.window_rect().w() / 4.0) as f64;
@@ -242,17 +398,99 @@ fn view(app: &App, model: &Model, frame: Frame) {
         .stroke(settings.stroke_color)
         .stroke_weight(1.0);
 
+    if model.show_build_info {
+        build_info::draw_overlay(&draw, window);
+    }
+
     draw.to_frame(app, &frame).unwrap();
+    model.recorder.capture(&frame);
     model.egui.draw_to_frame(&frame).unwrap();
 }
 
-fn key_pressed(app: &App, _model: &mut Model, key: Key) {
-    match key {
-        Key::Q => app.quit(),
-        Key::S => {
-            app.main_window()
-                .capture_frame(get_save_path(&app.exe_name().unwrap()));
+/// The actions that can be run from the keyboard or the command palette.
+#[derive(Clone, Copy)]
+enum Action {
+    ToggleRecording,
+    SaveFrame,
+    ToggleBuildInfo,
+    Quit,
+}
+
+fn commands(recording: bool) -> Vec<Command<Action>> {
+    vec![
+        match recording {
+            false => Command::new("Start recording", Action::ToggleRecording),
+            true => Command::new("Stop recording", Action::ToggleRecording),
+        },
+        Command::new("Save frame", Action::SaveFrame),
+        Command::new("Toggle build info", Action::ToggleBuildInfo),
+        Command::new("Quit", Action::Quit),
+    ]
+}
+
+/// The hotkeys of the sketch, listed by the help overlay.
+fn bindings() -> Vec<Binding<Action>> {
+    vec![
+        Binding::new(
+            &[RECORD_KEY],
+            "Start or stop recording the frames",
+            Action::ToggleRecording,
+        ),
+        Binding::new(&[Key::S], "Save the current frame", Action::SaveFrame),
+        Binding::new(
+            &[BUILD_INFO_KEY],
+            "Show or hide the build in a corner",
+            Action::ToggleBuildInfo,
+        ),
+        Binding::new(&[Key::Q], "Quit", Action::Quit),
+    ]
+}
+
+fn perform(
+    app: &App,
+    recorder: &mut Recorder,
+    show_build_info: &mut bool,
+    settings: &Settings,
+    action: Action,
+) {
+    match action {
+        Action::ToggleRecording => {
+            recorder.toggle(&app.exe_name().unwrap(), app.main_window().device());
+        }
+        Action::SaveFrame => recorder.save_frame(
+            get_save_path(&app.exe_name().unwrap()),
+            build_info::parameters(settings),
+        ),
+        Action::ToggleBuildInfo => *show_build_info = !*show_build_info,
+        Action::Quit => {
+            recorder.stop(app.main_window().device());
+            app.quit();
         }
-        _other_key => {}
+    }
+}
+
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    if key == PALETTE_KEY {
+        model.palette.toggle();
+        return;
+    }
+    // The keys are used to type in the palette
+    if model.palette.is_open() {
+        return;
+    }
+
+    if key == HELP_KEY {
+        model.help.toggle();
+        return;
+    }
+
+    if let Some(action) = action_for(&bindings(), key) {
+        perform(
+            app,
+            &mut model.recorder,
+            &mut model.show_build_info,
+            &model.settings,
+            action,
+        );
     }
 }
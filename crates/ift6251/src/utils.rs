@@ -1,2 +1,24 @@
+pub mod ab;
+pub mod attract;
+pub mod audio;
+pub mod bookmarks;
+pub mod contours;
+pub mod density;
+pub mod field;
+pub mod fixed_point;
+#[cfg(any(test, feature = "golden"))]
+pub mod golden;
+pub mod hotkeys;
 pub mod images;
 pub mod mandelbrot;
+pub mod mandelbrot_gpu;
+pub mod palette;
+pub mod perturbation;
+pub mod presets;
+pub mod rng;
+pub mod spatial_hash;
+pub mod species;
+pub mod sph;
+pub mod synth;
+pub mod trajectories;
+pub mod widgets;
@@ -0,0 +1,177 @@
+/// A value that can be described as a flat list of named parameters.
+pub trait Parameters {
+    /// Returns the name and formatted value of each parameter.
+    fn parameters(&self) -> Vec<(&'static str, String)>;
+}
+
+/// One of the two slots of an [`AbSlots`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Slot {
+    A,
+    B,
+}
+
+impl Slot {
+    /// Returns the other slot.
+    pub fn other(self) -> Self {
+        match self {
+            Slot::A => Slot::B,
+            Slot::B => Slot::A,
+        }
+    }
+}
+
+/// Two slots holding snapshots of settings for A/B comparison.
+pub struct AbSlots<T> {
+    a: Option<T>,
+    b: Option<T>,
+    active: Option<Slot>,
+}
+
+impl<T: Clone + Parameters> AbSlots<T> {
+    /// Creates two empty slots.
+    pub fn new() -> Self {
+        Self {
+            a: None,
+            b: None,
+            active: None,
+        }
+    }
+
+    /// Stores a snapshot in the given slot, making it the active slot.
+    pub fn store(&mut self, slot: Slot, value: T) {
+        match slot {
+            Slot::A => self.a = Some(value),
+            Slot::B => self.b = Some(value),
+        }
+        self.active = Some(slot);
+    }
+
+    /// Returns the snapshot stored in the given slot, if any.
+    pub fn get(&self, slot: Slot) -> Option<&T> {
+        match slot {
+            Slot::A => self.a.as_ref(),
+            Slot::B => self.b.as_ref(),
+        }
+    }
+
+    /// Makes the given slot active and returns its snapshot, if any.
+    pub fn recall(&mut self, slot: Slot) -> Option<T> {
+        let value = self.get(slot).cloned();
+        if value.is_some() {
+            self.active = Some(slot);
+        }
+        value
+    }
+
+    /// Switches to the other slot and returns its snapshot, if any.
+    ///
+    /// If no slot is active, slot A is recalled.
+    pub fn toggle(&mut self) -> Option<T> {
+        let slot = self.active.map_or(Slot::A, Slot::other);
+        self.recall(slot)
+    }
+
+    /// The slot that was last stored or recalled.
+    pub fn active(&self) -> Option<Slot> {
+        self.active
+    }
+
+    /// Lists the parameters that differ between slot A and slot B.
+    ///
+    /// Each entry contains the name of the parameter, its value in A, and its value in B. The list
+    /// is empty if either slot is empty.
+    pub fn diff(&self) -> Vec<(&'static str, String, String)> {
+        let (Some(a), Some(b)) = (&self.a, &self.b) else {
+            return Vec::new();
+        };
+
+        a.parameters()
+            .into_iter()
+            .zip(b.parameters())
+            .filter(|((_, a), (_, b))| a != b)
+            .map(|((name, a), (_, b))| (name, a, b))
+            .collect()
+    }
+}
+
+impl<T: Clone + Parameters> Default for AbSlots<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Look {
+        size: f32,
+        color: &'static str,
+    }
+
+    impl Parameters for Look {
+        fn parameters(&self) -> Vec<(&'static str, String)> {
+            vec![
+                ("Size", format!("{:.1}", self.size)),
+                ("Color", self.color.to_string()),
+            ]
+        }
+    }
+
+    fn look(size: f32, color: &'static str) -> Look {
+        Look { size, color }
+    }
+
+    #[test]
+    fn stores_and_recalls_each_slot() {
+        let mut slots = AbSlots::new();
+        assert_eq!(slots.active(), None);
+
+        slots.store(Slot::A, look(1.0, "red"));
+        slots.store(Slot::B, look(2.0, "blue"));
+        assert_eq!(slots.active(), Some(Slot::B));
+        assert_eq!(slots.get(Slot::A), Some(&look(1.0, "red")));
+
+        assert_eq!(slots.recall(Slot::A), Some(look(1.0, "red")));
+        assert_eq!(slots.active(), Some(Slot::A));
+    }
+
+    #[test]
+    fn recalling_an_empty_slot_keeps_the_active_slot() {
+        let mut slots = AbSlots::new();
+        slots.store(Slot::A, look(1.0, "red"));
+
+        assert_eq!(slots.recall(Slot::B), None);
+        assert_eq!(slots.active(), Some(Slot::A));
+    }
+
+    #[test]
+    fn toggles_between_the_slots() {
+        let mut slots = AbSlots::new();
+        assert_eq!(slots.toggle(), None);
+
+        slots.store(Slot::A, look(1.0, "red"));
+        slots.store(Slot::B, look(2.0, "blue"));
+        assert_eq!(slots.toggle(), Some(look(1.0, "red")));
+        assert_eq!(slots.toggle(), Some(look(2.0, "blue")));
+        assert_eq!(slots.toggle(), Some(look(1.0, "red")));
+    }
+
+    #[test]
+    fn lists_only_the_parameters_that_differ() {
+        let mut slots = AbSlots::new();
+        slots.store(Slot::A, look(1.0, "red"));
+        assert!(slots.diff().is_empty());
+
+        slots.store(Slot::B, look(1.0, "blue"));
+        assert_eq!(
+            slots.diff(),
+            [("Color", "red".to_string(), "blue".to_string())]
+        );
+
+        slots.store(Slot::B, look(1.0, "red"));
+        assert!(slots.diff().is_empty());
+    }
+}
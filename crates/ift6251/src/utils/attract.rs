@@ -0,0 +1,152 @@
+/// A value that can be interpolated with another value of the same type.
+pub trait Blend {
+    /// Returns the value at `t` between `self` (at 0) and `other` (at 1).
+    fn blend(&self, other: &Self, t: f32) -> Self;
+}
+
+/// An idle attract loop that cycles through presets when no one is interacting.
+///
+/// Once the idle timeout has elapsed, each preset is held for a while before blending smoothly
+/// into the next one, wrapping back to the first preset after the last.
+pub struct AttractLoop {
+    /// The number of seconds without interaction before the loop starts.
+    pub idle_timeout: f32,
+    /// The number of seconds each preset is held.
+    pub hold: f32,
+    /// The number of seconds spent blending from one preset to the next.
+    pub transition: f32,
+    /// The number of seconds since the last interaction, kept in double precision so the loop
+    /// still advances smoothly after days of running.
+    idle: f64,
+}
+
+impl AttractLoop {
+    /// Creates a new attract loop.
+    ///
+    /// All durations are in seconds.
+    pub fn new(idle_timeout: f32, hold: f32, transition: f32) -> Self {
+        Self {
+            idle_timeout,
+            hold,
+            transition,
+            idle: 0.0,
+        }
+    }
+
+    /// Resets the idle timer, stopping the loop until the timeout elapses again.
+    pub fn interact(&mut self) {
+        self.idle = 0.0;
+    }
+
+    /// Whether the idle timeout has elapsed.
+    pub fn is_active(&self) -> bool {
+        self.idle >= self.idle_timeout as f64
+    }
+
+    /// Advances the idle timer by `delta` seconds and returns the blended preset to show, if any.
+    ///
+    /// Returns `None` while someone is interacting or if there are no presets.
+    pub fn tick<T: Blend + Clone>(&mut self, delta: f32, presets: &[T]) -> Option<T> {
+        self.idle += delta as f64;
+        if !self.is_active() || presets.is_empty() {
+            return None;
+        }
+
+        // Find the current preset and how far into its period we are
+        let period = (self.hold + self.transition).max(f32::EPSILON) as f64;
+        let elapsed = self.idle - self.idle_timeout as f64;
+        let index = ((elapsed / period) as u64 % presets.len() as u64) as usize;
+        let local = (elapsed % period) as f32;
+
+        let current = &presets[index];
+        if local < self.hold || presets.len() == 1 {
+            return Some(current.clone());
+        }
+
+        // Ease in and out of the transition
+        let next = &presets[(index + 1) % presets.len()];
+        let t = ((local - self.hold) / self.transition.max(f32::EPSILON)).clamp(0.0, 1.0);
+        let t = t * t * (3.0 - 2.0 * t);
+        Some(current.blend(next, t))
+    }
+}
+
+impl Default for AttractLoop {
+    fn default() -> Self {
+        Self::new(60.0, 20.0, 10.0)
+    }
+}
+
+/// Linearly interpolates between two numbers.
+pub fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// Linearly interpolates between two arrays component-wise.
+pub fn lerp_array<const N: usize>(a: [f32; N], b: [f32; N], t: f32) -> [f32; N] {
+    std::array::from_fn(|i| lerp(a[i], b[i], t))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    impl Blend for f32 {
+        fn blend(&self, other: &Self, t: f32) -> Self {
+            lerp(*self, *other, t)
+        }
+    }
+
+    const PRESETS: [f32; 3] = [0.0, 10.0, 20.0];
+
+    #[test]
+    fn waits_for_the_idle_timeout() {
+        let mut attract = AttractLoop::new(5.0, 2.0, 1.0);
+        assert_eq!(attract.tick(4.0, &PRESETS), None);
+        assert!(!attract.is_active());
+        assert_eq!(attract.tick(1.5, &PRESETS), Some(0.0));
+        assert!(attract.is_active());
+
+        attract.interact();
+        assert!(!attract.is_active());
+        assert_eq!(attract.tick(1.0, &PRESETS), None);
+    }
+
+    #[test]
+    fn holds_then_blends_into_the_next_preset() {
+        let mut attract = AttractLoop::new(0.0, 2.0, 2.0);
+        assert_eq!(attract.tick(1.0, &PRESETS), Some(0.0));
+        // Halfway through the transition, the ease is symmetric
+        assert_eq!(attract.tick(2.0, &PRESETS), Some(5.0));
+        assert_eq!(attract.tick(2.0, &PRESETS), Some(10.0));
+    }
+
+    #[test]
+    fn wraps_back_to_the_first_preset() {
+        let mut attract = AttractLoop::new(0.0, 2.0, 2.0);
+        // Halfway through the transition from the last preset to the first
+        assert_eq!(attract.tick(11.0, &PRESETS), Some(10.0));
+        assert_eq!(attract.tick(1.0, &PRESETS), Some(0.0));
+    }
+
+    #[test]
+    fn shows_nothing_without_presets() {
+        let mut attract = AttractLoop::new(0.0, 2.0, 2.0);
+        assert_eq!(attract.tick(1.0, &[] as &[f32]), None);
+        assert_eq!(attract.tick(1.0, &[7.0]), Some(7.0));
+    }
+
+    #[test]
+    fn keeps_advancing_after_days() {
+        let mut attract = AttractLoop::new(0.0, 2.0, 2.0);
+        // A week of frames at 60 FPS, in steps small enough to vanish in a single precision timer
+        let week = 7.0 * 24.0 * 3600.0;
+        attract.idle = week + 2.5;
+        let before = attract.tick(1.0 / 60.0, &PRESETS).unwrap();
+        (0..60).for_each(|_| {
+            attract.tick(1.0 / 60.0, &PRESETS);
+        });
+        let after = attract.tick(1.0 / 60.0, &PRESETS).unwrap();
+        assert_ne!(before, after);
+    }
+}
@@ -0,0 +1,496 @@
+use std::{collections::VecDeque, path::Path};
+
+use spectrum_analyzer::{FrequencyLimit, samples_fft_to_spectrum, windows::hann_window};
+
+/// The extensions of the audio files the sketches can load, those decoded by `audrey`.
+pub const AUDIO_EXTENSIONS: &[&str] = &["wav", "flac", "ogg", "caf"];
+
+/// The features of a buffer of audio the sketches react to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AudioFeatures {
+    /// The sum of the magnitudes of the spectrum, at least 1.
+    pub magnitude: f32,
+    /// The mean magnitude of the low frequencies.
+    pub bass: f32,
+    /// The mean magnitude of the middle frequencies.
+    pub mid: f32,
+    /// The mean magnitude of the high frequencies.
+    pub treble: f32,
+    /// Whether a sound starts in the buffer.
+    pub onset: bool,
+}
+
+impl Default for AudioFeatures {
+    fn default() -> Self {
+        Self {
+            magnitude: 1.0,
+            bass: 0.0,
+            mid: 0.0,
+            treble: 0.0,
+            onset: false,
+        }
+    }
+}
+
+impl AudioFeatures {
+    /// Replaces the features by those of a newer buffer, keeping an onset that was not taken yet.
+    ///
+    /// The audio thread usually analyzes several buffers per frame, so this makes sure an onset is
+    /// not missed by the sketch.
+    pub fn update(&mut self, newer: AudioFeatures) {
+        let onset = self.onset || newer.onset;
+        *self = newer;
+        self.onset = onset;
+    }
+
+    /// Returns the features and clears the onset, so that each onset is only seen once.
+    pub fn take(&mut self) -> AudioFeatures {
+        let features = *self;
+        self.onset = false;
+        features
+    }
+}
+
+/// Analyzes the spectrum of successive buffers of audio.
+///
+/// The spectrum is split into bass, mid and treble bands at configurable edges. Onsets are
+/// detected from the spectral flux, the amount by which the spectrum grows from one buffer to the
+/// next, compared to an adaptive threshold following the recent flux.
+pub struct AudioAnalyzer {
+    /// The frequencies separating the bass from the mid band and the mid from the treble band, in
+    /// hertz.
+    band_edges: [f32; 2],
+    /// The frequency and the magnitude of each bin of the spectrum of the last buffer.
+    spectrum: Vec<(f32, f32)>,
+    flux_history: VecDeque<f32>,
+}
+
+impl Default for AudioAnalyzer {
+    fn default() -> Self {
+        Self {
+            band_edges: [250.0, 4000.0],
+            spectrum: Vec::new(),
+            flux_history: VecDeque::new(),
+        }
+    }
+}
+
+impl AudioAnalyzer {
+    /// The lowest frequency of the analysis, in hertz.
+    pub const MIN_FREQUENCY: f32 = 20.0;
+    /// The number of buffers the adaptive threshold follows.
+    const FLUX_HISTORY_LENGTH: usize = 32;
+    /// How many times the mean of the recent flux the flux must reach for an onset.
+    const ONSET_THRESHOLD: f32 = 1.5;
+    /// The flux, relative to the magnitude of the spectrum, below which there is no onset.
+    const MIN_ONSET_FLUX: f32 = 0.1;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the frequencies separating the bands, in hertz.
+    pub fn with_band_edges(mut self, bass_max: f32, mid_max: f32) -> Self {
+        self.set_band_edges(bass_max, mid_max);
+        self
+    }
+
+    /// The frequencies separating the bass from the mid band and the mid from the treble band, in
+    /// hertz.
+    pub fn band_edges(&self) -> [f32; 2] {
+        self.band_edges
+    }
+
+    /// Sets the frequencies separating the bands, in hertz.
+    ///
+    /// The mid band is kept from being inverted.
+    pub fn set_band_edges(&mut self, bass_max: f32, mid_max: f32) {
+        self.band_edges = [bass_max, mid_max.max(bass_max)];
+    }
+
+    /// Computes the mean magnitude of bands spaced logarithmically between two frequencies, over
+    /// the spectrum of the last buffer.
+    pub fn log_bands<const N: usize>(&self, min_frequency: f32, max_frequency: f32) -> [f32; N] {
+        let mut sums = [0.0; N];
+        let mut counts = [0; N];
+        let octaves = (max_frequency / min_frequency).log2();
+        self.spectrum.iter().for_each(|&(frequency, value)| {
+            let t = (frequency / min_frequency).log2() / octaves;
+            if (0.0..1.0).contains(&t) {
+                let band = (t * N as f32) as usize;
+                sums[band] += value;
+                counts[band] += 1;
+            }
+        });
+        std::array::from_fn(|i| sums[i] / counts[i].max(1) as f32)
+    }
+
+    /// Computes the features of a buffer of samples; its length must be a power of 2.
+    pub fn analyze(&mut self, samples: &[f32], sample_rate: u32) -> AudioFeatures {
+        // Apply hann window for smoothing
+        let hann_window = hann_window(samples);
+        let spectrum = samples_fft_to_spectrum(
+            &hann_window,
+            sample_rate,
+            FrequencyLimit::Min(Self::MIN_FREQUENCY),
+            None,
+        );
+        let Ok(spectrum) = spectrum else {
+            self.spectrum.clear();
+            return AudioFeatures::default();
+        };
+        let spectrum: Vec<(f32, f32)> = spectrum
+            .data()
+            .iter()
+            .map(|(frequency, value)| (frequency.val(), value.val()))
+            .collect();
+
+        // Compute the mean magnitude of each band
+        let [bass_max, mid_max] = self.band_edges;
+        let mut sums = [0.0; 3];
+        let mut counts = [0; 3];
+        spectrum.iter().for_each(|&(frequency, value)| {
+            let band = match frequency {
+                f if f < bass_max => 0,
+                f if f < mid_max => 1,
+                _ => 2,
+            };
+            sums[band] += value;
+            counts[band] += 1;
+        });
+        let [bass, mid, treble] = std::array::from_fn(|i| sums[i] / counts[i].max(1) as f32);
+
+        let total = spectrum.iter().map(|(_, value)| value).sum::<f32>();
+        let onset = self.detect_onset(&spectrum, total);
+        self.spectrum = spectrum;
+
+        AudioFeatures {
+            magnitude: total.max(1.0),
+            bass,
+            mid,
+            treble,
+            onset,
+        }
+    }
+
+    /// Compares the flux from the previous spectrum to the recent flux.
+    fn detect_onset(&mut self, spectrum: &[(f32, f32)], total: f32) -> bool {
+        // The spectrum of a buffer of a different length cannot be compared
+        let flux = match self.spectrum.len() == spectrum.len() && total > 0.0 {
+            true => {
+                let growth = spectrum
+                    .iter()
+                    .zip(&self.spectrum)
+                    .map(|((_, current), (_, previous))| (current - previous).max(0.0))
+                    .sum::<f32>();
+                growth / total
+            }
+            false => 0.0,
+        };
+
+        let mean = match self.flux_history.is_empty() {
+            true => 0.0,
+            false => self.flux_history.iter().sum::<f32>() / self.flux_history.len() as f32,
+        };
+        if self.flux_history.len() == Self::FLUX_HISTORY_LENGTH {
+            self.flux_history.pop_front();
+        }
+        self.flux_history.push_back(flux);
+
+        flux > Self::MIN_ONSET_FLUX && flux > mean * Self::ONSET_THRESHOLD
+    }
+}
+
+/// The spectrum and the waveform of the last buffer analyzed, for the sketches to display.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AudioScope {
+    /// The mean magnitude of bands spaced logarithmically between the two frequencies.
+    pub bands: Vec<f32>,
+    /// The lowest and the highest frequencies of the bands, in hertz.
+    pub frequencies: [f32; 2],
+    /// The samples of the buffer mixed down to mono, thinned out to at most
+    /// [`AudioScope::WAVEFORM_LENGTH`].
+    pub waveform: Vec<f32>,
+}
+
+impl AudioScope {
+    /// The number of bands of the spectrum.
+    pub const BANDS: usize = 96;
+    /// The number of samples of the waveform.
+    pub const WAVEFORM_LENGTH: usize = 256;
+
+    /// Captures the spectrum of the buffer last analyzed by `analyzer`, and the buffer itself,
+    /// whose samples interleave `channels` channels.
+    pub fn capture(
+        analyzer: &AudioAnalyzer,
+        samples: &[f32],
+        channels: usize,
+        sample_rate: u32,
+    ) -> Self {
+        let frequencies = [AudioAnalyzer::MIN_FREQUENCY, sample_rate as f32 / 2.0];
+        let bands = analyzer.log_bands::<{ Self::BANDS }>(frequencies[0], frequencies[1]);
+        let frames = samples.chunks_exact(channels.max(1));
+        let step = frames.len().div_ceil(Self::WAVEFORM_LENGTH).max(1);
+        let waveform = frames
+            .step_by(step)
+            .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+            .collect();
+        Self {
+            bands: bands.to_vec(),
+            frequencies,
+            waveform,
+        }
+    }
+
+    /// The frequency at a position along the bands, from 0 at the lowest to 1 at the highest.
+    pub fn frequency_at(&self, position: f32) -> f32 {
+        let [min, max] = self.frequencies;
+        min * (max / min).powf(position)
+    }
+
+    /// The position of a frequency along the bands, from 0 at the lowest to 1 at the highest.
+    pub fn position_of(&self, frequency: f32) -> f32 {
+        let [min, max] = self.frequencies;
+        (frequency / min).log2() / (max / min).log2()
+    }
+}
+
+/// Decodes a stereo audio file in memory, returning its frames and its sample rate, so it can be
+/// sought through and looped.
+pub fn read_track(path: impl AsRef<Path>) -> Result<(Vec<[f32; 2]>, u32), &'static str> {
+    let mut reader = audrey::open(path).map_err(|_| "Failed to load audio file")?;
+    let sample_rate = reader.description().sample_rate();
+    let frames = reader
+        .frames::<[f32; 2]>()
+        .collect::<Result<_, _>>()
+        .map_err(|_| "Failed to decode audio file")?;
+    Ok((frames, sample_rate))
+}
+
+/// The transport of a track played by the audio thread: whether it plays, where its playhead is,
+/// whether it loops, and its gain.
+///
+/// It is shared with the sketch, which controls it and shows the playhead.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transport {
+    pub playing: bool,
+    /// Whether the track starts over at its end instead of stopping.
+    pub looping: bool,
+    /// The factor the samples of the track are scaled by.
+    pub gain: f32,
+    /// The frame of the track under the playhead.
+    position: usize,
+    /// The number of frames of the track.
+    length: usize,
+    sample_rate: u32,
+}
+
+impl Default for Transport {
+    /// The transport of an empty track.
+    fn default() -> Self {
+        Self::new(0, 48000)
+    }
+}
+
+impl Transport {
+    /// Creates the transport of a track of the given number of frames, playing from its start.
+    pub fn new(length: usize, sample_rate: u32) -> Self {
+        Self {
+            playing: length > 0,
+            looping: false,
+            gain: 1.0,
+            position: 0,
+            length,
+            sample_rate,
+        }
+    }
+
+    /// The time under the playhead, in seconds.
+    pub fn position(&self) -> f32 {
+        self.position as f32 / self.sample_rate as f32
+    }
+
+    /// The length of the track, in seconds.
+    pub fn duration(&self) -> f32 {
+        self.length as f32 / self.sample_rate as f32
+    }
+
+    /// Moves the playhead to the given time, in seconds, clamped to the track.
+    pub fn seek(&mut self, seconds: f32) {
+        let frame = (seconds.max(0.0) * self.sample_rate as f32) as usize;
+        self.position = frame.min(self.length);
+    }
+
+    /// Plays or pauses the track, starting it over if it played to its end.
+    pub fn toggle(&mut self) {
+        if !self.playing && self.position >= self.length {
+            self.position = 0;
+        }
+        self.playing = !self.playing && self.length > 0;
+    }
+
+    /// Adds the next frames of the track to the frames of the output, advancing the playhead.
+    ///
+    /// The track stops at its end unless it loops. The transport is only meant for `track`, but
+    /// frames past its end are never read.
+    pub fn play_into<'a>(
+        &mut self,
+        track: &[[f32; 2]],
+        output: impl IntoIterator<Item = &'a mut [f32]>,
+    ) {
+        if !self.playing {
+            return;
+        }
+        for frame in output {
+            if self.position >= self.length.min(track.len()) {
+                if !self.looping || track.is_empty() {
+                    self.playing = false;
+                    return;
+                }
+                self.position = 0;
+            }
+            frame
+                .iter_mut()
+                .zip(track[self.position])
+                .for_each(|(sample, track_sample)| *sample += track_sample * self.gain);
+            self.position += 1;
+        }
+    }
+}
+
+/// Formats a time in seconds as minutes and seconds, such as `1:05`.
+pub fn format_time(seconds: f32) -> String {
+    let seconds = seconds.max(0.0) as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 48000;
+    const BUFFER_LENGTH: usize = 1024;
+
+    /// A buffer of a sine wave of the given frequency, starting at the given sample.
+    fn sine(frequency: f32, start: usize) -> Vec<f32> {
+        (start..start + BUFFER_LENGTH)
+            .map(|i| (std::f32::consts::TAU * frequency * i as f32 / SAMPLE_RATE as f32).sin())
+            .collect()
+    }
+
+    #[test]
+    fn the_scope_shows_tones_at_their_frequency() {
+        let mut analyzer = AudioAnalyzer::new();
+        let samples = sine(1000.0, 0);
+        analyzer.analyze(&samples, SAMPLE_RATE);
+        let scope = AudioScope::capture(&analyzer, &samples, 1, SAMPLE_RATE);
+
+        let loudest = (0..AudioScope::BANDS)
+            .max_by(|&a, &b| scope.bands[a].total_cmp(&scope.bands[b]))
+            .unwrap();
+        let position = (loudest as f32 + 0.5) / AudioScope::BANDS as f32;
+        let frequency = scope.frequency_at(position);
+        assert!((800.0..1250.0).contains(&frequency), "{frequency}");
+        assert!((scope.position_of(frequency) - position).abs() < 1e-4);
+        assert_eq!(scope.waveform.len(), AudioScope::WAVEFORM_LENGTH);
+    }
+
+    #[test]
+    fn tones_fall_in_their_band() {
+        let mut analyzer = AudioAnalyzer::new();
+        let low = analyzer.analyze(&sine(100.0, 0), SAMPLE_RATE);
+        assert!(low.bass > low.mid && low.bass > low.treble);
+
+        let high = analyzer.analyze(&sine(8000.0, 0), SAMPLE_RATE);
+        assert!(high.treble > high.bass && high.treble > high.mid);
+    }
+
+    #[test]
+    fn band_edges_move_tones_between_bands() {
+        let mut analyzer = AudioAnalyzer::new().with_band_edges(1000.0, 2000.0);
+        let features = analyzer.analyze(&sine(440.0, 0), SAMPLE_RATE);
+        assert!(features.bass > features.mid);
+
+        analyzer.set_band_edges(200.0, 2000.0);
+        let features = analyzer.analyze(&sine(440.0, 0), SAMPLE_RATE);
+        assert!(features.mid > features.bass);
+    }
+
+    #[test]
+    fn log_bands_split_the_spectrum_by_octaves() {
+        let mut analyzer = AudioAnalyzer::new();
+        analyzer.analyze(&sine(1400.0, 0), SAMPLE_RATE);
+
+        // 1.4kHz is in the fifth octave above 62.5Hz
+        let bands: [f32; 8] = analyzer.log_bands(62.5, 16000.0);
+        let loudest = (0..8).max_by(|&a, &b| bands[a].total_cmp(&bands[b]));
+        assert_eq!(loudest, Some(4));
+    }
+
+    #[test]
+    fn onsets_are_detected_once() {
+        let mut analyzer = AudioAnalyzer::new();
+        let silence = vec![0.0; BUFFER_LENGTH];
+        (0..8).for_each(|_| assert!(!analyzer.analyze(&silence, SAMPLE_RATE).onset));
+
+        // A tone starting after silence is an onset, but holding it is not
+        assert!(analyzer.analyze(&sine(440.0, 0), SAMPLE_RATE).onset);
+        (1..8).for_each(|i| {
+            let features = analyzer.analyze(&sine(440.0, i * BUFFER_LENGTH), SAMPLE_RATE);
+            assert!(!features.onset);
+        });
+    }
+
+    /// Plays a transport into a silent output of the given number of mono frames.
+    fn play(transport: &mut Transport, track: &[[f32; 2]], frames: usize) -> Vec<f32> {
+        let mut output = vec![0.0; frames];
+        transport.play_into(track, output.chunks_mut(1));
+        output
+    }
+
+    #[test]
+    fn tracks_stop_at_their_end_unless_they_loop() {
+        let track: Vec<[f32; 2]> = (1..=3).map(|i| [i as f32; 2]).collect();
+        let mut transport = Transport::new(track.len(), 3);
+        transport.gain = 0.5;
+        assert_eq!(play(&mut transport, &track, 4), [0.5, 1.0, 1.5, 0.0]);
+        assert!(!transport.playing);
+
+        transport.toggle();
+        transport.looping = true;
+        assert_eq!(play(&mut transport, &track, 4), [0.5, 1.0, 1.5, 0.5]);
+        assert!(transport.playing);
+
+        // Paused tracks leave the output silent
+        transport.toggle();
+        assert_eq!(play(&mut transport, &track, 2), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn seeking_is_clamped_to_the_track() {
+        let track = [[1.0; 2], [2.0; 2], [3.0; 2], [4.0; 2]];
+        let mut transport = Transport::new(track.len(), 2);
+        assert_eq!(transport.duration(), 2.0);
+
+        transport.seek(1.0);
+        assert_eq!(transport.position(), 1.0);
+        assert_eq!(play(&mut transport, &track, 1), [3.0]);
+        transport.seek(10.0);
+        assert_eq!(transport.position(), 2.0);
+        transport.seek(-1.0);
+        assert_eq!(transport.position(), 0.0);
+        assert_eq!(format_time(65.9), "1:05");
+    }
+
+    #[test]
+    fn onsets_wait_to_be_taken() {
+        let mut features = AudioFeatures::default();
+        features.update(AudioFeatures {
+            onset: true,
+            ..AudioFeatures::default()
+        });
+        features.update(AudioFeatures::default());
+        assert!(features.take().onset);
+        assert!(!features.take().onset);
+    }
+}
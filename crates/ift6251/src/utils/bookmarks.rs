@@ -0,0 +1,154 @@
+use std::{
+    fmt,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::Path,
+};
+
+use crate::utils::mandelbrot::{IterationSettings, Style, Viewport};
+
+/// The file the mandelbrot sketch saves its bookmarks to.
+pub const BOOKMARKS_PATH: &str = "./mandelbrot_bookmarks.txt";
+
+/// The first line of a bookmarks file, naming the values of each bookmark.
+const HEADER: &str = "# re im width height max_iterations escape_radius power seed hue_scale saturation noise_x noise_y noise_z";
+
+/// A location of Mandlebrot's set saved along with the settings it was rendered with.
+///
+/// A bookmarks file holds one bookmark per line, as whitespace separated values. Empty lines and
+/// lines starting with `#` are ignored. The numbers are written in full so a location deep in the
+/// set is restored exactly.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bookmark {
+    pub viewport: Viewport,
+    pub iteration: IterationSettings,
+    pub style: Style,
+}
+
+impl Bookmark {
+    /// The number of values of a line of the bookmarks file.
+    const VALUES: usize = 13;
+
+    /// Parses a line of a bookmarks file.
+    pub fn parse(line: &str) -> Result<Self, &'static str> {
+        let values: Vec<&str> = line.split_whitespace().collect();
+        if values.len() != Self::VALUES {
+            return Err("A bookmark does not have the right number of values");
+        }
+        let float = |i: usize| {
+            values[i]
+                .parse::<f64>()
+                .map_err(|_| "A bookmark has an invalid number")
+        };
+        let integer = |i: usize| {
+            values[i]
+                .parse::<u64>()
+                .map_err(|_| "A bookmark has an invalid integer")
+        };
+
+        let (re, im) = (float(0)?, float(1)?);
+        let (width, height) = (float(2)?, float(3)?);
+        let viewport = Viewport::from_center((re, im), (width, height));
+        let iteration = IterationSettings::new(integer(4)? as usize)
+            .with_escape_radius(float(5)?)
+            .with_power(float(6)?);
+        let style = Style {
+            seed: integer(7)?
+                .try_into()
+                .map_err(|_| "A bookmark has an invalid seed")?,
+            hue_scale: float(8)?,
+            saturation: values[9]
+                .parse()
+                .map_err(|_| "A bookmark has an invalid number")?,
+            noise_scale: [float(10)?, float(11)?, float(12)?],
+        };
+        Ok(Self {
+            viewport,
+            iteration,
+            style,
+        })
+    }
+}
+
+impl fmt::Display for Bookmark {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (re, im) = self.viewport.center();
+        let (width, height) = self.viewport.size();
+        let iteration = &self.iteration;
+        let style = &self.style;
+        let [noise_x, noise_y, noise_z] = style.noise_scale;
+        write!(
+            f,
+            "{re:e} {im:e} {width:e} {height:e} {} {} {} {} {} {} {noise_x} {noise_y} {noise_z}",
+            iteration.max_iterations,
+            iteration.escape_radius,
+            iteration.power,
+            style.seed,
+            style.hue_scale,
+            style.saturation
+        )
+    }
+}
+
+/// Reads the bookmarks of a file, in order.
+pub fn read_bookmarks(path: impl AsRef<Path>) -> Result<Vec<Bookmark>, &'static str> {
+    let file = File::open(path).map_err(|_| "Failed to open the bookmarks file")?;
+    BufReader::new(file)
+        .lines()
+        .filter_map(|line| match line {
+            Ok(line) if line.trim().is_empty() || line.trim_start().starts_with('#') => None,
+            Ok(line) => Some(Bookmark::parse(&line)),
+            Err(_) => Some(Err("Failed to read the bookmarks file")),
+        })
+        .collect()
+}
+
+/// Appends a bookmark to a file, which is created if needed.
+pub fn append_bookmark(path: impl AsRef<Path>, bookmark: &Bookmark) -> Result<(), &'static str> {
+    let path = path.as_ref();
+    let is_new = !path.exists();
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|_| "Failed to open the bookmarks file")?;
+    if is_new {
+        writeln!(file, "{HEADER}").map_err(|_| "Failed to write the bookmarks file")?;
+    }
+    writeln!(file, "{bookmark}").map_err(|_| "Failed to write the bookmarks file")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bookmarks_round_trip() {
+        let bookmark = Bookmark {
+            viewport: Viewport::new(
+                (-0.7436438870371, -0.7436438870369),
+                (0.1318259042, 0.13182590425),
+            ),
+            iteration: IterationSettings::new(5000).with_power(3.5),
+            style: Style {
+                seed: 4_000_000_000,
+                ..Style::default()
+            },
+        };
+        let path = std::env::temp_dir().join(format!("bookmarks_{}.txt", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        append_bookmark(&path, &bookmark).unwrap();
+        append_bookmark(&path, &Bookmark::parse(&bookmark.to_string()).unwrap()).unwrap();
+
+        let bookmarks = read_bookmarks(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(bookmarks, [bookmark, bookmark]);
+    }
+
+    #[test]
+    fn bad_bookmarks_are_rejected() {
+        assert!(Bookmark::parse("0 0 1 1 100").is_err());
+        assert!(Bookmark::parse("0 0 1 1 100 2 2 -1 0 0.5 1 1 1").is_err());
+        assert!(Bookmark::parse("0 zero 1 1 100 2 2 0 0 0.5 1 1 1").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use nannou::geom::{Vec2, vec2};
+
+/// Traces the contour lines of a grid of values at a threshold with marching squares.
+///
+/// The values are sampled at the centers of the cells of a grid covering the unit square, row by
+/// row from the top like [`DensityGrid`](crate::utils::density::DensityGrid). Past the edges of
+/// the grid, the values are taken to be below the threshold, so every contour is closed.
+///
+/// Returns the segments of the contours, in the coordinates of the unit square with its y axis
+/// pointing up. The segments are linearly interpolated between the values of the cells.
+pub fn contour_segments(cells: &[Vec<f64>], threshold: f64) -> Vec<[Vec2; 2]> {
+    let height = cells.len() as isize;
+    let width = cells.first().map_or(0, Vec::len) as isize;
+    let value = |column: isize, row: isize| match (0..width).contains(&column)
+        && (0..height).contains(&row)
+    {
+        true => cells[row as usize][column as usize],
+        false => f64::NEG_INFINITY,
+    };
+    let point = |column: f64, row: f64| {
+        vec2(
+            ((column + 0.5) / width as f64) as f32,
+            (1.0 - (row + 0.5) / height as f64) as f32,
+        )
+    };
+    // Where the contour crosses the edge between two corners, as a fraction of the edge
+    let crossing = |a: f64, b: f64| match (a.is_finite(), b.is_finite()) {
+        (true, true) => (threshold - a) / (b - a),
+        (true, false) => 1.0,
+        (false, true) => 0.0,
+        (false, false) => 0.5,
+    };
+
+    let mut segments = Vec::new();
+    (-1..height).for_each(|row| {
+        (-1..width).for_each(|column| {
+            let (c, r) = (column as f64, row as f64);
+            let top_left = value(column, row);
+            let top_right = value(column + 1, row);
+            let bottom_right = value(column + 1, row + 1);
+            let bottom_left = value(column, row + 1);
+            let case = [top_left, top_right, bottom_right, bottom_left]
+                .into_iter()
+                .fold(0, |case, corner| case << 1 | (corner > threshold) as u8);
+            if case == 0 || case == 15 {
+                return;
+            }
+
+            let top = point(c + crossing(top_left, top_right), r);
+            let right = point(c + 1.0, r + crossing(top_right, bottom_right));
+            let bottom = point(c + crossing(bottom_left, bottom_right), r + 1.0);
+            let left = point(c, r + crossing(top_left, bottom_left));
+
+            // The saddles are resolved by the average of the corners
+            let center_inside =
+                (top_left + top_right + bottom_right + bottom_left) / 4.0 > threshold;
+            match case {
+                1 | 14 => segments.push([left, bottom]),
+                2 | 13 => segments.push([bottom, right]),
+                3 | 12 => segments.push([left, right]),
+                4 | 11 => segments.push([top, right]),
+                6 | 9 => segments.push([top, bottom]),
+                7 | 8 => segments.push([left, top]),
+                5 if center_inside => segments.extend([[left, top], [bottom, right]]),
+                5 => segments.extend([[top, right], [left, bottom]]),
+                10 if center_inside => segments.extend([[top, right], [left, bottom]]),
+                _ => segments.extend([[left, top], [bottom, right]]),
+            }
+        });
+    });
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_single_cell_is_circled() {
+        let cells = vec![
+            vec![0.0, 0.0, 0.0],
+            vec![0.0, 1.0, 0.0],
+            vec![0.0, 0.0, 0.0],
+        ];
+        let segments = contour_segments(&cells, 0.5);
+        assert_eq!(segments.len(), 4);
+
+        // The contour crosses halfway between the center and its neighbours
+        let center = vec2(0.5, 0.5);
+        segments.iter().flatten().for_each(|point| {
+            assert!((point.distance(center) - 1.0 / 6.0).abs() < 1e-6);
+        });
+        // Each end of a segment is the start of another, so the contour is closed
+        segments.iter().flatten().for_each(|point| {
+            let ends = segments.iter().flatten().filter(|other| *other == point);
+            assert_eq!(ends.count(), 2);
+        });
+    }
+
+    #[test]
+    fn contours_close_along_the_edges() {
+        assert!(contour_segments(&vec![vec![0.0; 4]; 4], 0.5).is_empty());
+        assert_eq!(contour_segments(&vec![vec![1.0; 4]; 4], 0.5).len(), 4 * 4);
+    }
+}
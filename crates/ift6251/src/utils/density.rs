@@ -0,0 +1,153 @@
+use nannou::image::{self, RgbaImage};
+
+use crate::utils::{
+    images::{equalize, recalibrate},
+    mandelbrot::Style,
+};
+
+/// A grid accumulating positions over time, like a long exposure.
+///
+/// The grid covers the unit square: `(0, 0)` is its bottom left corner and `(1, 1)` its top right
+/// corner, whatever the size of its cells.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DensityGrid {
+    /// The density of the cells, row by row from the top.
+    cells: Vec<Vec<f64>>,
+}
+
+impl DensityGrid {
+    /// Creates an empty grid; its size must not be null.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            cells: vec![vec![0.0; width.max(1)]; height.max(1)],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.cells[0].len()
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// The density of the cells, row by row from the top.
+    pub fn cells(&self) -> &[Vec<f64>] {
+        &self.cells
+    }
+
+    /// Adds a weight to the cell at a point of the unit square, ignoring points outside of it.
+    pub fn deposit(&mut self, x: f32, y: f32, weight: f64) {
+        if !(0.0..=1.0).contains(&x) || !(0.0..=1.0).contains(&y) {
+            return;
+        }
+        let column = ((x * self.width() as f32) as usize).min(self.width() - 1);
+        let row = (((1.0 - y) * self.height() as f32) as usize).min(self.height() - 1);
+        self.cells[row][column] += weight;
+    }
+
+    /// Scales the density of every cell, so that old positions fade away when below 1.
+    pub fn fade(&mut self, factor: f64) {
+        self.cells
+            .iter_mut()
+            .flatten()
+            .for_each(|cell| *cell *= factor);
+    }
+
+    pub fn clear(&mut self) {
+        self.fade(0.0);
+    }
+
+    /// Spreads the density of each cell over its neighbours, once per pass, with a 3x3 box blur.
+    ///
+    /// The density spreading past the edges of the grid is lost.
+    pub fn blur(&mut self, passes: u32) {
+        let (width, height) = (self.width(), self.height());
+        (0..passes).for_each(|_| {
+            let cells = &self.cells;
+            let blurred = (0..height)
+                .map(|row| {
+                    (0..width)
+                        .map(|column| {
+                            let rows = row.saturating_sub(1)..(row + 2).min(height);
+                            let columns = column.saturating_sub(1)..(column + 2).min(width);
+                            let sum: f64 = cells[rows]
+                                .iter()
+                                .map(|row| row[columns.clone()].iter().sum::<f64>())
+                                .sum();
+                            sum / 9.0
+                        })
+                        .collect()
+                })
+                .collect();
+            self.cells = blurred;
+        });
+    }
+
+    /// Colors the grid into an image of its size.
+    ///
+    /// The density is compressed logarithmically, then recalibrated and equalized so the sparse
+    /// regions stay visible next to the dense ones. Empty cells are black.
+    pub fn to_image(&self, style: &Style) -> RgbaImage {
+        let mut array: Vec<Vec<f64>> = self
+            .cells
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.ln_1p()).collect())
+            .collect();
+        // Recalibrating an empty grid would divide by zero
+        if array.iter().flatten().all(|&cell| cell <= 0.0) {
+            let (width, height) = (self.width() as u32, self.height() as u32);
+            return RgbaImage::from_pixel(width, height, image::Rgba([0, 0, 0, 255]));
+        }
+        recalibrate(&mut array);
+        equalize(&mut array, 0.0);
+        style.colorize(&array, 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions_land_in_their_cell() {
+        let mut grid = DensityGrid::new(4, 2);
+        grid.deposit(0.0, 1.0, 1.0);
+        grid.deposit(1.0, 0.0, 2.0);
+        grid.deposit(0.6, 0.2, 3.0);
+        grid.deposit(1.5, 0.5, 4.0);
+        assert_eq!(grid.cells, [[1.0, 0.0, 0.0, 0.0], [0.0, 0.0, 3.0, 2.0]]);
+
+        grid.fade(0.5);
+        assert_eq!(grid.cells[1][2], 1.5);
+        grid.clear();
+        assert!(grid.cells.iter().flatten().all(|&cell| cell == 0.0));
+    }
+
+    #[test]
+    fn blurring_spreads_the_density() {
+        let mut grid = DensityGrid::new(3, 3);
+        grid.deposit(0.5, 0.5, 9.0);
+        grid.blur(1);
+        assert!(grid.cells.iter().flatten().all(|&cell| cell == 1.0));
+
+        // The density spreading past the edges is lost
+        grid.blur(1);
+        assert_eq!(grid.cells[1][1], 1.0);
+        assert_eq!(grid.cells[0][0], 4.0 / 9.0);
+    }
+
+    #[test]
+    fn only_the_visited_cells_are_lit() {
+        let mut grid = DensityGrid::new(2, 1);
+        assert_eq!(
+            grid.to_image(&Style::default()).get_pixel(1, 0).0,
+            [0, 0, 0, 255]
+        );
+
+        grid.deposit(0.75, 0.5, 1.0);
+        let image = grid.to_image(&Style::default());
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0, 255]);
+        assert_ne!(image.get_pixel(1, 0).0, [0, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,138 @@
+use std::path::Path;
+
+use nannou::image;
+
+/// A 2D vector field sampled on a regular grid.
+///
+/// The field covers the unit square: `(0, 0)` is its bottom left corner and `(1, 1)` its top right
+/// corner, whatever the size of its grid. It is interpolated bilinearly between the cells.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VectorField {
+    width: usize,
+    height: usize,
+    /// The vectors of the cells, row by row from the top.
+    vectors: Vec<[f32; 2]>,
+}
+
+impl VectorField {
+    /// Creates a field from its vectors, row by row from the top.
+    pub fn new(width: usize, height: usize, vectors: Vec<[f32; 2]>) -> Result<Self, &'static str> {
+        if width == 0 || height == 0 || vectors.len() != width * height {
+            return Err("The vector field does not have the right number of vectors");
+        }
+        Ok(Self {
+            width,
+            height,
+            vectors,
+        })
+    }
+
+    /// Reads a field from a file, an image unless its extension is `csv`.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, &'static str> {
+        let path = path.as_ref();
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("csv") => {
+                let text = std::fs::read_to_string(path)
+                    .map_err(|_| "Failed to read the vector field file")?;
+                Self::parse_csv(&text)
+            }
+            _ => {
+                let image =
+                    image::open(path).map_err(|_| "Failed to read the vector field image")?;
+                Ok(Self::from_image(&image.to_rgb8()))
+            }
+        }
+    }
+
+    /// Creates a field from the red and green channels of an image, for the x and y components.
+    ///
+    /// A channel at 128 is a null component, while 0 and 255 are components of -1 and 1. The y
+    /// components point up the image.
+    pub fn from_image(image: &image::RgbImage) -> Self {
+        let component = |channel: u8| (channel as f32 - 128.0) / 127.0;
+        let vectors = image
+            .pixels()
+            .map(|&image::Rgb([r, g, _])| [component(r).max(-1.0), component(g).max(-1.0)])
+            .collect();
+        Self {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            vectors,
+        }
+    }
+
+    /// Parses a field where each line is a row of the grid, from the top, holding the x and y
+    /// components of its vectors separated by commas.
+    ///
+    /// Empty lines and lines starting with `#` are ignored.
+    pub fn parse_csv(text: &str) -> Result<Self, &'static str> {
+        let mut width = None;
+        let mut vectors = Vec::new();
+        let rows = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        for row in rows {
+            let values = row
+                .split(',')
+                .map(|value| value.trim().parse::<f32>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| "The vector field has an invalid number")?;
+            if values.len() % 2 != 0 || *width.get_or_insert(values.len() / 2) != values.len() / 2 {
+                return Err("The rows of the vector field do not have the same number of vectors");
+            }
+            vectors.extend(values.chunks_exact(2).map(|vector| [vector[0], vector[1]]));
+        }
+        let width = width.unwrap_or(0);
+        Self::new(width, vectors.len() / width.max(1), vectors)
+    }
+
+    /// Samples the field at a point of the unit square, clamped to its edges.
+    pub fn sample(&self, x: f32, y: f32) -> [f32; 2] {
+        // The centers of the cells are their samples
+        let column = (x.clamp(0.0, 1.0) * self.width as f32 - 0.5).max(0.0);
+        let row = ((1.0 - y.clamp(0.0, 1.0)) * self.height as f32 - 0.5).max(0.0);
+        let (left, top) = (column as usize, row as usize);
+        let right = (left + 1).min(self.width - 1);
+        let bottom = (top + 1).min(self.height - 1);
+        let (tx, ty) = (column.fract(), row.fract());
+
+        let vector = |column: usize, row: usize| self.vectors[row * self.width + column];
+        let lerp =
+            |a: [f32; 2], b: [f32; 2], t: f32| [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t];
+        let upper = lerp(vector(left, top), vector(right, top), tx);
+        let lower = lerp(vector(left, bottom), vector(right, bottom), tx);
+        lerp(upper, lower, ty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_fields_are_interpolated() {
+        let field = VectorField::parse_csv("# x,y\n1,0, 0,1\n\n-1,0, 0,-1\n").unwrap();
+        assert_eq!(field.sample(0.0, 1.0), [1.0, 0.0]);
+        assert_eq!(field.sample(1.0, 0.0), [0.0, -1.0]);
+        assert_eq!(field.sample(0.5, 0.5), [0.0, 0.0]);
+        assert_eq!(field.sample(0.5, 2.0), [0.5, 0.5]);
+    }
+
+    #[test]
+    fn bad_csv_fields_are_rejected() {
+        assert!(VectorField::parse_csv("").is_err());
+        assert!(VectorField::parse_csv("1,0,0").is_err());
+        assert!(VectorField::parse_csv("1,0,0,1\n1,0").is_err());
+        assert!(VectorField::parse_csv("1,up").is_err());
+    }
+
+    #[test]
+    fn image_channels_map_to_components() {
+        let mut image = image::RgbImage::from_pixel(2, 2, image::Rgb([128, 128, 0]));
+        image.put_pixel(0, 0, image::Rgb([255, 0, 0]));
+        let field = VectorField::from_image(&image);
+        assert_eq!(field.sample(0.0, 1.0), [1.0, -1.0]);
+        assert_eq!(field.sample(1.0, 0.0), [0.0, 0.0]);
+    }
+}
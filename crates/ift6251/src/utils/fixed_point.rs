@@ -0,0 +1,312 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    ops::{Add, Mul, Neg, Sub},
+};
+
+/// A signed real number in fixed point, with as many fractional bits as it needs.
+///
+/// The magnitude is stored in words of 32 bits, the last one being the integer part, so the
+/// numbers must stay below 2^32 in magnitude. The operations keep the precision of their most
+/// precise operand, truncating the bits of the products below it.
+#[derive(Clone, Debug)]
+pub struct BigFixed {
+    negative: bool,
+    /// The words of the magnitude from the least significant, all but the last one being the
+    /// fraction.
+    words: Vec<u32>,
+}
+
+impl BigFixed {
+    const WORD_BITS: i32 = u32::BITS as i32;
+
+    pub fn zero() -> Self {
+        Self {
+            negative: false,
+            words: vec![0],
+        }
+    }
+
+    /// Converts an `f64` exactly, with as many fractional words as its bits need.
+    ///
+    /// The values that are not finite are converted to zero, and the bits of the integer part past
+    /// the first 32 are dropped.
+    pub fn from_f64(x: f64) -> Self {
+        if !x.is_finite() || x == 0.0 {
+            return Self::zero();
+        }
+
+        // The value is the mantissa times 2 to the exponent, subnormals having no implicit bit
+        let bits = x.abs().to_bits();
+        let mantissa = bits & ((1 << 52) - 1);
+        let (mantissa, exponent) = match (bits >> 52) as i32 {
+            0 => (mantissa, -1074),
+            biased => (mantissa | 1 << 52, biased - 1075),
+        };
+
+        let fraction_words = ((-exponent).max(0) as usize).div_ceil(u32::BITS as usize);
+        let mut words = vec![0; fraction_words + 1];
+        let total_bits = words.len() as i32 * Self::WORD_BITS;
+        (0..53)
+            .filter(|bit| mantissa >> bit & 1 == 1)
+            .map(|bit| bit + exponent + fraction_words as i32 * Self::WORD_BITS)
+            .filter(|position| (0..total_bits).contains(position))
+            .for_each(|position| {
+                words[(position / Self::WORD_BITS) as usize] |= 1 << (position % Self::WORD_BITS);
+            });
+
+        Self {
+            negative: x < 0.0,
+            words,
+        }
+        .normalized()
+    }
+
+    /// Rounds the number to the nearest `f64`, up to the rounding of the sum of its words.
+    pub fn to_f64(&self) -> f64 {
+        let fraction_words = self.fraction_words() as i32;
+        let magnitude: f64 = self
+            .words
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(i, &word)| {
+                word as f64 * 2f64.powi((i as i32 - fraction_words) * Self::WORD_BITS)
+            })
+            .sum();
+        match self.negative {
+            true => -magnitude,
+            false => magnitude,
+        }
+    }
+
+    /// The number of words of 32 bits of the fraction.
+    pub fn fraction_words(&self) -> usize {
+        self.words.len() - 1
+    }
+
+    /// Returns the number with another number of fractional words, padding it with zeros or
+    /// truncating its lowest bits.
+    pub fn with_fraction_words(mut self, fraction_words: usize) -> Self {
+        let current = self.fraction_words();
+        match fraction_words.cmp(&current) {
+            Ordering::Greater => {
+                let padding = std::iter::repeat_n(0, fraction_words - current);
+                self.words.splice(0..0, padding);
+            }
+            Ordering::Less => {
+                self.words.drain(..current - fraction_words);
+            }
+            Ordering::Equal => {}
+        }
+        self.normalized()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.words.iter().all(|&word| word == 0)
+    }
+
+    /// Zero is never negative, so the numbers compare equal whatever the operations led to them.
+    fn normalized(mut self) -> Self {
+        self.negative &= !self.is_zero();
+        self
+    }
+
+    /// Brings two numbers to the precision of the most precise one.
+    fn aligned(&self, other: &Self) -> (Self, Self) {
+        let fraction_words = self.fraction_words().max(other.fraction_words());
+        (
+            self.clone().with_fraction_words(fraction_words),
+            other.clone().with_fraction_words(fraction_words),
+        )
+    }
+}
+
+/// The numbers are equal whatever their precision.
+impl PartialEq for BigFixed {
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b) = self.aligned(other);
+        a.negative == b.negative && a.words == b.words
+    }
+}
+
+impl Eq for BigFixed {}
+
+/// Compares the magnitudes of words of the same length.
+fn compare_magnitudes(a: &[u32], b: &[u32]) -> Ordering {
+    a.iter().rev().cmp(b.iter().rev())
+}
+
+/// Adds magnitudes of the same length, dropping the carry past the integer part.
+fn add_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut carry = 0;
+    a.iter()
+        .zip(b)
+        .map(|(&a, &b)| {
+            let sum = a as u64 + b as u64 + carry;
+            carry = sum >> 32;
+            sum as u32
+        })
+        .collect()
+}
+
+/// Subtracts a magnitude from a larger one of the same length.
+fn subtract_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut borrow = 0;
+    a.iter()
+        .zip(b)
+        .map(|(&a, &b)| {
+            let (difference, overflow_b) = a.overflowing_sub(b);
+            let (difference, overflow_borrow) = difference.overflowing_sub(borrow);
+            borrow = (overflow_b || overflow_borrow) as u32;
+            difference
+        })
+        .collect()
+}
+
+impl Add for &BigFixed {
+    type Output = BigFixed;
+
+    fn add(self, other: &BigFixed) -> BigFixed {
+        let (a, b) = self.aligned(other);
+        let (words, negative) = match (
+            a.negative == b.negative,
+            compare_magnitudes(&a.words, &b.words),
+        ) {
+            (true, _) => (add_magnitudes(&a.words, &b.words), a.negative),
+            (false, Ordering::Less) => (subtract_magnitudes(&b.words, &a.words), b.negative),
+            (false, _) => (subtract_magnitudes(&a.words, &b.words), a.negative),
+        };
+        BigFixed { negative, words }.normalized()
+    }
+}
+
+impl Neg for &BigFixed {
+    type Output = BigFixed;
+
+    fn neg(self) -> BigFixed {
+        BigFixed {
+            negative: !self.negative,
+            words: self.words.clone(),
+        }
+        .normalized()
+    }
+}
+
+impl Sub for &BigFixed {
+    type Output = BigFixed;
+
+    fn sub(self, other: &BigFixed) -> BigFixed {
+        self + &-other
+    }
+}
+
+impl Mul for &BigFixed {
+    type Output = BigFixed;
+
+    fn mul(self, other: &BigFixed) -> BigFixed {
+        let (a, b) = self.aligned(other);
+        let fraction_words = a.fraction_words();
+
+        // The product has twice the fractional words, the lowest ones are truncated
+        let mut product = vec![0u64; 2 * a.words.len()];
+        a.words.iter().enumerate().for_each(|(i, &a)| {
+            let mut carry = 0;
+            b.words.iter().enumerate().for_each(|(j, &b)| {
+                let sum = product[i + j] + a as u64 * b as u64 + carry;
+                product[i + j] = sum & u32::MAX as u64;
+                carry = sum >> 32;
+            });
+            product[i + b.words.len()] += carry;
+        });
+        let words = product[fraction_words..fraction_words + a.words.len()]
+            .iter()
+            .map(|&word| word as u32)
+            .collect();
+
+        BigFixed {
+            negative: a.negative != b.negative,
+            words,
+        }
+        .normalized()
+    }
+}
+
+/// Formats the number in decimal, truncated to the precision of the formatter, 6 digits by
+/// default, like `f64` does.
+impl fmt::Display for BigFixed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = match (self.negative, f.sign_plus()) {
+            (true, _) => "-",
+            (false, true) => "+",
+            (false, false) => "",
+        };
+        let integer = self.words[self.fraction_words()];
+        write!(f, "{sign}{integer}")?;
+
+        // Multiplying the fraction by 10 carries its next digit out of it
+        let decimals = f.precision().unwrap_or(6);
+        if decimals > 0 {
+            let mut fraction = self.words[..self.fraction_words()].to_vec();
+            let digits: String = (0..decimals)
+                .map(|_| {
+                    let mut carry = 0;
+                    fraction.iter_mut().for_each(|word| {
+                        let value = *word as u64 * 10 + carry;
+                        *word = value as u32;
+                        carry = value >> 32;
+                    });
+                    char::from_digit(carry as u32, 10).unwrap()
+                })
+                .collect();
+            write!(f, ".{digits}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn f64_round_trips(x in -1e9..1e9f64) {
+            prop_assert_eq!(BigFixed::from_f64(x).to_f64(), x);
+        }
+
+        #[test]
+        fn operations_match_f64(a in -100.0..100.0f64, b in -100.0..100.0f64) {
+            // The words are summed into an f64, so the results can be rounded twice
+            let (big_a, big_b) = (BigFixed::from_f64(a), BigFixed::from_f64(b));
+            let tolerance = (a.abs() + b.abs()) * 1e-15;
+            prop_assert!(((&big_a + &big_b).to_f64() - (a + b)).abs() <= tolerance);
+            prop_assert!(((&big_a - &big_b).to_f64() - (a - b)).abs() <= tolerance);
+            let product = (&big_a * &big_b).to_f64();
+            prop_assert!((product - a * b).abs() <= (a * b).abs() * 1e-15);
+        }
+    }
+
+    #[test]
+    fn bits_below_f64_are_kept() {
+        let one = BigFixed::from_f64(1.0);
+        let tiny = BigFixed::from_f64(1e-30);
+        let sum = &one + &tiny;
+        assert_eq!(sum.to_f64(), 1.0);
+        assert_eq!((&sum - &one).to_f64(), 1e-30);
+        assert_eq!(&(&sum - &tiny) - &one, BigFixed::zero());
+
+        let half = BigFixed::from_f64(-0.5);
+        assert_eq!((&(&sum * &half) - &half).to_f64(), -0.5e-30);
+    }
+
+    #[test]
+    fn decimals_are_exact() {
+        let x = &BigFixed::from_f64(-0.75) - &BigFixed::from_f64(1e-20);
+        assert_eq!(format!("{x:.22}"), "-0.7500000000000000000099");
+        assert_eq!(format!("{:+.3}", BigFixed::from_f64(2.5)), "+2.500");
+        assert_eq!(BigFixed::from_f64(0.125).to_string(), "0.125000");
+    }
+}
@@ -0,0 +1,216 @@
+use std::path::PathBuf;
+
+use nannou::image::{self, RgbaImage};
+
+/// The environment variable that, when set, overwrites the golden images with the actual renders.
+pub const UPDATE_VARIABLE: &str = "UPDATE_GOLDEN";
+
+/// The maximum value of the perceptual difference between two colors.
+const MAX_DELTA: f64 = 35215.0;
+
+/// How different an image may be from its golden image before a comparison fails.
+#[derive(Clone, Copy, Debug)]
+pub struct Tolerance {
+    /// The perceptual difference above which two pixels are considered different, in the range
+    /// [0, 1].
+    pub threshold: f64,
+    /// The fraction of pixels that may differ, in the range [0, 1].
+    pub max_differing: f64,
+}
+
+impl Tolerance {
+    /// A tolerance requiring the images to be identical.
+    pub const EXACT: Self = Self {
+        threshold: 0.0,
+        max_differing: 0.0,
+    };
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            threshold: 0.01,
+            max_differing: 0.001,
+        }
+    }
+}
+
+/// The result of the comparison of two images.
+#[derive(Clone, Copy, Debug)]
+pub struct Comparison {
+    /// The number of pixels whose difference exceeds the threshold.
+    pub differing: usize,
+    /// The number of pixels compared.
+    pub total: usize,
+    /// The largest perceptual difference between two pixels, in the range [0, 1].
+    pub max_delta: f64,
+}
+
+impl Comparison {
+    /// Whether the images are within the tolerance of each other.
+    pub fn passes(&self, tolerance: Tolerance) -> bool {
+        self.differing as f64 <= tolerance.max_differing * self.total as f64
+    }
+}
+
+/// Compares two images pixel by pixel with a perceptual metric.
+///
+/// The difference of two pixels is measured in the YIQ color space, which weighs the luminance
+/// more than the chrominance like the human eye does (Kotsarenko and Ramos, 2010). The colors are
+/// blended onto white according to their alpha before being compared.
+///
+/// Returns an error if the images do not have the same size.
+pub fn compare(
+    expected: &RgbaImage,
+    actual: &RgbaImage,
+    threshold: f64,
+) -> Result<Comparison, &'static str> {
+    if expected.dimensions() != actual.dimensions() {
+        return Err("The images do not have the same size");
+    }
+
+    let mut comparison = Comparison {
+        differing: 0,
+        total: expected.pixels().len(),
+        max_delta: 0.0,
+    };
+    expected.pixels().zip(actual.pixels()).for_each(|(a, b)| {
+        let delta = color_delta(a, b);
+        comparison.max_delta = comparison.max_delta.max(delta);
+        if delta > threshold {
+            comparison.differing += 1;
+        }
+    });
+
+    Ok(comparison)
+}
+
+/// Returns an image highlighting in red the pixels that differ by more than the threshold.
+///
+/// The pixels that match are drawn as a faded grayscale version of the expected image.
+///
+/// Returns an error if the images do not have the same size.
+pub fn diff_image(
+    expected: &RgbaImage,
+    actual: &RgbaImage,
+    threshold: f64,
+) -> Result<RgbaImage, &'static str> {
+    if expected.dimensions() != actual.dimensions() {
+        return Err("The images do not have the same size");
+    }
+
+    let (width, height) = expected.dimensions();
+    Ok(RgbaImage::from_fn(width, height, |x, y| {
+        let a = expected.get_pixel(x, y);
+        let b = actual.get_pixel(x, y);
+        if color_delta(a, b) > threshold {
+            image::Rgba([255, 0, 0, 255])
+        } else {
+            let luma = (255.0 - (255.0 - yiq(a)[0]) * 0.1) as u8;
+            image::Rgba([luma, luma, luma, 255])
+        }
+    }))
+}
+
+/// Asserts that an image matches the golden image of the given name.
+///
+/// The golden images are stored as PNG files in the `golden` directory of the crate. If
+/// [`UPDATE_VARIABLE`] is set, the image is saved as the new golden image instead. On failure, the
+/// actual image and a diff image are saved in the temporary directory to help find what changed.
+///
+/// # Panics
+///
+/// This function panics if the image does not match the golden image within the tolerance, or if
+/// the golden image does not exist and [`UPDATE_VARIABLE`] is not set, so a forgotten golden image
+/// cannot pass silently.
+pub fn assert_golden(name: &str, image: &RgbaImage, tolerance: Tolerance) {
+    let path = golden_path(name);
+    if std::env::var_os(UPDATE_VARIABLE).is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        image.save(&path).unwrap();
+        println!("Saved golden image to: {}", path.display());
+        return;
+    }
+    if !path.exists() {
+        panic!(
+            "Golden {name} does not exist at {}; set {UPDATE_VARIABLE}=1 to create it",
+            path.display()
+        );
+    }
+
+    let expected = image::open(&path).unwrap().to_rgba8();
+    let comparison = match compare(&expected, image, tolerance.threshold) {
+        Ok(comparison) => comparison,
+        Err(error) => panic!(
+            "{error}: golden {name} is {:?} but got {:?}",
+            expected.dimensions(),
+            image.dimensions()
+        ),
+    };
+    if comparison.passes(tolerance) {
+        return;
+    }
+
+    let directory = std::env::temp_dir().join("golden");
+    std::fs::create_dir_all(&directory).unwrap();
+    let actual_path = directory.join(format!("{name}.actual.png"));
+    let diff_path = directory.join(format!("{name}.diff.png"));
+    image.save(&actual_path).unwrap();
+    diff_image(&expected, image, tolerance.threshold)
+        .unwrap()
+        .save(&diff_path)
+        .unwrap();
+
+    panic!(
+        "Golden {name} differs in {} of {} pixels (max delta {:.4}), see {} and {}; \
+         set {UPDATE_VARIABLE}=1 to accept the new render",
+        comparison.differing,
+        comparison.total,
+        comparison.max_delta,
+        actual_path.display(),
+        diff_path.display()
+    );
+}
+
+/// Converts a 2D array of luminance values in the range [0, 255] to a grayscale image.
+///
+/// This is useful to compare the intermediate arrays of the renderers.
+pub fn from_luma(array: &[Vec<f64>]) -> RgbaImage {
+    let width = array[0].len() as u32;
+    let height = array.len() as u32;
+    RgbaImage::from_fn(width, height, |x, y| {
+        let luma = array[y as usize][x as usize].clamp(0.0, 255.0).round() as u8;
+        image::Rgba([luma, luma, luma, 255])
+    })
+}
+
+/// The path of the golden image of the given name.
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("golden")
+        .join(format!("{name}.png"))
+}
+
+/// The perceptual difference between two colors, in the range [0, 1].
+fn color_delta(a: &image::Rgba<u8>, b: &image::Rgba<u8>) -> f64 {
+    if a == b {
+        return 0.0;
+    }
+
+    let (a, b) = (yiq(a), yiq(b));
+    let [y, i, q] = std::array::from_fn(|k| a[k] - b[k]);
+    (0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q) / MAX_DELTA
+}
+
+/// Converts a color blended onto white to the YIQ color space.
+fn yiq(color: &image::Rgba<u8>) -> [f64; 3] {
+    let [r, g, b, a] = color.0.map(f64::from);
+    let alpha = a / 255.0;
+    let [r, g, b] = [r, g, b].map(|c| 255.0 + (c - 255.0) * alpha);
+
+    [
+        r * 0.29889531 + g * 0.58662247 + b * 0.11448223,
+        r * 0.59597799 - g * 0.27417610 - b * 0.32180189,
+        r * 0.21147017 - g * 0.52261711 + b * 0.31114694,
+    ]
+}
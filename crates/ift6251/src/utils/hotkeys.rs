@@ -0,0 +1,159 @@
+use nannou::prelude::Key;
+use nannou_egui::egui;
+
+use crate::utils::palette::PALETTE_KEY;
+
+/// The key showing and hiding the list of the hotkeys in every sketch, `?` on most layouts.
+pub const HELP_KEY: Key = Key::Slash;
+
+/// The keys bound to an action of a sketch.
+///
+/// Each sketch lists its bindings in a single table, which both runs the actions of the pressed
+/// keys and documents them in the [`HotkeyHelp`] overlay, so the two cannot disagree.
+pub struct Binding<A> {
+    /// The keys running the action, any of them will do.
+    pub keys: Vec<Key>,
+    /// What the action does, as listed in the overlay.
+    pub description: String,
+    pub action: A,
+}
+
+impl<A> Binding<A> {
+    /// Creates a new binding.
+    pub fn new(keys: &[Key], description: impl Into<String>, action: A) -> Self {
+        Self {
+            keys: keys.to_vec(),
+            description: description.into(),
+            action,
+        }
+    }
+
+    /// The names of the keys of the binding, separated by slashes.
+    pub fn keys_label(&self) -> String {
+        self.keys
+            .iter()
+            .map(|&key| key_name(key))
+            .collect::<Vec<_>>()
+            .join(" / ")
+    }
+}
+
+/// Returns the action of the first binding of the key, if any.
+pub fn action_for<A: Clone>(bindings: &[Binding<A>], key: Key) -> Option<A> {
+    bindings
+        .iter()
+        .find(|binding| binding.keys.contains(&key))
+        .map(|binding| binding.action.clone())
+}
+
+/// Lists the keys and descriptions of the bindings, as shown by [`HotkeyHelp::show`].
+pub fn describe<A>(bindings: &[Binding<A>]) -> Vec<(String, String)> {
+    bindings
+        .iter()
+        .map(|binding| (binding.keys_label(), binding.description.clone()))
+        .collect()
+}
+
+/// The name of a key as printed on most keyboards.
+pub fn key_name(key: Key) -> String {
+    match key {
+        Key::Slash => "?".to_owned(),
+        Key::Plus => "+".to_owned(),
+        Key::Equals => "=".to_owned(),
+        Key::Minus => "-".to_owned(),
+        Key::Comma => ",".to_owned(),
+        Key::Period => ".".to_owned(),
+        Key::Escape => "Esc".to_owned(),
+        Key::Key0 => "0".to_owned(),
+        Key::Key1 => "1".to_owned(),
+        Key::Key2 => "2".to_owned(),
+        Key::Key3 => "3".to_owned(),
+        Key::Key4 => "4".to_owned(),
+        Key::Key5 => "5".to_owned(),
+        Key::Key6 => "6".to_owned(),
+        Key::Key7 => "7".to_owned(),
+        Key::Key8 => "8".to_owned(),
+        Key::Key9 => "9".to_owned(),
+        key => format!("{key:?}"),
+    }
+}
+
+/// An overlay listing the hotkeys of a sketch, opened with [`HELP_KEY`].
+///
+/// Like the command palette, the overlay only holds whether it is open; the hotkeys are given every
+/// frame from the binding table of the sketch.
+#[derive(Default)]
+pub struct HotkeyHelp {
+    open: bool,
+}
+
+impl HotkeyHelp {
+    /// Creates a closed overlay.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens or closes the overlay.
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Shows the overlay if it is open, listing the keys shared by every sketch before the hotkeys.
+    ///
+    /// # Arguments
+    ///
+    /// - `ctx` - The egui context of the frame.
+    /// - `hotkeys` - The names of the keys and what they do, see [`describe`].
+    pub fn show(&self, ctx: &egui::Context, hotkeys: &[(String, String)]) {
+        if !self.open {
+            return;
+        }
+
+        let shared = [
+            (key_name(PALETTE_KEY), "Open the command palette".to_owned()),
+            (key_name(HELP_KEY), "Show or hide this list".to_owned()),
+        ];
+        egui::Window::new("Hotkeys")
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("hotkeys")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        shared
+                            .iter()
+                            .chain(hotkeys)
+                            .for_each(|(keys, description)| {
+                                ui.strong(keys);
+                                ui.label(description);
+                                ui.end_row();
+                            });
+                    });
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn any_key_of_a_binding_runs_its_action() {
+        let bindings = [
+            Binding::new(&[Key::X, Key::Escape], "Quit", 0),
+            Binding::new(&[Key::Plus, Key::Equals], "Zoom in", 1),
+        ];
+        assert_eq!(action_for(&bindings, Key::Escape), Some(0));
+        assert_eq!(action_for(&bindings, Key::Equals), Some(1));
+        assert_eq!(action_for(&bindings, Key::A), None);
+        assert_eq!(
+            describe(&bindings),
+            vec![
+                ("X / Esc".to_owned(), "Quit".to_owned()),
+                ("+ / =".to_owned(), "Zoom in".to_owned()),
+            ]
+        );
+    }
+}
@@ -34,8 +34,8 @@ pub fn create_texture(
 /// # Arguments
 ///
 /// - `mat` - A mutable reference to a 2D vector (`Vec<Vec<f64>>`) containing pixel values.
-///     The pixel values should be of type `f64`, but they may not necessarily be within the
-///     [0, 255] range before recalibration.
+///   The pixel values should be of type `f64`, but they may not necessarily be within the
+///   [0, 255] range before recalibration.
 ///
 /// # Panics
 ///
@@ -145,3 +145,89 @@ pub fn equalize(array: &mut [Vec<f64>], thresh: f64) {
         });
     });
 }
+
+/// Finds the pixels of a 2D array that differ strongly from one of their direct neighbours.
+///
+/// Such pixels lie on the edges of the image, where aliasing shows, so they are the ones worth
+/// supersampling.
+///
+/// # Arguments
+///
+/// - `array` - A 2D vector of `f64` values representing the pixel intensities of an image.
+/// - `threshold` - The difference between two neighbours above which both are returned, relative
+///   to the largest of the two values.
+///
+/// # Returns
+///
+/// The `(x, y)` coordinates of the pixels on an edge, row by row.
+pub fn edge_pixels(array: &[Vec<f64>], threshold: f64) -> Vec<(usize, usize)> {
+    let height = array.len();
+    let differs = |a: f64, b: f64| (a - b).abs() > threshold * a.abs().max(b.abs());
+
+    let mut edges = Vec::new();
+    (0..height).for_each(|i| {
+        let width = array[i].len();
+        (0..width).for_each(|j| {
+            let luma = array[i][j];
+            let on_edge = (i > 0 && differs(luma, array[i - 1][j]))
+                || (i + 1 < height && differs(luma, array[i + 1][j]))
+                || (j > 0 && differs(luma, array[i][j - 1]))
+                || (j + 1 < width && differs(luma, array[i][j + 1]));
+            if on_edge {
+                edges.push((j, i));
+            }
+        });
+    });
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::golden::{Tolerance, assert_golden, from_luma};
+
+    /// A smooth but uneven pattern with values outside of [0, 255].
+    fn pattern(width: usize, height: usize) -> Vec<Vec<f64>> {
+        (0..height)
+            .map(|i| {
+                (0..width)
+                    .map(|j| {
+                        let (x, y) = (j as f64 / width as f64, i as f64 / height as f64);
+                        (x * 7.0).sin() * (y * 5.0).cos() * 400.0 + x.powi(3) * 200.0
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn recalibrate_matches_golden() {
+        let mut array = pattern(64, 48);
+        recalibrate(&mut array);
+        assert_golden("images_recalibrate", &from_luma(&array), Tolerance::EXACT);
+    }
+
+    #[test]
+    fn equalize_matches_golden() {
+        let mut array = pattern(64, 48);
+        recalibrate(&mut array);
+        equalize(&mut array, 0.0);
+        assert_golden("images_equalize", &from_luma(&array), Tolerance::EXACT);
+    }
+
+    #[test]
+    fn edge_pixels_lie_on_both_sides_of_a_step() {
+        let array: Vec<Vec<f64>> = (0..4)
+            .map(|_| (0..6).map(|j| if j < 3 { 10.0 } else { 100.0 }).collect())
+            .collect();
+        let edges = edge_pixels(&array, 0.1);
+        assert_eq!(edges.len(), 8);
+        assert!(edges.iter().all(|&(x, _)| x == 2 || x == 3));
+
+        // A smooth ramp has no edge
+        let ramp: Vec<Vec<f64>> = (0..4)
+            .map(|_| (0..6).map(|j| 100.0 + j as f64).collect())
+            .collect();
+        assert!(edge_pixels(&ramp, 0.1).is_empty());
+    }
+}
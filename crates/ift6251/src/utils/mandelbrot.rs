@@ -1,3 +1,405 @@
+use nannou::{
+    color::{IntoColor, LinSrgb, encoding::Srgb, hsl},
+    image::{self, ImageBuffer, Pixel, RgbaImage},
+    noise::{NoiseFn, Perlin, Seedable},
+};
+use point_cloud_renderer::color::{ColorSpace, LinearImage};
+use rayon::iter::{ParallelBridge, ParallelIterator};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::rng::{random, random_range};
+
+/// The region of the complex plane shown by the image.
+///
+/// The viewport is stored as its center and half its size rather than as the bounds of its ranges,
+/// so zooming never moves the center and the rounding errors of the operations do not accumulate
+/// into a drift of the image.
+///
+/// The size of the viewport is always strictly positive.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    center: (f64, f64),
+    half_size: (f64, f64),
+}
+
+impl Viewport {
+    /// Creates a viewport spanning the given ranges of the real and imaginary axes.
+    ///
+    /// # Arguments
+    ///
+    /// - `x_range` - The range of the real axis.
+    /// - `y_range` - The range of the imaginary axis.
+    pub fn new(x_range: (f64, f64), y_range: (f64, f64)) -> Self {
+        let half_size =
+            |range: (f64, f64)| ((range.1 - range.0).abs() / 2.0).max(f64::MIN_POSITIVE);
+        Self {
+            center: ((x_range.0 + x_range.1) / 2.0, (y_range.0 + y_range.1) / 2.0),
+            half_size: (half_size(x_range), half_size(y_range)),
+        }
+    }
+
+    /// Creates a viewport of the given size around a point of the complex plane.
+    ///
+    /// # Arguments
+    ///
+    /// - `center` - The real and imaginary parts of the center.
+    /// - `size` - The width and height of the viewport.
+    pub fn from_center(center: (f64, f64), size: (f64, f64)) -> Self {
+        let half_size = |size: f64| (size.abs() / 2.0).max(f64::MIN_POSITIVE);
+        Self {
+            center,
+            half_size: (half_size(size.0), half_size(size.1)),
+        }
+    }
+
+    /// The center of the viewport.
+    pub fn center(&self) -> (f64, f64) {
+        self.center
+    }
+
+    /// Moves the center of the viewport to a point of the complex plane.
+    pub fn set_center(&mut self, center: (f64, f64)) {
+        self.center = center;
+    }
+
+    /// The width and height of the viewport.
+    pub fn size(&self) -> (f64, f64) {
+        (2.0 * self.half_size.0, 2.0 * self.half_size.1)
+    }
+
+    /// The range of the real axis covered by the viewport.
+    pub fn x_range(&self) -> (f64, f64) {
+        (
+            self.center.0 - self.half_size.0,
+            self.center.0 + self.half_size.0,
+        )
+    }
+
+    /// The range of the imaginary axis covered by the viewport.
+    pub fn y_range(&self) -> (f64, f64) {
+        (
+            self.center.1 - self.half_size.1,
+            self.center.1 + self.half_size.1,
+        )
+    }
+
+    /// Scales the viewport around its center.
+    ///
+    /// A factor smaller than 1 zooms in. Factors that are not strictly positive are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// - `factor` - The factor to scale the size of the viewport by.
+    pub fn zoom(&mut self, factor: f64) {
+        if factor <= 0.0 || !factor.is_finite() {
+            return;
+        }
+        self.half_size.0 = (self.half_size.0 * factor).max(f64::MIN_POSITIVE);
+        self.half_size.1 = (self.half_size.1 * factor).max(f64::MIN_POSITIVE);
+    }
+
+    /// Scales the viewport around a point of the complex plane, which stays at the same place in
+    /// the image.
+    ///
+    /// This is how zooming toward the cursor behaves. A factor smaller than 1 zooms in. Factors
+    /// that are not strictly positive are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// - `c` - The real and imaginary parts of the point to zoom around.
+    /// - `factor` - The factor to scale the size of the viewport by.
+    pub fn zoom_at(&mut self, c: (f64, f64), factor: f64) {
+        if factor <= 0.0 || !factor.is_finite() {
+            return;
+        }
+        self.center.0 = c.0 + (self.center.0 - c.0) * factor;
+        self.center.1 = c.1 + (self.center.1 - c.1) * factor;
+        self.zoom(factor);
+    }
+
+    /// Moves the center of the viewport.
+    ///
+    /// # Arguments
+    ///
+    /// - `offset` - The offset along the real and imaginary axes.
+    pub fn shift(&mut self, offset: (f64, f64)) {
+        self.center.0 += offset.0;
+        self.center.1 += offset.1;
+    }
+
+    /// Returns the shift speed, as a fraction of the size of the viewport.
+    ///
+    /// # Arguments
+    ///
+    /// - `factor` - How big the shift should be; the viewport is crossed in `factor` shifts.
+    pub fn shift_speed(&self, factor: u32) -> (f64, f64) {
+        let (width, height) = self.size();
+        (width / factor as f64, height / factor as f64)
+    }
+
+    /// Returns how much the viewport is magnified compared to another one.
+    ///
+    /// # Arguments
+    ///
+    /// - `reference` - The viewport of magnification 1, usually the initial view of the set.
+    pub fn magnification(&self, reference: &Viewport) -> f64 {
+        reference.half_size.1 / self.half_size.1
+    }
+
+    /// Returns the number of decimals needed to tell the points of two adjacent pixels apart.
+    ///
+    /// This is how many digits the coordinates must be shared with so another renderer shows the
+    /// same location. The number is capped at the decimals an `f64` can hold around the center.
+    ///
+    /// # Arguments
+    ///
+    /// - `width` - The width of the image.
+    /// - `height` - The height of the image.
+    pub fn coordinate_digits(&self, width: usize, height: usize) -> usize {
+        self.pixel_digits(width, height).min(self.max_digits())
+    }
+
+    /// Returns the number of decimals needed to tell the points of two adjacent pixels apart,
+    /// whether an `f64` can hold them or not.
+    ///
+    /// # Arguments
+    ///
+    /// - `width` - The width of the image.
+    /// - `height` - The height of the image.
+    pub fn pixel_digits(&self, width: usize, height: usize) -> usize {
+        let pixel_size = (self.size().0 / width as f64).min(self.size().1 / height as f64);
+        (-pixel_size.log10()).ceil().max(0.0) as usize + 1
+    }
+
+    /// Whether adjacent pixels are too close for the precision of an `f64` to tell them apart.
+    ///
+    /// Past this depth, the image turns blocky and zooming further requires arbitrary precision.
+    ///
+    /// # Arguments
+    ///
+    /// - `width` - The width of the image.
+    /// - `height` - The height of the image.
+    pub fn is_precision_exhausted(&self, width: usize, height: usize) -> bool {
+        let pixel_size = (self.size().0 / width as f64).min(self.size().1 / height as f64);
+        let magnitude = self.center.0.abs().max(self.center.1.abs()).max(1.0);
+        pixel_size < magnitude * f64::EPSILON * 64.0
+    }
+
+    /// The largest number of meaningful decimals of the coordinates around the center.
+    fn max_digits(&self) -> usize {
+        let magnitude = self.center.0.abs().max(self.center.1.abs()).max(1.0);
+        (-(magnitude * f64::EPSILON).log10()).floor() as usize
+    }
+
+    /// Maps a pixel of the image to a point of the complex plane.
+    ///
+    /// # Arguments
+    ///
+    /// - `pixel` - The x and y coordinates of the pixel.
+    /// - `width` - The width of the image.
+    /// - `height` - The height of the image.
+    ///
+    /// # Returns
+    ///
+    /// - The real and imaginary parts of the point.
+    pub fn complex_at(&self, pixel: (f64, f64), width: usize, height: usize) -> (f64, f64) {
+        let (x_range, y_range) = (self.x_range(), self.y_range());
+        (
+            pixel.0 / width as f64 * (x_range.1 - x_range.0) + x_range.0,
+            pixel.1 / height as f64 * (y_range.1 - y_range.0) + y_range.0,
+        )
+    }
+
+    /// Maps a pixel of the image to its offset from the center of the viewport.
+    ///
+    /// Unlike [`Viewport::complex_at`], the offset keeps its precision however deep the zoom is.
+    ///
+    /// # Arguments
+    ///
+    /// - `pixel` - The x and y coordinates of the pixel.
+    /// - `width` - The width of the image.
+    /// - `height` - The height of the image.
+    ///
+    /// # Returns
+    ///
+    /// - The offsets along the real and imaginary axes.
+    pub fn offset_at(&self, pixel: (f64, f64), width: usize, height: usize) -> (f64, f64) {
+        (
+            (pixel.0 / width as f64 * 2.0 - 1.0) * self.half_size.0,
+            (pixel.1 / height as f64 * 2.0 - 1.0) * self.half_size.1,
+        )
+    }
+
+    /// Maps a point of the complex plane to a pixel of the image.
+    ///
+    /// This is the inverse of [`Viewport::complex_at`].
+    ///
+    /// # Arguments
+    ///
+    /// - `c` - The real and imaginary parts of the point.
+    /// - `width` - The width of the image.
+    /// - `height` - The height of the image.
+    ///
+    /// # Returns
+    ///
+    /// - The x and y coordinates of the pixel, which may lie outside of the image.
+    pub fn pixel_at(&self, c: (f64, f64), width: usize, height: usize) -> (f64, f64) {
+        let (x_range, y_range) = (self.x_range(), self.y_range());
+        (
+            (c.0 - x_range.0) / (x_range.1 - x_range.0) * width as f64,
+            (c.1 - y_range.0) / (y_range.1 - y_range.0) * height as f64,
+        )
+    }
+}
+
+/// The distribution of the escape iterations of the samples of a view.
+///
+/// This helps choose the maximum number of iterations: if many samples are still escaping close
+/// to the maximum, the boundary of the set is not resolved and more iterations are needed.
+#[derive(Clone, Debug, Default)]
+pub struct IterationStats {
+    /// The number of samples escaping after each number of iterations.
+    counts: Vec<u64>,
+    /// The number of samples that never escaped.
+    interior: u64,
+}
+
+impl IterationStats {
+    /// Creates empty statistics for samples iterated at most `max_iterations` times.
+    pub fn new(max_iterations: usize) -> Self {
+        Self {
+            counts: vec![0; max_iterations],
+            interior: 0,
+        }
+    }
+
+    /// Creates statistics from the number of samples escaping after each number of iterations, up
+    /// to the maximum, and the number of samples that never escaped.
+    pub fn from_counts(counts: Vec<u64>, interior: u64) -> Self {
+        Self { counts, interior }
+    }
+
+    /// Records the result of a sample, as returned by [`is_in_mandelbrot`].
+    pub fn record(&mut self, escape: Option<usize>) {
+        match escape {
+            Some(i) => self.counts[i] += 1,
+            None => self.interior += 1,
+        }
+    }
+
+    /// Adds the samples of other statistics with the same maximum number of iterations.
+    pub fn merge(&mut self, other: &Self) {
+        self.counts
+            .iter_mut()
+            .zip(&other.counts)
+            .for_each(|(count, other)| *count += other);
+        self.interior += other.interior;
+    }
+
+    /// The total number of samples.
+    pub fn samples(&self) -> u64 {
+        self.counts.iter().sum::<u64>() + self.interior
+    }
+
+    /// The fraction of the samples that never escaped, in the range [0, 1].
+    pub fn interior_fraction(&self) -> f64 {
+        match self.samples() {
+            0 => 0.0,
+            samples => self.interior as f64 / samples as f64,
+        }
+    }
+
+    /// The number of iterations within which the fraction `q` of the escaping samples escaped.
+    ///
+    /// Returns `None` if no sample escaped.
+    pub fn escape_percentile(&self, q: f64) -> Option<usize> {
+        let escaped: u64 = self.counts.iter().sum();
+        if escaped == 0 {
+            return None;
+        }
+
+        let target = (q.clamp(0.0, 1.0) * escaped as f64).ceil().max(1.0) as u64;
+        let mut cumulative = 0;
+        self.counts.iter().position(|count| {
+            cumulative += count;
+            cumulative >= target
+        })
+    }
+
+    /// Groups the escaping samples into `bins` bins of iterations of equal width.
+    pub fn histogram(&self, bins: usize) -> Vec<u64> {
+        let bins = bins.max(1);
+        let mut histogram = vec![0; bins];
+        let max_iterations = self.counts.len().max(1);
+        self.counts.iter().enumerate().for_each(|(i, count)| {
+            histogram[i * bins / max_iterations] += count;
+        });
+        histogram
+    }
+}
+
+/// The parameters of the sequence z -> z^d + c iterated for each pixel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IterationSettings {
+    /// The maximum number of iterations to check for divergence.
+    pub max_iterations: usize,
+    /// The modulus past which the sequence is considered to diverge.
+    pub escape_radius: f64,
+    /// The exponent d of the sequence, 2 for the classic sets.
+    pub power: f64,
+}
+
+impl IterationSettings {
+    /// The classic sequence z -> z^2 + c, diverging past a modulus of 2.
+    pub fn new(max_iterations: usize) -> Self {
+        Self {
+            max_iterations,
+            escape_radius: 2.0,
+            power: 2.0,
+        }
+    }
+
+    /// Sets the escape radius.
+    pub fn with_escape_radius(mut self, escape_radius: f64) -> Self {
+        self.escape_radius = escape_radius;
+        self
+    }
+
+    /// Sets the exponent of the sequence.
+    pub fn with_power(mut self, power: f64) -> Self {
+        self.power = power;
+        self
+    }
+}
+
+/// The fractals that can be rendered.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fractal {
+    Mandelbrot,
+    /// The Julia set of a point of the complex plane, given by its real and imaginary parts.
+    Julia((f64, f64)),
+}
+
+impl Fractal {
+    /// Determines if a pixel belongs to the fractal and returns the path of the sequence.
+    ///
+    /// See [`is_in_mandelbrot`] and [`is_in_julia`].
+    pub fn orbit(
+        &self,
+        pixel: (f64, f64),
+        width: usize,
+        height: usize,
+        viewport: &Viewport,
+        settings: &IterationSettings,
+    ) -> (Option<usize>, f64, Vec<(usize, usize)>) {
+        let (x, y) = pixel;
+        match *self {
+            Fractal::Mandelbrot => is_in_mandelbrot(x, y, width, height, viewport, settings),
+            Fractal::Julia(c) => is_in_julia(x, y, width, height, viewport, c, settings),
+        }
+    }
+}
+
 /// Determines if a pixel belongs to Mandlebrot's set and returns the path of the sequence.
 ///
 /// # Arguments
@@ -6,162 +408,243 @@
 /// - `y` - The y-coordinate of the pixel.
 /// - `width` - The width of the image.
 /// - `height` - The height of the image.
-/// - `max_iterations` - The maximum number of iterations to check for divergence.
+/// - `viewport` - The region of the complex plane shown by the image.
+/// - `settings` - The parameters of the sequence.
 ///
 /// # Returns
 ///
 /// A tuple containing:
-/// - A boolean indicating whether the pixel belongs to Mandlebrot's set.
+/// - The iteration at which the sequence escapes, or `None` if the pixel belongs to Mandlebrot's
+///   set.
+/// - The continuous escape value of the sequence, see [`smooth_escape`], or the maximum number of
+///   iterations if it never escapes.
 /// - A vector of `(usize, usize)` tuples representing the x, y coordinates of the pixel at each
-///     and every iteration. This is useful for visualizing the path of the sequence.
+///   and every iteration. This is useful for visualizing the path of the sequence.
 pub fn is_in_mandelbrot(
     x: f64,
     y: f64,
     width: usize,
     height: usize,
-    x_range: (f64, f64),
-    y_range: (f64, f64),
-
-    max_iterations: usize,
-) -> (Option<usize>, Vec<(usize, usize)>) {
+    viewport: &Viewport,
+    settings: &IterationSettings,
+) -> (Option<usize>, f64, Vec<(usize, usize)>) {
     // Compute the real and imaginary parts of the number c associated with the pixel
-    let c_real = map(x, (0.0, width as f64), x_range);
-    let c_imaginary = map(y, (0.0, height as f64), y_range);
+    let c = viewport.complex_at((x, y), width, height);
+    orbit((0.0, 0.0), c, width, height, viewport, settings)
+}
+
+/// Determines if a pixel belongs to the Julia set of `c` and returns the path of the sequence.
+///
+/// The sequence is the same as for Mandlebrot's set, but it starts at the number associated with
+/// the pixel and adds the fixed number `c` at each iteration.
+///
+/// # Arguments
+///
+/// - `x` - The x-coordinate of the pixel.
+/// - `y` - The y-coordinate of the pixel.
+/// - `width` - The width of the image.
+/// - `height` - The height of the image.
+/// - `viewport` - The region of the complex plane shown by the image.
+/// - `c` - The real and imaginary parts of the number defining the Julia set.
+/// - `settings` - The parameters of the sequence.
+///
+/// # Returns
+///
+/// The same tuple as [`is_in_mandelbrot`].
+pub fn is_in_julia(
+    x: f64,
+    y: f64,
+    width: usize,
+    height: usize,
+    viewport: &Viewport,
+    c: (f64, f64),
+    settings: &IterationSettings,
+) -> (Option<usize>, f64, Vec<(usize, usize)>) {
+    let z = viewport.complex_at((x, y), width, height);
+    orbit(z, c, width, height, viewport, settings)
+}
+
+/// Iterates the sequence z -> z^d + c from `z`, recording the pixels it visits until it diverges.
+fn orbit(
+    z: (f64, f64),
+    c: (f64, f64),
+    width: usize,
+    height: usize,
+    viewport: &Viewport,
+    settings: &IterationSettings,
+) -> (Option<usize>, f64, Vec<(usize, usize)>) {
+    let (c_real, c_imaginary) = c;
+    let max_iterations = settings.max_iterations;
     let mut pixels = Vec::with_capacity(max_iterations);
 
     // Initialize the first number in the sequence
-    let mut real = 0.0;
-    let mut imaginary = 0.0;
+    let (mut real, mut imaginary) = z;
     for i in 0..max_iterations {
         // Compute next number in the sequence
-        let (new_real, new_imaginary) = calculate_next(c_real, c_imaginary, real, imaginary);
+        let (new_real, new_imaginary) = if settings.power == 2.0 {
+            calculate_next(c_real, c_imaginary, real, imaginary)
+        } else {
+            calculate_next_power(c_real, c_imaginary, real, imaginary, settings.power)
+        };
 
         // Update the current number in the sequence
         real = new_real;
         imaginary = new_imaginary;
 
-        // The sequence diverges to infinity if the modulus of the number is greater than 2
-        // Else, we cannot conclude that the sequence diverges
-        let diverges = calculate_modulus(real, imaginary) > 2.0;
-        if diverges {
-            return (Some(i), pixels);
+        // The sequence diverges to infinity if the modulus of the number is greater than the
+        // escape radius. Else, we cannot conclude that the sequence diverges
+        let modulus = calculate_modulus(real, imaginary);
+        if modulus > settings.escape_radius {
+            return (Some(i), smooth_escape(i, modulus, settings), pixels);
         }
         // Store the x,y coordinates at each iteration
-        let i = map_inverse(real, (0.0, width as f64), x_range);
-        let j = map_inverse(imaginary, (0.0, height as f64), y_range);
+        let (i, j) = viewport.pixel_at((real, imaginary), width, height);
+        let (i, j) = (i as i32, j as i32);
         if i < width as i32 && i >= 0 && j < height as i32 && j >= 0 {
             pixels.push((i as usize, j as usize));
         }
     }
-    // We cannot conclude that the sequence diverges so the pixel belongs to Mandlebrot's set
-    (None, pixels)
+    // We cannot conclude that the sequence diverges so the pixel belongs to the set
+    (None, max_iterations as f64, pixels)
 }
 
-/// Takes a number and maps it from one range to another.
-///
-/// # Arguments
+/// The continuous escape value of a sequence, which grows smoothly across the bands of the
+/// escape iterations.
 ///
-/// - `x` - The number to map.
-/// - `x_range` - The range of x.
-/// - `c_range` - The range to map x to.
+/// The modulus of the sequence is at most about the escape radius raised to the power when it
+/// escapes, so its log-log relative to the escape radius gives the fraction of the iteration left
+/// before it would have escaped. The value is within `iteration` and `iteration + 1`; larger escape
+/// radii make its gradient smoother.
 ///
-/// # Returns
+/// # Arguments
 ///
-/// - The mapped number.
-fn map(x: f64, x_range: (f64, f64), c_range: (f64, f64)) -> f64 {
-    (x - x_range.0) / (x_range.1 - x_range.0) * (c_range.1 - c_range.0) + c_range.0
+/// - `iteration` - The iteration at which the sequence escapes.
+/// - `modulus` - The modulus of the number of the sequence at that iteration.
+/// - `settings` - The parameters of the sequence.
+pub fn smooth_escape(iteration: usize, modulus: f64, settings: &IterationSettings) -> f64 {
+    // A power of 1 grows linearly, without a fraction of iteration to recover
+    if settings.power <= 1.0 || settings.escape_radius <= 1.0 {
+        return iteration as f64;
+    }
+    let fraction = (modulus.ln() / settings.escape_radius.ln()).ln() / settings.power.ln();
+    iteration as f64 + 1.0 - fraction.clamp(0.0, 1.0)
 }
 
-/// Performs an inverse map
-///
-/// # Arguments
-///
-/// - `c` - The number to map.
-/// - `x_range` - The range of x.
-/// - `c_range` - The range to map x to.
-///
-/// # Returns
-///
-/// - The mapped number.
-fn map_inverse(c: f64, x_range: (f64, f64), c_range: (f64, f64)) -> i32 {
-    ((c - c_range.0) / (c_range.1 - c_range.0) * (x_range.1 - x_range.0) + x_range.0) as i32
+/// The settings that color a rendered fractal.
+///
+/// The hue of each pixel follows its lightness, offset by Perlin noise over the image so the
+/// colors vary across the fractal. The noise is mirrored around the horizontal center of the image,
+/// like the set itself.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Style {
+    /// The seed of the Perlin noise.
+    pub seed: u32,
+    /// How much the hue changes with the lightness.
+    pub hue_scale: f64,
+    pub saturation: f32,
+    /// The scales of the noise along the x axis, the y axis and the lightness.
+    pub noise_scale: [f64; 3],
 }
 
-/// Takes the x and y ranges and zooms in by a factor of `zoom_factor`
-///
-/// # Arguments
-///
-/// - `x_range` - The range of x.
-/// - `y_range` - The range of y.
-/// - `zoom_factor` - The factor to zoom in by.
-///
-/// # Returns
-///
-/// - The new x and y ranges after zooming in.
-pub fn zoom(
-    x_range: (f64, f64),
-    y_range: (f64, f64),
-    zoom_factor: f64,
-) -> ((f64, f64), (f64, f64)) {
-    // Calculate the center of the current image range
-    let x_center = (x_range.0 + x_range.1) / 2.0;
-    let y_center = (y_range.0 + y_range.1) / 2.0;
+impl Style {
+    /// Draws a style within the ranges of the settings of the sketch.
+    pub fn random() -> Self {
+        Self {
+            seed: random(),
+            hue_scale: random_range(0.0, 1.0),
+            saturation: random_range(0.0, 1.0),
+            noise_scale: [
+                random_range(0.5, 1.5),
+                random_range(0.0, 0.75),
+                random_range(0.0, 1.0),
+            ],
+        }
+    }
 
-    // Move the range so that the center aligns with the origin
-    let x_range_translated = shift(x_range, -x_center);
-    let y_range_translated = shift(y_range, -y_center);
+    /// Colors an array of lightnesses in the range [0, 255] into an image.
+    ///
+    /// # Arguments
+    ///
+    /// - `array` - The lightness of each pixel, row by row.
+    /// - `hue_phase` - An offset added to every hue, in turns, to cycle the palette.
+    pub fn colorize(&self, array: &[Vec<f64>], hue_phase: f64) -> RgbaImage {
+        self.colorize_with(array, hue_phase, |color| {
+            let (r, g, b) = color.into_format::<u8>().into_components();
+            image::Rgba([r, g, b, 255])
+        })
+    }
 
-    // Scale the range
-    let x_range_scaled = scale(x_range_translated, zoom_factor);
-    let y_range_scaled = scale(y_range_translated, zoom_factor);
+    /// Colors an array of lightnesses like [`Style::colorize`], keeping the linear channels in
+    /// floating point so the smooth ramps of the lightness are not quantized to 8 bits.
+    pub fn colorize_linear(&self, array: &[Vec<f64>], hue_phase: f64) -> LinearImage {
+        self.colorize_with(array, hue_phase, |color| {
+            let (r, g, b) = color.into_components();
+            image::Rgba(ColorSpace::Srgb.to_linear([r, g, b, 1.0]))
+        })
+    }
 
-    // Move the range back so that the center returns to its original position
-    let x_range_final = shift(x_range_scaled, x_center);
-    let y_range_final = shift(y_range_scaled, y_center);
+    /// Colors an array of lightnesses, converting the color of each pixel with `to_pixel`.
+    ///
+    /// The channels of the colors are the ones shown by the 8-bit images, so they are sRGB despite
+    /// their type.
+    fn colorize_with<P>(
+        &self,
+        array: &[Vec<f64>],
+        hue_phase: f64,
+        to_pixel: impl Fn(LinSrgb) -> P + Sync,
+    ) -> ImageBuffer<P, Vec<P::Subpixel>>
+    where
+        P: Pixel + Send + Sync + 'static,
+        P::Subpixel: Send + Sync,
+    {
+        let width = array[0].len() as u32;
+        let height = array.len() as u32;
+        let height_half = height as f64 / 2.0;
+        let noise = Perlin::new().set_seed(self.seed);
+        let [scale_x, scale_y, scale_z] = self.noise_scale;
 
-    (x_range_final, y_range_final)
-}
+        let mut image = ImageBuffer::new(width, height);
+        image
+            .enumerate_pixels_mut()
+            .par_bridge()
+            .for_each(|(x, y, pixel)| {
+                let symmetry_y = (y as f64 / height_half - 1.0).abs();
 
-/// Takes a range and scales it by a factor
-///
-/// # Arguments
-///
-/// - `range` - The range to scale.
-/// - `factor` - The factor to scale by.
-///
-/// # Returns
-///
-/// - The new range after scaling.
-pub fn scale(range: (f64, f64), factor: f64) -> (f64, f64) {
-    (range.0 * factor, range.1 * factor)
+                let lightness = array[y as usize][x as usize] / 255.0;
+                let hue = (lightness * self.hue_scale
+                    + hue_phase
+                    + noise.get([
+                        lightness * scale_z,
+                        x as f64 / width as f64 * scale_x,
+                        symmetry_y * scale_y,
+                    ])) as f32;
+
+                *pixel = to_pixel(hsl(hue, self.saturation, lightness as f32).into_rgb::<Srgb>());
+            });
+        image
+    }
 }
 
-/// Takes a range and shifts it by an offset
-///
-/// # Arguments
-///
-/// - `range` - The range to shift.
-/// - `offset` - The offset to shift by.
-///
-/// # Returns
-///
-/// - The new range after shifting.
-pub fn shift(range: (f64, f64), offset: f64) -> (f64, f64) {
-    (range.0 + offset, range.1 + offset)
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            seed: Perlin::DEFAULT_SEED,
+            hue_scale: 0.0,
+            saturation: 0.5,
+            noise_scale: [1.35, 0.75, 1.0],
+        }
+    }
 }
 
-/// Takes a range and returns the shift speed
-///
-/// # Arguments
-///
-/// - `range` - The range to calculate the shift speed for.
-/// - `factor` - How big the shift should be.
-///
-/// # Returns
-///
-/// - The shift speed.
-pub fn get_shift_speed(range: (f64, f64), factor: u32) -> f64 {
-    (range.1 - range.0) / factor as f64
+/// How the escape iterations of the samples are turned into the lightness of the pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Coloring {
+    /// The whole escape iterations are spread evenly over the lightnesses by histogram
+    /// equalization.
+    #[default]
+    Equalized,
+    /// The continuous escape values are scaled linearly to the lightnesses, without bands.
+    Smooth,
 }
 
 /// Takes the real and imaginary parts of a number as arguments. Returns the modulus of the number
@@ -195,3 +678,299 @@ fn calculate_next(c_real: f64, c_imaginary: f64, real: f64, imaginary: f64) -> (
     let res_imaginary = 2.0 * real * imaginary + c_imaginary;
     (res_real, res_imaginary)
 }
+
+/// Calculates the next number in the sequence for any exponent, in polar form
+///
+/// # Arguments
+///
+/// - `c_real`: The real part of the number c.
+/// - `c_imaginary`: The imaginary part of the number c.
+/// - `real`: The real part of the number z[n].
+/// - `imaginary`: The imaginary part of the number z[n].
+/// - `power`: The exponent d the number z[n] is raised to.
+///
+/// # Returns
+///
+/// - A tuple containing the real and imaginary parts of the next number in the sequence.
+fn calculate_next_power(
+    c_real: f64,
+    c_imaginary: f64,
+    real: f64,
+    imaginary: f64,
+    power: f64,
+) -> (f64, f64) {
+    let modulus = calculate_modulus(real, imaginary).powf(power);
+    let argument = imaginary.atan2(real) * power;
+    (
+        modulus * argument.cos() + c_real,
+        modulus * argument.sin() + c_imaginary,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    const WIDTH: usize = 512;
+    const HEIGHT: usize = 384;
+
+    /// Viewports from the whole set down to deep zooms, with various aspect ratios.
+    fn viewport() -> impl Strategy<Value = Viewport> {
+        (-2.0..2.0, -2.0..2.0, -10.0..1.0, 0.5..2.0).prop_map(
+            |(x, y, scale, aspect): (f64, f64, f64, f64)| {
+                let half_width = 10.0f64.powf(scale);
+                Viewport {
+                    center: (x, y),
+                    half_size: (half_width, half_width * aspect),
+                }
+            },
+        )
+    }
+
+    /// Whether two values are equal up to a relative tolerance.
+    fn close(a: f64, b: f64, tolerance: f64) -> bool {
+        (a - b).abs() <= tolerance * a.abs().max(b.abs())
+    }
+
+    proptest! {
+        #[test]
+        fn pixels_round_trip(
+            viewport in viewport(),
+            x in 0.0..WIDTH as f64,
+            y in 0.0..HEIGHT as f64,
+        ) {
+            let c = viewport.complex_at((x, y), WIDTH, HEIGHT);
+            let (i, j) = viewport.pixel_at(c, WIDTH, HEIGHT);
+            prop_assert!((i - x).abs() < 1e-2, "{i} != {x}");
+            prop_assert!((j - y).abs() < 1e-2, "{j} != {y}");
+        }
+
+        #[test]
+        fn offsets_follow_the_points(
+            viewport in viewport(),
+            x in 0.0..WIDTH as f64,
+            y in 0.0..HEIGHT as f64,
+        ) {
+            let c = viewport.complex_at((x, y), WIDTH, HEIGHT);
+            let offset = viewport.offset_at((x, y), WIDTH, HEIGHT);
+            // The points are rounded around their magnitude, the offsets are not
+            let (width, height) = viewport.size();
+            let error = (viewport.center.0 + offset.0 - c.0).abs();
+            prop_assert!(error <= c.0.abs().max(width) * 1e-12);
+            let error = (viewport.center.1 + offset.1 - c.1).abs();
+            prop_assert!(error <= c.1.abs().max(height) * 1e-12);
+        }
+
+        #[test]
+        fn corners_map_to_ranges(viewport in viewport()) {
+            let (x_range, y_range) = (viewport.x_range(), viewport.y_range());
+            prop_assert_eq!(viewport.complex_at((0.0, 0.0), WIDTH, HEIGHT), (x_range.0, y_range.0));
+            let (x, y) = viewport.complex_at((WIDTH as f64, HEIGHT as f64), WIDTH, HEIGHT);
+            prop_assert!(close(x, x_range.1, 1e-12) && close(y, y_range.1, 1e-12));
+        }
+
+        #[test]
+        fn new_round_trips_ranges(viewport in viewport()) {
+            let rebuilt = Viewport::new(viewport.x_range(), viewport.y_range());
+            prop_assert!(close(rebuilt.center.0, viewport.center.0, 1e-12));
+            prop_assert!(close(rebuilt.center.1, viewport.center.1, 1e-12));
+            prop_assert!(close(rebuilt.half_size.0, viewport.half_size.0, 1e-5));
+            prop_assert!(close(rebuilt.half_size.1, viewport.half_size.1, 1e-5));
+        }
+
+        #[test]
+        fn zoom_keeps_center(viewport in viewport(), factor in 0.5..2.0) {
+            let mut zoomed = viewport;
+            zoomed.zoom(factor);
+            prop_assert_eq!(zoomed.center(), viewport.center());
+            let (width, height) = viewport.size();
+            let (zoomed_width, zoomed_height) = zoomed.size();
+            prop_assert!(close(zoomed_width, width * factor, 1e-15));
+            prop_assert!(close(zoomed_height, height * factor, 1e-15));
+        }
+
+        #[test]
+        fn zoom_at_keeps_point_in_place(
+            viewport in viewport(),
+            x in 0.0..WIDTH as f64,
+            y in 0.0..HEIGHT as f64,
+            factor in 0.5..2.0,
+        ) {
+            let c = viewport.complex_at((x, y), WIDTH, HEIGHT);
+            let mut zoomed = viewport;
+            zoomed.zoom_at(c, factor);
+            let (i, j) = zoomed.pixel_at(c, WIDTH, HEIGHT);
+            prop_assert!((i - x).abs() < 1e-2, "{i} != {x}");
+            prop_assert!((j - y).abs() < 1e-2, "{j} != {y}");
+        }
+
+        #[test]
+        fn zoom_does_not_drift(viewport in viewport(), factor in 0.9..1.1, steps in 1..500usize) {
+            let mut zoomed = viewport;
+            (0..steps).for_each(|_| zoomed.zoom(factor));
+            (0..steps).for_each(|_| zoomed.zoom(1.0 / factor));
+            prop_assert_eq!(zoomed.center(), viewport.center());
+            prop_assert!(close(zoomed.half_size.0, viewport.half_size.0, 1e-12));
+            prop_assert!(close(zoomed.half_size.1, viewport.half_size.1, 1e-12));
+
+            // The aspect ratio is kept along the way
+            let aspect = |viewport: &Viewport| viewport.half_size.1 / viewport.half_size.0;
+            prop_assert!(close(aspect(&zoomed), aspect(&viewport), 1e-12));
+        }
+
+        #[test]
+        fn shift_does_not_drift(
+            viewport in viewport(),
+            steps in 1..500usize,
+            factor in 10..100u32,
+        ) {
+            let mut shifted = viewport;
+            let (dx, dy) = viewport.shift_speed(factor);
+            (0..steps).for_each(|_| shifted.shift((dx, -dy)));
+            (0..steps).for_each(|_| shifted.shift((-dx, dy)));
+            prop_assert_eq!(shifted.size(), viewport.size());
+
+            // Each shift rounds the center to the precision of the plane, not of the viewport
+            let (width, height) = viewport.size();
+            prop_assert!((shifted.center.0 - viewport.center.0).abs() < width * 1e-3);
+            prop_assert!((shifted.center.1 - viewport.center.1).abs() < height * 1e-3);
+        }
+
+        #[test]
+        fn invalid_zoom_is_ignored(
+            viewport in viewport(),
+            factor in prop_oneof![Just(0.0), Just(f64::NAN), Just(f64::INFINITY), -10.0..0.0],
+        ) {
+            let mut zoomed = viewport;
+            zoomed.zoom(factor);
+            prop_assert_eq!(zoomed, viewport);
+        }
+    }
+
+    #[test]
+    fn stats_count_samples() {
+        let mut stats = IterationStats::new(10);
+        [Some(0), Some(2), Some(2), Some(9), None]
+            .into_iter()
+            .for_each(|escape| {
+                stats.record(escape);
+            });
+        let mut other = IterationStats::new(10);
+        other.record(None);
+        stats.merge(&other);
+
+        assert_eq!(stats.samples(), 6);
+        assert_eq!(stats.interior_fraction(), 2.0 / 6.0);
+        assert_eq!(stats.escape_percentile(0.5), Some(2));
+        assert_eq!(stats.escape_percentile(1.0), Some(9));
+        assert_eq!(stats.histogram(2), vec![3, 1]);
+        assert_eq!(IterationStats::new(10).escape_percentile(0.5), None);
+    }
+
+    #[test]
+    fn digits_follow_zoom() {
+        let mut viewport = Viewport::new((-2.0, 0.5), (-1.25, 1.25));
+        let reference = viewport;
+        assert_eq!(viewport.coordinate_digits(512, 512), 4);
+        assert_eq!(viewport.magnification(&reference), 1.0);
+
+        viewport.zoom(1e-6);
+        assert_eq!(viewport.coordinate_digits(512, 512), 10);
+        assert!((viewport.magnification(&reference) - 1e6).abs() < 1e-3);
+        assert!(!viewport.is_precision_exhausted(512, 512));
+
+        viewport.zoom(1e-12);
+        assert_eq!(viewport.coordinate_digits(512, 512), 15);
+        assert_eq!(viewport.pixel_digits(512, 512), 22);
+        assert!(viewport.is_precision_exhausted(512, 512));
+    }
+
+    #[test]
+    fn julia_of_zero_is_the_unit_disc() {
+        let viewport = Viewport::new((-2.0, 2.0), (-2.0, 2.0));
+        let julia = Fractal::Julia((0.0, 0.0));
+        // The pixels map to 0.5 and 1.5 on the real axis
+        let settings = IterationSettings::new(100);
+        let (inside, _, _) = julia.orbit((125.0, 100.0), 200, 200, &viewport, &settings);
+        let (outside, _, _) = julia.orbit((175.0, 100.0), 200, 200, &viewport, &settings);
+        assert_eq!(inside, None);
+        assert!(outside.is_some());
+
+        // Starting a Julia set at 0 follows the sequence of Mandlebrot's set for its point
+        let pixel = (100.0, 20.0);
+        let c = viewport.complex_at(pixel, 200, 200);
+        let (mandelbrot, _, _) = Fractal::Mandelbrot.orbit(pixel, 200, 200, &viewport, &settings);
+        let (julia, _, _) = Fractal::Julia(c).orbit((100.0, 100.0), 200, 200, &viewport, &settings);
+        assert!(mandelbrot.is_some());
+        assert_eq!(mandelbrot, julia);
+    }
+
+    #[test]
+    fn power_two_matches_the_classic_sequence() {
+        let z = (0.3, -0.7);
+        let c = (-0.4, 0.2);
+        let (real, imaginary) = calculate_next(c.0, c.1, z.0, z.1);
+        let (power_real, power_imaginary) = calculate_next_power(c.0, c.1, z.0, z.1, 2.0);
+        assert!(close(real, power_real, 1e-12));
+        assert!(close(imaginary, power_imaginary, 1e-12));
+    }
+
+    #[test]
+    fn escape_radius_delays_divergence() {
+        let viewport = Viewport::new((-2.0, 2.0), (-2.0, 2.0));
+        // The pixel maps to 1 on the real axis, which escapes
+        let pixel = (150.0, 100.0);
+        let escape = |settings: IterationSettings| {
+            let (escape, _, _) = Fractal::Mandelbrot.orbit(pixel, 200, 200, &viewport, &settings);
+            escape.unwrap()
+        };
+        let classic = escape(IterationSettings::new(100));
+        assert!(escape(IterationSettings::new(100).with_escape_radius(1000.0)) > classic);
+
+        // The cubic set does not reach as far along the negative real axis
+        let pixel = (30.0, 100.0);
+        let (square, _, _) =
+            Fractal::Mandelbrot.orbit(pixel, 200, 200, &viewport, &IterationSettings::new(100));
+        let cubic = IterationSettings::new(100).with_power(3.0);
+        let (cube, _, _) = Fractal::Mandelbrot.orbit(pixel, 200, 200, &viewport, &cubic);
+        assert_eq!(square, None);
+        assert!(cube.is_some());
+    }
+
+    #[test]
+    fn smooth_escape_is_continuous() {
+        let viewport = Viewport::new((-2.0, 2.0), (-2.0, 2.0));
+        let settings = IterationSettings::new(100).with_escape_radius(1000.0);
+        // The pixels map to the real axis past 0.25, where the escape iterations decrease in bands
+        let escapes: Vec<_> = (0..400)
+            .map(|x| {
+                let pixel = (262.5 + x as f64 * 0.1, 200.0);
+                let (escape, smooth, _) =
+                    Fractal::Mandelbrot.orbit(pixel, 400, 400, &viewport, &settings);
+                let escape = escape.unwrap() as f64;
+                assert!(
+                    (escape..=escape + 1.0).contains(&smooth),
+                    "{smooth} at {escape}"
+                );
+                (escape, smooth)
+            })
+            .collect();
+
+        // The smooth values change far less than the bands from one sample to the next
+        assert!(escapes.windows(2).any(|pair| pair[0].0 != pair[1].0));
+        assert!(
+            escapes
+                .windows(2)
+                .all(|pair| (pair[0].1 - pair[1].1).abs() < 0.5)
+        );
+    }
+
+    #[test]
+    fn new_accepts_reversed_ranges() {
+        let viewport = Viewport::new((0.5, -2.0), (1.25, -1.25));
+        assert_eq!(viewport.center(), (-0.75, 0.0));
+        assert_eq!(viewport.size(), (2.5, 2.5));
+    }
+}
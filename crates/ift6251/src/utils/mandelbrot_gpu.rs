@@ -0,0 +1,241 @@
+use std::sync::{Arc, mpsc};
+
+use nannou::wgpu::{self, DeviceQueuePair};
+
+use crate::utils::mandelbrot::{Coloring, Fractal, IterationSettings, IterationStats, Viewport};
+
+/// Renders the escape iterations of the fractals with a compute shader.
+///
+/// The shader sums the same samples as the CPU renderer into one value per pixel, so the arrays
+/// can be recalibrated, equalized and colored alike. It iterates in single precision, so deep
+/// zooms must be rendered on the CPU; see [`MandelbrotPipeline::is_precise_enough`].
+pub struct MandelbrotPipeline {
+    device_queue_pair: Arc<DeviceQueuePair>,
+    params_buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    compute_pipeline: wgpu::ComputePipeline,
+}
+
+/// The parameters of a render.
+///
+/// The struct is sent as-is to the compute shader, so its layout must match the `Params` struct
+/// declared in `mandelbrot.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Params {
+    center: [f32; 2],
+    half_size: [f32; 2],
+    julia_c: [f32; 2],
+    size: [u32; 2],
+    max_iterations: u32,
+    fractal: u32,
+    samples: u32,
+    select_interior: u32,
+    escape_radius: f32,
+    power: f32,
+    smooth_coloring: u32,
+    _padding: f32,
+}
+
+impl Params {
+    fn as_bytes(&self) -> &[u8] {
+        unsafe { wgpu::bytes::from(self) }
+    }
+}
+
+impl MandelbrotPipeline {
+    /// The number of pixels of each side of the workgroups of the shader.
+    const WORKGROUP_SIZE: u32 = 8; // Must match @workgroup_size(8, 8) in the shader
+    /// How many times coarser than the resolution of single precision the samples must be spaced.
+    const PRECISION_MARGIN: f64 = 16.0;
+
+    pub fn new(device_queue_pair: Arc<DeviceQueuePair>) -> Self {
+        let device = device_queue_pair.device();
+        let shader = device.create_shader_module(wgpu::include_wgsl!("../shaders/mandelbrot.wgsl"));
+
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Mandelbrot Params Buffer"),
+            size: std::mem::size_of::<Params>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout = wgpu::BindGroupLayoutBuilder::new()
+            .uniform_buffer(wgpu::ShaderStages::COMPUTE, false)
+            .storage_buffer(wgpu::ShaderStages::COMPUTE, false, false)
+            .storage_buffer(wgpu::ShaderStages::COMPUTE, false, false)
+            .build(device);
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Mandelbrot Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Mandelbrot Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        Self {
+            device_queue_pair,
+            params_buffer,
+            bind_group_layout,
+            compute_pipeline,
+        }
+    }
+
+    /// Whether the samples of a render are far enough apart to be told apart in single precision.
+    pub fn is_precise_enough(viewport: &Viewport, width: usize, samples: usize) -> bool {
+        let (x_range, y_range) = (viewport.x_range(), viewport.y_range());
+        let magnitude = [x_range.0, x_range.1, y_range.0, y_range.1]
+            .into_iter()
+            .fold(1.0, |magnitude: f64, bound| magnitude.max(bound.abs()));
+        let spacing = viewport.size().0 / (width * samples.max(1)) as f64;
+        spacing > magnitude * f32::EPSILON as f64 * Self::PRECISION_MARGIN
+    }
+
+    /// Renders the array of a fractal, summing `samples` by `samples` samples per pixel, along with
+    /// the statistics of its samples.
+    ///
+    /// Like the CPU renderer, each sample adds its escape iteration to its pixel, or the maximum
+    /// number of iterations for the samples within the set when `select_interior` is set. With the
+    /// smooth coloring, the samples add their continuous escape value instead.
+    ///
+    /// Fails if the array cannot be read back from the GPU, such as after a device loss.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &self,
+        width: usize,
+        height: usize,
+        fractal: Fractal,
+        viewport: &Viewport,
+        settings: &IterationSettings,
+        samples: usize,
+        select_interior: bool,
+        coloring: Coloring,
+    ) -> Result<(Vec<Vec<f64>>, IterationStats), &'static str> {
+        let device = self.device_queue_pair.device();
+        let queue = self.device_queue_pair.queue();
+        let (width, height) = (width.max(1), height.max(1));
+
+        let (center, size) = (viewport.center(), viewport.size());
+        let julia_c = match fractal {
+            Fractal::Mandelbrot => (0.0, 0.0),
+            Fractal::Julia(c) => c,
+        };
+        let params = Params {
+            center: [center.0 as f32, center.1 as f32],
+            half_size: [(size.0 / 2.0) as f32, (size.1 / 2.0) as f32],
+            julia_c: [julia_c.0 as f32, julia_c.1 as f32],
+            size: [width as u32, height as u32],
+            max_iterations: settings.max_iterations as u32,
+            fractal: matches!(fractal, Fractal::Julia(_)) as u32,
+            samples: samples.max(1) as u32,
+            select_interior: select_interior as u32,
+            escape_radius: settings.escape_radius as f32,
+            power: settings.power as f32,
+            smooth_coloring: (coloring == Coloring::Smooth) as u32,
+            _padding: 0.0,
+        };
+        queue.write_buffer(&self.params_buffer, 0, params.as_bytes());
+
+        // The values of the pixels and the counts of the iterations, the last one for the interior
+        let values_size = (width * height * std::mem::size_of::<f32>()) as wgpu::BufferAddress;
+        let counts_size =
+            ((settings.max_iterations + 1) * std::mem::size_of::<u32>()) as wgpu::BufferAddress;
+        let storage_buffer = |label, size| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_SRC
+                    | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+        let values_buffer = storage_buffer("Mandelbrot Values Buffer", values_size);
+        let counts_buffer = storage_buffer("Mandelbrot Counts Buffer", counts_size);
+        let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Mandelbrot Staging Buffer"),
+            size: values_size + counts_size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = wgpu::BindGroupBuilder::new()
+            .binding(self.params_buffer.as_entire_binding())
+            .binding(values_buffer.as_entire_binding())
+            .binding(counts_buffer.as_entire_binding())
+            .build(device, &self.bind_group_layout);
+
+        // The counts are accumulated by the shader, so they must start at zero
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Mandelbrot Encoder"),
+        });
+        encoder.clear_buffer(&counts_buffer, 0, None);
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Mandelbrot Compute Pass"),
+            });
+            compute_pass.set_pipeline(&self.compute_pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            compute_pass.dispatch_workgroups(
+                (width as u32).div_ceil(Self::WORKGROUP_SIZE),
+                (height as u32).div_ceil(Self::WORKGROUP_SIZE),
+                1,
+            );
+        }
+        encoder.copy_buffer_to_buffer(&values_buffer, 0, &staging_buffer, 0, values_size);
+        encoder.copy_buffer_to_buffer(&counts_buffer, 0, &staging_buffer, values_size, counts_size);
+        queue.submit(Some(encoder.finish()));
+
+        // Map the staging buffer and wait for the render to complete
+        let slice = staging_buffer.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            sender.send(result).ok();
+        });
+        device.poll(wgpu::Maintain::Wait);
+        if !matches!(receiver.recv(), Ok(Ok(()))) {
+            return Err("Failed to map the fractal read back from the GPU");
+        }
+
+        let bytes = slice.get_mapped_range();
+        let (values, counts) = bytes.split_at(values_size as usize);
+        let array = values
+            .chunks_exact(width * std::mem::size_of::<f32>())
+            .map(|row| {
+                row.chunks_exact(std::mem::size_of::<f32>())
+                    .map(|value| f32::from_ne_bytes(value.try_into().unwrap()) as f64)
+                    .collect()
+            })
+            .collect();
+        let mut counts: Vec<u64> = counts
+            .chunks_exact(std::mem::size_of::<u32>())
+            .map(|count| u32::from_ne_bytes(count.try_into().unwrap()) as u64)
+            .collect();
+        let interior = counts.pop().unwrap_or(0);
+        drop(bytes);
+        staging_buffer.unmap();
+
+        Ok((array, IterationStats::from_counts(counts, interior)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn params_match_the_layout_of_the_shader() {
+        assert_eq!(std::mem::size_of::<Params>(), 64);
+    }
+
+    #[test]
+    fn deep_zooms_need_double_precision() {
+        let viewport = Viewport::new((-2.0, 0.5), (-1.25, 1.25));
+        assert!(MandelbrotPipeline::is_precise_enough(&viewport, 1024, 2));
+
+        let deep = Viewport::from_center((-0.75, 0.1), (1e-6, 1e-6));
+        assert!(!MandelbrotPipeline::is_precise_enough(&deep, 1024, 2));
+    }
+}
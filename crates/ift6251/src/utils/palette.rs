@@ -0,0 +1,210 @@
+use nannou::prelude::Key;
+use nannou_egui::egui;
+
+/// The key opening and closing the command palette in every sketch.
+pub const PALETTE_KEY: Key = Key::F1;
+
+/// An action that can be run from the command palette.
+pub struct Command<A> {
+    /// The name shown in the palette and matched against the query.
+    pub name: String,
+    /// The action run when the command is picked.
+    pub action: A,
+}
+
+impl<A> Command<A> {
+    /// Creates a new command.
+    pub fn new(name: impl Into<String>, action: A) -> Self {
+        Self {
+            name: name.into(),
+            action,
+        }
+    }
+}
+
+/// A keyboard-driven palette to fuzzy search and run the actions of a sketch.
+///
+/// The palette only holds the state of its window; the commands are given every frame so they can
+/// depend on the state of the sketch.
+#[derive(Default)]
+pub struct CommandPalette {
+    open: bool,
+    query: String,
+    selected: usize,
+}
+
+impl CommandPalette {
+    /// Creates a closed palette.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens the palette with an empty query, or closes it.
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    /// Whether the palette is open.
+    ///
+    /// While it is open, the sketches should ignore their hotkeys since they are used to type the
+    /// query.
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Shows the palette if it is open and returns the action picked this frame, if any.
+    ///
+    /// The arrow keys move the selection, `Enter` runs the selected command and `Esc` closes the
+    /// palette.
+    pub fn show<A: Clone>(&mut self, ctx: &egui::Context, commands: &[Command<A>]) -> Option<A> {
+        if !self.open {
+            return None;
+        }
+
+        let matches = matches(&self.query, commands);
+        let (up, down, enter, escape) = ctx.input(|input| {
+            (
+                input.key_pressed(egui::Key::ArrowUp),
+                input.key_pressed(egui::Key::ArrowDown),
+                input.key_pressed(egui::Key::Enter),
+                input.key_pressed(egui::Key::Escape),
+            )
+        });
+        if down {
+            self.selected += 1;
+        }
+        if up {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        self.selected = self.selected.min(matches.len().saturating_sub(1));
+
+        let mut picked = enter.then(|| matches.get(self.selected).copied()).flatten();
+        egui::Window::new("Command palette")
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 40.0])
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let query = egui::TextEdit::singleline(&mut self.query).hint_text("Search actions");
+                ui.add(query).request_focus();
+
+                matches.iter().enumerate().for_each(|(i, command)| {
+                    if ui
+                        .selectable_label(i == self.selected, &command.name)
+                        .clicked()
+                    {
+                        picked = Some(command);
+                    }
+                });
+            });
+
+        if escape || picked.is_some() {
+            self.toggle();
+        }
+        picked.map(|command| command.action.clone())
+    }
+}
+
+/// Returns the commands matching the query, from the best to the worst match.
+pub fn matches<'a, A>(query: &str, commands: &'a [Command<A>]) -> Vec<&'a Command<A>> {
+    let mut matches: Vec<_> = commands
+        .iter()
+        .filter_map(|command| fuzzy_score(query, &command.name).map(|score| (score, command)))
+        .collect();
+    // The sort is stable, so equal matches keep the order of the commands
+    matches.sort_by(|(a, _), (b, _)| b.cmp(a));
+    matches.into_iter().map(|(_, command)| command).collect()
+}
+
+/// Scores how well a candidate matches a query, ignoring case.
+///
+/// Every character of the query must appear in the candidate, in order. Consecutive characters and
+/// characters at the start of a word score higher. Returns `None` if the candidate does not match.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<u32> {
+    let mut score = 0;
+    let mut previous_matched = false;
+    let mut previous = ' ';
+    let mut query = query.chars().flat_map(char::to_lowercase).peekable();
+
+    for c in candidate.chars().flat_map(char::to_lowercase) {
+        let Some(&wanted) = query.peek() else {
+            break;
+        };
+
+        if c == wanted {
+            score += 1;
+            if previous_matched {
+                score += 2;
+            }
+            if !previous.is_alphanumeric() {
+                score += 3;
+            }
+            query.next();
+            previous_matched = true;
+        } else {
+            previous_matched = false;
+        }
+        previous = c;
+    }
+
+    query.peek().is_none().then_some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_subsequences_ignoring_case() {
+        assert!(fuzzy_score("sv", "Save").is_some());
+        assert!(fuzzy_score("SAVE", "save settings").is_some());
+        assert!(fuzzy_score("tgl trl", "Toggle trails").is_some());
+        assert_eq!(fuzzy_score("", "Anything"), Some(0));
+    }
+
+    #[test]
+    fn rejects_non_matches() {
+        assert_eq!(fuzzy_score("z", "Save settings"), None);
+        assert_eq!(fuzzy_score("evas", "Save"), None);
+        assert_eq!(fuzzy_score("saves", "Save"), None);
+        assert_eq!(fuzzy_score("a", ""), None);
+    }
+
+    #[test]
+    fn scores_consecutive_characters_higher() {
+        let consecutive = fuzzy_score("ab", "xabx").unwrap();
+        let scattered = fuzzy_score("ab", "xaxb").unwrap();
+        assert!(consecutive > scattered);
+    }
+
+    #[test]
+    fn scores_word_starts_higher() {
+        let word_starts = fuzzy_score("ss", "Save settings").unwrap();
+        let inside_word = fuzzy_score("ss", "Class").unwrap();
+        assert!(word_starts > inside_word);
+
+        let prefix = fuzzy_score("save", "Save").unwrap();
+        let suffix = fuzzy_score("save", "Autosave").unwrap();
+        assert!(prefix > suffix);
+    }
+
+    #[test]
+    fn sorts_matches_from_best_to_worst() {
+        let commands = [
+            Command::new("Reset camera", 0),
+            Command::new("Load cloud", 1),
+            Command::new("Toggle trails", 2),
+            Command::new("Recenter", 3),
+            Command::new("Clear trails", 4),
+        ];
+
+        let found: Vec<_> = matches("tr", &commands)
+            .into_iter()
+            .map(|command| command.action)
+            .collect();
+        // The ties keep the order of the commands and the non-matches are left out
+        assert_eq!(found, [4, 2, 0, 3]);
+    }
+}
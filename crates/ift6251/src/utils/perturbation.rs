@@ -0,0 +1,231 @@
+use crate::utils::{
+    fixed_point::BigFixed,
+    mandelbrot::{IterationSettings, smooth_escape},
+};
+
+/// The center of a view of Mandlebrot's set, kept in arbitrary precision as the view moves.
+///
+/// Past a zoom of about 1e-14, an `f64` cannot tell adjacent pixels apart, nor move the center by
+/// less than a pixel. The center is the reference of the perturbation of the samples of the view,
+/// see [`ReferenceOrbit`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreciseCenter {
+    pub real: BigFixed,
+    pub imaginary: BigFixed,
+}
+
+impl PreciseCenter {
+    pub fn new(center: (f64, f64)) -> Self {
+        Self {
+            real: BigFixed::from_f64(center.0),
+            imaginary: BigFixed::from_f64(center.1),
+        }
+    }
+
+    /// Moves the center, however small the offset is compared to it.
+    ///
+    /// # Arguments
+    ///
+    /// - `offset` - The offset along the real and imaginary axes.
+    pub fn shift(&mut self, offset: (f64, f64)) {
+        self.real = &self.real + &BigFixed::from_f64(offset.0);
+        self.imaginary = &self.imaginary + &BigFixed::from_f64(offset.1);
+    }
+
+    /// Rounds the center to the nearest `f64`s.
+    pub fn to_f64(&self) -> (f64, f64) {
+        (self.real.to_f64(), self.imaginary.to_f64())
+    }
+}
+
+/// The orbit of the center of a view of Mandlebrot's set, iterated in arbitrary precision.
+///
+/// The other samples of the view are iterated in double precision as perturbations of this orbit:
+/// writing a sample as `c = C + δc` and its sequence as `z = Z + δ`, the deltas follow
+/// `δ -> 2Zδ + δ² + δc`, which only involves the small offsets from the orbit of the center. Only
+/// the classic sequence z -> z^2 + c is perturbed, whatever the power of the settings.
+#[derive(Clone, Debug)]
+pub struct ReferenceOrbit {
+    /// The numbers of the sequence of the center rounded to `f64`, starting at 0.
+    orbit: Vec<(f64, f64)>,
+}
+
+impl ReferenceOrbit {
+    /// The fractional words added to the precision of the center, which absorb the rounding errors
+    /// amplified along the orbit.
+    const GUARD_WORDS: usize = 2;
+
+    /// Iterates the sequence of the center until it escapes or reaches the maximum number of
+    /// iterations.
+    ///
+    /// # Arguments
+    ///
+    /// - `center` - The reference point, usually the center of the view.
+    /// - `settings` - The parameters of the sequence.
+    pub fn mandelbrot(center: &PreciseCenter, settings: &IterationSettings) -> Self {
+        let fraction_words = center
+            .real
+            .fraction_words()
+            .max(center.imaginary.fraction_words())
+            + Self::GUARD_WORDS;
+        let c_real = center.real.clone().with_fraction_words(fraction_words);
+        let c_imaginary = center.imaginary.clone().with_fraction_words(fraction_words);
+
+        let mut orbit = Vec::with_capacity(settings.max_iterations + 1);
+        orbit.push((0.0, 0.0));
+        let (mut real, mut imaginary) = (BigFixed::zero(), BigFixed::zero());
+        for _ in 0..settings.max_iterations {
+            let cross = &real * &imaginary;
+            let new_real = &(&(&real * &real) - &(&imaginary * &imaginary)) + &c_real;
+            imaginary = &(&cross + &cross) + &c_imaginary;
+            real = new_real;
+
+            let z = (real.to_f64(), imaginary.to_f64());
+            orbit.push(z);
+            if (z.0.powi(2) + z.1.powi(2)).sqrt() > settings.escape_radius {
+                break;
+            }
+        }
+        Self { orbit }
+    }
+
+    /// The number of iterations of the orbit, fewer than the maximum if the center escapes.
+    pub fn len(&self) -> usize {
+        self.orbit.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the iteration at which the sample offset from the center escapes, like
+    /// [`is_in_mandelbrot`](crate::utils::mandelbrot::is_in_mandelbrot), or `None` if it never
+    /// does.
+    ///
+    /// When the sample comes closer to 0 than to the orbit, or reaches the end of the orbit, its
+    /// delta is rebased onto the start of the orbit. This avoids the glitches of the samples
+    /// straying away from the reference.
+    ///
+    /// # Arguments
+    ///
+    /// - `delta_c` - The offset of the sample from the center.
+    /// - `settings` - The parameters of the sequence, the same as for the orbit.
+    pub fn escape(&self, delta_c: (f64, f64), settings: &IterationSettings) -> Option<usize> {
+        self.smooth_escape(delta_c, settings).0
+    }
+
+    /// Returns the iteration at which the sample offset from the center escapes along with its
+    /// continuous escape value, like
+    /// [`is_in_mandelbrot`](crate::utils::mandelbrot::is_in_mandelbrot).
+    ///
+    /// See [`ReferenceOrbit::escape`].
+    pub fn smooth_escape(
+        &self,
+        delta_c: (f64, f64),
+        settings: &IterationSettings,
+    ) -> (Option<usize>, f64) {
+        let mut delta = (0.0, 0.0);
+        let mut n = 0;
+        for i in 0..settings.max_iterations {
+            let ((real, imaginary), (d_real, d_imaginary)) = (self.orbit[n], delta);
+            delta = (
+                2.0 * (real * d_real - imaginary * d_imaginary) + d_real.powi(2)
+                    - d_imaginary.powi(2)
+                    + delta_c.0,
+                2.0 * (real * d_imaginary + imaginary * d_real)
+                    + 2.0 * d_real * d_imaginary
+                    + delta_c.1,
+            );
+            n += 1;
+
+            let z = (self.orbit[n].0 + delta.0, self.orbit[n].1 + delta.1);
+            let modulus = (z.0.powi(2) + z.1.powi(2)).sqrt();
+            if modulus > settings.escape_radius {
+                return (Some(i), smooth_escape(i, modulus, settings));
+            }
+            if modulus < (delta.0.powi(2) + delta.1.powi(2)).sqrt() || n == self.orbit.len() - 1 {
+                delta = z;
+                n = 0;
+            }
+        }
+        (None, settings.max_iterations as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::mandelbrot::{Viewport, is_in_mandelbrot};
+
+    #[test]
+    fn perturbation_matches_direct_iteration() {
+        let settings = IterationSettings::new(200);
+        let viewport = Viewport::from_center((-0.75, 0.1), (0.5, 0.5));
+        let reference =
+            ReferenceOrbit::mandelbrot(&PreciseCenter::new(viewport.center()), &settings);
+
+        // Rounding differs between the methods, so the samples on the boundary may disagree
+        let (width, height) = (32, 32);
+        let matching = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x as f64, y as f64)))
+            .filter(|&pixel| {
+                let (direct, _, _) =
+                    is_in_mandelbrot(pixel.0, pixel.1, width, height, &viewport, &settings);
+                let c = viewport.complex_at(pixel, width, height);
+                let center = viewport.center();
+                let perturbed = reference.escape((c.0 - center.0, c.1 - center.1), &settings);
+                direct == perturbed
+            })
+            .count();
+        assert!(
+            matching >= width * height * 95 / 100,
+            "{matching} samples match"
+        );
+    }
+
+    #[test]
+    fn deep_samples_are_told_apart() {
+        let settings = IterationSettings::new(1000);
+        let is_inside = |c: &PreciseCenter| {
+            let orbit = ReferenceOrbit::mandelbrot(c, &settings);
+            orbit.escape((0.0, 0.0), &settings).is_none()
+        };
+
+        // Bisect the boundary of the set along a horizontal line, down to 1e-20
+        let mut inside = PreciseCenter::new((0.0, 0.1));
+        let mut step = 0.5;
+        while step > 1e-20 {
+            step /= 2.0;
+            let mut middle = inside.clone();
+            middle.shift((step, 0.0));
+            if is_inside(&middle) {
+                inside = middle;
+            }
+        }
+
+        // The samples 1e-20 apart round to the same f64 around the boundary
+        let offsets: Vec<(f64, f64)> = (0..8).map(|x| (x as f64 * 1e-20, 0.0)).collect();
+        let rounded = inside.to_f64();
+        assert!(
+            offsets
+                .iter()
+                .all(|offset| rounded.0 + offset.0 == rounded.0)
+        );
+
+        // Each sample iterated on its own in arbitrary precision escapes like its perturbation
+        let reference = ReferenceOrbit::mandelbrot(&inside, &settings);
+        let escapes: Vec<_> = offsets
+            .iter()
+            .map(|&offset| {
+                let mut sample = inside.clone();
+                sample.shift(offset);
+                let direct = ReferenceOrbit::mandelbrot(&sample, &settings);
+                let direct = direct.escape((0.0, 0.0), &settings);
+                assert_eq!(reference.escape(offset, &settings), direct);
+                direct
+            })
+            .collect();
+        assert_eq!(escapes[0], None);
+        assert!(escapes.iter().any(Option::is_some));
+    }
+}
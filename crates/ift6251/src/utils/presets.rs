@@ -0,0 +1,185 @@
+use std::path::{Path, PathBuf};
+
+use nannou_egui::egui;
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::utils::widgets::Dropdown;
+
+/// The directory the presets of every sketch are saved under.
+pub const PRESETS_DIRECTORY: &str = "./presets";
+
+/// Named presets of the settings of a sketch, saved as JSON files.
+///
+/// Each sketch has its own directory of presets, holding one `<name>.json` file per preset.
+pub struct PresetLibrary {
+    directory: PathBuf,
+    /// The names of the saved presets, sorted.
+    names: Vec<String>,
+    /// The name of the preset to save or load.
+    pub name: String,
+}
+
+/// What the preset widget asks the sketch to do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PresetRequest {
+    Save,
+    Load,
+}
+
+impl PresetLibrary {
+    /// Opens the presets of a sketch, saved under `./presets/<sketch>`.
+    pub fn new(sketch: &str) -> Self {
+        Self::in_directory(Path::new(PRESETS_DIRECTORY).join(sketch))
+    }
+
+    /// Opens the presets saved in a directory, which is only created once a preset is saved.
+    pub fn in_directory(directory: impl Into<PathBuf>) -> Self {
+        let mut library = Self {
+            directory: directory.into(),
+            names: Vec::new(),
+            name: "default".to_owned(),
+        };
+        library.refresh();
+        library
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Lists the presets saved in the directory again.
+    pub fn refresh(&mut self) {
+        self.names = std::fs::read_dir(&self.directory)
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .map(|entry| entry.path())
+                    .filter(|path| {
+                        path.extension()
+                            .is_some_and(|extension| extension == "json")
+                    })
+                    .filter_map(|path| Some(path.file_stem()?.to_str()?.to_owned()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        self.names.sort();
+    }
+
+    /// The file of the preset of the current name.
+    fn path(&self) -> Result<PathBuf, &'static str> {
+        let name = self.name.trim();
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            return Err("The name of the preset is not valid");
+        }
+        Ok(self.directory.join(format!("{name}.json")))
+    }
+
+    /// Saves settings as the preset of the current name, replacing it.
+    pub fn save<T: Serialize>(&mut self, settings: &T) -> Result<PathBuf, &'static str> {
+        let path = self.path()?;
+        let json =
+            serde_json::to_string_pretty(settings).map_err(|_| "Failed to serialize the preset")?;
+        std::fs::create_dir_all(&self.directory)
+            .map_err(|_| "Failed to create the presets directory")?;
+        std::fs::write(&path, json).map_err(|_| "Failed to write the preset file")?;
+        self.refresh();
+        Ok(path)
+    }
+
+    /// Loads the settings of the preset of the current name.
+    pub fn load<T: DeserializeOwned>(&self) -> Result<T, &'static str> {
+        read_preset(self.path()?)
+    }
+
+    /// Loads the settings of every saved preset along with their names, sorted by name.
+    ///
+    /// The presets that cannot be read, such as those saved by an older version of the sketch,
+    /// are skipped.
+    pub fn load_all<T: DeserializeOwned>(&self) -> Vec<(String, T)> {
+        self.names
+            .iter()
+            .filter_map(|name| {
+                let path = self.directory.join(format!("{name}.json"));
+                Some((name.clone(), read_preset(path).ok()?))
+            })
+            .collect()
+    }
+
+    /// Shows a dropdown of the saved presets, the name of the preset and buttons to save or load
+    /// it.
+    pub fn show(&mut self, ui: &mut egui::Ui) -> Option<PresetRequest> {
+        let mut request = None;
+        let names = self.names.iter().map(|name| (name.clone(), name.clone()));
+        ui.add(Dropdown::new("preset", &mut self.name, names));
+        ui.text_edit_singleline(&mut self.name);
+        ui.horizontal(|ui| {
+            if ui.button("Save preset").clicked() {
+                request = Some(PresetRequest::Save);
+            }
+            if ui.button("Load preset").clicked() {
+                request = Some(PresetRequest::Load);
+            }
+        });
+        request
+    }
+}
+
+/// Reads the settings of a preset file, which may be outside of the libraries of the sketches.
+pub fn read_preset<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, &'static str> {
+    let json = std::fs::read_to_string(path).map_err(|_| "Failed to read the preset file")?;
+    serde_json::from_str(&json).map_err(|_| "The preset file is not valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_round_trip() {
+        let directory = std::env::temp_dir().join(format!("presets_{}", std::process::id()));
+        let mut library = PresetLibrary::in_directory(&directory);
+        assert!(library.names().is_empty());
+
+        library.name = "calm".to_owned();
+        library.save(&[0.5f32, 2.0]).unwrap();
+        library.name = "busy".to_owned();
+        library.save(&[4.0f32, 8.0]).unwrap();
+        assert_eq!(library.names(), ["busy", "calm"]);
+
+        library.name = "calm".to_owned();
+        assert_eq!(library.load::<[f32; 2]>(), Ok([0.5, 2.0]));
+        assert!(library.load::<String>().is_err());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn load_all_skips_invalid_presets() {
+        let directory = std::env::temp_dir().join(format!("presets_all_{}", std::process::id()));
+        let mut library = PresetLibrary::in_directory(&directory);
+        library.name = "b".to_owned();
+        library.save(&2.0f32).unwrap();
+        library.name = "a".to_owned();
+        library.save(&1.0f32).unwrap();
+        library.name = "broken".to_owned();
+        library.save(&"not a number").unwrap();
+
+        assert_eq!(
+            library.load_all::<f32>(),
+            [("a".to_owned(), 1.0), ("b".to_owned(), 2.0)]
+        );
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn names_cannot_leave_the_directory() {
+        let mut library = PresetLibrary::in_directory(std::env::temp_dir());
+        ["", "  ", "../cloud", "a/b", ".hidden"]
+            .into_iter()
+            .for_each(|name| {
+                library.name = name.to_owned();
+                assert!(library.save(&0).is_err());
+            });
+    }
+}
@@ -0,0 +1,177 @@
+use std::sync::Mutex;
+
+use nannou::rand::{
+    Rng, SeedableRng,
+    distributions::{Distribution, Standard, uniform::SampleUniform},
+    rngs::StdRng,
+};
+use nannou_egui::egui;
+
+/// The flag setting the seed on the command line of the sketches.
+pub const SEED_FLAG: &str = "--seed";
+
+/// The generator shared by the random draws of the sketches, so a run started from the same seed
+/// draws the same numbers in the same order and its generative results can be reproduced.
+///
+/// The seed is drawn at random when it is not set, and printed so a run worth keeping can be
+/// started again.
+static RNG: Mutex<Option<SeededRng>> = Mutex::new(None);
+
+/// A generator of random numbers that remembers the seed it started from.
+pub struct SeededRng {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Draws a value of the standard distribution of its type, such as a float in [0, 1).
+    pub fn random<T>(&mut self) -> T
+    where
+        Standard: Distribution<T>,
+    {
+        self.rng.r#gen()
+    }
+
+    /// Draws a value between two bounds, in either order, the higher one excluded.
+    pub fn random_range<T: PartialOrd + SampleUniform>(&mut self, min: T, max: T) -> T {
+        let (min, max) = if min <= max { (min, max) } else { (max, min) };
+        self.rng.gen_range(min..max)
+    }
+}
+
+/// Sets the seed given with `--seed` on the command line, or draws one, and prints it.
+///
+/// The sketches call this as they start, before drawing any number.
+pub fn load(sketch: &str) -> u64 {
+    let seed = parse(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{e}\nUsage: {sketch} [{SEED_FLAG} NUMBER]");
+        std::process::exit(2);
+    });
+    let seed = seed.unwrap_or_else(nannou::rand::random);
+    println!("Random seed: {seed}");
+    set_seed(seed);
+    seed
+}
+
+/// Parses the seed among the arguments, leaving out the others for the sketch.
+pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Option<u64>, &'static str> {
+    let mut seed = None;
+    while let Some(arg) = args.next() {
+        if arg == SEED_FLAG {
+            let value = args
+                .next()
+                .and_then(|value| value.parse().ok())
+                .ok_or("The seed must be a positive integer")?;
+            seed = Some(value);
+        }
+    }
+    Ok(seed)
+}
+
+/// The seed the draws started from.
+pub fn seed() -> u64 {
+    with_generator(|rng| rng.seed())
+}
+
+/// Starts the draws again from a seed.
+pub fn set_seed(seed: u64) {
+    *RNG.lock().unwrap() = Some(SeededRng::new(seed));
+}
+
+/// Draws a value of the standard distribution of its type from the shared generator.
+pub fn random<T>() -> T
+where
+    Standard: Distribution<T>,
+{
+    with_generator(SeededRng::random)
+}
+
+/// Draws a value between two bounds from the shared generator, like nannou's `random_range`.
+pub fn random_range<T: PartialOrd + SampleUniform>(min: T, max: T) -> T {
+    with_generator(|rng| rng.random_range(min, max))
+}
+
+/// Draws a float in [0, 1) from the shared generator, like nannou's `random_f32`.
+pub fn random_f32() -> f32 {
+    random()
+}
+
+/// Shows the seed, which can be edited or drawn anew, and a button to start the draws again from
+/// it.
+///
+/// Returns whether the draws started again, for the sketch to regenerate what it drew from them.
+pub fn show(ui: &mut egui::Ui) -> bool {
+    let mut seed = seed();
+    let mut restart = false;
+    ui.horizontal(|ui| {
+        ui.label("Seed");
+        restart |= ui.add(egui::DragValue::new(&mut seed)).changed();
+        if ui.button("New seed").clicked() {
+            seed = nannou::rand::random();
+            restart = true;
+        }
+        restart |= ui.button("Restart").clicked();
+    });
+    if restart {
+        set_seed(seed);
+    }
+    restart
+}
+
+/// Runs a function with the shared generator, seeded at random if no seed was set.
+fn with_generator<T>(f: impl FnOnce(&mut SeededRng) -> T) -> T {
+    let mut rng = RNG.lock().unwrap();
+    f(rng.get_or_insert_with(|| SeededRng::new(nannou::rand::random())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter().map(|arg| arg.to_string())
+    }
+
+    #[test]
+    fn the_same_seed_draws_the_same_numbers() {
+        let draws = |seed| {
+            let mut rng = SeededRng::new(seed);
+            (0..8)
+                .map(|_| (rng.random::<f32>(), rng.random_range(-5, 5)))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(draws(42), draws(42));
+        assert_ne!(draws(42), draws(43));
+    }
+
+    #[test]
+    fn ranges_are_drawn_in_either_order() {
+        let mut rng = SeededRng::new(7);
+        (0..100).for_each(|_| {
+            let value = rng.random_range(10.0, -10.0);
+            assert!((-10.0..10.0).contains(&value));
+        });
+    }
+
+    #[test]
+    fn seed_is_parsed_among_other_arguments() {
+        assert_eq!(
+            parse(args(&["--monitor", "1", "--seed", "1234"])),
+            Ok(Some(1234))
+        );
+        assert_eq!(parse(args(&["--monitor", "1"])), Ok(None));
+        assert!(parse(args(&["--seed", "-3"])).is_err());
+        assert!(parse(args(&["--seed"])).is_err());
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+use nannou::geom::Vec2;
+
+/// Buckets points into square cells, to find the neighbours of a point without going through
+/// every other point.
+#[derive(Clone, Debug)]
+pub struct SpatialHash {
+    cell_size: f32,
+    points: Vec<Vec2>,
+    /// The indices of the points within each cell holding some.
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl SpatialHash {
+    /// Buckets points into cells of a size, usually the largest distance the neighbours are
+    /// searched within.
+    pub fn new(cell_size: f32, points: impl IntoIterator<Item = Vec2>) -> Self {
+        let cell_size = cell_size.max(f32::EPSILON);
+        let points: Vec<Vec2> = points.into_iter().collect();
+        let mut cells: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+        points.iter().enumerate().for_each(|(i, &point)| {
+            cells.entry(cell(point, cell_size)).or_default().push(i);
+        });
+        Self {
+            cell_size,
+            points,
+            cells,
+        }
+    }
+
+    pub fn points(&self) -> &[Vec2] {
+        &self.points
+    }
+
+    /// The indices of the points within a distance of a point, including the point itself if it
+    /// was bucketed.
+    pub fn neighbours(&self, point: Vec2, radius: f32) -> impl Iterator<Item = usize> + '_ {
+        let (column, row) = cell(point, self.cell_size);
+        let reach = (radius / self.cell_size).ceil() as i32;
+        (row - reach..=row + reach)
+            .flat_map(move |row| (column - reach..=column + reach).map(move |column| (column, row)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .filter(move |&i| self.points[i].distance_squared(point) <= radius * radius)
+    }
+}
+
+/// The cell holding a point.
+fn cell(point: Vec2, cell_size: f32) -> (i32, i32) {
+    (
+        (point.x / cell_size).floor() as i32,
+        (point.y / cell_size).floor() as i32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use nannou::geom::vec2;
+
+    use super::*;
+
+    #[test]
+    fn neighbours_match_a_brute_force_search() {
+        let points: Vec<Vec2> = (0..200)
+            .map(|i| {
+                vec2(
+                    (i * 37 % 101) as f32 * 3.1 - 150.0,
+                    (i * 53 % 89) as f32 * 2.7 - 120.0,
+                )
+            })
+            .collect();
+        let hash = SpatialHash::new(20.0, points.iter().copied());
+
+        [vec2(0.0, 0.0), vec2(-140.0, 100.0), vec2(55.5, -33.3)]
+            .into_iter()
+            .for_each(|point| {
+                [5.0, 20.0, 45.0].into_iter().for_each(|radius| {
+                    let mut found: Vec<usize> = hash.neighbours(point, radius).collect();
+                    found.sort();
+                    let expected: Vec<usize> = (0..points.len())
+                        .filter(|&i| points[i].distance(point) <= radius)
+                        .collect();
+                    assert_eq!(found, expected);
+                });
+            });
+    }
+
+    #[test]
+    fn a_point_is_its_own_neighbour() {
+        let hash = SpatialHash::new(10.0, [vec2(-0.5, 0.5), vec2(30.0, 0.0)]);
+        assert_eq!(
+            hash.neighbours(vec2(-0.5, 0.5), 1.0).collect::<Vec<_>>(),
+            [0]
+        );
+    }
+}
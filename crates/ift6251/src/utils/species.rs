@@ -0,0 +1,163 @@
+use std::{fmt, path::Path};
+
+use crate::utils::rng::random_range;
+
+/// How much each species of particles is attracted to each other species.
+///
+/// Positive values attract and negative values repel, from -1 to 1. The matrix is not symmetric:
+/// a species can chase another that flees from it.
+///
+/// A matrix is saved as one line per species, holding how much it is attracted to each species as
+/// whitespace separated values. Empty lines and lines starting with `#` are ignored.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InteractionMatrix {
+    species: usize,
+    /// The attraction of each species to each species, row by row.
+    values: Vec<f32>,
+}
+
+impl Default for InteractionMatrix {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl InteractionMatrix {
+    /// The largest number of species.
+    pub const MAX_SPECIES: usize = 8;
+
+    /// Creates a matrix where the species ignore each other.
+    pub fn new(species: usize) -> Self {
+        let species = species.clamp(1, Self::MAX_SPECIES);
+        Self {
+            species,
+            values: vec![0.0; species * species],
+        }
+    }
+
+    /// Creates a matrix of random attractions.
+    pub fn random(species: usize) -> Self {
+        let mut matrix = Self::new(species);
+        matrix
+            .values
+            .iter_mut()
+            .for_each(|value| *value = random_range(-1.0, 1.0));
+        matrix
+    }
+
+    pub fn species(&self) -> usize {
+        self.species
+    }
+
+    /// Changes the number of species, keeping the attractions between the remaining species.
+    pub fn resize(&mut self, species: usize) {
+        let mut resized = Self::new(species);
+        (0..self.species.min(resized.species)).for_each(|a| {
+            (0..self.species.min(resized.species)).for_each(|b| resized.set(a, b, self.get(a, b)));
+        });
+        *self = resized;
+    }
+
+    /// How much the species `a` is attracted to the species `b`.
+    pub fn get(&self, a: usize, b: usize) -> f32 {
+        self.values[a * self.species + b]
+    }
+
+    pub fn set(&mut self, a: usize, b: usize, value: f32) {
+        self.values[a * self.species + b] = value.clamp(-1.0, 1.0);
+    }
+
+    /// The attraction of a species to another, to edit it in place; it must be kept within
+    /// [-1, 1].
+    pub fn get_mut(&mut self, a: usize, b: usize) -> &mut f32 {
+        &mut self.values[a * self.species + b]
+    }
+
+    /// Parses the lines of a matrix file.
+    pub fn parse(text: &str) -> Result<Self, &'static str> {
+        let rows = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                line.split_whitespace()
+                    .map(|value| value.parse::<f32>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| "The interaction matrix has an invalid number")
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let species = rows.len();
+        if species == 0 || species > Self::MAX_SPECIES {
+            return Err("The interaction matrix does not have a valid number of species");
+        }
+        if rows.iter().any(|row| row.len() != species) {
+            return Err("The interaction matrix is not square");
+        }
+        let mut matrix = Self::new(species);
+        rows.iter().enumerate().for_each(|(a, row)| {
+            row.iter()
+                .enumerate()
+                .for_each(|(b, &value)| matrix.set(a, b, value));
+        });
+        Ok(matrix)
+    }
+
+    /// Reads a matrix from a file.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, &'static str> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|_| "Failed to read the interaction matrix file")?;
+        Self::parse(&text)
+    }
+
+    /// Writes the matrix to a file, replacing it.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), &'static str> {
+        std::fs::write(path, self.to_string())
+            .map_err(|_| "Failed to write the interaction matrix file")
+    }
+}
+
+impl fmt::Display for InteractionMatrix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# How much each row is attracted to each column")?;
+        self.values.chunks(self.species).try_for_each(|row| {
+            let row: Vec<String> = row.iter().map(f32::to_string).collect();
+            writeln!(f, "{}", row.join(" "))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matrices_round_trip() {
+        let matrix = InteractionMatrix::random(3);
+        assert_eq!(InteractionMatrix::parse(&matrix.to_string()), Ok(matrix));
+
+        let matrix = InteractionMatrix::parse("# chase\n0 1\n\n-0.5 2\n").unwrap();
+        assert_eq!(matrix.species(), 2);
+        assert_eq!(matrix.get(0, 1), 1.0);
+        assert_eq!(matrix.get(1, 0), -0.5);
+        assert_eq!(matrix.get(1, 1), 1.0);
+    }
+
+    #[test]
+    fn bad_matrices_are_rejected() {
+        assert!(InteractionMatrix::parse("").is_err());
+        assert!(InteractionMatrix::parse("0 1\n1").is_err());
+        assert!(InteractionMatrix::parse("0 up\n1 0").is_err());
+    }
+
+    #[test]
+    fn resizing_keeps_the_remaining_species() {
+        let mut matrix = InteractionMatrix::new(2);
+        matrix.set(1, 0, 0.5);
+        matrix.resize(3);
+        assert_eq!(matrix.get(1, 0), 0.5);
+        assert_eq!(matrix.get(2, 1), 0.0);
+        matrix.resize(1);
+        assert_eq!(matrix, InteractionMatrix::new(1));
+    }
+}
@@ -0,0 +1,183 @@
+use std::f32::consts::PI;
+
+use nannou::geom::{Vec2, vec2};
+
+use crate::utils::spatial_hash::SpatialHash;
+
+/// A fluid simulated with smoothed-particle hydrodynamics, in pixels and frames.
+///
+/// Each particle spreads its mass over the smoothing radius. Where the particles are denser than
+/// the rest density, their pressure pushes them apart, and where they are sparser, it pulls them
+/// together. The viscosity evens out the velocities of the neighbours, and the weight of the fluid
+/// pulls it down, so it pools like a liquid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fluid {
+    /// The distance the particles interact within.
+    pub smoothing_radius: f32,
+    /// The density, in mass per square pixel, the pressure brings the particles back to.
+    pub rest_density: f32,
+    /// How strongly the pressure follows the difference to the rest density.
+    pub stiffness: f32,
+    pub viscosity: f32,
+    /// The downward acceleration of the fluid, in pixels per frame squared.
+    pub gravity: f32,
+}
+
+impl Default for Fluid {
+    fn default() -> Self {
+        Self {
+            smoothing_radius: 24.0,
+            rest_density: 0.03,
+            stiffness: 2.0,
+            viscosity: 5.0,
+            gravity: 0.05,
+        }
+    }
+}
+
+impl Fluid {
+    /// The largest acceleration of a particle, which keeps the simulation from blowing up when the
+    /// particles are squeezed together.
+    const MAX_ACCELERATION: f32 = 2.0;
+
+    /// The density at each particle, its own mass included.
+    pub fn densities(&self, hash: &SpatialHash, masses: &[f32]) -> Vec<f32> {
+        let h = self.smoothing_radius;
+        hash.points()
+            .iter()
+            .map(|&point| {
+                hash.neighbours(point, h)
+                    .map(|j| masses[j] * poly6(hash.points()[j].distance_squared(point), h))
+                    .sum()
+            })
+            .collect()
+    }
+
+    /// Returns the acceleration of each particle from the pressure, the viscosity and the weight
+    /// of the fluid.
+    ///
+    /// # Arguments
+    ///
+    /// - `positions` - The positions of the particles.
+    /// - `velocities` - The displacements of the particles each frame.
+    /// - `masses` - The masses of the particles.
+    pub fn accelerations(
+        &self,
+        positions: &[Vec2],
+        velocities: &[Vec2],
+        masses: &[f32],
+    ) -> Vec<Vec2> {
+        let h = self.smoothing_radius;
+        let hash = SpatialHash::new(h, positions.iter().copied());
+        let densities = self.densities(&hash, masses);
+        let pressures: Vec<f32> = densities
+            .iter()
+            .map(|density| self.stiffness * (density - self.rest_density))
+            .collect();
+
+        (0..positions.len())
+            .map(|i| {
+                let force = hash
+                    .neighbours(positions[i], h)
+                    .filter(|&j| j != i)
+                    .map(|j| {
+                        let offset = positions[i] - positions[j];
+                        let distance = offset.length();
+                        let direction = offset / distance.max(f32::EPSILON);
+
+                        // The pressure is shared between the particles, so they push each other
+                        // equally
+                        let pressure = (pressures[i] + pressures[j]) / (2.0 * densities[j]);
+                        let pressure =
+                            -masses[j] * pressure * spiky_gradient(distance, h) * direction;
+                        let viscosity =
+                            self.viscosity * masses[j] * (velocities[j] - velocities[i])
+                                / densities[j]
+                                * viscosity_laplacian(distance, h);
+                        pressure + viscosity
+                    })
+                    .fold(Vec2::ZERO, |force, neighbour| force + neighbour);
+                let acceleration = force / densities[i] + vec2(0.0, -self.gravity);
+                acceleration.clamp_length_max(Self::MAX_ACCELERATION)
+            })
+            .collect()
+    }
+}
+
+/// The kernel the densities are smoothed with, normalized over the disc of radius `h`.
+fn poly6(distance_squared: f32, h: f32) -> f32 {
+    match distance_squared < h * h {
+        true => 4.0 / (PI * h.powi(8)) * (h * h - distance_squared).powi(3),
+        false => 0.0,
+    }
+}
+
+/// The slope of the kernel the pressure is smoothed with, which does not flatten out as the
+/// particles get close, so they keep pushing each other apart.
+fn spiky_gradient(distance: f32, h: f32) -> f32 {
+    match distance < h {
+        true => -30.0 / (PI * h.powi(5)) * (h - distance).powi(2),
+        false => 0.0,
+    }
+}
+
+/// The laplacian of the kernel the viscosity is smoothed with.
+fn viscosity_laplacian(distance: f32, h: f32) -> f32 {
+    match distance < h {
+        true => 40.0 / (PI * h.powi(5)) * (h - distance),
+        false => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_lonely_particle_is_only_weighed_down() {
+        let fluid = Fluid::default();
+        let positions = [vec2(0.0, 0.0), vec2(100.0, 0.0)];
+        let hash = SpatialHash::new(fluid.smoothing_radius, positions);
+        let densities = fluid.densities(&hash, &[2.0, 1.0]);
+        assert_eq!(densities[0], 2.0 * poly6(0.0, fluid.smoothing_radius));
+
+        let accelerations = fluid.accelerations(&positions, &[Vec2::ZERO; 2], &[2.0, 1.0]);
+        assert!(
+            accelerations
+                .iter()
+                .all(|&a| a == vec2(0.0, -fluid.gravity))
+        );
+    }
+
+    #[test]
+    fn crowded_particles_push_each_other_apart() {
+        let fluid = Fluid {
+            gravity: 0.0,
+            viscosity: 0.0,
+            ..Fluid::default()
+        };
+        let positions = [vec2(-1.0, 0.0), vec2(1.0, 0.0), vec2(0.0, 1.5)];
+        let accelerations = fluid.accelerations(&positions, &[Vec2::ZERO; 3], &[10.0; 3]);
+        positions
+            .iter()
+            .zip(&accelerations)
+            .for_each(|(position, acceleration)| {
+                // Each particle moves away from the center of the crowd
+                let center = vec2(0.0, 0.5);
+                assert!(acceleration.dot(*position - center) > 0.0);
+            });
+    }
+
+    #[test]
+    fn viscosity_evens_out_the_velocities() {
+        let fluid = Fluid {
+            gravity: 0.0,
+            stiffness: 0.0,
+            ..Fluid::default()
+        };
+        let positions = [vec2(0.0, 0.0), vec2(5.0, 0.0)];
+        let velocities = [vec2(0.0, 1.0), vec2(0.0, -1.0)];
+        let accelerations = fluid.accelerations(&positions, &velocities, &[5.0; 2]);
+        assert!(accelerations[0].y < 0.0 && accelerations[1].y > 0.0);
+    }
+}
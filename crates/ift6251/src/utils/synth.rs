@@ -0,0 +1,140 @@
+use std::f32::consts::{FRAC_PI_4, TAU};
+
+/// A short sine tone with a percussive envelope, panned between the two channels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Blip {
+    frequency: f32,
+    /// The gains of the left and right channels.
+    gains: [f32; 2],
+    duration: f32,
+    /// The number of samples played.
+    samples: u32,
+    /// The time of the next sample, in seconds.
+    time: f32,
+}
+
+impl Blip {
+    /// The duration of the fade in, in seconds, which keeps the start of the blip from clicking.
+    const ATTACK: f32 = 0.002;
+    /// The number of times the amplitude decays by a factor of e over the duration of the blip.
+    const DECAY: f32 = 6.0;
+
+    /// Creates a blip of the given frequency and duration, in hertz and seconds.
+    ///
+    /// The pan goes from -1 for the left channel to 1 for the right channel.
+    pub fn new(frequency: f32, pan: f32, volume: f32, duration: f32) -> Self {
+        // Equal-power panning keeps the loudness constant across the stereo field
+        let angle = (pan.clamp(-1.0, 1.0) + 1.0) * FRAC_PI_4;
+        Self {
+            frequency,
+            gains: [angle.cos() * volume, angle.sin() * volume],
+            duration,
+            samples: 0,
+            time: 0.0,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.time >= self.duration
+    }
+
+    /// Computes the next stereo frame of the blip and advances it by one sample.
+    pub fn next_frame(&mut self, sample_rate: u32) -> [f32; 2] {
+        if self.is_finished() {
+            return [0.0; 2];
+        }
+        let attack = (self.time / Self::ATTACK).min(1.0);
+        let envelope = attack * (-Self::DECAY * self.time / self.duration).exp();
+        let sample = (TAU * self.frequency * self.time).sin() * envelope;
+        // Counting the samples keeps the error of the time from accumulating
+        self.samples += 1;
+        self.time = self.samples as f32 / sample_rate as f32;
+        self.gains.map(|gain| sample * gain)
+    }
+}
+
+/// Plays a bounded number of blips at once, mixed into the buffers of an audio stream.
+///
+/// The voices are allocated upfront, so that triggering blips does not allocate on the audio
+/// thread.
+pub struct BlipSynth {
+    blips: Vec<Blip>,
+}
+
+impl Default for BlipSynth {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BlipSynth {
+    /// The number of blips that can play at once.
+    pub const MAX_VOICES: usize = 32;
+
+    pub fn new() -> Self {
+        Self {
+            blips: Vec::with_capacity(Self::MAX_VOICES),
+        }
+    }
+
+    /// Starts a blip, replacing the oldest one when all the voices are playing.
+    pub fn trigger(&mut self, blip: Blip) {
+        if self.blips.len() == Self::MAX_VOICES {
+            self.blips.remove(0);
+        }
+        self.blips.push(blip);
+    }
+
+    /// Adds the blips to stereo frames, removing those that finish.
+    pub fn render<'a>(&mut self, frames: impl Iterator<Item = &'a mut [f32]>, sample_rate: u32) {
+        if self.blips.is_empty() {
+            return;
+        }
+        frames.for_each(|frame| {
+            let mix = self.blips.iter_mut().fold([0.0; 2], |[left, right], blip| {
+                let [blip_left, blip_right] = blip.next_frame(sample_rate);
+                [left + blip_left, right + blip_right]
+            });
+            frame
+                .iter_mut()
+                .zip(mix)
+                .for_each(|(sample, value)| *sample += value);
+        });
+        self.blips.retain(|blip| !blip.is_finished());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 48000;
+
+    #[test]
+    fn blips_are_panned_and_fade_out() {
+        let mut synth = BlipSynth::new();
+        synth.trigger(Blip::new(440.0, -1.0, 1.0, 0.1));
+        let mut frames = vec![[0.0f32; 2]; SAMPLE_RATE as usize / 5];
+        synth.render(frames.iter_mut().map(|frame| &mut frame[..]), SAMPLE_RATE);
+
+        let peak = |frames: &[[f32; 2]], channel: usize| {
+            frames
+                .iter()
+                .map(|frame| frame[channel].abs())
+                .fold(0.0, f32::max)
+        };
+        let (tone, silence) = frames.split_at(SAMPLE_RATE as usize / 10);
+        assert!(peak(tone, 0) > 0.5);
+        assert!(peak(tone, 1) < 1e-6);
+        assert_eq!(peak(silence, 0), 0.0);
+        assert!(synth.blips.is_empty());
+    }
+
+    #[test]
+    fn the_oldest_voice_is_replaced() {
+        let mut synth = BlipSynth::new();
+        (0..=BlipSynth::MAX_VOICES).for_each(|i| synth.trigger(Blip::new(i as f32, 0.0, 1.0, 1.0)));
+        assert_eq!(synth.blips.len(), BlipSynth::MAX_VOICES);
+        assert_eq!(synth.blips[0].frequency, 1.0);
+    }
+}
@@ -0,0 +1,174 @@
+use std::{collections::BTreeMap, fmt::Write, path::Path};
+
+use nannou::geom::{Rect, Vec2};
+
+/// The formats the trajectories can be written to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrajectoryFormat {
+    /// One polyline per path, for pen plotters and vector editors.
+    Svg,
+    /// One row per position, with the id of the path, the frame and the coordinates.
+    Csv,
+}
+
+impl TrajectoryFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TrajectoryFormat::Svg => "svg",
+            TrajectoryFormat::Csv => "csv",
+        }
+    }
+}
+
+/// The paths of moving objects, recorded over a bounded number of frames.
+///
+/// Each path is identified by the id of its object, and the objects appearing or disappearing
+/// while recording have shorter paths.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trajectories {
+    paths: BTreeMap<u64, Trajectory>,
+    /// The number of frames recorded so far.
+    frame: u32,
+    /// The number of frames to record.
+    frames: u32,
+}
+
+/// The positions of an object over consecutive frames.
+#[derive(Clone, Debug, PartialEq)]
+struct Trajectory {
+    /// The frame of the first position.
+    start: u32,
+    positions: Vec<Vec2>,
+}
+
+impl Trajectories {
+    /// Starts recording paths over the given number of frames.
+    pub fn new(frames: u32) -> Self {
+        Self {
+            paths: BTreeMap::new(),
+            frame: 0,
+            frames: frames.max(1),
+        }
+    }
+
+    /// Whether all the frames were recorded.
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.frames
+    }
+
+    /// The fraction of the frames recorded, from 0 to 1.
+    pub fn progress(&self) -> f32 {
+        self.frame as f32 / self.frames as f32
+    }
+
+    /// Records the positions of the objects for the current frame, then moves to the next frame.
+    pub fn record(&mut self, positions: impl Iterator<Item = (u64, Vec2)>) {
+        if self.is_finished() {
+            return;
+        }
+        let frame = self.frame;
+        positions.for_each(|(id, position)| {
+            self.paths
+                .entry(id)
+                .or_insert_with(|| Trajectory {
+                    start: frame,
+                    positions: Vec::new(),
+                })
+                .positions
+                .push(position);
+        });
+        self.frame += 1;
+    }
+
+    /// Formats the paths as an SVG document covering the bounds, with its y axis pointing down.
+    ///
+    /// Paths of a single position cannot be drawn and are left out.
+    pub fn to_svg(&self, bounds: &Rect) -> String {
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+            w = bounds.w(),
+            h = bounds.h()
+        )
+        .unwrap();
+        self.paths
+            .values()
+            .filter(|path| path.positions.len() > 1)
+            .for_each(|path| {
+                let points: Vec<String> = path
+                    .positions
+                    .iter()
+                    .map(|p| format!("{:.2},{:.2}", p.x - bounds.left(), bounds.top() - p.y))
+                    .collect();
+                writeln!(
+                    svg,
+                    r#"<polyline points="{}" fill="none" stroke="black" stroke-width="1"/>"#,
+                    points.join(" ")
+                )
+                .unwrap();
+            });
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Formats the positions as CSV, with a header line.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("id,frame,x,y\n");
+        self.paths.iter().for_each(|(id, path)| {
+            path.positions
+                .iter()
+                .zip(path.start..)
+                .for_each(|(p, frame)| writeln!(csv, "{id},{frame},{},{}", p.x, p.y).unwrap());
+        });
+        csv
+    }
+
+    /// Writes the paths to a file, replacing it.
+    pub fn write(
+        &self,
+        path: impl AsRef<Path>,
+        format: TrajectoryFormat,
+        bounds: &Rect,
+    ) -> Result<(), &'static str> {
+        let text = match format {
+            TrajectoryFormat::Svg => self.to_svg(bounds),
+            TrajectoryFormat::Csv => self.to_csv(),
+        };
+        std::fs::write(path, text).map_err(|_| "Failed to write the trajectories file")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_start_when_their_object_appears() {
+        let mut trajectories = Trajectories::new(2);
+        trajectories.record([(3, Vec2::new(1.0, 2.0))].into_iter());
+        trajectories.record([(3, Vec2::new(1.5, 2.0)), (1, Vec2::new(0.0, 0.0))].into_iter());
+        assert!(trajectories.is_finished());
+
+        // Recording stops after the last frame
+        trajectories.record([(3, Vec2::new(9.0, 9.0))].into_iter());
+        assert_eq!(
+            trajectories.to_csv(),
+            "id,frame,x,y\n1,1,0,0\n3,0,1,2\n3,1,1.5,2\n"
+        );
+    }
+
+    #[test]
+    fn svg_paths_are_flipped_into_the_bounds() {
+        let mut trajectories = Trajectories::new(3);
+        let bounds = Rect::from_w_h(100.0, 50.0);
+        trajectories.record([(0, Vec2::new(-50.0, 25.0)), (1, Vec2::ZERO)].into_iter());
+        trajectories.record([(0, Vec2::new(50.0, -25.0))].into_iter());
+
+        let svg = trajectories.to_svg(&bounds);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains(r#"points="0.00,0.00 100.00,50.00""#));
+        // A single position cannot be drawn
+        assert_eq!(svg.matches("<polyline").count(), 1);
+    }
+}
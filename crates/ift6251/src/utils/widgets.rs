@@ -0,0 +1,624 @@
+use std::{
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+
+use crate::utils::audio::AudioScope;
+
+use nannou::color::{Hsl, Hsla, LinSrgb};
+use nannou_egui::egui::{
+    self, Color32, Response, Sense, Stroke, Ui, Widget,
+    ecolor::Hsva,
+    emath::Numeric,
+    widgets::color_picker::{self, Alpha},
+};
+
+/// A slider under its label, with a button resetting it to its default value.
+///
+/// Unless it is given, the default value is the value of the slider when it is first shown, so the
+/// sketches reset to their initial settings. The label also identifies the slider, so it must be
+/// unique within its window.
+pub struct LabelledSlider<'a, N> {
+    label: String,
+    value: &'a mut N,
+    range: RangeInclusive<N>,
+    default: Option<N>,
+    logarithmic: bool,
+}
+
+impl<'a, N: Numeric + Send + Sync> LabelledSlider<'a, N> {
+    pub fn new(label: impl Into<String>, value: &'a mut N, range: RangeInclusive<N>) -> Self {
+        Self {
+            label: label.into(),
+            value,
+            range,
+            default: None,
+            logarithmic: false,
+        }
+    }
+
+    pub fn with_default(mut self, default: N) -> Self {
+        self.default = Some(default);
+        self
+    }
+
+    /// Spreads the values of the slider logarithmically, for the ranges spanning several orders of
+    /// magnitude.
+    pub fn logarithmic(mut self) -> Self {
+        self.logarithmic = true;
+        self
+    }
+}
+
+impl<N: Numeric + Send + Sync> Widget for LabelledSlider<'_, N> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let id = ui.make_persistent_id(&self.label);
+        let default = match self.default {
+            Some(default) => default,
+            None => ui.data_mut(|data| *data.get_temp_mut_or_insert_with(id, || *self.value)),
+        };
+
+        ui.label(format!("{}:", self.label));
+        ui.horizontal(|ui| {
+            let slider = egui::Slider::new(self.value, self.range).logarithmic(self.logarithmic);
+            let mut response = ui.add(slider);
+            let reset = ui
+                .add_enabled(*self.value != default, egui::Button::new("reset").small())
+                .on_hover_text(format!("Reset to {}", default.to_f64()));
+            if reset.clicked() {
+                *self.value = default;
+                response.mark_changed();
+            }
+            response
+        })
+        .inner
+    }
+}
+
+/// A slider scaling the whole GUI, on top of the scale factor of the display that egui already
+/// follows on HiDPI displays.
+///
+/// The scale is kept in the memory of egui, so the sketches do not need to store it.
+pub struct UiScale;
+
+impl Widget for UiScale {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let id = egui::Id::new("ui_scale");
+        let mut scale = ui.data_mut(|data| *data.get_temp_mut_or(id, 1.0f32));
+        let response =
+            ui.add(LabelledSlider::new("UI scale", &mut scale, 0.5..=3.0).with_default(1.0));
+        if response.changed() {
+            ui.data_mut(|data| data.insert_temp(id, scale));
+            ui.ctx().set_style(scaled_style(scale));
+        }
+        response
+    }
+}
+
+/// The default style of egui, with its text and spacing scaled.
+fn scaled_style(scale: f32) -> egui::Style {
+    let mut style = egui::Style::default();
+    style
+        .text_styles
+        .values_mut()
+        .for_each(|font| font.size *= scale);
+
+    let spacing = &mut style.spacing;
+    spacing.item_spacing *= scale;
+    spacing.button_padding *= scale;
+    spacing.interact_size *= scale;
+    spacing.indent *= scale;
+    spacing.slider_width *= scale;
+    spacing.combo_width *= scale;
+    spacing.text_edit_width *= scale;
+    spacing.icon_width *= scale;
+    spacing.icon_width_inner *= scale;
+    spacing.icon_spacing *= scale;
+    spacing.tooltip_width *= scale;
+    spacing.combo_height *= scale;
+    spacing.scroll_bar_width *= scale;
+    style
+}
+
+/// A dropdown choosing a value among named options, such as the variants of an enum.
+///
+/// The label also identifies the dropdown, so it must be unique within its window.
+pub struct Dropdown<'a, T> {
+    label: String,
+    value: &'a mut T,
+    options: Vec<(T, String)>,
+}
+
+impl<'a, T: Clone + PartialEq> Dropdown<'a, T> {
+    pub fn new(
+        label: impl Into<String>,
+        value: &'a mut T,
+        options: impl IntoIterator<Item = (T, impl Into<String>)>,
+    ) -> Self {
+        Self {
+            label: label.into(),
+            value,
+            options: options
+                .into_iter()
+                .map(|(option, name)| (option, name.into()))
+                .collect(),
+        }
+    }
+
+    /// The name of the chosen option, or nothing if it is not among the options.
+    fn selected_name(&self) -> &str {
+        self.options
+            .iter()
+            .find(|(option, _)| option == self.value)
+            .map_or("", |(_, name)| name)
+    }
+}
+
+impl<T: Clone + PartialEq> Widget for Dropdown<'_, T> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let previous = self.value.clone();
+        let selected = self.selected_name().to_owned();
+        let mut response = egui::ComboBox::from_label(&self.label)
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                self.options.into_iter().for_each(|(option, name)| {
+                    ui.selectable_value(&mut *self.value, option, name);
+                });
+            })
+            .response;
+        if *self.value != previous {
+            response.mark_changed();
+        }
+        response
+    }
+}
+
+/// A color button beside its label, opening a picker for a color of nannou.
+pub struct HslaPicker<'a> {
+    label: String,
+    color: &'a mut Hsla,
+}
+
+impl<'a> HslaPicker<'a> {
+    pub fn new(label: impl Into<String>, color: &'a mut Hsla) -> Self {
+        Self {
+            label: label.into(),
+            color,
+        }
+    }
+}
+
+impl Widget for HslaPicker<'_> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.horizontal(|ui| {
+            ui.label(format!("{}:", self.label));
+            // The color only goes back to nannou when it is picked, so it is not rounded every frame
+            let mut hsva = to_hsva(*self.color);
+            let response = color_picker::color_edit_button_hsva(ui, &mut hsva, Alpha::OnlyBlend);
+            if response.changed() {
+                *self.color = from_hsva(hsva);
+            }
+            response
+        })
+        .inner
+    }
+}
+
+/// Converts a color of nannou to egui, through the linear channels both of them are based on.
+fn to_hsva(color: Hsla) -> Hsva {
+    let (red, green, blue) = LinSrgb::from(color.color).into_components();
+    Hsva::from_rgba_unmultiplied(red, green, blue, color.alpha)
+}
+
+fn from_hsva(hsva: Hsva) -> Hsla {
+    let [red, green, blue, alpha] = hsva.to_rgba_unmultiplied();
+    Hsla {
+        color: Hsl::from(LinSrgb::new(red, green, blue)),
+        alpha,
+    }
+}
+
+/// The file remembering the directory a file was last picked from, so the pickers open there
+/// across runs.
+const LAST_DIRECTORY_FILE: &str = "./.last_directory";
+
+/// A text field holding the path of a file under its label, with a browser of the directories
+/// listing the files that have one of the extensions.
+///
+/// The browser opens in the directory a file was last picked from, by any picker, or else beside
+/// the path.
+pub struct FilePicker<'a> {
+    label: String,
+    path: &'a mut String,
+    extensions: &'a [&'a str],
+    picked: Option<&'a mut bool>,
+}
+
+impl<'a> FilePicker<'a> {
+    /// Picks any file when no extension is given.
+    pub fn new(label: impl Into<String>, path: &'a mut String, extensions: &'a [&'a str]) -> Self {
+        Self {
+            label: label.into(),
+            path,
+            extensions,
+            picked: None,
+        }
+    }
+
+    /// Sets `picked` when a file is chosen in the browser, so it can be opened right away.
+    pub fn picked(mut self, picked: &'a mut bool) -> Self {
+        self.picked = Some(picked);
+        self
+    }
+}
+
+impl Widget for FilePicker<'_> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        ui.label(format!("{}:", self.label));
+        ui.horizontal(|ui| {
+            let mut response = ui.text_edit_singleline(self.path);
+            let id = egui::Id::new(LAST_DIRECTORY_FILE);
+            let menu = ui.menu_button("Browse", |ui| {
+                let directory = ui
+                    .data_mut(|data| data.get_temp::<PathBuf>(id))
+                    .unwrap_or_else(|| initial_directory(self.path));
+                let (next, file) = browse(ui, id, &directory, self.extensions);
+                ui.data_mut(|data| data.insert_temp(id, next.unwrap_or(directory)));
+                file
+            });
+            if let Some(file) = menu.inner.flatten() {
+                if let Some(directory) = file.parent().and_then(Path::to_str) {
+                    std::fs::write(LAST_DIRECTORY_FILE, directory).ok();
+                }
+                *self.path = file.to_string_lossy().into_owned();
+                if let Some(picked) = self.picked {
+                    *picked = true;
+                }
+                response.mark_changed();
+                ui.close_menu();
+            }
+            response
+        })
+        .inner
+    }
+}
+
+/// The directory the browser of a file picker opens in, when it was not browsed yet this run.
+fn initial_directory(path: &str) -> PathBuf {
+    let directory = std::fs::read_to_string(LAST_DIRECTORY_FILE)
+        .ok()
+        .map(PathBuf::from)
+        .filter(|directory| directory.is_dir())
+        .unwrap_or_else(|| directory_beside(path));
+    // Absolute paths can always be browsed up to their root
+    std::fs::canonicalize(&directory).unwrap_or(directory)
+}
+
+/// Lists a directory in the browser of a file picker, returning the directory to browse next and
+/// the file picked, if any.
+///
+/// The listing is kept under the `id` of the browser and only read again when browsing another
+/// directory or when refreshed, rather than every frame.
+fn browse(
+    ui: &mut Ui,
+    id: egui::Id,
+    directory: &Path,
+    extensions: &[&str],
+) -> (Option<PathBuf>, Option<PathBuf>) {
+    let (mut next, mut file) = (None, None);
+    let mut refresh = false;
+    ui.horizontal(|ui| {
+        ui.label(directory.to_string_lossy());
+        refresh = ui.small_button("Refresh").clicked();
+    });
+    if !extensions.is_empty() {
+        ui.weak(format!("Showing {}", extensions.join(", ")));
+    }
+    ui.separator();
+
+    let id = id.with("listing");
+    let cached = ui
+        .data_mut(|data| data.get_temp::<Listing>(id))
+        .filter(|listing| !refresh && listing.is_of(directory, extensions));
+    let listing = cached.unwrap_or_else(|| {
+        let listing = list_directory(directory, extensions);
+        ui.data_mut(|data| data.insert_temp(id, listing.clone()));
+        listing
+    });
+    let name = |path: &Path| {
+        path.file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    };
+    egui::ScrollArea::vertical()
+        .max_height(400.0)
+        .show(ui, |ui| {
+            if let Some(parent) = directory.parent()
+                && ui.button("../").clicked()
+            {
+                next = Some(parent.to_owned());
+            }
+            for subdirectory in listing.directories {
+                if ui.button(format!("{}/", name(&subdirectory))).clicked() {
+                    next = Some(subdirectory);
+                }
+            }
+            if listing.files.is_empty() {
+                ui.label("No matching files");
+            }
+            file = listing
+                .files
+                .into_iter()
+                .find(|file| ui.button(name(file)).clicked());
+        });
+    (next, file)
+}
+
+/// The directory of a path, or the path itself if it is a directory.
+fn directory_beside(path: &str) -> PathBuf {
+    let path = Path::new(path);
+    match path.parent() {
+        _ if path.is_dir() => path,
+        Some(parent) if parent != Path::new("") => parent,
+        _ => Path::new("."),
+    }
+    .to_owned()
+}
+
+/// The subdirectories and the files of a directory, sorted.
+#[derive(Clone)]
+struct Listing {
+    directory: PathBuf,
+    extensions: Vec<String>,
+    directories: Vec<PathBuf>,
+    /// The files with one of the extensions, or all of them if none is given.
+    files: Vec<PathBuf>,
+}
+
+impl Listing {
+    /// Whether this is the listing of the directory, filtered by the same extensions.
+    fn is_of(&self, directory: &Path, extensions: &[&str]) -> bool {
+        self.directory == directory
+            && self
+                .extensions
+                .iter()
+                .map(String::as_str)
+                .eq(extensions.iter().copied())
+    }
+}
+
+fn list_directory(directory: &Path, extensions: &[&str]) -> Listing {
+    let entries: Vec<PathBuf> = std::fs::read_dir(directory)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .collect()
+        })
+        .unwrap_or_default();
+    let (mut directories, files): (Vec<PathBuf>, Vec<PathBuf>) =
+        entries.into_iter().partition(|entry| entry.is_dir());
+    let mut files: Vec<PathBuf> = files
+        .into_iter()
+        .filter(|file| {
+            extensions.is_empty()
+                || file.extension().is_some_and(|extension| {
+                    extensions
+                        .iter()
+                        .any(|accepted| extension.eq_ignore_ascii_case(accepted))
+                })
+        })
+        .collect();
+    directories.sort();
+    files.sort();
+    Listing {
+        directory: directory.to_owned(),
+        extensions: extensions
+            .iter()
+            .map(|extension| extension.to_string())
+            .collect(),
+        directories,
+        files,
+    }
+}
+
+/// The spectrum of the audio as bars behind its waveform, colored by the bass, mid and treble bands.
+///
+/// The edges between the bands are drawn over the spectrum, and dragging near one moves it, so the
+/// bands can be fitted to the frequencies driving the deformation.
+pub struct SpectrumScope<'a> {
+    scope: &'a AudioScope,
+    band_edges: &'a mut [f32; 2],
+}
+
+impl<'a> SpectrumScope<'a> {
+    /// The colors of the bass, mid and treble bands.
+    pub const BAND_COLORS: [Color32; 3] = [
+        Color32::from_rgb(230, 90, 70),
+        Color32::from_rgb(240, 190, 60),
+        Color32::from_rgb(80, 160, 230),
+    ];
+    const HEIGHT: f32 = 120.0;
+
+    pub fn new(scope: &'a AudioScope, band_edges: &'a mut [f32; 2]) -> Self {
+        Self { scope, band_edges }
+    }
+}
+
+impl Widget for SpectrumScope<'_> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let size = egui::vec2(ui.available_width().max(200.0), Self::HEIGHT);
+        let (rect, mut response) = ui.allocate_exact_size(size, Sense::click_and_drag());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+        let scope = self.scope;
+        if scope.bands.is_empty() {
+            let color = ui.visuals().weak_text_color();
+            let font = egui::FontId::default();
+            painter.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                "No audio",
+                font,
+                color,
+            );
+            return response;
+        }
+
+        // The bars are scaled to the loudest band, their square root keeping the quiet bands visible
+        let loudest = scope.bands.iter().copied().fold(f32::EPSILON, f32::max);
+        let bar_width = rect.width() / scope.bands.len() as f32;
+        scope.bands.iter().enumerate().for_each(|(i, &value)| {
+            let frequency = scope.frequency_at((i as f32 + 0.5) / scope.bands.len() as f32);
+            let band = self
+                .band_edges
+                .iter()
+                .filter(|&&edge| frequency >= edge)
+                .count();
+            let height = (value / loudest).sqrt() * rect.height();
+            let left = rect.left() + i as f32 * bar_width;
+            let bar = egui::Rect::from_min_max(
+                egui::pos2(left, rect.bottom() - height),
+                egui::pos2(left + bar_width, rect.bottom()),
+            );
+            painter.rect_filled(bar, 0.0, Self::BAND_COLORS[band]);
+        });
+
+        // The waveform runs across the middle
+        let last = scope.waveform.len().saturating_sub(1).max(1) as f32;
+        let points = scope
+            .waveform
+            .iter()
+            .enumerate()
+            .map(|(i, &sample)| {
+                let x = rect.left() + i as f32 / last * rect.width();
+                egui::pos2(
+                    x,
+                    rect.center().y - sample.clamp(-1.0, 1.0) * rect.height() / 2.0,
+                )
+            })
+            .collect();
+        painter.add(egui::Shape::line(
+            points,
+            Stroke::new(1.0, ui.visuals().text_color()),
+        ));
+
+        // Move the edge nearest to the pointer, keeping the mid band from being inverted
+        if let Some(pointer) = response
+            .interact_pointer_pos()
+            .filter(|_| response.dragged())
+        {
+            let position = ((pointer.x - rect.left()) / rect.width()).clamp(0.0, 1.0);
+            let frequency = scope.frequency_at(position);
+            let distance = |edge: f32| (scope.position_of(edge) - position).abs();
+            match distance(self.band_edges[0]) <= distance(self.band_edges[1]) {
+                true => self.band_edges[0] = frequency.min(self.band_edges[1]),
+                false => self.band_edges[1] = frequency.max(self.band_edges[0]),
+            }
+            response.mark_changed();
+        }
+
+        let stroke = Stroke::new(2.0, ui.visuals().strong_text_color());
+        self.band_edges.iter().for_each(|&edge| {
+            let x = rect.left() + scope.position_of(edge).clamp(0.0, 1.0) * rect.width();
+            painter.line_segment(
+                [egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+                stroke,
+            );
+            painter.text(
+                egui::pos2(x + 3.0, rect.top() + 2.0),
+                egui::Align2::LEFT_TOP,
+                format!("{edge:.0} Hz"),
+                egui::FontId::proportional(11.0),
+                stroke.color,
+            );
+        });
+        response.on_hover_text("Drag to move the edges between the bands")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nannou::color::hsla;
+
+    use super::*;
+
+    #[test]
+    fn colors_round_trip_through_egui() {
+        [
+            hsla(0.2, 1.0, 0.5, 0.1),
+            hsla(0.76, 0.54, 0.23, 1.0),
+            hsla(0.92, 0.74, 0.28, 0.01),
+        ]
+        .into_iter()
+        .for_each(|color| {
+            let back = from_hsva(to_hsva(color));
+            let hue = (back.hue - color.hue).to_degrees().abs();
+            assert!(hue < 0.1, "{color:?} became {back:?}");
+            assert!((back.saturation - color.saturation).abs() < 1e-3);
+            assert!((back.lightness - color.lightness).abs() < 1e-3);
+            assert_eq!(back.alpha, color.alpha);
+        });
+
+        // The lightness of nannou is linear, like the value of egui
+        let gray = to_hsva(hsla(0.0, 0.0, 0.5, 1.0));
+        assert!((gray.v - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn dropdowns_name_the_chosen_option() {
+        let mut value = 2;
+        let dropdown = Dropdown::new("value", &mut value, [(1, "One"), (2, "Two")]);
+        assert_eq!(dropdown.selected_name(), "Two");
+        let mut value = 3;
+        let dropdown = Dropdown::new("value", &mut value, [(1, "One"), (2, "Two")]);
+        assert_eq!(dropdown.selected_name(), "");
+    }
+
+    #[test]
+    fn the_directory_beside_a_path_is_listed() {
+        let directory = std::env::temp_dir().join(format!("widgets_{}", std::process::id()));
+        std::fs::create_dir_all(directory.join("nested.ply")).unwrap();
+        ["b.PLY", "a.ply", "c.e57", "notes.txt"]
+            .into_iter()
+            .for_each(|name| std::fs::write(directory.join(name), "").unwrap());
+
+        let names = |paths: Vec<PathBuf>| -> Vec<String> {
+            paths
+                .iter()
+                .map(|path| path.file_name().unwrap().to_str().unwrap().to_owned())
+                .collect()
+        };
+        let listed = |path: &Path, extensions: &[&str]| -> Vec<String> {
+            names(list_directory(&directory_beside(path.to_str().unwrap()), extensions).files)
+        };
+        assert_eq!(
+            listed(&directory.join("missing.ply"), &["ply", "e57"]),
+            ["a.ply", "b.PLY", "c.e57"]
+        );
+        assert_eq!(listed(&directory, &["txt"]), ["notes.txt"]);
+        assert_eq!(listed(&directory.join("a.ply"), &[]).len(), 4);
+        assert_eq!(
+            names(list_directory(&directory, &["ply"]).directories),
+            ["nested.ply"]
+        );
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn listings_are_reused_for_the_same_directory_and_extensions() {
+        let directory =
+            std::env::temp_dir().join(format!("widgets_listing_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let listing = list_directory(&directory, &["ply", "e57"]);
+
+        assert!(listing.is_of(&directory, &["ply", "e57"]));
+        assert!(!listing.is_of(&directory, &["ply"]));
+        assert!(!listing.is_of(&directory, &[]));
+        assert!(!listing.is_of(&directory.join("nested"), &["ply", "e57"]));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,218 @@
+use std::{
+    ffi::OsString,
+    path::PathBuf,
+    process::Command,
+    time::{Duration, Instant, SystemTime},
+};
+
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::utils::presets::PresetLibrary;
+
+/// The flag running a sketch under a supervisor, optionally followed by how many seconds without a
+/// frame the sketch is restarted after.
+pub const SUPERVISE_FLAG: &str = "--supervise";
+
+/// How long the sketch may go without a frame before it is restarted, unless given with the flag.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long the sketch may take to draw its first frame, as loading a scan can take a while.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+/// How often the supervisor checks on the sketch.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How long the supervisor waits before restarting the sketch, so a sketch crashing on startup
+/// does not spin.
+const RESTART_DELAY: Duration = Duration::from_secs(2);
+/// How often the sketch touches its heartbeat file.
+const BEAT_INTERVAL: Duration = Duration::from_secs(1);
+/// How often the sketch autosaves its settings.
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+/// The name of the preset the settings are autosaved to.
+const AUTOSAVE_PRESET: &str = "autosave";
+
+/// The variable giving the supervised sketch the path of its heartbeat file.
+const HEARTBEAT_VARIABLE: &str = "IFT6251_HEARTBEAT";
+/// The variable telling the supervised sketch it was restarted and should restore its autosave.
+const RESTARTED_VARIABLE: &str = "IFT6251_RESTARTED";
+
+/// Runs the sketch under a supervisor when it was started with [`SUPERVISE_FLAG`], for
+/// unattended installations.
+///
+/// The supervisor starts the sketch again as a child process, and restarts it when it crashes or
+/// stops producing frames for the timeout. It only returns in the child, or when the sketch is not
+/// supervised, and exits once the sketch quits normally.
+pub fn supervise() {
+    let args: Vec<OsString> = std::env::args_os().skip(1).collect();
+    let Some(timeout) = supervise_timeout(&args) else {
+        return;
+    };
+    if std::env::var_os(HEARTBEAT_VARIABLE).is_some() {
+        return;
+    }
+
+    let exe = std::env::current_exe().expect("Failed to find the executable of the sketch");
+    let heartbeat = std::env::temp_dir().join(format!("ift6251_{}.heartbeat", std::process::id()));
+    let mut restarted = false;
+    loop {
+        let _ = std::fs::remove_file(&heartbeat);
+        let mut command = Command::new(&exe);
+        command.args(&args).env(HEARTBEAT_VARIABLE, &heartbeat);
+        if restarted {
+            command.env(RESTARTED_VARIABLE, "1");
+        }
+        let mut child = command.spawn().expect("Failed to start the sketch");
+        let started = SystemTime::now();
+
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            match child.try_wait() {
+                Ok(Some(status)) if status.success() => std::process::exit(0),
+                Ok(Some(status)) => {
+                    eprintln!("The sketch exited with {status}, restarting it");
+                    break;
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("Failed to check on the sketch: {e}"),
+            }
+
+            let last_beat = std::fs::metadata(&heartbeat).and_then(|metadata| metadata.modified());
+            let stalled = match last_beat {
+                Ok(last_beat) => is_stalled(last_beat.max(started), SystemTime::now(), timeout),
+                Err(_) => is_stalled(started, SystemTime::now(), STARTUP_TIMEOUT),
+            };
+            if stalled {
+                eprintln!("The sketch stopped producing frames, restarting it");
+                let _ = child.kill();
+                let _ = child.wait();
+                break;
+            }
+        }
+
+        restarted = true;
+        std::thread::sleep(RESTART_DELAY);
+    }
+}
+
+/// Returns the timeout given with [`SUPERVISE_FLAG`] among the arguments, or `None` if the flag
+/// was not given.
+pub fn supervise_timeout(args: &[OsString]) -> Option<Duration> {
+    let position = args.iter().position(|arg| arg == SUPERVISE_FLAG)?;
+    let seconds = args
+        .get(position + 1)
+        .and_then(|arg| arg.to_str()?.parse::<f32>().ok())
+        .filter(|&seconds| seconds > 0.0);
+    Some(seconds.map_or(DEFAULT_TIMEOUT, Duration::from_secs_f32))
+}
+
+/// Whether the sketch went without a frame for longer than the timeout since its last beat.
+pub fn is_stalled(last_beat: SystemTime, now: SystemTime, timeout: Duration) -> bool {
+    now.duration_since(last_beat)
+        .is_ok_and(|elapsed| elapsed > timeout)
+}
+
+/// The side of the supervision running in the sketch, which reports that frames are produced and
+/// autosaves the settings to restore after a restart.
+pub struct Watchdog {
+    heartbeat: PathBuf,
+    restarted: bool,
+    last_beat: Option<Instant>,
+    last_autosave: Instant,
+    /// The presets of the sketch, which hold the autosave.
+    presets: PresetLibrary,
+}
+
+impl Watchdog {
+    /// Returns the watchdog of the sketch if it is supervised, see [`supervise`].
+    pub fn from_env(sketch: &str) -> Option<Self> {
+        let heartbeat = std::env::var_os(HEARTBEAT_VARIABLE)?.into();
+        let mut presets = PresetLibrary::new(sketch);
+        presets.name = AUTOSAVE_PRESET.to_owned();
+        Some(Self {
+            heartbeat,
+            restarted: std::env::var_os(RESTARTED_VARIABLE).is_some(),
+            last_beat: None,
+            last_autosave: Instant::now(),
+            presets,
+        })
+    }
+
+    /// Returns the autosaved settings if the sketch was restarted.
+    pub fn restore<T: DeserializeOwned>(&self) -> Option<T> {
+        if !self.restarted {
+            return None;
+        }
+        match self.presets.load() {
+            Ok(settings) => {
+                println!("Restoring the autosaved settings");
+                Some(settings)
+            }
+            Err(e) => {
+                eprintln!("{e}: {AUTOSAVE_PRESET}");
+                None
+            }
+        }
+    }
+
+    /// Reports a new frame to the supervisor, for the sketches without settings to autosave.
+    pub fn beat(&mut self) {
+        if self
+            .last_beat
+            .is_none_or(|last_beat| last_beat.elapsed() >= BEAT_INTERVAL)
+        {
+            if let Err(e) = std::fs::write(&self.heartbeat, []) {
+                eprintln!("Failed to write the heartbeat: {e}");
+            }
+            self.last_beat = Some(Instant::now());
+        }
+    }
+
+    /// Reports a new frame to the supervisor, and autosaves the settings when it is time.
+    ///
+    /// The settings are only built when they are saved.
+    pub fn tick<T: Serialize>(&mut self, settings: impl FnOnce() -> T) {
+        self.beat();
+        if self.last_autosave.elapsed() >= AUTOSAVE_INTERVAL {
+            if let Err(e) = self.presets.save(&settings()) {
+                eprintln!("{e}: {AUTOSAVE_PRESET}");
+            }
+            self.last_autosave = Instant::now();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_flag_takes_an_optional_timeout() {
+        let args = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<_>>();
+        assert_eq!(supervise_timeout(&args(&[])), None);
+        assert_eq!(supervise_timeout(&args(&["cloud.ply"])), None);
+        assert_eq!(
+            supervise_timeout(&args(&["--supervise"])),
+            Some(DEFAULT_TIMEOUT)
+        );
+        assert_eq!(
+            supervise_timeout(&args(&["--supervise", "cloud.ply"])),
+            Some(DEFAULT_TIMEOUT)
+        );
+        assert_eq!(
+            supervise_timeout(&args(&["--supervise", "2.5"])),
+            Some(Duration::from_millis(2500))
+        );
+        assert_eq!(
+            supervise_timeout(&args(&["--supervise", "-1"])),
+            Some(DEFAULT_TIMEOUT)
+        );
+    }
+
+    #[test]
+    fn stalls_after_the_timeout() {
+        let beat = SystemTime::UNIX_EPOCH + Duration::from_secs(100);
+        let timeout = Duration::from_secs(10);
+        assert!(!is_stalled(beat, beat + Duration::from_secs(5), timeout));
+        assert!(is_stalled(beat, beat + Duration::from_secs(11), timeout));
+        // A beat after the clock was read is not a stall
+        assert!(!is_stalled(beat, beat - Duration::from_secs(1), timeout));
+    }
+}
@@ -0,0 +1,108 @@
+use nannou::{geom::Point3, wgpu};
+
+/// A single gravity well that pulls the points of the cloud towards it.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct Attractor {
+    pub position: [f32; 3],
+    pub strength: f32,
+}
+
+/// The attractors currently placed in the cloud.
+///
+/// The struct is sent as-is to the compute shader, so its layout must match the `Attractors`
+/// struct declared in `compute.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct AttractorData {
+    attractors: [Attractor; Self::MAX_ATTRACTORS],
+    count: u32,
+    /// The distance at which the pull of an attractor is halved.
+    pub radius: f32,
+    _padding: [u32; 2],
+}
+
+impl AttractorData {
+    /// The maximum number of attractors; must match `MAX_ATTRACTORS` in `compute.wgsl`.
+    pub const MAX_ATTRACTORS: usize = 16;
+
+    /// Creates an empty set of attractors.
+    pub fn new(radius: f32) -> Self {
+        Self {
+            attractors: [Attractor::default(); Self::MAX_ATTRACTORS],
+            count: 0,
+            radius,
+            _padding: [0; 2],
+        }
+    }
+
+    /// Adds an attractor at the given position.
+    ///
+    /// Once the maximum number of attractors is reached, the oldest one is replaced.
+    pub fn push(&mut self, position: Point3, strength: f32) {
+        let attractor = Attractor {
+            position: position.to_array(),
+            strength,
+        };
+
+        if (self.count as usize) < Self::MAX_ATTRACTORS {
+            self.attractors[self.count as usize] = attractor;
+            self.count += 1;
+        } else {
+            self.attractors.rotate_left(1);
+            self.attractors[Self::MAX_ATTRACTORS - 1] = attractor;
+        }
+    }
+
+    /// Removes the most recently added attractor.
+    pub fn pop(&mut self) -> Option<Attractor> {
+        if self.count == 0 {
+            return None;
+        }
+        self.count -= 1;
+        Some(self.attractors[self.count as usize])
+    }
+
+    /// Removes all the attractors.
+    pub fn clear(&mut self) {
+        self.count = 0;
+    }
+
+    /// Sets the strength of every attractor.
+    pub fn set_strength(&mut self, strength: f32) {
+        self.attractors_mut()
+            .iter_mut()
+            .for_each(|attractor| attractor.strength = strength);
+    }
+
+    /// The attractors currently placed.
+    pub fn attractors(&self) -> &[Attractor] {
+        &self.attractors[..self.count as usize]
+    }
+
+    /// The attractors currently placed.
+    pub fn attractors_mut(&mut self) -> &mut [Attractor] {
+        &mut self.attractors[..self.count as usize]
+    }
+
+    /// The number of attractors currently placed.
+    pub fn len(&self) -> usize {
+        self.count as usize
+    }
+
+    /// Whether there are no attractors placed.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Returns the struct as a byte slice.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { wgpu::bytes::from(self) }
+    }
+}
+
+impl Default for AttractorData {
+    fn default() -> Self {
+        Self::new(10.0)
+    }
+}
@@ -0,0 +1,135 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, TryRecvError},
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
+
+use nannou::image::{self, RgbaImage};
+
+/// The extensions of the images a sequence can be made of.
+pub const IMAGE_EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+
+/// A sequence of images played behind the points, so the scans can be composited over filmed
+/// footage.
+///
+/// Videos are played once converted to images, such as with `ffmpeg -i video.mp4 frame_%05d.png`.
+/// The frames are decoded one at a time on a background thread, and the last decoded frame stays
+/// shown until the next one is ready, so the window keeps its frame rate when the disk cannot keep
+/// up with the sequence.
+pub struct ImageSequence {
+    frames: Vec<PathBuf>,
+    /// The number of frames played per second of the timeline.
+    pub frame_rate: f32,
+    /// The frame being decoded, with the receiver of its image.
+    pending: Option<(usize, Receiver<Option<RgbaImage>>)>,
+    /// The last frame decoded.
+    shown: Option<usize>,
+}
+
+impl ImageSequence {
+    /// Opens the sequence holding the image at `path`, which is made of the images of its
+    /// directory with the same extension, in the order of their names.
+    pub fn open(path: impl AsRef<Path>, frame_rate: f32) -> Result<Self, &'static str> {
+        let path = path.as_ref();
+        if !path.is_file() {
+            return Err("The frame does not exist");
+        }
+        let extension = path
+            .extension()
+            .filter(|extension| {
+                IMAGE_EXTENSIONS
+                    .iter()
+                    .any(|image| extension.eq_ignore_ascii_case(image))
+            })
+            .ok_or("The frame is not an image")?;
+
+        let directory = path
+            .parent()
+            .filter(|directory| !directory.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let mut frames: Vec<PathBuf> = std::fs::read_dir(directory)
+            .map_err(|_| "Failed to read the directory of the frames")?
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|frame| frame.extension() == Some(extension))
+            .collect();
+        frames.sort();
+
+        Ok(Self {
+            frames,
+            frame_rate,
+            pending: None,
+            shown: None,
+        })
+    }
+
+    /// The number of frames of the sequence.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Whether the sequence has no frame.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// The frame shown at the given time of the timeline, in seconds, the sequence looping.
+    pub fn frame_at(&self, time: f32) -> usize {
+        (time.max(0.0) * self.frame_rate) as usize % self.frames.len().max(1)
+    }
+
+    /// Returns the image of the frame at the given time of the timeline once it is decoded, to be
+    /// called every frame.
+    ///
+    /// `None` is returned while the frame shown is still current or the next one is being decoded,
+    /// unless `wait` is set, in which case this blocks until the frame at the time is decoded, so
+    /// each captured frame holds its matching image.
+    pub fn poll(&mut self, time: f32, wait: bool) -> Option<RgbaImage> {
+        let index = self.frame_at(time);
+        loop {
+            if self.pending.is_none() && self.shown != Some(index) && !self.is_empty() {
+                self.pending = Some((index, self.decode(index)));
+            }
+            let (decoding, receiver) = self.pending.as_ref()?;
+            let image = match wait {
+                true => receiver.recv().ok().flatten(),
+                false => match receiver.try_recv() {
+                    Ok(image) => image,
+                    Err(TryRecvError::Empty) => return None,
+                    Err(TryRecvError::Disconnected) => None,
+                },
+            };
+            // Frames that cannot be read are skipped instead of being read again every frame
+            self.shown = Some(*decoding);
+            self.pending = None;
+            if !wait || self.shown == Some(index) {
+                return image;
+            }
+        }
+    }
+
+    /// Starts decoding a frame on a new thread.
+    ///
+    /// The web has no threads, so the frame is decoded right away there.
+    fn decode(&self, index: usize) -> Receiver<Option<RgbaImage>> {
+        let (sender, receiver) = mpsc::channel();
+        let path = self.frames[index].clone();
+        let read = move || match image::open(&path) {
+            Ok(image) => Some(image.to_rgba8()),
+            Err(_) => {
+                eprintln!("Failed to read the frame: {}", path.display());
+                None
+            }
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        thread::spawn(move || {
+            sender.send(read()).ok();
+        });
+        #[cfg(target_arch = "wasm32")]
+        sender.send(read()).ok();
+
+        receiver
+    }
+}
@@ -1,6 +1,7 @@
-use nannou::{glam::EulerRot, prelude::*};
+use nannou::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::point::Point;
+use crate::{collision::OccupancyGrid, gravity::GravityData, point::Point};
 
 /// Defines the direction the camera can move in
 pub enum Direction {
@@ -13,6 +14,7 @@ pub enum Direction {
 }
 
 /// A simple first person camera.
+#[derive(Clone, Copy)]
 pub struct Camera {
     /// The position of the camera.
     pub position: Point3,
@@ -64,7 +66,11 @@ impl Camera {
     /// Given a point cloud, choose the proper camera position and direction to fit all points
     pub fn fit_points(&mut self, points: &[Point]) {
         let (min, max) = Point::bounding_box(points);
+        self.fit_bounds(min, max);
+    }
 
+    /// Choose the camera position and direction to fit a bounding box, given its corners.
+    pub fn fit_bounds(&mut self, min: Vec3, max: Vec3) {
         // Compute the center of the bounding box
         let center = (min + max) / 2.0;
 
@@ -77,21 +83,20 @@ impl Camera {
 
         // Move the camera backward along the new forward direction
         self.position = (center - Vec3::Z * distance) * Self::COORD_SCALE;
-        // Look at the center first to get the correct forward direction
-        self.look_at(center);
+        // The camera is placed in its own units, scaled from those of the point cloud
+        self.look_at(center * Self::COORD_SCALE);
     }
 
-    /// Sets the pitch and yaw of the camera to look at a target.
+    /// Sets the pitch and yaw of the camera to look at a target, in the units of the camera.
     pub fn look_at(&mut self, target: Point3) {
-        let current_direction = self.direction();
-        let target_direction = (target - self.position).normalize();
-
-        let rotation = Quat::from_rotation_arc(current_direction, target_direction);
+        let direction = (target - self.position).normalize();
+        if !direction.is_finite() {
+            return;
+        }
 
-        // Extract pitch and yaw from the quaternion
-        let (yaw, pitch, _) = rotation.to_euler(EulerRot::YXZ);
-        self.yaw += yaw;
-        self.pitch += pitch;
+        // Invert `pitch_yaw_to_direction`, the yaw going from +X towards -Z
+        self.pitch = direction.y.asin().clamp(Self::MIN_PITCH, Self::MAX_PITCH);
+        self.yaw = (-direction.z).atan2(direction.x);
     }
 
     /// Increments the pitch and yaw of the camera by a given delta.
@@ -114,7 +119,15 @@ impl Camera {
     }
 
     /// Moves the camera in the given direction by the given amount.
-    pub fn move_towards(&mut self, direction: Direction, amount: f32) {
+    ///
+    /// With obstacles, the camera slides along the occupied voxels of the grid it runs into, or
+    /// stops at them, instead of going through.
+    pub fn move_towards(
+        &mut self,
+        direction: Direction,
+        amount: f32,
+        obstacles: Option<&OccupancyGrid>,
+    ) {
         let direction = match direction {
             Direction::Forward => self.direction(),
             Direction::Backward => -self.direction(),
@@ -137,17 +150,43 @@ impl Camera {
                 Camera::pitch_yaw_to_direction(pitch, self.yaw)
             }
         };
-        self.position += direction * amount;
+        let target = self.position + direction * amount;
+        self.position = match obstacles {
+            Some(grid) => {
+                let to_scene = self.to_scene();
+                let reached = grid.slide(
+                    to_scene.transform_point3(self.position),
+                    to_scene.transform_point3(target),
+                );
+                to_scene.inverse().transform_point3(reached)
+            }
+            None => target,
+        };
+    }
+
+    /// The transformation from the units of the camera to the coordinates of the scene the point
+    /// clouds are placed in, undoing the scale of the camera and the rotation of the world.
+    pub fn to_scene(&self) -> Mat4 {
+        self.config.rotation.inverse() * Mat4::from_scale(Vec3::splat(1.0 / Self::COORD_SCALE))
     }
 
     /// The projection matrix for the camera.
     pub fn projection(&self) -> Mat4 {
-        Mat4::perspective_rh_gl(
+        let projection = Mat4::perspective_rh_gl(
             self.config.fov_y,
             self.config.aspect_ratio,
             self.config.near,
             self.config.far,
-        )
+        );
+        match self.config.off_center {
+            // Stretch the part of the view over the whole target, making the frustum asymmetric
+            Some([left, right, bottom, top]) => {
+                let scale = vec3(2.0 / (right - left), 2.0 / (top - bottom), 1.0);
+                let center = vec3((left + right) / 2.0, (bottom + top) / 2.0, 0.0);
+                Mat4::from_scale(scale) * Mat4::from_translation(-center) * projection
+            }
+            None => projection,
+        }
     }
 
     /// The camera's "view" matrix.
@@ -157,6 +196,32 @@ impl Camera {
         Mat4::look_to_rh(self.position, direction, up)
     }
 
+    /// Casts a ray from the camera through a point on the screen.
+    ///
+    /// The screen point is given in normalized device coordinates, where (-1, -1) is the
+    /// bottom-left corner and (1, 1) is the top-right corner of the window. The returned origin
+    /// and normalized direction are expressed in the coordinates of the point cloud.
+    pub fn screen_ray(&self, ndc: Vec2) -> (Point3, Vec3) {
+        let transforms = self.uniforms();
+        let inverse = (transforms.proj * transforms.view * transforms.world).inverse();
+
+        // Unproject the point on the near and far planes
+        let near = inverse.project_point3(ndc.extend(-1.0));
+        let far = inverse.project_point3(ndc.extend(1.0));
+
+        (near, (far - near).normalize())
+    }
+
+    /// Projects a position of the point cloud onto the window, in the coordinates of nannou centered
+    /// on its middle.
+    ///
+    /// Returns `None` for the positions behind the camera.
+    pub fn to_window(&self, position: Point3, window: Rect) -> Option<Vec2> {
+        let transforms = self.uniforms();
+        let clip = transforms.proj * transforms.view * transforms.world * position.extend(1.0);
+        (clip.w > 0.0).then(|| vec2(clip.x, clip.y) / clip.w * window.wh() / 2.0)
+    }
+
     /// The uniforms for the camera.
     pub fn uniforms(&self) -> CameraTransforms {
         let scale = Mat4::from_scale(Vec3::splat(Self::COORD_SCALE));
@@ -169,13 +234,365 @@ impl Camera {
     }
 }
 
+/// A pose of the camera along a path, along with the progress of the gravity effects.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Keyframe {
+    /// The position of the camera.
+    pub position: Point3,
+    /// Rotation around the x axis in radians.
+    pub pitch: f32,
+    /// Rotation around the y axis in radians.
+    pub yaw: f32,
+    /// The progress of the gravity collapse.
+    pub collapse: f32,
+    /// The progress of the reassembly after the collapse.
+    pub reassemble: f32,
+    /// The time of the pose in seconds, from the first keyframe of the path.
+    pub time: f32,
+}
+
+impl Keyframe {
+    /// Sets the pose of the camera to the keyframe.
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.set_position(self.position);
+        camera.pitch = self.pitch;
+        camera.yaw = self.yaw;
+    }
+
+    /// Sets the progress of the gravity effects to the keyframe.
+    pub fn apply_gravity(&self, gravity: &mut GravityData) {
+        gravity.collapse = self.collapse;
+        gravity.reassemble = self.reassemble;
+    }
+}
+
+/// A path of the camera through recorded keyframes.
+///
+/// The poses between the keyframes are interpolated with Catmull-Rom splines, so the camera
+/// passes smoothly through each of them. The path is played back at a fixed frame rate: each
+/// frame advances the path by the same time, whatever the time taken to render it, so the frames
+/// can be captured into a video.
+pub struct CameraPath {
+    keyframes: Vec<Keyframe>,
+    /// The time at which the first keyframe was recorded.
+    start_time: f32,
+    /// The frame shown by the playback, if playing.
+    frame: Option<u32>,
+    /// The number of frames played per second of the path.
+    pub frame_rate: f32,
+}
+
+impl CameraPath {
+    /// Creates an empty path played back at the given frame rate.
+    pub fn new(frame_rate: f32) -> Self {
+        Self {
+            keyframes: Vec::new(),
+            start_time: 0.0,
+            frame: None,
+            frame_rate,
+        }
+    }
+
+    /// The recorded keyframes, in order of time.
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// Records the pose of the camera and the progress of the gravity effects at the given time, in
+    /// seconds.
+    ///
+    /// The times are kept relative to the first keyframe. The yaw is unwrapped so the path turns
+    /// the shortest way around to each keyframe.
+    pub fn add_keyframe(&mut self, camera: &Camera, gravity: &GravityData, time: f32) {
+        let Some(last) = self.keyframes.last() else {
+            self.start_time = time;
+            self.keyframes.push(Keyframe {
+                position: camera.position,
+                pitch: camera.pitch,
+                yaw: camera.yaw,
+                collapse: gravity.collapse,
+                reassemble: gravity.reassemble,
+                time: 0.0,
+            });
+            return;
+        };
+
+        let keyframe = Keyframe {
+            position: camera.position,
+            pitch: camera.pitch,
+            yaw: unwrap_yaw(last.yaw, camera.yaw),
+            collapse: gravity.collapse,
+            reassemble: gravity.reassemble,
+            time: (time - self.start_time).max(last.time),
+        };
+        self.keyframes.push(keyframe);
+    }
+
+    /// Removes all of the keyframes and stops the playback.
+    pub fn clear(&mut self) {
+        self.keyframes.clear();
+        self.frame = None;
+    }
+
+    /// The time between the first and the last keyframes, in seconds.
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |last| last.time)
+    }
+
+    /// The number of frames of the playback.
+    pub fn frame_count(&self) -> u32 {
+        (self.duration() * self.frame_rate).floor() as u32 + 1
+    }
+
+    /// The pose of the camera at the given time, in seconds from the first keyframe.
+    ///
+    /// The time is clamped to the path, and `None` is returned if the path has no keyframe.
+    pub fn sample(&self, time: f32) -> Option<Keyframe> {
+        let keyframes = &self.keyframes;
+        let last = keyframes.len().checked_sub(1)?;
+        let time = time.clamp(0.0, self.duration());
+
+        // Find the segment holding the time, skipping the keyframes recorded at the same time
+        let i = keyframes
+            .partition_point(|keyframe| keyframe.time <= time)
+            .saturating_sub(1)
+            .min(last.saturating_sub(1));
+        let (k1, k2) = (keyframes[i], keyframes[(i + 1).min(last)]);
+        let span = k2.time - k1.time;
+        if span <= f32::EPSILON {
+            return Some(Keyframe { time, ..k2 });
+        }
+
+        // The end keyframes are repeated to pass through the first and last poses
+        let k0 = keyframes[i.saturating_sub(1)];
+        let k3 = keyframes[(i + 2).min(last)];
+        let t = (time - k1.time) / span;
+        Some(Keyframe {
+            position: catmull_rom(k0.position, k1.position, k2.position, k3.position, t),
+            pitch: catmull_rom(k0.pitch, k1.pitch, k2.pitch, k3.pitch, t)
+                .clamp(Camera::MIN_PITCH, Camera::MAX_PITCH),
+            yaw: catmull_rom(k0.yaw, k1.yaw, k2.yaw, k3.yaw, t),
+            collapse: catmull_rom(k0.collapse, k1.collapse, k2.collapse, k3.collapse, t)
+                .clamp(0.0, 1.0),
+            reassemble: catmull_rom(
+                k0.reassemble,
+                k1.reassemble,
+                k2.reassemble,
+                k3.reassemble,
+                t,
+            )
+            .clamp(0.0, 1.0),
+            time,
+        })
+    }
+
+    /// Starts playing the path from its first keyframe.
+    ///
+    /// Nothing is played unless the path has at least two keyframes.
+    pub fn play(&mut self) {
+        if self.keyframes.len() >= 2 {
+            self.frame = Some(0);
+        }
+    }
+
+    /// Stops the playback.
+    pub fn stop(&mut self) {
+        self.frame = None;
+    }
+
+    /// Whether the path is being played.
+    pub fn is_playing(&self) -> bool {
+        self.frame.is_some()
+    }
+
+    /// Returns the pose of the next frame of the playback, along with its index.
+    ///
+    /// The playback stops after the last frame, and `None` is returned when not playing.
+    pub fn next_frame(&mut self) -> Option<(u32, Keyframe)> {
+        let frame = self.frame?;
+        self.frame = (frame + 1 < self.frame_count()).then_some(frame + 1);
+        let pose = self.sample(frame as f32 / self.frame_rate)?;
+        Some((frame, pose))
+    }
+}
+
+impl Default for CameraPath {
+    fn default() -> Self {
+        Self::new(30.0)
+    }
+}
+
+/// A pose of the camera stored under a number key, to come back to it later.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CameraBookmark {
+    /// The position of the camera.
+    pub position: [f32; 3],
+    /// Rotation around the x axis in radians.
+    pub pitch: f32,
+    /// Rotation around the y axis in radians.
+    pub yaw: f32,
+}
+
+impl CameraBookmark {
+    /// The current pose of the camera.
+    pub fn new(camera: &Camera) -> Self {
+        Self {
+            position: camera.position.to_array(),
+            pitch: camera.pitch,
+            yaw: camera.yaw,
+        }
+    }
+
+    /// Sets the pose of the camera to the bookmark.
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.set_position(Point3::from(self.position));
+        camera.pitch = self.pitch;
+        camera.yaw = self.yaw;
+    }
+}
+
+/// A transition of the camera from its pose to a recalled bookmark.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct BookmarkTransition {
+    from: CameraBookmark,
+    to: CameraBookmark,
+    /// The time since the start of the transition, in seconds.
+    elapsed: f32,
+    /// The length of the transition, in seconds.
+    duration: f32,
+}
+
+/// The bookmarks of the camera, kept in numbered slots.
+///
+/// Recalling a bookmark moves the camera to it over a few frames rather than at once: the
+/// transition eases in and out, and the yaw turns the shortest way around. Only the slots are
+/// serialized, so the bookmarks of a cloud can be saved next to it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CameraBookmarks {
+    slots: [Option<CameraBookmark>; Self::SLOTS],
+    #[serde(skip)]
+    transition: Option<BookmarkTransition>,
+}
+
+impl CameraBookmarks {
+    /// The number of slots, one per number key from 1 to 9.
+    pub const SLOTS: usize = 9;
+
+    /// The bookmark stored in a slot, counted from 0.
+    pub fn get(&self, slot: usize) -> Option<CameraBookmark> {
+        self.slots.get(slot).copied().flatten()
+    }
+
+    /// The slots holding a bookmark, counted from 0.
+    pub fn stored(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..Self::SLOTS).filter(|&slot| self.slots[slot].is_some())
+    }
+
+    /// Stores the pose of the camera in a slot, counted from 0, replacing its bookmark.
+    pub fn store(&mut self, slot: usize, camera: &Camera) {
+        if let Some(bookmark) = self.slots.get_mut(slot) {
+            *bookmark = Some(CameraBookmark::new(camera));
+        }
+    }
+
+    /// Starts moving the camera to the bookmark of a slot, counted from 0, over the given duration
+    /// in seconds.
+    ///
+    /// Returns whether the slot held a bookmark.
+    pub fn recall(&mut self, slot: usize, camera: &Camera, duration: f32) -> bool {
+        let Some(mut to) = self.get(slot) else {
+            return false;
+        };
+        let from = CameraBookmark::new(camera);
+        to.yaw = unwrap_yaw(from.yaw, to.yaw);
+        self.transition = Some(BookmarkTransition {
+            from,
+            to,
+            elapsed: 0.0,
+            duration: duration.max(0.0),
+        });
+        true
+    }
+
+    /// Whether the camera is moving to a recalled bookmark.
+    pub fn is_transitioning(&self) -> bool {
+        self.transition.is_some()
+    }
+
+    /// Stops moving the camera to the recalled bookmark, leaving it where it is.
+    pub fn cancel(&mut self) {
+        self.transition = None;
+    }
+
+    /// Moves the camera along the transition to the recalled bookmark by the time elapsed since
+    /// the last frame, in seconds.
+    ///
+    /// Returns whether the camera moved.
+    pub fn advance(&mut self, camera: &mut Camera, delta_time: f32) -> bool {
+        let Some(transition) = &mut self.transition else {
+            return false;
+        };
+        transition.elapsed += delta_time;
+        let t = if transition.duration > 0.0 {
+            (transition.elapsed / transition.duration).min(1.0)
+        } else {
+            1.0
+        };
+        let eased = t * t * (3.0 - 2.0 * t);
+        let (from, to) = (transition.from, transition.to);
+        let position = Vec3::from(from.position).lerp(Vec3::from(to.position), eased);
+        CameraBookmark {
+            position: position.to_array(),
+            pitch: from.pitch + (to.pitch - from.pitch) * eased,
+            yaw: from.yaw + (to.yaw - from.yaw) * eased,
+        }
+        .apply(camera);
+        if t >= 1.0 {
+            self.transition = None;
+        }
+        true
+    }
+}
+
+/// The yaw equal to `yaw` up to whole turns that is closest to `from`, so turning from one to the
+/// other goes the shortest way around.
+fn unwrap_yaw(from: f32, yaw: f32) -> f32 {
+    let tau = std::f32::consts::TAU;
+    let mut delta = (yaw - from).rem_euclid(tau);
+    if delta > tau / 2.0 {
+        delta -= tau;
+    }
+    from + delta
+}
+
+/// Interpolates between `p1` and `p2` with a uniform Catmull-Rom spline.
+fn catmull_rom<T>(p0: T, p1: T, p2: T, p3: T, t: f32) -> T
+where
+    T: Copy
+        + std::ops::Add<Output = T>
+        + std::ops::Sub<Output = T>
+        + std::ops::Mul<f32, Output = T>,
+{
+    let t2 = t * t;
+    let t3 = t2 * t;
+    (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+        * 0.5
+}
+
 /// The configuration for a camera.
+#[derive(Clone, Copy)]
 pub struct CameraConfig {
     rotation: Mat4,
     aspect_ratio: f32,
     fov_y: f32,
     near: f32,
     far: f32,
+    /// The part of the view drawn, as its left, right, bottom and top edges in normalized device
+    /// coordinates; the whole view when it is not given.
+    off_center: Option<[f32; 4]>,
 }
 
 impl CameraConfig {
@@ -189,6 +606,7 @@ impl CameraConfig {
             fov_y: fov_y.to_radians(),
             near,
             far,
+            off_center: None,
         }
     }
 
@@ -200,18 +618,65 @@ impl CameraConfig {
         self
     }
 
+    /// Sets the angle of rotation around the y axis.
+    ///
+    /// The angle is in degrees.
+    pub fn set_rotation(&mut self, angle: f32) {
+        self.rotation = Mat4::from_rotation_y(angle.to_radians());
+    }
+
+    /// Sets the angle of rotation around a vertical axis going through the pivot.
+    ///
+    /// The angle is in degrees.
+    pub fn set_rotation_about(&mut self, angle: f32, pivot: Point3) {
+        self.rotation = Mat4::from_translation(pivot)
+            * Mat4::from_rotation_y(angle.to_radians())
+            * Mat4::from_translation(-pivot);
+    }
+
     /// Sets the aspect ratio of the camera.
     pub fn with_aspect_ratio(mut self, width: u32, height: u32) -> Self {
         self.aspect_ratio = width as f32 / height as f32;
         self
     }
 
+    /// Sets the aspect ratio of the camera.
+    pub fn set_aspect_ratio(&mut self, width: u32, height: u32) {
+        self.aspect_ratio = width as f32 / height as f32;
+    }
+
+    /// Sets the vertical field of view so the view spans the given horizontal field of view at
+    /// the aspect ratio of the camera.
+    ///
+    /// The angle is in degrees.
+    pub fn set_horizontal_fov(&mut self, fov_x: f32) {
+        let half_width = (fov_x.to_radians() / 2.0).tan();
+        self.fov_y = (half_width / self.aspect_ratio).atan() * 2.0;
+    }
+
     /// Sets the z-near and z-far of the camera.
     pub fn with_range(mut self, near: f32, far: f32) -> Self {
         self.near = near;
         self.far = far;
         self
     }
+
+    /// Draws only a part of the view, given by its left, right, bottom and top edges in normalized
+    /// device coordinates, with an asymmetric frustum.
+    ///
+    /// The part fills the whole target, so the tiles of a larger image, each drawn with its own
+    /// part of the view at the aspect ratio of the whole image, line up at native resolution.
+    pub fn with_off_center_projection(mut self, edges: [f32; 4]) -> Self {
+        self.off_center = Some(edges);
+        self
+    }
+
+    /// Draws only a part of the view, or the whole view when `None`.
+    ///
+    /// See [`CameraConfig::with_off_center_projection`].
+    pub fn set_off_center_projection(&mut self, edges: Option<[f32; 4]>) {
+        self.off_center = edges;
+    }
 }
 
 impl Default for CameraConfig {
@@ -235,3 +700,326 @@ impl CameraTransforms {
         unsafe { wgpu::bytes::from(self) }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+
+    use super::*;
+
+    const EPSILON: f32 = 1e-5;
+
+    /// A camera seeing 90 degrees vertically on a 4:3 screen, from 0.1 to 100 units.
+    fn camera() -> Camera {
+        Camera::new(CameraConfig::new((800, 600), 90.0, (0.1, 100.0)))
+    }
+
+    fn assert_vec_eq(actual: Vec3, expected: Vec3) {
+        assert!(
+            actual.abs_diff_eq(expected, EPSILON),
+            "{actual} is not {expected}"
+        );
+    }
+
+    fn assert_mat_eq(actual: Mat4, expected: [f32; 16]) {
+        let expected = Mat4::from_cols_array(&expected);
+        assert!(
+            actual.abs_diff_eq(expected, EPSILON),
+            "{actual} is not {expected}"
+        );
+    }
+
+    #[test]
+    fn the_yaw_turns_the_camera_left() {
+        let mut camera = camera();
+        // The camera starts looking down +Z, the yaw being measured from +X towards -Z
+        assert_vec_eq(camera.direction(), Vec3::Z);
+        camera.yaw = 0.0;
+        assert_vec_eq(camera.direction(), Vec3::X);
+        camera.yaw = FRAC_PI_2;
+        assert_vec_eq(camera.direction(), -Vec3::Z);
+
+        // Facing +Z with +Y up, the left of the camera is +X
+        let mut camera = Camera::new(CameraConfig::default());
+        camera.update_yaw(0.1);
+        assert!(camera.direction().x > 0.0);
+        camera.update_yaw(-0.2);
+        assert!(camera.direction().x < 0.0);
+    }
+
+    #[test]
+    fn the_pitch_tilts_the_camera_up_and_stops_at_the_poles() {
+        let mut camera = camera();
+        camera.update_pitch(FRAC_PI_4);
+        assert_vec_eq(camera.direction(), vec3(0.0, 1.0, 1.0).normalize());
+        camera.update_pitch(FRAC_PI_2);
+        assert_eq!(camera.pitch, Camera::MAX_PITCH);
+        camera.update_pitch(-4.0);
+        assert_eq!(camera.pitch, Camera::MIN_PITCH);
+    }
+
+    #[test]
+    fn the_camera_moves_along_its_own_axes() {
+        let cases = [
+            (Direction::Forward, Vec3::Z),
+            (Direction::Backward, -Vec3::Z),
+            (Direction::Left, Vec3::X),
+            (Direction::Right, -Vec3::X),
+            (Direction::Up, Vec3::Y),
+            (Direction::Down, -Vec3::Y),
+        ];
+        for (direction, expected) in cases {
+            let mut camera = camera().with_position(Vec3::ZERO);
+            camera.move_towards(direction, 2.0, None);
+            assert_vec_eq(camera.position, expected * 2.0);
+        }
+
+        // Strafing stays level when the camera looks up
+        let mut camera = camera().with_position(Vec3::ZERO);
+        camera.update_pitch(FRAC_PI_4);
+        camera.move_towards(Direction::Right, 1.0, None);
+        assert_vec_eq(camera.position, -Vec3::X);
+    }
+
+    #[test]
+    fn the_view_matrix_is_stable() {
+        assert_mat_eq(
+            camera().view(),
+            [
+                -1.0, 0.0, 0.0, 0.0, //
+                0.0, 1.0, 0.0, 0.0, //
+                0.0, 0.0, -1.0, 0.0, //
+                0.0, 0.0, -1.0, 1.0,
+            ],
+        );
+
+        let mut camera = camera().with_position(vec3(1.0, 2.0, 3.0));
+        camera.yaw = 0.0;
+        assert_mat_eq(
+            camera.view(),
+            [
+                0.0, 0.0, -1.0, 0.0, //
+                0.0, 1.0, 0.0, 0.0, //
+                1.0, 0.0, 0.0, 0.0, //
+                -3.0, -2.0, 1.0, 1.0,
+            ],
+        );
+    }
+
+    #[test]
+    fn the_projection_matrix_is_stable() {
+        let (near, far) = (0.1, 100.0);
+        assert_mat_eq(
+            camera().projection(),
+            [
+                0.75,
+                0.0,
+                0.0,
+                0.0, //
+                0.0,
+                1.0,
+                0.0,
+                0.0, //
+                0.0,
+                0.0,
+                (far + near) / (near - far),
+                -1.0, //
+                0.0,
+                0.0,
+                2.0 * far * near / (near - far),
+                0.0,
+            ],
+        );
+    }
+
+    #[test]
+    fn the_uniforms_scale_the_cloud_to_the_camera() {
+        let transforms = camera().uniforms();
+        assert_eq!(transforms.world, Mat4::IDENTITY);
+        assert_mat_eq(
+            transforms.view,
+            [
+                -0.01, 0.0, 0.0, 0.0, //
+                0.0, 0.01, 0.0, 0.0, //
+                0.0, 0.0, -0.01, 0.0, //
+                0.0, 0.0, -1.0, 1.0,
+            ],
+        );
+
+        // A point 100 units down +Z is 2 units in front of the camera, at the center of the screen
+        let view = transforms.view.transform_point3(vec3(0.0, 0.0, 100.0));
+        assert_vec_eq(view, vec3(0.0, 0.0, -2.0));
+        let ndc = transforms.proj.project_point3(view);
+        assert_vec_eq(ndc * vec3(1.0, 1.0, 0.0), Vec3::ZERO);
+    }
+
+    #[test]
+    fn the_rotation_of_the_world_turns_about_the_pivot() {
+        let mut config = CameraConfig::default();
+        config.set_rotation_about(90.0, vec3(1.0, 0.0, 0.0));
+        let pivot = config.rotation.transform_point3(vec3(1.0, 0.0, 0.0));
+        assert_vec_eq(pivot, vec3(1.0, 0.0, 0.0));
+        let point = config.rotation.transform_point3(vec3(2.0, 0.0, 0.0));
+        assert_vec_eq(point, vec3(1.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn off_center_tiles_split_the_view() {
+        // A point at the center of the top-right quarter of the view, 200 units in front of the
+        // camera, whose right is -X as it looks down +Z
+        let point = vec3(-0.5 * 2.0 / 0.75, 0.5 * 2.0, 1.0) * 100.0;
+        let full = camera().uniforms();
+        let ndc = (full.proj * full.view).project_point3(point);
+        assert_vec_eq(ndc * vec3(1.0, 1.0, 0.0), vec3(0.5, 0.5, 0.0));
+
+        // Drawn at the center of the top-right tile of a 2x2 grid, at the same depth
+        let mut camera = camera();
+        camera.config = camera
+            .config
+            .with_off_center_projection([0.0, 1.0, 0.0, 1.0]);
+        let tile = camera.uniforms();
+        let tile_ndc = (tile.proj * tile.view).project_point3(point);
+        assert_vec_eq(tile_ndc, vec3(0.0, 0.0, ndc.z));
+
+        // And outside the bottom-left tile
+        camera
+            .config
+            .set_off_center_projection(Some([-1.0, 0.0, -1.0, 0.0]));
+        let tile = camera.uniforms();
+        let tile_ndc = (tile.proj * tile.view).project_point3(point);
+        assert_vec_eq(tile_ndc, vec3(2.0, 2.0, ndc.z));
+    }
+
+    #[test]
+    fn turned_cameras_meet_at_their_edges() {
+        // A point 45 degrees to the right of the camera, which looks down +Z with its right to -X
+        let mut camera = camera();
+        camera.config.set_horizontal_fov(90.0);
+        assert!((camera.config.fov_y - 0.75f32.atan() * 2.0).abs() < EPSILON);
+        let eye = camera.position / Camera::COORD_SCALE;
+        let point = eye + vec3(-1.0, 0.0, 1.0) * 50.0;
+        let transforms = camera.uniforms();
+        let ndc = (transforms.proj * transforms.view).project_point3(point);
+        assert_vec_eq(ndc * vec3(1.0, 1.0, 0.0), vec3(1.0, 0.0, 0.0));
+
+        // On the left edge of the camera turned a quarter turn to the right
+        camera.update_yaw(-FRAC_PI_2);
+        let transforms = camera.uniforms();
+        let turned_ndc = (transforms.proj * transforms.view).project_point3(point);
+        assert_vec_eq(turned_ndc, vec3(-1.0, 0.0, ndc.z));
+    }
+
+    #[test]
+    fn look_at_points_the_camera_at_the_target() {
+        let targets = [
+            vec3(0.0, 0.0, 5.0),
+            vec3(3.0, 0.0, 0.0),
+            vec3(-2.0, 4.0, -1.0),
+            vec3(1.0, -3.0, 2.0),
+        ];
+        for target in targets {
+            let mut camera = camera().with_position(Vec3::ZERO);
+            camera.look_at(target);
+            assert_vec_eq(camera.direction(), target.normalize());
+        }
+    }
+
+    #[test]
+    fn fitting_centers_the_points_on_the_screen() {
+        let points = [
+            Point::new([100.0, -50.0, 20.0], [255; 4]),
+            Point::new([300.0, 50.0, 60.0], [255; 4]),
+        ];
+        let mut camera = camera();
+        camera.fit_points(&points);
+
+        // The bounding sphere fills the 90 degrees of the view, its radius back from its center
+        let radius = vec3(100.0, 50.0, 20.0).length();
+        assert_vec_eq(camera.position, vec3(200.0, 0.0, 40.0 - radius) * 0.01);
+        assert_vec_eq(camera.direction(), Vec3::Z);
+
+        let transforms = camera.uniforms();
+        let center = (transforms.proj * transforms.view).project_point3(vec3(200.0, 0.0, 40.0));
+        assert_vec_eq(center * vec3(1.0, 1.0, 0.0), Vec3::ZERO);
+    }
+
+    #[test]
+    fn recalled_bookmarks_ease_the_camera_the_shortest_way_around() {
+        let mut bookmarks = CameraBookmarks::default();
+        let mut camera = camera().with_position(vec3(2.0, 0.0, 0.0));
+        camera.yaw = 0.1;
+        bookmarks.store(0, &camera);
+        assert!(!bookmarks.recall(1, &camera, 1.0));
+
+        camera.set_position(Vec3::ZERO);
+        camera.yaw = std::f32::consts::TAU - 0.1;
+        assert!(bookmarks.recall(0, &camera, 1.0));
+
+        // Halfway through, the eased transition is halfway, having turned up across 0
+        assert!(bookmarks.advance(&mut camera, 0.5));
+        assert_vec_eq(camera.position, vec3(1.0, 0.0, 0.0));
+        assert!((camera.yaw - std::f32::consts::TAU).abs() < EPSILON);
+        assert!(bookmarks.is_transitioning());
+
+        assert!(bookmarks.advance(&mut camera, 0.6));
+        assert_vec_eq(camera.position, vec3(2.0, 0.0, 0.0));
+        assert!(!bookmarks.is_transitioning());
+        assert!(!bookmarks.advance(&mut camera, 0.1));
+    }
+
+    #[test]
+    fn the_camera_slides_along_the_walls_of_the_cloud() {
+        // A wall of points across the x axis, 50 units from the origin of the cloud
+        let wall: Vec<Point> = (-50..=50)
+            .flat_map(|y| (-50..=50).map(move |z| (y, z)))
+            .flat_map(|(y, z)| {
+                let point = Point::new([50.0, y as f32, z as f32], [255; 4]);
+                [point, point]
+            })
+            .chain([
+                Point::new([-50.0, -50.0, -50.0], [255; 4]),
+                Point::new([-50.0, -50.0, -50.0], [255; 4]),
+            ])
+            .collect();
+        let grid = OccupancyGrid::build(&wall, 100);
+
+        // Facing the wall, the camera stops before it
+        let mut facing = camera().with_position(Vec3::ZERO);
+        facing.yaw = 0.0;
+        facing.move_towards(Direction::Forward, 1.0, Some(&grid));
+        let x = facing.position.x / Camera::COORD_SCALE;
+        assert!((40.0..50.0).contains(&x), "{x} is not before the wall");
+
+        // Heading diagonally into the wall, the camera slides along it
+        let mut diagonal = camera().with_position(Vec3::ZERO);
+        diagonal.yaw = -FRAC_PI_4;
+        diagonal.move_towards(Direction::Forward, 0.6, Some(&grid));
+        assert!(diagonal.position.x / Camera::COORD_SCALE < 50.0);
+        assert!((diagonal.position.z - 0.6 * FRAC_PI_4.sin()).abs() < EPSILON);
+
+        // Without obstacles, it goes through
+        let mut free = camera().with_position(Vec3::ZERO);
+        free.yaw = 0.0;
+        free.move_towards(Direction::Forward, 1.0, None);
+        assert_vec_eq(free.position, Vec3::X);
+    }
+
+    #[test]
+    fn positions_along_a_screen_ray_project_back_onto_its_pixel() {
+        let window = Rect::from_w_h(800.0, 600.0);
+        let mut camera = camera();
+        camera.update_yaw(0.3);
+        camera.update_pitch(-0.2);
+        let pixel = vec2(120.0, -80.0);
+        let (origin, direction) = camera.screen_ray(pixel / vec2(400.0, 300.0));
+        let projected = camera
+            .to_window(origin + direction * 150.0, window)
+            .unwrap();
+        assert!(
+            projected.abs_diff_eq(pixel, 1e-2),
+            "{projected} is not {pixel}"
+        );
+
+        assert_eq!(camera.to_window(origin - direction * 150.0, window), None);
+    }
+}
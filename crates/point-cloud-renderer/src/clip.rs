@@ -0,0 +1,95 @@
+use nannou::{prelude::*, wgpu};
+use serde::{Deserialize, Serialize};
+
+use crate::camera::Camera;
+
+/// An axis-aligned box the points are cropped to, like six clipping planes, to slice through the
+/// walls of a scan.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ClipBox {
+    /// Whether the points outside of the box are hidden.
+    pub enabled: bool,
+    /// The corner of the box with the lowest coordinates, in the coordinates of the point cloud.
+    pub min: [f32; 3],
+    /// The corner of the box with the highest coordinates, in the coordinates of the point cloud.
+    pub max: [f32; 3],
+    /// Whether the outline of the box is drawn over the points.
+    pub show_outline: bool,
+}
+
+impl Default for ClipBox {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min: [-100.0; 3],
+            max: [100.0; 3],
+            show_outline: true,
+        }
+    }
+}
+
+impl ClipBox {
+    /// Fits the box to a bounding box, so it starts out keeping every point.
+    pub fn fit(&mut self, (min, max): (Point3, Point3)) {
+        self.min = min.to_array();
+        self.max = max.to_array();
+    }
+
+    /// Returns the uniforms of the box.
+    pub fn uniforms(&self) -> ClipUniforms {
+        ClipUniforms {
+            min: self.min,
+            enabled: self.enabled as u32,
+            max: self.max,
+            _padding: 0,
+        }
+    }
+
+    /// Projects the edges of the box to the window, as segments in the coordinates of the window
+    /// centered on its middle.
+    ///
+    /// The edges reaching behind the camera are left out.
+    pub fn outline(&self, camera: &Camera, window: Rect) -> Vec<[Vec2; 2]> {
+        let transforms = camera.uniforms();
+        let view_proj = transforms.proj * transforms.view * transforms.world;
+        let corner = |i: usize| {
+            let pick = |axis: usize| match i >> axis & 1 {
+                0 => self.min[axis],
+                _ => self.max[axis],
+            };
+            view_proj * vec4(pick(0), pick(1), pick(2), 1.0)
+        };
+        let project = |clip: Vec4| vec2(clip.x, clip.y) / clip.w * window.wh() / 2.0;
+
+        // The corners are indexed by the bits of their axes, so the edges join the corners
+        // differing by a single bit
+        (0..8)
+            .flat_map(|i| (0..3).map(move |axis| (i, i | 1 << axis)))
+            .filter(|(start, end)| start != end)
+            .filter_map(|(start, end)| {
+                let (start, end) = (corner(start), corner(end));
+                (start.w > 0.0 && end.w > 0.0).then(|| [project(start), project(end)])
+            })
+            .collect()
+    }
+}
+
+/// The uniforms of the clip box.
+///
+/// The struct is sent as-is to the shaders, so its layout must match the `Clip` struct declared in
+/// `render.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct ClipUniforms {
+    min: [f32; 3],
+    enabled: u32,
+    max: [f32; 3],
+    _padding: u32,
+}
+
+impl ClipUniforms {
+    /// Returns the struct as a byte slice.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { wgpu::bytes::from(self) }
+    }
+}
@@ -0,0 +1,153 @@
+use nannou::prelude::*;
+
+use crate::point::Point;
+
+/// A coarse grid of the voxels of a point cloud holding points, which the camera collides with
+/// so it stops at the walls of a scan instead of flying through them.
+///
+/// A voxel is occupied once enough points fall in it, so the stray points of a noisy scan do not
+/// leave obstacles floating in its rooms. Outside of the bounds of the cloud, the space is free.
+///
+/// The grid is built in the coordinates of the cloud and placed in the scene along with it, so the
+/// positions it is queried with are in the coordinates of the scene.
+pub struct OccupancyGrid {
+    /// The corner of the grid with the lowest coordinates, in the coordinates of the cloud.
+    min: Vec3,
+    /// The size of the side of a voxel, in the coordinates of the cloud.
+    voxel_size: f32,
+    /// The number of voxels along each axis.
+    dims: [usize; 3],
+    /// The occupancy of the voxels, one bit per voxel, ordered by x, then y, then z.
+    occupied: Vec<u64>,
+    /// The number of voxels kept free around the camera, so it stops before its near plane enters
+    /// a wall.
+    pub padding: usize,
+    /// The transformation from the coordinates of the scene to those of the cloud.
+    to_cloud: Mat4,
+}
+
+impl OccupancyGrid {
+    /// The number of points a voxel must hold to be occupied.
+    pub const MIN_POINTS: u16 = 2;
+
+    /// Builds the grid over the points, with `resolution` voxels along the longest side of their
+    /// bounding box.
+    pub fn build(points: &[Point], resolution: usize) -> Self {
+        let (min, max) = Point::bounding_box(points);
+        let resolution = resolution.max(1);
+        let extent = (max - min).max_element();
+        let voxel_size = if extent.is_finite() && extent > 0.0 {
+            extent / resolution as f32
+        } else {
+            1.0
+        };
+        let dims = if points.is_empty() {
+            [0; 3]
+        } else {
+            ((max - min) / voxel_size)
+                .to_array()
+                .map(|side| side as usize + 1)
+        };
+
+        // Count the points of each voxel, only keeping the voxels holding enough of them
+        let voxel_count = dims.iter().product::<usize>();
+        let mut counts = vec![0u16; voxel_count];
+        let mut grid = Self {
+            min,
+            voxel_size,
+            dims,
+            occupied: vec![0; voxel_count.div_ceil(64)],
+            padding: 1,
+            to_cloud: Mat4::IDENTITY,
+        };
+        for point in points {
+            if let Some(index) = grid.index_in_cloud(Vec3::from(point.position)) {
+                counts[index] = counts[index].saturating_add(1);
+            }
+        }
+        for (index, _) in counts
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count >= Self::MIN_POINTS)
+        {
+            grid.occupied[index / 64] |= 1 << (index % 64);
+        }
+        grid
+    }
+
+    /// The size of the side of a voxel, in the coordinates of the cloud.
+    pub fn voxel_size(&self) -> f32 {
+        self.voxel_size
+    }
+
+    /// Places the grid in the scene with the transform of its cloud, given as the matrix from the
+    /// coordinates of the cloud to those of the scene.
+    pub fn set_placement(&mut self, placement: Mat4) {
+        self.to_cloud = placement.inverse();
+    }
+
+    /// Whether the voxel holding a position, in the coordinates of the scene, is occupied.
+    pub fn is_occupied(&self, position: Vec3) -> bool {
+        self.is_occupied_in_cloud(self.to_cloud.transform_point3(position))
+    }
+
+    /// Whether the camera at a position, in the coordinates of the scene, would be within the
+    /// padding of an occupied voxel.
+    pub fn is_blocked(&self, position: Vec3) -> bool {
+        let position = self.to_cloud.transform_point3(position);
+        let padding = self.padding as isize;
+        (-padding..=padding).any(|x| {
+            (-padding..=padding).any(|y| {
+                (-padding..=padding).any(|z| {
+                    let offset = vec3(x as f32, y as f32, z as f32) * self.voxel_size;
+                    self.is_occupied_in_cloud(position + offset)
+                })
+            })
+        })
+    }
+
+    /// How far the camera gets moving from `from` towards `to`, in the coordinates of the scene.
+    ///
+    /// The movement is split into steps smaller than a voxel so it cannot skip over a thin wall.
+    /// At each step, the axes the camera would be blocked along are dropped from the movement, so
+    /// it slides along the walls it runs into rather than stopping dead. A camera already inside
+    /// a blocked voxel moves freely, so it can leave it.
+    pub fn slide(&self, from: Vec3, to: Vec3) -> Vec3 {
+        if self.is_blocked(from) {
+            return to;
+        }
+        let movement = to - from;
+        let span = self.to_cloud.transform_vector3(movement);
+        let steps = (span.abs().max_element() / (self.voxel_size / 2.0))
+            .ceil()
+            .max(1.0);
+        let step = movement / steps;
+        let mut position = from;
+        for _ in 0..steps as usize {
+            for axis in 0..3 {
+                let mut next = position;
+                next[axis] += step[axis];
+                if !self.is_blocked(next) {
+                    position = next;
+                }
+            }
+        }
+        position
+    }
+
+    /// Whether the voxel holding a position, in the coordinates of the cloud, is occupied.
+    fn is_occupied_in_cloud(&self, position: Vec3) -> bool {
+        self.index_in_cloud(position)
+            .is_some_and(|index| self.occupied[index / 64] & (1 << (index % 64)) != 0)
+    }
+
+    /// The index of the voxel holding a position, in the coordinates of the cloud, if it is within
+    /// the grid.
+    fn index_in_cloud(&self, position: Vec3) -> Option<usize> {
+        let cell = ((position - self.min) / self.voxel_size).floor();
+        let [x, y, z] = cell.to_array();
+        let inside = |value: f32, dim: usize| value >= 0.0 && (value as usize) < dim;
+        (inside(x, self.dims[0]) && inside(y, self.dims[1]) && inside(z, self.dims[2]))
+            .then(|| (z as usize * self.dims[1] + y as usize) * self.dims[0] + x as usize)
+    }
+}
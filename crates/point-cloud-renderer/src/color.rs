@@ -0,0 +1,72 @@
+use nannou::image::{ImageBuffer, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+/// An image of linear RGBA channels in floating point, as rendered before it is encoded to sRGB,
+/// for the outputs that keep more than 8 bits of precision.
+pub type LinearImage = ImageBuffer<Rgba<f32>, Vec<f32>>;
+
+/// The encoding of the channels of a color.
+///
+/// The renderer works in linear space, where light adds up: the colors of the points are stored
+/// linear, blended linearly by the GPU and only encoded to sRGB when the frame is presented or
+/// captured. Colors read from or written to files, and 8-bit colors in general, are sRGB.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorSpace {
+    /// The channels are gamma encoded, as stored in images and point cloud files.
+    Srgb,
+    /// The channels are proportional to the intensity of the light.
+    Linear,
+}
+
+impl ColorSpace {
+    /// Converts an RGBA color in the range [0, 1] from this space to linear space.
+    ///
+    /// The alpha channel is always linear, so it is kept as is.
+    pub fn to_linear(self, [red, green, blue, alpha]: [f32; 4]) -> [f32; 4] {
+        match self {
+            ColorSpace::Srgb => {
+                let [red, green, blue] = [red, green, blue].map(srgb_to_linear);
+                [red, green, blue, alpha]
+            }
+            ColorSpace::Linear => [red, green, blue, alpha],
+        }
+    }
+
+    /// Converts an RGBA color in the range [0, 1] from linear space to this space.
+    pub fn from_linear(self, [red, green, blue, alpha]: [f32; 4]) -> [f32; 4] {
+        match self {
+            ColorSpace::Srgb => {
+                let [red, green, blue] = [red, green, blue].map(linear_to_srgb);
+                [red, green, blue, alpha]
+            }
+            ColorSpace::Linear => [red, green, blue, alpha],
+        }
+    }
+}
+
+/// Decodes a channel of an sRGB color, following the piecewise transfer function of the standard.
+pub fn srgb_to_linear(channel: f32) -> f32 {
+    if channel <= 0.04045 {
+        channel / 12.92
+    } else {
+        ((channel + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encodes a linear channel to sRGB, the inverse of [`srgb_to_linear`].
+pub fn linear_to_srgb(channel: f32) -> f32 {
+    if channel <= 0.0031308 {
+        channel * 12.92
+    } else {
+        1.055 * channel.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Encodes a linear image to an 8-bit sRGB image, clamping the channels to the range [0, 1].
+pub fn encode_srgb(image: &LinearImage) -> RgbaImage {
+    let quantize = |channel: f32| (channel.clamp(0.0, 1.0) * 255.0).round() as u8;
+    RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+        let Rgba(color) = *image.get_pixel(x, y);
+        Rgba(ColorSpace::Srgb.from_linear(color).map(quantize))
+    })
+}
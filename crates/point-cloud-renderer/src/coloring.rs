@@ -0,0 +1,150 @@
+use nannou::wgpu;
+use serde::{Deserialize, Serialize};
+
+use crate::{color::srgb_to_linear, point::Point};
+
+/// What the color of the points shows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorMode {
+    /// The colors of the points, as loaded.
+    Rgb,
+    /// The height of the points, mapped through the ramp over the range.
+    Elevation,
+    /// The brightness of the points, mapped through the ramp. The intensity of scans without
+    /// colors is loaded as gray, so this recolors it.
+    Intensity,
+    /// The distance from the points to the camera, mapped through the ramp over the range.
+    Distance,
+}
+
+/// The color ramps the stops of the ramp can be reset to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RampPreset {
+    Viridis,
+    Turbo,
+    Magma,
+    Grayscale,
+}
+
+impl RampPreset {
+    /// Every preset, in the order they are listed.
+    pub const ALL: [RampPreset; 4] = [
+        RampPreset::Viridis,
+        RampPreset::Turbo,
+        RampPreset::Magma,
+        RampPreset::Grayscale,
+    ];
+
+    /// The name of the preset, as listed to the user.
+    pub fn name(self) -> &'static str {
+        match self {
+            RampPreset::Viridis => "Viridis",
+            RampPreset::Turbo => "Turbo",
+            RampPreset::Magma => "Magma",
+            RampPreset::Grayscale => "Grayscale",
+        }
+    }
+
+    /// The evenly spaced stops of the ramp, in linear space.
+    pub fn stops(self) -> [[f32; 3]; RAMP_STOPS] {
+        // The ramps are published in sRGB
+        let srgb: [u32; RAMP_STOPS] = match self {
+            RampPreset::Viridis => [
+                0x440154, 0x46327e, 0x365c8d, 0x277f8e, 0x1fa187, 0x4ac16d, 0xa0da39, 0xfde725,
+            ],
+            RampPreset::Turbo => [
+                0x30123b, 0x4662d7, 0x36aaf9, 0x1ae4b6, 0x72fe5e, 0xc8ef34, 0xfb8022, 0x7a0403,
+            ],
+            RampPreset::Magma => [
+                0x000004, 0x1c1044, 0x4f127b, 0x812581, 0xb5367a, 0xe55964, 0xfb8761, 0xfcfdbf,
+            ],
+            RampPreset::Grayscale => [
+                0x000000, 0x242424, 0x494949, 0x6d6d6d, 0x929292, 0xb6b6b6, 0xdbdbdb, 0xffffff,
+            ],
+        };
+        srgb.map(|hex| {
+            [16, 8, 0].map(|shift| srgb_to_linear(((hex >> shift) & 0xff) as f32 / 255.0))
+        })
+    }
+}
+
+/// The number of stops of the color ramp; must match `RAMP_STOPS` in `render.wgsl`.
+pub const RAMP_STOPS: usize = 8;
+
+/// How the points are colored.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ColorSettings {
+    pub mode: ColorMode,
+    /// The evenly spaced colors the values are mapped to, in linear space like the colors of the
+    /// points.
+    pub stops: [[f32; 3]; RAMP_STOPS],
+    /// The values mapped to the first and last stops in the elevation and distance modes, in the
+    /// units of the point cloud.
+    pub range: [f32; 2],
+}
+
+impl Default for ColorSettings {
+    fn default() -> Self {
+        Self {
+            mode: ColorMode::Rgb,
+            stops: RampPreset::Viridis.stops(),
+            range: [-100.0, 100.0],
+        }
+    }
+}
+
+impl ColorSettings {
+    /// Fits the range to the heights of the points, so the ramp spans the whole cloud.
+    pub fn fit_elevation(&mut self, points: &[Point]) {
+        let (min, max) = Point::bounding_box(points);
+        self.range = [min.y, max.y];
+    }
+
+    /// The color of the ramp at `t`, from 0 for the first stop to 1 for the last, interpolated
+    /// between the stops like the shaders do.
+    pub fn ramp(&self, t: f32) -> [f32; 3] {
+        let x = t.clamp(0.0, 1.0) * (RAMP_STOPS - 1) as f32;
+        let i = (x as usize).min(RAMP_STOPS - 2);
+        let (low, high) = (self.stops[i], self.stops[i + 1]);
+        let f = x - i as f32;
+        [0, 1, 2].map(|c| low[c] + (high[c] - low[c]) * f)
+    }
+
+    /// Returns the uniforms of the settings.
+    pub fn uniforms(&self) -> ColorUniforms {
+        let mode = match self.mode {
+            ColorMode::Rgb => 0,
+            ColorMode::Elevation => 1,
+            ColorMode::Intensity => 2,
+            ColorMode::Distance => 3,
+        };
+        ColorUniforms {
+            stops: self.stops.map(|[red, green, blue]| [red, green, blue, 1.0]),
+            mode,
+            low: self.range[0],
+            high: self.range[1],
+            _padding: 0,
+        }
+    }
+}
+
+/// The uniforms of the coloring of the points.
+///
+/// The struct is sent as-is to the shaders, so its layout must match the `Coloring` struct declared
+/// in `render.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct ColorUniforms {
+    stops: [[f32; 4]; RAMP_STOPS],
+    mode: u32,
+    low: f32,
+    high: f32,
+    _padding: u32,
+}
+
+impl ColorUniforms {
+    /// Returns the struct as a byte slice.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { wgpu::bytes::from(self) }
+    }
+}
@@ -0,0 +1,38 @@
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator,
+};
+
+use crate::{coloring::ColorSettings, kdtree::KdTree, point::Point};
+
+/// The distance from each point to the nearest point of a reference scan of the same space.
+///
+/// Comparing two scans taken at different times shows what changed in between, such as the
+/// progress of a construction or the erosion of a cliff, as the points far from the other scan.
+pub fn distances_to(points: &[Point], reference: &[Point]) -> Vec<f32> {
+    let tree = KdTree::new(reference);
+    points
+        .par_iter()
+        .map(|point| tree.nearest_distance(point.position).unwrap_or(0.0))
+        .collect()
+}
+
+/// Colors the points by their distance to a reference scan through the ramp of the settings, the
+/// distances from zero to `max_distance` spanning the whole ramp.
+///
+/// The colors are replaced, so the points keep showing the change while the other settings are
+/// edited.
+pub fn color_by_change(
+    points: &mut [Point],
+    reference: &[Point],
+    settings: &ColorSettings,
+    max_distance: f32,
+) {
+    let distances = distances_to(points, reference);
+    points
+        .par_iter_mut()
+        .zip(distances)
+        .for_each(|(point, distance)| {
+            let [red, green, blue] = settings.ramp(distance / max_distance.max(f32::EPSILON));
+            point.color = [red, green, blue, point.color[3]];
+        });
+}
@@ -0,0 +1,155 @@
+use nannou::wgpu;
+use serde::{Deserialize, Serialize};
+
+/// A deformation of the points, run as its own compute pass.
+///
+/// Each pass moves the points from where the previous passes left them, so the order of the
+/// effects changes the result; the springs pulling the points back to rest always run last.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Effect {
+    /// The noise wind, with its gusts.
+    Wind,
+    /// A divergence-free noise field, swirling the points without bunching them up.
+    CurlNoise,
+    /// The pull of the attractors.
+    Attractors,
+    /// The displacement by the bands of the spectrum of the audio.
+    Spectrum,
+    /// The shockwave sent by the beats of the audio.
+    Pulse,
+    /// The wavefronts sent by the onsets of the audio, travelling through the points.
+    Impulses,
+    /// The points collapsing to the floor or to their centroid under gravity.
+    Collapse,
+    /// The points springing back to their rest, past it and back.
+    Reassemble,
+}
+
+impl Effect {
+    /// Every effect, in the default order.
+    pub const ALL: [Effect; 8] = [
+        Effect::Wind,
+        Effect::CurlNoise,
+        Effect::Attractors,
+        Effect::Spectrum,
+        Effect::Pulse,
+        Effect::Impulses,
+        Effect::Collapse,
+        Effect::Reassemble,
+    ];
+
+    /// The name of the effect, as listed to the user.
+    pub fn name(self) -> &'static str {
+        match self {
+            Effect::Wind => "Wind",
+            Effect::CurlNoise => "Curl noise",
+            Effect::Attractors => "Attractors",
+            Effect::Spectrum => "Spectrum",
+            Effect::Pulse => "Audio shockwave",
+            Effect::Impulses => "Audio impulses",
+            Effect::Collapse => "Gravity collapse",
+            Effect::Reassemble => "Reassemble",
+        }
+    }
+
+    /// The entry point of the pass of the effect in `compute.wgsl`.
+    pub(crate) fn entry_point(self) -> &'static str {
+        match self {
+            Effect::Wind => "cs_wind",
+            Effect::CurlNoise => "cs_curl_noise",
+            Effect::Attractors => "cs_attractors",
+            Effect::Spectrum => "cs_spectrum",
+            Effect::Pulse => "cs_pulse",
+            Effect::Impulses => "cs_impulses",
+            Effect::Collapse => "cs_collapse",
+            Effect::Reassemble => "cs_reassemble",
+        }
+    }
+}
+
+/// An effect of the stack, which can be turned off without losing its place.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EffectLayer {
+    pub effect: Effect,
+    pub enabled: bool,
+}
+
+/// The effects deforming the points, in the order their passes run.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EffectStack {
+    pub layers: Vec<EffectLayer>,
+}
+
+impl Default for EffectStack {
+    /// Every effect in the default order, the curl noise being off as it changes the look of the
+    /// wind and the impulses as they answer the same onsets as the shockwave; the gravity effects
+    /// are on, as they do nothing until their progress is raised.
+    fn default() -> Self {
+        let layers = Effect::ALL
+            .into_iter()
+            .map(|effect| EffectLayer {
+                effect,
+                enabled: !matches!(effect, Effect::CurlNoise | Effect::Impulses),
+            })
+            .collect();
+        Self { layers }
+    }
+}
+
+impl EffectStack {
+    /// The enabled effects, in the order their passes run.
+    pub fn enabled(&self) -> impl Iterator<Item = Effect> + '_ {
+        self.layers
+            .iter()
+            .filter(|layer| layer.enabled)
+            .map(|layer| layer.effect)
+    }
+
+    /// Moves the effect at `index` one pass earlier.
+    pub fn move_up(&mut self, index: usize) {
+        if index > 0 && index < self.layers.len() {
+            self.layers.swap(index - 1, index);
+        }
+    }
+
+    /// Moves the effect at `index` one pass later.
+    pub fn move_down(&mut self, index: usize) {
+        if index + 1 < self.layers.len() {
+            self.layers.swap(index, index + 1);
+        }
+    }
+}
+
+/// The settings of the curl noise effect.
+///
+/// The struct is sent as-is to the compute shader, so its layout must match the `CurlNoise` struct
+/// declared in `compute.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CurlNoiseData {
+    /// How far the points are moved each frame, scaled by the amplitude of the sound.
+    pub strength: f32,
+    /// The frequency of the noise field, in the units of the point cloud.
+    pub scale: f32,
+    /// How fast the field evolves over time.
+    pub speed: f32,
+    _padding: u32,
+}
+
+impl Default for CurlNoiseData {
+    fn default() -> Self {
+        Self {
+            strength: 0.2,
+            scale: 0.02,
+            speed: 0.2,
+            _padding: 0,
+        }
+    }
+}
+
+impl CurlNoiseData {
+    /// Returns the struct as a byte slice.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { wgpu::bytes::from(self) }
+    }
+}
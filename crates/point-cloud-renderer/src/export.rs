@@ -0,0 +1,96 @@
+use std::{
+    path::PathBuf,
+    sync::mpsc::{self, SyncSender, TrySendError},
+    thread::{self, JoinHandle},
+};
+
+use crate::{loader::write_ply, point::Point};
+
+/// Exports an animated point cloud as a sequence of numbered PLY files.
+///
+/// The files are written by a background thread so that the render loop is not stalled by the
+/// disk. At most [`PlySequence::QUEUE_SIZE`] frames can be waiting to be written; frames
+/// submitted while the queue is full are dropped.
+pub struct PlySequence {
+    sender: Option<SyncSender<(PathBuf, Vec<Point>)>>,
+    worker: Option<JoinHandle<()>>,
+    directory: PathBuf,
+    every_n_frames: u32,
+    frame: u32,
+    written: u32,
+}
+
+impl PlySequence {
+    /// The number of frames that can be waiting to be written.
+    pub const QUEUE_SIZE: usize = 2;
+
+    /// Starts a new sequence in the given directory, exporting every `every_n_frames` frames.
+    pub fn new(directory: &str, every_n_frames: u32) -> Result<Self, &'static str> {
+        let directory = PathBuf::from(directory);
+        if std::fs::create_dir_all(&directory).is_err() {
+            return Err("Failed to create the export directory");
+        }
+
+        let (sender, receiver) = mpsc::sync_channel::<(PathBuf, Vec<Point>)>(Self::QUEUE_SIZE);
+        let worker = thread::spawn(move || {
+            receiver.into_iter().for_each(|(path, points)| {
+                if let Err(e) = write_ply(&path.to_string_lossy(), &points) {
+                    eprintln!("{e}: {}", path.display());
+                }
+            });
+        });
+
+        Ok(Self {
+            sender: Some(sender),
+            worker: Some(worker),
+            directory,
+            every_n_frames: every_n_frames.max(1),
+            frame: 0,
+            written: 0,
+        })
+    }
+
+    /// Advances the sequence by one frame and returns the number of the frame if it should be
+    /// exported.
+    pub fn tick(&mut self) -> Option<u32> {
+        let frame = self.frame;
+        self.frame += 1;
+        frame.is_multiple_of(self.every_n_frames).then_some(frame)
+    }
+
+    /// Queues the points of a frame returned by [`Self::tick`] to be written to disk.
+    ///
+    /// The points may be submitted a few frames later, once they are read back from the GPU.
+    pub fn submit(&mut self, frame: u32, points: Vec<Point>) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+
+        let path = self.directory.join(format!("frame_{frame:05}.ply"));
+        match sender.try_send((path, points)) {
+            Ok(_) => self.written += 1,
+            Err(TrySendError::Full(_)) => eprintln!("warning: export queue full, dropping frame"),
+            Err(TrySendError::Disconnected(_)) => eprintln!("error: export thread stopped"),
+        }
+    }
+
+    /// The number of frames queued for export so far.
+    pub fn written(&self) -> u32 {
+        self.written
+    }
+
+    /// The directory the sequence is exported to.
+    pub fn directory(&self) -> &PathBuf {
+        &self.directory
+    }
+}
+
+impl Drop for PlySequence {
+    fn drop(&mut self) {
+        // Close the channel and wait for the remaining frames to be written
+        self.sender.take();
+        if let Some(worker) = self.worker.take() {
+            worker.join().ok();
+        }
+    }
+}
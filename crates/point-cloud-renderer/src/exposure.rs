@@ -0,0 +1,111 @@
+use nannou::wgpu;
+use serde::{Deserialize, Serialize};
+
+/// The exposure of the frame, brightened or darkened before it is shown, so the image stays
+/// readable on a projector as the density of the cloud and the effects vary.
+///
+/// In automatic mode, the average luminance of the lit pixels of the frame is measured on the GPU
+/// each frame and the exposure eases towards the one bringing it to the target, the black
+/// background being left out so a sparse cloud is not blown out. The manual stops are then added
+/// as a compensation.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExposureSettings {
+    /// Whether the exposure adapts to the luminance of the frame.
+    pub auto: bool,
+    /// The exposure in stops, each doubling the brightness; the compensation of the automatic
+    /// exposure when it adapts.
+    pub stops: f32,
+    /// The average luminance the automatic exposure brings the lit pixels to, in the range (0, 1].
+    pub target: f32,
+    /// How fast the automatic exposure adapts, as the inverse of the time in seconds it takes to
+    /// cover about two thirds of the way.
+    pub speed: f32,
+    /// The lowest and highest exposures the automatic exposure adapts to, in stops.
+    pub range: [f32; 2],
+}
+
+impl ExposureSettings {
+    /// Whether the frame goes through the exposure stage.
+    pub fn is_active(&self) -> bool {
+        self.auto || self.stops != 0.0
+    }
+}
+
+impl Default for ExposureSettings {
+    fn default() -> Self {
+        Self {
+            auto: false,
+            stops: 0.0,
+            target: 0.18,
+            speed: 1.5,
+            range: [-3.0, 3.0],
+        }
+    }
+}
+
+/// The uniforms of the automatic exposure.
+///
+/// The struct is sent as-is to the shaders, so its layout must match the `Settings` struct
+/// declared in `exposure.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct ExposureUniforms {
+    stops: f32,
+    target_luminance: f32,
+    speed: f32,
+    delta_time: f32,
+    range: [f32; 2],
+    _padding: [u32; 2],
+}
+
+impl ExposureUniforms {
+    /// Creates the uniforms of a frame shown `delta_time` seconds after the previous one.
+    pub fn new(settings: &ExposureSettings, delta_time: f32) -> Self {
+        let [min, max] = settings.range;
+        Self {
+            stops: settings.stops,
+            target_luminance: settings.target.max(f32::EPSILON),
+            speed: settings.speed.max(0.0),
+            delta_time,
+            range: [min.min(max), max.max(min)],
+            _padding: [0; 2],
+        }
+    }
+
+    /// Returns the struct as a byte slice.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { wgpu::bytes::from(self) }
+    }
+}
+
+/// The exposure applied to the frame, written by the automatic exposure on the GPU, or by the CPU
+/// when it is fixed.
+///
+/// The struct is sent as-is to the shaders, so its layout must match the `Exposure` struct
+/// declared in `exposure.wgsl` and `warp.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct ExposureState {
+    /// The exposure applied, in stops.
+    stops: f32,
+    /// The exposure the automatic exposure adapted to, before its compensation.
+    adapted: f32,
+    _padding: [u32; 2],
+}
+
+impl ExposureState {
+    /// Creates the state of a fixed exposure, from which the automatic exposure adapts when it is
+    /// turned on.
+    pub fn fixed(stops: f32) -> Self {
+        Self {
+            stops,
+            adapted: 0.0,
+            _padding: [0; 2],
+        }
+    }
+
+    /// Returns the struct as a byte slice.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { wgpu::bytes::from(self) }
+    }
+}
@@ -0,0 +1,58 @@
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::Response;
+
+use crate::{
+    loader::LoadHandle,
+    registry::{LoadOptions, Registry},
+};
+
+impl Registry {
+    /// Starts downloading a point cloud in the background, for the viewer running in a web page,
+    /// which cannot read files from the disk.
+    ///
+    /// The file is read with the loader of the extension of the URL once it is downloaded, so the
+    /// format must be readable from memory, see
+    /// [`Loader::load_bytes`](crate::registry::Loader::load_bytes).
+    pub fn fetch_async(&self, url: &str, options: LoadOptions) -> LoadHandle {
+        // The query of the URL is not part of the name of the file
+        let path = url.split(['?', '#']).next().unwrap_or(url);
+        let loader = self.find_loader(path).cloned();
+        let (handle, sender, progress) = LoadHandle::channel(url);
+
+        let url = url.to_owned();
+        wasm_bindgen_futures::spawn_local(async move {
+            let result = match loader {
+                Some(loader) => match fetch_bytes(&url).await {
+                    Ok(bytes) => loader.load_bytes(&bytes, &options, &progress),
+                    Err(e) => Err(e),
+                },
+                None => Err("Unsupported point cloud format"),
+            };
+            sender.send(result).ok();
+        });
+        handle
+    }
+}
+
+/// Downloads the file at the URL with the fetch API of the browser.
+async fn fetch_bytes(url: &str) -> Result<Vec<u8>, &'static str> {
+    let window = web_sys::window().ok_or("Failed to access the window of the page")?;
+    let response = JsFuture::from(window.fetch_with_str(url))
+        .await
+        .map_err(|_| "Failed to fetch the point cloud")?;
+    let response: Response = response
+        .dyn_into()
+        .map_err(|_| "Failed to read the response")?;
+    if !response.ok() {
+        return Err("The server did not return the point cloud");
+    }
+
+    let buffer = response
+        .array_buffer()
+        .map_err(|_| "Failed to read the response")?;
+    let buffer = JsFuture::from(buffer)
+        .await
+        .map_err(|_| "Failed to download the point cloud")?;
+    Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+}
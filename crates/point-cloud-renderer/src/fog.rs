@@ -0,0 +1,77 @@
+use nannou::{prelude::*, wgpu};
+use serde::{Deserialize, Serialize};
+
+use crate::camera::Camera;
+
+/// An infinite horizontal plane of fog with a fake reflection of the points above it.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FogSettings {
+    /// Whether the fog plane is drawn.
+    pub enabled: bool,
+    /// The color of the fog in linear space, like the colors of the points, in the range [0, 1].
+    pub color: [f32; 3],
+    /// The height of the plane in the coordinates of the point cloud.
+    pub height: f32,
+    /// How quickly the fog becomes opaque with the distance to the camera.
+    pub density: f32,
+    /// How visible the reflection of the points is, in the range [0, 1].
+    pub reflection_strength: f32,
+}
+
+impl FogSettings {
+    /// Whether the reflection of the points should be drawn.
+    pub fn draws_reflection(&self) -> bool {
+        self.enabled && self.reflection_strength > 0.0
+    }
+}
+
+impl Default for FogSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            color: [0.5, 0.5, 0.55],
+            height: 0.0,
+            density: 0.01,
+            reflection_strength: 0.3,
+        }
+    }
+}
+
+/// The uniforms of the fog plane.
+///
+/// The struct is sent as-is to the shaders, so its layout must match the `Fog` struct declared in
+/// `fog.wgsl` and `render.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct FogUniforms {
+    inverse_view_proj: Mat4,
+    view_proj: Mat4,
+    color: [f32; 3],
+    height: f32,
+    density: f32,
+    reflection_strength: f32,
+    _padding: [u32; 2],
+}
+
+impl FogUniforms {
+    /// Combines the settings of the fog with the transforms of the camera.
+    pub fn new(settings: &FogSettings, camera: &Camera) -> Self {
+        let transforms = camera.uniforms();
+        let view_proj = transforms.proj * transforms.view * transforms.world;
+
+        Self {
+            inverse_view_proj: view_proj.inverse(),
+            view_proj,
+            color: settings.color,
+            height: settings.height,
+            density: settings.density,
+            reflection_strength: settings.reflection_strength,
+            _padding: [0; 2],
+        }
+    }
+
+    /// Returns the struct as a byte slice.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { wgpu::bytes::from(self) }
+    }
+}
@@ -0,0 +1,288 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+};
+
+use nannou::{prelude::*, wgpu};
+use rayon::{
+    iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator},
+    slice::{ParallelSlice, ParallelSliceMut},
+};
+
+use crate::{
+    color::ColorSpace,
+    loader::{
+        LoadHandle, LoadProgress, PlyFormat, PlyProperty, PlyType, read_ply_header,
+        skip_ply_element,
+    },
+};
+
+/// The extensions of the files of 3D Gaussian Splatting captures that can be read.
+pub const GAUSSIAN_EXTENSIONS: [&str; 2] = ["splat", "ply"];
+
+/// A 3D Gaussian of a Gaussian Splatting capture, an anisotropic blob of color.
+///
+/// The struct is sent as-is to the shaders, so its layout must match the `Gaussian` struct declared
+/// in `gaussian.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gaussian {
+    /// The center of the Gaussian.
+    pub position: [f32; 3],
+    /// The opacity of the Gaussian at its center, in the range [0, 1].
+    pub opacity: f32,
+    /// The upper triangle of the covariance of the Gaussian, its xx, xy and xz terms then its yy,
+    /// yz and zz terms, each row padded to 16 bytes.
+    covariance: [[f32; 4]; 2],
+    /// The color of the Gaussian in linear space, like the colors of the points.
+    pub color: [f32; 4],
+}
+
+impl Gaussian {
+    /// Creates a Gaussian from its axes, scaled by `scale` then rotated by `rotation`.
+    ///
+    /// The color is in sRGB, in the range [0, 1].
+    pub fn new(position: [f32; 3], scale: [f32; 3], rotation: Quat, color: [f32; 3]) -> Self {
+        // The rotations of the captures are not always normalized, and may be null
+        let rotation = match rotation.length_squared() > 0.0 {
+            true => rotation.normalize(),
+            false => Quat::IDENTITY,
+        };
+        let axes = Mat3::from_quat(rotation) * Mat3::from_diagonal(Vec3::from(scale));
+        let sigma = axes * axes.transpose();
+        let [red, green, blue] = color;
+        Self {
+            position,
+            opacity: 1.0,
+            covariance: [
+                [sigma.x_axis.x, sigma.y_axis.x, sigma.z_axis.x, 0.0],
+                [sigma.y_axis.y, sigma.z_axis.y, sigma.z_axis.z, 0.0],
+            ],
+            color: ColorSpace::Srgb.to_linear([red, green, blue, 1.0]),
+        }
+    }
+
+    /// Sets the opacity of the Gaussian at its center.
+    pub fn with_opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Turns the Gaussian from the Y-down coordinates of the captures, those of COLMAP, to the Y-up
+    /// coordinates of the renderer, rotating it half a turn about the X axis.
+    fn to_y_up(mut self) -> Self {
+        let [x, y, z] = self.position;
+        self.position = [x, -y, -z];
+        let [[xx, xy, xz, _], [yy, yz, zz, _]] = self.covariance;
+        self.covariance = [[xx, -xy, -xz, 0.0], [yy, yz, zz, 0.0]];
+        self
+    }
+
+    /// Convert a slice of Gaussians to a byte slice.
+    pub fn as_bytes(gaussians: &[Gaussian]) -> &[u8] {
+        unsafe { wgpu::bytes::from_slice(gaussians) }
+    }
+}
+
+/// The size of the Gaussians on the screen, as the uniforms of the Gaussian stage.
+///
+/// The struct is sent as-is to the shaders, so its layout must match the `GaussianView` struct
+/// declared in `gaussian.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GaussianView {
+    /// The size of the render target in pixels.
+    pub viewport: [f32; 2],
+    /// How much the Gaussians are scaled, 1 drawing them as captured.
+    pub scale: f32,
+    _padding: u32,
+}
+
+impl GaussianView {
+    pub fn new(viewport: [f32; 2], scale: f32) -> Self {
+        Self {
+            viewport,
+            scale,
+            _padding: 0,
+        }
+    }
+
+    /// Returns the struct as a byte slice.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { wgpu::bytes::from(self) }
+    }
+}
+
+/// The order to draw the Gaussians in, from the farthest to the nearest along the view, as they
+/// are blended over each other.
+pub fn sort_back_to_front(positions: &[[f32; 3]], world_view: Mat4) -> Vec<u32> {
+    // The depths are sorted as integers, the bits of a float being flipped to keep their order
+    let mut keys: Vec<(u32, u32)> = positions
+        .par_iter()
+        .enumerate()
+        .map(|(index, &position)| {
+            let depth = world_view.transform_point3(Vec3::from(position)).z;
+            let bits = depth.to_bits();
+            let key = match bits >> 31 {
+                0 => bits | 0x8000_0000,
+                _ => !bits,
+            };
+            (key, index as u32)
+        })
+        .collect();
+    keys.par_sort_unstable();
+    keys.into_iter().map(|(_, index)| index).collect()
+}
+
+/// Starts reading the Gaussians of a capture on a background thread, from a `.splat` file or from
+/// the PLY file written by the training of 3D Gaussian Splatting.
+///
+/// See [`read_gaussians`].
+pub fn read_gaussians_async(path: &str) -> LoadHandle<Vec<Gaussian>> {
+    LoadHandle::spawn(path, read_gaussians_reporting)
+}
+
+/// Reads the Gaussians of a capture from a `.splat` file or from the PLY file written by the
+/// training of 3D Gaussian Splatting.
+///
+/// The captures are Y-down, so the Gaussians are turned to the Y-up coordinates of the renderer.
+/// Only the base color of the spherical harmonics is kept, so the colors do not change with the
+/// view.
+pub fn read_gaussians(path: &str) -> Result<Vec<Gaussian>, &'static str> {
+    read_gaussians_reporting(path, &LoadProgress::default())
+}
+
+fn read_gaussians_reporting(
+    path: &str,
+    progress: &LoadProgress,
+) -> Result<Vec<Gaussian>, &'static str> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(_) => return Err("Failed to open Gaussian splats file"),
+    };
+    let reader = BufReader::new(file);
+    let extension = Path::new(path)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    let gaussians = match extension.as_deref() {
+        Some("splat") => read_splat(reader, progress)?,
+        Some("ply") => read_gaussian_ply(reader, progress)?,
+        _ => return Err("Unsupported Gaussian splats format"),
+    };
+    Ok(gaussians.into_iter().map(Gaussian::to_y_up).collect())
+}
+
+/// Reads the records of a `.splat` file, of 32 bytes each: the position and the scale as floats,
+/// then the color with its opacity and the rotation as bytes.
+fn read_splat(
+    mut reader: impl Read,
+    progress: &LoadProgress,
+) -> Result<Vec<Gaussian>, &'static str> {
+    const RECORD_SIZE: usize = 32;
+    let mut bytes = Vec::new();
+    if reader.read_to_end(&mut bytes).is_err() {
+        return Err("Failed to read Gaussian splats file");
+    }
+    if bytes.len() % RECORD_SIZE != 0 {
+        return Err("Truncated Gaussian splats file");
+    }
+    progress.set_total((bytes.len() / RECORD_SIZE) as u64);
+
+    let float =
+        |record: &[u8], i: usize| f32::from_le_bytes(record[i * 4..i * 4 + 4].try_into().unwrap());
+    let gaussians = bytes
+        .par_chunks_exact(RECORD_SIZE)
+        .map(|record| {
+            let position = [float(record, 0), float(record, 1), float(record, 2)];
+            let scale = [float(record, 3), float(record, 4), float(record, 5)];
+            let [red, green, blue, alpha] = [24, 25, 26, 27].map(|i| record[i] as f32 / 255.0);
+            // The rotation is stored as w, x, y, z, each mapped from [-1, 1] to [0, 255]
+            let [w, x, y, z] = [28, 29, 30, 31].map(|i| (record[i] as f32 - 128.0) / 128.0);
+            Gaussian::new(
+                position,
+                scale,
+                Quat::from_xyzw(x, y, z, w),
+                [red, green, blue],
+            )
+            .with_opacity(alpha)
+        })
+        .collect();
+    progress.add((bytes.len() / RECORD_SIZE) as u64);
+    Ok(gaussians)
+}
+
+/// Reads the vertices of a binary PLY file written by the training of 3D Gaussian Splatting.
+///
+/// Its scales are logarithmic, its opacities are before their sigmoid and its colors are the base
+/// coefficients of spherical harmonics.
+fn read_gaussian_ply(
+    mut reader: impl BufRead,
+    progress: &LoadProgress,
+) -> Result<Vec<Gaussian>, &'static str> {
+    // The weight of the base coefficient of the spherical harmonics
+    const SH_C0: f32 = 0.282_094_8;
+
+    let (format, elements) = read_ply_header(&mut reader)?;
+    let PlyFormat::BinaryLittleEndian = format else {
+        return Err("Unsupported Gaussian splats PLY format");
+    };
+    let Some(vertex) = elements.iter().position(|element| element.name == "vertex") else {
+        return Err("Missing PLY vertices");
+    };
+    for element in &elements[..vertex] {
+        skip_ply_element(&mut reader, format, element)?;
+    }
+
+    // Locate the properties of the Gaussians in a record
+    let element = &elements[vertex];
+    let mut fields = HashMap::new();
+    let mut stride = 0;
+    for property in &element.properties {
+        let PlyProperty::Scalar { name, kind } = property else {
+            return Err("Unsupported list property in PLY vertices");
+        };
+        fields.insert(name.as_str(), (stride, *kind));
+        stride += kind.size();
+    }
+    let names = [
+        "x", "y", "z", "f_dc_0", "f_dc_1", "f_dc_2", "opacity", "scale_0", "scale_1", "scale_2",
+        "rot_0", "rot_1", "rot_2", "rot_3",
+    ];
+    let Some(layout) = names
+        .iter()
+        .map(|name| fields.get(name).copied())
+        .collect::<Option<Vec<(usize, PlyType)>>>()
+    else {
+        return Err("Not a Gaussian splats PLY file");
+    };
+
+    progress.set_total(element.count as u64);
+    let mut bytes = vec![0; element.count * stride];
+    if reader.read_exact(&mut bytes).is_err() {
+        return Err("Truncated PLY vertices");
+    }
+    let gaussians = bytes
+        .par_chunks_exact(stride)
+        .map(|record| {
+            let value = |i: usize| {
+                let (offset, kind) = layout[i];
+                kind.read_le(&record[offset..]) as f32
+            };
+            let color = [3, 4, 5].map(|i| (0.5 + SH_C0 * value(i)).clamp(0.0, 1.0));
+            let opacity = 1.0 / (1.0 + (-value(6)).exp());
+            let scale = [7, 8, 9].map(|i| value(i).exp());
+            let [w, x, y, z] = [10, 11, 12, 13].map(value);
+            Gaussian::new(
+                [value(0), value(1), value(2)],
+                scale,
+                Quat::from_xyzw(x, y, z, w),
+                color,
+            )
+            .with_opacity(opacity)
+        })
+        .collect();
+    progress.add(element.count as u64);
+    Ok(gaussians)
+}
@@ -0,0 +1,147 @@
+use nannou::{prelude::*, wgpu};
+use serde::{Deserialize, Serialize};
+
+/// The guides drawn among the points to tell the orientation and scale of a cloud, such as a scan
+/// loaded on its side: a grid on the ground at y = 0, arrows along the axes from the origin, and
+/// the outline of the bounding box of the points.
+///
+/// They are drawn in the coordinates of the cloud, so they turn along with the world, and tested
+/// against the depth of the points so the points in front of them hide them.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GizmoSettings {
+    /// Whether the guides are drawn.
+    pub enabled: bool,
+    pub grid: bool,
+    pub axes: bool,
+    pub bounds: bool,
+}
+
+impl GizmoSettings {
+    /// The color of the lines of the grid, in linear space.
+    const GRID_COLOR: [f32; 4] = [0.2, 0.2, 0.2, 1.0];
+    /// The color of the outline of the bounding box, in linear space.
+    const BOUNDS_COLOR: [f32; 4] = [1.0, 0.6, 0.1, 1.0];
+    /// The colors of the x, y and z axes, in linear space.
+    const AXIS_COLORS: [[f32; 4]; 3] = [
+        [1.0, 0.1, 0.1, 1.0],
+        [0.1, 1.0, 0.1, 1.0],
+        [0.1, 0.3, 1.0, 1.0],
+    ];
+    /// The highest number of lines of the grid along each axis.
+    const MAX_GRID_LINES: f32 = 100.0;
+
+    /// Whether any guide is drawn.
+    pub fn is_visible(&self) -> bool {
+        self.enabled && (self.grid || self.axes || self.bounds)
+    }
+
+    /// The lines of the guides around the bounding box of the points, given by its corners in the
+    /// coordinates of the cloud and the matrix placing the cloud in the scene, as pairs of
+    /// vertices.
+    ///
+    /// The spacing of the grid is the power of ten fitting about ten cells across the cloud, and
+    /// the grid spans the cloud and the origin of the scene.
+    pub fn lines(&self, (min, max): (Point3, Point3), placement: Mat4) -> Vec<GizmoVertex> {
+        let mut vertices = Vec::new();
+        if !(self.is_visible() && min.is_finite() && max.is_finite()) {
+            return vertices;
+        }
+
+        // The corners are indexed by the bits of their axes, so the edges join the corners
+        // differing by a single bit
+        let corners: [Vec3; 8] = std::array::from_fn(|i| {
+            let corner = Vec3::select(BVec3::new(i & 1 != 0, i & 2 != 0, i & 4 != 0), max, min);
+            placement.transform_point3(corner)
+        });
+        let (min, max) = corners
+            .iter()
+            .fold((corners[0], corners[0]), |(min, max), &corner| {
+                (min.min(corner), max.max(corner))
+            });
+        let mut line = |start: Vec3, end: Vec3, color: [f32; 4]| {
+            vertices.push(GizmoVertex::new(start, color));
+            vertices.push(GizmoVertex::new(end, color));
+        };
+
+        let extent = (max - min).max_element().max(f32::EPSILON);
+        let spacing = 10.0f32.powf((extent / 10.0).log10().floor());
+        let low = (min.min(Vec3::ZERO) / spacing).floor() * spacing;
+        let high = (max.max(Vec3::ZERO) / spacing).ceil() * spacing;
+
+        if self.grid {
+            let lines = |low: f32, high: f32| {
+                let count = ((high - low) / spacing).round().min(Self::MAX_GRID_LINES) as u32;
+                (0..=count).map(move |i| low + i as f32 * spacing)
+            };
+            lines(low.x, high.x).for_each(|x| {
+                let color = Self::GRID_COLOR;
+                line(vec3(x, 0.0, low.z), vec3(x, 0.0, high.z), color);
+            });
+            lines(low.z, high.z).for_each(|z| {
+                let color = Self::GRID_COLOR;
+                line(vec3(low.x, 0.0, z), vec3(high.x, 0.0, z), color);
+            });
+        }
+
+        if self.axes {
+            // Each arrow is a shaft with two barbs, bent towards the next axis
+            let length = extent / 4.0;
+            let axes = [Vec3::X, Vec3::Y, Vec3::Z];
+            axes.iter().enumerate().for_each(|(i, &axis)| {
+                let color = Self::AXIS_COLORS[i];
+                let tip = axis * length;
+                let side = axes[(i + 1) % 3] * length * 0.05;
+                let back = tip - axis * length * 0.1;
+                line(Vec3::ZERO, tip, color);
+                line(tip, back + side, color);
+                line(tip, back - side, color);
+            });
+        }
+
+        if self.bounds {
+            (0..8)
+                .flat_map(|i| (0..3).map(move |axis| (i, i | 1 << axis)))
+                .filter(|(start, end)| start != end)
+                .for_each(|(start, end)| line(corners[start], corners[end], Self::BOUNDS_COLOR));
+        }
+
+        vertices
+    }
+}
+
+impl Default for GizmoSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            grid: true,
+            axes: true,
+            bounds: true,
+        }
+    }
+}
+
+/// A vertex of the lines of the guides.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct GizmoVertex {
+    position: [f32; 3],
+    /// The color in linear space, like the colors of the points.
+    color: [f32; 4],
+}
+
+impl GizmoVertex {
+    pub const ATTRIBS: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4];
+
+    fn new(position: Vec3, color: [f32; 4]) -> Self {
+        Self {
+            position: position.to_array(),
+            color,
+        }
+    }
+
+    /// Returns the vertices as a byte slice.
+    pub fn as_bytes(vertices: &[Self]) -> &[u8] {
+        unsafe { wgpu::bytes::from_slice(vertices) }
+    }
+}
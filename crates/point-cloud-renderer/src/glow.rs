@@ -0,0 +1,82 @@
+use nannou::wgpu;
+use serde::{Deserialize, Serialize};
+
+/// The points blended additively into the frame instead of hiding each other, so dense areas
+/// glow.
+///
+/// Without the depth test, the points inside a structure shine through its surface and wash out
+/// the image. A coarse depth pre-pass keeps the nearest depth of each tile of the screen, and the
+/// points lying behind it are dimmed rather than culled, keeping the glow while the structure
+/// stays readable.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GlowSettings {
+    /// Whether the points are blended additively, without the depth test.
+    pub additive: bool,
+    /// How much light each point adds, as a fraction of its color.
+    pub intensity: f32,
+    /// How much the points hidden behind the nearest points of their tile are dimmed, in the
+    /// range [0, 1].
+    pub occlusion: f32,
+    /// The distance behind the nearest point of a tile over which the hidden points fade, in the
+    /// units of the point cloud.
+    pub falloff: f32,
+}
+
+impl GlowSettings {
+    /// The number of columns and rows of tiles the coarse depth is kept for, about eight pixels
+    /// wide on a 1080p screen.
+    pub const DEPTH_GRID: [u32; 2] = [240, 135];
+
+    /// Whether the coarse depth pre-pass must run to dim the hidden points.
+    pub fn attenuates(&self) -> bool {
+        self.additive && self.occlusion > 0.0
+    }
+}
+
+impl Default for GlowSettings {
+    fn default() -> Self {
+        Self {
+            additive: false,
+            intensity: 0.3,
+            occlusion: 0.8,
+            falloff: 5.0,
+        }
+    }
+}
+
+/// The uniforms of the additive blending.
+///
+/// The struct is sent as-is to the shaders, so its layout must match the `Glow` struct declared in
+/// `render.wgsl` and `occlusion.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct GlowUniforms {
+    additive: u32,
+    intensity: f32,
+    occlusion: f32,
+    falloff: f32,
+    grid: [u32; 2],
+    _padding: [u32; 2],
+}
+
+impl GlowUniforms {
+    pub fn new(settings: &GlowSettings) -> Self {
+        Self {
+            additive: settings.additive as u32,
+            intensity: settings.intensity,
+            // The coarse depth is only written when the points are dimmed
+            occlusion: match settings.attenuates() {
+                true => settings.occlusion,
+                false => 0.0,
+            },
+            falloff: settings.falloff.max(f32::EPSILON),
+            grid: GlowSettings::DEPTH_GRID,
+            _padding: [0; 2],
+        }
+    }
+
+    /// Returns the struct as a byte slice.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { wgpu::bytes::from(self) }
+    }
+}
@@ -0,0 +1,92 @@
+use nannou::wgpu;
+use serde::{Deserialize, Serialize};
+
+use crate::point::Point;
+
+/// The settings of the gravity effects: the points collapsing under gravity, and reassembling to
+/// their rest with the overshoot of a spring.
+///
+/// Both effects are driven by their progress, from 0 to 1, instead of by time, so they can be
+/// keyframed along the camera path for choreographed moments. Each places the points at their pose
+/// along the effect, overriding the effects before it in the stack; nothing moves while the
+/// progress is 0.
+///
+/// The struct is sent as-is to the compute shader, so its layout must match the `Gravity` struct
+/// declared in `compute.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GravityData {
+    /// The centroid of the points at rest.
+    center: [f32; 3],
+    /// The height of the floor the points fall onto, the bottom of the cloud.
+    floor: f32,
+    /// The progress of the collapse, from the points at rest to all of them fallen.
+    pub collapse: f32,
+    /// The progress of the reassembly, from where the effects before it leave the points, such as
+    /// a collapse, to their rest.
+    pub reassemble: f32,
+    /// How far the points spring past their rest while reassembling; 1.7 overshoots by a tenth.
+    pub overshoot: f32,
+    toward_center: u32,
+    /// The height of the cloud above the floor.
+    height: f32,
+    /// The distance of the farthest point from the centroid.
+    radius: f32,
+    _padding: [u32; 2],
+}
+
+impl Default for GravityData {
+    fn default() -> Self {
+        Self {
+            center: [0.0; 3],
+            floor: 0.0,
+            collapse: 0.0,
+            reassemble: 0.0,
+            overshoot: 1.7,
+            toward_center: 0,
+            height: 0.0,
+            radius: 0.0,
+            _padding: [0; 2],
+        }
+    }
+}
+
+impl GravityData {
+    /// Fits the floor and the centroid the points collapse toward to the points at rest.
+    pub fn fit(&mut self, points: &[Point]) {
+        if points.is_empty() {
+            return;
+        }
+        let (min, max) = Point::bounding_box(points);
+        let sum = points.iter().fold([0.0f64; 3], |sum, point| {
+            std::array::from_fn(|i| sum[i] + point.position[i] as f64)
+        });
+        let center = sum.map(|sum| (sum / points.len() as f64) as f32);
+        self.radius = points
+            .iter()
+            .map(|point| {
+                (0..3)
+                    .map(|i| (point.position[i] - center[i]).powi(2))
+                    .sum::<f32>()
+            })
+            .fold(0.0, f32::max)
+            .sqrt();
+        self.center = center;
+        self.floor = min.y;
+        self.height = max.y - min.y;
+    }
+
+    /// Whether the points collapse toward their centroid instead of falling to the floor.
+    pub fn toward_center(&self) -> bool {
+        self.toward_center != 0
+    }
+
+    pub fn set_toward_center(&mut self, toward_center: bool) {
+        self.toward_center = toward_center as u32;
+    }
+
+    /// Returns the struct as a byte slice.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { wgpu::bytes::from(self) }
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::VecDeque;
+
+use crate::point::Point;
+
+/// A state of the point cloud saved before a destructive operation.
+struct Snapshot {
+    label: String,
+    points: Vec<Point>,
+}
+
+impl Snapshot {
+    fn size(&self) -> usize {
+        self.points.len() * std::mem::size_of::<Point>()
+    }
+}
+
+/// An undo/redo history of the destructive operations applied to a point cloud.
+///
+/// The history stores full snapshots of the points. Once the snapshots take more memory than the
+/// budget, the oldest ones are forgotten.
+pub struct CloudHistory {
+    undo: VecDeque<Snapshot>,
+    redo: Vec<Snapshot>,
+    budget: usize,
+}
+
+impl CloudHistory {
+    /// Creates an empty history holding at most `budget` bytes of points.
+    pub fn new(budget: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            budget,
+        }
+    }
+
+    /// Records the points as they were before an operation, clearing the redo history.
+    pub fn record(&mut self, label: impl Into<String>, points: Vec<Point>) {
+        self.redo.clear();
+        self.undo.push_back(Snapshot {
+            label: label.into(),
+            points,
+        });
+        self.enforce_budget();
+    }
+
+    /// Undoes the last operation, swapping the points back to how they were before it.
+    ///
+    /// The current points are moved to the redo history. Returns the label of the undone
+    /// operation, or `None` if there is nothing to undo.
+    pub fn undo(&mut self, points: &mut Vec<Point>) -> Option<String> {
+        let mut snapshot = self.undo.pop_back()?;
+        std::mem::swap(points, &mut snapshot.points);
+        let label = snapshot.label.clone();
+        self.redo.push(snapshot);
+        self.enforce_budget();
+        Some(label)
+    }
+
+    /// Redoes the last undone operation, swapping the points to how they were after it.
+    ///
+    /// The current points are moved to the undo history. Returns the label of the redone
+    /// operation, or `None` if there is nothing to redo.
+    pub fn redo(&mut self, points: &mut Vec<Point>) -> Option<String> {
+        let mut snapshot = self.redo.pop()?;
+        std::mem::swap(points, &mut snapshot.points);
+        let label = snapshot.label.clone();
+        self.undo.push_back(snapshot);
+        self.enforce_budget();
+        Some(label)
+    }
+
+    /// The label of the operation that would be undone, if any.
+    pub fn undo_label(&self) -> Option<&str> {
+        self.undo.back().map(|snapshot| snapshot.label.as_str())
+    }
+
+    /// The label of the operation that would be redone, if any.
+    pub fn redo_label(&self) -> Option<&str> {
+        self.redo.last().map(|snapshot| snapshot.label.as_str())
+    }
+
+    /// The number of bytes of points held by the history.
+    pub fn size(&self) -> usize {
+        self.undo.iter().chain(&self.redo).map(Snapshot::size).sum()
+    }
+
+    /// Removes every snapshot.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// Forgets the oldest snapshots until the history fits in its budget.
+    ///
+    /// The most recent undo step is always kept, since it is the one most likely to be needed.
+    fn enforce_budget(&mut self) {
+        while self.size() > self.budget && self.undo.len() > 1 {
+            self.undo.pop_front();
+        }
+        while self.size() > self.budget && !self.redo.is_empty() {
+            self.redo.remove(0);
+        }
+    }
+}
+
+impl Default for CloudHistory {
+    fn default() -> Self {
+        // 1 GiB, enough for a few large scans
+        Self::new(1 << 30)
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    borrow::Cow,
+    future::Future,
+    path::{Path, PathBuf},
+    pin::pin,
+    task::{Context, Poll, Waker},
+    time::SystemTime,
+};
+
+use nannou::wgpu;
+
+/// Watches the WGSL files of the deformation stages, so they can be edited while the renderer
+/// runs instead of rebuilding it.
+///
+/// The files are polled for a change of their modification time, which is cheap enough to do
+/// every frame.
+pub struct ShaderWatcher {
+    directory: PathBuf,
+    modified: [Option<SystemTime>; 2],
+    /// The error of the last reload, until the shaders compile again.
+    error: Option<String>,
+}
+
+/// The sources of the shaders of the deformation stages.
+pub struct ShaderSources {
+    pub compute: String,
+    pub render: String,
+}
+
+impl ShaderWatcher {
+    /// The directory of the shaders in the sources of the renderer.
+    pub const SOURCE_DIRECTORY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/shaders");
+    const FILES: [&str; 2] = ["compute.wgsl", "render.wgsl"];
+
+    /// Starts watching the shaders in `directory`, which are only reloaded once they change.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        let directory = directory.into();
+        let modified = Self::modified(&directory);
+        Self {
+            directory,
+            modified,
+            error: None,
+        }
+    }
+
+    /// The directory the shaders are read from.
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// The error of the last reload, shown until the shaders are fixed.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Returns the sources of the shaders if any changed since the last call, once they parse and
+    /// validate.
+    ///
+    /// When they do not, the error is kept in [`ShaderWatcher::error`] and `None` is returned, so
+    /// the last good shaders stay in use.
+    pub fn poll(&mut self) -> Option<ShaderSources> {
+        let modified = Self::modified(&self.directory);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+
+        let result = self.read().and_then(|sources| {
+            validate(&sources.compute, Self::FILES[0])?;
+            validate(&sources.render, Self::FILES[1])?;
+            Ok(sources)
+        });
+        match result {
+            Ok(sources) => Some(sources),
+            Err(e) => {
+                self.fail(e);
+                None
+            }
+        }
+    }
+
+    /// Records the outcome of creating the pipelines from the sources returned by
+    /// [`ShaderWatcher::poll`].
+    pub(crate) fn finish(&mut self, result: Result<(), String>) {
+        match result {
+            Ok(()) => self.error = None,
+            Err(e) => self.fail(e),
+        }
+    }
+
+    fn fail(&mut self, error: String) {
+        eprintln!("error: failed to reload the shaders, keeping the last good ones\n{error}");
+        self.error = Some(error);
+    }
+
+    fn read(&self) -> Result<ShaderSources, String> {
+        let [compute, render] = Self::FILES.map(|file| {
+            let path = self.directory.join(file);
+            std::fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read {}: {e}", path.display()))
+        });
+        Ok(ShaderSources {
+            compute: compute?,
+            render: render?,
+        })
+    }
+
+    fn modified(directory: &Path) -> [Option<SystemTime>; 2] {
+        Self::FILES.map(|file| {
+            std::fs::metadata(directory.join(file))
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+    }
+}
+
+/// Parses and validates a WGSL shader, returning the error formatted against its source.
+pub fn validate(source: &str, path: &str) -> Result<(), String> {
+    let module = naga::front::wgsl::parse_str(source)
+        .map_err(|e| e.emit_to_string_with_path(source, path))?;
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .map_err(|e| e.emit_to_string_with_path(source, path))?;
+    Ok(())
+}
+
+/// Creates a shader module from a WGSL source.
+pub(crate) fn create_module(
+    device: &wgpu::Device,
+    label: &str,
+    source: &str,
+) -> wgpu::ShaderModule {
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(source)),
+    })
+}
+
+/// Runs `create` in an error scope, returning the validation error of the device instead of
+/// panicking, such as when a shader no longer matches the layouts of the bind groups.
+pub(crate) fn catch_validation<T>(
+    device: &wgpu::Device,
+    create: impl FnOnce() -> T,
+) -> Result<T, String> {
+    device.push_error_scope(wgpu_upstream::ErrorFilter::Validation);
+    let value = create();
+    // The native backends report the error of the scope right away
+    let mut error = pin!(device.pop_error_scope());
+    match error.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(Some(e)) => Err(e.to_string()),
+        Poll::Ready(None) | Poll::Pending => Ok(value),
+    }
+}
@@ -0,0 +1,109 @@
+use nannou::{geom::Point3, wgpu};
+
+/// The number of wavefronts that can travel through the cloud at once.
+pub const MAX_WAVEFRONTS: usize = 8;
+
+/// A wavefront in flight, leaving its origin at the speed of the impulses.
+///
+/// The struct is sent as-is to the compute shader, so its layout must match the `Wavefront` struct
+/// declared in `compute.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Wavefront {
+    origin: [f32; 3],
+    /// The time since the wavefront left its origin, in seconds.
+    age: f32,
+    /// How far the wavefront moves the points it passes, zero once it has faded away.
+    amplitude: f32,
+    _padding: [u32; 3],
+}
+
+/// The settings and state of the impulses the onsets of the audio send through the cloud.
+///
+/// Each onset spawns a spherical wavefront that travels outward through the points at rest: a
+/// compression followed by a rarefaction, pushing the points ahead of it outward and pulling those
+/// behind it back, so they settle once it has passed. The wavefronts are damped as they travel and
+/// overlap freely, so sound visibly moves through the space.
+///
+/// The struct is sent as-is to the compute shader, so its layout must match the `Impulses` struct
+/// declared in `compute.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImpulseData {
+    wavefronts: [Wavefront; MAX_WAVEFRONTS],
+    /// How fast the wavefronts travel, in units of the point cloud per second.
+    pub speed: f32,
+    /// How quickly the wavefronts fade, as the exponential decay rate of their amplitude per
+    /// second.
+    pub damping: f32,
+    /// The distance between the compression and the rarefaction of a wavefront, in units of the
+    /// point cloud.
+    pub wavelength: f32,
+    /// How far a wavefront moves the points each frame when it leaves its origin.
+    pub strength: f32,
+}
+
+impl Default for ImpulseData {
+    fn default() -> Self {
+        Self {
+            wavefronts: [Wavefront::default(); MAX_WAVEFRONTS],
+            speed: 40.0,
+            damping: 1.5,
+            wavelength: 3.0,
+            strength: 0.05,
+        }
+    }
+}
+
+impl ImpulseData {
+    /// The amplitude below which a wavefront has faded away.
+    const MIN_AMPLITUDE: f32 = 1e-4;
+
+    /// Whether a wavefront is in flight.
+    pub fn is_active(&self) -> bool {
+        self.wavefronts
+            .iter()
+            .any(|wavefront| wavefront.amplitude > 0.0)
+    }
+
+    /// Sends a new wavefront from the given origin, replacing the faintest one when
+    /// [`MAX_WAVEFRONTS`] are already in flight.
+    pub fn spawn(&mut self, origin: Point3) {
+        let faintest = self
+            .wavefronts
+            .iter_mut()
+            .min_by(|a, b| a.amplitude.total_cmp(&b.amplitude))
+            .unwrap();
+        *faintest = Wavefront {
+            origin: origin.to_array(),
+            age: 0.0,
+            amplitude: self.strength,
+            _padding: [0; 3],
+        };
+    }
+
+    /// Advances the wavefronts in flight by the given number of seconds.
+    pub fn tick(&mut self, delta: f32) {
+        let decay = (-self.damping * delta).exp();
+        self.wavefronts
+            .iter_mut()
+            .filter(|wavefront| wavefront.amplitude > 0.0)
+            .for_each(|wavefront| {
+                wavefront.age += delta;
+                wavefront.amplitude *= decay;
+                if wavefront.amplitude < Self::MIN_AMPLITUDE {
+                    wavefront.amplitude = 0.0;
+                }
+            });
+    }
+
+    /// Removes every wavefront in flight.
+    pub fn clear(&mut self) {
+        self.wavefronts = [Wavefront::default(); MAX_WAVEFRONTS];
+    }
+
+    /// Returns the struct as a byte slice.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { wgpu::bytes::from(self) }
+    }
+}
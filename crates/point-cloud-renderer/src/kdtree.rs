@@ -0,0 +1,205 @@
+use crate::point::Point;
+
+/// A k-d tree over the positions of a point cloud, to find the point of the cloud nearest to any
+/// position without going through every point.
+///
+/// The tree is stored implicitly: the positions are ordered so the median of each range along the
+/// axis of its depth sits in the middle of the range, with the positions before and after it as
+/// its two subtrees.
+pub struct KdTree {
+    positions: Vec<[f32; 3]>,
+}
+
+impl KdTree {
+    /// Builds the tree over the positions of the points.
+    pub fn new(points: &[Point]) -> Self {
+        let mut positions: Vec<[f32; 3]> = points.iter().map(|point| point.position).collect();
+        Self::build(&mut positions, 0);
+        Self { positions }
+    }
+
+    /// The number of positions in the tree.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Whether the tree holds no position.
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// The position of the tree nearest to `target`, or `None` if the tree is empty.
+    pub fn nearest(&self, target: [f32; 3]) -> Option<[f32; 3]> {
+        let mut best = None;
+        Self::search(&self.positions, 0, target, &mut best);
+        best.map(|(position, _)| position)
+    }
+
+    /// The distance from `target` to the nearest position of the tree, or `None` if the tree is
+    /// empty.
+    pub fn nearest_distance(&self, target: [f32; 3]) -> Option<f32> {
+        self.nearest(target)
+            .map(|position| distance_squared(position, target).sqrt())
+    }
+
+    /// The `k` positions of the tree nearest to `target`, from the nearest, or all of them if the
+    /// tree holds fewer.
+    pub fn nearest_k(&self, target: [f32; 3], k: usize) -> Vec<[f32; 3]> {
+        let mut best = Vec::with_capacity(k + 1);
+        if k > 0 {
+            Self::search_k(&self.positions, 0, target, k, &mut best);
+        }
+        best.into_iter().map(|(position, _)| position).collect()
+    }
+
+    fn build(positions: &mut [[f32; 3]], depth: usize) {
+        if positions.len() <= 1 {
+            return;
+        }
+        let axis = depth % 3;
+        let middle = positions.len() / 2;
+        positions.select_nth_unstable_by(middle, |a, b| a[axis].total_cmp(&b[axis]));
+        let (before, after) = positions.split_at_mut(middle);
+        Self::build(before, depth + 1);
+        Self::build(&mut after[1..], depth + 1);
+    }
+
+    fn search(
+        positions: &[[f32; 3]],
+        depth: usize,
+        target: [f32; 3],
+        best: &mut Option<([f32; 3], f32)>,
+    ) {
+        if positions.is_empty() {
+            return;
+        }
+        let axis = depth % 3;
+        let middle = positions.len() / 2;
+        let median = positions[middle];
+
+        let distance = distance_squared(median, target);
+        if best.is_none_or(|(_, best_distance)| distance < best_distance) {
+            *best = Some((median, distance));
+        }
+
+        // Search the side of the target first, then the other if it can hold a nearer position
+        let offset = target[axis] - median[axis];
+        let (near, far) = match offset < 0.0 {
+            true => (&positions[..middle], &positions[middle + 1..]),
+            false => (&positions[middle + 1..], &positions[..middle]),
+        };
+        Self::search(near, depth + 1, target, best);
+        if best.is_none_or(|(_, best_distance)| offset * offset < best_distance) {
+            Self::search(far, depth + 1, target, best);
+        }
+    }
+
+    /// Searches like [`Self::search`], keeping the `k` nearest positions found so far sorted by
+    /// their distance.
+    fn search_k(
+        positions: &[[f32; 3]],
+        depth: usize,
+        target: [f32; 3],
+        k: usize,
+        best: &mut Vec<([f32; 3], f32)>,
+    ) {
+        if positions.is_empty() {
+            return;
+        }
+        let axis = depth % 3;
+        let middle = positions.len() / 2;
+        let median = positions[middle];
+
+        // The farthest of the positions kept, which a nearer one replaces once `k` are kept
+        let bound = |best: &Vec<([f32; 3], f32)>| match best.len() < k {
+            true => f32::INFINITY,
+            false => best[k - 1].1,
+        };
+
+        let distance = distance_squared(median, target);
+        if distance < bound(best) {
+            let index = best.partition_point(|&(_, kept)| kept <= distance);
+            best.insert(index, (median, distance));
+            best.truncate(k);
+        }
+
+        let offset = target[axis] - median[axis];
+        let (near, far) = match offset < 0.0 {
+            true => (&positions[..middle], &positions[middle + 1..]),
+            false => (&positions[middle + 1..], &positions[..middle]),
+        };
+        Self::search_k(near, depth + 1, target, k, best);
+        if offset * offset < bound(best) {
+            Self::search_k(far, depth + 1, target, k, best);
+        }
+    }
+}
+
+fn distance_squared(a: [f32; 3], b: [f32; 3]) -> f32 {
+    (0..3).map(|i| (a[i] - b[i]) * (a[i] - b[i])).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    fn random_points(count: usize, seed: u64) -> Vec<Point> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..count)
+            .map(|_| {
+                let position = [(); 3].map(|_| rng.random_range(-10.0..10.0));
+                Point::new(position, [255; 4])
+            })
+            .collect()
+    }
+
+    /// The distances of the `k` points nearest to `target`, going through every point.
+    fn brute_force(points: &[Point], target: [f32; 3], k: usize) -> Vec<f32> {
+        let mut distances: Vec<f32> = points
+            .iter()
+            .map(|point| distance_squared(point.position, target))
+            .collect();
+        distances.sort_by(f32::total_cmp);
+        distances.truncate(k);
+        distances
+    }
+
+    #[test]
+    fn nearest_k_matches_brute_force() {
+        let points = random_points(2000, 1);
+        let tree = KdTree::new(&points);
+        random_points(50, 2).iter().for_each(|target| {
+            for k in [1, 8, 17] {
+                let nearest = tree.nearest_k(target.position, k);
+                let distances: Vec<f32> = nearest
+                    .iter()
+                    .map(|&position| distance_squared(position, target.position))
+                    .collect();
+                assert_eq!(distances, brute_force(&points, target.position, k));
+            }
+        });
+    }
+
+    #[test]
+    fn nearest_k_returns_every_point_of_a_small_tree() {
+        let points = random_points(5, 3);
+        let tree = KdTree::new(&points);
+        assert_eq!(tree.nearest_k([0.0; 3], 10).len(), 5);
+        assert!(tree.nearest_k([0.0; 3], 0).is_empty());
+        assert!(KdTree::new(&[]).nearest_k([0.0; 3], 3).is_empty());
+    }
+
+    #[test]
+    fn nearest_matches_nearest_k() {
+        let points = random_points(500, 4);
+        let tree = KdTree::new(&points);
+        random_points(20, 5).iter().for_each(|target| {
+            assert_eq!(
+                tree.nearest(target.position),
+                tree.nearest_k(target.position, 1).first().copied()
+            );
+        });
+    }
+}
@@ -0,0 +1,94 @@
+use nannou::{glam::EulerRot, prelude::*, wgpu};
+
+use crate::point::CloudData;
+
+/// How a point cloud is placed in the scene, to composite several scans together.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CloudTransform {
+    /// The offset of the cloud, in the units of the scene.
+    pub translation: [f32; 3],
+    /// The rotation of the cloud around the x, y and z axes, applied in this order, in degrees.
+    pub rotation: [f32; 3],
+    /// The uniform scale of the cloud.
+    pub scale: f32,
+}
+
+impl Default for CloudTransform {
+    fn default() -> Self {
+        Self {
+            translation: [0.0; 3],
+            rotation: [0.0; 3],
+            scale: 1.0,
+        }
+    }
+}
+
+impl CloudTransform {
+    /// The matrix moving the points from the coordinates of the cloud to those of the scene.
+    pub fn matrix(&self) -> Mat4 {
+        let [x, y, z] = self.rotation.map(f32::to_radians);
+        Mat4::from_scale_rotation_translation(
+            Vec3::splat(self.scale),
+            Quat::from_euler(EulerRot::ZYX, z, y, x),
+            Vec3::from(self.translation),
+        )
+    }
+
+    /// Returns the uniforms of the transform.
+    pub fn uniforms(&self) -> TransformUniforms {
+        TransformUniforms {
+            model: self.matrix(),
+        }
+    }
+}
+
+/// The settings of a point cloud of the scene.
+///
+/// The points of each cloud are deformed in the coordinates of the cloud, then placed in the scene
+/// by its transform, where they are colored and cropped.
+#[derive(Clone, Copy, PartialEq)]
+pub struct CloudLayer {
+    pub transform: CloudTransform,
+    /// Whether the cloud is deformed and drawn.
+    pub visible: bool,
+    /// The parameters of the deformation of this cloud, or `None` to follow those of the scene.
+    ///
+    /// The values driven by the simulation, such as the time and the sound, are always those of
+    /// the scene.
+    pub overrides: Option<CloudData>,
+}
+
+impl Default for CloudLayer {
+    fn default() -> Self {
+        Self {
+            transform: CloudTransform::default(),
+            visible: true,
+            overrides: None,
+        }
+    }
+}
+
+impl CloudLayer {
+    /// The parameters the points of the cloud are deformed with, given those of the scene.
+    pub fn cloud_data(&self, scene: &CloudData) -> CloudData {
+        self.overrides
+            .map_or(*scene, |overrides| overrides.driven_by(scene))
+    }
+}
+
+/// The uniforms of the transform of a cloud.
+///
+/// The struct is sent as-is to the shaders, so its layout must match the `Model` struct declared
+/// in `render.wgsl` and `fallback.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TransformUniforms {
+    model: Mat4,
+}
+
+impl TransformUniforms {
+    /// Returns the struct as a byte slice.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { wgpu::bytes::from(self) }
+    }
+}
@@ -1,4 +1,43 @@
+pub mod attractor;
+pub mod background;
 pub mod camera;
+pub mod clip;
+pub mod collision;
+pub mod color;
+pub mod coloring;
+pub mod diff;
+pub mod effects;
+#[cfg(feature = "files")]
+pub mod export;
+pub mod exposure;
+#[cfg(target_arch = "wasm32")]
+pub mod fetch;
+pub mod fog;
+pub mod gaussian;
+pub mod gizmo;
+pub mod glow;
+pub mod gravity;
+pub mod history;
+#[cfg(feature = "hot-reload")]
+pub mod hot_reload;
+pub mod impulse;
+pub mod kdtree;
+pub mod layer;
+pub mod lighting;
 pub mod loader;
+pub mod lod;
+pub mod mask;
+pub mod metadata;
+pub mod picking;
 pub mod pipeline;
 pub mod point;
+pub mod pulse;
+pub mod readback;
+pub mod registry;
+pub mod resolution;
+pub mod spectrum;
+pub mod still;
+#[cfg(feature = "files")]
+pub mod streaming;
+pub mod trail;
+pub mod warp;
@@ -0,0 +1,75 @@
+use nannou::{prelude::*, wgpu};
+use serde::{Deserialize, Serialize};
+
+/// How the points are shaded from their normals.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShadingMode {
+    /// The points are drawn in their colors, as loaded.
+    Unlit,
+    /// The points are lit by a distant light fixed in the scene, like the sun.
+    Lambert,
+    /// The points are lit by a light at the camera, so the surfaces facing it are the brightest.
+    Headlight,
+}
+
+/// The diffuse lighting of the points, so a scan reads as surfaces instead of a cloud of
+/// confetti.
+///
+/// Only the points with a normal are lit, the others keep their colors. The normals are estimated
+/// with [`crate::loader::estimate_normals`] and face either side of the surface, so both sides are
+/// lit alike. The points drawn at rest by the devices without compute shaders are never lit.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LightingSettings {
+    pub mode: ShadingMode,
+    /// The direction towards the light of the Lambert mode, in the coordinates of the scene.
+    pub direction: [f32; 3],
+    /// The light reaching the surfaces facing away from the light, in the range [0, 1], so they
+    /// are not drawn black.
+    pub ambient: f32,
+}
+
+impl Default for LightingSettings {
+    fn default() -> Self {
+        Self {
+            mode: ShadingMode::Unlit,
+            direction: [0.3, 1.0, 0.5],
+            ambient: 0.25,
+        }
+    }
+}
+
+/// The uniforms of the lighting of the points.
+///
+/// The struct is sent as-is to the shaders, so its layout must match the `Lighting` struct declared
+/// in `render.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct LightingUniforms {
+    direction: [f32; 3],
+    mode: u32,
+    ambient: f32,
+    _padding: [u32; 3],
+}
+
+impl LightingUniforms {
+    pub fn new(settings: &LightingSettings) -> Self {
+        let mode = match settings.mode {
+            ShadingMode::Unlit => 0,
+            ShadingMode::Lambert => 1,
+            ShadingMode::Headlight => 2,
+        };
+        Self {
+            direction: Vec3::from(settings.direction)
+                .normalize_or_zero()
+                .to_array(),
+            mode,
+            ambient: settings.ambient.clamp(0.0, 1.0),
+            _padding: [0; 3],
+        }
+    }
+
+    /// Returns the struct as a byte slice.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { wgpu::bytes::from(self) }
+    }
+}
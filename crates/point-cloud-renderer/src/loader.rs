@@ -1,17 +1,43 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver, Sender, TryRecvError},
+    },
+};
+
+#[cfg(feature = "files")]
 use e57::{CartesianCoordinate, E57Reader};
-use rand::Rng;
-use rayon::iter::{ParallelBridge, ParallelIterator};
+use nannou::glam::{DVec3, Vec3};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+#[cfg(feature = "files")]
+use rayon::iter::ParallelBridge;
+use rayon::{
+    iter::{IntoParallelIterator, IntoParallelRefMutIterator, ParallelIterator},
+    slice::ParallelSlice,
+};
 
-use crate::point::Point;
+use crate::{
+    color::ColorSpace,
+    kdtree::KdTree,
+    point::Point,
+    registry::{Generator, LoadOptions, Loader, Registry},
+};
 
-/// Generates a random point cloud with the given number of points.
+/// Generates a random point cloud with the given number of points, the same for the same seed.
 pub fn generate_random_point_cloud(
     num_points: usize,
     range_x: (f32, f32),
     range_y: (f32, f32),
     range_z: (f32, f32),
+    seed: u64,
 ) -> Vec<Point> {
-    let mut rng = rand::rng();
+    let mut rng = StdRng::seed_from_u64(seed);
     let mut points = Vec::with_capacity(num_points);
 
     (0..num_points).for_each(|_| {
@@ -37,8 +63,184 @@ pub fn generate_random_point_cloud(
     points
 }
 
+/// Generates a point cloud with the given number of points spread evenly over a sphere, colored by
+/// their direction from its center, the same for the same seed.
+pub fn generate_sphere_point_cloud(num_points: usize, radius: f32, seed: u64) -> Vec<Point> {
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    (0..num_points)
+        .map(|_| {
+            // A uniform height and angle spread the points evenly over the sphere
+            let z: f32 = rng.random_range(-1.0..1.0);
+            let angle: f32 = rng.random_range(0.0..std::f32::consts::TAU);
+            let ring = (1.0 - z * z).sqrt();
+            let direction = [ring * angle.cos(), ring * angle.sin(), z];
+
+            let position = direction.map(|axis| axis * radius);
+            let [red, green, blue] = direction.map(|axis| ((axis + 1.0) / 2.0 * 255.0) as u8);
+            Point::new(position, [red, green, blue, 255])
+        })
+        .collect()
+}
+
+/// The points of the cube generated by [`generate_random_point_cloud`], 200 units wide.
+pub struct RandomCube;
+
+impl Generator for RandomCube {
+    fn name(&self) -> &str {
+        "Random cube"
+    }
+
+    fn generate(&self, count: usize, seed: u64) -> Vec<Point> {
+        let range = (-100.0, 100.0);
+        generate_random_point_cloud(count, range, range, range, seed)
+    }
+}
+
+/// The points of the sphere generated by [`generate_sphere_point_cloud`], 100 units in radius.
+pub struct RandomSphere;
+
+impl Generator for RandomSphere {
+    fn name(&self) -> &str {
+        "Random sphere"
+    }
+
+    fn generate(&self, count: usize, seed: u64) -> Vec<Point> {
+        generate_sphere_point_cloud(count, 100.0, seed)
+    }
+}
+
+/// The progress of a point cloud being loaded, shared by the loading thread and its handle.
+#[derive(Default)]
+pub struct LoadProgress {
+    read: AtomicU64,
+    total: AtomicU64,
+}
+
+impl LoadProgress {
+    /// Sets the total number of points of the file, once it is known.
+    pub fn set_total(&self, total: u64) {
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    /// Adds to the number of points read so far.
+    pub fn add(&self, read: u64) {
+        self.read.fetch_add(read, Ordering::Relaxed);
+    }
+}
+
+/// A point cloud being loaded by a background thread, or any other content read from a file,
+/// such as Gaussian splats.
+///
+/// The handle is meant to be polled every frame with [`LoadHandle::try_take`], so the window keeps
+/// responding while large scans are read. Dropping the handle discards the points once they are
+/// read.
+pub struct LoadHandle<T = Vec<Point>> {
+    path: String,
+    progress: Arc<LoadProgress>,
+    receiver: Receiver<LoadResult<T>>,
+}
+
+/// The points of a loaded point cloud, or why it could not be loaded.
+type LoadResult<T> = Result<T, &'static str>;
+
+impl<T: Send + 'static> LoadHandle<T> {
+    /// Starts loading the file at `path` with `load` on a new thread.
+    ///
+    /// The web has no threads, so the file is loaded right away there.
+    pub(crate) fn spawn<F>(path: &str, load: F) -> Self
+    where
+        F: FnOnce(&str, &LoadProgress) -> Result<T, &'static str> + Send + 'static,
+    {
+        let (handle, sender, progress) = Self::channel(path);
+        let thread_path = path.to_owned();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        thread::spawn(move || {
+            sender.send(load(&thread_path, &progress)).ok();
+        });
+        #[cfg(target_arch = "wasm32")]
+        sender.send(load(&thread_path, &progress)).ok();
+
+        handle
+    }
+
+    /// Creates a handle for the file at `path`, along with the sender of its result and the
+    /// progress its loader reports to.
+    pub(crate) fn channel(path: &str) -> (Self, Sender<LoadResult<T>>, Arc<LoadProgress>) {
+        let progress = Arc::new(LoadProgress::default());
+        let (sender, receiver) = mpsc::channel();
+        let handle = Self {
+            path: path.to_owned(),
+            progress: progress.clone(),
+            receiver,
+        };
+        (handle, sender, progress)
+    }
+
+    /// The path of the file being loaded.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The number of points read so far and the total number of points of the file.
+    ///
+    /// The total is 0 until the header of the file is read.
+    pub fn progress(&self) -> (u64, u64) {
+        (
+            self.progress.read.load(Ordering::Relaxed),
+            self.progress.total.load(Ordering::Relaxed),
+        )
+    }
+
+    /// The fraction of the points of the file read so far, in the range [0, 1].
+    pub fn fraction(&self) -> f32 {
+        let (read, total) = self.progress();
+        match total {
+            0 => 0.0,
+            total => (read as f64 / total as f64).min(1.0) as f32,
+        }
+    }
+
+    /// Returns the result of the load if it is complete, without blocking.
+    ///
+    /// The result is only returned once.
+    pub fn try_take(&self) -> Option<Result<T, &'static str>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err("The loading thread stopped")),
+        }
+    }
+}
+
+/// Starts reading a point cloud from an E57 file on a background thread.
+///
+/// See [`read_e57`].
+#[cfg(feature = "files")]
+pub fn read_e57_async(path: &str) -> LoadHandle {
+    LoadHandle::spawn(path, read_e57_reporting)
+}
+
+/// Starts reading a point cloud from a file on a background thread, picking the format from its
+/// extension.
+///
+/// See [`read_point_cloud`].
+pub fn read_point_cloud_async(path: &str, classes: &[u8]) -> LoadHandle {
+    let options = LoadOptions {
+        classes: classes.to_vec(),
+    };
+    Registry::default().load_async(path, options)
+}
+
 /// Reads a point cloud from an E57 file and returns the points.
+#[cfg(feature = "files")]
 pub fn read_e57(path: &str) -> Result<Vec<Point>, &'static str> {
+    read_e57_reporting(path, &LoadProgress::default())
+}
+
+#[cfg(feature = "files")]
+fn read_e57_reporting(path: &str, progress: &LoadProgress) -> Result<Vec<Point>, &'static str> {
     // Open E57 input file for reading
     let mut file = match E57Reader::from_file(path) {
         Ok(file) => file,
@@ -46,9 +248,16 @@ pub fn read_e57(path: &str) -> Result<Vec<Point>, &'static str> {
     };
 
     let mut points = Vec::new();
+    let pointclouds = file.pointclouds();
+    progress.set_total(
+        pointclouds
+            .iter()
+            .map(|pointcloud| pointcloud.records)
+            .sum(),
+    );
 
     // Loop over all point clouds in the E57 file
-    for pointcloud in file.pointclouds().into_iter() {
+    for pointcloud in pointclouds.into_iter() {
         let mut iter = match file.pointcloud_simple(&pointcloud) {
             Ok(iter) => iter,
             Err(_) => return Err("Failed to read point cloud"),
@@ -64,6 +273,7 @@ pub fn read_e57(path: &str) -> Result<Vec<Point>, &'static str> {
         let mut cloud_points: Vec<Point> = iter
             .par_bridge()
             .filter_map(|p| {
+                progress.add(1);
                 let p = match p {
                     Ok(p) => p,
                     Err(_) => return None,
@@ -84,7 +294,8 @@ pub fn read_e57(path: &str) -> Result<Vec<Point>, &'static str> {
 
                 // If available, write RGB color or intensity color values
                 if let Some(color) = p.color {
-                    point.set_color_f32([color.red, color.green, color.blue, 1.0]);
+                    point
+                        .set_color_f32([color.red, color.green, color.blue, 1.0], ColorSpace::Srgb);
                 }
 
                 Some(point)
@@ -96,3 +307,1068 @@ pub fn read_e57(path: &str) -> Result<Vec<Point>, &'static str> {
 
     Ok(points)
 }
+
+/// Reads a point cloud from a file, picking the format from its extension.
+///
+/// The supported formats are those of the default [`Registry`]: E57 (`.e57`), PLY (`.ply`) and LAS
+/// (`.las`, or `.laz` when compressed). The points of LAS files are filtered by `classes` like
+/// [`read_las`] does; the other formats are not classified.
+pub fn read_point_cloud(path: &str, classes: &[u8]) -> Result<Vec<Point>, &'static str> {
+    let options = LoadOptions {
+        classes: classes.to_vec(),
+    };
+    Registry::default().load(path, &options)
+}
+
+/// The E57 format, read by [`read_e57`].
+#[cfg(feature = "files")]
+pub struct E57Loader;
+
+#[cfg(feature = "files")]
+impl Loader for E57Loader {
+    fn name(&self) -> &str {
+        "E57"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["e57"]
+    }
+
+    fn load(
+        &self,
+        path: &str,
+        _options: &LoadOptions,
+        progress: &LoadProgress,
+    ) -> Result<Vec<Point>, &'static str> {
+        read_e57_reporting(path, progress)
+    }
+}
+
+/// The PLY format, read by [`read_ply`].
+pub struct PlyLoader;
+
+impl Loader for PlyLoader {
+    fn name(&self) -> &str {
+        "PLY"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ply"]
+    }
+
+    fn load(
+        &self,
+        path: &str,
+        _options: &LoadOptions,
+        progress: &LoadProgress,
+    ) -> Result<Vec<Point>, &'static str> {
+        read_ply_reporting(path, progress)
+    }
+
+    fn load_bytes(
+        &self,
+        bytes: &[u8],
+        _options: &LoadOptions,
+        progress: &LoadProgress,
+    ) -> Result<Vec<Point>, &'static str> {
+        read_ply_from(bytes, progress)
+    }
+}
+
+/// The LAS format and its compressed LAZ variant, read by [`read_las`].
+#[cfg(feature = "files")]
+pub struct LasLoader;
+
+#[cfg(feature = "files")]
+impl Loader for LasLoader {
+    fn name(&self) -> &str {
+        "LAS"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["las", "laz"]
+    }
+
+    fn load(
+        &self,
+        path: &str,
+        options: &LoadOptions,
+        progress: &LoadProgress,
+    ) -> Result<Vec<Point>, &'static str> {
+        read_las_reporting(path, &options.classes, progress)
+    }
+}
+
+/// Reads a point cloud from an ASCII or binary little-endian PLY file and returns the points.
+///
+/// The positions are read in the coordinate system of the renderer (Y-up), like they are written
+/// by [`write_ply`]. The colors are read from the `red`, `green`, `blue` and `alpha` properties of
+/// the vertices if available, else from their `intensity` normalized over the cloud. Vertices
+/// without either are white.
+pub fn read_ply(path: &str) -> Result<Vec<Point>, &'static str> {
+    read_ply_reporting(path, &LoadProgress::default())
+}
+
+/// Reads a point cloud from a PLY file already in memory, such as one fetched over the network.
+///
+/// See [`read_ply`].
+pub fn read_ply_bytes(bytes: &[u8]) -> Result<Vec<Point>, &'static str> {
+    read_ply_from(bytes, &LoadProgress::default())
+}
+
+fn read_ply_reporting(path: &str, progress: &LoadProgress) -> Result<Vec<Point>, &'static str> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(_) => return Err("Failed to open PLY file"),
+    };
+    read_ply_from(BufReader::new(file), progress)
+}
+
+fn read_ply_from(
+    mut reader: impl BufRead + Send,
+    progress: &LoadProgress,
+) -> Result<Vec<Point>, &'static str> {
+    let (format, elements) = read_ply_header(&mut reader)?;
+
+    // Skip the elements stored before the vertices, such as the faces of a mesh
+    let Some(vertex) = elements.iter().position(|element| element.name == "vertex") else {
+        return Err("Missing PLY vertices");
+    };
+    for element in &elements[..vertex] {
+        skip_ply_element(&mut reader, format, element)?;
+    }
+
+    let element = &elements[vertex];
+    let layout = VertexLayout::new(element)?;
+    progress.set_total(element.count as u64);
+    let mut points = match format {
+        PlyFormat::Ascii => {
+            // The lines are read before they are parsed, so the parallel parse keeps the order of
+            // the vertices in the file
+            let lines = match reader
+                .lines()
+                .take(element.count)
+                .collect::<Result<Vec<_>, _>>()
+            {
+                Ok(lines) => lines,
+                Err(_) => return Err("Failed to read PLY vertices"),
+            };
+            if lines.len() < element.count {
+                return Err("Truncated PLY vertices");
+            }
+            lines
+                .into_par_iter()
+                .map(|line| {
+                    progress.add(1);
+                    let values: Vec<f64> = match line.split_whitespace().map(str::parse).collect() {
+                        Ok(values) => values,
+                        Err(_) => return Err("Invalid PLY vertex"),
+                    };
+                    if values.len() < element.properties.len() {
+                        return Err("Invalid PLY vertex");
+                    }
+                    Ok(layout.to_point(|field| values[field.index]))
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        }
+        PlyFormat::BinaryLittleEndian => {
+            // The vertices are read in chunks to report the progress of the load
+            const CHUNK_SIZE: usize = 1 << 20;
+            // The count of the header is not trusted with an allocation, as a truncated or
+            // corrupted file may claim any number of vertices
+            let mut points = Vec::with_capacity(element.count.min(CHUNK_SIZE));
+            let mut bytes = Vec::new();
+            while points.len() < element.count {
+                let count = CHUNK_SIZE.min(element.count - points.len());
+                bytes.resize(count * layout.stride, 0);
+                if reader.read_exact(&mut bytes).is_err() {
+                    return Err("Truncated PLY vertices");
+                }
+                let mut chunk_points: Vec<Point> = bytes
+                    .par_chunks_exact(layout.stride)
+                    .map(|record| {
+                        layout.to_point(|field| field.kind.read_le(&record[field.offset..]))
+                    })
+                    .collect();
+                points.append(&mut chunk_points);
+                progress.add(count as u64);
+            }
+            points
+        }
+    };
+
+    if layout.color.is_none() && layout.intensity.is_some() {
+        normalize_intensities(&mut points);
+    }
+
+    Ok(points)
+}
+
+/// Reads a point cloud from a LAS or compressed LAZ file, such as an aerial lidar scan, and
+/// returns the points.
+///
+/// Only the points whose classification code is in `classes` are kept (for example 2 for the
+/// ground and 6 for buildings), or every point if `classes` is empty. The points are colored with
+/// their RGB values if the file has some, else with their intensity normalized over the cloud.
+///
+/// The coordinates of lidar scans are usually georeferenced and too large to be represented
+/// precisely as `f32`, so the cloud is centered on the origin.
+#[cfg(feature = "files")]
+pub fn read_las(path: &str, classes: &[u8]) -> Result<Vec<Point>, &'static str> {
+    read_las_reporting(path, classes, &LoadProgress::default())
+}
+
+#[cfg(feature = "files")]
+fn read_las_reporting(
+    path: &str,
+    classes: &[u8],
+    progress: &LoadProgress,
+) -> Result<Vec<Point>, &'static str> {
+    let mut reader = match las::Reader::from_path(path) {
+        Ok(reader) => reader,
+        Err(_) => return Err("Failed to open LAS file"),
+    };
+
+    let header = reader.header();
+    let has_color = header.point_format().has_color;
+    progress.set_total(header.number_of_points());
+    let bounds = header.bounds();
+    let center = [
+        (bounds.min.x + bounds.max.x) / 2.0,
+        (bounds.min.y + bounds.max.y) / 2.0,
+        (bounds.min.z + bounds.max.z) / 2.0,
+    ];
+
+    // The reader is not thread-safe, so the points are read in chunks converted in parallel
+    const CHUNK_SIZE: u64 = 1 << 20;
+    let mut points = Vec::new();
+    loop {
+        let chunk = match reader.read_points(CHUNK_SIZE) {
+            Ok(chunk) => chunk,
+            Err(_) => return Err("Failed to read LAS points"),
+        };
+        if chunk.is_empty() {
+            break;
+        }
+        progress.add(chunk.len() as u64);
+
+        let mut chunk_points: Vec<Point> = chunk
+            .into_par_iter()
+            .filter_map(|p| {
+                if !classes.is_empty() && !classes.contains(&u8::from(p.classification)) {
+                    return None;
+                }
+
+                let mut point = Point::default();
+
+                // We use the Z-up coordinate system,
+                // so we swap the Y and Z coordinates
+                point.position[0] = -(p.x - center[0]) as f32;
+                point.position[1] = (p.z - center[2]) as f32;
+                point.position[2] = (p.y - center[1]) as f32;
+
+                // The raw values are kept until the range of the cloud is known
+                match p.color {
+                    Some(color) if has_color => point.set_color_f32(
+                        [color.red as f32, color.green as f32, color.blue as f32, 1.0],
+                        ColorSpace::Linear,
+                    ),
+                    _ => {
+                        point.set_color_f32([p.intensity as f32, 0.0, 0.0, 1.0], ColorSpace::Linear)
+                    }
+                }
+
+                Some(point)
+            })
+            .collect();
+
+        points.append(&mut chunk_points);
+    }
+
+    if points.is_empty() {
+        return Err("No point of the LAS file is in the selected classes");
+    }
+
+    if has_color {
+        // Many files store 8-bit colors in the 16-bit channels of the format
+        let max = points
+            .iter()
+            .map(|point| point.color[0].max(point.color[1]).max(point.color[2]))
+            .fold(0.0, f32::max);
+        let full_scale = if max <= u8::MAX as f32 {
+            u8::MAX as f32
+        } else {
+            u16::MAX as f32
+        };
+        points.iter_mut().for_each(|point| {
+            let [red, green, blue, alpha] = point.color;
+            let [red, green, blue] = [red, green, blue].map(|c| c / full_scale);
+            point.set_color_f32([red, green, blue, alpha], ColorSpace::Srgb);
+        });
+    } else {
+        normalize_intensities(&mut points);
+    }
+
+    Ok(points)
+}
+
+/// Colors the points in grayscale from their raw intensity, normalized over the cloud.
+///
+/// The raw intensity of each point is expected in its red channel. The normalized intensities are
+/// taken as sRGB, so they look evenly spaced.
+fn normalize_intensities(points: &mut [Point]) {
+    let (min, max) = points
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(min, max), point| {
+            (min.min(point.color[0]), max.max(point.color[0]))
+        });
+    let range = (max - min).max(f32::EPSILON);
+    points.iter_mut().for_each(|point| {
+        let intensity = (point.color[0] - min) / range;
+        point.set_color_f32(
+            [intensity, intensity, intensity, point.color[3]],
+            ColorSpace::Srgb,
+        );
+    });
+}
+
+/// Estimates the normals of the points from the plane best fitting each point and its `k` nearest
+/// neighbors, so the scans can be lit as the surfaces they were captured from.
+///
+/// The normal is the direction the neighbors spread the least along, the eigenvector of the
+/// smallest eigenvalue of their covariance. Which side of the surface it faces cannot be told from
+/// the neighbors alone, so the shaders light both sides alike. The points whose neighbors lie on a
+/// line, or coincide, are left without a normal and drawn unlit.
+pub fn estimate_normals(points: &mut [Point], k: usize) {
+    let tree = KdTree::new(points);
+    // The point itself is among its nearest positions, and a plane needs at least three
+    let k = k.max(2) + 1;
+    points.par_iter_mut().for_each(|point| {
+        let neighbors = tree.nearest_k(point.position, k);
+        point.set_normal(fit_normal(&neighbors).map(|normal| normal.to_array()));
+    });
+}
+
+/// The normal of the plane best fitting the positions, if they span one.
+fn fit_normal(positions: &[[f32; 3]]) -> Option<Vec3> {
+    if positions.len() < 3 {
+        return None;
+    }
+    let positions: Vec<DVec3> = positions
+        .iter()
+        .map(|&[x, y, z]| DVec3::new(x as f64, y as f64, z as f64))
+        .collect();
+    let mean = positions
+        .iter()
+        .fold(DVec3::ZERO, |sum, &position| sum + position)
+        / positions.len() as f64;
+
+    // The covariance of the positions, symmetric, as its diagonal and the entries above it
+    let (mut diagonal, mut upper) = (DVec3::ZERO, DVec3::ZERO);
+    positions.iter().for_each(|&position| {
+        let d = position - mean;
+        diagonal += d * d;
+        upper += DVec3::new(d.x * d.y, d.x * d.z, d.y * d.z);
+    });
+
+    // The eigenvalues of a symmetric 3x3 matrix in closed form, from the trigonometric solution
+    // of its characteristic polynomial
+    let q = (diagonal.x + diagonal.y + diagonal.z) / 3.0;
+    let p = (((diagonal - DVec3::splat(q)).length_squared() + 2.0 * upper.length_squared()) / 6.0)
+        .sqrt();
+    if p <= f64::EPSILON * q.abs().max(f64::MIN_POSITIVE) {
+        return None;
+    }
+    let [xx, yy, zz] = ((diagonal - DVec3::splat(q)) / p).to_array();
+    let [xy, xz, yz] = (upper / p).to_array();
+    let determinant =
+        xx * (yy * zz - yz * yz) - xy * (xy * zz - yz * xz) + xz * (xy * yz - yy * xz);
+    let angle = (determinant / 2.0).clamp(-1.0, 1.0).acos() / 3.0;
+    let largest = q + 2.0 * p * angle.cos();
+    let smallest = q + 2.0 * p * (angle + 2.0 * std::f64::consts::PI / 3.0).cos();
+
+    // The eigenvector is orthogonal to the rows of the covariance less the eigenvalue, so it is
+    // along the largest of their cross products
+    let rows = [
+        DVec3::new(diagonal.x - smallest, upper.x, upper.y),
+        DVec3::new(upper.x, diagonal.y - smallest, upper.z),
+        DVec3::new(upper.y, upper.z, diagonal.z - smallest),
+    ];
+    let normal = [
+        rows[0].cross(rows[1]),
+        rows[0].cross(rows[2]),
+        rows[1].cross(rows[2]),
+    ]
+    .into_iter()
+    .max_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))?;
+
+    // The cross products vanish when the two smallest eigenvalues are equal, the positions lying
+    // on a line
+    let spread = largest - smallest;
+    if normal.length() <= 1e-6 * spread * spread {
+        return None;
+    }
+    let normal = normal.normalize();
+    Some(Vec3::new(normal.x as f32, normal.y as f32, normal.z as f32))
+}
+
+/// The encodings of the body of a PLY file.
+#[derive(Clone, Copy)]
+pub(crate) enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+/// The scalar types of the properties of a PLY file.
+#[derive(Clone, Copy)]
+pub(crate) enum PlyType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl PlyType {
+    fn parse(name: &str) -> Option<Self> {
+        let kind = match name {
+            "char" | "int8" => Self::Int8,
+            "uchar" | "uint8" => Self::UInt8,
+            "short" | "int16" => Self::Int16,
+            "ushort" | "uint16" => Self::UInt16,
+            "int" | "int32" => Self::Int32,
+            "uint" | "uint32" => Self::UInt32,
+            "float" | "float32" => Self::Float32,
+            "double" | "float64" => Self::Float64,
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    /// The number of bytes taken by a value of this type.
+    pub(crate) fn size(self) -> usize {
+        match self {
+            Self::Int8 | Self::UInt8 => 1,
+            Self::Int16 | Self::UInt16 => 2,
+            Self::Int32 | Self::UInt32 | Self::Float32 => 4,
+            Self::Float64 => 8,
+        }
+    }
+
+    /// The value of a color channel of this type at full intensity.
+    fn full_scale(self) -> f64 {
+        match self {
+            Self::Int8 => i8::MAX as f64,
+            Self::UInt8 => u8::MAX as f64,
+            Self::Int16 => i16::MAX as f64,
+            Self::UInt16 => u16::MAX as f64,
+            Self::Int32 => i32::MAX as f64,
+            Self::UInt32 => u32::MAX as f64,
+            Self::Float32 | Self::Float64 => 1.0,
+        }
+    }
+
+    /// Reads a little-endian value of this type from the start of the bytes.
+    pub(crate) fn read_le(self, bytes: &[u8]) -> f64 {
+        let mut array = [0; 8];
+        array[..self.size()].copy_from_slice(&bytes[..self.size()]);
+        let [b0, b1, b2, b3, b4, b5, b6, b7] = array;
+        match self {
+            Self::Int8 => b0 as i8 as f64,
+            Self::UInt8 => b0 as f64,
+            Self::Int16 => i16::from_le_bytes([b0, b1]) as f64,
+            Self::UInt16 => u16::from_le_bytes([b0, b1]) as f64,
+            Self::Int32 => i32::from_le_bytes([b0, b1, b2, b3]) as f64,
+            Self::UInt32 => u32::from_le_bytes([b0, b1, b2, b3]) as f64,
+            Self::Float32 => f32::from_le_bytes([b0, b1, b2, b3]) as f64,
+            Self::Float64 => f64::from_le_bytes([b0, b1, b2, b3, b4, b5, b6, b7]),
+        }
+    }
+}
+
+/// A property of the elements of a PLY file.
+pub(crate) enum PlyProperty {
+    Scalar { name: String, kind: PlyType },
+    List { count: PlyType, item: PlyType },
+}
+
+/// A group of elements of a PLY file, such as its vertices or faces.
+pub(crate) struct PlyElement {
+    pub(crate) name: String,
+    pub(crate) count: usize,
+    pub(crate) properties: Vec<PlyProperty>,
+}
+
+/// Reads a line of the header of a PLY file.
+fn read_ply_line(reader: &mut impl BufRead, line: &mut String) -> Result<(), &'static str> {
+    line.clear();
+    match reader.read_line(line) {
+        Ok(0) | Err(_) => Err("Failed to read PLY header"),
+        Ok(_) => Ok(()),
+    }
+}
+
+/// Reads the header of a PLY file, leaving the reader at the start of its body.
+pub(crate) fn read_ply_header(
+    reader: &mut impl BufRead,
+) -> Result<(PlyFormat, Vec<PlyElement>), &'static str> {
+    let mut line = String::new();
+    read_ply_line(reader, &mut line)?;
+    if line.trim_end() != "ply" {
+        return Err("Not a PLY file");
+    }
+
+    let mut format = None;
+    let mut elements: Vec<PlyElement> = Vec::new();
+    loop {
+        read_ply_line(reader, &mut line)?;
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("format") => {
+                format = match words.next() {
+                    Some("ascii") => Some(PlyFormat::Ascii),
+                    Some("binary_little_endian") => Some(PlyFormat::BinaryLittleEndian),
+                    _ => return Err("Unsupported PLY format"),
+                };
+            }
+            Some("element") => {
+                let name = words.next();
+                let count = words.next().and_then(|count| count.parse().ok());
+                let (Some(name), Some(count)) = (name, count) else {
+                    return Err("Invalid PLY element");
+                };
+                elements.push(PlyElement {
+                    name: name.to_owned(),
+                    count,
+                    properties: Vec::new(),
+                });
+            }
+            Some("property") => {
+                let Some(element) = elements.last_mut() else {
+                    return Err("PLY property outside of an element");
+                };
+                let property = match words.next() {
+                    Some("list") => {
+                        let count = words.next().and_then(PlyType::parse);
+                        let item = words.next().and_then(PlyType::parse);
+                        let (Some(count), Some(item)) = (count, item) else {
+                            return Err("Invalid PLY property");
+                        };
+                        PlyProperty::List { count, item }
+                    }
+                    kind => {
+                        let kind = kind.and_then(PlyType::parse);
+                        let (Some(kind), Some(name)) = (kind, words.next()) else {
+                            return Err("Invalid PLY property");
+                        };
+                        PlyProperty::Scalar {
+                            name: name.to_owned(),
+                            kind,
+                        }
+                    }
+                };
+                element.properties.push(property);
+            }
+            Some("end_header") => break,
+            // Comments, object info and empty lines
+            _ => {}
+        }
+    }
+
+    match format {
+        Some(format) => Ok((format, elements)),
+        None => Err("Missing PLY format"),
+    }
+}
+
+/// Skips the data of an element in the body of a PLY file.
+pub(crate) fn skip_ply_element(
+    reader: &mut impl BufRead,
+    format: PlyFormat,
+    element: &PlyElement,
+) -> Result<(), &'static str> {
+    let mut line = String::new();
+    for _ in 0..element.count {
+        if let PlyFormat::Ascii = format {
+            if read_ply_line(reader, &mut line).is_err() {
+                return Err("Truncated PLY element");
+            }
+            continue;
+        }
+
+        for property in &element.properties {
+            let size = match property {
+                PlyProperty::Scalar { kind, .. } => kind.size(),
+                PlyProperty::List { count, item } => {
+                    let mut bytes = [0; 8];
+                    if reader.read_exact(&mut bytes[..count.size()]).is_err() {
+                        return Err("Truncated PLY element");
+                    }
+                    count.read_le(&bytes) as usize * item.size()
+                }
+            };
+            let skipped = std::io::copy(&mut reader.take(size as u64), &mut std::io::sink());
+            if !matches!(skipped, Ok(skipped) if skipped == size as u64) {
+                return Err("Truncated PLY element");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The location of a property in a vertex of a PLY file.
+#[derive(Clone, Copy)]
+struct Field {
+    /// The index of the property, which is its index among the values of an ASCII line.
+    index: usize,
+    /// The offset of the property in bytes, in a binary record.
+    offset: usize,
+    kind: PlyType,
+}
+
+/// The properties of the vertices of a PLY file that are mapped to a [`Point`].
+struct VertexLayout {
+    position: [Field; 3],
+    color: Option<[Field; 3]>,
+    alpha: Option<Field>,
+    intensity: Option<Field>,
+    normal: Option<[Field; 3]>,
+    /// The size of a vertex in bytes, in a binary file.
+    stride: usize,
+}
+
+impl VertexLayout {
+    fn new(element: &PlyElement) -> Result<Self, &'static str> {
+        let mut fields = HashMap::new();
+        let mut offset = 0;
+        for (index, property) in element.properties.iter().enumerate() {
+            let PlyProperty::Scalar { name, kind } = property else {
+                return Err("Unsupported list property in PLY vertices");
+            };
+            fields.insert(
+                name.to_lowercase(),
+                Field {
+                    index,
+                    offset,
+                    kind: *kind,
+                },
+            );
+            offset += kind.size();
+        }
+
+        // Find the first property with one of the usual names of an attribute
+        let find = |names: &[&str]| names.iter().find_map(|name| fields.get(*name).copied());
+        let (Some(x), Some(y), Some(z)) = (find(&["x"]), find(&["y"]), find(&["z"])) else {
+            return Err("Missing PLY vertex position");
+        };
+        let red = find(&["red", "r", "diffuse_red"]);
+        let green = find(&["green", "g", "diffuse_green"]);
+        let blue = find(&["blue", "b", "diffuse_blue"]);
+        let color = match (red, green, blue) {
+            (Some(red), Some(green), Some(blue)) => Some([red, green, blue]),
+            _ => None,
+        };
+        let normal = match (
+            find(&["nx", "normal_x"]),
+            find(&["ny", "normal_y"]),
+            find(&["nz", "normal_z"]),
+        ) {
+            (Some(x), Some(y), Some(z)) => Some([x, y, z]),
+            _ => None,
+        };
+
+        Ok(Self {
+            position: [x, y, z],
+            color,
+            alpha: find(&["alpha", "a", "diffuse_alpha"]),
+            intensity: find(&["intensity", "scalar_intensity"]),
+            normal,
+            stride: offset,
+        })
+    }
+
+    /// Builds a point from the values of the properties of a vertex.
+    fn to_point(&self, value: impl Fn(Field) -> f64) -> Point {
+        let mut point = Point::default();
+        point.set_position(self.position.map(|field| value(field) as f32));
+
+        let channel =
+            |field: Field| (value(field) / field.kind.full_scale()).clamp(0.0, 1.0) as f32;
+        let alpha = self.alpha.map_or(1.0, channel);
+        match (self.color, self.intensity) {
+            (Some(color), _) => {
+                let [red, green, blue] = color.map(channel);
+                point.set_color_f32([red, green, blue, alpha], ColorSpace::Srgb);
+            }
+            // The raw intensity is kept until the range of the cloud is known
+            (None, Some(intensity)) => {
+                let raw = [value(intensity) as f32, 0.0, 0.0, alpha];
+                point.set_color_f32(raw, ColorSpace::Linear);
+            }
+            (None, None) => point.set_color_f32([1.0, 1.0, 1.0, alpha], ColorSpace::Srgb),
+        }
+
+        // A null normal is written for the points whose normal is not known
+        if let Some(normal) = self.normal {
+            let normal = Vec3::from(normal.map(|field| value(field) as f32)).normalize_or_zero();
+            if normal != Vec3::ZERO {
+                point.set_normal(Some(normal.to_array()));
+            }
+        }
+
+        point
+    }
+}
+
+/// Writes a point cloud to a binary little-endian PLY file.
+///
+/// The positions are written in the coordinate system of the renderer (Y-up) and the colors are
+/// written as 8-bit sRGB values, like the PLY files are read. If any point has a normal, the
+/// normals are written as well, null for the points without one.
+pub fn write_ply(path: &str, points: &[Point]) -> Result<(), &'static str> {
+    let file = match File::create(path) {
+        Ok(file) => file,
+        Err(_) => return Err("Failed to create PLY file"),
+    };
+    let mut writer = BufWriter::new(file);
+
+    let normals = points.iter().any(Point::has_normal);
+    let normal_properties = match normals {
+        true => "property float nx\nproperty float ny\nproperty float nz\n",
+        false => "",
+    };
+    let header = format!(
+        "ply\n\
+         format binary_little_endian 1.0\n\
+         element vertex {}\n\
+         property float x\n\
+         property float y\n\
+         property float z\n\
+         property uchar red\n\
+         property uchar green\n\
+         property uchar blue\n\
+         property uchar alpha\n\
+         {normal_properties}\
+         end_header\n",
+        points.len()
+    );
+    if writer.write_all(header.as_bytes()).is_err() {
+        return Err("Failed to write PLY header");
+    }
+
+    let size = if normals { 28 } else { 16 };
+    for point in points {
+        let mut bytes = [0u8; 28];
+        point.position.iter().enumerate().for_each(|(i, p)| {
+            bytes[i * 4..(i + 1) * 4].copy_from_slice(&p.to_le_bytes());
+        });
+        let color = point.color_in(ColorSpace::Srgb);
+        color.iter().enumerate().for_each(|(i, c)| {
+            bytes[12 + i] = (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        });
+        let normal = point.normal().unwrap_or_default();
+        normal.iter().enumerate().for_each(|(i, n)| {
+            bytes[16 + i * 4..16 + (i + 1) * 4].copy_from_slice(&n.to_le_bytes());
+        });
+
+        if writer.write_all(&bytes[..size]).is_err() {
+            return Err("Failed to write PLY vertex");
+        }
+    }
+
+    match writer.flush() {
+        Ok(_) => Ok(()),
+        Err(_) => Err("Failed to write PLY file"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A path in the temporary directory for the file of a test.
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("loader_{name}_{}.ply", std::process::id()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn assert_close(actual: [f32; 4], expected: [f32; 4]) {
+        assert!(
+            actual
+                .iter()
+                .zip(expected)
+                .all(|(a, b)| (a - b).abs() < 1e-3),
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    #[test]
+    fn ply_round_trip() {
+        let points = [
+            Point::new([1.0, -2.5, 3.25], [255, 0, 0, 255]),
+            Point::new([0.0, 0.0, 0.0], [12, 34, 56, 128]),
+            Point::new([-1e4, 1e-3, 7.0], [255, 255, 255, 0]),
+        ];
+        let path = temp_path("round_trip");
+        write_ply(&path, &points).unwrap();
+        let read = read_ply(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(read.len(), points.len());
+        read.iter().zip(&points).for_each(|(read, point)| {
+            assert_eq!(read.position, point.position);
+            assert_close(read.color, point.color);
+        });
+    }
+
+    #[test]
+    fn reads_ascii_ply() {
+        let ply = "ply\n\
+                   format ascii 1.0\n\
+                   comment written by hand\n\
+                   element vertex 2\n\
+                   property float x\n\
+                   property float y\n\
+                   property float z\n\
+                   property uchar red\n\
+                   property uchar green\n\
+                   property uchar blue\n\
+                   end_header\n\
+                   1 2 3 255 0 0\n\
+                   -4.5 0 6 0 0 255\n";
+        let points = read_ply_bytes(ply.as_bytes()).unwrap();
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].position, [1.0, 2.0, 3.0]);
+        assert_eq!(points[1].position, [-4.5, 0.0, 6.0]);
+        assert_close(points[0].color_in(ColorSpace::Srgb), [1.0, 0.0, 0.0, 1.0]);
+        assert_close(points[1].color_in(ColorSpace::Srgb), [0.0, 0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn reads_ascii_vertices_in_the_order_of_the_file() {
+        // Enough vertices to be parsed by several threads
+        const COUNT: usize = 100_000;
+        let mut ply = format!(
+            "ply\nformat ascii 1.0\nelement vertex {COUNT}\n\
+             property float x\nproperty float y\nproperty float z\nend_header\n"
+        );
+        (0..COUNT).for_each(|i| ply.push_str(&format!("{i} 0 0\n")));
+
+        let points = read_ply_bytes(ply.as_bytes()).unwrap();
+        assert_eq!(points.len(), COUNT);
+        assert!(
+            points
+                .iter()
+                .enumerate()
+                .all(|(i, point)| point.position[0] == i as f32)
+        );
+    }
+
+    #[test]
+    fn skips_extra_elements_and_properties_in_ascii() {
+        let ply = "ply\n\
+                   format ascii 1.0\n\
+                   element camera 1\n\
+                   property float focal\n\
+                   element vertex 2\n\
+                   property float confidence\n\
+                   property float x\n\
+                   property float y\n\
+                   property float z\n\
+                   property float scan_angle\n\
+                   element face 1\n\
+                   property list uchar int vertex_indices\n\
+                   end_header\n\
+                   35.0\n\
+                   0.9 1 2 3 45\n\
+                   0.1 4 5 6 -45\n\
+                   3 0 1 1\n";
+        let points = read_ply_bytes(ply.as_bytes()).unwrap();
+        let positions: Vec<_> = points.iter().map(|point| point.position).collect();
+        assert_eq!(positions, [[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        assert_close(points[0].color_in(ColorSpace::Srgb), [1.0; 4]);
+    }
+
+    #[test]
+    fn skips_extra_elements_and_properties_in_binary() {
+        let mut ply = b"ply\n\
+                        format binary_little_endian 1.0\n\
+                        element face 2\n\
+                        property list uchar int vertex_indices\n\
+                        element vertex 2\n\
+                        property double time\n\
+                        property float x\n\
+                        property float y\n\
+                        property float z\n\
+                        property uchar red\n\
+                        property uchar green\n\
+                        property uchar blue\n\
+                        property ushort return_number\n\
+                        end_header\n"
+            .to_vec();
+        // A triangle and an empty face
+        ply.push(3);
+        [0i32, 1, 1]
+            .iter()
+            .for_each(|i| ply.extend(i.to_le_bytes()));
+        ply.push(0);
+        for (time, position, color) in [
+            (0.5f64, [1.0f32, 2.0, 3.0], [0u8, 255, 0]),
+            (1.5, [4.0, 5.0, 6.0], [255, 255, 255]),
+        ] {
+            ply.extend(time.to_le_bytes());
+            position.iter().for_each(|p| ply.extend(p.to_le_bytes()));
+            ply.extend(color);
+            ply.extend(7u16.to_le_bytes());
+        }
+
+        let points = read_ply_bytes(&ply).unwrap();
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].position, [1.0, 2.0, 3.0]);
+        assert_eq!(points[1].position, [4.0, 5.0, 6.0]);
+        assert_close(points[0].color_in(ColorSpace::Srgb), [0.0, 1.0, 0.0, 1.0]);
+        assert_close(points[1].color_in(ColorSpace::Srgb), [1.0; 4]);
+    }
+
+    #[test]
+    fn normalizes_intensities_over_the_cloud() {
+        let ply = "ply\n\
+                   format ascii 1.0\n\
+                   element vertex 3\n\
+                   property float x\n\
+                   property float y\n\
+                   property float z\n\
+                   property float intensity\n\
+                   end_header\n\
+                   0 0 0 1000\n\
+                   1 0 0 1500\n\
+                   2 0 0 2000\n";
+        let points = read_ply_bytes(ply.as_bytes()).unwrap();
+        [0.0, 0.5, 1.0]
+            .into_iter()
+            .zip(&points)
+            .for_each(|(intensity, point)| {
+                let gray = [intensity, intensity, intensity, 1.0];
+                assert_close(point.color_in(ColorSpace::Srgb), gray);
+            });
+    }
+
+    #[test]
+    fn rejects_truncated_vertices() {
+        // The header claims far more vertices than the file holds
+        let mut ply = b"ply\n\
+                        format binary_little_endian 1.0\n\
+                        element vertex 4000000000000\n\
+                        property float x\n\
+                        property float y\n\
+                        property float z\n\
+                        end_header\n"
+            .to_vec();
+        [1.0f32, 2.0, 3.0]
+            .iter()
+            .for_each(|p| ply.extend(p.to_le_bytes()));
+        assert_eq!(read_ply_bytes(&ply).err(), Some("Truncated PLY vertices"));
+
+        let ascii = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\n\
+                     property float y\nproperty float z\nend_header\n1 2 3\n";
+        assert_eq!(
+            read_ply_bytes(ascii.as_bytes()).err(),
+            Some("Truncated PLY vertices")
+        );
+    }
+
+    /// Asserts that two normals are along the same line, as they may face either side.
+    fn assert_parallel(normal: Vec3, expected: Vec3) {
+        assert!(
+            normal.dot(expected.normalize()).abs() > 0.9999,
+            "{normal:?} is not along {expected:?}"
+        );
+    }
+
+    #[test]
+    fn fit_normal_finds_the_normal_of_a_plane() {
+        // A tilted grid in the plane of normal (1, 2, 2) / 3, off the origin
+        let normal = Vec3::new(1.0, 2.0, 2.0) / 3.0;
+        let u = normal.cross(Vec3::X).normalize();
+        let v = normal.cross(u);
+        let positions: Vec<[f32; 3]> = (0..25)
+            .map(|i| {
+                let (a, b) = ((i % 5) as f32 - 2.0, (i / 5) as f32 - 2.0);
+                (Vec3::new(100.0, -50.0, 20.0) + u * a + v * b * 0.5).to_array()
+            })
+            .collect();
+        assert_parallel(fit_normal(&positions).unwrap(), normal);
+
+        // Three points are enough
+        let triangle = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]];
+        assert_parallel(fit_normal(&triangle).unwrap(), Vec3::Y);
+    }
+
+    #[test]
+    fn fit_normal_rejects_degenerate_neighborhoods() {
+        let line: Vec<[f32; 3]> = (0..10).map(|i| [i as f32, 2.0 * i as f32, -1.0]).collect();
+        assert_eq!(fit_normal(&line), None);
+        assert_eq!(fit_normal(&[[3.0, 4.0, 5.0]; 8]), None);
+        assert_eq!(fit_normal(&[[0.0; 3], [1.0; 3]]), None);
+        assert_eq!(fit_normal(&[]), None);
+    }
+
+    #[test]
+    fn fit_normal_is_stable_for_noisy_planes() {
+        // A plane of y = 0 jittered slightly off it
+        let mut rng = StdRng::seed_from_u64(7);
+        let positions: Vec<[f32; 3]> = (0..32)
+            .map(|_| {
+                [
+                    rng.random_range(-1.0..1.0),
+                    rng.random_range(-1e-3..1e-3),
+                    rng.random_range(-1.0..1.0),
+                ]
+            })
+            .collect();
+        let normal = fit_normal(&positions).unwrap();
+        assert!(normal.dot(Vec3::Y).abs() > 0.99);
+    }
+
+    #[test]
+    fn estimate_normals_lights_planes_and_skips_lines() {
+        let mut points: Vec<Point> = (0..100)
+            .map(|i| Point::new([(i % 10) as f32, 0.0, (i / 10) as f32], [255; 4]))
+            .collect();
+        estimate_normals(&mut points, 8);
+        points.iter().for_each(|point| {
+            assert_parallel(Vec3::from(point.normal().unwrap()), Vec3::Y);
+        });
+
+        let mut line: Vec<Point> = (0..20)
+            .map(|i| Point::new([i as f32, 0.0, 0.0], [255; 4]))
+            .collect();
+        estimate_normals(&mut line, 8);
+        assert!(line.iter().all(|point| !point.has_normal()));
+    }
+
+    #[test]
+    fn ply_round_trip_keeps_normals() {
+        let mut points = [
+            Point::new([0.0, 1.0, 2.0], [255; 4]),
+            Point::new([3.0, 4.0, 5.0], [255; 4]),
+        ];
+        points[0].set_normal(Some([0.0, -0.6, 0.8]));
+        let path = temp_path("normals");
+        write_ply(&path, &points).unwrap();
+        let read = read_ply(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_parallel(
+            Vec3::from(read[0].normal().unwrap()),
+            Vec3::new(0.0, -0.6, 0.8),
+        );
+        assert!(!read[1].has_normal());
+    }
+}
@@ -0,0 +1,344 @@
+use std::{cmp::Ordering, collections::BinaryHeap, ops::Range};
+
+use nannou::prelude::*;
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+
+use crate::{camera::Camera, point::Point};
+
+/// The settings of the level of detail of the drawn points.
+#[derive(Clone, Copy, PartialEq)]
+pub struct LodSettings {
+    /// Whether only the visible nodes of the octree are drawn, at the detail they need.
+    pub enabled: bool,
+    /// The largest spacing between the drawn points of a node, in pixels, before its children are
+    /// drawn too.
+    pub max_error: f32,
+    /// The maximum number of points drawn per frame.
+    pub point_budget: u32,
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_error: 2.0,
+            point_budget: 10_000_000,
+        }
+    }
+}
+
+/// A cubic cell of the octree.
+struct OctreeNode {
+    /// The corner of the cell with the smallest coordinates.
+    min: Vec3,
+    /// The length of the sides of the cell.
+    size: f32,
+    /// The points held by the node itself, as a range of the reordered points.
+    range: Range<u32>,
+    children: Vec<usize>,
+}
+
+impl OctreeNode {
+    fn len(&self) -> u32 {
+        self.range.end - self.range.start
+    }
+}
+
+/// An octree splitting a point cloud into levels of detail.
+///
+/// Each node holds a spatially uniform sample of the points of its cell, and hands the remaining
+/// points down to its children. Drawing a node along with all of its ancestors thus shows its cell
+/// at the density of its depth, without duplicating any point. The points of each node are stored
+/// contiguously so a node is drawn with a single range of vertices.
+pub struct Octree {
+    nodes: Vec<OctreeNode>,
+}
+
+impl Octree {
+    /// The number of points under which a node is not subdivided.
+    const LEAF_CAPACITY: usize = 16_384;
+    /// The resolution of the grid a node samples its points with, per side.
+    const GRID_SIZE: u32 = 32;
+    /// The maximum depth of the octree, which stops the subdivision of coincident points.
+    const MAX_DEPTH: u32 = 16;
+    /// How far the cells are grown for culling, as a fraction of their size, since the points can
+    /// be deformed out of their cell.
+    const CULL_MARGIN: f32 = 0.25;
+
+    /// Builds the octree of a point cloud, reordering the points so each node holds a contiguous
+    /// range of them.
+    pub fn build(points: &mut [Point]) -> Self {
+        let (min, max) = Point::bounding_box(points);
+        let size = (max - min).max_element().max(f32::EPSILON);
+        let indices = (0..points.len() as u32).collect();
+        let subtree = Self::build_node(points, indices, min, size, 0);
+
+        let reordered: Vec<Point> = subtree
+            .order
+            .into_par_iter()
+            .map(|i| points[i as usize])
+            .collect();
+        points.copy_from_slice(&reordered);
+
+        Self {
+            nodes: subtree.nodes,
+        }
+    }
+
+    /// The number of nodes of the octree.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Whether the octree has no node.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Builds the subtree of the cell at `min` holding the points at `indices`.
+    fn build_node(
+        points: &[Point],
+        indices: Vec<u32>,
+        min: Vec3,
+        size: f32,
+        depth: u32,
+    ) -> Subtree {
+        if indices.len() <= Self::LEAF_CAPACITY || depth >= Self::MAX_DEPTH {
+            let node = OctreeNode {
+                min,
+                size,
+                range: 0..indices.len() as u32,
+                children: Vec::new(),
+            };
+            return Subtree {
+                nodes: vec![node],
+                order: indices,
+            };
+        }
+
+        // Keep the first point of each cell of a grid, and hand the others down to their octant
+        let grid = Self::GRID_SIZE;
+        let half = size / 2.0;
+        let mut occupied = vec![false; (grid * grid * grid) as usize];
+        let mut sample = Vec::new();
+        let mut octants: [Vec<u32>; 8] = Default::default();
+        indices.into_iter().for_each(|i| {
+            let position = Vec3::from(points[i as usize].position) - min;
+            let [x, y, z] = (position / size * grid as f32)
+                .to_array()
+                .map(|coordinate| (coordinate as u32).min(grid - 1));
+            let key = ((z * grid + y) * grid + x) as usize;
+            if !occupied[key] {
+                occupied[key] = true;
+                sample.push(i);
+                return;
+            }
+
+            let octant = (position.x >= half) as usize
+                | ((position.y >= half) as usize) << 1
+                | ((position.z >= half) as usize) << 2;
+            octants[octant].push(i);
+        });
+
+        let children: Vec<Subtree> = octants
+            .into_par_iter()
+            .enumerate()
+            .filter(|(_, indices)| !indices.is_empty())
+            .map(|(octant, indices)| {
+                let corner = vec3(
+                    (octant & 1) as f32,
+                    ((octant >> 1) & 1) as f32,
+                    ((octant >> 2) & 1) as f32,
+                );
+                Self::build_node(points, indices, min + corner * half, half, depth + 1)
+            })
+            .collect();
+
+        // Append the subtrees after the node, offsetting their nodes and points
+        let mut nodes = vec![OctreeNode {
+            min,
+            size,
+            range: 0..sample.len() as u32,
+            children: Vec::new(),
+        }];
+        let mut order = sample;
+        children.into_iter().for_each(|child| {
+            let node_offset = nodes.len();
+            let point_offset = order.len() as u32;
+            nodes[0].children.push(node_offset);
+            nodes.extend(child.nodes.into_iter().map(|mut node| {
+                node.range = node.range.start + point_offset..node.range.end + point_offset;
+                node.children
+                    .iter_mut()
+                    .for_each(|child| *child += node_offset);
+                node
+            }));
+            order.extend(child.order);
+        });
+
+        Subtree { nodes, order }
+    }
+
+    /// Selects the ranges of points to draw from the camera.
+    ///
+    /// The nodes outside of the view frustum are culled. The visible nodes are refined from the
+    /// coarsest to the most detailed, in order of their screen-space error, until the spacing of
+    /// their points is under the maximum error or the point budget is spent.
+    pub fn select(
+        &self,
+        camera: &Camera,
+        viewport_height: f32,
+        settings: &LodSettings,
+    ) -> Vec<Range<u32>> {
+        let transforms = camera.uniforms();
+        let model_view = transforms.view * transforms.world;
+        let view_proj = transforms.proj * model_view;
+
+        // The number of pixels covered by a unit of length at a distance of 1 from the camera
+        let focal_length = transforms.proj.y_axis.y * viewport_height / 2.0;
+        // The number of units of the view per unit of the point cloud
+        let scale = model_view.x_axis.truncate().length();
+
+        let is_visible = |node: &OctreeNode| {
+            let margin = node.size * Self::CULL_MARGIN;
+            let min = node.min - margin;
+            let size = node.size + 2.0 * margin;
+            let corners = (0..8).map(|i| {
+                let corner = vec3((i & 1) as f32, ((i >> 1) & 1) as f32, ((i >> 2) & 1) as f32);
+                view_proj * (min + corner * size).extend(1.0)
+            });
+
+            // The cell is outside of the frustum if all of its corners are outside of a plane
+            let mut outside = [true; 6];
+            corners.for_each(|clip| {
+                let planes = [
+                    clip.x < -clip.w,
+                    clip.x > clip.w,
+                    clip.y < -clip.w,
+                    clip.y > clip.w,
+                    clip.z < -clip.w,
+                    clip.z > clip.w,
+                ];
+                outside
+                    .iter_mut()
+                    .zip(planes)
+                    .for_each(|(outside, plane)| *outside &= plane);
+            });
+            !outside.contains(&true)
+        };
+
+        // The spacing of the points of the node, projected on the screen
+        let error = |node: &OctreeNode| {
+            let center = node.min + Vec3::splat(node.size / 2.0);
+            let radius = node.size * 3.0f32.sqrt() / 2.0 * scale;
+            let distance = model_view.transform_point3(center).length() - radius;
+            if distance <= f32::EPSILON {
+                return f32::INFINITY;
+            }
+            let spacing = node.size / Self::GRID_SIZE as f32 * scale;
+            spacing * focal_length / distance
+        };
+
+        let mut ranges = Vec::new();
+        let Some(root) = self.nodes.first() else {
+            return ranges;
+        };
+        if !is_visible(root) {
+            return ranges;
+        }
+
+        let mut drawn = 0;
+        let mut candidates = BinaryHeap::from([Candidate {
+            error: f32::INFINITY,
+            node: 0,
+        }]);
+        while let Some(Candidate {
+            error: node_error,
+            node,
+        }) = candidates.pop()
+        {
+            let node = &self.nodes[node];
+            if drawn + node.len() > settings.point_budget && !ranges.is_empty() {
+                break;
+            }
+            ranges.push(node.range.clone());
+            drawn += node.len();
+
+            if node_error <= settings.max_error {
+                continue;
+            }
+            node.children
+                .iter()
+                .filter(|&&child| is_visible(&self.nodes[child]))
+                .for_each(|&child| {
+                    candidates.push(Candidate {
+                        error: error(&self.nodes[child]),
+                        node: child,
+                    });
+                });
+        }
+
+        // Merge the adjacent ranges to issue fewer draw calls
+        ranges.sort_by_key(|range| range.start);
+        let mut merged: Vec<Range<u32>> = Vec::with_capacity(ranges.len());
+        ranges
+            .into_iter()
+            .for_each(|range| match merged.last_mut() {
+                Some(last) if last.end == range.start => last.end = range.end,
+                _ => merged.push(range),
+            });
+        merged
+    }
+
+    /// The ranges of the points of the nodes whose cell passes a test, given the corner with the
+    /// smallest coordinates and the size of the cell.
+    ///
+    /// The descendants of the nodes failing the test are left out without being tested.
+    pub(crate) fn ranges_where(&self, mut test: impl FnMut(Vec3, f32) -> bool) -> Vec<Range<u32>> {
+        let mut ranges = Vec::new();
+        let mut pending = match self.nodes.is_empty() {
+            true => Vec::new(),
+            false => vec![0],
+        };
+        while let Some(node) = pending.pop() {
+            let node = &self.nodes[node];
+            if test(node.min, node.size) {
+                ranges.push(node.range.clone());
+                pending.extend(&node.children);
+            }
+        }
+        ranges
+    }
+}
+
+/// The nodes and reordered point indices of a subtree being built.
+struct Subtree {
+    nodes: Vec<OctreeNode>,
+    order: Vec<u32>,
+}
+
+/// A node waiting to be drawn, ordered by its screen-space error.
+struct Candidate {
+    error: f32,
+    node: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.error.total_cmp(&other.error)
+    }
+}
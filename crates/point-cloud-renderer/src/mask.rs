@@ -0,0 +1,98 @@
+use nannou::wgpu;
+use serde::{Deserialize, Serialize};
+
+/// Restricts an effect to the points within a height range and/or a color range.
+///
+/// The height is tested against the initial position of the points so that the mask does not
+/// move along with the deformation.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Mask {
+    /// The color selected by the color mask in linear space, like the colors of the points, in the
+    /// range [0, 1].
+    pub color: [f32; 3],
+    /// The maximum distance to `color` for a point to be selected.
+    pub color_tolerance: f32,
+    /// The minimum height of the points selected by the height mask.
+    pub min_height: f32,
+    /// The maximum height of the points selected by the height mask.
+    pub max_height: f32,
+    /// The distance over which the height mask fades in and out.
+    pub feather: f32,
+    flags: u32,
+}
+
+impl Mask {
+    const HEIGHT: u32 = 1 << 0;
+    const COLOR: u32 = 1 << 1;
+    const INVERT: u32 = 1 << 2;
+
+    /// Whether the height mask is enabled.
+    pub fn height_enabled(&self) -> bool {
+        self.flags & Self::HEIGHT != 0
+    }
+
+    /// Whether the color mask is enabled.
+    pub fn color_enabled(&self) -> bool {
+        self.flags & Self::COLOR != 0
+    }
+
+    /// Whether the mask selects the points outside of the ranges instead.
+    pub fn inverted(&self) -> bool {
+        self.flags & Self::INVERT != 0
+    }
+
+    /// Enables or disables the height mask.
+    pub fn set_height_enabled(&mut self, enabled: bool) {
+        self.set_flag(Self::HEIGHT, enabled);
+    }
+
+    /// Enables or disables the color mask.
+    pub fn set_color_enabled(&mut self, enabled: bool) {
+        self.set_flag(Self::COLOR, enabled);
+    }
+
+    /// Inverts the selection of the mask.
+    pub fn set_inverted(&mut self, inverted: bool) {
+        self.set_flag(Self::INVERT, inverted);
+    }
+
+    fn set_flag(&mut self, flag: u32, enabled: bool) {
+        if enabled {
+            self.flags |= flag;
+        } else {
+            self.flags &= !flag;
+        }
+    }
+}
+
+impl Default for Mask {
+    fn default() -> Self {
+        Self {
+            color: [1.0, 1.0, 1.0],
+            color_tolerance: 0.2,
+            min_height: 0.0,
+            max_height: 100.0,
+            feather: 1.0,
+            flags: 0,
+        }
+    }
+}
+
+/// The masks of each effect applied by the compute shader.
+///
+/// The struct is sent as-is to the compute shader, so its layout must match the `Masks` struct
+/// declared in `compute.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct EffectMasks {
+    pub wind: Mask,
+    pub attractors: Mask,
+}
+
+impl EffectMasks {
+    /// Returns the struct as a byte slice.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { wgpu::bytes::from(self) }
+    }
+}
@@ -0,0 +1,86 @@
+use std::fmt;
+
+use nannou::geom::{Point3, Vec3};
+
+use crate::point::Point;
+
+/// Where the points of a cloud come from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CloudSource {
+    /// A file read from the disk or fetched over the network.
+    File {
+        path: String,
+        /// The size of the file in bytes, when it could be read.
+        size: Option<u64>,
+    },
+    /// A generator of the registry, by name.
+    Generator(String),
+}
+
+impl CloudSource {
+    /// Describes the file at `path`, reading its size from the disk.
+    pub fn file(path: &str) -> Self {
+        Self::File {
+            path: path.to_owned(),
+            size: std::fs::metadata(path).ok().map(|metadata| metadata.len()),
+        }
+    }
+}
+
+impl fmt::Display for CloudSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CloudSource::File { path, .. } => write!(f, "{path}"),
+            CloudSource::Generator(name) => write!(f, "{name} generator"),
+        }
+    }
+}
+
+/// A summary of a point cloud, as shown to the user after loading it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CloudMetadata {
+    pub source: CloudSource,
+    pub point_count: usize,
+    /// The corners of the bounding box of the points.
+    pub bounds: (Point3, Point3),
+}
+
+impl CloudMetadata {
+    /// Measures the points of a cloud.
+    pub fn new(source: CloudSource, points: &[Point]) -> Self {
+        Self {
+            source,
+            point_count: points.len(),
+            bounds: Point::bounding_box(points),
+        }
+    }
+
+    /// Measures the points again after they changed, keeping the source.
+    pub fn measure(&mut self, points: &[Point]) {
+        self.point_count = points.len();
+        self.bounds = Point::bounding_box(points);
+    }
+
+    /// The size of the bounding box along each axis, zero for an empty cloud.
+    pub fn dimensions(&self) -> Vec3 {
+        let (min, max) = self.bounds;
+        (max - min).max(Vec3::ZERO)
+    }
+
+    /// The average number of points per unit of the bounding box.
+    ///
+    /// The flat axes of the box are left out, so a planar cloud gives the points per unit of area
+    /// rather than an infinite density.
+    pub fn density(&self) -> f32 {
+        let extent = self
+            .dimensions()
+            .to_array()
+            .into_iter()
+            .filter(|&size| size > f32::EPSILON)
+            .product::<f32>();
+        match self.point_count {
+            0 => 0.0,
+            count => count as f32 / extent,
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use nannou::prelude::*;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{color::ColorSpace, lod::Octree, point::Point};
+
+/// A point of the cloud picked on the screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PickedPoint {
+    /// The index of the point in the cloud.
+    pub index: usize,
+    /// The position of the point, in the coordinates of the cloud.
+    pub position: Vec3,
+    /// The color of the point in sRGB, in the range [0, 1].
+    pub color: [f32; 4],
+}
+
+/// A ray cast from the camera through the cursor, picking the point of the cloud it passes by.
+///
+/// The points are too small to be hit exactly, so the ray picks among the points within a narrow
+/// cone around it the one nearest to the camera. The points are picked at their rest positions,
+/// before the effects deform them.
+pub struct PickRay {
+    origin: Vec3,
+    direction: Vec3,
+    /// The angle between the ray and the side of the cone, in radians.
+    tolerance: f32,
+}
+
+impl PickRay {
+    /// The angle of the cone around the ray, in radians, a few pixels wide on most screens.
+    pub const TOLERANCE: f32 = 0.005;
+
+    /// Creates a ray from an origin and a direction, such as returned by
+    /// [`crate::camera::Camera::screen_ray`], with the given angle of the cone in radians.
+    pub fn new(origin: Vec3, direction: Vec3, tolerance: f32) -> Self {
+        Self {
+            origin,
+            direction: direction.normalize(),
+            tolerance,
+        }
+    }
+
+    /// Picks the point of the ray nearest to the camera.
+    ///
+    /// With the octree the points were reordered with, only the points of the cells the cone
+    /// reaches are tested, otherwise every point is.
+    pub fn pick(&self, points: &[Point], octree: Option<&Octree>) -> Option<PickedPoint> {
+        let ranges = match octree {
+            Some(octree) => octree.ranges_where(|min, size| self.reaches_cell(min, size)),
+            None => std::iter::once(0..points.len() as u32).collect(),
+        };
+        let (index, _) = ranges
+            .into_par_iter()
+            .flat_map(|range| range.into_par_iter())
+            .filter_map(|index| {
+                let position = Vec3::from(points[index as usize].position);
+                self.distance_to(position)
+                    .map(|distance| (index as usize, distance))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))?;
+
+        let point = &points[index];
+        Some(PickedPoint {
+            index,
+            position: Vec3::from(point.position),
+            color: point.color_in(ColorSpace::Srgb),
+        })
+    }
+
+    /// The distance along the ray to a position, if it is within the cone.
+    fn distance_to(&self, position: Vec3) -> Option<f32> {
+        let offset = position - self.origin;
+        let distance = offset.dot(self.direction);
+        let inside = distance > 0.0 && distance >= offset.length() * self.tolerance.cos();
+        inside.then_some(distance)
+    }
+
+    /// Whether the cone may reach into the cubic cell with the given corner and size, tested on
+    /// the sphere bounding the cell.
+    fn reaches_cell(&self, min: Vec3, size: f32) -> bool {
+        let center = min + Vec3::splat(size / 2.0);
+        let radius = size * 3.0f32.sqrt() / 2.0;
+        let offset = center - self.origin;
+        let distance = offset.length();
+        if distance <= radius {
+            return true;
+        }
+        let angle = (offset.dot(self.direction) / distance)
+            .clamp(-1.0, 1.0)
+            .acos();
+        angle - (radius / distance).asin() <= self.tolerance
+    }
+}
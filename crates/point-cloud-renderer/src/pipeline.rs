@@ -1,238 +1,2416 @@
-use nannou::prelude::*;
+use std::{collections::HashMap, ops::Range, sync::mpsc};
 
+use nannou::{
+    image::{GenericImage, RgbaImage},
+    prelude::*,
+};
+
+#[cfg(feature = "hot-reload")]
+use crate::hot_reload::{self, ShaderWatcher};
 use crate::{
-    camera::{Camera, CameraTransforms},
+    attractor::AttractorData,
+    camera::Camera,
+    clip::ClipBox,
+    color::{LinearImage, encode_srgb},
+    coloring::ColorSettings,
+    effects::{CurlNoiseData, Effect, EffectStack},
+    exposure::{ExposureSettings, ExposureState, ExposureUniforms},
+    fog::{FogSettings, FogUniforms},
+    gaussian::{Gaussian, GaussianView, sort_back_to_front},
+    gizmo::{GizmoSettings, GizmoVertex},
+    glow::{GlowSettings, GlowUniforms},
+    gravity::GravityData,
+    impulse::ImpulseData,
+    layer::CloudLayer,
+    lighting::{LightingSettings, LightingUniforms},
+    lod::{LodSettings, Octree},
+    mask::EffectMasks,
     point::{CloudData, Point},
+    pulse::PulseData,
+    readback::PointReadback,
+    spectrum::{SpectralLayout, SpectrumData},
+    trail::TrailData,
+    warp::{WarpSettings, WarpVertex},
 };
 
 pub struct GPUPipeline {
+    /// The point clouds of the scene; the first is the main cloud, which is never removed.
+    clouds: Vec<GpuCloud>,
+    /// The tiles of the main cloud streamed from the disk, by their index, drawn at rest over its
+    /// overview.
+    tiles: HashMap<usize, StreamedTile>,
+    camera_buffer: wgpu::Buffer,
+    /// The parameters of the deformation of the scene, which the clouds without overrides follow.
+    cloud_data: CloudData,
+    attractor_buffer: wgpu::Buffer,
+    mask_buffer: wgpu::Buffer,
+    spectrum_buffer: wgpu::Buffer,
+    pulse_buffer: wgpu::Buffer,
+    curl_noise_buffer: wgpu::Buffer,
+    gravity_buffer: wgpu::Buffer,
+    impulse_buffer: wgpu::Buffer,
+    /// The effects deforming the points, in the order their passes run.
+    effects: EffectStack,
+    fog_buffer: wgpu::Buffer,
+    fog_settings: FogSettings,
+    glow_buffer: wgpu::Buffer,
+    glow_settings: GlowSettings,
+    /// The nearest depth of each tile of the screen, written by the pre-pass of the additive mode.
+    coarse_depth_buffer: wgpu::Buffer,
+    lighting_buffer: wgpu::Buffer,
+    color_buffer: wgpu::Buffer,
+    clip_buffer: wgpu::Buffer,
+    trail_data_buffer: wgpu::Buffer,
+    trail_data: TrailData,
+    /// The size of the largest storage buffer of the device, which bounds the length of the
+    /// trails of the largest cloud.
+    buffer_limit: u64,
+    depth_texture: wgpu::Texture,
+    depth_texture_view: wgpu::TextureView,
+    msaa_samples: u32,
+    deform: Option<DeformStages>,
+    fallback_pipeline: wgpu::RenderPipeline,
+    fallback_bind_group_layout: wgpu::BindGroupLayout,
+    splats: bool,
+    /// Whether the points are held at their current deformation, skipping the compute pass.
+    frozen: bool,
+    fog_pipeline: wgpu::RenderPipeline,
+    fog_bind_group: wgpu::BindGroup,
+    gizmo_pipeline: wgpu::RenderPipeline,
+    gizmo_bind_group: wgpu::BindGroup,
+    /// The lines of the guides showing the orientation and scale of the cloud, if they are drawn.
+    gizmo_lines: Option<GizmoLines>,
+    render_scale: f32,
+    offscreen: Option<Offscreen>,
+    warp_settings: WarpSettings,
+    warp_vertex_buffer: wgpu::Buffer,
+    warp_vertex_count: u32,
+    warp_pipeline: wgpu::RenderPipeline,
+    warp_bind_group_layout: wgpu::BindGroupLayout,
+    warp_sampler: wgpu::Sampler,
+    /// The unwarped frame, drawn by the windows showing other parts of a panorama.
+    panel_vertex_buffer: wgpu::Buffer,
+    /// The targets of the windows showing other parts of a panorama, by their index.
+    panels: Vec<Option<Offscreen>>,
+    exposure_settings: ExposureSettings,
+    exposure_uniforms_buffer: wgpu::Buffer,
+    /// The exposure applied to the frame by the warp stage.
+    exposure_buffer: wgpu::Buffer,
+    /// The stage measuring the luminance of the frame, if the device can run compute shaders.
+    exposure_stage: Option<ExposureStage>,
+    /// The image drawn behind the points, if any.
+    background: Option<Background>,
+    background_pipeline: wgpu::RenderPipeline,
+    background_bind_group_layout: wgpu::BindGroupLayout,
+    /// The stage drawing Gaussian splats, if the device can read them from storage buffers.
+    gaussian_stage: Option<GaussianStage>,
+    /// The Gaussians of a 3D Gaussian Splatting capture drawn among the points, if any.
+    gaussians: Option<GaussianCloud>,
+    /// How much the Gaussians are scaled, 1 drawing them as captured.
+    gaussian_scale: f32,
+    camera: Camera,
+    lod_settings: LodSettings,
+    /// The watcher reloading the shaders of the deformation when they are edited, if enabled.
+    #[cfg(feature = "hot-reload")]
+    shader_watcher: Option<ShaderWatcher>,
+}
+
+/// The stages deforming the points in a compute pass and drawing them from storage buffers.
+///
+/// They are only created on the devices supporting compute shaders and storage buffers in the
+/// vertex stage; the others, such as browsers limited to WebGL2, draw the points at rest from the
+/// vertex buffer instead.
+struct DeformStages {
+    pipelines: DeformPipelines,
+    render_bind_group_layout: wgpu::BindGroupLayout,
+    compute_bind_group_layout: wgpu::BindGroupLayout,
+    /// The coarse depth pre-pass of the additive mode, which is not reloaded with the others.
+    occlusion_pipeline: wgpu::ComputePipeline,
+    occlusion_bind_group_layout: wgpu::BindGroupLayout,
+}
+
+/// The pipelines of the deformation stages, which are swapped when the shaders are reloaded.
+struct DeformPipelines {
+    render: wgpu::RenderPipeline,
+    splat: wgpu::RenderPipeline,
+    /// The points and splats blended additively, without the depth test.
+    additive_render: wgpu::RenderPipeline,
+    additive_splat: wgpu::RenderPipeline,
+    /// The compute pass of each effect, in the order of [`Effect::ALL`].
+    effects: Vec<wgpu::ComputePipeline>,
+    /// The compute pass pulling the points back to rest after the effects.
+    settle: wgpu::ComputePipeline,
+}
+
+/// A point cloud of the scene, with its own buffers so it is deformed and placed apart from the
+/// others.
+struct GpuCloud {
+    buffers: CloudBuffers,
+    layer: CloudLayer,
+    fallback_bind_group: wgpu::BindGroup,
+    /// The bind groups of the deformation stages, unless the device cannot run them or hold the
+    /// points in storage buffers.
+    deform_bind_groups: Option<DeformBindGroups>,
+    octree: Option<Octree>,
+    visible_ranges: Vec<Range<u32>>,
+}
+
+/// The buffers holding a point cloud and its parameters.
+struct CloudBuffers {
     vertex_buffer: wgpu::Buffer,
     vertex_buffer_len: u32,
     initial_vertex_buffer: wgpu::Buffer,
-    camera_buffer: wgpu::Buffer,
+    trail_buffer: wgpu::Buffer,
     cloud_data_buffer: wgpu::Buffer,
-    depth_texture: wgpu::Texture,
+    transform_buffer: wgpu::Buffer,
+}
+
+impl DeformStages {
+    /// Creates the stages from the modules of `compute.wgsl` and `render.wgsl`.
+    fn new(
+        device: &wgpu::Device,
+        compute_shader: &wgpu::ShaderModule,
+        render_shader: &wgpu::ShaderModule,
+        msaa_samples: u32,
+    ) -> Self {
+        let render_bind_group_layout = GPUPipeline::create_render_bind_group_layout(device);
+        let compute_bind_group_layout = GPUPipeline::create_compute_bind_group_layout(device);
+        let pipelines = Self::create_pipelines(
+            device,
+            &render_bind_group_layout,
+            &compute_bind_group_layout,
+            compute_shader,
+            render_shader,
+            msaa_samples,
+        );
+
+        let occlusion_shader =
+            device.create_shader_module(wgpu::include_wgsl!("shaders/occlusion.wgsl"));
+        let occlusion_bind_group_layout = GPUPipeline::create_occlusion_bind_group_layout(device);
+        let occlusion_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Occlusion Pipeline Layout"),
+                bind_group_layouts: &[&occlusion_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let occlusion_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Occlusion Pipeline"),
+            layout: Some(&occlusion_pipeline_layout),
+            module: &occlusion_shader,
+            entry_point: "cs_depth",
+        });
+
+        Self {
+            pipelines,
+            render_bind_group_layout,
+            compute_bind_group_layout,
+            occlusion_pipeline,
+            occlusion_bind_group_layout,
+        }
+    }
+
+    /// Creates the pipelines of the stages, which the bind groups of the clouds are bound to
+    /// through the layouts.
+    fn create_pipelines(
+        device: &wgpu::Device,
+        render_bind_group_layout: &wgpu::BindGroupLayout,
+        compute_bind_group_layout: &wgpu::BindGroupLayout,
+        compute_shader: &wgpu::ShaderModule,
+        render_shader: &wgpu::ShaderModule,
+        msaa_samples: u32,
+    ) -> DeformPipelines {
+        // Create the pipeline layout
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &[render_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        // The points either hide each other through the depth test, or add up without it
+        let create_render_pipeline = |vertex_entry_point,
+                                      fragment_entry_point,
+                                      topology,
+                                      additive| {
+            let builder =
+                wgpu::RenderPipelineBuilder::from_layout(&render_pipeline_layout, render_shader)
+                    .vertex_entry_point(vertex_entry_point)
+                    .fragment_shader(render_shader)
+                    .fragment_entry_point(fragment_entry_point)
+                    .color_format(Frame::TEXTURE_FORMAT)
+                    .alpha_blend(wgpu::BlendComponent::REPLACE)
+                    .primitive_topology(topology)
+                    .depth_format(GPUPipeline::DEPTH_FORMAT)
+                    .sample_count(msaa_samples);
+            match additive {
+                true => builder
+                    .color_blend(wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::One,
+                        dst_factor: wgpu::BlendFactor::One,
+                        operation: wgpu::BlendOperation::Add,
+                    })
+                    .depth_write_enabled(false)
+                    .depth_compare(wgpu::CompareFunction::Always),
+                false => builder.color_blend(wgpu::BlendComponent::REPLACE),
+            }
+            .build(device)
+        };
+
+        // Create the render pipeline
+        let points = wgpu::PrimitiveTopology::PointList;
+        let render_pipeline = create_render_pipeline("vs_main", "fs_main", points, false);
+        let additive_render = create_render_pipeline("vs_main", "fs_main", points, true);
+
+        // Create the splat pipeline
+        // It expands each point into a quad, drawing sparse clouds as surfaces instead of dust
+        let quads = wgpu::PrimitiveTopology::TriangleList;
+        let splat_pipeline = create_render_pipeline("vs_splat", "fs_splat", quads, false);
+        let additive_splat = create_render_pipeline("vs_splat", "fs_splat", quads, true);
+
+        // Compute pipeline
+        let compute_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Compute Pipeline Layout"),
+                bind_group_layouts: &[compute_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let create_compute_pipeline = |entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Compute Pipeline"),
+                layout: Some(&compute_pipeline_layout),
+                module: compute_shader,
+                entry_point,
+            })
+        };
+        let effects = Effect::ALL
+            .into_iter()
+            .map(|effect| create_compute_pipeline(effect.entry_point()))
+            .collect();
+        let settle = create_compute_pipeline("cs_settle");
+
+        DeformPipelines {
+            render: render_pipeline,
+            splat: splat_pipeline,
+            additive_render,
+            additive_splat,
+            effects,
+            settle,
+        }
+    }
+
+    /// The compute pipeline of the pass of an effect.
+    fn effect_pipeline(&self, effect: Effect) -> &wgpu::ComputePipeline {
+        &self.pipelines.effects[effect as usize]
+    }
+}
+
+struct DeformBindGroups {
+    render: wgpu::BindGroup,
+    compute: wgpu::BindGroup,
+    occlusion: wgpu::BindGroup,
+}
+
+/// A downscaled color and depth target the points are rendered to before being upscaled to the
+/// frame.
+struct Offscreen {
+    color_texture: wgpu::Texture,
+    color_texture_view: wgpu::TextureView,
+    _depth_texture: wgpu::Texture,
     depth_texture_view: wgpu::TextureView,
-    render_pipeline: wgpu::RenderPipeline,
-    render_bind_group: wgpu::BindGroup,
-    compute_bind_group: wgpu::BindGroup,
-    compute_pipeline: wgpu::ComputePipeline,
-    camera: Camera,
+    reshaper: wgpu::TextureReshaper,
+    warp_target: Option<WarpTarget>,
+}
+
+/// An image covering the frame behind the points.
+struct Background {
+    texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+}
+
+/// The pipeline drawing the Gaussians of a capture, blended from the farthest to the nearest.
+struct GaussianStage {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    view_buffer: wgpu::Buffer,
+}
+
+/// The Gaussians of a capture on the GPU, with the order they are drawn in.
+struct GaussianCloud {
+    buffer: wgpu::Buffer,
+    order_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    /// The centers of the Gaussians, kept to sort them along the view.
+    positions: Vec<[f32; 3]>,
+    /// The transform of the view the Gaussians were last sorted along.
+    sorted_for: Option<Mat4>,
+}
+
+/// A tile of the main cloud on the GPU, read from the vertex buffer like the points of the
+/// fallback pipeline.
+struct StreamedTile {
+    vertex_buffer: wgpu::Buffer,
+    len: u32,
+}
+
+/// The vertices of the lines of the guides.
+struct GizmoLines {
+    buffer: wgpu::Buffer,
+    vertex_count: u32,
+}
+
+/// A single-sampled copy of the offscreen target that the warp stage can sample.
+struct WarpTarget {
+    resolved_texture: wgpu::Texture,
+    resolved_texture_view: wgpu::TextureView,
+    resolver: wgpu::TextureReshaper,
+    bind_group: wgpu::BindGroup,
+    /// The bind group of the automatic exposure reading the resolved texture, if it can run.
+    exposure_bind_group: Option<wgpu::BindGroup>,
 }
 
-impl GPUPipeline {
-    const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+/// The compute pipeline adapting the exposure to the luminance of the frame.
+struct ExposureStage {
+    pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+}
+
+impl GPUPipeline {
+    const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+    /// The number of points deformed by each workgroup; must match `@workgroup_size` in
+    /// `compute.wgsl`.
+    const WORKGROUP_SIZE: u32 = 256;
+
+    pub fn new(
+        window: &Window,
+        points: &[Point],
+        camera: Camera,
+        cloud_data: CloudData,
+        attractor_data: AttractorData,
+        masks: EffectMasks,
+        spectrum_data: SpectrumData,
+    ) -> Self {
+        let (window_width, window_height) = window.inner_size_pixels();
+        Self::from_device(
+            window.device(),
+            [window_width, window_height],
+            window.msaa_samples(),
+            points,
+            camera,
+            cloud_data,
+            attractor_data,
+            masks,
+            spectrum_data,
+        )
+    }
+
+    /// Creates a pipeline rendering to targets of the given size and sample count, which need not
+    /// belong to a window.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_device(
+        device: &wgpu::Device,
+        [width, height]: [u32; 2],
+        msaa_samples: u32,
+        points: &[Point],
+        camera: Camera,
+        cloud_data: CloudData,
+        attractor_data: AttractorData,
+        masks: EffectMasks,
+        spectrum_data: SpectrumData,
+    ) -> Self {
+        // Load shaders
+        let fallback_shader =
+            device.create_shader_module(wgpu::include_wgsl!("shaders/fallback.wgsl"));
+        let fog_shader = device.create_shader_module(wgpu::include_wgsl!("shaders/fog.wgsl"));
+        let gizmo_shader = device.create_shader_module(wgpu::include_wgsl!("shaders/gizmo.wgsl"));
+        let warp_shader = device.create_shader_module(wgpu::include_wgsl!("shaders/warp.wgsl"));
+        let background_shader =
+            device.create_shader_module(wgpu::include_wgsl!("shaders/background.wgsl"));
+
+        // Create the depth buffer texture
+        let depth_texture =
+            Self::create_depth_texture(device, [width, height], Self::DEPTH_FORMAT, msaa_samples);
+        let depth_texture_view = depth_texture.view().build();
+
+        // Uniform buffer (for camera)
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Uniforms Buffer"),
+            contents: camera.uniforms().as_bytes(),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // Create the attractors uniform buffer
+        let attractor_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Attractors Uniforms Buffer"),
+            contents: attractor_data.as_bytes(),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // Create the effect masks uniform buffer
+        let mask_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Effect Masks Uniforms Buffer"),
+            contents: masks.as_bytes(),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // Create the spectrum uniform buffer
+        let spectrum_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Spectrum Uniforms Buffer"),
+            contents: spectrum_data.as_bytes(),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // Create the pulse uniform buffer
+        let pulse_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Pulse Uniforms Buffer"),
+            contents: PulseData::default().as_bytes(),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // Create the curl noise uniform buffer
+        let curl_noise_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Curl Noise Uniforms Buffer"),
+            contents: CurlNoiseData::default().as_bytes(),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // Create the gravity uniform buffer
+        let gravity_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Gravity Uniforms Buffer"),
+            contents: GravityData::default().as_bytes(),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // Create the impulse uniform buffer
+        let impulse_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Impulse Uniforms Buffer"),
+            contents: ImpulseData::default().as_bytes(),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // Create the fog uniform buffer
+        let fog_settings = FogSettings::default();
+        let fog_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Fog Uniforms Buffer"),
+            contents: FogUniforms::new(&fog_settings, &camera).as_bytes(),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // Create the glow uniform buffer and the coarse depth of its pre-pass
+        let glow_settings = GlowSettings::default();
+        let glow_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Glow Uniforms Buffer"),
+            contents: GlowUniforms::new(&glow_settings).as_bytes(),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let [columns, rows] = GlowSettings::DEPTH_GRID;
+        let coarse_depth_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Coarse Depth Buffer"),
+            size: (columns * rows) as wgpu::BufferAddress
+                * std::mem::size_of::<u32>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // Create the lighting uniform buffer
+        let lighting_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Lighting Uniforms Buffer"),
+            contents: LightingUniforms::new(&LightingSettings::default()).as_bytes(),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // Create the coloring uniform buffer
+        let color_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Coloring Uniforms Buffer"),
+            contents: ColorSettings::default().uniforms().as_bytes(),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // Create the clip box uniform buffer
+        let clip_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Clip Uniforms Buffer"),
+            contents: ClipBox::default().uniforms().as_bytes(),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // Create the trail uniform buffer
+        let trail_data = TrailData::default();
+        let trail_data_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Trail Uniforms Buffer"),
+            contents: trail_data.as_bytes(),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // Create the fog bind group
+        let fog_bind_group_layout = wgpu::BindGroupLayoutBuilder::new()
+            .uniform_buffer(wgpu::ShaderStages::FRAGMENT, false)
+            .build(device);
+        let fog_bind_group = wgpu::BindGroupBuilder::new()
+            .binding(fog_buffer.as_entire_binding())
+            .build(device, &fog_bind_group_layout);
+
+        // Create the deformation stages, if the device can run them
+        let supported = Self::supports_deformation(&device.limits());
+        let deform = supported.then(|| {
+            let compute_shader =
+                device.create_shader_module(wgpu::include_wgsl!("shaders/compute.wgsl"));
+            let render_shader =
+                device.create_shader_module(wgpu::include_wgsl!("shaders/render.wgsl"));
+            DeformStages::new(device, &compute_shader, &render_shader, msaa_samples)
+        });
+        if !supported {
+            eprintln!(
+                "warning: compute shaders are not supported by the device, the points are drawn without deformation"
+            );
+        }
+
+        // Create the fallback pipeline
+        // It draws the points at rest from the vertex buffer, needing neither compute nor storage
+        let fallback_bind_group_layout = wgpu::BindGroupLayoutBuilder::new()
+            .uniform_buffer(wgpu::ShaderStages::VERTEX, false)
+            .uniform_buffer(wgpu::ShaderStages::VERTEX, false)
+            .uniform_buffer(wgpu::ShaderStages::VERTEX, false)
+            .uniform_buffer(wgpu::ShaderStages::VERTEX, false)
+            .uniform_buffer(wgpu::ShaderStages::VERTEX, false)
+            .build(device);
+        let fallback_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Fallback Pipeline Layout"),
+                bind_group_layouts: &[&fallback_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let fallback_pipeline =
+            wgpu::RenderPipelineBuilder::from_layout(&fallback_pipeline_layout, &fallback_shader)
+                .vertex_entry_point("vs_main")
+                .fragment_shader(&fallback_shader)
+                .fragment_entry_point("fs_main")
+                .add_vertex_buffer::<Point>(&Point::ATTRIBS)
+                .color_format(Frame::TEXTURE_FORMAT)
+                .color_blend(wgpu::BlendComponent::REPLACE)
+                .alpha_blend(wgpu::BlendComponent::REPLACE)
+                .primitive_topology(wgpu::PrimitiveTopology::PointList)
+                .depth_format(Self::DEPTH_FORMAT)
+                .sample_count(msaa_samples)
+                .build(device);
+
+        // Create the fog pipeline
+        // It is drawn after the points, testing against their depth so it only covers the points
+        // below the plane, and blends over them
+        let fog_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Fog Pipeline Layout"),
+            bind_group_layouts: &[&fog_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let fog_pipeline =
+            wgpu::RenderPipelineBuilder::from_layout(&fog_pipeline_layout, &fog_shader)
+                .vertex_entry_point("vs_main")
+                .fragment_shader(&fog_shader)
+                .fragment_entry_point("fs_main")
+                .color_format(Frame::TEXTURE_FORMAT)
+                .color_blend(wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                    operation: wgpu::BlendOperation::Add,
+                })
+                .alpha_blend(wgpu::BlendComponent::OVER)
+                .primitive_topology(wgpu::PrimitiveTopology::TriangleList)
+                .depth_format(Self::DEPTH_FORMAT)
+                .depth_write_enabled(false)
+                .depth_compare(wgpu::CompareFunction::LessEqual)
+                .sample_count(msaa_samples)
+                .build(device);
+
+        // Create the gizmo pipeline
+        // It draws the lines of the guides after the points, hidden by the points in front of them
+        let gizmo_bind_group_layout = wgpu::BindGroupLayoutBuilder::new()
+            .uniform_buffer(wgpu::ShaderStages::VERTEX, false)
+            .build(device);
+        let gizmo_bind_group = wgpu::BindGroupBuilder::new()
+            .binding(camera_buffer.as_entire_binding())
+            .build(device, &gizmo_bind_group_layout);
+        let gizmo_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Gizmo Pipeline Layout"),
+                bind_group_layouts: &[&gizmo_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let gizmo_pipeline =
+            wgpu::RenderPipelineBuilder::from_layout(&gizmo_pipeline_layout, &gizmo_shader)
+                .vertex_entry_point("vs_main")
+                .fragment_shader(&gizmo_shader)
+                .fragment_entry_point("fs_main")
+                .add_vertex_buffer::<GizmoVertex>(&GizmoVertex::ATTRIBS)
+                .color_format(Frame::TEXTURE_FORMAT)
+                .primitive_topology(wgpu::PrimitiveTopology::LineList)
+                .depth_format(Self::DEPTH_FORMAT)
+                .depth_write_enabled(false)
+                .depth_compare(wgpu::CompareFunction::LessEqual)
+                .sample_count(msaa_samples)
+                .build(device);
+
+        // Create the warp pipeline
+        // It draws the offscreen target onto a mesh covering the corner pins of the projection
+        let warp_settings = WarpSettings::default();
+        let warp_vertices = warp_settings.vertices();
+        let warp_vertex_buffer = Self::create_warp_vertex_buffer(device, &warp_vertices);
+        let panel_vertex_buffer = Self::create_warp_vertex_buffer(device, &warp_vertices);
+        let warp_sampler = wgpu::SamplerBuilder::new()
+            .mag_filter(wgpu::FilterMode::Linear)
+            .min_filter(wgpu::FilterMode::Linear)
+            .build(device);
+        let warp_bind_group_layout = wgpu::BindGroupLayoutBuilder::new()
+            .texture(
+                wgpu::ShaderStages::FRAGMENT,
+                false,
+                wgpu::TextureViewDimension::D2,
+                wgpu::TextureSampleType::Float { filterable: true },
+            )
+            .sampler(wgpu::ShaderStages::FRAGMENT, true)
+            .uniform_buffer(wgpu::ShaderStages::FRAGMENT, false)
+            .build(device);
+        let warp_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Warp Pipeline Layout"),
+            bind_group_layouts: &[&warp_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let warp_pipeline =
+            wgpu::RenderPipelineBuilder::from_layout(&warp_pipeline_layout, &warp_shader)
+                .vertex_entry_point("vs_main")
+                .fragment_shader(&warp_shader)
+                .fragment_entry_point("fs_main")
+                .add_vertex_buffer::<WarpVertex>(&WarpVertex::ATTRIBS)
+                .color_format(Frame::TEXTURE_FORMAT)
+                .primitive_topology(wgpu::PrimitiveTopology::TriangleList)
+                .sample_count(msaa_samples)
+                .build(device);
+
+        // Create the background pipeline
+        // It is drawn first, at the far plane without writing depth, so the points cover it
+        let background_bind_group_layout = wgpu::BindGroupLayoutBuilder::new()
+            .texture(
+                wgpu::ShaderStages::FRAGMENT,
+                false,
+                wgpu::TextureViewDimension::D2,
+                wgpu::TextureSampleType::Float { filterable: true },
+            )
+            .sampler(wgpu::ShaderStages::FRAGMENT, true)
+            .build(device);
+        let background_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Background Pipeline Layout"),
+                bind_group_layouts: &[&background_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let background_pipeline = wgpu::RenderPipelineBuilder::from_layout(
+            &background_pipeline_layout,
+            &background_shader,
+        )
+        .vertex_entry_point("vs_main")
+        .fragment_shader(&background_shader)
+        .fragment_entry_point("fs_main")
+        .color_format(Frame::TEXTURE_FORMAT)
+        .primitive_topology(wgpu::PrimitiveTopology::TriangleList)
+        .depth_format(Self::DEPTH_FORMAT)
+        .depth_write_enabled(false)
+        .depth_compare(wgpu::CompareFunction::Always)
+        .sample_count(msaa_samples)
+        .build(device);
+
+        // Create the Gaussian splatting stage, if the device can read the Gaussians from storage
+        // They are blended over the points without hiding each other, in the order they are sorted
+        let gaussian_stage = supported.then(|| {
+            let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/gaussian.wgsl"));
+            let bind_group_layout = wgpu::BindGroupLayoutBuilder::new()
+                .storage_buffer(wgpu::ShaderStages::VERTEX, false, true)
+                .storage_buffer(wgpu::ShaderStages::VERTEX, false, true)
+                .uniform_buffer(wgpu::ShaderStages::VERTEX, false)
+                .uniform_buffer(wgpu::ShaderStages::VERTEX, false)
+                .build(device);
+            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Gaussian Pipeline Layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+            let pipeline = wgpu::RenderPipelineBuilder::from_layout(&layout, &shader)
+                .vertex_entry_point("vs_main")
+                .fragment_shader(&shader)
+                .fragment_entry_point("fs_main")
+                .color_format(Frame::TEXTURE_FORMAT)
+                .color_blend(wgpu::BlendComponent::OVER)
+                .alpha_blend(wgpu::BlendComponent::OVER)
+                .primitive_topology(wgpu::PrimitiveTopology::TriangleList)
+                .depth_format(Self::DEPTH_FORMAT)
+                .depth_write_enabled(false)
+                .depth_compare(wgpu::CompareFunction::LessEqual)
+                .sample_count(msaa_samples)
+                .build(device);
+            let view_buffer = device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("Gaussian View Uniforms Buffer"),
+                contents: GaussianView::new([width as f32, height as f32], 1.0).as_bytes(),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+            GaussianStage {
+                pipeline,
+                bind_group_layout,
+                view_buffer,
+            }
+        });
+
+        // Create the exposure buffers and the stage measuring the luminance of the frame
+        // The exposure is eased on the GPU, so the frame is never read back
+        let exposure_settings = ExposureSettings::default();
+        let exposure_uniforms_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Exposure Uniforms Buffer"),
+            contents: ExposureUniforms::new(&exposure_settings, 0.0).as_bytes(),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let exposure_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Exposure Buffer"),
+            contents: ExposureState::fixed(exposure_settings.stops).as_bytes(),
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::UNIFORM
+                | wgpu::BufferUsages::COPY_DST,
+        });
+        let exposure_stage = supported.then(|| {
+            let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/exposure.wgsl"));
+            let bind_group_layout = wgpu::BindGroupLayoutBuilder::new()
+                .texture(
+                    wgpu::ShaderStages::COMPUTE,
+                    false,
+                    wgpu::TextureViewDimension::D2,
+                    wgpu::TextureSampleType::Float { filterable: true },
+                )
+                .uniform_buffer(wgpu::ShaderStages::COMPUTE, false)
+                .storage_buffer(wgpu::ShaderStages::COMPUTE, false, false)
+                .build(device);
+            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Exposure Pipeline Layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+            let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Exposure Pipeline"),
+                layout: Some(&layout),
+                module: &shader,
+                entry_point: "cs_exposure",
+            });
+            ExposureStage {
+                pipeline,
+                bind_group_layout,
+            }
+        });
+
+        let mut pipeline = GPUPipeline {
+            clouds: Vec::new(),
+            camera_buffer,
+            cloud_data,
+            attractor_buffer,
+            mask_buffer,
+            spectrum_buffer,
+            pulse_buffer,
+            curl_noise_buffer,
+            gravity_buffer,
+            impulse_buffer,
+            effects: EffectStack::default(),
+            fog_buffer,
+            fog_settings,
+            glow_buffer,
+            glow_settings,
+            coarse_depth_buffer,
+            lighting_buffer,
+            color_buffer,
+            clip_buffer,
+            trail_data_buffer,
+            trail_data,
+            buffer_limit: Self::buffer_limit(&device.limits()),
+            depth_texture,
+            depth_texture_view,
+            msaa_samples,
+            deform,
+            fallback_pipeline,
+            fallback_bind_group_layout,
+            splats: false,
+            frozen: false,
+            fog_pipeline,
+            fog_bind_group,
+            gizmo_pipeline,
+            gizmo_bind_group,
+            gizmo_lines: None,
+            tiles: HashMap::new(),
+            render_scale: 1.0,
+            offscreen: None,
+            warp_settings,
+            warp_vertex_buffer,
+            warp_vertex_count: warp_vertices.len() as u32,
+            warp_pipeline,
+            warp_bind_group_layout,
+            warp_sampler,
+            panel_vertex_buffer,
+            panels: Vec::new(),
+            exposure_settings,
+            exposure_uniforms_buffer,
+            exposure_buffer,
+            exposure_stage,
+            background: None,
+            background_pipeline,
+            background_bind_group_layout,
+            gaussian_stage,
+            gaussians: None,
+            gaussian_scale: 1.0,
+            camera,
+            lod_settings: LodSettings::default(),
+            #[cfg(feature = "hot-reload")]
+            shader_watcher: None,
+        };
+        pipeline.add_cloud(device, points, None, CloudLayer::default());
+        pipeline
+    }
+
+    pub fn render(&mut self, frame: &Frame) {
+        let device = frame.device_queue_pair().device();
+        let mut encoder = frame.command_encoder();
+
+        // Step 1: Dispatch compute pass
+        if !self.frozen
+            && let Some(deform) = self.active_deform()
+        {
+            self.dispatch_compute(&mut encoder, deform);
+        }
+
+        // Step 2: Insert buffer barrier to sync compute output to render input
+        encoder.insert_debug_marker("Buffer Sync Barrier");
+
+        // If the window has changed size, recreate our depth texture to match.
+        if frame.texture_size() != self.depth_texture.size() {
+            self.update_depth_texture(device, &mut encoder, frame);
+        }
+
+        // Step 3: Select the points to draw and dispatch render pass
+        self.update_visible_ranges(frame.texture_size()[1] as f32);
+        let warp = self.post_processes();
+        if self.render_scale < 1.0 || warp {
+            // Render offscreen, possibly at a lower resolution, then upscale or warp to the frame
+            // The warp stage also applies the exposure
+            let size = frame
+                .texture_size()
+                .map(|length| ((length as f32 * self.render_scale).round() as u32).max(1));
+            let outdated = self.offscreen.as_ref().is_none_or(|offscreen| {
+                offscreen.color_texture.size() != size || offscreen.warp_target.is_some() != warp
+            });
+            if outdated {
+                self.offscreen =
+                    Some(self.create_offscreen(device, size, frame.texture_msaa_samples(), warp));
+            }
+
+            self.prepare_gaussians(device, &mut encoder, size);
+            let offscreen = self.offscreen.as_ref().unwrap();
+            self.dispatch_render(
+                &mut encoder,
+                &offscreen.color_texture_view,
+                &offscreen.depth_texture_view,
+            );
+            match &offscreen.warp_target {
+                Some(target) => {
+                    target
+                        .resolver
+                        .encode_render_pass(&target.resolved_texture_view, &mut encoder);
+                    self.dispatch_exposure(&mut encoder, target);
+                    self.dispatch_warp(&mut encoder, frame, target, &self.warp_vertex_buffer);
+                }
+                None => offscreen
+                    .reshaper
+                    .encode_render_pass(frame.texture_view(), &mut encoder),
+            }
+        } else {
+            self.prepare_gaussians(device, &mut encoder, frame.texture_size());
+            self.dispatch_render(&mut encoder, frame.texture_view(), &self.depth_texture_view);
+        }
+    }
+
+    /// Renders the points seen by another camera to the frame of another window, such as a part
+    /// of a panorama spanning several monitors.
+    ///
+    /// The points are drawn as deformed by the last call to [`GPUPipeline::render`], so each
+    /// window shows the same instant. Each panel keeps its own target, and the frame is exposed
+    /// like the main frame but not warped. The Gaussians are sorted again for each camera.
+    pub fn render_panel(&mut self, frame: &Frame, panel: usize, camera: &Camera) {
+        let device = frame.device_queue_pair().device();
+        let mut encoder = frame.command_encoder();
+        let main_camera = std::mem::replace(&mut self.camera, *camera);
+        self.update_camera_transforms(device, &mut encoder);
+
+        let size = frame.texture_size();
+        self.update_visible_ranges(size[1] as f32);
+        let exposed = self.exposure_settings.is_active();
+        if self.panels.len() <= panel {
+            self.panels.resize_with(panel + 1, || None);
+        }
+        let outdated = self.panels[panel].as_ref().is_none_or(|target| {
+            target.color_texture.size() != size || target.warp_target.is_some() != exposed
+        });
+        if outdated {
+            self.panels[panel] =
+                Some(self.create_offscreen(device, size, frame.texture_msaa_samples(), exposed));
+        }
+
+        self.prepare_gaussians(device, &mut encoder, size);
+        let target = self.panels[panel].as_ref().unwrap();
+        self.dispatch_render(
+            &mut encoder,
+            &target.color_texture_view,
+            &target.depth_texture_view,
+        );
+        match &target.warp_target {
+            Some(resolved) => {
+                resolved
+                    .resolver
+                    .encode_render_pass(&resolved.resolved_texture_view, &mut encoder);
+                self.dispatch_warp(&mut encoder, frame, resolved, &self.panel_vertex_buffer);
+            }
+            None => target
+                .reshaper
+                .encode_render_pass(frame.texture_view(), &mut encoder),
+        }
+
+        // The main window keeps its camera
+        self.camera = main_camera;
+        self.update_camera_transforms(device, &mut encoder);
+    }
+
+    /// Renders the points to an image of the given size instead of to the frame.
+    ///
+    /// The points are drawn at their current deformation, as seen by the camera, without the
+    /// render scale, the warp or the exposure of the frame. This blocks until the image is read
+    /// back from the GPU, and fails if the image cannot be mapped, such as after a device loss.
+    ///
+    /// The points are shaded in linear space and the image is encoded to sRGB when it is read back,
+    /// like the frame is when it is presented, so the image looks the same as the window.
+    pub fn render_to_image(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: [u32; 2],
+    ) -> Result<RgbaImage, &'static str> {
+        self.render_to_linear_image(device, queue, size)
+            .map(|image| encode_srgb(&image))
+    }
+
+    /// Renders the points to an image of the given size like [`GPUPipeline::render_to_image`],
+    /// keeping the linear channels of the frame in floating point instead of encoding them to 8-bit
+    /// sRGB, for outputs graded without banding.
+    pub fn render_to_linear_image(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: [u32; 2],
+    ) -> Result<LinearImage, &'static str> {
+        self.render_part_to_image(device, queue, size, size[1])
+    }
+
+    /// Renders the points to a large image as a grid of `tiles` columns and rows of `tile_size`
+    /// pixels, for displays larger than the textures of the device such as video walls.
+    ///
+    /// Each tile is drawn with its own part of the view, through an asymmetric frustum at the
+    /// aspect ratio of the whole image, so the tiles line up at native resolution. The tiles are
+    /// rendered and read back one at a time, like [`GPUPipeline::render_to_image`].
+    pub fn render_tiled_to_image(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        tile_size: [u32; 2],
+        tiles: [u32; 2],
+    ) -> Result<RgbaImage, &'static str> {
+        self.render_tiled_to_linear_image(device, queue, tile_size, tiles)
+            .map(|image| encode_srgb(&image))
+    }
+
+    /// Renders the points to a large image as a grid of tiles like
+    /// [`GPUPipeline::render_tiled_to_image`], keeping the linear channels in floating point like
+    /// [`GPUPipeline::render_to_linear_image`].
+    pub fn render_tiled_to_linear_image(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        tile_size: [u32; 2],
+        tiles: [u32; 2],
+    ) -> Result<LinearImage, &'static str> {
+        let [width, height] = tile_size;
+        let [columns, rows] = tiles.map(|count| count.max(1));
+        let image_size = [width * columns, height * rows];
+        let camera = self.camera;
+
+        // The tiles are counted from the top-left corner, like the pixels of the image
+        let mut image = LinearImage::new(image_size[0], image_size[1]);
+        for row in 0..rows {
+            for column in 0..columns {
+                let origin = [column * width, row * height];
+                let tile = self.render_region_to_linear_image(
+                    device, queue, &camera, image_size, origin, tile_size,
+                )?;
+                image
+                    .copy_from(&tile, origin[0], origin[1])
+                    .expect("the tile lies inside the image");
+            }
+        }
+        Ok(image)
+    }
+
+    /// Renders the region of an image of `image_size` pixels starting at `origin` from its
+    /// top-left corner, as seen by another camera, to an image of the size of the region.
+    ///
+    /// The region is drawn with its own part of the view, through an asymmetric frustum at the
+    /// aspect ratio of the whole image, so the regions of an image line up. This blocks until the
+    /// region is read back from the GPU, in linear floating point like
+    /// [`GPUPipeline::render_to_linear_image`].
+    pub fn render_region_to_linear_image(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        camera: &Camera,
+        image_size: [u32; 2],
+        origin: [u32; 2],
+        size: [u32; 2],
+    ) -> Result<LinearImage, &'static str> {
+        let [image_width, image_height] = image_size.map(|length| length as f32);
+        let left = origin[0] as f32 / image_width * 2.0 - 1.0;
+        let right = (origin[0] + size[0]) as f32 / image_width * 2.0 - 1.0;
+        let top = 1.0 - origin[1] as f32 / image_height * 2.0;
+        let bottom = 1.0 - (origin[1] + size[1]) as f32 / image_height * 2.0;
+
+        let main_camera = std::mem::replace(&mut self.camera, *camera);
+        self.camera
+            .config
+            .set_aspect_ratio(image_size[0], image_size[1]);
+        self.camera
+            .config
+            .set_off_center_projection(Some([left, right, bottom, top]));
+        let image = self.render_part_to_image(device, queue, size, image_size[1]);
+
+        // The frame keeps its camera, even if the region failed to render
+        self.camera = main_camera;
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Restore Camera Encoder"),
+        });
+        self.update_camera_transforms(device, &mut encoder);
+        queue.submit(Some(encoder.finish()));
+        image
+    }
+
+    /// Renders the part of the view set on the camera to an image of the given size, the level of
+    /// detail being refined for the whole view, `viewport_height` pixels high.
+    ///
+    /// The resolved frame is copied back as is, in the half floats of the format of the frame.
+    fn render_part_to_image(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: [u32; 2],
+        viewport_height: u32,
+    ) -> Result<LinearImage, &'static str> {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Render To Image Encoder"),
+        });
+        self.update_camera_transforms(device, &mut encoder);
+        self.update_visible_ranges(viewport_height as f32);
+
+        // Render to a target of the size of the image, resolved to a texture that can be copied
+        self.prepare_gaussians(device, &mut encoder, size);
+        let target = self.create_offscreen(device, size, self.msaa_samples, true);
+        let resolved = target.warp_target.as_ref().unwrap();
+        self.dispatch_render(
+            &mut encoder,
+            &target.color_texture_view,
+            &target.depth_texture_view,
+        );
+        resolved
+            .resolver
+            .encode_render_pass(&resolved.resolved_texture_view, &mut encoder);
+
+        // The rows of the copy are padded to the alignment wgpu requires
+        const TEXEL_SIZE: u32 = 4 * std::mem::size_of::<u16>() as u32;
+        let [width, height] = size;
+        let row_size = width * TEXEL_SIZE;
+        let padded_row_size = row_size.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Image Staging Buffer"),
+            size: padded_row_size as wgpu::BufferAddress * height as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        encoder.copy_texture_to_buffer(
+            resolved.resolved_texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &staging_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_size),
+                    rows_per_image: None,
+                },
+            },
+            resolved.resolved_texture.extent(),
+        );
+        queue.submit(Some(encoder.finish()));
+
+        // Map the staging buffer and wait for the copy to complete
+        let slice = staging_buffer.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            sender.send(result).ok();
+        });
+        device.poll(wgpu::Maintain::Wait);
+        if !matches!(receiver.recv(), Ok(Ok(()))) {
+            return Err("Failed to map the image read back from the GPU");
+        }
+
+        let channels = slice
+            .get_mapped_range()
+            .chunks_exact(padded_row_size as usize)
+            .flat_map(|row| row[..row_size as usize].chunks_exact(2))
+            .map(|bytes| half_to_f32(u16::from_le_bytes([bytes[0], bytes[1]])))
+            .collect();
+        staging_buffer.unmap();
+        Ok(
+            LinearImage::from_raw(width, height, channels)
+                .expect("the copy covers the whole image"),
+        )
+    }
+
+    /// Updates the output warp used for projection mapping.
+    pub fn update_warp(&mut self, device: &wgpu::Device, warp_settings: WarpSettings) {
+        self.warp_settings = warp_settings;
+        let vertices = self.warp_settings.vertices();
+        self.warp_vertex_buffer = Self::create_warp_vertex_buffer(device, &vertices);
+        self.warp_vertex_count = vertices.len() as u32;
+    }
+
+    /// Draws an image behind the points, stretched over the frame.
+    ///
+    /// The texture is only reallocated when the size of the image changes, so the frames of a
+    /// sequence are uploaded to the same texture.
+    pub fn update_background(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        image: &RgbaImage,
+    ) {
+        let size = [image.width(), image.height()];
+        let outdated = self
+            .background
+            .as_ref()
+            .is_none_or(|background| background.texture.size() != size);
+        if outdated {
+            // The images are encoded in sRGB, so they are sampled in the linear space of the points
+            let texture = wgpu::TextureBuilder::new()
+                .size(size)
+                .format(wgpu::TextureFormat::Rgba8UnormSrgb)
+                .usage(wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST)
+                .build(device);
+            // The linear sampler of the warp also smooths the background
+            let bind_group = wgpu::BindGroupBuilder::new()
+                .texture_view(&texture.view().build())
+                .sampler(&self.warp_sampler)
+                .build(device, &self.background_bind_group_layout);
+            self.background = Some(Background {
+                texture,
+                bind_group,
+            });
+        }
+        let background = self.background.as_ref().unwrap();
+        background
+            .texture
+            .upload_data(device, encoder, image.as_raw());
+    }
+
+    /// Stops drawing an image behind the points.
+    pub fn clear_background(&mut self) {
+        self.background = None;
+    }
+
+    /// Draws the Gaussians of a 3D Gaussian Splatting capture among the points, replacing those
+    /// drawn before.
+    ///
+    /// The Gaussians are not drawn on the devices that cannot read them from storage buffers, or
+    /// when there are too many to fit in one.
+    pub fn set_gaussians(&mut self, device: &wgpu::Device, gaussians: &[Gaussian]) {
+        self.gaussians = None;
+        let Some(stage) = &self.gaussian_stage else {
+            eprintln!(
+                "warning: storage buffers are not supported by the device, the Gaussians are not drawn"
+            );
+            return;
+        };
+        let size = std::mem::size_of_val(gaussians) as u64;
+        if gaussians.is_empty() || size > device.limits().max_storage_buffer_binding_size as u64 {
+            eprintln!(
+                "warning: the Gaussians exceed the storage buffers of the device, they are not drawn"
+            );
+            return;
+        }
+
+        let buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Gaussian Buffer"),
+            contents: Gaussian::as_bytes(gaussians),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let order: Vec<u32> = (0..gaussians.len() as u32).collect();
+        let order_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Gaussian Order Buffer"),
+            contents: unsafe { wgpu::bytes::from_slice(&order) },
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = wgpu::BindGroupBuilder::new()
+            .binding(buffer.as_entire_binding())
+            .binding(order_buffer.as_entire_binding())
+            .binding(self.camera_buffer.as_entire_binding())
+            .binding(stage.view_buffer.as_entire_binding())
+            .build(device, &stage.bind_group_layout);
+        self.gaussians = Some(GaussianCloud {
+            buffer,
+            order_buffer,
+            bind_group,
+            positions: gaussians.iter().map(|gaussian| gaussian.position).collect(),
+            sorted_for: None,
+        });
+    }
+
+    /// Stops drawing the Gaussians of a capture.
+    pub fn clear_gaussians(&mut self) {
+        self.gaussians = None;
+    }
+
+    /// The number of Gaussians drawn.
+    pub fn gaussian_count(&self) -> usize {
+        self.gaussians
+            .as_ref()
+            .map_or(0, |gaussians| gaussians.positions.len())
+    }
+
+    /// Sets how much the Gaussians are scaled, 1 drawing them as captured.
+    pub fn set_gaussian_scale(&mut self, scale: f32) {
+        self.gaussian_scale = scale.max(0.0);
+    }
+
+    /// Sorts the Gaussians from the farthest to the nearest whenever the view moves, and sizes them
+    /// for a target of `size` pixels.
+    fn prepare_gaussians(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        size: [u32; 2],
+    ) {
+        let (Some(stage), Some(gaussians)) = (&self.gaussian_stage, &mut self.gaussians) else {
+            return;
+        };
+        let view = GaussianView::new(size.map(|length| length as f32), self.gaussian_scale);
+        Self::write_uniforms(
+            device,
+            encoder,
+            "Gaussian View Uniforms Buffer",
+            view.as_bytes(),
+            &stage.view_buffer,
+        );
+
+        let transforms = self.camera.uniforms();
+        let world_view = transforms.view * transforms.world;
+        if gaussians.sorted_for == Some(world_view) {
+            return;
+        }
+        let order = sort_back_to_front(&gaussians.positions, world_view);
+        Self::write_uniforms(
+            device,
+            encoder,
+            "Gaussian Order Buffer",
+            unsafe { wgpu::bytes::from_slice(&order) },
+            &gaussians.order_buffer,
+        );
+        gaussians.sorted_for = Some(world_view);
+    }
+
+    /// The fraction of the frame resolution the points are rendered at.
+    pub fn render_scale(&self) -> f32 {
+        self.render_scale
+    }
+
+    /// Sets the fraction of the frame resolution the points are rendered at, in the range (0, 1].
+    ///
+    /// Below 1, the points are rendered to an offscreen target which is then upscaled to the frame.
+    pub fn set_render_scale(&mut self, scale: f32) {
+        self.render_scale = scale.clamp(f32::EPSILON, 1.0);
+        if self.render_scale >= 1.0 && !self.post_processes() {
+            self.offscreen = None;
+        }
+    }
+
+    /// Updates the exposure of the frame, adapting the automatic exposure to the luminance of the
+    /// frame shown `delta_time` seconds after the previous one.
+    ///
+    /// The automatic exposure adapts over the frames, so this is called every frame while it is
+    /// enabled. On the devices without compute shaders, the exposure stays fixed.
+    pub fn update_exposure(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        exposure_settings: ExposureSettings,
+        delta_time: f32,
+    ) {
+        let was_auto = self.adapts_exposure();
+        self.exposure_settings = exposure_settings;
+        if self.adapts_exposure() {
+            Self::write_uniforms(
+                device,
+                encoder,
+                "Exposure Uniforms Buffer",
+                ExposureUniforms::new(&self.exposure_settings, delta_time).as_bytes(),
+                &self.exposure_uniforms_buffer,
+            );
+        }
+        // The automatic exposure adapts from the fixed one
+        if !self.adapts_exposure() || !was_auto {
+            Self::write_uniforms(
+                device,
+                encoder,
+                "Exposure Buffer",
+                ExposureState::fixed(self.exposure_settings.stops).as_bytes(),
+                &self.exposure_buffer,
+            );
+        }
+        if self.render_scale >= 1.0 && !self.post_processes() {
+            self.offscreen = None;
+        }
+    }
+
+    /// Whether the frame is rendered offscreen to be warped or exposed before it is shown.
+    fn post_processes(&self) -> bool {
+        self.warp_settings.enabled || self.exposure_settings.is_active()
+    }
+
+    /// Whether the exposure adapts to the luminance of the frame.
+    fn adapts_exposure(&self) -> bool {
+        self.exposure_settings.auto && self.exposure_stage.is_some()
+    }
+
+    pub fn update_camera_transforms(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        Self::write_uniforms(
+            device,
+            encoder,
+            "Camera Uniforms Buffer",
+            self.camera.uniforms().as_bytes(),
+            &self.camera_buffer,
+        );
+
+        // The fog plane depends on the camera
+        Self::write_uniforms(
+            device,
+            encoder,
+            "Fog Uniforms Buffer",
+            FogUniforms::new(&self.fog_settings, &self.camera).as_bytes(),
+            &self.fog_buffer,
+        );
+    }
+
+    /// Updates the parameters of the deformation of the scene, of which the clouds overriding
+    /// them only take the values driven by the simulation.
+    pub fn update_cloud_data(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        cloud_data: CloudData,
+    ) {
+        self.cloud_data = cloud_data;
+        self.clouds.iter().for_each(|cloud| {
+            Self::write_uniforms(
+                device,
+                encoder,
+                "Cloud Data Uniforms Buffer",
+                cloud.layer.cloud_data(&self.cloud_data).as_bytes(),
+                &cloud.buffers.cloud_data_buffer,
+            );
+        });
+    }
+
+    pub fn update_attractors(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        attractor_data: &AttractorData,
+    ) {
+        Self::write_uniforms(
+            device,
+            encoder,
+            "Attractors Uniforms Buffer",
+            attractor_data.as_bytes(),
+            &self.attractor_buffer,
+        );
+    }
+
+    pub fn update_masks(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        masks: &EffectMasks,
+    ) {
+        Self::write_uniforms(
+            device,
+            encoder,
+            "Effect Masks Uniforms Buffer",
+            masks.as_bytes(),
+            &self.mask_buffer,
+        );
+    }
+
+    pub fn update_spectrum(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        spectrum_data: &SpectrumData,
+    ) {
+        Self::write_uniforms(
+            device,
+            encoder,
+            "Spectrum Uniforms Buffer",
+            spectrum_data.as_bytes(),
+            &self.spectrum_buffer,
+        );
+    }
+
+    pub fn update_pulse(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        pulse_data: &PulseData,
+    ) {
+        Self::write_uniforms(
+            device,
+            encoder,
+            "Pulse Uniforms Buffer",
+            pulse_data.as_bytes(),
+            &self.pulse_buffer,
+        );
+    }
+
+    pub fn update_curl_noise(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        curl_noise_data: &CurlNoiseData,
+    ) {
+        Self::write_uniforms(
+            device,
+            encoder,
+            "Curl Noise Uniforms Buffer",
+            curl_noise_data.as_bytes(),
+            &self.curl_noise_buffer,
+        );
+    }
+
+    pub fn update_gravity(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        gravity_data: &GravityData,
+    ) {
+        Self::write_uniforms(
+            device,
+            encoder,
+            "Gravity Uniforms Buffer",
+            gravity_data.as_bytes(),
+            &self.gravity_buffer,
+        );
+    }
+
+    pub fn update_impulses(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        impulse_data: &ImpulseData,
+    ) {
+        Self::write_uniforms(
+            device,
+            encoder,
+            "Impulse Uniforms Buffer",
+            impulse_data.as_bytes(),
+            &self.impulse_buffer,
+        );
+    }
+
+    /// Sets the effects deforming the points and the order their passes run in.
+    pub fn set_effects(&mut self, effects: EffectStack) {
+        self.effects = effects;
+    }
+
+    pub fn update_fog(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        fog_settings: FogSettings,
+    ) {
+        self.fog_settings = fog_settings;
+        Self::write_uniforms(
+            device,
+            encoder,
+            "Fog Uniforms Buffer",
+            FogUniforms::new(&self.fog_settings, &self.camera).as_bytes(),
+            &self.fog_buffer,
+        );
+    }
+
+    /// Updates how the points are blended into the frame.
+    pub fn update_glow(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        glow_settings: GlowSettings,
+    ) {
+        self.glow_settings = glow_settings;
+        Self::write_uniforms(
+            device,
+            encoder,
+            "Glow Uniforms Buffer",
+            GlowUniforms::new(&self.glow_settings).as_bytes(),
+            &self.glow_buffer,
+        );
+    }
+
+    /// Updates how the points are lit from their normals.
+    pub fn update_lighting(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        lighting: &LightingSettings,
+    ) {
+        Self::write_uniforms(
+            device,
+            encoder,
+            "Lighting Uniforms Buffer",
+            LightingUniforms::new(lighting).as_bytes(),
+            &self.lighting_buffer,
+        );
+    }
+
+    /// Updates how the points are colored.
+    pub fn update_coloring(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        color_settings: &ColorSettings,
+    ) {
+        Self::write_uniforms(
+            device,
+            encoder,
+            "Coloring Uniforms Buffer",
+            color_settings.uniforms().as_bytes(),
+            &self.color_buffer,
+        );
+    }
+
+    /// Updates the box the points are cropped to.
+    pub fn update_clip(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        clip_box: &ClipBox,
+    ) {
+        Self::write_uniforms(
+            device,
+            encoder,
+            "Clip Uniforms Buffer",
+            clip_box.uniforms().as_bytes(),
+            &self.clip_buffer,
+        );
+    }
+
+    /// Updates the trails of the points, reallocating their ring buffer if their length changed.
+    pub fn update_trail(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        trail_data: TrailData,
+    ) {
+        let mut trail_data = trail_data;
+        let max_length = self.max_trail_length();
+        if trail_data.length() > max_length {
+            trail_data.set_length(max_length);
+        }
+        let reallocate = trail_data.slots() != self.trail_data.slots();
+        self.trail_data = trail_data;
+        if reallocate {
+            self.clouds.iter_mut().for_each(|cloud| {
+                cloud.buffers.trail_buffer = Self::create_trail_buffer(
+                    device,
+                    cloud.buffers.vertex_buffer_len as usize,
+                    &trail_data,
+                );
+            });
+            self.recreate_bind_groups(device);
+        }
+
+        Self::write_uniforms(
+            device,
+            encoder,
+            "Trail Uniforms Buffer",
+            self.trail_data.as_bytes(),
+            &self.trail_data_buffer,
+        );
+    }
+
+    /// Replaces the points of the main cloud, keeping its layer.
+    pub fn new_point_cloud(&mut self, device: &wgpu::Device, points: &[Point]) {
+        let largest = self.clouds[1..]
+            .iter()
+            .map(|cloud| cloud.buffers.vertex_buffer_len as usize)
+            .fold(points.len(), usize::max);
+        self.fit_trails(device, largest);
+        let layer = self.clouds[0].layer;
+        self.clouds[0] = self.create_cloud(device, points, None, layer);
+    }
+
+    /// Adds a point cloud to the scene, with the octree its points were reordered with if any, and
+    /// returns its index.
+    pub fn add_cloud(
+        &mut self,
+        device: &wgpu::Device,
+        points: &[Point],
+        octree: Option<Octree>,
+        layer: CloudLayer,
+    ) -> usize {
+        let largest = self
+            .clouds
+            .iter()
+            .map(|cloud| cloud.buffers.vertex_buffer_len as usize)
+            .fold(points.len(), usize::max);
+        self.fit_trails(device, largest);
+        let cloud = self.create_cloud(device, points, octree, layer);
+        self.clouds.push(cloud);
+        self.clouds.len() - 1
+    }
+
+    /// The longest trails the device can record for every cloud of the scene.
+    pub fn max_trail_length(&self) -> u32 {
+        let largest = self
+            .clouds
+            .iter()
+            .map(|cloud| cloud.buffers.vertex_buffer_len as usize)
+            .max()
+            .unwrap_or(0);
+        TrailData::max_length(largest, self.buffer_limit)
+    }
+
+    /// Shortens the trails so the ring buffer of a cloud of `largest` points fits in a buffer of
+    /// the device, before its buffers are allocated.
+    fn fit_trails(&mut self, device: &wgpu::Device, largest: usize) {
+        let max_length = TrailData::max_length(largest, self.buffer_limit);
+        if self.trail_data.length() <= max_length {
+            return;
+        }
+        eprintln!(
+            "Shortening the trails to {max_length} ghosts so the positions of {largest} points \
+             fit in a buffer of the GPU"
+        );
+        self.trail_data.set_length(max_length);
+        let trail_data = self.trail_data;
+        self.clouds.iter_mut().for_each(|cloud| {
+            cloud.buffers.trail_buffer = Self::create_trail_buffer(
+                device,
+                cloud.buffers.vertex_buffer_len as usize,
+                &trail_data,
+            );
+        });
+        self.recreate_bind_groups(device);
+    }
+
+    /// Removes the cloud at the index from the scene, shifting the following ones down.
+    ///
+    /// The main cloud, at index 0, cannot be removed.
+    pub fn remove_cloud(&mut self, index: usize) {
+        if index > 0 && index < self.clouds.len() {
+            self.clouds.remove(index);
+        }
+    }
+
+    /// The number of clouds of the scene, including the main cloud.
+    pub fn cloud_count(&self) -> usize {
+        self.clouds.len()
+    }
+
+    /// Updates how the cloud at the index is placed, shown and deformed.
+    pub fn update_layer(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        index: usize,
+        layer: CloudLayer,
+    ) {
+        let Some(cloud) = self.clouds.get_mut(index) else {
+            return;
+        };
+        cloud.layer = layer;
+        Self::write_uniforms(
+            device,
+            encoder,
+            "Transform Uniforms Buffer",
+            layer.transform.uniforms().as_bytes(),
+            &cloud.buffers.transform_buffer,
+        );
+        Self::write_uniforms(
+            device,
+            encoder,
+            "Cloud Data Uniforms Buffer",
+            layer.cloud_data(&self.cloud_data).as_bytes(),
+            &cloud.buffers.cloud_data_buffer,
+        );
+    }
+
+    /// Sets whether the points are drawn as splats, camera-facing discs of the point size of the
+    /// cloud data, instead of single pixels.
+    pub fn set_splats(&mut self, splats: bool) {
+        self.splats = splats;
+    }
 
-    pub fn new(window: &Window, points: &[Point], camera: Camera, cloud_data: CloudData) -> Self {
-        // Initialize utilities
-        let device = window.device();
-        let msaa_samples = window.msaa_samples();
-        let (window_width, window_height) = window.inner_size_pixels();
+    /// Sets the octree of the main point cloud, which must have been uploaded in the order the
+    /// octree was built with.
+    pub fn set_octree(&mut self, octree: Option<Octree>) {
+        self.clouds[0].octree = octree;
+    }
 
-        // Load shaders
-        let compute_shader =
-            device.create_shader_module(wgpu::include_wgsl!("shaders/compute.wgsl"));
-        let render_shader = device.create_shader_module(wgpu::include_wgsl!("shaders/render.wgsl"));
+    /// The octree of the main point cloud, if its level of detail is built.
+    pub fn octree(&self) -> Option<&Octree> {
+        self.clouds[0].octree.as_ref()
+    }
 
-        // Create the depth buffer texture
-        let depth_texture = Self::create_depth_texture(
-            device,
-            [window_width, window_height],
-            Self::DEPTH_FORMAT,
-            msaa_samples,
-        );
-        let depth_texture_view = depth_texture.view().build();
+    /// Uploads a tile of the main cloud streamed from the disk, by its index, replacing the tile
+    /// of the same index if any.
+    ///
+    /// The tiles are placed with the main cloud and drawn at rest, as single pixels, over it.
+    pub fn upload_tile(&mut self, device: &wgpu::Device, index: usize, points: &[Point]) {
+        let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Tile Vertex Buffer"),
+            contents: Point::as_bytes(points),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let tile = StreamedTile {
+            vertex_buffer,
+            len: points.len() as u32,
+        };
+        self.tiles.insert(index, tile);
+    }
 
-        // Create the initial vertex buffer
-        let initial_vertex_buffer = Self::create_initial_vertex_buffer(device, points);
+    /// Evicts a tile of the main cloud from the GPU.
+    pub fn evict_tile(&mut self, index: usize) {
+        self.tiles.remove(&index);
+    }
 
-        // Create the vertex buffer
-        let vertex_buffer = Self::create_vertex_buffer(device, points);
+    /// Evicts every tile of the main cloud from the GPU.
+    pub fn clear_tiles(&mut self) {
+        self.tiles.clear();
+    }
 
-        // Uniform buffer (for camera)
-        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Camera Uniforms Buffer"),
-            contents: camera.uniforms().as_bytes(),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+    /// Sets the guides drawn around the bounding box of the points, given by its corners in the
+    /// coordinates of the cloud and the matrix placing the cloud in the scene.
+    pub fn update_gizmo(
+        &mut self,
+        device: &wgpu::Device,
+        settings: &GizmoSettings,
+        bounds: (Point3, Point3),
+        placement: Mat4,
+    ) {
+        let vertices = settings.lines(bounds, placement);
+        self.gizmo_lines = (!vertices.is_empty()).then(|| GizmoLines {
+            buffer: device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("Gizmo Vertex Buffer"),
+                contents: GizmoVertex::as_bytes(&vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            }),
+            vertex_count: vertices.len() as u32,
         });
+    }
 
-        // Create the Data storage buffer
-        let cloud_data_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("Cloud Data Uniforms Buffer"),
-            contents: cloud_data.as_bytes(),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
+    /// Updates the settings of the level of detail.
+    pub fn set_lod_settings(&mut self, lod_settings: LodSettings) {
+        self.lod_settings = lod_settings;
+    }
 
-        // Create the render bind group
-        let (render_bind_group_layout, render_bind_group) =
-            Self::create_render_bind_group(device, &vertex_buffer, &camera_buffer);
+    /// Returns the number of points drawn in the last frame.
+    pub fn drawn_points(&self) -> u32 {
+        let tiles = match self.clouds[0].layer.visible {
+            true => self.tiles.values().map(|tile| tile.len).sum(),
+            false => 0,
+        };
+        self.visible_clouds()
+            .flat_map(|cloud| &cloud.visible_ranges)
+            .map(|range| range.end - range.start)
+            .sum::<u32>()
+            + tiles
+    }
 
-        // Create the compute bind group
-        let (compute_bind_group_layout, compute_bind_group) = Self::create_compute_bind_group(
-            device,
-            &vertex_buffer,
-            &initial_vertex_buffer,
-            &cloud_data_buffer,
-        );
+    /// Returns the number of bytes of the buffers of the pipeline, the textures of the frame aside.
+    ///
+    /// The points take up most of it, as they are held three times: deformed, at rest and in the
+    /// trails.
+    pub fn gpu_memory(&self) -> u64 {
+        let clouds: u64 = self
+            .clouds
+            .iter()
+            .flat_map(|cloud| {
+                let buffers = &cloud.buffers;
+                [
+                    &buffers.vertex_buffer,
+                    &buffers.initial_vertex_buffer,
+                    &buffers.trail_buffer,
+                    &buffers.cloud_data_buffer,
+                    &buffers.transform_buffer,
+                ]
+            })
+            .map(|buffer| buffer.size())
+            .sum();
+        let tiles: u64 = self
+            .tiles
+            .values()
+            .map(|tile| tile.vertex_buffer.size())
+            .sum();
+        let shared: u64 = [
+            &self.camera_buffer,
+            &self.attractor_buffer,
+            &self.mask_buffer,
+            &self.spectrum_buffer,
+            &self.pulse_buffer,
+            &self.impulse_buffer,
+            &self.fog_buffer,
+            &self.color_buffer,
+            &self.clip_buffer,
+            &self.trail_data_buffer,
+            &self.curl_noise_buffer,
+            &self.gravity_buffer,
+            &self.glow_buffer,
+            &self.coarse_depth_buffer,
+            &self.lighting_buffer,
+            &self.warp_vertex_buffer,
+            &self.panel_vertex_buffer,
+            &self.exposure_uniforms_buffer,
+            &self.exposure_buffer,
+        ]
+        .into_iter()
+        .chain(self.gizmo_lines.as_ref().map(|lines| &lines.buffer))
+        .chain(self.gaussian_stage.as_ref().map(|stage| &stage.view_buffer))
+        .chain(
+            self.gaussians
+                .iter()
+                .flat_map(|gaussians| [&gaussians.buffer, &gaussians.order_buffer]),
+        )
+        .map(|buffer| buffer.size())
+        .sum();
+        clouds + tiles + shared
+    }
 
-        // Create the pipeline layout
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&render_bind_group_layout],
-                push_constant_ranges: &[],
-            });
-        // Create the render pipeline
-        let render_pipeline =
-            wgpu::RenderPipelineBuilder::from_layout(&render_pipeline_layout, &render_shader)
-                .vertex_entry_point("vs_main")
-                .fragment_shader(&render_shader)
-                .fragment_entry_point("fs_main")
-                .color_format(Frame::TEXTURE_FORMAT)
-                .color_blend(wgpu::BlendComponent::REPLACE)
-                .alpha_blend(wgpu::BlendComponent::REPLACE)
-                .primitive_topology(wgpu::PrimitiveTopology::PointList)
-                .depth_format(Self::DEPTH_FORMAT)
-                .sample_count(msaa_samples)
-                .build(device);
+    /// The clouds that are deformed and drawn.
+    fn visible_clouds(&self) -> impl Iterator<Item = &GpuCloud> {
+        self.clouds.iter().filter(|cloud| cloud.layer.visible)
+    }
 
-        // Compute pipeline
-        let compute_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Compute Pipeline Layout"),
-                bind_group_layouts: &[&compute_bind_group_layout],
-                push_constant_ranges: &[],
-            });
-        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: Some("Compute Pipeline"),
-            layout: Some(&compute_pipeline_layout),
-            module: &compute_shader,
-            entry_point: "cs_main",
+    /// Selects the ranges of points to draw, which are all the points without level of detail.
+    fn update_visible_ranges(&mut self, viewport_height: f32) {
+        let (camera, lod_settings) = (&self.camera, &self.lod_settings);
+        self.clouds.iter_mut().for_each(|cloud| {
+            cloud.visible_ranges = match &cloud.octree {
+                Some(octree) if lod_settings.enabled => {
+                    octree.select(camera, viewport_height, lod_settings)
+                }
+                _ => std::iter::once(0..cloud.buffers.vertex_buffer_len).collect(),
+            };
         });
+    }
 
-        GPUPipeline {
-            vertex_buffer,
+    /// Creates the buffers and bind groups of a point cloud.
+    fn create_cloud(
+        &self,
+        device: &wgpu::Device,
+        points: &[Point],
+        octree: Option<Octree>,
+        layer: CloudLayer,
+    ) -> GpuCloud {
+        let buffers = CloudBuffers {
+            vertex_buffer: Self::create_vertex_buffer(device, points),
             vertex_buffer_len: points.len() as u32,
-            initial_vertex_buffer,
-            camera_buffer,
-            cloud_data_buffer,
-            depth_texture,
-            depth_texture_view,
-            render_bind_group,
-            compute_bind_group,
-            render_pipeline,
-            compute_pipeline,
-            camera,
+            initial_vertex_buffer: Self::create_initial_vertex_buffer(device, points),
+            trail_buffer: Self::create_trail_buffer(device, points.len(), &self.trail_data),
+            cloud_data_buffer: device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("Cloud Data Uniforms Buffer"),
+                contents: layer.cloud_data(&self.cloud_data).as_bytes(),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }),
+            transform_buffer: device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("Transform Uniforms Buffer"),
+                contents: layer.transform.uniforms().as_bytes(),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }),
+        };
+        let (fallback_bind_group, deform_bind_groups) = self.create_bind_groups(device, &buffers);
+        GpuCloud {
+            visible_ranges: std::iter::once(0..buffers.vertex_buffer_len).collect(),
+            buffers,
+            layer,
+            fallback_bind_group,
+            deform_bind_groups,
+            octree,
         }
     }
 
-    pub fn render(&mut self, frame: &Frame) {
-        let device = frame.device_queue_pair().device();
-        let mut encoder = frame.command_encoder();
-
-        // Step 1: Dispatch compute pass
-        self.dispatch_compute(&mut encoder);
+    /// Recreates the bind groups of the clouds after one of their buffers was reallocated.
+    fn recreate_bind_groups(&mut self, device: &wgpu::Device) {
+        (0..self.clouds.len()).for_each(|index| {
+            let (fallback, deform) = self.create_bind_groups(device, &self.clouds[index].buffers);
+            let cloud = &mut self.clouds[index];
+            cloud.fallback_bind_group = fallback;
+            cloud.deform_bind_groups = deform;
+        });
+    }
 
-        // Step 2: Insert buffer barrier to sync compute output to render input
-        encoder.insert_debug_marker("Buffer Sync Barrier");
+    /// Creates the bind groups of the buffers of a cloud, for the fallback pipeline and, unless
+    /// the buffers exceed the limits of the device, for the deformation stages.
+    fn create_bind_groups(
+        &self,
+        device: &wgpu::Device,
+        buffers: &CloudBuffers,
+    ) -> (wgpu::BindGroup, Option<DeformBindGroups>) {
+        let fallback = wgpu::BindGroupBuilder::new()
+            .binding(self.camera_buffer.as_entire_binding())
+            .binding(self.fog_buffer.as_entire_binding())
+            .binding(self.color_buffer.as_entire_binding())
+            .binding(self.clip_buffer.as_entire_binding())
+            .binding(buffers.transform_buffer.as_entire_binding())
+            .build(device, &self.fallback_bind_group_layout);
 
-        // If the window has changed size, recreate our depth texture to match.
-        if frame.texture_size() != self.depth_texture.size() {
-            self.update_depth_texture(device, &mut encoder, frame);
-        }
+        let deform = self.deform.as_ref().and_then(|deform| {
+            let fits = Self::fits_storage(
+                &device.limits(),
+                &[
+                    &buffers.vertex_buffer,
+                    &buffers.initial_vertex_buffer,
+                    &buffers.trail_buffer,
+                ],
+            );
+            if !fits {
+                eprintln!(
+                    "warning: the point cloud exceeds the storage buffers of the device, the points are drawn without deformation"
+                );
+                return None;
+            }
 
-        // Step 3: Dispatch render pass
-        self.dispatch_render(&mut encoder, frame);
+            let render = wgpu::BindGroupBuilder::new()
+                .binding(buffers.vertex_buffer.as_entire_binding())
+                .binding(self.camera_buffer.as_entire_binding())
+                .binding(self.fog_buffer.as_entire_binding())
+                .binding(buffers.trail_buffer.as_entire_binding())
+                .binding(self.trail_data_buffer.as_entire_binding())
+                .binding(buffers.cloud_data_buffer.as_entire_binding())
+                .binding(self.color_buffer.as_entire_binding())
+                .binding(self.clip_buffer.as_entire_binding())
+                .binding(buffers.transform_buffer.as_entire_binding())
+                .binding(self.glow_buffer.as_entire_binding())
+                .binding(self.coarse_depth_buffer.as_entire_binding())
+                .binding(self.lighting_buffer.as_entire_binding())
+                .build(device, &deform.render_bind_group_layout);
+            let compute = wgpu::BindGroupBuilder::new()
+                .binding(buffers.vertex_buffer.as_entire_binding())
+                .binding(buffers.initial_vertex_buffer.as_entire_binding())
+                .binding(buffers.cloud_data_buffer.as_entire_binding())
+                .binding(self.attractor_buffer.as_entire_binding())
+                .binding(self.mask_buffer.as_entire_binding())
+                .binding(self.spectrum_buffer.as_entire_binding())
+                .binding(buffers.trail_buffer.as_entire_binding())
+                .binding(self.trail_data_buffer.as_entire_binding())
+                .binding(self.pulse_buffer.as_entire_binding())
+                .binding(self.curl_noise_buffer.as_entire_binding())
+                .binding(self.gravity_buffer.as_entire_binding())
+                .binding(self.impulse_buffer.as_entire_binding())
+                .build(device, &deform.compute_bind_group_layout);
+            let occlusion = wgpu::BindGroupBuilder::new()
+                .binding(buffers.vertex_buffer.as_entire_binding())
+                .binding(self.camera_buffer.as_entire_binding())
+                .binding(self.clip_buffer.as_entire_binding())
+                .binding(buffers.transform_buffer.as_entire_binding())
+                .binding(self.glow_buffer.as_entire_binding())
+                .binding(self.coarse_depth_buffer.as_entire_binding())
+                .build(device, &deform.occlusion_bind_group_layout);
+            Some(DeformBindGroups {
+                render,
+                compute,
+                occlusion,
+            })
+        });
+        (fallback, deform)
     }
 
-    pub fn update_camera_transforms(
-        &mut self,
+    /// Copies the current, deformed, points of the main cloud from the GPU back to the CPU.
+    ///
+    /// This blocks until the GPU has finished all the submitted work, so it is meant for a single
+    /// copy; [`Self::request_points`] reads the points without waiting, such as every frame of an
+    /// export.
+    pub fn read_back_points(
+        &self,
         device: &wgpu::Device,
-        encoder: &mut wgpu::CommandEncoder,
-    ) {
-        let camera_storage_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("Camera Uniforms Buffer"),
-            contents: self.camera.uniforms().as_bytes(),
-            usage: wgpu::BufferUsages::COPY_SRC,
+        queue: &wgpu::Queue,
+    ) -> Result<Vec<Point>, &'static str> {
+        let buffers = &self.clouds[0].buffers;
+        let size = buffers.vertex_buffer_len as wgpu::BufferAddress
+            * std::mem::size_of::<Point>() as wgpu::BufferAddress;
+        let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Vertex Staging Buffer"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
         });
 
-        // Copy the new uniforms buffer to the uniform buffer.
-        encoder.copy_buffer_to_buffer(
-            &camera_storage_buffer,
-            0,
-            &self.camera_buffer,
-            0,
-            std::mem::size_of::<CameraTransforms>() as wgpu::BufferAddress,
-        );
+        // Copy the vertex buffer to the staging buffer
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Read Back Encoder"),
+        });
+        encoder.copy_buffer_to_buffer(&buffers.vertex_buffer, 0, &staging_buffer, 0, size);
+        queue.submit(Some(encoder.finish()));
+
+        // Map the staging buffer and wait for the copy to complete
+        let slice = staging_buffer.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            sender.send(result).ok();
+        });
+        device.poll(wgpu::Maintain::Wait);
+        if !matches!(receiver.recv(), Ok(Ok(()))) {
+            return Err("Failed to map the points read back from the GPU");
+        }
+
+        let points = Point::from_bytes(&slice.get_mapped_range());
+        staging_buffer.unmap();
+        Ok(points)
     }
 
-    pub fn update_cloud_data(
-        &mut self,
+    /// Starts copying the current, deformed, points of the main cloud to a staging buffer of
+    /// `readback` under `tag`, to be collected by [`PointReadback::poll`] on a later frame.
+    ///
+    /// Returns whether the copy was started, which it is not if every staging buffer is in use.
+    pub fn request_points(
+        &self,
         device: &wgpu::Device,
-        encoder: &mut wgpu::CommandEncoder,
-        cloud_data: CloudData,
-    ) {
-        let cloud_data_storage_buffer = device.create_buffer_init(&BufferInitDescriptor {
-            label: Some("Cloud Data Uniforms Buffer"),
-            contents: cloud_data.as_bytes(),
-            usage: wgpu::BufferUsages::COPY_SRC,
+        queue: &wgpu::Queue,
+        readback: &mut PointReadback,
+        tag: u32,
+    ) -> bool {
+        let buffers = &self.clouds[0].buffers;
+        let size = buffers.vertex_buffer_len as wgpu::BufferAddress
+            * std::mem::size_of::<Point>() as wgpu::BufferAddress;
+        readback.request(device, queue, &buffers.vertex_buffer, size, tag)
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    /// Whether the points are deformed, which depends on the support of the device for compute
+    /// shaders and on the size of the point clouds.
+    ///
+    /// Otherwise, the points are drawn at rest, without trails or splats.
+    pub fn deforms(&self) -> bool {
+        self.deform.is_some()
+            && self
+                .clouds
+                .iter()
+                .all(|cloud| cloud.deform_bind_groups.is_some())
+    }
+
+    /// Holds the points at their current deformation, skipping the compute pass, or lets them move
+    /// again.
+    ///
+    /// The stills rendered over several frames freeze the points so their tiles show the same
+    /// instant.
+    pub fn set_frozen(&mut self, frozen: bool) {
+        self.frozen = frozen;
+    }
+
+    /// Starts reloading the shaders of the deformation from `directory` whenever they are edited,
+    /// or stops with `None`.
+    #[cfg(feature = "hot-reload")]
+    pub fn watch_shaders(&mut self, directory: Option<std::path::PathBuf>) {
+        self.shader_watcher = directory.map(ShaderWatcher::new);
+    }
+
+    /// The watcher of the shaders, while they are reloaded.
+    #[cfg(feature = "hot-reload")]
+    pub fn shader_watcher(&self) -> Option<&ShaderWatcher> {
+        self.shader_watcher.as_ref()
+    }
+
+    /// Swaps in the shaders of the deformation if they were edited, to be called once per frame.
+    ///
+    /// Shaders that fail to compile, or that no longer match the bind groups of the renderer, are
+    /// reported by the watcher and the last good pipelines are kept.
+    #[cfg(feature = "hot-reload")]
+    pub fn reload_shaders(&mut self, device: &wgpu::Device) {
+        let (Some(watcher), Some(deform)) = (&mut self.shader_watcher, &mut self.deform) else {
+            return;
+        };
+        let Some(sources) = watcher.poll() else {
+            return;
+        };
+
+        let pipelines = hot_reload::catch_validation(device, || {
+            let compute_shader =
+                hot_reload::create_module(device, "compute.wgsl", &sources.compute);
+            let render_shader = hot_reload::create_module(device, "render.wgsl", &sources.render);
+            DeformStages::create_pipelines(
+                device,
+                &deform.render_bind_group_layout,
+                &deform.compute_bind_group_layout,
+                &compute_shader,
+                &render_shader,
+                self.msaa_samples,
+            )
         });
+        watcher.finish(pipelines.map(|pipelines| {
+            deform.pipelines = pipelines;
+            println!(
+                "Reloaded the shaders from {}",
+                watcher.directory().display()
+            );
+        }));
+    }
 
-        // Copy the new uniforms buffer to the uniform buffer.
-        encoder.copy_buffer_to_buffer(
-            &cloud_data_storage_buffer,
-            0,
-            &self.cloud_data_buffer,
-            0,
-            std::mem::size_of::<CloudData>() as wgpu::BufferAddress,
-        );
+    /// The deformation stages, if the points are deformed.
+    fn active_deform(&self) -> Option<&DeformStages> {
+        self.deform.as_ref().filter(|_| self.deforms())
     }
 
-    pub fn new_point_cloud(&mut self, device: &wgpu::Device, points: &[Point]) {
-        self.initial_vertex_buffer = Self::create_initial_vertex_buffer(device, points);
-        self.vertex_buffer = Self::create_vertex_buffer(device, points);
+    /// Whether the device supports the compute pass and reading the points from storage buffers in
+    /// the vertex stage, which the devices limited to WebGL2 do not.
+    fn supports_deformation(limits: &wgpu::Limits) -> bool {
+        limits.max_compute_workgroup_size_x >= Self::WORKGROUP_SIZE
+            && limits.max_compute_invocations_per_workgroup >= Self::WORKGROUP_SIZE
+            && limits.max_storage_buffers_per_shader_stage >= 3
+    }
 
-        // Create the render bind group
-        let (_, render_bind_group) =
-            Self::create_render_bind_group(device, &self.vertex_buffer, &self.camera_buffer);
+    /// The size of the largest buffer requested when none is configured, for big point clouds
+    /// and long trails.
+    pub const DEFAULT_BUFFER_SIZE: u64 = 2 << 30;
 
-        // Create the compute bind group
-        let (_, compute_bind_group) = Self::create_compute_bind_group(
-            device,
-            &self.vertex_buffer,
-            &self.initial_vertex_buffer,
-            &self.cloud_data_buffer,
-        );
+    /// The limits to request from an adapter supporting `supported`, with buffers of up to
+    /// `buffer_size` bytes.
+    ///
+    /// Each limit is the smaller of the wanted and supported ones, so devices without compute
+    /// shaders or with small storage buffers still open and draw the points without deformation.
+    pub fn negotiate_limits(supported: &wgpu::Limits, buffer_size: u64) -> wgpu::Limits {
+        let binding_size = buffer_size.min(u32::MAX as u64) as u32;
+        wgpu::Limits {
+            max_storage_buffer_binding_size: supported
+                .max_storage_buffer_binding_size
+                .min(binding_size),
+            max_buffer_size: supported.max_buffer_size.min(buffer_size),
+            ..supported.clone()
+        }
+    }
 
-        self.render_bind_group = render_bind_group;
-        self.compute_bind_group = compute_bind_group;
-        self.vertex_buffer_len = points.len() as u32;
+    /// The number of points of the largest cloud the device can deform, as its points must fit in
+    /// a single storage buffer.
+    pub fn max_points(limits: &wgpu::Limits) -> usize {
+        (Self::buffer_limit(limits) / std::mem::size_of::<Point>() as u64) as usize
     }
 
-    pub fn camera_mut(&mut self) -> &mut Camera {
-        &mut self.camera
+    /// The size of the largest buffer the device can bind as a storage buffer.
+    fn buffer_limit(limits: &wgpu::Limits) -> u64 {
+        (limits.max_storage_buffer_binding_size as u64).min(limits.max_buffer_size)
+    }
+
+    /// Whether every buffer can be bound as a storage buffer on the device.
+    fn fits_storage(limits: &wgpu::Limits, buffers: &[&wgpu::Buffer]) -> bool {
+        buffers
+            .iter()
+            .all(|buffer| buffer.size() <= limits.max_storage_buffer_binding_size as u64)
     }
 
-    fn dispatch_compute(&self, encoder: &mut wgpu::CommandEncoder) {
+    fn dispatch_compute(&self, encoder: &mut wgpu::CommandEncoder, deform: &DeformStages) {
+        // The coarse depth is written anew each frame, an empty tile holding no point
+        let attenuates = self.glow_settings.attenuates();
+        if attenuates {
+            encoder.clear_buffer(&self.coarse_depth_buffer, 0, None);
+        }
+
         let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
             label: Some("Compute Pass"),
         });
-        compute_pass.set_pipeline(&self.compute_pipeline);
-        compute_pass.set_bind_group(0, &self.compute_bind_group, &[]);
-        let workgroup_size = 256; // Must match @workgroup_size(256) in the shader
-        let num_workgroups = self.vertex_buffer_len.div_ceil(workgroup_size);
-        compute_pass.dispatch_workgroups(num_workgroups, 1, 1);
+        for cloud in self.visible_clouds() {
+            let Some(bind_groups) = &cloud.deform_bind_groups else {
+                continue;
+            };
+            compute_pass.set_bind_group(0, &bind_groups.compute, &[]);
+            let num_workgroups = cloud
+                .buffers
+                .vertex_buffer_len
+                .div_ceil(Self::WORKGROUP_SIZE);
+
+            // Run the pass of each effect in the order of the stack, then pull the points back
+            let pipelines = self
+                .effects
+                .enabled()
+                .map(|effect| deform.effect_pipeline(effect))
+                .chain([&deform.pipelines.settle]);
+            for pipeline in pipelines {
+                compute_pass.set_pipeline(pipeline);
+                compute_pass.dispatch_workgroups(num_workgroups, 1, 1);
+            }
+        }
+
+        // Keep the nearest point of each tile once every cloud is deformed, as they hide each other
+        if attenuates {
+            compute_pass.set_pipeline(&deform.occlusion_pipeline);
+            for cloud in self.visible_clouds() {
+                let Some(bind_groups) = &cloud.deform_bind_groups else {
+                    continue;
+                };
+                compute_pass.set_bind_group(0, &bind_groups.occlusion, &[]);
+                let num_workgroups = cloud
+                    .buffers
+                    .vertex_buffer_len
+                    .div_ceil(Self::WORKGROUP_SIZE);
+                compute_pass.dispatch_workgroups(num_workgroups, 1, 1);
+            }
+        }
     }
 
-    fn dispatch_render(&self, encoder: &mut wgpu::CommandEncoder, frame: &Frame) {
+    fn dispatch_render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        color_view: &wgpu::TextureView,
+        depth_view: &wgpu::TextureView,
+    ) {
         let mut render_pass = wgpu::RenderPassBuilder::new()
-            .color_attachment(frame.texture_view(), |color| color)
+            .color_attachment(color_view, |color| color)
             // We'll use a depth texture to assist with the order of rendering fragments based on depth.
-            .depth_stencil_attachment(&self.depth_texture_view, |depth| depth)
+            .depth_stencil_attachment(depth_view, |depth| depth)
+            .begin(encoder);
+
+        // Draw the background behind the points
+        if let Some(background) = &self.background {
+            render_pass.set_pipeline(&self.background_pipeline);
+            render_pass.set_bind_group(0, &background.bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+
+        // The splats are drawn with six vertices per point
+        let deform = self.active_deform();
+        let additive = self.glow_settings.additive;
+        let (pipeline, vertices_per_point) = match deform {
+            Some(deform) if self.splats && additive => (&deform.pipelines.additive_splat, 6),
+            Some(deform) if self.splats => (&deform.pipelines.splat, 6),
+            Some(deform) if additive => (&deform.pipelines.additive_render, 1),
+            Some(deform) => (&deform.pipelines.render, 1),
+            // Without storage buffers, the points are read as vertices and cannot be expanded
+            None => (&self.fallback_pipeline, 1),
+        };
+        let vertices =
+            |range: &Range<u32>| range.start * vertices_per_point..range.end * vertices_per_point;
+        render_pass.set_pipeline(pipeline);
+
+        // The second instance draws the reflection of the points in the fog plane
+        let instances = if self.fog_settings.draws_reflection() {
+            2
+        } else {
+            1
+        };
+        for cloud in self.visible_clouds() {
+            match cloud
+                .deform_bind_groups
+                .as_ref()
+                .filter(|_| deform.is_some())
+            {
+                Some(bind_groups) => render_pass.set_bind_group(0, &bind_groups.render, &[]),
+                None => {
+                    render_pass.set_bind_group(0, &cloud.fallback_bind_group, &[]);
+                    render_pass.set_vertex_buffer(0, cloud.buffers.vertex_buffer.slice(..));
+                }
+            }
+            cloud
+                .visible_ranges
+                .iter()
+                .for_each(|range| render_pass.draw(vertices(range), 0..instances));
+
+            // The instances from the third onwards draw the ghosts of the points
+            if self.trail_data.is_enabled() && deform.is_some() {
+                let ghosts = 2..2 + self.trail_data.length();
+                cloud
+                    .visible_ranges
+                    .iter()
+                    .for_each(|range| render_pass.draw(vertices(range), ghosts.clone()));
+            }
+        }
+
+        // Draw the streamed tiles of the main cloud at rest, as they have no deformation buffers
+        let main = &self.clouds[0];
+        if main.layer.visible && !self.tiles.is_empty() {
+            render_pass.set_pipeline(&self.fallback_pipeline);
+            render_pass.set_bind_group(0, &main.fallback_bind_group, &[]);
+            self.tiles.values().for_each(|tile| {
+                render_pass.set_vertex_buffer(0, tile.vertex_buffer.slice(..));
+                render_pass.draw(0..tile.len, 0..instances);
+            });
+        }
+
+        // Draw the guides among the points
+        if let Some(lines) = &self.gizmo_lines {
+            render_pass.set_pipeline(&self.gizmo_pipeline);
+            render_pass.set_bind_group(0, &self.gizmo_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, lines.buffer.slice(..));
+            render_pass.draw(0..lines.vertex_count, 0..1);
+        }
+
+        // Draw the Gaussians over the points, which hide the Gaussians behind them
+        if let (Some(stage), Some(gaussians)) = (&self.gaussian_stage, &self.gaussians) {
+            render_pass.set_pipeline(&stage.pipeline);
+            render_pass.set_bind_group(0, &gaussians.bind_group, &[]);
+            render_pass.draw(0..6, 0..gaussians.positions.len() as u32);
+        }
+
+        // Draw the fog plane over the points below it
+        if self.fog_settings.enabled {
+            render_pass.set_pipeline(&self.fog_pipeline);
+            render_pass.set_bind_group(0, &self.fog_bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+
+    fn dispatch_exposure(&self, encoder: &mut wgpu::CommandEncoder, target: &WarpTarget) {
+        let (Some(stage), Some(bind_group)) = (&self.exposure_stage, &target.exposure_bind_group)
+        else {
+            return;
+        };
+        if !self.exposure_settings.auto {
+            return;
+        }
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Exposure Pass"),
+        });
+        compute_pass.set_pipeline(&stage.pipeline);
+        compute_pass.set_bind_group(0, bind_group, &[]);
+        compute_pass.dispatch_workgroups(1, 1, 1);
+    }
+
+    fn dispatch_warp(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        frame: &Frame,
+        target: &WarpTarget,
+        vertex_buffer: &wgpu::Buffer,
+    ) {
+        let mut render_pass = wgpu::RenderPassBuilder::new()
+            .color_attachment(frame.texture_view(), |color| color)
             .begin(encoder);
-        render_pass.set_pipeline(&self.render_pipeline);
-        render_pass.set_bind_group(0, &self.render_bind_group, &[]);
-        render_pass.draw(0..self.vertex_buffer_len, 0..1);
+        render_pass.set_pipeline(&self.warp_pipeline);
+        render_pass.set_bind_group(0, &target.bind_group, &[]);
+        // The frame is tessellated the same whether it is warped or not
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.draw(0..self.warp_vertex_count, 0..1);
     }
 
     fn update_depth_texture(
@@ -251,6 +2429,30 @@ impl GPUPipeline {
         self.update_camera_transforms(device, encoder);
     }
 
+    /// Copies the given contents to a uniform buffer through a staging buffer.
+    fn write_uniforms(
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        label: &str,
+        contents: &[u8],
+        buffer: &wgpu::Buffer,
+    ) {
+        let storage_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some(label),
+            contents,
+            usage: wgpu::BufferUsages::COPY_SRC,
+        });
+
+        // Copy the new uniforms buffer to the uniform buffer.
+        encoder.copy_buffer_to_buffer(
+            &storage_buffer,
+            0,
+            buffer,
+            0,
+            contents.len() as wgpu::BufferAddress,
+        );
+    }
+
     fn create_depth_texture(
         device: &wgpu::Device,
         size: [u32; 2],
@@ -265,11 +2467,122 @@ impl GPUPipeline {
             .build(device)
     }
 
+    fn create_offscreen(
+        &self,
+        device: &wgpu::Device,
+        size: [u32; 2],
+        sample_count: u32,
+        warp: bool,
+    ) -> Offscreen {
+        let color_texture = wgpu::TextureBuilder::new()
+            .size(size)
+            .format(Frame::TEXTURE_FORMAT)
+            .usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING)
+            .sample_count(sample_count)
+            .build(device);
+        let color_texture_view = color_texture.view().build();
+        let depth_texture =
+            Self::create_depth_texture(device, size, Self::DEPTH_FORMAT, sample_count);
+        let depth_texture_view = depth_texture.view().build();
+
+        // Upscales, and resolves if needed, the offscreen target to the frame
+        let reshaper = wgpu::TextureReshaper::new(
+            device,
+            &color_texture_view,
+            sample_count,
+            color_texture.sample_type(),
+            sample_count,
+            Frame::TEXTURE_FORMAT,
+        );
+
+        // Resolve the offscreen target to a texture the warp stage can sample
+        let warp_target = warp.then(|| {
+            let resolved_texture = wgpu::TextureBuilder::new()
+                .size(size)
+                .format(Frame::TEXTURE_FORMAT)
+                .usage(
+                    wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING
+                        | wgpu::TextureUsages::COPY_SRC,
+                )
+                .build(device);
+            let resolved_texture_view = resolved_texture.view().build();
+            let resolver = wgpu::TextureReshaper::new(
+                device,
+                &color_texture_view,
+                sample_count,
+                color_texture.sample_type(),
+                1,
+                Frame::TEXTURE_FORMAT,
+            );
+            let bind_group = wgpu::BindGroupBuilder::new()
+                .texture_view(&resolved_texture_view)
+                .sampler(&self.warp_sampler)
+                .binding(self.exposure_buffer.as_entire_binding())
+                .build(device, &self.warp_bind_group_layout);
+            let exposure_bind_group = self.exposure_stage.as_ref().map(|stage| {
+                wgpu::BindGroupBuilder::new()
+                    .texture_view(&resolved_texture_view)
+                    .binding(self.exposure_uniforms_buffer.as_entire_binding())
+                    .binding(self.exposure_buffer.as_entire_binding())
+                    .build(device, &stage.bind_group_layout)
+            });
+
+            WarpTarget {
+                resolved_texture,
+                resolved_texture_view,
+                resolver,
+                bind_group,
+                exposure_bind_group,
+            }
+        });
+
+        Offscreen {
+            color_texture,
+            color_texture_view,
+            _depth_texture: depth_texture,
+            depth_texture_view,
+            reshaper,
+            warp_target,
+        }
+    }
+
+    fn create_warp_vertex_buffer(device: &wgpu::Device, vertices: &[WarpVertex]) -> wgpu::Buffer {
+        device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Warp Vertex Buffer"),
+            contents: WarpVertex::as_bytes(vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        })
+    }
+
     fn create_vertex_buffer(device: &wgpu::Device, points: &[Point]) -> wgpu::Buffer {
         device.create_buffer_init(&BufferInitDescriptor {
             label: Some("Vertex Buffer"),
             contents: Point::as_bytes(points),
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::VERTEX
+                | wgpu::BufferUsages::COPY_SRC,
+        })
+    }
+
+    /// Creates the ring buffer holding the previous positions of the points.
+    ///
+    /// Only a placeholder is allocated while the trails are disabled.
+    fn create_trail_buffer(
+        device: &wgpu::Device,
+        points_len: usize,
+        trail_data: &TrailData,
+    ) -> wgpu::Buffer {
+        let position_size = std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress;
+        let size = match trail_data.is_enabled() {
+            true => points_len as wgpu::BufferAddress * trail_data.slots() as wgpu::BufferAddress,
+            false => 1,
+        } * position_size;
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Trail Buffer"),
+            size,
             usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
         })
     }
 
@@ -281,44 +2594,90 @@ impl GPUPipeline {
         })
     }
 
-    fn create_render_bind_group(
-        device: &wgpu::Device,
-        vertex_buffer: &wgpu::Buffer,
-        camera_buffer: &wgpu::Buffer,
-    ) -> (wgpu::BindGroupLayout, wgpu::BindGroup) {
-        println!("Creating render bind group");
-        let render_bind_group_layout = wgpu::BindGroupLayoutBuilder::new()
+    fn create_render_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        wgpu::BindGroupLayoutBuilder::new()
             .storage_buffer(wgpu::ShaderStages::VERTEX, false, true)
             .uniform_buffer(wgpu::ShaderStages::VERTEX, false)
-            .build(device);
-        let render_bind_group = wgpu::BindGroupBuilder::new()
-            .binding(vertex_buffer.as_entire_binding())
-            .binding(camera_buffer.as_entire_binding())
-            .build(device, &render_bind_group_layout);
+            .uniform_buffer(wgpu::ShaderStages::VERTEX, false)
+            .storage_buffer(wgpu::ShaderStages::VERTEX, false, true)
+            .uniform_buffer(wgpu::ShaderStages::VERTEX, false)
+            .uniform_buffer(wgpu::ShaderStages::VERTEX, false)
+            .uniform_buffer(wgpu::ShaderStages::VERTEX, false)
+            .uniform_buffer(wgpu::ShaderStages::VERTEX, false)
+            .uniform_buffer(wgpu::ShaderStages::VERTEX, false)
+            .uniform_buffer(wgpu::ShaderStages::VERTEX, false)
+            .storage_buffer(wgpu::ShaderStages::VERTEX, false, true)
+            .uniform_buffer(wgpu::ShaderStages::VERTEX, false)
+            .build(device)
+    }
 
-        println!("Render bind group created");
-        (render_bind_group_layout, render_bind_group)
+    fn create_occlusion_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        wgpu::BindGroupLayoutBuilder::new()
+            .storage_buffer(wgpu::ShaderStages::COMPUTE, false, true)
+            .uniform_buffer(wgpu::ShaderStages::COMPUTE, false)
+            .uniform_buffer(wgpu::ShaderStages::COMPUTE, false)
+            .uniform_buffer(wgpu::ShaderStages::COMPUTE, false)
+            .uniform_buffer(wgpu::ShaderStages::COMPUTE, false)
+            .storage_buffer(wgpu::ShaderStages::COMPUTE, false, false)
+            .build(device)
     }
 
-    fn create_compute_bind_group(
-        device: &wgpu::Device,
-        vertex_buffer: &wgpu::Buffer,
-        initial_vertex_buffer: &wgpu::Buffer,
-        cloud_data_buffer: &wgpu::Buffer,
-    ) -> (wgpu::BindGroupLayout, wgpu::BindGroup) {
-        println!("Creating compute bind group");
-        let compute_bind_group_layout = wgpu::BindGroupLayoutBuilder::new()
+    fn create_compute_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        wgpu::BindGroupLayoutBuilder::new()
             .storage_buffer(wgpu::ShaderStages::COMPUTE, false, false)
             .storage_buffer(wgpu::ShaderStages::COMPUTE, false, true)
             .uniform_buffer(wgpu::ShaderStages::COMPUTE, false)
-            .build(device);
-        let compute_bind_group = wgpu::BindGroupBuilder::new()
-            .binding(vertex_buffer.as_entire_binding())
-            .binding(initial_vertex_buffer.as_entire_binding())
-            .binding(cloud_data_buffer.as_entire_binding())
-            .build(device, &compute_bind_group_layout);
+            .uniform_buffer(wgpu::ShaderStages::COMPUTE, false)
+            .uniform_buffer(wgpu::ShaderStages::COMPUTE, false)
+            .uniform_buffer(wgpu::ShaderStages::COMPUTE, false)
+            .storage_buffer(wgpu::ShaderStages::COMPUTE, false, false)
+            .uniform_buffer(wgpu::ShaderStages::COMPUTE, false)
+            .uniform_buffer(wgpu::ShaderStages::COMPUTE, false)
+            .uniform_buffer(wgpu::ShaderStages::COMPUTE, false)
+            .uniform_buffer(wgpu::ShaderStages::COMPUTE, false)
+            .uniform_buffer(wgpu::ShaderStages::COMPUTE, false)
+            .build(device)
+    }
+}
 
-        println!("Compute bind group created");
-        (compute_bind_group_layout, compute_bind_group)
+/// Decodes a half float, the format of the channels of the frame.
+fn half_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+    match exponent {
+        // Subnormal numbers, without the implicit leading one
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
     }
 }
+
+/// Renders a point cloud to an image without a window, at the aspect ratio of the image.
+///
+/// The points are drawn as they are given, without the deformation of the compute pass. This
+/// blocks until the image is read back from the GPU, and fails if it cannot be mapped.
+pub fn render_to_image(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    mut camera: Camera,
+    points: &[Point],
+    size: [u32; 2],
+) -> Result<RgbaImage, &'static str> {
+    camera.config.set_aspect_ratio(size[0], size[1]);
+    let cloud_data = CloudData::new(0.0, [0.0; 3], 0.0, 0.0);
+    let spectrum_data = SpectrumData::new(SpectralLayout::Off, 0.0, Point::bounding_box(points));
+    let mut pipeline = GPUPipeline::from_device(
+        device,
+        size,
+        1,
+        points,
+        camera,
+        cloud_data,
+        AttractorData::default(),
+        EffectMasks::default(),
+        spectrum_data,
+    );
+    pipeline.render_to_image(device, queue, size)
+}
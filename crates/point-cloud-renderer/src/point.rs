@@ -1,28 +1,52 @@
-use nannou::{geom::Point3, wgpu};
+use nannou::{geom::Point3, glam::Vec3, wgpu};
+use serde::{Deserialize, Serialize};
+
+use crate::color::ColorSpace;
 
 #[repr(C)]
 #[derive(Clone, Copy)]
 pub struct Point {
     pub position: [f32; 3],
-    _padding: u32,
+    /// The unit normal of the surface the point was scanned from, packed in the padding of the
+    /// position by [`Self::set_normal`], 0 if it is not known.
+    normal: u32,
+    /// The color of the point in linear space, with straight alpha, as the shaders blend it.
     pub color: [f32; 4],
 }
 
 impl Point {
     /// The vertex format for a point.
-    pub const ATTRIBS: [wgpu::VertexAttribute; 2] =
-        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4];
+    ///
+    /// The offsets are given explicitly as the packed normal pads the position to 16 bytes before
+    /// the color.
+    pub const ATTRIBS: [wgpu::VertexAttribute; 3] = [
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x3,
+            offset: 0,
+            shader_location: 0,
+        },
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x4,
+            offset: 16,
+            shader_location: 1,
+        },
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Uint32,
+            offset: 12,
+            shader_location: 2,
+        },
+    ];
 
     /// Create a new point with a position and color.
     ///
-    /// The color is in the range [0, 255].
+    /// The color is in sRGB, in the range [0, 255].
     pub fn new(position: [f32; 3], color: [u8; 4]) -> Self {
-        let color = color.map(|c| c as f32 / 255.0);
-        Self {
+        let mut point = Self {
             position,
-            _padding: 0,
-            color,
-        }
+            ..Self::default()
+        };
+        point.set_color(color);
+        point
     }
 
     /// Computes the bounding box of a point cloud.
@@ -54,6 +78,24 @@ impl Point {
         )
     }
 
+    /// Keeps at most `max_points` of the points, evenly spread through the cloud.
+    pub fn decimate(points: Vec<Self>, max_points: usize) -> Vec<Self> {
+        if points.len() <= max_points {
+            return points;
+        }
+        Self::sample(&points, max_points)
+    }
+
+    /// Copies at most `max_points` of the points, evenly spread through the cloud, keeping the
+    /// points themselves.
+    pub fn sample(points: &[Self], max_points: usize) -> Vec<Self> {
+        let count = points.len().min(max_points);
+        let step = points.len() as f64 / count.max(1) as f64;
+        (0..count)
+            .map(|i| points[(i as f64 * step) as usize])
+            .collect()
+    }
+
     /// Set the position of the point.
     pub fn set_position(&mut self, position: [f32; 3]) {
         self.position = position;
@@ -61,46 +103,234 @@ impl Point {
 
     /// Set the color of the point.
     ///
-    /// The color is in the range [0, 255].
+    /// The color is in sRGB, in the range [0, 255].
     pub fn set_color(&mut self, color: [u8; 4]) {
-        self.color = color.map(|c| c as f32 / 255.0);
+        self.set_color_f32(color.map(|c| c as f32 / 255.0), ColorSpace::Srgb);
     }
 
     /// Set the color of the point.
     ///
-    /// The color is in the range [0, 1].
-    pub fn set_color_f32(&mut self, color: [f32; 4]) {
-        self.color = color;
+    /// The color is in the range [0, 1], in the given color space.
+    pub fn set_color_f32(&mut self, color: [f32; 4], space: ColorSpace) {
+        self.color = space.to_linear(color);
+    }
+
+    /// Whether the normal of the point is known, so it can be lit.
+    pub fn has_normal(&self) -> bool {
+        self.normal != 0
+    }
+
+    /// The unit normal of the point, if it is known.
+    ///
+    /// The normal may face either side of the surface, as it is stored facing up the z axis.
+    pub fn normal(&self) -> Option<[f32; 3]> {
+        if self.normal == 0 {
+            return None;
+        }
+        let unpack = |bits: u32| (bits & 0xffff) as f32 / 65535.0 * 2.0 - 1.0;
+        let (x, y) = (unpack(self.normal), unpack(self.normal >> 16));
+        let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+        Some(Vec3::new(x, y, z).normalize().to_array())
+    }
+
+    /// Set the unit normal of the point, or forget it.
+    ///
+    /// Either side of a surface is lit alike, so the normal is flipped to face up the z axis and
+    /// only its x and y are kept, as 16-bit fractions of [-1, 1] sharing the padding of the
+    /// position; the z is recovered from them. The null bits decode to a vector outside of the
+    /// unit disk, so they stand for an unknown normal.
+    pub fn set_normal(&mut self, normal: Option<[f32; 3]>) {
+        self.normal = match normal {
+            Some([x, y, z]) => {
+                let sign = if z < 0.0 { -1.0 } else { 1.0 };
+                let quantize =
+                    |v: f32| (((v * sign).clamp(-1.0, 1.0) * 0.5 + 0.5) * 65535.0).round() as u32;
+                // A unit normal cannot have both x and y at -1, so its bits are never null
+                quantize(x) | (quantize(y) << 16)
+            }
+            None => 0,
+        };
+    }
+
+    /// The color of the point in the given color space, in the range [0, 1].
+    pub fn color_in(&self, space: ColorSpace) -> [f32; 4] {
+        space.from_linear(self.color)
     }
 
     /// Convert a slice of points to a byte slice.
     pub fn as_bytes(points: &[Point]) -> &[u8] {
         unsafe { wgpu::bytes::from_slice(points) }
     }
+
+    /// Convert a byte slice, typically read back from the GPU, to a vector of points.
+    pub fn from_bytes(bytes: &[u8]) -> Vec<Point> {
+        bytes
+            .chunks_exact(std::mem::size_of::<Point>())
+            .map(|chunk| unsafe { std::ptr::read_unaligned(chunk.as_ptr() as *const Point) })
+            .collect()
+    }
 }
 
 impl Default for Point {
     fn default() -> Self {
         Self {
             position: [0.0, 0.0, 0.0],
-            _padding: 0,
+            normal: 0,
             color: [0.0, 0.0, 0.0, 1.0],
         }
     }
 }
 
+/// The parameters of the deformation applied to the cloud by the compute shader.
+///
+/// The struct is sent as-is to the compute and render shaders, so its layout must match the `Data`
+/// struct declared in `compute.wgsl` and `render.wgsl`.
 #[repr(C)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct CloudData {
     pub sound_amplitude: f32,
     pub wind_strength: f32,
     pub noise_scale: f32,
     pub spring_constant: f32,
+    /// The direction the wind blows towards; its length scales the wind.
+    pub wind_direction: [f32; 3],
+    /// The time elapsed since the start of the simulation in seconds.
+    pub time: f32,
+    /// How much the gusts modulate the strength of the wind.
+    pub gust_strength: f32,
+    /// How often the gusts change in Hz.
+    pub gust_frequency: f32,
+    /// How much the gusts deviate the wind from its direction.
+    pub gust_spread: f32,
+    /// The diameter of the splats drawn for the points, in units of the point cloud.
+    pub point_size: f32,
+    /// How much the audio bands raise the wind strength, the noise scale and the spring constant,
+    /// as fractions of their values.
+    pub band_modulation: [f32; 3],
+    #[serde(skip)]
+    _padding: f32,
+}
+
+/// A parameter of the deformation that a band of the audio can modulate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BandTarget {
+    /// The band does not modulate the deformation.
+    Off,
+    WindStrength,
+    NoiseScale,
+    SpringConstant,
 }
 
 impl CloudData {
+    /// Creates a new set of cloud parameters with the wind blowing in the given direction.
+    ///
+    /// The gusts are disabled by default.
+    pub fn new(
+        wind_strength: f32,
+        wind_direction: [f32; 3],
+        noise_scale: f32,
+        spring_constant: f32,
+    ) -> Self {
+        Self {
+            sound_amplitude: 1.0,
+            wind_strength,
+            noise_scale,
+            spring_constant,
+            wind_direction,
+            time: 0.0,
+            gust_strength: 0.0,
+            gust_frequency: 0.2,
+            gust_spread: 0.0,
+            point_size: 0.02,
+            band_modulation: [0.0; 3],
+            _padding: 0.0,
+        }
+    }
+
+    /// Sets the modulation of the parameters from the levels of the audio bands.
+    ///
+    /// Each band raises the parameter it targets by its level, and the bands targeting the same
+    /// parameter add up.
+    pub fn set_band_levels(&mut self, targets: [BandTarget; 3], levels: [f32; 3]) {
+        self.band_modulation = [0.0; 3];
+        targets.iter().zip(levels).for_each(|(target, level)| {
+            let index = match target {
+                BandTarget::Off => return,
+                BandTarget::WindStrength => 0,
+                BandTarget::NoiseScale => 1,
+                BandTarget::SpringConstant => 2,
+            };
+            self.band_modulation[index] += level;
+        });
+    }
+
+    /// Sets the gust model of the wind.
+    pub fn with_gusts(mut self, strength: f32, frequency: f32, spread: f32) -> Self {
+        self.gust_strength = strength;
+        self.gust_frequency = frequency;
+        self.gust_spread = spread;
+        self
+    }
+
+    /// Takes the values driven by the simulation, the time, the sound amplitude and the modulation
+    /// of the bands, from another set of parameters, keeping the others.
+    pub fn driven_by(mut self, driver: &CloudData) -> Self {
+        self.sound_amplitude = driver.sound_amplitude;
+        self.time = driver.time;
+        self.band_modulation = driver.band_modulation;
+        self
+    }
+
     /// Returns the struct as a byte slice.
     pub fn as_bytes(&self) -> &[u8] {
         unsafe { wgpu::bytes::from(self) }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn point_is_32_bytes() {
+        assert_eq!(std::mem::size_of::<Point>(), 32);
+    }
+
+    #[test]
+    fn normals_are_packed_along_their_line() {
+        let normals = [
+            [0.0, 0.0, 1.0],
+            [0.0, 0.0, -1.0],
+            [1.0, 0.0, 0.0],
+            [-1.0, 0.0, 0.0],
+            [0.0, -1.0, 0.0],
+            [0.48, -0.6, 0.64],
+            [-0.36, 0.48, -0.8],
+            [
+                -std::f32::consts::FRAC_1_SQRT_2,
+                -std::f32::consts::FRAC_1_SQRT_2,
+                0.0,
+            ],
+        ];
+        normals.into_iter().for_each(|normal| {
+            let mut point = Point::default();
+            point.set_normal(Some(normal));
+            assert!(point.has_normal());
+            let unpacked = Vec3::from(point.normal().unwrap());
+            assert!(
+                unpacked.dot(Vec3::from(normal)).abs() > 0.9999,
+                "{normal:?} unpacked as {unpacked:?}"
+            );
+        });
+    }
+
+    #[test]
+    fn points_have_no_normal_by_default() {
+        let mut point = Point::new([1.0, 2.0, 3.0], [255; 4]);
+        assert!(!point.has_normal());
+        assert_eq!(point.normal(), None);
+        point.set_normal(Some([0.0, 1.0, 0.0]));
+        point.set_normal(None);
+        assert_eq!(point.normal(), None);
+    }
+}
@@ -0,0 +1,86 @@
+use nannou::{geom::Point3, wgpu};
+
+/// The settings and state of the shockwave triggered by the beats of the audio.
+///
+/// On a beat, a spherical wave front leaves the center and pushes the points outward as it passes
+/// them, fading as it travels.
+///
+/// The struct is sent as-is to the compute shader, so its layout must match the `Pulse` struct
+/// declared in `compute.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq)]
+pub struct PulseData {
+    center: [f32; 3],
+    radius: f32,
+    amplitude: f32,
+    /// The thickness of the wave front, in units of the point cloud.
+    pub width: f32,
+    /// How far the wave front pushes the points each frame when it starts.
+    pub strength: f32,
+    /// How fast the wave front travels outward, in units of the point cloud per second.
+    pub speed: f32,
+    /// How quickly the wave fades, as the exponential decay rate of its amplitude per second.
+    pub decay: f32,
+    age: f32,
+    _padding: [u32; 2],
+}
+
+impl Default for PulseData {
+    fn default() -> Self {
+        Self::new(0.05, 20.0, 2.0, 2.0)
+    }
+}
+
+impl PulseData {
+    /// The amplitude below which a wave has faded away.
+    const MIN_AMPLITUDE: f32 = 1e-4;
+
+    /// Creates a shockwave with no wave in flight.
+    pub fn new(strength: f32, speed: f32, decay: f32, width: f32) -> Self {
+        Self {
+            center: [0.0; 3],
+            radius: 0.0,
+            amplitude: 0.0,
+            width,
+            strength,
+            speed,
+            decay,
+            age: 0.0,
+            _padding: [0; 2],
+        }
+    }
+
+    /// Whether a wave is in flight.
+    pub fn is_active(&self) -> bool {
+        self.amplitude > 0.0
+    }
+
+    /// Sends a new wave from the given center, replacing the wave in flight.
+    pub fn trigger(&mut self, center: Point3) {
+        self.center = center.to_array();
+        self.age = 0.0;
+        self.update_front();
+    }
+
+    /// Advances the wave in flight by the given number of seconds.
+    pub fn tick(&mut self, delta: f32) {
+        if self.is_active() {
+            self.age += delta;
+            self.update_front();
+        }
+    }
+
+    /// Moves the wave front to its position at the age of the wave.
+    fn update_front(&mut self) {
+        self.radius = self.speed * self.age;
+        self.amplitude = self.strength * (-self.decay * self.age).exp();
+        if self.amplitude < Self::MIN_AMPLITUDE {
+            self.amplitude = 0.0;
+        }
+    }
+
+    /// Returns the struct as a byte slice.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { wgpu::bytes::from(self) }
+    }
+}
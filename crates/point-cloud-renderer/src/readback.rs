@@ -0,0 +1,131 @@
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+use nannou::wgpu;
+
+use crate::point::Point;
+
+/// A buffer of the ring of a [`PointReadback`], which the GPU copies a cloud into.
+struct ReadbackSlot {
+    buffer: wgpu::Buffer,
+    /// The tag of the copy in flight and the result of its mapping once the GPU is done, if the
+    /// buffer is in use.
+    pending: Option<(u32, Receiver<Result<(), wgpu::BufferAsyncError>>)>,
+}
+
+/// Reads the points of a cloud back from the GPU without waiting on it, such as every frame of an
+/// export.
+///
+/// Each copy goes to a staging buffer of a small ring and is mapped once the GPU has run it, which
+/// [`Self::poll`] checks on the following frames. A copy asked for while every buffer is still in
+/// flight is dropped rather than stalling the frame.
+#[derive(Default)]
+pub struct PointReadback {
+    slots: Vec<ReadbackSlot>,
+}
+
+impl PointReadback {
+    /// The number of copies that can be in flight at once.
+    pub const RING_SIZE: usize = 3;
+
+    /// Copies the first `size` bytes of the points of `source` to a free staging buffer, under a
+    /// `tag` returned along with the points, such as the number of the frame.
+    ///
+    /// Returns whether the copy was started, which it is not if every staging buffer is in use.
+    pub fn request(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        source: &wgpu::Buffer,
+        size: wgpu::BufferAddress,
+        tag: u32,
+    ) -> bool {
+        let free = self.slots.iter().position(|slot| slot.pending.is_none());
+        let index = match free {
+            Some(index) => index,
+            None if self.slots.len() < Self::RING_SIZE => {
+                self.slots.push(ReadbackSlot {
+                    buffer: create_staging_buffer(device, size),
+                    pending: None,
+                });
+                self.slots.len() - 1
+            }
+            None => return false,
+        };
+
+        // The buffer is replaced when the cloud changed size since it was created
+        let slot = &mut self.slots[index];
+        if slot.buffer.size() != size {
+            slot.buffer = create_staging_buffer(device, size);
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Read Back Encoder"),
+        });
+        encoder.copy_buffer_to_buffer(source, 0, &slot.buffer, 0, size);
+        queue.submit(Some(encoder.finish()));
+
+        let (sender, receiver) = mpsc::channel();
+        slot.buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                sender.send(result).ok();
+            });
+        slot.pending = Some((tag, receiver));
+        true
+    }
+
+    /// Returns the points of the copies the GPU is done with, along with their tags in the order
+    /// they were asked for, without waiting on the others.
+    pub fn poll(&mut self, device: &wgpu::Device) -> Vec<(u32, Result<Vec<Point>, &'static str>)> {
+        self.collect(device, wgpu::Maintain::Poll)
+    }
+
+    /// Waits for the GPU to be done with every copy in flight and returns their points, such as
+    /// when an export stops so its last frames are not lost.
+    pub fn finish(
+        &mut self,
+        device: &wgpu::Device,
+    ) -> Vec<(u32, Result<Vec<Point>, &'static str>)> {
+        self.collect(device, wgpu::Maintain::Wait)
+    }
+
+    fn collect(
+        &mut self,
+        device: &wgpu::Device,
+        maintain: wgpu::Maintain,
+    ) -> Vec<(u32, Result<Vec<Point>, &'static str>)> {
+        device.poll(maintain);
+        let mut done: Vec<_> = self
+            .slots
+            .iter_mut()
+            .filter_map(|slot| {
+                let (tag, receiver) = slot.pending.as_ref()?;
+                let points = match receiver.try_recv() {
+                    Err(TryRecvError::Empty) => return None,
+                    Ok(Ok(())) => {
+                        let points = Point::from_bytes(&slot.buffer.slice(..).get_mapped_range());
+                        slot.buffer.unmap();
+                        Ok(points)
+                    }
+                    Ok(Err(_)) | Err(TryRecvError::Disconnected) => {
+                        Err("Failed to map the points read back from the GPU")
+                    }
+                };
+                let tag = *tag;
+                slot.pending = None;
+                Some((tag, points))
+            })
+            .collect();
+        done.sort_by_key(|(tag, _)| *tag);
+        done
+    }
+}
+
+fn create_staging_buffer(device: &wgpu::Device, size: wgpu::BufferAddress) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Vertex Staging Buffer"),
+        size,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
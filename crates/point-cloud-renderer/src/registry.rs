@@ -0,0 +1,167 @@
+use std::{path::Path, sync::Arc};
+
+#[cfg(feature = "files")]
+use crate::loader::{E57Loader, LasLoader};
+use crate::{
+    loader::{LoadHandle, LoadProgress, PlyLoader, RandomCube, RandomSphere},
+    point::Point,
+};
+
+/// A format of point cloud files.
+///
+/// The formats are looked up by the extension of the files in a [`Registry`], so crates using the
+/// renderer can read their own formats by registering a loader.
+pub trait Loader: Send + Sync {
+    /// The name of the format, as listed to the user.
+    fn name(&self) -> &str;
+
+    /// The extensions of the files of the format, in lowercase and without the dot.
+    fn extensions(&self) -> &[&str];
+
+    /// Reads the points of a file, adding the points read to `progress` as it goes.
+    fn load(
+        &self,
+        path: &str,
+        options: &LoadOptions,
+        progress: &LoadProgress,
+    ) -> Result<Vec<Point>, &'static str>;
+
+    /// Reads the points of a file already in memory, such as one fetched over the network.
+    ///
+    /// The formats that can only be read from the disk keep the default, which fails.
+    fn load_bytes(
+        &self,
+        _bytes: &[u8],
+        _options: &LoadOptions,
+        _progress: &LoadProgress,
+    ) -> Result<Vec<Point>, &'static str> {
+        Err("The format can only be read from a file")
+    }
+}
+
+/// A procedural source of points, registered by name in a [`Registry`].
+pub trait Generator: Send + Sync {
+    /// The name of the generator, as listed to the user.
+    fn name(&self) -> &str;
+
+    /// Generates a point cloud of `count` points, the same for the same seed so the clouds can be
+    /// reproduced.
+    fn generate(&self, count: usize, seed: u64) -> Vec<Point>;
+}
+
+/// The options given to every loader, which each applies as far as it makes sense for its format.
+#[derive(Clone, Debug, Default)]
+pub struct LoadOptions {
+    /// The classification codes of the points to keep, or every point if empty.
+    pub classes: Vec<u8>,
+}
+
+/// The loaders and generators available to read or create point clouds.
+///
+/// The default registry holds the formats and generators of the renderer; more can be registered
+/// on top of them, and a loader registered later takes over the extensions it shares with earlier
+/// ones.
+#[derive(Clone)]
+pub struct Registry {
+    loaders: Vec<Arc<dyn Loader>>,
+    generators: Vec<Arc<dyn Generator>>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        #[cfg(feature = "files")]
+        registry.register_loader(E57Loader);
+        registry.register_loader(PlyLoader);
+        #[cfg(feature = "files")]
+        registry.register_loader(LasLoader);
+        registry.register_generator(RandomCube);
+        registry.register_generator(RandomSphere);
+        registry
+    }
+}
+
+impl Registry {
+    /// Creates a registry without any loader or generator.
+    pub fn empty() -> Self {
+        Self {
+            loaders: Vec::new(),
+            generators: Vec::new(),
+        }
+    }
+
+    /// Adds a loader for the files with its extensions.
+    pub fn register_loader(&mut self, loader: impl Loader + 'static) {
+        self.loaders.push(Arc::new(loader));
+    }
+
+    /// Adds a generator, replacing the one with the same name if any.
+    pub fn register_generator(&mut self, generator: impl Generator + 'static) {
+        self.generators
+            .retain(|registered| registered.name() != generator.name());
+        self.generators.push(Arc::new(generator));
+    }
+
+    /// The loaders, in the order they were registered.
+    pub fn loaders(&self) -> impl Iterator<Item = &dyn Loader> {
+        self.loaders.iter().map(|loader| loader.as_ref())
+    }
+
+    /// The generators, in the order they were registered.
+    pub fn generators(&self) -> impl Iterator<Item = &dyn Generator> {
+        self.generators.iter().map(|generator| generator.as_ref())
+    }
+
+    /// The extensions of every format that can be read, without duplicates.
+    pub fn extensions(&self) -> Vec<&str> {
+        let mut extensions: Vec<&str> = Vec::new();
+        self.loaders()
+            .flat_map(|loader| loader.extensions().iter().copied())
+            .for_each(|extension| {
+                if !extensions.contains(&extension) {
+                    extensions.push(extension);
+                }
+            });
+        extensions
+    }
+
+    /// Returns the loader reading the file at `path`, picked from the extension of the file.
+    pub fn loader_for(&self, path: &str) -> Option<&dyn Loader> {
+        self.find_loader(path).map(|loader| loader.as_ref())
+    }
+
+    /// Returns the generator with the given name.
+    pub fn generator(&self, name: &str) -> Option<&dyn Generator> {
+        self.generators().find(|generator| generator.name() == name)
+    }
+
+    /// Reads a point cloud from a file with the loader of its extension.
+    pub fn load(&self, path: &str, options: &LoadOptions) -> Result<Vec<Point>, &'static str> {
+        self.loader_for(path)
+            .ok_or("Unsupported point cloud format")?
+            .load(path, options, &LoadProgress::default())
+    }
+
+    /// Starts reading a point cloud from a file on a background thread with the loader of its
+    /// extension.
+    ///
+    /// See [`Registry::load`].
+    pub fn load_async(&self, path: &str, options: LoadOptions) -> LoadHandle {
+        let loader = self.find_loader(path).cloned();
+        LoadHandle::spawn(path, move |path, progress| match loader {
+            Some(loader) => loader.load(path, &options, progress),
+            None => Err("Unsupported point cloud format"),
+        })
+    }
+
+    pub(crate) fn find_loader(&self, path: &str) -> Option<&Arc<dyn Loader>> {
+        let extension = Path::new(path)
+            .extension()?
+            .to_string_lossy()
+            .to_lowercase();
+        self.loaders
+            .iter()
+            .rev()
+            .find(|loader| loader.extensions().contains(&extension.as_str()))
+    }
+}
@@ -0,0 +1,80 @@
+/// Adjusts the render scale of the pipeline to hold a target frame rate.
+///
+/// The frame time is smoothed over a few frames, and the scale is stepped down when the frames
+/// take too long and back up when there is headroom. Changes are spaced out in time since each one
+/// recreates the offscreen targets.
+pub struct ResolutionScaler {
+    /// Whether the scale adapts to the frame rate; the scale stays at 1 otherwise.
+    pub enabled: bool,
+    /// The frame rate to hold, in frames per second.
+    pub target_fps: f32,
+    /// The lowest scale the resolution can drop to, in the range (0, 1].
+    pub min_scale: f32,
+    scale: f32,
+    frame_time: f32,
+    cooldown: f32,
+}
+
+impl ResolutionScaler {
+    /// The amount by which the scale changes at once.
+    const STEP: f32 = 0.05;
+    /// The number of seconds between two changes of the scale.
+    const COOLDOWN: f32 = 0.5;
+    /// How much of the new frame time goes into the smoothed frame time.
+    const SMOOTHING: f32 = 0.1;
+
+    /// Creates a scaler holding the given frame rate.
+    pub fn new(target_fps: f32, min_scale: f32) -> Self {
+        Self {
+            enabled: false,
+            target_fps,
+            min_scale,
+            scale: 1.0,
+            frame_time: 1.0 / target_fps,
+            cooldown: 0.0,
+        }
+    }
+
+    /// The current render scale, in the range [min_scale, 1].
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Feeds the duration of the last frame, in seconds, and returns the render scale to use.
+    pub fn update(&mut self, frame_time: f32) -> f32 {
+        if !self.enabled {
+            self.scale = 1.0;
+            return self.scale;
+        }
+
+        self.frame_time += (frame_time - self.frame_time) * Self::SMOOTHING;
+        self.cooldown -= frame_time;
+        if self.cooldown > 0.0 {
+            return self.scale;
+        }
+
+        // Leave some slack around the target so the scale does not oscillate
+        let target = 1.0 / self.target_fps.max(1.0);
+        let min_scale = self.min_scale.clamp(Self::STEP, 1.0);
+        let scale = if self.frame_time > target * 1.1 {
+            self.scale - Self::STEP
+        } else if self.frame_time < target * 0.8 {
+            self.scale + Self::STEP
+        } else {
+            self.scale
+        }
+        .clamp(min_scale, 1.0);
+
+        if scale != self.scale {
+            self.scale = scale;
+            self.cooldown = Self::COOLDOWN;
+        }
+        self.scale
+    }
+}
+
+impl Default for ResolutionScaler {
+    fn default() -> Self {
+        Self::new(60.0, 0.5)
+    }
+}
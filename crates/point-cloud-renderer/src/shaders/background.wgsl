@@ -0,0 +1,26 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>, // <x, y, z, w>
+    @location(0) uv: vec2<f32>, // <u, v>
+};
+
+@group(0) @binding(0)
+var background_texture: texture_2d<f32>;
+
+@group(0) @binding(1)
+var background_sampler: sampler;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // A single triangle covering the whole screen, at the far plane
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var output: VertexOutput;
+    output.position = vec4<f32>(uv * 2.0 - 1.0, 1.0, 1.0);
+    // The rows of the image go down the screen
+    output.uv = vec2<f32>(uv.x, 1.0 - uv.y);
+    return output;
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(background_texture, background_sampler, vertex.uv);
+}
@@ -1,5 +1,6 @@
 struct VertexInput {
     @location(0) position: vec3<f32>, // <x, y, z>
+    @location(2) normal: u32, // Packed in the padding of the position, 0 if not known
     @location(1) color: vec4<f32>, // <r, g, b, a>
 };
 
@@ -8,6 +9,112 @@ struct Data {
     wind_strength: f32,
     noise_scale: f32,
     spring_constant: f32,
+    wind_direction: vec3<f32>,
+    time: f32,
+    gust_strength: f32,
+    gust_frequency: f32,
+    gust_spread: f32,
+    point_size: f32,
+    band_modulation: vec3<f32>,
+};
+
+const MAX_ATTRACTORS: u32 = 16u;
+
+struct Attractor {
+    position: vec3<f32>,
+    strength: f32,
+};
+
+struct Attractors {
+    attractors: array<Attractor, MAX_ATTRACTORS>,
+    count: u32,
+    radius: f32,
+};
+
+const MASK_HEIGHT: u32 = 1u;
+const MASK_COLOR: u32 = 2u;
+const MASK_INVERT: u32 = 4u;
+
+struct Mask {
+    color: vec3<f32>,
+    color_tolerance: f32,
+    min_height: f32,
+    max_height: f32,
+    feather: f32,
+    flags: u32,
+};
+
+struct Masks {
+    wind: Mask,
+    attractors: Mask,
+};
+
+const SPECTRUM_BANDS: u32 = 16u;
+const SPECTRUM_AXIS_X: u32 = 1u;
+const SPECTRUM_RADIAL: u32 = 2u;
+
+struct Spectrum {
+    bands: array<vec4<f32>, 4>, // SPECTRUM_BANDS packed by 4 for the uniform layout
+    center: vec3<f32>,
+    band_layout: u32,
+    axis_min: f32,
+    axis_max: f32,
+    radius: f32,
+    strength: f32,
+};
+
+struct Pulse {
+    center: vec3<f32>,
+    radius: f32,
+    amplitude: f32,
+    width: f32,
+    strength: f32,
+    speed: f32,
+    decay: f32,
+    age: f32,
+};
+
+const MAX_WAVEFRONTS: u32 = 8u;
+
+struct Wavefront {
+    origin: vec3<f32>,
+    age: f32,
+    amplitude: f32,
+};
+
+struct Impulses {
+    wavefronts: array<Wavefront, MAX_WAVEFRONTS>,
+    speed: f32,
+    damping: f32,
+    wavelength: f32,
+    strength: f32,
+};
+
+struct CurlNoise {
+    strength: f32,
+    scale: f32,
+    speed: f32,
+};
+
+struct Gravity {
+    center: vec3<f32>,
+    floor: f32,
+    collapse: f32,
+    reassemble: f32,
+    overshoot: f32,
+    toward_center: u32,
+    height: f32,
+    radius: f32,
+};
+
+struct Trail {
+    slots: u32,
+    head: u32,
+    filled: u32,
+    record: u32,
+    fade: f32,
+    interval: u32,
+    frame: u32,
 };
 
 @group(0) @binding(0)
@@ -19,8 +126,38 @@ var<storage, read> vertices_initial: array<VertexInput>;
 @group(0) @binding(2)
 var<uniform> data: Data;
 
+@group(0) @binding(3)
+var<uniform> attractors: Attractors;
+
+@group(0) @binding(4)
+var<uniform> masks: Masks;
+
+@group(0) @binding(5)
+var<uniform> spectrum: Spectrum;
+
+@group(0) @binding(6)
+var<storage, read_write> trail_positions: array<vec4<f32>>;
+
+@group(0) @binding(7)
+var<uniform> trail: Trail;
+
+@group(0) @binding(8)
+var<uniform> pulse: Pulse;
+
+@group(0) @binding(9)
+var<uniform> curl_noise: CurlNoise;
+
+@group(0) @binding(10)
+var<uniform> gravity: Gravity;
+
+@group(0) @binding(11)
+var<uniform> impulses: Impulses;
+
+// Each effect is a pass of its own, moving the points from where the previous passes left them,
+// in the order of the effect stack; `cs_settle` runs last to pull them back to rest
+
 @compute @workgroup_size(256)
-fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+fn cs_wind(@builtin(global_invocation_id) id: vec3<u32>) {
     let index = id.x;
 
     // Prevent out-of-bounds access
@@ -28,22 +165,284 @@ fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
         return;
     }
 
-    var current_position = vertices[index].position;
+    let current_position = vertices[index].position;
 
-    // Simulate wind-like vector field using noise
-    let wind = perlin_noise_3d(current_position * data.noise_scale) * data.wind_strength * data.sound_amplitude;
+    // Gusts are low-frequency noise over time modulating the strength and direction of the wind
+    let gust_time = data.time * data.gust_frequency;
+    let gust = max(1.0 + data.gust_strength * perlin_noise_3d(vec3<f32>(gust_time, 0.5, 0.5)), 0.0);
+    let gust_deviation = vec3<f32>(
+        perlin_noise_3d(vec3<f32>(gust_time, 17.5, 0.5)),
+        perlin_noise_3d(vec3<f32>(gust_time, 0.5, 31.5)),
+        perlin_noise_3d(vec3<f32>(gust_time, 53.5, 7.5)),
+    ) * data.gust_spread * length(data.wind_direction);
+    let direction = data.wind_direction + gust_deviation;
+
+    // Simulate wind-like vector field using noise carried along by the wind
+    // The audio bands raise the wind strength and the noise scale
+    let wind_strength = data.wind_strength * (1.0 + data.band_modulation.x);
+    let noise_scale = data.noise_scale * (1.0 + data.band_modulation.y);
+
+    let turbulence = perlin_noise_3d((current_position - direction * gust_time) * noise_scale);
+    let wind = direction * turbulence * gust * wind_strength * data.sound_amplitude * mask_weight(masks.wind, index);
 
     // Apply wind force to the point's position
-    current_position += wind;
+    vertices[index].position = current_position + wind;
+}
+
+@compute @workgroup_size(256)
+fn cs_curl_noise(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+
+    // Prevent out-of-bounds access
+    if index >= arrayLength(&vertices) {
+        return;
+    }
+
+    // The curl of a noise field has no divergence, so the points swirl without bunching up
+    let current_position = vertices[index].position;
+    let sample = current_position * curl_noise.scale + vec3<f32>(data.time * curl_noise.speed);
+    let velocity = curl(sample) * curl_noise.strength * data.sound_amplitude * mask_weight(masks.wind, index);
+    vertices[index].position = current_position + velocity;
+}
+
+@compute @workgroup_size(256)
+fn cs_attractors(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+
+    // Prevent out-of-bounds access
+    if index >= arrayLength(&vertices) {
+        return;
+    }
+
+    var current_position = vertices[index].position;
+
+    // Pull the point towards each attractor
+    // The pull is halved at a distance of `radius` and never overshoots the attractor
+    let attractors_weight = mask_weight(masks.attractors, index);
+    for (var i = 0u; i < min(attractors.count, MAX_ATTRACTORS); i++) {
+        let attractor = attractors.attractors[i];
+        let offset = attractor.position - current_position;
+        let distance = length(offset);
+        if distance > 0.0 {
+            let falloff = 1.0 / (1.0 + distance * distance / (attractors.radius * attractors.radius));
+            let pull = min(attractor.strength * falloff * attractors_weight, distance);
+            current_position += offset / distance * pull;
+        }
+    }
+
+    vertices[index].position = current_position;
+}
+
+@compute @workgroup_size(256)
+fn cs_spectrum(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+
+    // Prevent out-of-bounds access
+    if index >= arrayLength(&vertices) {
+        return;
+    }
+
+    // Displace the point by the energy of the band of the spectrum its region maps to
+    vertices[index].position += spectral_displacement(vertices_initial[index].position);
+}
+
+@compute @workgroup_size(256)
+fn cs_pulse(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+
+    // Prevent out-of-bounds access
+    if index >= arrayLength(&vertices) || pulse.amplitude <= 0.0 {
+        return;
+    }
+
+    // Push the point outward as the wave front of the pulse passes it
+    let current_position = vertices[index].position;
+    let offset = current_position - pulse.center;
+    let distance = length(offset);
+    if distance > 0.0 {
+        let front = (distance - pulse.radius) / max(pulse.width, 0.0001);
+        vertices[index].position = current_position + offset / distance * pulse.amplitude * exp(-front * front);
+    }
+}
+
+@compute @workgroup_size(256)
+fn cs_impulses(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+
+    // Prevent out-of-bounds access
+    if index >= arrayLength(&vertices) {
+        return;
+    }
+
+    // The wavefronts travel through the points at rest, so their phase does not drift with the
+    // other effects; each is a compression followed by a rarefaction, pushing the points ahead of
+    // it outward and pulling those behind it back
+    let rest = vertices_initial[index].position;
+    let wavelength = max(impulses.wavelength, 0.0001);
+    var displacement = vec3<f32>(0.0);
+    for (var i = 0u; i < MAX_WAVEFRONTS; i++) {
+        let wavefront = impulses.wavefronts[i];
+        let offset = rest - wavefront.origin;
+        let distance = length(offset);
+        if wavefront.amplitude <= 0.0 || distance <= 0.0 {
+            continue;
+        }
+        let phase = (distance - impulses.speed * wavefront.age) / wavelength;
+        let profile = phase * exp((1.0 - phase * phase) * 0.5);
+        displacement += offset / distance * wavefront.amplitude * profile;
+    }
+    vertices[index].position += displacement;
+}
+
+@compute @workgroup_size(256)
+fn cs_collapse(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+
+    // Prevent out-of-bounds access
+    if index >= arrayLength(&vertices) || gravity.collapse <= 0.0 {
+        return;
+    }
+
+    // Every point falls from rest with the same acceleration, the longest fall taking the whole
+    // collapse, so the points closest to the floor land first
+    let fallen = min(gravity.collapse, 1.0) * min(gravity.collapse, 1.0);
+    let rest = vertices_initial[index].position;
+    if gravity.toward_center != 0u {
+        let offset = rest - gravity.center;
+        let distance = length(offset);
+        let remaining = max(distance - fallen * gravity.radius, 0.0);
+        let direction = select(vec3<f32>(0.0), offset / distance, distance > 0.0);
+        vertices[index].position = gravity.center + direction * remaining;
+    } else {
+        let height = max(rest.y - fallen * gravity.height, gravity.floor);
+        vertices[index].position = vec3<f32>(rest.x, height, rest.z);
+    }
+}
+
+@compute @workgroup_size(256)
+fn cs_reassemble(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+
+    // Prevent out-of-bounds access
+    if index >= arrayLength(&vertices) || gravity.reassemble <= 0.0 {
+        return;
+    }
+
+    // Ease the point from where the previous passes left it to its rest, springing past it first
+    let t = min(gravity.reassemble, 1.0) - 1.0;
+    let overshoot = gravity.overshoot;
+    let eased = 1.0 + (overshoot + 1.0) * t * t * t + overshoot * t * t;
+    vertices[index].position = mix(vertices[index].position, vertices_initial[index].position, eased);
+}
+
+@compute @workgroup_size(256)
+fn cs_settle(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+
+    // Prevent out-of-bounds access
+    if index >= arrayLength(&vertices) {
+        return;
+    }
+
+    var current_position = vertices[index].position;
 
     // Calculate the distance from the original position
     let displacement = current_position - vertices_initial[index].position;
 
     // Apply the spring-like restorative force
-    current_position -= data.spring_constant * displacement;
+    // The audio bands raise the spring constant
+    let spring_constant = min(data.spring_constant * (1.0 + data.band_modulation.z), 1.0);
+    current_position -= spring_constant * displacement;
 
     // Update the vertex position
     vertices[index].position = current_position;
+
+    // Record the position in the ring buffer of the trail, one slot per frame for all the points
+    if trail.record != 0u && trail.slots > 1u {
+        trail_positions[trail.head * arrayLength(&vertices) + index] = vec4<f32>(current_position, 1.0);
+    }
+}
+
+// Computes the curl of a vector field of three offset noises, by central differences
+fn curl(position: vec3<f32>) -> vec3<f32> {
+    let e = 0.1;
+    let dx = vec3<f32>(e, 0.0, 0.0);
+    let dy = vec3<f32>(0.0, e, 0.0);
+    let dz = vec3<f32>(0.0, 0.0, e);
+    let dfz_dy = noise_field(position + dy).z - noise_field(position - dy).z;
+    let dfy_dz = noise_field(position + dz).y - noise_field(position - dz).y;
+    let dfx_dz = noise_field(position + dz).x - noise_field(position - dz).x;
+    let dfz_dx = noise_field(position + dx).z - noise_field(position - dx).z;
+    let dfy_dx = noise_field(position + dx).y - noise_field(position - dx).y;
+    let dfx_dy = noise_field(position + dy).x - noise_field(position - dy).x;
+    return vec3<f32>(dfz_dy - dfy_dz, dfx_dz - dfz_dx, dfy_dx - dfx_dy) / (2.0 * e);
+}
+
+// Samples a vector field made of three noises offset from each other
+fn noise_field(position: vec3<f32>) -> vec3<f32> {
+    return vec3<f32>(
+        perlin_noise_3d(position),
+        perlin_noise_3d(position + vec3<f32>(31.4, 47.2, 12.9)),
+        perlin_noise_3d(position + vec3<f32>(-27.1, 9.8, 63.5)),
+    );
+}
+
+// Reads the energy of a band of the spectrum
+fn spectrum_band(band: u32) -> f32 {
+    let i = min(band, SPECTRUM_BANDS - 1u);
+    return spectrum.bands[i / 4u][i % 4u];
+}
+
+// Computes the displacement of a point caused by the band of the spectrum its region maps to
+fn spectral_displacement(position: vec3<f32>) -> vec3<f32> {
+    var t: f32;
+    var direction: vec3<f32>;
+    if spectrum.band_layout == SPECTRUM_AXIS_X {
+        // Slices along the x axis lift the points
+        t = (position.x - spectrum.axis_min) / max(spectrum.axis_max - spectrum.axis_min, 0.0001);
+        direction = vec3<f32>(0.0, 1.0, 0.0);
+    } else if spectrum.band_layout == SPECTRUM_RADIAL {
+        // Shells around the center push the points outwards
+        let offset = position - spectrum.center;
+        let distance = length(offset);
+        t = distance / spectrum.radius;
+        direction = select(vec3<f32>(0.0), offset / distance, distance > 0.0);
+    } else {
+        return vec3<f32>(0.0);
+    }
+
+    // Interpolate between neighbouring bands so the regions blend smoothly
+    let band = clamp(t, 0.0, 1.0) * f32(SPECTRUM_BANDS - 1u);
+    let low = u32(floor(band));
+    let energy = mix(spectrum_band(low), spectrum_band(low + 1u), fract(band));
+
+    return direction * energy * spectrum.strength;
+}
+
+// Computes how much an effect applies to a point given the mask of the effect
+fn mask_weight(mask: Mask, index: u32) -> f32 {
+    var weight = 1.0;
+
+    // Only select points within the height range, using the initial position so the mask stays still
+    if (mask.flags & MASK_HEIGHT) != 0u {
+        let height = vertices_initial[index].position.y;
+        let feather = max(mask.feather, 0.0001);
+        weight *= smoothstep(mask.min_height - feather, mask.min_height + feather, height);
+        weight *= 1.0 - smoothstep(mask.max_height - feather, mask.max_height + feather, height);
+    }
+
+    // Only select points close to the color of the mask
+    if (mask.flags & MASK_COLOR) != 0u {
+        let distance = length(vertices_initial[index].color.rgb - mask.color);
+        weight *= select(0.0, 1.0, distance <= mask.color_tolerance);
+    }
+
+    // Select the points outside of the ranges instead
+    if (mask.flags & MASK_INVERT) != 0u && (mask.flags & (MASK_HEIGHT | MASK_COLOR)) != 0u {
+        weight = 1.0 - weight;
+    }
+
+    return weight;
 }
 
 fn permute4(x: vec4<f32>) -> vec4<f32> { return ((x * 34. + 1.) * x) % vec4<f32>(289.); }
@@ -0,0 +1,81 @@
+// The automatic exposure, measuring the average luminance of the lit pixels of the frame on a
+// coarse grid and easing the exposure towards the one bringing it to the target
+
+struct Settings {
+    stops: f32,
+    target_luminance: f32,
+    speed: f32,
+    delta_time: f32,
+    range: vec2<f32>,
+};
+
+struct Exposure {
+    stops: f32,
+    adapted: f32,
+};
+
+// The pixels darker than this are the background, left out of the average
+const MIN_LUMINANCE: f32 = 0.002;
+// The number of columns and rows of pixels sampled
+const GRID: u32 = 64u;
+// Must match `@workgroup_size`
+const WORKGROUP_SIZE: u32 = 256u;
+
+@group(0) @binding(0)
+var frame_texture: texture_2d<f32>;
+
+@group(0) @binding(1)
+var<uniform> settings: Settings;
+
+@group(0) @binding(2)
+var<storage, read_write> exposure: Exposure;
+
+var<workgroup> log_sums: array<f32, WORKGROUP_SIZE>;
+var<workgroup> counts: array<f32, WORKGROUP_SIZE>;
+
+@compute @workgroup_size(256)
+fn cs_exposure(@builtin(local_invocation_index) index: u32) {
+    // Sum the logarithms of the luminance of the lit pixels sampled by this invocation
+    let size = textureDimensions(frame_texture);
+    var log_sum = 0.0;
+    var count = 0.0;
+    for (var i = index; i < GRID * GRID; i += WORKGROUP_SIZE) {
+        let cell = vec2<u32>(i % GRID, i / GRID);
+        let pixel = (cell * 2u + 1u) * size / (2u * GRID);
+        let color = textureLoad(frame_texture, pixel, 0).rgb;
+        let luminance = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+        if luminance > MIN_LUMINANCE {
+            log_sum += log2(luminance);
+            count += 1.0;
+        }
+    }
+    log_sums[index] = log_sum;
+    counts[index] = count;
+    workgroupBarrier();
+
+    // Reduce the sums of the workgroup
+    for (var stride = WORKGROUP_SIZE / 2u; stride > 0u; stride /= 2u) {
+        if index < stride {
+            log_sums[index] += log_sums[index + stride];
+            counts[index] += counts[index + stride];
+        }
+        workgroupBarrier();
+    }
+    if index != 0u {
+        return;
+    }
+
+    // An empty frame keeps the exposure it had
+    if counts[0] > 0.0 {
+        // The logarithm of the geometric mean, which a few bright points do not dominate
+        let average = log_sums[0] / counts[0];
+        let wanted = clamp(
+            log2(settings.target_luminance) - average,
+            settings.range.x,
+            settings.range.y,
+        );
+        let blend = 1.0 - exp(-settings.speed * settings.delta_time);
+        exposure.adapted = mix(exposure.adapted, wanted, blend);
+    }
+    exposure.stops = exposure.adapted + settings.stops;
+}
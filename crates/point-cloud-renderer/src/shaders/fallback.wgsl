@@ -0,0 +1,135 @@
+// Draws the points at rest straight from the vertex buffer, for the devices without compute shaders
+// or storage buffers. It mirrors `render.wgsl` without the deformation, the trails or the splats.
+
+struct VertexInput {
+    @location(0) position: vec3<f32>, // <x, y, z>
+    @location(1) color: vec4<f32>, // <r, g, b, a>
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>, // <x, y, z, w>
+    @location(0) color: vec4<f32>, // <r, g, b, a>
+}
+
+struct CameraTransforms {
+    world: mat4x4<f32>,
+    view: mat4x4<f32>,
+    proj: mat4x4<f32>,
+};
+
+struct Fog {
+    inverse_view_proj: mat4x4<f32>,
+    view_proj: mat4x4<f32>,
+    color: vec3<f32>,
+    height: f32,
+    density: f32,
+    reflection_strength: f32,
+};
+
+// The number of stops of the color ramp; must match `RAMP_STOPS` in `coloring.rs`
+const RAMP_STOPS: u32 = 8u;
+
+struct Coloring {
+    stops: array<vec4<f32>, RAMP_STOPS>,
+    mode: u32, // 0: RGB, 1: elevation, 2: intensity, 3: distance
+    low: f32,
+    high: f32,
+};
+
+struct Clip {
+    min: vec3<f32>,
+    enabled: u32,
+    max: vec3<f32>,
+};
+
+// The transform of the cloud, from its coordinates to those of the scene
+struct Model {
+    matrix: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraTransforms;
+
+@group(0) @binding(1)
+var<uniform> fog: Fog;
+
+@group(0) @binding(2)
+var<uniform> coloring: Coloring;
+
+@group(0) @binding(3)
+var<uniform> clip: Clip;
+
+@group(0) @binding(4)
+var<uniform> model: Model;
+
+// A position outside of the clip volume
+const HIDDEN: vec4<f32> = vec4<f32>(0.0, 0.0, 2.0, 1.0);
+
+// Maps a value in [0, 1] to the color ramp, blending between its stops
+fn ramp(t: f32) -> vec3<f32> {
+    let x = clamp(t, 0.0, 1.0) * f32(RAMP_STOPS - 1u);
+    let i = min(u32(x), RAMP_STOPS - 2u);
+    return mix(coloring.stops[i].rgb, coloring.stops[i + 1u].rgb, x - f32(i));
+}
+
+// Encodes a linear channel to sRGB
+fn linear_to_srgb(channel: f32) -> f32 {
+    if channel <= 0.0031308 {
+        return channel * 12.92;
+    }
+    return 1.055 * pow(channel, 1.0 / 2.4) - 0.055;
+}
+
+// The color of a point in the color mode, from its position in the scene
+fn point_color(position: vec3<f32>, color: vec4<f32>) -> vec4<f32> {
+    var value: f32;
+    switch coloring.mode {
+        case 1u: {
+            value = position.y;
+        }
+        case 2u: {
+            // The intensities are loaded as sRGB grays, so they are encoded back to be evenly spaced
+            let luminance = dot(color.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+            return vec4<f32>(ramp(linear_to_srgb(luminance)), color.a);
+        }
+        case 3u: {
+            value = length((camera.view * camera.world * vec4<f32>(position, 1.0)).xyz);
+        }
+        default: {
+            return color;
+        }
+    }
+    let t = (value - coloring.low) / max(coloring.high - coloring.low, 1e-6);
+    return vec4<f32>(ramp(t), color.a);
+}
+
+@vertex
+fn vs_main(vertex: VertexInput, @builtin(instance_index) instance: u32) -> VertexOutput {
+    var output: VertexOutput;
+    var position = (model.matrix * vec4<f32>(vertex.position, 1.0)).xyz;
+    output.color = point_color(position, vertex.color);
+
+    // The points outside of the clip box are cropped, along with their reflection
+    if clip.enabled != 0u && (any(position < clip.min) || any(position > clip.max)) {
+        output.position = HIDDEN;
+        return output;
+    }
+
+    // The second instance is the reflection of the points above the fog plane
+    if instance == 1u {
+        if position.y < fog.height {
+            output.position = HIDDEN;
+            return output;
+        }
+        position.y = 2.0 * fog.height - position.y;
+        output.color = vec4<f32>(mix(fog.color, output.color.rgb, fog.reflection_strength), output.color.a);
+    }
+
+    output.position = camera.proj * camera.view * camera.world * vec4<f32>(position, 1.0);
+    return output;
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    return vertex.color;
+}
@@ -0,0 +1,58 @@
+struct Fog {
+    inverse_view_proj: mat4x4<f32>,
+    view_proj: mat4x4<f32>,
+    color: vec3<f32>,
+    height: f32,
+    density: f32,
+    reflection_strength: f32,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>, // <x, y, z, w>
+    @location(0) ndc: vec2<f32>, // <x, y>
+};
+
+struct FragmentOutput {
+    @location(0) color: vec4<f32>, // <r, g, b, a>
+    @builtin(frag_depth) depth: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> fog: Fog;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // A single triangle covering the whole screen
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var output: VertexOutput;
+    output.ndc = uv * 2.0 - 1.0;
+    output.position = vec4<f32>(output.ndc, 0.0, 1.0);
+    return output;
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> FragmentOutput {
+    // Cast a ray from the camera through the pixel
+    let near = fog.inverse_view_proj * vec4<f32>(vertex.ndc, -1.0, 1.0);
+    let far = fog.inverse_view_proj * vec4<f32>(vertex.ndc, 1.0, 1.0);
+    let origin = near.xyz / near.w;
+    let direction = far.xyz / far.w - origin;
+
+    // Intersect the ray with the plane, which is infinite so it extends past the far plane
+    let t = (fog.height - origin.y) / direction.y;
+    if direction.y == 0.0 || t <= 0.0 {
+        discard;
+    }
+    let hit = origin + direction * t;
+
+    // The fog thickens with the distance to the camera, but lets the reflection show through
+    let distance = t * length(direction);
+    let opacity = max(1.0 - fog.reflection_strength, 1.0 - exp(-fog.density * distance));
+
+    // Write the depth of the plane so only the points below it are covered
+    let clip = fog.view_proj * vec4<f32>(hit, 1.0);
+    var output: FragmentOutput;
+    output.color = vec4<f32>(fog.color, clamp(opacity, 0.0, 1.0));
+    output.depth = clamp(clip.z / clip.w, 0.0, 0.9999999);
+    return output;
+}
@@ -0,0 +1,134 @@
+// Draws the Gaussians of a 3D Gaussian Splatting capture as ellipses facing the camera, blended
+// from the farthest to the nearest
+
+struct Gaussian {
+    position: vec3<f32>,
+    opacity: f32,
+    // The xx, xy and xz terms, then the yy, yz and zz terms of the covariance
+    covariance: array<vec4<f32>, 2>,
+    color: vec4<f32>,
+};
+
+struct CameraTransforms {
+    world: mat4x4<f32>,
+    view: mat4x4<f32>,
+    proj: mat4x4<f32>,
+};
+
+struct GaussianView {
+    viewport: vec2<f32>,
+    scale: f32,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    // The position within the ellipse, in standard deviations along its axes
+    @location(1) offset: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var<storage, read> gaussians: array<Gaussian>;
+
+// The indices of the Gaussians, from the farthest to the nearest
+@group(0) @binding(1)
+var<storage, read> order: array<u32>;
+
+@group(0) @binding(2)
+var<uniform> camera: CameraTransforms;
+
+@group(0) @binding(3)
+var<uniform> view: GaussianView;
+
+// A position outside of the clip volume
+const HIDDEN: vec4<f32> = vec4<f32>(0.0, 0.0, 2.0, 1.0);
+
+// The ellipses are cut at three standard deviations
+const EXTENT: f32 = 3.0;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex: u32, @builtin(instance_index) instance: u32) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+
+    var output: VertexOutput;
+    let gaussian = gaussians[order[instance]];
+    let world_view = camera.view * camera.world;
+    let center = world_view * vec4<f32>(gaussian.position, 1.0);
+    let distance = -center.z;
+    let clip_position = camera.proj * center;
+    let ndc = clip_position.xy / clip_position.w;
+    if distance < 0.01 || any(abs(ndc) > vec2<f32>(1.3)) {
+        output.position = HIDDEN;
+        return output;
+    }
+
+    // Project the covariance to the screen, in pixels, through the Jacobian of the projection
+    let a = gaussian.covariance[0];
+    let b = gaussian.covariance[1];
+    let scale = view.scale * view.scale;
+    let sigma = mat3x3<f32>(
+        vec3<f32>(a.x, a.y, a.z),
+        vec3<f32>(a.y, b.x, b.y),
+        vec3<f32>(a.z, b.y, b.z),
+    ) * scale;
+    let focal = vec2<f32>(camera.proj[0][0], camera.proj[1][1]) * view.viewport / 2.0;
+    let jacobian = mat3x3<f32>(
+        vec3<f32>(focal.x / distance, 0.0, 0.0),
+        vec3<f32>(0.0, focal.y / distance, 0.0),
+        vec3<f32>(focal * center.xy / (distance * distance), 0.0),
+    );
+    let rotation = mat3x3<f32>(world_view[0].xyz, world_view[1].xyz, world_view[2].xyz);
+    let t = jacobian * rotation;
+    let projected = t * sigma * transpose(t);
+
+    // A pixel of blur keeps the Gaussians smaller than a pixel from flickering
+    let xx = projected[0][0] + 0.3;
+    let xy = projected[0][1];
+    let yy = projected[1][1] + 0.3;
+
+    // Find the axes of the ellipse, the eigenvectors of the projected covariance
+    let middle = (xx + yy) / 2.0;
+    let spread = sqrt(max((xx - yy) * (xx - yy) / 4.0 + xy * xy, 0.0));
+    let major = middle + spread;
+    let minor = max(middle - spread, 0.1);
+    var axis = vec2<f32>(1.0, 0.0);
+    if abs(xy) > 1e-6 {
+        axis = normalize(vec2<f32>(xy, major - xx));
+    } else if yy > xx {
+        axis = vec2<f32>(0.0, 1.0);
+    }
+    let major_axis = axis * min(sqrt(major), 1024.0);
+    let minor_axis = vec2<f32>(-axis.y, axis.x) * min(sqrt(minor), 1024.0);
+
+    let corner = corners[vertex] * EXTENT;
+    let offset = corner.x * major_axis + corner.y * minor_axis;
+    output.position = vec4<f32>(
+        (ndc + offset * 2.0 / view.viewport) * clip_position.w,
+        clip_position.z,
+        clip_position.w,
+    );
+    output.color = vec4<f32>(gaussian.color.rgb, gaussian.opacity);
+    output.offset = corner;
+    return output;
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let power = dot(vertex.offset, vertex.offset);
+    if power > EXTENT * EXTENT {
+        discard;
+    }
+    // The colors are premultiplied by their alpha, to be blended over the farther Gaussians
+    let alpha = min(vertex.color.a * exp(-0.5 * power), 0.99);
+    if alpha < 1.0 / 255.0 {
+        discard;
+    }
+    return vec4<f32>(vertex.color.rgb * alpha, alpha);
+}
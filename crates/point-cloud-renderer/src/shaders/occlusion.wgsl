@@ -0,0 +1,79 @@
+// The coarse depth pre-pass of the additive blending, keeping the nearest point of each tile of the
+// screen so the points behind it can be dimmed
+
+struct VertexInput {
+    position: vec3<f32>,
+    normal: u32,
+    color: vec4<f32>,
+};
+
+struct CameraTransforms {
+    world: mat4x4<f32>,
+    view: mat4x4<f32>,
+    proj: mat4x4<f32>,
+};
+
+struct Clip {
+    min: vec3<f32>,
+    enabled: u32,
+    max: vec3<f32>,
+};
+
+struct Model {
+    matrix: mat4x4<f32>,
+};
+
+struct Glow {
+    additive: u32,
+    intensity: f32,
+    occlusion: f32,
+    falloff: f32,
+    grid: vec2<u32>,
+};
+
+@group(0) @binding(0)
+var<storage, read> vertices: array<VertexInput>;
+
+@group(0) @binding(1)
+var<uniform> camera: CameraTransforms;
+
+@group(0) @binding(2)
+var<uniform> clip: Clip;
+
+@group(0) @binding(3)
+var<uniform> model: Model;
+
+@group(0) @binding(4)
+var<uniform> glow: Glow;
+
+// The inverse of the distance to the nearest point of each tile, zero when the tile is empty, so
+// the nearest point is the largest; positive floats keep their order when read as integers
+@group(0) @binding(5)
+var<storage, read_write> coarse_depth: array<atomic<u32>>;
+
+@compute @workgroup_size(256)
+fn cs_depth(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if index >= arrayLength(&vertices) {
+        return;
+    }
+
+    // The points cropped by the clip box hide nothing
+    let position = (model.matrix * vec4<f32>(vertices[index].position, 1.0)).xyz;
+    if clip.enabled != 0u && (any(position < clip.min) || any(position > clip.max)) {
+        return;
+    }
+
+    let view_position = camera.view * camera.world * vec4<f32>(position, 1.0);
+    let distance = -view_position.z;
+    if distance <= 0.0 {
+        return;
+    }
+    let clip_position = camera.proj * view_position;
+    let ndc = clip_position.xy / clip_position.w;
+    if any(abs(ndc) > vec2<f32>(1.0)) {
+        return;
+    }
+    let cell = min(vec2<u32>((ndc * 0.5 + 0.5) * vec2<f32>(glow.grid)), glow.grid - 1u);
+    atomicMax(&coarse_depth[cell.y * glow.grid.x + cell.x], bitcast<u32>(1.0 / distance));
+}
@@ -1,11 +1,20 @@
 struct VertexInput {
     @location(0) position: vec3<f32>, // <x, y, z>
+    @location(2) normal: u32, // Packed in the padding of the position, 0 if not known
     @location(1) color: vec4<f32>, // <r, g, b, a>
 };
 
 struct VertexOutput {
     @builtin(position) position: vec4<f32>, // <x, y, z, w>
     @location(0) color: vec4<f32>, // <r, g, b, a>
+    @location(1) uv: vec2<f32>, // The position within the splat, in [-1, 1]
+}
+
+// A point in view space, hidden when it must not be drawn
+struct ViewPoint {
+    position: vec4<f32>,
+    color: vec4<f32>,
+    hidden: bool,
 }
 
 struct CameraTransforms {
@@ -14,19 +23,289 @@ struct CameraTransforms {
     proj: mat4x4<f32>,
 };
 
+struct Fog {
+    inverse_view_proj: mat4x4<f32>,
+    view_proj: mat4x4<f32>,
+    color: vec3<f32>,
+    height: f32,
+    density: f32,
+    reflection_strength: f32,
+};
+
+struct Data {
+    sound_amplitude: f32,
+    wind_strength: f32,
+    noise_scale: f32,
+    spring_constant: f32,
+    wind_direction: vec3<f32>,
+    time: f32,
+    gust_strength: f32,
+    gust_frequency: f32,
+    gust_spread: f32,
+    point_size: f32,
+    band_modulation: vec3<f32>,
+};
+
+// The number of stops of the color ramp; must match `RAMP_STOPS` in `coloring.rs`
+const RAMP_STOPS: u32 = 8u;
+
+struct Coloring {
+    stops: array<vec4<f32>, RAMP_STOPS>,
+    mode: u32, // 0: RGB, 1: elevation, 2: intensity, 3: distance
+    low: f32,
+    high: f32,
+};
+
+struct Clip {
+    min: vec3<f32>,
+    enabled: u32,
+    max: vec3<f32>,
+};
+
+// The transform of the cloud, from its coordinates to those of the scene
+struct Model {
+    matrix: mat4x4<f32>,
+};
+
+struct Trail {
+    slots: u32,
+    head: u32,
+    filled: u32,
+    record: u32,
+    fade: f32,
+    interval: u32,
+    frame: u32,
+};
+
+struct Glow {
+    additive: u32,
+    intensity: f32,
+    occlusion: f32,
+    falloff: f32,
+    grid: vec2<u32>,
+};
+
+struct Lighting {
+    direction: vec3<f32>, // Towards the light, in the coordinates of the scene
+    mode: u32, // 0: unlit, 1: Lambert, 2: headlight
+    ambient: f32,
+};
+
 @group(0) @binding(0)
 var<storage, read> vertices: array<VertexInput>;
 
 @group(0) @binding(1)
 var<uniform> camera: CameraTransforms;
 
+@group(0) @binding(2)
+var<uniform> fog: Fog;
+
+@group(0) @binding(3)
+var<storage, read> trail_positions: array<vec4<f32>>;
+
+@group(0) @binding(4)
+var<uniform> trail: Trail;
+
+@group(0) @binding(5)
+var<uniform> data: Data;
+
+@group(0) @binding(6)
+var<uniform> coloring: Coloring;
+
+@group(0) @binding(7)
+var<uniform> clip: Clip;
+
+@group(0) @binding(8)
+var<uniform> model: Model;
+
+@group(0) @binding(9)
+var<uniform> glow: Glow;
+
+// The inverse of the distance to the nearest point of each tile of the screen, written by the
+// pre-pass in `occlusion.wgsl`
+@group(0) @binding(10)
+var<storage, read> coarse_depth: array<u32>;
+
+@group(0) @binding(11)
+var<uniform> lighting: Lighting;
+
+// A position outside of the clip volume
+const HIDDEN: vec4<f32> = vec4<f32>(0.0, 0.0, 2.0, 1.0);
+
+// Maps a value in [0, 1] to the color ramp, blending between its stops
+fn ramp(t: f32) -> vec3<f32> {
+    let x = clamp(t, 0.0, 1.0) * f32(RAMP_STOPS - 1u);
+    let i = min(u32(x), RAMP_STOPS - 2u);
+    return mix(coloring.stops[i].rgb, coloring.stops[i + 1u].rgb, x - f32(i));
+}
+
+// Encodes a linear channel to sRGB
+fn linear_to_srgb(channel: f32) -> f32 {
+    if channel <= 0.0031308 {
+        return channel * 12.92;
+    }
+    return 1.055 * pow(channel, 1.0 / 2.4) - 0.055;
+}
+
+// The color of a point in the color mode, from its position in the scene
+fn point_color(position: vec3<f32>, color: vec4<f32>) -> vec4<f32> {
+    var value: f32;
+    switch coloring.mode {
+        case 1u: {
+            value = position.y;
+        }
+        case 2u: {
+            // The intensities are loaded as sRGB grays, so they are encoded back to be evenly spaced
+            let luminance = dot(color.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+            return vec4<f32>(ramp(linear_to_srgb(luminance)), color.a);
+        }
+        case 3u: {
+            value = length((camera.view * camera.world * vec4<f32>(position, 1.0)).xyz);
+        }
+        default: {
+            return color;
+        }
+    }
+    let t = (value - coloring.low) / max(coloring.high - coloring.low, 1e-6);
+    return vec4<f32>(ramp(t), color.a);
+}
+
+// How much light a point adds in the additive mode, dimmed by how far it lies behind the nearest
+// point of its tile so the structure shows through the glow
+fn brightness(view_position: vec4<f32>, clip_position: vec4<f32>) -> f32 {
+    if glow.additive == 0u {
+        return 1.0;
+    }
+    let ndc = clip_position.xy / clip_position.w;
+    if glow.occlusion <= 0.0 || any(abs(ndc) > vec2<f32>(1.0)) {
+        return glow.intensity;
+    }
+    let cell = min(vec2<u32>((ndc * 0.5 + 0.5) * vec2<f32>(glow.grid)), glow.grid - 1u);
+    let nearest = bitcast<f32>(coarse_depth[cell.y * glow.grid.x + cell.x]);
+    if nearest <= 0.0 {
+        return glow.intensity;
+    }
+    let behind = max(-view_position.z - 1.0 / nearest, 0.0);
+    let hidden = 1.0 - exp(-behind / glow.falloff);
+    return glow.intensity * (1.0 - glow.occlusion * hidden);
+}
+
+// The normal of a point packed by `Point::set_normal`, null if it is not known
+fn unpack_normal(packed: u32) -> vec3<f32> {
+    if packed == 0u {
+        return vec3<f32>(0.0);
+    }
+    let xy = unpack2x16unorm(packed) * 2.0 - 1.0;
+    return normalize(vec3<f32>(xy, sqrt(max(1.0 - dot(xy, xy), 0.0))));
+}
+
+// How much of the light reaches a point, from its normal in the coordinates of the cloud
+//
+// The normals may face either side of the surface, so both sides are lit alike
+fn diffuse(position: vec3<f32>, normal: vec3<f32>) -> f32 {
+    if lighting.mode == 0u || all(normal == vec3<f32>(0.0)) {
+        return 1.0;
+    }
+    let scene_normal = normalize((model.matrix * vec4<f32>(normal, 0.0)).xyz);
+    var light: f32;
+    if lighting.mode == 1u {
+        light = abs(dot(scene_normal, lighting.direction));
+    } else {
+        // The light is at the camera, so the normal and the direction to the camera are taken
+        // in view space
+        let worldview = camera.view * camera.world;
+        let view_normal = normalize((worldview * vec4<f32>(scene_normal, 0.0)).xyz);
+        let view_position = (worldview * vec4<f32>(position, 1.0)).xyz;
+        light = abs(dot(view_normal, normalize(-view_position)));
+    }
+    return mix(lighting.ambient, 1.0, light);
+}
+
+fn view_point(index: u32, instance: u32) -> ViewPoint {
+    var point: ViewPoint;
+    var position = (model.matrix * vec4<f32>(vertices[index].position, 1.0)).xyz;
+    point.color = point_color(position, vertices[index].color);
+    point.color = vec4<f32>(point.color.rgb * diffuse(position, unpack_normal(vertices[index].normal)), point.color.a);
+    point.hidden = false;
+
+    // The points outside of the clip box are cropped, along with their reflection and ghosts
+    if clip.enabled != 0u && (any(position < clip.min) || any(position > clip.max)) {
+        point.hidden = true;
+        return point;
+    }
+
+    // The second instance is the reflection of the points above the fog plane
+    if instance == 1u {
+        if position.y < fog.height {
+            point.hidden = true;
+            return point;
+        }
+        position.y = 2.0 * fog.height - position.y;
+        point.color = vec4<f32>(mix(fog.color, point.color.rgb, fog.reflection_strength), point.color.a);
+    }
+
+    // The following instances are the ghosts of the points at their previous positions
+    if instance >= 2u {
+        let age = instance - 1u;
+        if age >= trail.filled {
+            point.hidden = true;
+            return point;
+        }
+        let slot = (trail.head + trail.slots - age) % trail.slots;
+        let ghost = trail_positions[slot * arrayLength(&vertices) + index].xyz;
+        position = (model.matrix * vec4<f32>(ghost, 1.0)).xyz;
+        point.color = vec4<f32>(point.color.rgb * pow(trail.fade, f32(age)), point.color.a);
+    }
+
+    let worldview: mat4x4<f32> = camera.view * camera.world;
+    point.position = worldview * vec4<f32>(position, 1.0);
+    return point;
+}
+
 @vertex
-fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+fn vs_main(@builtin(vertex_index) index: u32, @builtin(instance_index) instance: u32) -> VertexOutput {
+    var output: VertexOutput;
+    let point = view_point(index, instance);
+    output.color = point.color;
+    if point.hidden {
+        output.position = HIDDEN;
+        return output;
+    }
+
     // Compute the projected vertex position
+    output.position = camera.proj * point.position;
+    output.color = vec4<f32>(point.color.rgb * brightness(point.position, output.position), point.color.a);
+    return output;
+}
+
+// Expands each point into a quad of two triangles facing the camera
+@vertex
+fn vs_splat(@builtin(vertex_index) vertex: u32, @builtin(instance_index) instance: u32) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+
     var output: VertexOutput;
-    let worldview: mat4x4<f32> = camera.view * camera.world;
-    output.position = camera.proj * worldview * vec4<f32>(vertices[index].position, 1.0);
-    output.color = vertices[index].color;
+    let point = view_point(vertex / 6u, instance);
+    output.color = point.color;
+    if point.hidden {
+        output.position = HIDDEN;
+        return output;
+    }
+
+    // The size is scaled like the points from the units of the cloud to the units of the view
+    let center = camera.proj * point.position;
+    output.color = vec4<f32>(point.color.rgb * brightness(point.position, center), point.color.a);
+    let corner = corners[vertex % 6u];
+    let scale = length((camera.view * camera.world * model.matrix)[0].xyz);
+    let offset = corner * data.point_size * scale / 2.0;
+    output.position = camera.proj * (point.position + vec4<f32>(offset, 0.0, 0.0));
+    output.uv = corner;
     return output;
 }
 
@@ -34,3 +313,12 @@ fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
 fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
     return vertex.color;
 }
+
+// Rounds the quads into discs
+@fragment
+fn fs_splat(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    if dot(vertex.uv, vertex.uv) > 1.0 {
+        discard;
+    }
+    return vertex.color;
+}
@@ -0,0 +1,37 @@
+struct VertexInput {
+    @location(0) position: vec2<f32>, // <x, y>
+    @location(1) uv: vec2<f32>, // <u, v>
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>, // <x, y, z, w>
+    @location(0) uv: vec2<f32>, // <u, v>
+};
+
+struct Exposure {
+    stops: f32,
+    adapted: f32,
+};
+
+@group(0) @binding(0)
+var frame_texture: texture_2d<f32>;
+
+@group(0) @binding(1)
+var frame_sampler: sampler;
+
+@group(0) @binding(2)
+var<uniform> exposure: Exposure;
+
+@vertex
+fn vs_main(vertex: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+    output.position = vec4<f32>(vertex.position, 0.0, 1.0);
+    output.uv = vertex.uv;
+    return output;
+}
+
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(frame_texture, frame_sampler, vertex.uv);
+    return vec4<f32>(color.rgb * exp2(exposure.stops), color.a);
+}
@@ -0,0 +1,85 @@
+use nannou::{geom::Point3, wgpu};
+
+/// How the bands of the spectrum are laid out over the cloud.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpectralLayout {
+    /// The spectrum does not displace the points.
+    Off,
+    /// The bands are slices along the x axis, lowest frequencies on the left, and displace the
+    /// points upwards.
+    AxisX,
+    /// The bands are shells around the center of the cloud, lowest frequencies in the middle, and
+    /// displace the points outwards.
+    Radial,
+}
+
+/// Maps the bands of the audio spectrum to spatial regions of the cloud.
+///
+/// The struct is sent as-is to the compute shader, so its layout must match the `Spectrum` struct
+/// declared in `compute.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq)]
+pub struct SpectrumData {
+    /// The energy of each band of the spectrum, from the lowest to the highest frequencies.
+    pub bands: [f32; Self::BANDS],
+    center: [f32; 3],
+    band_layout: u32,
+    axis_min: f32,
+    axis_max: f32,
+    radius: f32,
+    /// How far the energy of a band displaces the points.
+    pub strength: f32,
+}
+
+impl SpectrumData {
+    /// The number of bands; must match `SPECTRUM_BANDS` in `compute.wgsl`.
+    pub const BANDS: usize = 16;
+
+    /// Creates a spectrum mapping covering the given bounding box.
+    pub fn new(layout: SpectralLayout, strength: f32, (min, max): (Point3, Point3)) -> Self {
+        let mut data = Self {
+            bands: [0.0; Self::BANDS],
+            center: [0.0; 3],
+            band_layout: 0,
+            axis_min: 0.0,
+            axis_max: 1.0,
+            radius: 1.0,
+            strength,
+        };
+        data.set_layout(layout);
+        data.set_bounds((min, max));
+        data
+    }
+
+    /// Fits the spatial bands to the given bounding box.
+    pub fn set_bounds(&mut self, (min, max): (Point3, Point3)) {
+        let center = (min + max) / 2.0;
+        self.center = center.to_array();
+        self.axis_min = min.x;
+        self.axis_max = max.x;
+        self.radius = (max - center).length().max(f32::EPSILON);
+    }
+
+    /// The layout of the bands over the cloud.
+    pub fn layout(&self) -> SpectralLayout {
+        match self.band_layout {
+            1 => SpectralLayout::AxisX,
+            2 => SpectralLayout::Radial,
+            _ => SpectralLayout::Off,
+        }
+    }
+
+    /// Sets the layout of the bands over the cloud.
+    pub fn set_layout(&mut self, layout: SpectralLayout) {
+        self.band_layout = match layout {
+            SpectralLayout::Off => 0,
+            SpectralLayout::AxisX => 1,
+            SpectralLayout::Radial => 2,
+        };
+    }
+
+    /// Returns the struct as a byte slice.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { wgpu::bytes::from(self) }
+    }
+}
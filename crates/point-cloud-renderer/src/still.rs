@@ -0,0 +1,115 @@
+use nannou::{
+    image::{GenericImage, RgbaImage},
+    wgpu,
+};
+
+use crate::{
+    camera::Camera,
+    color::{LinearImage, encode_srgb},
+    pipeline::GPUPipeline,
+};
+
+/// A still of any resolution, rendered a tile at a time so it can exceed the size of the textures
+/// of the device and be spread over several frames, showing its progress.
+///
+/// The still keeps the camera it was started with. The points should be frozen while it renders,
+/// see [`GPUPipeline::set_frozen`], so its tiles show the same instant.
+pub struct TiledStill {
+    camera: Camera,
+    size: [u32; 2],
+    /// The number of columns and rows of tiles.
+    tiles: [u32; 2],
+    /// The index of the next tile to render, counted row by row from the top-left corner.
+    next: u32,
+    /// The linear channels of the still, encoded when it is taken out.
+    image: LinearImage,
+}
+
+impl TiledStill {
+    /// Starts a still of the given size, split into tiles of at most `max_tile` pixels a side.
+    pub fn new(camera: Camera, size: [u32; 2], max_tile: u32) -> Self {
+        let size = size.map(|length| length.max(1));
+        Self {
+            camera,
+            size,
+            tiles: size.map(|length| length.div_ceil(max_tile.max(1))),
+            next: 0,
+            image: LinearImage::new(size[0], size[1]),
+        }
+    }
+
+    /// The size of the still in pixels.
+    pub fn size(&self) -> [u32; 2] {
+        self.size
+    }
+
+    /// The number of tiles of the still.
+    pub fn tile_count(&self) -> u32 {
+        self.tiles[0] * self.tiles[1]
+    }
+
+    /// The number of tiles rendered so far.
+    pub fn rendered_tiles(&self) -> u32 {
+        self.next
+    }
+
+    /// The fraction of the tiles rendered so far, in the range [0, 1].
+    pub fn fraction(&self) -> f32 {
+        self.next as f32 / self.tile_count() as f32
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.next >= self.tile_count()
+    }
+
+    /// Renders the next tile of the still, if any, blocking until it is read back from the GPU.
+    ///
+    /// Fails if the tile cannot be read back, in which case the tile is not counted as rendered.
+    pub fn render_next(
+        &mut self,
+        pipeline: &mut GPUPipeline,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<(), &'static str> {
+        if self.is_done() {
+            return Ok(());
+        }
+        let (origin, size) = self.tile(self.next);
+        let tile = pipeline.render_region_to_linear_image(
+            device,
+            queue,
+            &self.camera,
+            self.size,
+            origin,
+            size,
+        )?;
+        self.image
+            .copy_from(&tile, origin[0], origin[1])
+            .expect("the tile lies inside the image");
+        self.next += 1;
+        Ok(())
+    }
+
+    /// The image of the still, with the tiles rendered so far, encoded to 8-bit sRGB.
+    pub fn into_image(self) -> RgbaImage {
+        encode_srgb(&self.image)
+    }
+
+    /// The linear channels of the still in floating point, with the tiles rendered so far.
+    pub fn into_linear_image(self) -> LinearImage {
+        self.image
+    }
+
+    /// The origin and size of a tile, the tiles splitting the image evenly up to a pixel.
+    fn tile(&self, index: u32) -> ([u32; 2], [u32; 2]) {
+        let [columns, rows] = self.tiles;
+        let [width, height] = self.size;
+        let edge = |i: u32, count: u32, length: u32| i * length / count;
+        let (column, row) = (index % columns, index / columns);
+        let left = edge(column, columns, width);
+        let top = edge(row, rows, height);
+        let right = edge(column + 1, columns, width);
+        let bottom = edge(row + 1, rows, height);
+        ([left, top], [right - left, bottom - top])
+    }
+}
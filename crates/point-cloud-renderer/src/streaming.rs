@@ -0,0 +1,695 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        mpsc::{self, Receiver, Sender, TryRecvError},
+    },
+    thread,
+};
+
+use nannou::prelude::*;
+
+use crate::{
+    loader::{LoadHandle, LoadProgress},
+    point::Point,
+};
+
+/// The bytes opening a tile cache.
+const MAGIC: [u8; 8] = *b"PCTILES\0";
+
+/// The version of the layout of the tile caches, raised when it changes so older caches are
+/// written anew.
+const VERSION: u32 = 1;
+
+/// The size of the header of a tile cache: the magic bytes, the version, the size of a point, and
+/// the numbers of tiles and points.
+const HEADER_SIZE: u64 = 8 + 4 + 4 + 8 + 8;
+
+/// The size of an entry of the index of a tile cache: the corners of the bounds of the tile, the
+/// index of its first point and its number of points.
+const ENTRY_SIZE: u64 = 6 * 4 + 8 + 8;
+
+/// A tile of a cloud streamed from the disk, a box of neighboring points.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tile {
+    /// The corner of the bounds of the points with the lowest coordinates.
+    pub min: Vec3,
+    /// The corner of the bounds of the points with the highest coordinates.
+    pub max: Vec3,
+    /// The index of the first point of the tile in the cache.
+    start: u64,
+    /// The number of points of the tile.
+    pub count: u64,
+}
+
+impl Tile {
+    /// The squared distance from a position to the bounds of the tile, 0 inside them.
+    fn distance_squared(&self, position: Vec3) -> f32 {
+        (self.min - position)
+            .max(position - self.max)
+            .max(Vec3::ZERO)
+            .length_squared()
+    }
+}
+
+/// A point cloud split into spatial tiles on the disk, for the clouds too large to be held by the
+/// GPU at once.
+///
+/// The cache is a single file: a header, the index of the tiles, then the points of the tiles one
+/// after the other, as they are laid out in the buffers of the GPU. The numbers are in the byte
+/// order of the machine, as the cache is only meant to be read where it was written.
+pub struct TileCache {
+    path: String,
+    tiles: Vec<Tile>,
+}
+
+impl TileCache {
+    /// The largest number of points of a tile, small enough to be read and uploaded within a
+    /// frame or two.
+    pub const MAX_TILE_POINTS: usize = 1 << 18;
+
+    /// Splits the points into tiles and writes them to a cache at `path`, reporting the points
+    /// written to `progress`.
+    ///
+    /// The points are split in halves at their median along the longest side of their bounds
+    /// until each part holds at most [`Self::MAX_TILE_POINTS`], so the tiles are compact and
+    /// evenly filled however the points are spread.
+    pub fn write(path: &str, points: Vec<Point>, progress: &LoadProgress) -> io::Result<Self> {
+        Self::write_tiles(path, points, progress, Self::MAX_TILE_POINTS)
+    }
+
+    /// Writes the points to a cache at `path` in tiles of at most `max_tile_points` points.
+    fn write_tiles(
+        path: &str,
+        mut points: Vec<Point>,
+        progress: &LoadProgress,
+        max_tile_points: usize,
+    ) -> io::Result<Self> {
+        progress.set_total(points.len() as u64);
+        let mut tiles = Vec::new();
+        split(&mut points, 0, max_tile_points, &mut tiles);
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&VERSION.to_ne_bytes())?;
+        writer.write_all(&(std::mem::size_of::<Point>() as u32).to_ne_bytes())?;
+        writer.write_all(&(tiles.len() as u64).to_ne_bytes())?;
+        writer.write_all(&(points.len() as u64).to_ne_bytes())?;
+        for tile in &tiles {
+            for value in tile.min.to_array().into_iter().chain(tile.max.to_array()) {
+                writer.write_all(&value.to_ne_bytes())?;
+            }
+            writer.write_all(&tile.start.to_ne_bytes())?;
+            writer.write_all(&tile.count.to_ne_bytes())?;
+        }
+        for chunk in points.chunks(Self::MAX_TILE_POINTS) {
+            writer.write_all(Point::as_bytes(chunk))?;
+            progress.add(chunk.len() as u64);
+        }
+        writer.flush()?;
+
+        Ok(Self {
+            path: path.to_owned(),
+            tiles,
+        })
+    }
+
+    /// Opens the cache at `path`, reading its index.
+    ///
+    /// Every tile of the index must lie within the file, so a truncated or corrupted cache is
+    /// rejected here rather than when its tiles are read.
+    pub fn open(path: &str) -> io::Result<Self> {
+        let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+        let file = File::open(path)?;
+        let length = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(invalid("Not a tile cache"));
+        }
+        if read_u32(&mut reader)? != VERSION
+            || read_u32(&mut reader)? != std::mem::size_of::<Point>() as u32
+        {
+            return Err(invalid("The tile cache was written by another version"));
+        }
+        let tile_count = read_u64(&mut reader)?;
+        let point_count = read_u64(&mut reader)?;
+        let points_offset = tile_count
+            .checked_mul(ENTRY_SIZE)
+            .and_then(|index_size| index_size.checked_add(HEADER_SIZE))
+            .filter(|&offset| offset <= length)
+            .ok_or_else(|| invalid("The index of the tile cache is truncated"))?;
+        let point_size = std::mem::size_of::<Point>() as u64;
+        let max_points = (length - points_offset) / point_size;
+
+        let tiles = (0..tile_count)
+            .map(|_| {
+                let mut corners = [0.0; 6];
+                for value in &mut corners {
+                    *value = f32::from_bits(read_u32(&mut reader)?);
+                }
+                Ok(Tile {
+                    min: vec3(corners[0], corners[1], corners[2]),
+                    max: vec3(corners[3], corners[4], corners[5]),
+                    start: read_u64(&mut reader)?,
+                    count: read_u64(&mut reader)?,
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let within_file = |tile: &Tile| {
+            tile.count <= Self::MAX_TILE_POINTS as u64
+                && tile
+                    .start
+                    .checked_add(tile.count)
+                    .is_some_and(|end| end <= max_points)
+        };
+        if point_count > max_points
+            || !tiles.iter().all(within_file)
+            || tiles.iter().map(|tile| tile.count).sum::<u64>() != point_count
+        {
+            return Err(invalid("The index of the tile cache is corrupted"));
+        }
+        Ok(Self {
+            path: path.to_owned(),
+            tiles,
+        })
+    }
+
+    /// The path of the cache of the cloud at `source`, in the cache directory of the user, or in
+    /// the temporary directory if it cannot be written to.
+    ///
+    /// The name of the cache holds a hash of the path of the source, so the clouds of the same
+    /// name in different directories do not share a cache. Returns `None` if neither directory
+    /// can be written to.
+    pub fn path_for(source: &str) -> Option<String> {
+        let source_path = Path::new(source);
+        let absolute = std::fs::canonicalize(source_path).unwrap_or(source_path.to_path_buf());
+        let mut hasher = DefaultHasher::new();
+        absolute.hash(&mut hasher);
+        let name = source_path.file_name()?.to_string_lossy();
+        let file_name = format!("{name}.{:016x}.tiles", hasher.finish());
+
+        user_cache_directory()
+            .into_iter()
+            .chain([std::env::temp_dir()])
+            .map(|directory| directory.join("point-cloud-renderer").join("tiles"))
+            .find(|directory| is_writable(directory))
+            .map(|directory| directory.join(file_name).to_string_lossy().into_owned())
+    }
+
+    /// Opens the cache at `path` if it holds the `point_count` points of the file at `source`
+    /// and was written after it, so a cloud loaded again is not split again.
+    pub fn open_fresh(path: &str, source: &str, point_count: usize) -> Option<Self> {
+        let modified = |path| std::fs::metadata(path).and_then(|metadata| metadata.modified());
+        if modified(path).ok()? < modified(source).ok()? {
+            return None;
+        }
+        Self::open(path)
+            .ok()
+            .filter(|cache| cache.point_count() == point_count as u64)
+    }
+
+    /// The path of the cache.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The tiles of the cache.
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+
+    /// The number of points of the cache.
+    pub fn point_count(&self) -> u64 {
+        self.tiles.iter().map(|tile| tile.count).sum()
+    }
+
+    /// Reads the points of the tile at the index from the disk.
+    pub fn read_tile(&self, index: usize) -> io::Result<Vec<Point>> {
+        let tile = &self.tiles[index];
+        let point_size = std::mem::size_of::<Point>() as u64;
+        let offset = HEADER_SIZE + self.tiles.len() as u64 * ENTRY_SIZE + tile.start * point_size;
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut bytes = vec![0; (tile.count * point_size) as usize];
+        file.read_exact(&mut bytes)?;
+        Ok(Point::from_bytes(&bytes))
+    }
+}
+
+/// Starts splitting the points into a tile cache at `path` on a background thread.
+///
+/// See [`TileCache::write`].
+pub fn write_tiles_async(path: &str, points: Vec<Point>) -> LoadHandle<TileCache> {
+    LoadHandle::spawn(path, move |path, progress| {
+        TileCache::write(path, points, progress).map_err(|_| {
+            // A partial cache would only be written anew, so it is not left behind
+            let _ = std::fs::remove_file(path);
+            "Failed to write the tile cache"
+        })
+    })
+}
+
+/// The cache directory of the user, following the conventions of the platform.
+fn user_cache_directory() -> Option<PathBuf> {
+    let var = |name| std::env::var_os(name).map(PathBuf::from);
+    if cfg!(target_os = "windows") {
+        var("LOCALAPPDATA")
+    } else if cfg!(target_os = "macos") {
+        var("HOME").map(|home| home.join("Library").join("Caches"))
+    } else {
+        var("XDG_CACHE_HOME").or_else(|| var("HOME").map(|home| home.join(".cache")))
+    }
+}
+
+/// Whether files can be created in the directory, creating it if needed.
+fn is_writable(directory: &Path) -> bool {
+    let probe = directory.join(format!(".probe_{}", std::process::id()));
+    std::fs::create_dir_all(directory).is_ok()
+        && File::create(&probe).is_ok()
+        && std::fs::remove_file(&probe).is_ok()
+}
+
+/// Splits the points into tiles of at most `max_tile_points` points in place, appending the tiles
+/// to `tiles`, the first point being at `start` in the cache.
+fn split(points: &mut [Point], start: u64, max_tile_points: usize, tiles: &mut Vec<Tile>) {
+    let (min, max) = Point::bounding_box(points);
+    if points.len() <= max_tile_points {
+        if !points.is_empty() {
+            tiles.push(Tile {
+                min,
+                max,
+                start,
+                count: points.len() as u64,
+            });
+        }
+        return;
+    }
+    let size = max - min;
+    let axis = match size.max_element() {
+        longest if longest == size.x => 0,
+        longest if longest == size.y => 1,
+        _ => 2,
+    };
+    let middle = points.len() / 2;
+    points.select_nth_unstable_by(middle, |a, b| a.position[axis].total_cmp(&b.position[axis]));
+    let (before, after) = points.split_at_mut(middle);
+    split(before, start, max_tile_points, tiles);
+    split(after, start + middle as u64, max_tile_points, tiles);
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_ne_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_ne_bytes(bytes))
+}
+
+/// The tiles to upload to the GPU and to evict from it, as chosen by [`TileStreamer::update`].
+#[derive(Default)]
+pub struct TileChanges {
+    /// The tiles read from the disk, by their index, to upload.
+    pub loaded: Vec<(usize, Vec<Point>)>,
+    /// The indices of the tiles to evict.
+    pub evicted: Vec<usize>,
+}
+
+/// Streams the tiles of a cache nearest to the camera to the GPU, keeping as many of them as the
+/// budget allows.
+///
+/// The tiles are read from the disk on a background thread, so the frames never wait on it. The
+/// tiles the camera moved away from stay on the GPU until their room is needed, the least recently
+/// needed being evicted first, so the camera coming back finds them still there.
+pub struct TileStreamer {
+    cache: Arc<TileCache>,
+    /// Whether the tiles are streamed; the resident tiles are evicted when it is turned off.
+    pub enabled: bool,
+    /// The largest number of points kept on the GPU across the tiles.
+    pub budget: usize,
+    /// The frame each resident tile was last needed on, by the index of the tile.
+    resident: Vec<Option<u64>>,
+    /// The tiles being read from the disk.
+    pending: HashSet<usize>,
+    /// The tiles that could not be read, which are not asked for again.
+    broken: HashSet<usize>,
+    frame: u64,
+    requests: Sender<usize>,
+    results: Receiver<(usize, io::Result<Vec<Point>>)>,
+}
+
+impl TileStreamer {
+    /// The number of points drawn for the whole cloud while its tiles are streamed, deformed like
+    /// any other cloud.
+    pub const OVERVIEW_POINTS: usize = 2_000_000;
+
+    /// The largest number of tiles read from the disk at once, so the tiles asked for stay the
+    /// ones near the camera as it moves.
+    const MAX_PENDING: usize = 4;
+
+    /// The largest number of tiles uploaded in a frame, so the uploads do not stall it.
+    const MAX_UPLOADS: usize = 2;
+
+    /// Starts streaming the tiles of a cache, reading them on a new thread.
+    pub fn new(cache: TileCache) -> Self {
+        let cache = Arc::new(cache);
+        let (requests, receiver) = mpsc::channel::<usize>();
+        let (sender, results) = mpsc::channel();
+        let reader = cache.clone();
+        // The thread stops once the streamer, holding the sender of the requests, is dropped
+        thread::spawn(move || {
+            for tile in receiver {
+                if sender.send((tile, reader.read_tile(tile))).is_err() {
+                    return;
+                }
+            }
+        });
+        Self {
+            resident: vec![None; cache.tiles().len()],
+            cache,
+            enabled: true,
+            budget: 8_000_000,
+            pending: HashSet::new(),
+            broken: HashSet::new(),
+            frame: 0,
+            requests,
+            results,
+        }
+    }
+
+    /// The cache the tiles are streamed from.
+    pub fn cache(&self) -> &TileCache {
+        &self.cache
+    }
+
+    /// The number of tiles on the GPU and the number of their points.
+    pub fn resident(&self) -> (usize, u64) {
+        self.resident_tiles().fold((0, 0), |(tiles, points), tile| {
+            (tiles + 1, points + self.cache.tiles()[tile].count)
+        })
+    }
+
+    /// The number of tiles being read from the disk.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Chooses the tiles to keep on the GPU with the camera at `position`, in the coordinates of
+    /// the cloud, and returns the tiles to upload and evict.
+    ///
+    /// This is meant to be called every frame, applying the changes to the GPU right away.
+    pub fn update(&mut self, position: Vec3) -> TileChanges {
+        self.frame += 1;
+        let mut changes = TileChanges::default();
+        let tiles = self.cache.tiles();
+
+        // The nearest tiles fitting in the budget are needed
+        let mut wanted = HashSet::new();
+        if self.enabled {
+            let mut order: Vec<usize> = (0..tiles.len())
+                .filter(|tile| !self.broken.contains(tile))
+                .collect();
+            order.sort_by(|&a, &b| {
+                let distance = |tile: usize| tiles[tile].distance_squared(position);
+                distance(a).total_cmp(&distance(b))
+            });
+            let mut points = 0;
+            for tile in order {
+                points += tiles[tile].count;
+                if points > self.budget as u64 {
+                    break;
+                }
+                wanted.insert(tile);
+            }
+        }
+        let frame = self.frame;
+        wanted.iter().for_each(|&tile| {
+            if let Some(last_needed) = &mut self.resident[tile] {
+                *last_needed = frame;
+            }
+        });
+
+        // Upload the tiles read from the disk, unless the camera moved away from them since
+        loop {
+            if changes.loaded.len() >= Self::MAX_UPLOADS {
+                break;
+            }
+            let (tile, points) = match self.results.try_recv() {
+                Ok(result) => result,
+                Err(TryRecvError::Empty | TryRecvError::Disconnected) => break,
+            };
+            self.pending.remove(&tile);
+            match points {
+                Ok(points) if wanted.contains(&tile) => {
+                    self.resident[tile] = Some(frame);
+                    changes.loaded.push((tile, points));
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("Failed to read tile {tile} of {}: {e}", self.cache.path());
+                    self.broken.insert(tile);
+                }
+            }
+        }
+
+        // Make room for the needed tiles, evicting the least recently needed ones
+        let mut used: u64 = self
+            .resident_tiles()
+            .chain(self.pending.iter().copied())
+            .map(|tile| tiles[tile].count)
+            .sum();
+        let mut evictable: Vec<usize> = self
+            .resident_tiles()
+            .filter(|tile| !wanted.contains(tile))
+            .collect();
+        evictable.sort_by_key(|&tile| std::cmp::Reverse(self.resident[tile]));
+        let mut missing: Vec<usize> = wanted
+            .iter()
+            .copied()
+            .filter(|&tile| self.resident[tile].is_none() && !self.pending.contains(&tile))
+            .collect();
+        missing.sort_by(|&a, &b| {
+            let distance = |tile: usize| tiles[tile].distance_squared(position);
+            distance(a).total_cmp(&distance(b))
+        });
+        let budget = match self.enabled {
+            true => self.budget as u64,
+            false => 0,
+        };
+        let mut evict = |used: &mut u64, changes: &mut TileChanges| match evictable.pop() {
+            Some(tile) => {
+                self.resident[tile] = None;
+                *used -= tiles[tile].count;
+                changes.evicted.push(tile);
+                true
+            }
+            None => false,
+        };
+        while used > budget && evict(&mut used, &mut changes) {}
+        let mut requested = Vec::new();
+        for tile in missing {
+            if self.pending.len() + requested.len() >= Self::MAX_PENDING {
+                break;
+            }
+            let count = tiles[tile].count;
+            while used + count > budget && evict(&mut used, &mut changes) {}
+            if used + count > budget {
+                break;
+            }
+            used += count;
+            requested.push(tile);
+        }
+
+        // Ask for the missing tiles
+        for tile in requested {
+            if self.requests.send(tile).is_ok() {
+                self.pending.insert(tile);
+            }
+        }
+        changes
+    }
+
+    /// The indices of the tiles on the GPU.
+    fn resident_tiles(&self) -> impl Iterator<Item = usize> + '_ {
+        self.resident
+            .iter()
+            .enumerate()
+            .filter_map(|(tile, last_needed)| last_needed.map(|_| tile))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    /// A path in the temporary directory for the cache of a test.
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("tiles_{name}_{}.tiles", std::process::id()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Points along the x axis, one per unit, so the tile `i` of 8 points spans [8i, 8i + 7].
+    fn line(count: usize) -> Vec<Point> {
+        (0..count)
+            .map(|i| Point::new([i as f32, 0.0, 0.0], [i as u8, 0, 0, 255]))
+            .collect()
+    }
+
+    fn write_line(name: &str) -> (String, TileCache) {
+        let path = temp_path(name);
+        let cache = TileCache::write_tiles(&path, line(64), &LoadProgress::default(), 8).unwrap();
+        (path, cache)
+    }
+
+    /// Overwrites bytes of the file at `offset`.
+    fn patch(path: &str, offset: u64, bytes: &[u8]) {
+        let mut file = std::fs::OpenOptions::new().write(true).open(path).unwrap();
+        file.seek(SeekFrom::Start(offset)).unwrap();
+        file.write_all(bytes).unwrap();
+    }
+
+    #[test]
+    fn tiles_round_trip() {
+        let (path, written) = write_line("round_trip");
+        let cache = TileCache::open(&path).unwrap();
+        assert_eq!(cache.tiles(), written.tiles());
+        assert_eq!(cache.tiles().len(), 8);
+        assert_eq!(cache.point_count(), 64);
+
+        let mut read = Vec::new();
+        cache.tiles().iter().enumerate().for_each(|(index, tile)| {
+            let points = cache.read_tile(index).unwrap();
+            assert_eq!(points.len() as u64, tile.count);
+            points.iter().for_each(|point| {
+                let position = Vec3::from(point.position);
+                assert!(position.cmpge(tile.min).all() && position.cmple(tile.max).all());
+            });
+            read.extend(points);
+        });
+        read.sort_by(|a, b| a.position[0].total_cmp(&b.position[0]));
+        line(64).iter().zip(&read).for_each(|(expected, point)| {
+            assert_eq!(point.position, expected.position);
+            assert_eq!(point.color, expected.color);
+        });
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn open_rejects_other_files_and_versions() {
+        let (path, _) = write_line("magic");
+        patch(&path, 0, b"NOTTILES");
+        assert_eq!(
+            TileCache::open(&path).err().unwrap().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        let (path, _) = write_line("version");
+        patch(&path, 8, &(VERSION + 1).to_ne_bytes());
+        assert_eq!(
+            TileCache::open(&path).err().unwrap().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(temp_path("magic")).unwrap();
+    }
+
+    #[test]
+    fn open_rejects_tiles_outside_the_file() {
+        // A tile claiming more points than the file holds
+        let (path, _) = write_line("count");
+        let count_offset = HEADER_SIZE + 6 * 4 + 8;
+        patch(&path, count_offset, &u64::MAX.to_ne_bytes());
+        assert!(TileCache::open(&path).is_err());
+
+        // A file cut short of its last tile
+        let (truncated, _) = write_line("truncated");
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(&truncated)
+            .unwrap();
+        let length = file.metadata().unwrap().len();
+        file.set_len(length - 1).unwrap();
+        assert!(TileCache::open(&truncated).is_err());
+
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(truncated).unwrap();
+    }
+
+    #[test]
+    fn caches_of_sources_of_the_same_name_differ() {
+        let a = TileCache::path_for("./a/cloud.e57").unwrap();
+        let b = TileCache::path_for("./b/cloud.e57").unwrap();
+        assert_ne!(a, b);
+        assert!(a.ends_with(".tiles"));
+        assert_eq!(a, TileCache::path_for("./a/cloud.e57").unwrap());
+    }
+
+    /// Updates the streamer until the tiles it asked for are read, keeping track of the tiles on
+    /// the GPU.
+    fn settle(streamer: &mut TileStreamer, position: Vec3, resident: &mut HashSet<usize>) {
+        for _ in 0..1000 {
+            let changes = streamer.update(position);
+            changes
+                .evicted
+                .iter()
+                .for_each(|tile| assert!(resident.remove(tile)));
+            changes
+                .loaded
+                .iter()
+                .for_each(|(tile, _)| assert!(resident.insert(*tile)));
+            if streamer.pending() == 0 && changes.loaded.is_empty() && changes.evicted.is_empty() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        panic!("The streamer did not settle");
+    }
+
+    #[test]
+    fn streams_the_nearest_tiles_within_the_budget() {
+        let (path, cache) = write_line("streamer");
+        let mut streamer = TileStreamer::new(cache);
+        streamer.budget = 24;
+        let mut resident = HashSet::new();
+
+        settle(&mut streamer, vec3(-10.0, 0.0, 0.0), &mut resident);
+        assert_eq!(resident, HashSet::from([0, 1, 2]));
+        assert_eq!(streamer.resident(), (3, 24));
+
+        // Only the tile left behind is evicted, the others being still needed
+        settle(&mut streamer, vec3(20.0, 0.0, 0.0), &mut resident);
+        assert_eq!(resident, HashSet::from([1, 2, 3]));
+
+        // Far away, every tile is replaced without exceeding the budget
+        settle(&mut streamer, vec3(100.0, 0.0, 0.0), &mut resident);
+        assert_eq!(resident, HashSet::from([5, 6, 7]));
+        assert!(streamer.resident().1 <= streamer.budget as u64);
+
+        // A smaller budget evicts the farthest tiles
+        streamer.budget = 8;
+        settle(&mut streamer, vec3(100.0, 0.0, 0.0), &mut resident);
+        assert_eq!(resident, HashSet::from([7]));
+
+        streamer.enabled = false;
+        settle(&mut streamer, vec3(100.0, 0.0, 0.0), &mut resident);
+        assert!(resident.is_empty());
+        assert_eq!(streamer.resident(), (0, 0));
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
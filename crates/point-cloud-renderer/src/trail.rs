@@ -0,0 +1,134 @@
+use nannou::wgpu;
+
+/// The settings and state of the echo trails left by the points.
+///
+/// The compute shader keeps the last positions of every point in a ring buffer, and the ghosts of
+/// the points are drawn at those positions with a fading color.
+///
+/// The struct is sent as-is to the shaders, so its layout must match the `Trail` struct declared in
+/// `compute.wgsl` and `render.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq)]
+pub struct TrailData {
+    slots: u32,
+    head: u32,
+    filled: u32,
+    record: u32,
+    /// How much the color of a ghost fades with each step of age, in the range [0, 1].
+    pub fade: f32,
+    /// The number of frames between two recorded positions.
+    pub interval: u32,
+    frame: u32,
+    _padding: u32,
+}
+
+impl TrailData {
+    /// The maximum number of ghosts drawn per point.
+    pub const MAX_LENGTH: u32 = 16;
+
+    /// Creates trails of `length` ghosts recorded every `interval` frames.
+    pub fn new(length: u32, interval: u32, fade: f32) -> Self {
+        let mut data = Self {
+            slots: 1,
+            head: 0,
+            filled: 0,
+            record: 0,
+            fade,
+            interval,
+            frame: 0,
+            _padding: 0,
+        };
+        data.set_length(length);
+        data
+    }
+
+    /// The number of ghosts drawn per point.
+    pub fn length(&self) -> u32 {
+        self.slots - 1
+    }
+
+    /// Sets the number of ghosts drawn per point, clearing the recorded positions.
+    ///
+    /// The length is clamped to [`Self::MAX_LENGTH`]; a length of 0 disables the trails.
+    pub fn set_length(&mut self, length: u32) {
+        // The positions of the current frame take a slot of the ring
+        self.slots = length.min(Self::MAX_LENGTH) + 1;
+        self.reset();
+    }
+
+    /// The longest trails whose positions, for a cloud of `points` points, fit in a buffer of
+    /// `max_size` bytes, so the ring buffer of a large cloud does not exceed the limits of the
+    /// device.
+    pub fn max_length(points: usize, max_size: u64) -> u32 {
+        let position_size = std::mem::size_of::<[f32; 4]>() as u64;
+        let slots = max_size / (points.max(1) as u64 * position_size);
+        // The positions of the current frame take a slot of the ring
+        slots.saturating_sub(1).min(Self::MAX_LENGTH as u64) as u32
+    }
+
+    /// The number of positions stored per point by the ring buffer.
+    pub fn slots(&self) -> u32 {
+        self.slots
+    }
+
+    /// Whether the trails are drawn.
+    pub fn is_enabled(&self) -> bool {
+        self.slots > 1
+    }
+
+    /// Forgets the recorded positions, such as when the point cloud changes.
+    pub fn reset(&mut self) {
+        self.head = 0;
+        self.filled = 0;
+        self.frame = 0;
+    }
+
+    /// Advances the trails by a frame, recording the positions of the points every `interval`
+    /// frames.
+    pub fn tick(&mut self) {
+        self.record = self.frame.is_multiple_of(self.interval.max(1)) as u32;
+        self.frame = self.frame.wrapping_add(1);
+        if self.record != 0 {
+            self.head = (self.head + 1) % self.slots;
+            self.filled = (self.filled + 1).min(self.slots);
+        }
+    }
+
+    /// Returns the struct as a byte slice.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { wgpu::bytes::from(self) }
+    }
+}
+
+impl Default for TrailData {
+    fn default() -> Self {
+        Self::new(0, 4, 0.6)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn max_length_is_capped_for_small_clouds() {
+        assert_eq!(TrailData::max_length(0, 1 << 30), TrailData::MAX_LENGTH);
+        assert_eq!(TrailData::max_length(1000, 1 << 30), TrailData::MAX_LENGTH);
+    }
+
+    #[test]
+    fn max_length_fits_the_buffer() {
+        let points = 10_000_000;
+        let max_size = 1 << 30;
+        let length = TrailData::max_length(points, max_size);
+        assert!(length < TrailData::MAX_LENGTH);
+        assert!(points as u64 * (length as u64 + 1) * 16 <= max_size);
+        assert!(points as u64 * (length as u64 + 2) * 16 > max_size);
+    }
+
+    #[test]
+    fn max_length_disables_trails_without_room_for_a_ghost() {
+        assert_eq!(TrailData::max_length(1000, 1000 * 16), 0);
+        assert_eq!(TrailData::max_length(1000, 10), 0);
+    }
+}
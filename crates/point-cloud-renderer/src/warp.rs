@@ -0,0 +1,152 @@
+use nannou::{prelude::*, wgpu};
+
+/// A vertex of the mesh the rendered frame is warped onto.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct WarpVertex {
+    position: [f32; 2],
+    uv: [f32; 2],
+}
+
+impl WarpVertex {
+    pub const ATTRIBS: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2];
+
+    /// Returns the vertices as a byte slice.
+    pub fn as_bytes(vertices: &[Self]) -> &[u8] {
+        unsafe { wgpu::bytes::from_slice(vertices) }
+    }
+}
+
+/// The number of control points per side of the warp mesh.
+pub const MESH_SIZE: usize = 4;
+
+/// An output warp for projection mapping.
+///
+/// The frame is first mapped onto the quad defined by the four corner pins through a homography,
+/// which corrects the keystone of an off-axis projector. A mesh of control points can then offset
+/// regions of the frame to follow curved or uneven surfaces.
+///
+/// All positions are in normalized device coordinates, where (-1, -1) is the bottom-left corner
+/// and (1, 1) is the top-right corner of the window.
+#[derive(Clone, Copy, PartialEq)]
+pub struct WarpSettings {
+    /// Whether the frame is warped.
+    pub enabled: bool,
+    /// The bottom-left, bottom-right, top-right and top-left corners of the frame.
+    pub corners: [Vec2; 4],
+    /// Whether the mesh offsets are applied.
+    pub mesh_enabled: bool,
+    /// The offsets of the control points of the mesh, row by row from the bottom.
+    pub mesh: [Vec2; MESH_SIZE * MESH_SIZE],
+}
+
+impl WarpSettings {
+    /// The number of quads per side tessellating the warped frame.
+    const RESOLUTION: usize = 32;
+
+    /// Puts the corners back on the corners of the window and clears the mesh offsets.
+    pub fn reset(&mut self) {
+        *self = Self {
+            enabled: self.enabled,
+            mesh_enabled: self.mesh_enabled,
+            ..Self::default()
+        };
+    }
+
+    /// Maps a point of the frame to the window through the corner pins only.
+    ///
+    /// The point is given in the unit square, with (0, 0) at the bottom-left corner of the frame.
+    pub fn map_corners(&self, uv: Vec2) -> Vec2 {
+        let [p0, p1, p2, p3] = self.corners;
+
+        // Square to quad projective mapping (Heckbert, 1989)
+        let d1 = p1 - p2;
+        let d2 = p3 - p2;
+        let d3 = p0 - p1 + p2 - p3;
+        let det = d1.x * d2.y - d2.x * d1.y;
+        let (g, h) = if det.abs() > f32::EPSILON {
+            (
+                (d3.x * d2.y - d2.x * d3.y) / det,
+                (d1.x * d3.y - d3.x * d1.y) / det,
+            )
+        } else {
+            (0.0, 0.0)
+        };
+        let a = p1 - p0 + g * p1;
+        let b = p3 - p0 + h * p3;
+
+        (a * uv.x + b * uv.y + p0) / (g * uv.x + h * uv.y + 1.0)
+    }
+
+    /// Maps a point of the frame to the window through the corner pins and the mesh.
+    pub fn map(&self, uv: Vec2) -> Vec2 {
+        let position = self.map_corners(uv);
+        if !self.mesh_enabled {
+            return position;
+        }
+
+        // Bilinearly interpolate the offsets of the surrounding control points
+        let cell = uv.clamp(Vec2::ZERO, Vec2::ONE) * (MESH_SIZE - 1) as f32;
+        let x = (cell.x as usize).min(MESH_SIZE - 2);
+        let y = (cell.y as usize).min(MESH_SIZE - 2);
+        let t = cell - vec2(x as f32, y as f32);
+        let offset = |x: usize, y: usize| self.mesh[y * MESH_SIZE + x];
+        let bottom = offset(x, y).lerp(offset(x + 1, y), t.x);
+        let top = offset(x, y + 1).lerp(offset(x + 1, y + 1), t.x);
+
+        position + bottom.lerp(top, t.y)
+    }
+
+    /// The position in the frame of a control point of the mesh, in the unit square.
+    pub fn control_point_uv(x: usize, y: usize) -> Vec2 {
+        vec2(x as f32, y as f32) / (MESH_SIZE - 1) as f32
+    }
+
+    /// Tessellates the warped frame into a list of triangles, covering the window unwarped when
+    /// the warp is disabled.
+    pub fn vertices(&self) -> Vec<WarpVertex> {
+        let vertex = |x: usize, y: usize| {
+            let uv = vec2(x as f32, y as f32) / Self::RESOLUTION as f32;
+            let position = match self.enabled {
+                true => self.map(uv),
+                false => uv * 2.0 - 1.0,
+            };
+            WarpVertex {
+                position: position.to_array(),
+                // Textures are addressed from the top-left corner
+                uv: [uv.x, 1.0 - uv.y],
+            }
+        };
+
+        (0..Self::RESOLUTION)
+            .flat_map(|y| (0..Self::RESOLUTION).map(move |x| (x, y)))
+            .flat_map(|(x, y)| {
+                [
+                    vertex(x, y),
+                    vertex(x + 1, y),
+                    vertex(x + 1, y + 1),
+                    vertex(x, y),
+                    vertex(x + 1, y + 1),
+                    vertex(x, y + 1),
+                ]
+            })
+            .collect()
+    }
+}
+
+impl Default for WarpSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            corners: [
+                vec2(-1.0, -1.0),
+                vec2(1.0, -1.0),
+                vec2(1.0, 1.0),
+                vec2(-1.0, 1.0),
+            ],
+            mesh_enabled: false,
+            mesh: [Vec2::ZERO; MESH_SIZE * MESH_SIZE],
+        }
+    }
+}